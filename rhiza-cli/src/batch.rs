@@ -0,0 +1,39 @@
+use crate::parse_rhz;
+use anyhow::{Context, Result};
+use rhiza_core::dag::transaction::MAX_BATCH_OUTPUTS;
+use std::path::Path;
+
+/// Recipients and amounts (in smallest units) from a `rhiza send --batch`
+/// file, in file order
+///
+/// One `recipient,amount` per line, the amount in RHZ. Blank lines, lines
+/// starting with `#` and a `recipient,amount` header are skipped.
+pub fn read(path: &Path) -> Result<Vec<(String, u64)>> {
+    let csv = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let mut payments = Vec::new();
+    for (n, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.eq_ignore_ascii_case("recipient,amount") {
+            continue;
+        }
+        let at = || format!("{} line {}", path.display(), n + 1);
+        let (recipient, amount) = line
+            .split_once(',')
+            .with_context(|| format!("{}: expected recipient,amount", at()))?;
+        let recipient = recipient.trim();
+        if recipient.is_empty() {
+            anyhow::bail!("{}: no recipient", at());
+        }
+        let amount = parse_rhz(amount.trim()).with_context(at)?;
+        payments.push((recipient.to_string(), amount));
+    }
+    if !(2..=MAX_BATCH_OUTPUTS).contains(&payments.len()) {
+        anyhow::bail!(
+            "{} lists {} payments; a batch pays between 2 and {}",
+            path.display(),
+            payments.len(),
+            MAX_BATCH_OUTPUTS
+        );
+    }
+    Ok(payments)
+}
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Minimal blocking client for a running node's REST API
+pub struct NodeClient {
+    base_url: String,
+    http: reqwest::blocking::Client,
+    /// Bearer token for nodes that require API keys
+    api_key: Option<String>,
+}
+
+impl NodeClient {
+    pub fn new(base_url: &str, api_key: Option<&str>) -> Self {
+        NodeClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::blocking::Client::new(),
+            api_key: api_key.map(str::to_string),
+        }
+    }
+
+    /// GET a JSON resource
+    pub fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self
+            .request(reqwest::Method::GET, path)
+            .send()
+            .with_context(|| format!("Could not reach node at {}", self.base_url))?;
+        Self::decode(response)
+    }
+
+    /// GET a JSON resource with `query` as URL-encoded query parameters
+    pub fn get_query<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        let response = self
+            .request(reqwest::Method::GET, path)
+            .query(query)
+            .send()
+            .with_context(|| format!("Could not reach node at {}", self.base_url))?;
+        Self::decode(response)
+    }
+
+    /// POST a JSON body and decode the JSON response
+    pub fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let response = self
+            .request(reqwest::Method::POST, path)
+            .json(body)
+            .send()
+            .with_context(|| format!("Could not reach node at {}", self.base_url))?;
+        Self::decode(response)
+    }
+
+    /// PUT a JSON body and decode the JSON response
+    pub fn put<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let response = self
+            .request(reqwest::Method::PUT, path)
+            .json(body)
+            .send()
+            .with_context(|| format!("Could not reach node at {}", self.base_url))?;
+        Self::decode(response)
+    }
+
+    /// POST without a body, returning the response text
+    pub fn post_empty(&self, path: &str) -> Result<String> {
+        let response = self
+            .request(reqwest::Method::POST, path)
+            .send()
+            .with_context(|| format!("Could not reach node at {}", self.base_url))?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!("Node returned {}: {}", status, text);
+        }
+        Ok(text)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::blocking::RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(response: reqwest::blocking::Response) -> Result<T> {
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().unwrap_or_default();
+            anyhow::bail!("Node returned {}: {}", status, message);
+        }
+        Ok(response.json()?)
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use rhiza_core::dag::transaction::TransactionData;
+use rhiza_core::wallet::{Address, SigningSummary};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Print what `data` commits its signer to, decoded from the bytes the
+/// signature covers rather than taken from its fields
+pub fn show(data: &TransactionData) -> Result<()> {
+    let summary = SigningSummary::from_signing_bytes(&data.to_signing_bytes())
+        .map_err(|e| anyhow::anyhow!("Could not read the transaction back: {}", e))?;
+    let highlight = std::io::stdout().is_terminal();
+    for (label, value) in summary.lines() {
+        println!("  {:<13} {}", format!("{}:", label), emphasize(&value, highlight));
+    }
+    Ok(())
+}
+
+/// Show the summary and ask before the transaction is signed or leaves the
+/// machine; `assume_yes` (`--yes`) skips asking
+pub fn confirm(data: &TransactionData, assume_yes: bool) -> Result<()> {
+    println!();
+    println!("  ✍️  Check before signing");
+    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    show(data)?;
+    println!();
+    if assume_yes {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("No terminal to confirm on; check the summary above and pass --yes");
+    }
+    print!("  Compare the underlined checksum with the one you were given. Sign? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => anyhow::bail!("Not signed"),
+    }
+}
+
+/// An address with its checksum underlined on a terminal; anything else
+/// as it is
+fn emphasize(value: &str, highlight: bool) -> String {
+    match value.parse::<Address>() {
+        Ok(address) if highlight => {
+            let checksum = address.checksum();
+            format!("{}\x1b[1;4m{}\x1b[0m", &value[..value.len() - checksum.len()], checksum)
+        }
+        _ => value.to_string(),
+    }
+}
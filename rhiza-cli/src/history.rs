@@ -0,0 +1,190 @@
+use crate::client::NodeClient;
+use crate::{format_rhz, parse_public_key, HistoryEntry};
+use anyhow::{Context, Result};
+use rhiza_core::crypto::keys::PublicKey;
+use rhiza_core::wallet::address::Address;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+/// File formats `rhiza history` exports
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// One transaction as it affected the wallet, amounts in RHZ
+#[derive(Debug, Serialize)]
+pub struct ExportRow {
+    /// RFC 3339, UTC
+    pub date: String,
+    pub timestamp_ms: u64,
+    pub id: String,
+    pub tx_type: String,
+    /// "in", "out", "self" (between the wallet's own keys), or "fee" when
+    /// the wallet only paid someone else's fee
+    pub direction: &'static str,
+    /// Address on the other side; empty for self-transfers, batches the
+    /// wallet sent and newly issued coins
+    pub counterparty: String,
+    pub amount: String,
+    /// Fee and relay tip the wallet paid
+    pub fee: String,
+    /// What the wallet's balance changed by, signed
+    pub net: String,
+    pub memo: String,
+    /// "final" or "pending"
+    pub status: &'static str,
+    pub tags: Vec<String>,
+}
+
+/// Transactions types that issue new coins rather than move them
+const ISSUANCE: [&str; 3] = ["Genesis", "FounderAllocation", "RelayReward"];
+
+/// The wallet's transactions from `from_ms` to `to_ms`, oldest first
+///
+/// Asks the node for what each key sent, received or paid the fee of, so
+/// only transactions still in the node's DAG are found; history it pruned
+/// is left out.
+pub fn collect(
+    client: &NodeClient,
+    keys: &[PublicKey],
+    from_ms: u64,
+    to_ms: u64,
+) -> Result<Vec<ExportRow>> {
+    let mut entries = BTreeMap::new();
+    for key in keys {
+        let listed: Vec<HistoryEntry> = client.get(&format!("/transactions?key={}", key))?;
+        for entry in listed {
+            entries.insert(entry.id.clone(), entry);
+        }
+    }
+    let ours: HashSet<String> = keys.iter().map(|k| k.to_string()).collect();
+    let mut rows: Vec<ExportRow> = entries
+        .into_values()
+        .filter(|e| e.timestamp >= from_ms && e.timestamp <= to_ms)
+        .filter_map(|e| export_row(e, &ours).transpose())
+        .collect::<Result<_>>()?;
+    rows.sort_by_key(|row| row.timestamp_ms);
+    Ok(rows)
+}
+
+/// How a transaction affected the wallet; `None` if it didn't, as with coins
+/// issued to someone else
+fn export_row(entry: HistoryEntry, ours: &HashSet<String>) -> Result<Option<ExportRow>> {
+    let issued = ISSUANCE.contains(&entry.tx_type.as_str());
+    let sent = !issued && ours.contains(&entry.sender);
+    // A batch pays its outputs, not its recipient (the sender)
+    let batch = !entry.outputs.is_empty();
+    let credited: u64 = match batch {
+        true => entry.outputs.iter().filter(|o| ours.contains(&o.recipient)).map(|o| o.amount).sum(),
+        false => entry.amount,
+    };
+    let received = if batch { credited > 0 } else { ours.contains(&entry.recipient) };
+    let paid_fee = !issued && ours.contains(entry.fee_payer.as_ref().unwrap_or(&entry.sender));
+    if !sent && !received && !paid_fee {
+        return Ok(None);
+    }
+
+    let (direction, counterparty) = match (sent, received) {
+        _ if issued => ("in", None),
+        (true, _) if batch => ("out", None),
+        (true, true) => ("self", None),
+        (false, true) => ("in", Some(&entry.sender)),
+        (true, false) => ("out", Some(&entry.recipient)),
+        (false, false) => ("fee", Some(&entry.sender)),
+    };
+    let counterparty = match counterparty {
+        Some(key) => Address::from_public_key(&parse_public_key(key)?).to_string(),
+        None => String::new(),
+    };
+
+    let mut costs = if paid_fee { entry.fee } else { 0 };
+    if sent {
+        costs = costs.saturating_add(entry.relay_tip);
+    }
+    let mut net = -(costs as i128);
+    if received {
+        net += credited as i128;
+    }
+    if sent {
+        net -= entry.amount as i128;
+    }
+
+    let date = chrono::DateTime::from_timestamp_millis(entry.timestamp as i64)
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_default();
+    Ok(Some(ExportRow {
+        date,
+        timestamp_ms: entry.timestamp,
+        id: entry.id,
+        tx_type: entry.tx_type,
+        direction,
+        counterparty,
+        amount: format_rhz(if sent { entry.amount } else { credited }),
+        fee: format_rhz(costs),
+        net: format!("{}{}", if net < 0 { "-" } else { "" }, format_rhz(net.unsigned_abs() as u64)),
+        memo: entry.memo.unwrap_or_default(),
+        status: if entry.is_final { "final" } else { "pending" },
+        tags: entry.tags,
+    }))
+}
+
+const CSV_HEADER: &str = "date,id,type,direction,counterparty,amount_rhz,fee_rhz,net_rhz,memo,status,tags";
+
+/// Write the rows to `path`, or standard output without one
+pub fn write(rows: &[ExportRow], format: ExportFormat, path: Option<&Path>) -> Result<()> {
+    let out = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(rows)? + "\n",
+        ExportFormat::Csv => {
+            let mut out = String::from(CSV_HEADER);
+            out.push('\n');
+            for row in rows {
+                let fields = [
+                    row.date.as_str(),
+                    &row.id,
+                    &row.tx_type,
+                    row.direction,
+                    &row.counterparty,
+                    &row.amount,
+                    &row.fee,
+                    &row.net,
+                    &row.memo,
+                    row.status,
+                    &row.tags.join(";"),
+                ];
+                out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+                out.push('\n');
+            }
+            out
+        }
+    };
+    match path {
+        Some(path) => {
+            std::fs::write(path, out).with_context(|| format!("Could not write {}", path.display()))
+        }
+        None => Ok(std::io::stdout().write_all(out.as_bytes())?),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `--from` or `--to` as unix ms: an RFC 3339 time, or a date (UTC) taken
+/// as its first millisecond, or with `end_of_day` its last
+pub fn parse_date(date: &str, end_of_day: bool) -> Result<u64> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(date) {
+        return Ok(time.timestamp_millis().max(0) as u64);
+    }
+    let day = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date {}; use YYYY-MM-DD or an RFC 3339 time", date))?;
+    let start = day.and_time(chrono::NaiveTime::MIN).and_utc().timestamp_millis().max(0) as u64;
+    Ok(if end_of_day { start + 86_400_000 - 1 } else { start })
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::wallet::keyfile;
+use rhiza_core::wallet::keystore::KeyStore;
+use std::path::Path;
+
+pub use rhiza_core::wallet::keyfile::{create, create_hd, new_password, read_password};
+
+/// Unlock a wallet, encrypting it first if it is a legacy plaintext one
+pub fn unlock(path: &Path, keystore: KeyStore, password_file: Option<&Path>) -> Result<KeyPair> {
+    if keystore.is_encrypted() {
+        return Ok(keyfile::unlock(&keystore, password_file)?);
+    }
+
+    println!("⚠️  This wallet stores its secret key unencrypted. Choose a password to encrypt it.");
+    encrypt(path, keystore, password_file)
+}
+
+/// Encrypt a legacy wallet in place and return its keypair
+pub fn encrypt(path: &Path, keystore: KeyStore, password_file: Option<&Path>) -> Result<KeyPair> {
+    let keypair = keyfile::encrypt(path, keystore, password_file)?;
+    println!("🔒 Wallet encrypted.");
+    Ok(keypair)
+}
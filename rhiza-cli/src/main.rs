@@ -1,9 +1,31 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use rhiza_core::crypto::keys::KeyPair;
+use client::NodeClient;
+use rhiza_core::consensus::finality::FinalityStatus;
+use rhiza_core::consensus::proof::{FinalityProof, ProofPolicy};
+use rhiza_core::crypto::keys::{DerivationPath, KeyPair, PublicKey};
+use rhiza_core::crypto::Hash;
+use rhiza_core::crypto::mnemonic::Mnemonic;
+use rhiza_core::crypto::multisig::MultisigPolicy;
+use rhiza_core::dag::htlc::Preimage;
+use rhiza_core::dag::transaction::{
+    BatchOutput, Transaction, TransactionData, TransactionType, TIME_LOCK_THRESHOLD,
+};
 use rhiza_core::wallet::address::Address;
+use rhiza_core::wallet::contacts::{Contacts, CONTACTS_FILE};
 use rhiza_core::wallet::keystore::KeyStore;
-use std::path::PathBuf;
+use rhiza_core::wallet::uri::PaymentUri;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+mod batch;
+mod client;
+mod confirm;
+mod history;
+mod keyfile;
+mod offline;
+mod qr;
 
 /// Rhiza CLI — Wallet and tools for the Rhiza decentralized currency
 #[derive(Parser)]
@@ -20,6 +42,22 @@ struct Cli {
     /// Wallet directory
     #[arg(long, default_value = "~/.rhiza")]
     wallet_dir: String,
+
+    /// REST API URL of the node to talk to
+    #[arg(long, global = true, default_value = "http://127.0.0.1:7471")]
+    node_url: String,
+
+    /// API key token, for nodes that require one
+    #[arg(long, global = true, env = "RHIZA_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Read the wallet password from this file instead of prompting
+    #[arg(long, global = true, env = "RHIZA_PASSWORD_FILE")]
+    password_file: Option<PathBuf>,
+
+    /// Sign without asking to confirm the summary shown first (for scripts)
+    #[arg(long, global = true)]
+    yes: bool,
 }
 
 #[derive(Subcommand)]
@@ -30,17 +68,146 @@ enum Commands {
         action: WalletCommands,
     },
 
+    /// Sign a payment with the CLI wallet and submit it through the node
+    Send {
+        /// Recipient: contact name, rhz1... address, name@rhz alias, hex public key
+        /// or rhiza: payment URI
+        #[arg(required_unless_present = "batch")]
+        recipient: Option<String>,
+        /// Amount in RHZ, e.g. 1.5 (optional when a payment URI names it)
+        amount: Option<String>,
+        /// Public note attached to the payment
+        #[arg(long)]
+        memo: Option<String>,
+        /// Fee in RHZ, burned, to get ahead of other pending payments (see `fees`)
+        #[arg(long)]
+        fee: Option<String>,
+        /// Tip in RHZ for the relayer whose claim for the payment finalizes first
+        #[arg(long)]
+        relay_tip: Option<String>,
+        /// Hold the payment back from the recipient until a date (2027-01-31 or
+        /// RFC 3339), Unix time in ms, or a DAG depth (below 500000000)
+        #[arg(long)]
+        valid_after: Option<String>,
+        /// Pay every `recipient,amount` line (amount in RHZ) of this CSV file in
+        /// one transaction
+        #[arg(long, conflicts_with_all = ["recipient", "amount", "valid_after"])]
+        batch: Option<PathBuf>,
+    },
+
+    /// Show what a payment should offer in fee and relay tip to get ahead
+    Fees,
+
+    /// Look up transactions on the node, or pay from a key kept offline
+    Tx {
+        #[command(subcommand)]
+        action: TxCommands,
+    },
+
+    /// Show the node wallet's transaction history, or export this wallet's
+    History {
+        /// Only show transactions with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Export the wallet's transactions in this format, for accounting
+        /// or tax software
+        #[arg(long, value_enum)]
+        format: Option<history::ExportFormat>,
+        /// Export transactions from this date (YYYY-MM-DD, UTC) or RFC 3339 time
+        #[arg(long, requires = "format")]
+        from: Option<String>,
+        /// Export transactions up to and including this date or time
+        #[arg(long, requires = "format")]
+        to: Option<String>,
+        /// Write the export to this file instead of standard output
+        #[arg(short, long, requires = "format")]
+        output: Option<PathBuf>,
+    },
+
+    /// Tag the node wallet's transactions (stored locally, never on the DAG)
+    Tags {
+        #[command(subcommand)]
+        action: TagCommands,
+    },
+
+    /// Publish the node wallet's public key so its rhz1 address can be paid
+    Announce,
+
+    /// Human-readable aliases (name@rhz)
+    Alias {
+        #[command(subcommand)]
+        action: AliasCommands,
+    },
+
+    /// Names for the addresses you pay, kept with the wallet
+    Contacts {
+        #[command(subcommand)]
+        action: ContactCommands,
+    },
+
+    /// Accounts shared by several keys, spent with the signatures of M of N
+    Multisig {
+        #[command(subcommand)]
+        action: MultisigCommands,
+    },
+
+    /// Hash-time-locked payments, for swapping RHZ against other chains
+    Htlc {
+        #[command(subcommand)]
+        action: HtlcCommands,
+    },
+
     /// Show network information
     Info,
 
     /// Show protocol constants
     Protocol,
+
+    /// Audit the founder grant: allocation, vesting and every spend so far
+    Founder,
+
+    /// Rank relayers by the relays their final reward claims proved
+    Relayers {
+        /// day, week, month or all
+        #[arg(long, default_value = "week", value_parser = ["day", "week", "month", "all"])]
+        period: String,
+
+        /// How many relayers to list
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Manage a running node over its admin API
+    Node {
+        #[command(subcommand)]
+        action: NodeCommands,
+    },
 }
 
 #[derive(Subcommand)]
 enum WalletCommands {
     /// Create a new wallet
-    Create,
+    Create {
+        /// Derive the key from a new 24-word recovery phrase and print it
+        #[arg(long)]
+        mnemonic: bool,
+    },
+
+    /// Restore a wallet from its recovery phrase
+    Restore {
+        /// The 12-24 word recovery phrase
+        #[arg(required = true, num_args = 1..)]
+        phrase: Vec<String>,
+
+        /// Optional BIP39 passphrase ("25th word")
+        #[arg(long, env = "RHIZA_MNEMONIC_PASSPHRASE", default_value = "", hide_env_values = true)]
+        passphrase: String,
+
+        /// Key of another Ed25519 wallet to restore instead of the master key:
+        /// solana, stellar, solana:<account>, stellar:<account> or a path like m/44'/501'/0'/0'
+        #[arg(long, default_value = "m")]
+        path: DerivationPath,
+    },
 
     /// Show wallet address and balance
     Show,
@@ -50,12 +217,637 @@ enum WalletCommands {
 
     /// Export wallet (display secret key — be careful!)
     Export,
+
+    /// Encrypt a legacy plaintext wallet, or change the wallet password
+    Encrypt,
+
+    /// Hand out a fresh receive key (recovery-phrase wallets only)
+    Receive {
+        /// Account to derive the key in
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+        /// Amount in RHZ to ask for in the payment URI
+        #[arg(long)]
+        amount: Option<String>,
+        /// Memo to ask the payer to attach
+        #[arg(long)]
+        memo: Option<String>,
+        /// Print the payment URI as a QR code
+        #[arg(long)]
+        qr: bool,
+        /// Write the payment URI as a QR code to this PNG file
+        #[arg(long)]
+        png: Option<PathBuf>,
+    },
+
+    /// List the wallet's accounts and the receive keys handed out
+    Accounts,
+
+    /// Add an account to a recovery-phrase wallet
+    NewAccount {
+        /// A name for the account
+        label: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TxCommands {
+    /// Show a transaction and how close it is to finality
+    Status {
+        /// Transaction ID (hex)
+        id: String,
+    },
+
+    /// Show what the node saw happen to a transaction, step by step
+    Trace {
+        /// Transaction ID (hex)
+        id: String,
+    },
+
+    /// Replace a pending payment with a higher relay tip and, optionally,
+    /// a corrected memo
+    Replace {
+        /// Transaction ID (hex) of the payment to replace
+        id: String,
+        /// New relay tip in RHZ; must be higher than the payment's current one
+        #[arg(long = "tip")]
+        relay_tip: String,
+        /// Corrected memo (an empty one removes it; kept if not given)
+        #[arg(long)]
+        memo: Option<String>,
+    },
+
+    /// Fetch a final transaction's finality proof and check it locally
+    Proof {
+        /// Transaction ID (hex)
+        id: String,
+        /// Hex public key of a node whose attestation to trust (repeatable;
+        /// defaults to the node asked)
+        #[arg(long = "attester")]
+        attesters: Vec<String>,
+        /// Distinct keys other than the payer's that must have approved it
+        #[arg(long, default_value_t = ProofPolicy::default().min_witnesses)]
+        min_witnesses: usize,
+        /// Write the hex proof blob to this file instead of printing it
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Build an unsigned payment, for `tx sign` on a machine that holds the
+    /// key but never goes online
+    Create {
+        /// Recipient: contact name, rhz1... address, name@rhz alias, hex public key
+        /// or rhiza: payment URI
+        recipient: String,
+        /// Amount in RHZ, e.g. 1.5 (optional when a payment URI names it)
+        amount: Option<String>,
+        /// Paying key: hex public key, rhz1... address or contact name
+        /// (defaults to the CLI wallet's)
+        #[arg(long)]
+        from: Option<String>,
+        /// Public note attached to the payment
+        #[arg(long)]
+        memo: Option<String>,
+        /// Fee in RHZ, burned, to get ahead of other pending payments (see `fees`)
+        #[arg(long)]
+        fee: Option<String>,
+        /// Tip in RHZ for the relayer whose claim for the payment finalizes first
+        #[arg(long)]
+        relay_tip: Option<String>,
+        /// Hold the payment back from the recipient until a date, Unix time in
+        /// ms, or a DAG depth
+        #[arg(long)]
+        valid_after: Option<String>,
+        /// Hex ID of a transaction to approve (repeat for each; defaults to
+        /// two of the node's tips)
+        #[arg(long = "parent")]
+        parents: Vec<String>,
+        /// Network to pay on: mainnet, testnet, devnet or an ID (defaults to
+        /// the node's)
+        #[arg(long, value_parser = parse_network)]
+        network: Option<u32>,
+        /// File to write the unsigned payment to
+        #[arg(long, default_value = "unsigned-tx.json")]
+        out: PathBuf,
+    },
+
+    /// Sign a payment from `tx create` with the CLI wallet; needs no node
+    Sign {
+        /// Unsigned payment file
+        file: PathBuf,
+        /// File to write the signed payment to
+        #[arg(long, default_value = "signed-tx.json")]
+        out: PathBuf,
+    },
+
+    /// Submit a payment signed with `tx sign` through the node
+    Broadcast {
+        /// Signed payment file
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum AliasCommands {
+    /// Look up who owns an alias and where to pay it
+    Resolve {
+        /// Alias, e.g. alice@rhz
+        alias: String,
+    },
+
+    /// Announce an alias for the node wallet (first to finalize wins it)
+    Announce {
+        /// Alias, e.g. alice@rhz
+        alias: String,
+        /// Hex public key payments should go to (defaults to the node wallet)
+        #[arg(long)]
+        receive_pubkey: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContactCommands {
+    /// Save a name for an address or key
+    Add {
+        /// Name to pay the contact by, e.g. alice
+        name: String,
+        /// rhz1... address or hex public key
+        target: String,
+    },
+
+    /// Show all contacts
+    List,
+
+    /// Forget a contact
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MultisigCommands {
+    /// Show the account any THRESHOLD of the members control, to pay into
+    Address {
+        /// Signatures a spend needs
+        #[arg(long)]
+        threshold: u8,
+        /// Hex public key of a member (repeat for each)
+        #[arg(long = "member", required = true)]
+        members: Vec<String>,
+    },
+
+    /// Draft a spend from a multisig account, signed by the CLI wallet if
+    /// it's a member, for the other members to sign
+    Create {
+        /// Signatures a spend needs
+        #[arg(long)]
+        threshold: u8,
+        /// Hex public key of a member (repeat for each)
+        #[arg(long = "member", required = true)]
+        members: Vec<String>,
+        /// Recipient: contact name, rhz1... address, name@rhz alias or hex public key
+        recipient: String,
+        /// Amount in RHZ, e.g. 1.5
+        amount: String,
+        /// Public note attached to the payment
+        #[arg(long)]
+        memo: Option<String>,
+        /// Fee in RHZ, burned, paid by the multisig account
+        #[arg(long)]
+        fee: Option<String>,
+        /// File to write the draft to
+        #[arg(long, default_value = "multisig-spend.json")]
+        out: PathBuf,
+    },
+
+    /// Add the CLI wallet's signature to a drafted spend
+    Sign {
+        /// Draft file; the signature is added in place
+        file: PathBuf,
+    },
+
+    /// Submit a drafted spend once enough members have signed it
+    Finalize {
+        /// Draft file
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum HtlcCommands {
+    /// Make a random secret and the hash lock it opens
+    Secret,
+
+    /// Lock RHZ from the CLI wallet for the recipient to claim with the
+    /// secret behind a hash, or to take back after a timeout
+    Lock {
+        /// Recipient: contact name, rhz1... address, name@rhz alias or hex public key
+        recipient: String,
+        /// Amount in RHZ, e.g. 1.5
+        amount: String,
+        /// Hex SHA-256 of the secret
+        #[arg(long)]
+        hash_lock: String,
+        /// When the lock can be refunded: a date (2027-01-31) or RFC 3339
+        /// time, Unix time in ms, or a DAG depth (below 500000000)
+        #[arg(long)]
+        timeout: String,
+    },
+
+    /// Claim a lock made out to the CLI wallet with its secret
+    Claim {
+        /// Id of the lock transaction
+        id: String,
+        /// Hex secret whose SHA-256 is the lock's hash
+        #[arg(long)]
+        secret: String,
+    },
+
+    /// Take back a lock the CLI wallet made, once its timeout has passed
+    Refund {
+        /// Id of the lock transaction
+        id: String,
+    },
+
+    /// Show a lock, and its secret once a claim has revealed it
+    Status {
+        /// Id of the lock transaction
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NodeCommands {
+    /// Show version, uptime, DAG, pool and peer counts
+    Status,
+
+    /// List connected peers and their reputation
+    Peers,
+
+    /// Ban a peer and drop its connection
+    Ban {
+        /// Hex public key of the peer
+        peer_id: String,
+        /// How long; defaults to the misbehavior ban length, doubling with repeats
+        #[arg(long)]
+        hours: Option<u64>,
+        /// Why, for the ban list and the peer audit log
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Show DAG syncs in progress with peers
+    SyncStatus,
+
+    /// Unlock the node's wallet so its API may spend (asks for its password)
+    Unlock {
+        /// How long it stays unlocked; defaults to the node's wallet_session setting
+        #[arg(long)]
+        minutes: Option<u64>,
+    },
+
+    /// Lock the node's wallet again
+    Lock,
+
+    /// Stop the node
+    Shutdown,
+}
+
+#[derive(Subcommand)]
+enum TagCommands {
+    /// Set the tags on a transaction (no tags clears them)
+    Set {
+        /// Transaction ID
+        id: String,
+        /// Tags, e.g. groceries relay-income
+        tags: Vec<String>,
+    },
+
+    /// Show per-tag totals
+    Report,
+}
+
+/// A finalized alias binding as reported by the node API
+#[derive(Deserialize)]
+struct AliasEntry {
+    alias: String,
+    owner_address: String,
+    receive_address: String,
+    receive_public_key: String,
+}
+
+#[derive(Serialize)]
+struct AliasRequest {
+    alias: String,
+    receive_pubkey_hex: Option<String>,
+}
+
+/// A hash-time-locked contract, as reported by the node API
+#[derive(Deserialize)]
+struct HtlcInfo {
+    id: String,
+    locker: String,
+    claimer: String,
+    amount: u64,
+    hash_lock: String,
+    timeout: u64,
+    status: String,
+    expired: bool,
+    settled_by: Option<String>,
+    preimage: Option<String>,
+}
+
+/// A transaction accepted by the node API
+#[derive(Deserialize)]
+struct TransactionResponse {
+    id: String,
+    status: String,
+}
+
+/// A transaction with its finality status, as reported by the node API
+#[derive(Deserialize)]
+struct TransactionStatus {
+    transaction: Transaction,
+    status: String,
+    #[serde(default)]
+    approvers: u64,
+    needed: u64,
+    depth: u64,
+    #[serde(default)]
+    tip_collected_by: Option<String>,
+    #[serde(default)]
+    replaced_by: Option<String>,
+}
+
+/// A transaction's lifecycle on the node, as reported by the node API
+#[derive(Deserialize)]
+struct TransactionTrace {
+    id: String,
+    status: String,
+    approvers: u64,
+    needed: u64,
+    timeline: Vec<TraceStep>,
+}
+
+#[derive(Deserialize)]
+struct TraceStep {
+    at_ms: u64,
+    stage: String,
+    /// Whatever else the stage carries (origin, depth, peers, ...)
+    #[serde(flatten)]
+    details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TraceStep {
+    fn describe(&self) -> String {
+        let text = |value: Option<&serde_json::Value>| match value {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        let detail = |key: &str| text(self.details.get(key));
+        let origin = |key: &str| text(self.details.get("origin").and_then(|o| o.get(key)));
+        match self.stage.as_str() {
+            "first_seen" => match origin("via").as_str() {
+                "local" => "made or submitted here".to_string(),
+                "sync" => "fetched in a sync".to_string(),
+                via => format!("received by {} from {} over {}", via, origin("peer_id"), origin("transport")),
+            },
+            "validated" => "passed validation".to_string(),
+            "rejected" => format!("rejected: {}", detail("reason")),
+            "inserted" => format!("added to the DAG at depth {}", detail("depth")),
+            "gossiped" if detail("stem") == "true" => "sent down a Dandelion stem".to_string(),
+            "gossiped" => format!("flooded to {} peers", detail("peers")),
+            "approved" => format!("approved by {} ({} approvers)", detail("by"), detail("approvers")),
+            "finalized" => "final".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A finality proof as served by the node API
+#[derive(Deserialize)]
+struct FinalityProofResponse {
+    attester: String,
+    proof: String,
+}
+
+/// The founder grant as reported by the node API
+#[derive(Deserialize)]
+struct FounderReport {
+    address: String,
+    allocation: u64,
+    allocation_id: Option<String>,
+    vesting: FounderVesting,
+    balance: u64,
+    total_spent: u64,
+    spends: Vec<FounderSpend>,
+}
+
+#[derive(Deserialize)]
+struct FounderVesting {
+    schedule: String,
+    vested: u64,
+    locked: u64,
+}
+
+#[derive(Deserialize)]
+struct FounderSpend {
+    id: String,
+    recipient: PublicKey,
+    debited: u64,
+    is_final: bool,
+    rejected: bool,
+}
+
+/// The relay leaderboard as reported by the node API
+#[derive(Deserialize)]
+struct RelayLeaderboard {
+    relayers: usize,
+    total_relays: u64,
+    total_rewards: u64,
+    top: Vec<RelayerStanding>,
+    /// The node's own standing
+    local: Option<RelayerStanding>,
+}
+
+#[derive(Deserialize)]
+struct RelayerStanding {
+    rank: usize,
+    address: String,
+    claims: u64,
+    relays: u64,
+    rewards: u64,
+}
+
+/// The parts of the node's `/info` the CLI needs
+#[derive(Deserialize)]
+struct NodeInfo {
+    dag_size: u64,
+    /// Older nodes don't report it; they are all on mainnet
+    #[serde(default = "mainnet_id")]
+    network_id: u32,
+}
+
+fn mainnet_id() -> u32 {
+    rhiza_core::MAINNET_ID
+}
+
+/// A running node's status from its admin API
+#[derive(Deserialize)]
+struct AdminStatus {
+    version: String,
+    uptime_secs: u64,
+    network_id: u32,
+    address: String,
+    backend: String,
+    dag_size: u64,
+    dag_depth: u64,
+    tips_count: u64,
+    pending: u64,
+    work_bits: u32,
+    peers: u64,
+    held_messages: u64,
+    health: String,
+    update_available: Option<String>,
+}
+
+/// A connected peer as listed by the node API
+#[derive(Deserialize)]
+struct PeerEntry {
+    peer_id: String,
+    address: String,
+    direction: String,
+    bucket: String,
+    anchor: bool,
+    connected_since: u64,
+    reputation: PeerReputation,
+}
+
+#[derive(Deserialize)]
+struct PeerReputation {
+    score: i64,
+}
+
+#[derive(Serialize)]
+struct BanRequest {
+    peer_id: String,
+    duration_secs: Option<u64>,
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UnlockRequest<'a> {
+    password: &'a str,
+    timeout_secs: Option<u64>,
+}
+
+/// The node wallet's session as reported by the node API
+#[derive(Deserialize)]
+struct WalletSession {
+    unlocked_until: Option<u64>,
+}
+
+/// A ban as reported by the node API
+#[derive(Deserialize)]
+struct BanEntry {
+    peer_id: String,
+    address: Option<String>,
+    until_ms: u64,
+    reason: String,
+    count: u32,
+}
+
+/// Sync progress as reported by the node's admin API
+#[derive(Deserialize)]
+struct SyncStatus {
+    dag_depth: u64,
+    synced: bool,
+    parked: u64,
+    syncs: Vec<PeerSync>,
+}
+
+#[derive(Deserialize)]
+struct FeeEstimate {
+    pending: u64,
+    capacity: u64,
+    minimum: u128,
+    median: u128,
+    fast: u128,
+}
+
+#[derive(Deserialize)]
+struct PeerSync {
+    peer_id: String,
+    next_depth: u64,
+    target_depth: u64,
+    phase: String,
+}
+
+/// Balance lookup result, used to find the key behind an address
+#[derive(Deserialize)]
+struct AddressBalance {
+    public_key: Option<String>,
+}
+
+/// A transaction as listed by the node API
+#[derive(Deserialize)]
+struct HistoryEntry {
+    id: String,
+    tx_type: String,
+    sender: String,
+    recipient: String,
+    amount: u64,
+    amount_rhz: f64,
+    #[serde(default)]
+    fee: u64,
+    #[serde(default)]
+    fee_payer: Option<String>,
+    #[serde(default)]
+    relay_tip: u64,
+    is_incoming: bool,
+    #[serde(default)]
+    is_final: bool,
+    timestamp: u64,
+    memo: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// What a batch pays each recipient
+    #[serde(default)]
+    outputs: Vec<HistoryOutput>,
+}
+
+#[derive(Deserialize)]
+struct HistoryOutput {
+    recipient: String,
+    amount: u64,
+}
+
+#[derive(Serialize)]
+struct TagRequest {
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TagResponse {
+    id: String,
+    tags: Vec<String>,
+}
+
+/// Per-tag totals as reported by the node API
+#[derive(Deserialize)]
+struct TagTotals {
+    tag: String,
+    count: u64,
+    incoming: u64,
+    outgoing: u64,
+    net: i128,
 }
 
 fn expand_path(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
+    if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = dirs_next::home_dir() {
-            return home.join(&path[2..]);
+            return home.join(rest);
         }
     }
     PathBuf::from(path)
@@ -65,22 +857,35 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
     let wallet_dir = expand_path(&cli.wallet_dir);
     let wallet_path = wallet_dir.join("wallet.json");
+    let contacts_path = wallet_dir.join(CONTACTS_FILE);
+
+    let password_file = cli.password_file.as_deref();
 
     match cli.command {
         Commands::Wallet { action } => match action {
-            WalletCommands::Create => {
+            WalletCommands::Create { mnemonic } => {
                 if wallet_path.exists() {
                     println!("⚠️  Wallet already exists at {}", wallet_path.display());
                     println!("   Delete it first if you want to create a new one.");
                     return Ok(());
                 }
 
-                let keypair = KeyPair::generate();
+                let (keypair, phrase) = if mnemonic {
+                    let (keypair, phrase) = KeyPair::generate_with_mnemonic();
+                    (keypair, Some(phrase))
+                } else {
+                    (KeyPair::generate(), None)
+                };
                 let address = Address::from_public_key(&keypair.public_key);
-                let keystore = KeyStore::from_keypair(&keypair);
 
                 std::fs::create_dir_all(&wallet_dir)?;
-                keystore.save(&wallet_path)?;
+                match &phrase {
+                    Some(phrase) => {
+                        let seed = phrase.to_seed("")?;
+                        keyfile::create_hd(&wallet_path, &seed, DerivationPath::master(), password_file)?
+                    }
+                    None => keyfile::create(&wallet_path, &keypair, password_file)?,
+                }
 
                 println!();
                 println!("  🌿 Rhiza Wallet Created!");
@@ -89,8 +894,58 @@ fn main() -> Result<()> {
                 println!("  🔑 Public Key: {}", keypair.public_key);
                 println!("  📁 Saved to:   {}", wallet_path.display());
                 println!();
-                println!("  ⚠️  IMPORTANT: Back up your wallet.json file!");
-                println!("     Losing it means losing access to your RHZ forever.");
+                match phrase {
+                    Some(phrase) => {
+                        println!("  📝 Recovery phrase:");
+                        for (i, word) in phrase.phrase().split(' ').enumerate() {
+                            print!("  {:>2}. {:<10}", i + 1, word);
+                            if (i + 1).is_multiple_of(4) {
+                                println!();
+                            }
+                        }
+                        println!();
+                        println!("  ⚠️  IMPORTANT: Write these words down and keep them offline!");
+                        println!("     Anyone with the phrase controls your RHZ; restore with:");
+                        println!("     rhiza wallet restore <words...>");
+                    }
+                    None => {
+                        println!("  ⚠️  IMPORTANT: Back up your wallet.json file and its password!");
+                        println!("     Losing either means losing access to your RHZ forever.");
+                    }
+                }
+                println!();
+
+                Ok(())
+            }
+
+            WalletCommands::Restore { phrase, passphrase, path } => {
+                if wallet_path.exists() {
+                    println!("⚠️  Wallet already exists at {}", wallet_path.display());
+                    println!("   Delete it first if you want to restore another one.");
+                    return Ok(());
+                }
+
+                let phrase = Zeroizing::new(phrase.join(" "));
+                let passphrase = Zeroizing::new(passphrase);
+                let keypair = KeyPair::from_mnemonic_at(&phrase, &passphrase, &path)?;
+                let address = Address::from_public_key(&keypair.public_key);
+                let seed = Mnemonic::from_phrase(&phrase)?.to_seed(&passphrase)?;
+
+                std::fs::create_dir_all(&wallet_dir)?;
+                keyfile::create_hd(&wallet_path, &seed, path.clone(), password_file)?;
+
+                println!();
+                println!("  🌿 Rhiza Wallet Restored!");
+                println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  📍 Address:    {}", address);
+                println!("  🔑 Public Key: {}", keypair.public_key);
+                if !path.is_master() {
+                    println!("  🧭 Path:       {}", path);
+                }
+                println!("  📁 Saved to:   {}", wallet_path.display());
+                println!();
+                println!("  Receive keys handed out before are not listed; re-derive them");
+                println!("  with: rhiza wallet receive");
                 println!();
 
                 Ok(())
@@ -98,18 +953,24 @@ fn main() -> Result<()> {
 
             WalletCommands::Show => {
                 let keystore = load_wallet(&wallet_path)?;
-                let keypair = keystore.to_keypair()?;
-                let address = Address::from_public_key(&keypair.public_key);
+                let public_key = keystore.public_key()?;
+                let address = Address::from_public_key(&public_key);
 
                 // Check if this wallet is the founder
-                let is_founder = format!("{}", keypair.public_key) == rhiza_core::FOUNDER_PUBLIC_KEY;
+                let is_founder = format!("{}", public_key) == rhiza_core::FOUNDER_PUBLIC_KEY;
                 let founder_rhz = rhiza_core::FOUNDER_ALLOCATION / rhiza_core::UNITS_PER_RHZ;
 
                 println!();
                 println!("  🌿 Rhiza Wallet");
                 println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
                 println!("  📍 Address:    {}", address);
-                println!("  🔑 Public Key: {}", keypair.public_key);
+                println!("  🔑 Public Key: {}", public_key);
+                if !keystore.path().is_master() {
+                    println!("  🧭 Path:       {}", keystore.path());
+                }
+                if !keystore.is_encrypted() {
+                    println!("  ⚠️  Not encrypted — run: rhiza wallet encrypt");
+                }
                 if is_founder {
                     println!();
                     println!("  👑 Status:     FOUNDER");
@@ -123,37 +984,1064 @@ fn main() -> Result<()> {
 
             WalletCommands::Pubkey => {
                 let keystore = load_wallet(&wallet_path)?;
-                let keypair = keystore.to_keypair()?;
-                println!("{}", keypair.public_key);
+                println!("{}", keystore.public_key()?);
                 Ok(())
             }
 
             WalletCommands::Export => {
                 let keystore = load_wallet(&wallet_path)?;
-                let keypair = keystore.to_keypair()?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
 
                 println!();
                 println!("  ⚠️  WARNING: Never share your secret key!");
                 println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                println!("  🔐 Secret Key: {}", hex::encode(keypair.secret_bytes()));
+                println!("  🔐 Secret Key: {}", keypair.secret_hex().as_str());
+                println!("  🔑 Public Key: {}", keypair.public_key);
+                println!();
+
+                Ok(())
+            }
+
+            WalletCommands::Encrypt => {
+                let keystore = load_wallet(&wallet_path)?;
+                if keystore.is_encrypted() {
+                    // Changing the password: prove the old one first
+                    let mut keystore = keystore;
+                    let password = keyfile::read_password(None, "  Current password: ")?;
+                    keystore.unlock(&password)?;
+                    let new_password = keyfile::new_password(None)?;
+                    keystore.change_password(&password, &new_password)?;
+                    keystore.save(&wallet_path)?;
+                    println!("🔒 Wallet password changed.");
+                } else {
+                    keyfile::encrypt(&wallet_path, keystore, password_file)?;
+                }
+
+                Ok(())
+            }
+
+            WalletCommands::Receive { account, amount, memo, qr, png } => {
+                let amount = amount.as_deref().map(parse_rhz).transpose()?;
+                let mut keystore = load_hd_wallet(&wallet_path)?;
+                let password = keyfile::read_password(password_file, "  Wallet password: ")?;
+                let keypair = keystore.next_receive_key(&password, account)?;
+                keystore.save(&wallet_path)?;
+                let index = keystore
+                    .accounts()
+                    .iter()
+                    .find(|a| a.index == account)
+                    .map_or(0, |a| a.receive_keys.len() - 1);
+
+                println!();
+                println!("  📥 Receive key {} of account {}", index, account);
+                let address = Address::from_public_key(&keypair.public_key);
+                println!("  📍 Address:    {}", address);
                 println!("  🔑 Public Key: {}", keypair.public_key);
+                let mut uri = PaymentUri::new(address);
+                uri.amount = amount;
+                uri.memo = memo;
+                let uri = uri.to_string();
+                println!("  🔗 URI:        {}", uri);
+                println!();
+                if qr {
+                    println!("{}", qr::terminal(&uri)?);
+                    println!();
+                }
+                if let Some(path) = png {
+                    qr::write_png(&uri, &path)?;
+                    println!("  🖼️  QR code written to {}", path.display());
+                    println!();
+                }
+
+                Ok(())
+            }
+
+            WalletCommands::Accounts => {
+                let keystore = load_hd_wallet(&wallet_path)?;
+
+                println!();
+                for account in keystore.accounts() {
+                    println!("  📒 Account {} — {}", account.index, account.label);
+                    if account.receive_keys.is_empty() {
+                        println!("     (no receive keys yet)");
+                    }
+                    for (i, key) in account.receive_keys.iter().enumerate() {
+                        println!("     {:>3}. {}", i, Address::from_public_key(key));
+                    }
+                }
                 println!();
 
                 Ok(())
             }
+
+            WalletCommands::NewAccount { label } => {
+                let mut keystore = load_hd_wallet(&wallet_path)?;
+                let index = keystore.add_account(&label)?;
+                keystore.save(&wallet_path)?;
+                println!("📒 Added account {} — {}", index, label);
+                Ok(())
+            }
         },
 
-        Commands::Info => {
+        Commands::History { tag, format, from, to, output } => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            if let Some(format) = format {
+                let keystore = load_wallet(&wallet_path)?;
+                let mut keys = vec![keystore.public_key()?];
+                for account in keystore.accounts() {
+                    keys.extend(account.receive_keys.iter().cloned());
+                }
+                let from_ms = from.as_deref().map(|d| history::parse_date(d, false)).transpose()?;
+                let to_ms = to.as_deref().map(|d| history::parse_date(d, true)).transpose()?;
+                let (from_ms, to_ms) = (from_ms.unwrap_or(0), to_ms.unwrap_or(u64::MAX));
+                let mut rows = history::collect(&client, &keys, from_ms, to_ms)?;
+                if let Some(tag) = &tag {
+                    rows.retain(|row| row.tags.contains(tag));
+                }
+                history::write(&rows, format, output.as_deref())?;
+                if let Some(path) = &output {
+                    eprintln!("📄 {} transactions written to {}", rows.len(), path.display());
+                }
+                return Ok(());
+            }
+            let entries: Vec<HistoryEntry> = match &tag {
+                Some(tag) => client.get_query("/transactions", &[("tag", tag)])?,
+                None => client.get("/transactions")?,
+            };
+
             println!();
-            println!("  🌿 Rhiza Network Information");
+            println!("  🌿 Transaction History");
             println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("  Protocol:     Rhiza v0.1.0");
-            println!("  Consensus:    Proof of Relay (PoR)");
-            println!("  Structure:    Directed Acyclic Graph (DAG)");
-            println!("  Hash:         BLAKE3");
-            println!("  Signatures:   Ed25519");
-            println!("  Addresses:    Bech32m (rhz1...)");
-            println!("  Default Port: 7470");
+            if entries.is_empty() {
+                println!("  No transactions found.");
+            }
+            for entry in &entries {
+                let direction = if entry.is_incoming { "⬇️ " } else { "⬆️ " };
+                println!(
+                    "  {} {:<18} {:>16.8} RHZ  {}..",
+                    direction,
+                    entry.tx_type,
+                    entry.amount_rhz,
+                    &entry.id[..16]
+                );
+                if let Some(memo) = &entry.memo {
+                    println!("       📝 {}", memo);
+                }
+                if !entry.tags.is_empty() {
+                    println!("       🏷️  {}", entry.tags.join(", "));
+                }
+            }
+            println!();
+
+            Ok(())
+        }
+
+        Commands::Tags { action } => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            match action {
+                TagCommands::Set { id, tags } => {
+                    let response: TagResponse =
+                        client.put(&format!("/transactions/{}/tags", id), &TagRequest { tags })?;
+                    if response.tags.is_empty() {
+                        println!("🏷️  Cleared tags on {}", response.id);
+                    } else {
+                        println!("🏷️  {} → {}", response.id, response.tags.join(", "));
+                    }
+                }
+                TagCommands::Report => {
+                    let report: Vec<TagTotals> = client.get("/tags/report")?;
+                    let rhz = |units: u64| units as f64 / rhiza_core::UNITS_PER_RHZ as f64;
+
+                    println!();
+                    println!("  🏷️  Tag Report");
+                    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    if report.is_empty() {
+                        println!("  No tagged transactions yet.");
+                    }
+                    for totals in &report {
+                        println!(
+                            "  {:<20} {:>4} tx   in {:>14.8}   out {:>14.8}   net {:>+15.8} RHZ",
+                            totals.tag,
+                            totals.count,
+                            rhz(totals.incoming),
+                            rhz(totals.outgoing),
+                            totals.net as f64 / rhiza_core::UNITS_PER_RHZ as f64
+                        );
+                    }
+                    println!();
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::Send { recipient, amount, memo, fee, relay_tip, valid_after, batch } => {
+            let fee = fee.as_deref().map(parse_rhz).transpose()?.unwrap_or(0);
+            let relay_tip = relay_tip.as_deref().map(parse_rhz).transpose()?.unwrap_or(0);
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            if let Some(file) = batch {
+                let contacts = load_contacts(&contacts_path)?;
+                let outputs = batch::read(&file)?
+                    .into_iter()
+                    .map(|(recipient, amount)| {
+                        let recipient = resolve_recipient(&client, &contacts, &recipient)
+                            .with_context(|| format!("Could not pay {}", recipient))?;
+                        Ok(BatchOutput { recipient, amount })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let count = outputs.len();
+
+                let keystore = load_wallet(&wallet_path)?;
+                let parents = node_parents(&client)?;
+                let info: NodeInfo = client.get("/info")?;
+                let sender = keystore.public_key()?;
+                let nonce = info.dag_size;
+                let mut data = TransactionData::batch(sender, outputs, memo, relay_tip, parents, nonce);
+                data.network_id = info.network_id;
+                data.fee = fee;
+                confirm::confirm(&data, cli.yes)?;
+
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                let total = data.amount;
+                let tx = Transaction::new(data, &keypair);
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  💸 Sent {} RHZ to {} recipients in one transaction", format_rhz(total), count);
+                if fee > 0 {
+                    println!("  🔥 Fee: {} RHZ (burned)", format_rhz(fee));
+                }
+                if relay_tip > 0 {
+                    println!("  🎁 Relay tip: {} RHZ", format_rhz(relay_tip));
+                }
+                println!("  🆔 Transaction: {}", response.id);
+                match response.status.as_str() {
+                    "final" => println!("  ✅ Final"),
+                    status => println!("  ⏳ {} (not final yet)", status),
+                }
+                println!();
+                return Ok(());
+            }
+            let recipient = recipient.context("Give a recipient to pay")?;
+            let (recipient, amount, memo) = payment_request(recipient, amount, memo)?;
+            let valid_after = valid_after.as_deref().map(parse_valid_after).transpose()?.unwrap_or(0);
+            let recipient_key = resolve_recipient(&client, &load_contacts(&contacts_path)?, &recipient)?;
+
+            let keystore = load_wallet(&wallet_path)?;
+            let parents = node_parents(&client)?;
+            // Same nonce scheme as the node's own transactions, signed for
+            // the node's network
+            let info: NodeInfo = client.get("/info")?;
+
+            let mut data = TransactionData::transfer(
+                keystore.public_key()?,
+                recipient_key,
+                amount,
+                memo,
+                relay_tip,
+                parents,
+                info.dag_size,
+            );
+            data.network_id = info.network_id;
+            data.fee = fee;
+            data.valid_after = valid_after;
+            confirm::confirm(&data, cli.yes)?;
+
+            let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+            let tx = Transaction::new(data, &keypair);
+            let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+            println!();
+            println!("  💸 Sent {} RHZ to {}", format_rhz(amount), recipient);
+            if fee > 0 {
+                println!("  🔥 Fee: {} RHZ (burned)", format_rhz(fee));
+            }
+            if relay_tip > 0 {
+                println!("  🎁 Relay tip: {} RHZ", format_rhz(relay_tip));
+            }
+            if valid_after > 0 {
+                println!("  {}", FinalityStatus::Locked { until: valid_after });
+            }
+            println!("  🆔 Transaction: {}", response.id);
+            match response.status.as_str() {
+                "final" => println!("  ✅ Final"),
+                status => println!("  ⏳ {} (not final yet)", status),
+            }
+            println!();
+
+            Ok(())
+        }
+
+        Commands::Tx { action } => match action {
+            TxCommands::Status { id } => {
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let status: TransactionStatus = client.get(&format!("/tx/{}", parse_hash(&id)?))?;
+                let data = &status.transaction.data;
+
+                println!();
+                println!("  🧾 Transaction {}", status.transaction.id);
+                println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Type:      {:?}", data.tx_type);
+                println!("  From:      {}", Address::from_public_key(&data.sender));
+                if data.outputs.is_empty() {
+                    println!("  To:        {}", Address::from_public_key(&data.recipient));
+                }
+                for output in &data.outputs {
+                    let address = Address::from_public_key(&output.recipient);
+                    println!("  To:        {} ({} RHZ)", address, format_rhz(output.amount));
+                }
+                println!("  Amount:    {} RHZ", format_rhz(data.amount));
+                if data.fee > 0 {
+                    println!("  Fee:       {} RHZ (burned)", format_rhz(data.fee));
+                }
+                if let Some(memo) = &data.memo {
+                    println!("  Memo:      {}", memo);
+                }
+                if data.relay_tip > 0 {
+                    let collector = match &status.tip_collected_by {
+                        Some(relayer) => format!("collected by {}", relayer),
+                        None => "not collected yet".to_string(),
+                    };
+                    println!("  Relay tip: {} RHZ ({})", format_rhz(data.relay_tip), collector);
+                }
+                println!("  Depth:     {}", status.depth);
+                let finality = match status.status.as_str() {
+                    "final" => FinalityStatus::Final,
+                    "locked" => FinalityStatus::Locked { until: data.valid_after },
+                    "confirming" => FinalityStatus::Confirming {
+                        approvers: status.approvers,
+                        needed: status.needed,
+                    },
+                    _ => FinalityStatus::Pending,
+                };
+                println!("  Status:    {}", finality);
+                if let Some(replacement) = &status.replaced_by {
+                    println!("  Replaced:  by {} (no longer counts)", replacement);
+                }
+                println!();
+
+                Ok(())
+            }
+            TxCommands::Trace { id } => {
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let trace: TransactionTrace = client.get(&format!("/tx/{}/trace", parse_hash(&id)?))?;
+
+                println!();
+                println!("  🔎 Transaction {}", trace.id);
+                println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Status:    {} ({} of {} approvers)", trace.status, trace.approvers, trace.needed);
+                match trace.timeline.first() {
+                    None => println!("  The node kept no trace of it (seen before its last restart, or long ago)"),
+                    Some(first) => {
+                        for step in &trace.timeline {
+                            let offset = step.at_ms.saturating_sub(first.at_ms) as f64 / 1000.0;
+                            println!("  {:>+9.3}s  {}", offset, step.describe());
+                        }
+                    }
+                }
+                println!();
+
+                Ok(())
+            }
+            TxCommands::Replace { id, relay_tip, memo } => {
+                let relay_tip = parse_rhz(&relay_tip)?;
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let status: TransactionStatus = client.get(&format!("/tx/{}", parse_hash(&id)?))?;
+                if status.status == "final" {
+                    anyhow::bail!("Transaction {} is already final", status.transaction.id);
+                }
+                if let Some(replacement) = &status.replaced_by {
+                    anyhow::bail!("Transaction {} was already replaced by {}", status.transaction.id, replacement);
+                }
+
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                let original = &status.transaction;
+                if original.data.sender != keypair.public_key {
+                    anyhow::bail!("Transaction {} wasn't sent from this wallet", original.id);
+                }
+                let memo = match memo {
+                    Some(memo) => Some(memo).filter(|m| !m.trim().is_empty()),
+                    None => original.data.memo.clone(),
+                };
+
+                let tx = Transaction::replacement(original, &keypair, memo, relay_tip, node_parents(&client)?);
+                confirm::confirm(&tx.data, cli.yes)?;
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  🔁 Replaced {}", original.id);
+                println!("  🎁 Relay tip: {} RHZ", format_rhz(relay_tip));
+                println!("  🆔 Transaction: {}", response.id);
+                println!();
+
+                Ok(())
+            }
+            TxCommands::Proof { id, attesters, min_witnesses, out } => {
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let response: FinalityProofResponse = client.get(&format!("/tx/{}/proof", parse_hash(&id)?))?;
+                let attesters = if attesters.is_empty() { vec![response.attester.clone()] } else { attesters };
+                let policy = ProofPolicy {
+                    trusted_attesters: attesters.iter().map(|a| parse_public_key(a)).collect::<Result<_>>()?,
+                    min_witnesses,
+                    ..Default::default()
+                };
+
+                let bytes = hex::decode(&response.proof)?;
+                let report = FinalityProof::from_bytes(&bytes)
+                    .and_then(|proof| proof.verify(&policy))
+                    .map_err(|e| anyhow::anyhow!("Finality proof rejected: {}", e))?;
+                println!("✅ Transaction {} is final", report.transaction_id);
+                println!("   Weight {}, {} witnesses, attested by:", report.weight, report.witnesses);
+                for attester in &report.attesters {
+                    println!("   {}", attester);
+                }
+
+                match out {
+                    Some(path) => {
+                        std::fs::write(&path, &response.proof)?;
+                        println!("   Proof written to {}", path.display());
+                    }
+                    None => println!("\n{}", response.proof),
+                }
+                Ok(())
+            }
+            TxCommands::Create {
+                recipient,
+                amount,
+                from,
+                memo,
+                fee,
+                relay_tip,
+                valid_after,
+                parents,
+                network,
+                out,
+            } => {
+                let (recipient, amount, memo) = payment_request(recipient, amount, memo)?;
+                let fee = fee.as_deref().map(parse_rhz).transpose()?.unwrap_or(0);
+                let relay_tip = relay_tip.as_deref().map(parse_rhz).transpose()?.unwrap_or(0);
+                let valid_after = valid_after.as_deref().map(parse_valid_after).transpose()?.unwrap_or(0);
+                // Keys, parents and network given in full leave nothing to ask
+                // the node, so the payment can be built offline too
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let contacts = load_contacts(&contacts_path)?;
+                let recipient_key = resolve_recipient(&client, &contacts, &recipient)?;
+                let sender = match &from {
+                    Some(from) => resolve_recipient(&client, &contacts, from)?,
+                    None => load_wallet(&wallet_path)
+                        .context("Name the paying key with --from")?
+                        .public_key()?,
+                };
+                let parents = match parents.is_empty() {
+                    true => node_parents(&client)?.to_vec(),
+                    false => parents.iter().map(|p| parse_hash(p)).collect::<Result<_>>()?,
+                };
+                let network_id = match network {
+                    Some(network) => network,
+                    None => client.get::<NodeInfo>("/info")?.network_id,
+                };
+                let counts = rhiza_core::parent_counts(network_id);
+                if !counts.contains(&parents.len()) {
+                    let allowed = match counts.start() == counts.end() {
+                        true => counts.start().to_string(),
+                        false => format!("{} to {}", counts.start(), counts.end()),
+                    };
+                    anyhow::bail!("Network {} takes {} parents, not {}", network_id, allowed, parents.len());
+                }
+
+                // The node may not be asked, so the nonce is the time rather
+                // than its DAG size
+                let mut data = TransactionData::transfer(
+                    sender,
+                    recipient_key,
+                    amount,
+                    memo,
+                    relay_tip,
+                    parents,
+                    now_ms(),
+                );
+                data.network_id = network_id;
+                data.fee = fee;
+                data.valid_after = valid_after;
+                offline::write_unsigned(&out, &data)?;
+
+                println!();
+                println!("  📝 Unsigned payment");
+                println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                confirm::show(&data)?;
+                println!();
+                println!("  Written to {}; on the machine with the key, run:", out.display());
+                println!("    rhiza tx sign {}", out.display());
+                println!();
+
+                Ok(())
+            }
+            TxCommands::Sign { file, out } => {
+                let data = offline::read_unsigned(&file)?;
+                let keystore = load_wallet(&wallet_path)?;
+                if keystore.public_key()? != data.sender {
+                    anyhow::bail!(
+                        "{} pays from {}, not this wallet",
+                        file.display(),
+                        Address::from_public_key(&data.sender)
+                    );
+                }
+                confirm::confirm(&data, cli.yes)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                let tx = Transaction::new(data, &keypair);
+                offline::write_signed(&out, &tx)?;
+
+                println!("  ✍️  Signed payment {}", tx.id);
+                println!("  Written to {}; on a machine with a node, run:", out.display());
+                println!("    rhiza tx broadcast {}", out.display());
+                println!();
+
+                Ok(())
+            }
+            TxCommands::Broadcast { file } => {
+                let tx = offline::read_signed(&file)?;
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let info: NodeInfo = client.get("/info")?;
+                if info.network_id != tx.data.network_id {
+                    anyhow::bail!(
+                        "{} is signed for network {}, but the node is on network {}",
+                        file.display(),
+                        tx.data.network_id,
+                        info.network_id
+                    );
+                }
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  💸 Sent {} RHZ", format_rhz(tx.data.amount));
+                confirm::show(&tx.data)?;
+                println!("  🆔 Transaction: {}", response.id);
+                match response.status.as_str() {
+                    "final" => println!("  ✅ Final"),
+                    status => println!("  ⏳ {} (not final yet)", status),
+                }
+                println!();
+
+                Ok(())
+            }
+        },
+
+        Commands::Announce => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            let response: TransactionResponse = client.post("/announce", &())?;
+            println!("📣 Public key announced in {}", response.id);
+            println!("   Payments to the node wallet's rhz1 address now resolve.");
+
+            Ok(())
+        }
+
+        Commands::Alias { action } => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            match action {
+                AliasCommands::Resolve { alias } => {
+                    let entry: AliasEntry = client.get(&format!("/alias/{}", alias))?;
+
+                    println!();
+                    println!("  🌿 {}", entry.alias);
+                    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("  👤 Owner:      {}", entry.owner_address);
+                    println!("  📍 Pay to:     {}", entry.receive_address);
+                    println!("  🔑 Public Key: {}", entry.receive_public_key);
+                    println!();
+                }
+                AliasCommands::Announce { alias, receive_pubkey } => {
+                    let request = AliasRequest {
+                        alias: alias.clone(),
+                        receive_pubkey_hex: receive_pubkey,
+                    };
+                    let response: TransactionResponse = client.post("/alias", &request)?;
+                    println!("📣 Announced {} in {}", alias, response.id);
+                    println!("   The alias is yours once the announcement is final.");
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::Contacts { action } => {
+            let mut contacts = load_contacts(&contacts_path)?;
+            match action {
+                ContactCommands::Add { name, target } => {
+                    let contact = contacts.add(&name, &target)?.clone();
+                    contacts.save(&contacts_path)?;
+                    println!("📇 Saved {} → {}", contact.name, contact.address);
+                    if contact.public_key.is_none() {
+                        println!("   Paying it needs the key behind the address on the DAG; a hex key always works.");
+                    }
+                }
+                ContactCommands::List => {
+                    if contacts.is_empty() {
+                        println!("No contacts yet. Add one with: rhiza contacts add <name> <address>");
+                        return Ok(());
+                    }
+                    println!();
+                    println!("  📇 Contacts ({})", contacts.len());
+                    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    for contact in contacts.iter() {
+                        println!("  {:<16} {}", contact.name, contact.address);
+                    }
+                    println!();
+                }
+                ContactCommands::Remove { name } => {
+                    let Some(contact) = contacts.remove(&name) else {
+                        anyhow::bail!("There is no contact named {}", name);
+                    };
+                    contacts.save(&contacts_path)?;
+                    println!("🗑️  Removed {} ({})", contact.name, contact.address);
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::Multisig { action } => match action {
+            MultisigCommands::Address { threshold, members } => {
+                let policy = multisig_policy(threshold, &members)?;
+                let account = policy.account();
+                println!();
+                println!("  🔐 {}-of-{} multisig account", policy.threshold, policy.members.len());
+                println!("  Address:    {}", Address::from_public_key(&account));
+                println!("  Public key: {}", account);
+                println!();
+                println!("  Pay the public key to fund it; the address resolves once it has been paid.");
+                println!();
+
+                Ok(())
+            }
+            MultisigCommands::Create { threshold, members, recipient, amount, memo, fee, out } => {
+                let policy = multisig_policy(threshold, &members)?;
+                let amount = parse_rhz(&amount)?;
+                let fee = fee.as_deref().map(parse_rhz).transpose()?.unwrap_or(0);
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let recipient_key = resolve_recipient(&client, &load_contacts(&contacts_path)?, &recipient)?;
+
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+
+                let parents = node_parents(&client)?;
+                let info: NodeInfo = client.get("/info")?;
+                let nonce = info.dag_size;
+                let member = policy.members.contains(&keypair.public_key);
+                let mut tx = Transaction::multisig_spend(policy, recipient_key, amount, memo, parents, nonce)
+                    .for_network(info.network_id, &keypair)
+                    .with_fee(fee, &keypair);
+                if member {
+                    confirm::confirm(&tx.data, cli.yes)?;
+                    tx.sign_as_multisig_member(&keypair)
+                        .map_err(|e| anyhow::anyhow!("Could not sign: {}", e))?;
+                }
+                write_multisig_draft(&out, &tx)?;
+
+                println!();
+                describe_multisig_draft(&tx, &out);
+                if !member {
+                    println!("  ⚠️  This wallet isn't a member, so the draft is unsigned");
+                }
+                println!("  📝 Draft written to {}", out.display());
+                println!("     Members sign with: rhiza multisig sign {}", out.display());
+                println!();
+
+                Ok(())
+            }
+            MultisigCommands::Sign { file } => {
+                let mut tx = read_multisig_draft(&file)?;
+                confirm::confirm(&tx.data, cli.yes)?;
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                tx.sign_as_multisig_member(&keypair)
+                    .map_err(|e| anyhow::anyhow!("Could not sign: {}", e))?;
+                write_multisig_draft(&file, &tx)?;
+
+                println!();
+                describe_multisig_draft(&tx, &file);
+                println!("  ✍️  Signed as {}", keypair.public_key);
+                println!();
+
+                Ok(())
+            }
+            MultisigCommands::Finalize { file } => {
+                let tx = read_multisig_draft(&file)?;
+                tx.verify_multisig()
+                    .map_err(|e| anyhow::anyhow!("Not ready to submit: {}", e))?;
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  💸 Sent {} RHZ from the multisig account", format_rhz(tx.data.amount));
+                println!("  🆔 Transaction: {}", response.id);
+                match response.status.as_str() {
+                    "final" => println!("  ✅ Final"),
+                    status => println!("  ⏳ {} (not final yet)", status),
+                }
+                println!();
+
+                Ok(())
+            }
+        },
+
+        Commands::Htlc { action } => match action {
+            HtlcCommands::Secret => {
+                let secret = Preimage::generate();
+                println!();
+                println!("  🔑 Secret:    {}", secret);
+                println!("  🔒 Hash lock: {}", secret.hash_lock());
+                println!();
+                println!("  Share only the hash lock: the secret claims every lock made with it.");
+                println!();
+
+                Ok(())
+            }
+            HtlcCommands::Lock { recipient, amount, hash_lock, timeout } => {
+                let amount = parse_rhz(&amount)?;
+                let hash_lock = parse_hash(&hash_lock)?;
+                let timeout = parse_valid_after(&timeout)?;
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let recipient_key = resolve_recipient(&client, &load_contacts(&contacts_path)?, &recipient)?;
+
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+
+                let parents = node_parents(&client)?;
+                let info: NodeInfo = client.get("/info")?;
+                let tx = Transaction::htlc_lock(
+                    &keypair,
+                    recipient_key,
+                    amount,
+                    hash_lock,
+                    timeout,
+                    parents,
+                    info.dag_size,
+                )
+                .for_network(info.network_id, &keypair);
+                confirm::confirm(&tx.data, cli.yes)?;
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  🔒 Locked {} RHZ for {}", format_rhz(amount), recipient);
+                println!("  Refundable from {}", describe_lock(timeout));
+                println!("  🆔 Lock: {}", response.id);
+                println!("  Once it's final, the recipient claims it with:");
+                println!("    rhiza htlc claim {} --secret <secret>", response.id);
+                println!();
+
+                Ok(())
+            }
+            HtlcCommands::Claim { id, secret } => {
+                let secret = Preimage::from_hex(secret.trim())
+                    .map_err(|e| anyhow::anyhow!("Invalid secret: {}", e))?;
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let htlc: HtlcInfo = client.get(&format!("/htlc/{}", parse_hash(&id)?))?;
+                if secret.hash_lock() != parse_hash(&htlc.hash_lock)? {
+                    anyhow::bail!("That secret doesn't open lock {}", htlc.id);
+                }
+
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                if keypair.public_key.to_string() != htlc.claimer {
+                    anyhow::bail!("Lock {} is made out to {}, not this wallet", htlc.id, htlc.claimer);
+                }
+
+                let parents = node_parents(&client)?;
+                let info: NodeInfo = client.get("/info")?;
+                let locker = parse_public_key(&htlc.locker)?;
+                let lock = parse_hash(&htlc.id)?;
+                let tx = Transaction::htlc_claim(&keypair, locker, lock, secret, parents, info.dag_size)
+                    .for_network(info.network_id, &keypair);
+                confirm::confirm(&tx.data, cli.yes)?;
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  🔓 Claimed {} RHZ from lock {}", format_rhz(htlc.amount), htlc.id);
+                println!("  🆔 Transaction: {}", response.id);
+                println!("  The funds are yours once the claim is final.");
+                println!();
+
+                Ok(())
+            }
+            HtlcCommands::Refund { id } => {
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let htlc: HtlcInfo = client.get(&format!("/htlc/{}", parse_hash(&id)?))?;
+                if !htlc.expired {
+                    let timeout = describe_lock(htlc.timeout);
+                    anyhow::bail!("Lock {} can't be refunded before {}", htlc.id, timeout);
+                }
+
+                let keystore = load_wallet(&wallet_path)?;
+                let keypair = keyfile::unlock(&wallet_path, keystore, password_file)?;
+                if keypair.public_key.to_string() != htlc.locker {
+                    anyhow::bail!("Lock {} was made by {}, not this wallet", htlc.id, htlc.locker);
+                }
+
+                let parents = node_parents(&client)?;
+                let info: NodeInfo = client.get("/info")?;
+                let claimer = parse_public_key(&htlc.claimer)?;
+                let lock = parse_hash(&htlc.id)?;
+                let tx = Transaction::htlc_refund(&keypair, claimer, lock, parents, info.dag_size)
+                    .for_network(info.network_id, &keypair);
+                confirm::confirm(&tx.data, cli.yes)?;
+                let response: TransactionResponse = client.post("/tx/submit", &tx)?;
+
+                println!();
+                println!("  ↩️  Refunding {} RHZ from lock {}", format_rhz(htlc.amount), htlc.id);
+                println!("  🆔 Transaction: {}", response.id);
+                println!("  The funds are back once the refund is final, unless a claim gets there first.");
+                println!();
+
+                Ok(())
+            }
+            HtlcCommands::Status { id } => {
+                let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+                let htlc: HtlcInfo = client.get(&format!("/htlc/{}", parse_hash(&id)?))?;
+
+                println!();
+                println!("  🔒 HTLC {}", htlc.id);
+                println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  From:      {}", htlc.locker);
+                println!("  To:        {}", htlc.claimer);
+                println!("  Amount:    {} RHZ", format_rhz(htlc.amount));
+                println!("  Hash lock: {}", htlc.hash_lock);
+                let refundable = if htlc.expired { " (passed)" } else { "" };
+                println!("  Timeout:   {}{}", describe_lock(htlc.timeout), refundable);
+                match &htlc.settled_by {
+                    Some(by) => println!("  Status:    {} by {}", htlc.status, by),
+                    None => println!("  Status:    {}", htlc.status),
+                }
+                if let Some(secret) = &htlc.preimage {
+                    println!("  Secret:    {}", secret);
+                }
+                println!();
+
+                Ok(())
+            }
+        },
+
+        Commands::Info => {
+            println!();
+            println!("  🌿 Rhiza Network Information");
+            println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Protocol:     Rhiza v0.1.0");
+            println!("  Consensus:    Proof of Relay (PoR)");
+            println!("  Structure:    Directed Acyclic Graph (DAG)");
+            println!("  Hash:         BLAKE3");
+            println!("  Signatures:   Ed25519");
+            println!("  Addresses:    Bech32m (rhz1...)");
+            println!("  Default Port: 7470");
+            println!();
+
+            Ok(())
+        }
+
+        Commands::Founder => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            let report: FounderReport = client.get("/founder")?;
+
+            println!();
+            println!("  🌱 Founder Grant");
+            println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Address:     {}", report.address);
+            match &report.allocation_id {
+                Some(id) => println!("  Allocation:  {} RHZ in {}", format_rhz(report.allocation), id),
+                None => println!("  Allocation:  {} RHZ (not in this node's DAG)", format_rhz(report.allocation)),
+            }
+            println!(
+                "  Vesting:     {} ({} RHZ vested, {} RHZ locked)",
+                report.vesting.schedule,
+                format_rhz(report.vesting.vested),
+                format_rhz(report.vesting.locked)
+            );
+            println!("  Spent:       {} RHZ", format_rhz(report.total_spent));
+            println!("  Balance:     {} RHZ", format_rhz(report.balance));
+            println!();
+            if report.spends.is_empty() {
+                println!("  No spends from the founder key.");
+            }
+            for spend in &report.spends {
+                let status = if spend.rejected {
+                    "rejected"
+                } else if spend.is_final {
+                    "final"
+                } else {
+                    "pending"
+                };
+                println!(
+                    "  {}..  {:>16} RHZ → {}  ({})",
+                    &spend.id[..16],
+                    format_rhz(spend.debited),
+                    Address::from_public_key(&spend.recipient),
+                    status
+                );
+            }
+            println!();
+
+            Ok(())
+        }
+
+        Commands::Relayers { period, limit } => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            let path = format!("/relayers/top?period={}&limit={}", period, limit);
+            let board: RelayLeaderboard = client.get(&path)?;
+            let local_rank = board.local.as_ref().map(|s| s.rank);
+
+            println!();
+            println!("  📡 Relay Leaderboard ({})", period);
+            println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!(
+                "  {} relayers, {} relays, {} RHZ in rewards",
+                board.relayers,
+                board.total_relays,
+                format_rhz(board.total_rewards)
+            );
+            println!();
+            if board.top.is_empty() {
+                println!("  No final relay reward claims in this period.");
+            }
+            for standing in &board.top {
+                let marker = if Some(standing.rank) == local_rank { "  ← this node" } else { "" };
+                println!(
+                    "  #{:<4} {}  {:>8} relays  {:>16} RHZ  ({} claims){}",
+                    standing.rank,
+                    standing.address,
+                    standing.relays,
+                    format_rhz(standing.rewards),
+                    standing.claims,
+                    marker
+                );
+            }
+            println!();
+            match &board.local {
+                Some(me) => println!(
+                    "  This node ranks #{} of {} with {} relays.",
+                    me.rank, board.relayers, me.relays
+                ),
+                None => println!("  This node has no final relay reward claims in this period."),
+            }
+            println!();
+
+            Ok(())
+        }
+
+        Commands::Node { action } => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            match action {
+                NodeCommands::Status => {
+                    let status: AdminStatus = client.get("/admin/status")?;
+                    println!();
+                    println!("  🌿 Rhiza Node v{}", status.version);
+                    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("  Address:      {}", status.address);
+                    println!("  Network:      {} ({} backend)", status.network_id, status.backend);
+                    println!("  Uptime:       {}", format_duration(status.uptime_secs));
+                    println!("  Health:       {}", status.health);
+                    println!(
+                        "  DAG:          {} transactions, depth {}, {} tips",
+                        status.dag_size, status.dag_depth, status.tips_count
+                    );
+                    println!("  Pending:      {}", status.pending);
+                    println!("  Work:         {} bits per transaction", status.work_bits);
+                    println!("  Peers:        {}", status.peers);
+                    if status.held_messages > 0 {
+                        println!("  Held:         {} messages for absent peers", status.held_messages);
+                    }
+                    if let Some(version) = status.update_available {
+                        println!("  ⬆️  Update available: v{}", version);
+                    }
+                    println!();
+                }
+                NodeCommands::Peers => {
+                    let peers: Vec<PeerEntry> = client.get("/peers")?;
+                    let now = now_ms();
+                    println!();
+                    if peers.is_empty() {
+                        println!("  No peers connected.");
+                    }
+                    for peer in &peers {
+                        println!(
+                            "  {}..  {:<22} {:<8} {:<14} score {:>4}  up {}{}",
+                            &peer.peer_id[..16],
+                            peer.address,
+                            peer.direction,
+                            peer.bucket,
+                            peer.reputation.score,
+                            format_duration(now.saturating_sub(peer.connected_since) / 1_000),
+                            if peer.anchor { "  ⚓" } else { "" }
+                        );
+                    }
+                    println!();
+                }
+                NodeCommands::Ban { peer_id, hours, reason } => {
+                    let request = BanRequest {
+                        peer_id,
+                        duration_secs: hours.map(|h| h.saturating_mul(3_600)),
+                        reason,
+                    };
+                    let ban: BanEntry = client.post("/admin/peers/ban", &request)?;
+                    let length = format_duration(ban.until_ms.saturating_sub(now_ms()) / 1_000);
+                    println!("⛔ Banned {} for {} (ban #{}): {}", ban.peer_id, length, ban.count, ban.reason);
+                    if let Some(address) = ban.address {
+                        println!("   Address {} is banned with it", address);
+                    }
+                }
+                NodeCommands::SyncStatus => {
+                    let status: SyncStatus = client.get("/admin/sync")?;
+                    println!();
+                    let state = if status.synced { "in sync" } else { "syncing" };
+                    println!("  🔄 DAG depth {}: {}", status.dag_depth, state);
+                    if status.parked > 0 {
+                        println!("  {} transactions waiting for missing parents", status.parked);
+                    }
+                    for sync in &status.syncs {
+                        println!(
+                            "  {}..  depth {} of {}  ({})",
+                            &sync.peer_id[..16],
+                            sync.next_depth.min(sync.target_depth),
+                            sync.target_depth,
+                            sync.phase
+                        );
+                    }
+                    println!();
+                }
+                NodeCommands::Unlock { minutes } => {
+                    let password = keyfile::read_password(password_file, "  Node wallet password: ")?;
+                    let request = UnlockRequest {
+                        password: &password,
+                        timeout_secs: minutes.map(|m| m.saturating_mul(60)),
+                    };
+                    let session: WalletSession = client.post("/wallet/unlock", &request)?;
+                    let left = session.unlocked_until.unwrap_or_default().saturating_sub(now_ms()) / 1_000;
+                    println!("🔓 Node wallet unlocked for {}", format_duration(left));
+                }
+                NodeCommands::Lock => {
+                    client.post_empty("/wallet/lock")?;
+                    println!("🔒 Node wallet locked");
+                }
+                NodeCommands::Shutdown => {
+                    client.post_empty("/admin/shutdown")?;
+                    println!("🛑 Node at {} is shutting down", cli.node_url);
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::Fees => {
+            let client = NodeClient::new(&cli.node_url, cli.api_key.as_deref());
+            let estimate: FeeEstimate = client.get("/fees/estimate")?;
+            let rhz = |offer: u128| format_rhz(u64::try_from(offer).unwrap_or(u64::MAX));
+
+            println!();
+            println!("  🔥 Fee estimate ({} of {} pending)", estimate.pending, estimate.capacity);
+            println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Minimum:   {} RHZ", rhz(estimate.minimum));
+            println!("  Median:    {} RHZ", rhz(estimate.median));
+            println!("  Fast:      {} RHZ", rhz(estimate.fast));
+            println!();
+            println!("  Offers are fee plus relay tip. Fees are burned; tips go to the relayer.");
             println!();
 
             Ok(())
@@ -181,7 +2069,7 @@ fn main() -> Result<()> {
                 rhiza_core::RELAY_HALVING_INTERVAL
             );
             println!(
-                "  Finality Threshold:   {:>15} weight",
+                "  Finality Threshold:   {:>15} approvers",
                 rhiza_core::FINALITY_THRESHOLD
             );
             println!(
@@ -199,7 +2087,7 @@ fn main() -> Result<()> {
     }
 }
 
-fn load_wallet(path: &PathBuf) -> Result<KeyStore> {
+fn load_wallet(path: &Path) -> Result<KeyStore> {
     if !path.exists() {
         anyhow::bail!(
             "No wallet found. Create one with: rhiza wallet create"
@@ -207,3 +2095,234 @@ fn load_wallet(path: &PathBuf) -> Result<KeyStore> {
     }
     Ok(KeyStore::load(path)?)
 }
+
+/// Parse a time lock: a date, an RFC 3339 time, or a raw `valid_after`
+/// (a DAG depth below 500,000,000, Unix ms above)
+fn parse_valid_after(lock: &str) -> Result<u64> {
+    if let Ok(raw) = lock.parse::<u64>() {
+        return Ok(raw);
+    }
+    let time = match chrono::DateTime::parse_from_rfc3339(lock) {
+        Ok(time) => time.to_utc(),
+        Err(_) => chrono::NaiveDate::parse_from_str(lock, "%Y-%m-%d")
+            .with_context(|| format!("Invalid time lock {}; give a date, RFC 3339 time or depth", lock))?
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc(),
+    };
+    u64::try_from(time.timestamp_millis())
+        .ok()
+        .filter(|ms| *ms >= TIME_LOCK_THRESHOLD)
+        .ok_or_else(|| anyhow::anyhow!("{} is too early for a time lock", lock))
+}
+
+/// A time lock as a DAG depth or UTC time
+fn describe_lock(lock: u64) -> String {
+    if lock < TIME_LOCK_THRESHOLD {
+        return format!("depth {}", lock);
+    }
+    match chrono::DateTime::from_timestamp_millis(lock as i64) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => format!("{} ms", lock),
+    }
+}
+
+/// Parse an RHZ amount with up to 8 decimals into smallest units
+fn parse_rhz(amount: &str) -> Result<u64> {
+    let invalid = || anyhow::anyhow!("Invalid amount {:?}; use RHZ, e.g. 1.5", amount);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > 8 {
+        return Err(invalid());
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+    let fraction: u64 = format!("{:0<8}", fraction).parse().map_err(|_| invalid())?;
+    let units = whole
+        .checked_mul(rhiza_core::UNITS_PER_RHZ)
+        .and_then(|u| u.checked_add(fraction))
+        .ok_or_else(invalid)?;
+    if units == 0 {
+        anyhow::bail!("Amount must be more than zero");
+    }
+    Ok(units)
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Seconds as e.g. "3d 4h", "2h 5m" or "42s"
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Format smallest units as RHZ, without trailing zeros
+fn format_rhz(units: u64) -> String {
+    let whole = units / rhiza_core::UNITS_PER_RHZ;
+    let fraction = units % rhiza_core::UNITS_PER_RHZ;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    format!("{}.{}", whole, format!("{:08}", fraction).trim_end_matches('0'))
+}
+
+fn parse_public_key(hex_key: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid public key {}", hex_key))?;
+    Ok(PublicKey::from_bytes(bytes))
+}
+
+/// Two of the node's tips for a new transaction to approve
+fn node_parents(client: &NodeClient) -> Result<[Hash; 2]> {
+    let tips: Vec<String> = client.get("/dag/tips")?;
+    match tips.as_slice() {
+        [] => anyhow::bail!("The node has no tips to approve; is it synced?"),
+        [only] => Ok([parse_hash(only)?, parse_hash(only)?]),
+        [first, second, ..] => Ok([parse_hash(first)?, parse_hash(second)?]),
+    }
+}
+
+fn parse_network(network: &str) -> Result<u32, String> {
+    rhiza_core::parse_network_id(network).ok_or_else(|| format!("unknown network {:?}", network))
+}
+
+fn parse_hash(hex_hash: &str) -> Result<Hash> {
+    Hash::from_hex(hex_hash).map_err(|e| anyhow::anyhow!("Invalid hash {}: {}", hex_hash, e))
+}
+
+/// The recipient, amount and memo of a payment, read from a `rhiza:` URI
+/// when the recipient is one
+///
+/// An amount given alongside a URI that names one has to agree with it; a
+/// memo given replaces the URI's.
+fn payment_request(
+    recipient: String,
+    amount: Option<String>,
+    memo: Option<String>,
+) -> Result<(String, u64, Option<String>)> {
+    let amount = amount.as_deref().map(parse_rhz).transpose()?;
+    if !PaymentUri::is_uri(&recipient) {
+        let amount = amount.ok_or_else(|| anyhow::anyhow!("Give an amount to send"))?;
+        return Ok((recipient, amount, memo));
+    }
+    let uri: PaymentUri = recipient.parse().with_context(|| format!("Invalid payment URI {}", recipient))?;
+    let amount = match (amount, uri.amount) {
+        (Some(given), Some(asked)) if given != asked => {
+            anyhow::bail!("The payment URI asks for {} RHZ, not {}", format_rhz(asked), format_rhz(given))
+        }
+        (Some(amount), _) | (None, Some(amount)) => amount,
+        (None, None) => anyhow::bail!("The payment URI names no amount; give one to send"),
+    };
+    Ok((uri.address.to_string(), amount, memo.or(uri.memo)))
+}
+
+fn load_contacts(path: &Path) -> Result<Contacts> {
+    Contacts::load(path).with_context(|| format!("Could not read contacts from {}", path.display()))
+}
+
+/// Turn a contact name, hex key, `rhz1...` address or alias into the key
+/// to pay
+///
+/// Addresses and aliases are looked up on the node.
+fn resolve_recipient(client: &NodeClient, contacts: &Contacts, recipient: &str) -> Result<PublicKey> {
+    if let Some(contact) = contacts.get(recipient) {
+        if let Some(key) = &contact.public_key {
+            return Ok(key.clone());
+        }
+        // Names never read as addresses, so this can't loop
+        return resolve_recipient(client, contacts, &contact.address.to_string());
+    }
+    if recipient.len() == 64 && recipient.chars().all(|c| c.is_ascii_hexdigit()) {
+        return parse_public_key(recipient);
+    }
+    if recipient.to_ascii_lowercase().starts_with("rhz1") {
+        let balance: AddressBalance = client.get(&format!("/address/{}/balance", recipient))?;
+        let Some(key) = balance.public_key else {
+            anyhow::bail!(
+                "The key behind {} hasn't appeared on the DAG yet; ask the recipient to run: rhiza announce",
+                recipient
+            );
+        };
+        return parse_public_key(&key);
+    }
+    let entry: AliasEntry = client.get(&format!("/alias/{}", recipient))?;
+    parse_public_key(&entry.receive_public_key)
+}
+
+fn multisig_policy(threshold: u8, members: &[String]) -> Result<MultisigPolicy> {
+    let members = members.iter().map(|m| parse_public_key(m)).collect::<Result<_>>()?;
+    MultisigPolicy::new(threshold, members).map_err(|e| anyhow::anyhow!("Invalid multisig account: {}", e))
+}
+
+/// Read a drafted multisig spend, refusing one whose contents were altered
+/// since it was drafted
+fn read_multisig_draft(path: &Path) -> Result<Transaction> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let tx: Transaction =
+        serde_json::from_str(&json).with_context(|| format!("{} isn't a drafted spend", path.display()))?;
+    if tx.data.tx_type != TransactionType::Multisig || tx.multisig.is_none() {
+        anyhow::bail!("{} isn't a multisig spend", path.display());
+    }
+    if !tx.verify_id() {
+        anyhow::bail!("{} was altered after it was drafted", path.display());
+    }
+    Ok(tx)
+}
+
+fn write_multisig_draft(path: &Path, tx: &Transaction) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(tx)?)
+        .with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// What a drafted spend pays and who has signed it, so members see what
+/// they put their name to
+fn describe_multisig_draft(tx: &Transaction, path: &Path) {
+    let Some(witness) = &tx.multisig else {
+        return;
+    };
+    let data = &tx.data;
+    println!("  🔐 Multisig spend {}", tx.id);
+    println!("  ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("  From:      {}", Address::from_public_key(&data.sender));
+    println!("  To:        {}", Address::from_public_key(&data.recipient));
+    println!("  Amount:    {} RHZ", format_rhz(data.amount));
+    if data.fee > 0 {
+        println!("  Fee:       {} RHZ (burned)", format_rhz(data.fee));
+    }
+    if let Some(memo) = &data.memo {
+        println!("  Memo:      {}", memo);
+    }
+    println!("  Signed:    {} of {} needed", witness.signatures.len(), witness.policy.threshold);
+    for member in &witness.policy.members {
+        let signed = witness.signatures.iter().any(|s| s.signer == *member);
+        println!("    {} {}", if signed { "✅" } else { "⬜" }, member);
+    }
+    match witness.missing() {
+        0 => println!("  Ready:     rhiza multisig finalize {}", path.display()),
+        n => println!("  Waiting:   {} more signature{}", n, if n == 1 { "" } else { "s" }),
+    }
+}
+
+fn load_hd_wallet(path: &Path) -> Result<KeyStore> {
+    let keystore = load_wallet(path)?;
+    if !keystore.is_hd() {
+        anyhow::bail!(
+            "This wallet has no recovery phrase, so it can't derive receive keys. Create one with: rhiza wallet create --mnemonic"
+        );
+    }
+    Ok(keystore)
+}
@@ -0,0 +1,31 @@
+use anyhow::{Context, Result};
+use rhiza_core::dag::transaction::{Transaction, TransactionData};
+use std::path::Path;
+
+/// Read a payment written by `rhiza tx create`
+pub fn read_unsigned(path: &Path) -> Result<TransactionData> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("{} isn't an unsigned payment from rhiza tx create", path.display()))
+}
+
+pub fn write_unsigned(path: &Path, data: &TransactionData) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(data)? + "\n")
+        .with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// Read a payment signed by `rhiza tx sign`, refusing one altered since
+pub fn read_signed(path: &Path) -> Result<Transaction> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let tx: Transaction = serde_json::from_str(&json)
+        .with_context(|| format!("{} isn't a signed payment from rhiza tx sign", path.display()))?;
+    if !tx.verify_id() || !tx.verify_signature() {
+        anyhow::bail!("{} was altered after it was signed", path.display());
+    }
+    Ok(tx)
+}
+
+pub fn write_signed(path: &Path, tx: &Transaction) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(tx)? + "\n")
+        .with_context(|| format!("Could not write {}", path.display()))
+}
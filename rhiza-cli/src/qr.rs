@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{Color, QrCode};
+use std::path::Path;
+
+/// Pixels per QR module in PNG output
+const PNG_SCALE: usize = 8;
+
+/// Light modules around the code, as scanners expect
+const QUIET_ZONE: usize = 4;
+
+fn encode(data: &str) -> Result<QrCode> {
+    QrCode::new(data.as_bytes()).context("Could not encode QR code")
+}
+
+/// `data` as a QR code of half-height blocks, light on dark so it scans from
+/// a dark terminal
+pub fn terminal(data: &str) -> Result<String> {
+    Ok(encode(data)?
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+/// Write `data` as a QR code to a black and white PNG
+pub fn write_png(data: &str, path: &Path) -> Result<()> {
+    let code = encode(data)?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * PNG_SCALE;
+
+    // One bit per pixel, 1 for white, each row behind a "no filter" byte
+    let row_bytes = size.div_ceil(8);
+    let mut pixels = Vec::with_capacity(size * (row_bytes + 1));
+    for y in 0..size {
+        pixels.push(0);
+        let mut row = vec![0u8; row_bytes];
+        // Module coordinates; inside the quiet zone they wrap past `modules`
+        let my = (y / PNG_SCALE).wrapping_sub(QUIET_ZONE);
+        for x in 0..size {
+            let mx = (x / PNG_SCALE).wrapping_sub(QUIET_ZONE);
+            let dark = mx < modules && my < modules && colors[my * modules + mx] == Color::Dark;
+            if !dark {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        pixels.extend_from_slice(&row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(size as u32).to_be_bytes());
+    header.extend_from_slice(&(size as u32).to_be_bytes());
+    // Bit depth 1, greyscale, default compression, filtering and no interlace
+    header.extend_from_slice(&[1, 0, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+    write_chunk(&mut png, b"IEND", &[]);
+    std::fs::write(path, png).with_context(|| format!("Could not write {}", path.display()))
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// A zlib stream of uncompressed deflate blocks; a QR code is small enough
+/// that compressing it isn't worth a dependency
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
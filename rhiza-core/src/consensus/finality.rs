@@ -7,10 +7,7 @@ pub struct FinalityChecker;
 impl FinalityChecker {
     /// Check if a specific transaction has reached finality
     pub fn is_final(dag: &Dag, tx_id: &Hash) -> bool {
-        match dag.get(tx_id) {
-            Some(vertex) => vertex.cumulative_weight >= crate::FINALITY_THRESHOLD,
-            None => false,
-        }
+        dag.get(tx_id).is_some_and(|vertex| vertex.is_final)
     }
 
     /// Get finality status for a transaction
@@ -18,11 +15,16 @@ impl FinalityChecker {
         match dag.get(tx_id) {
             None => FinalityStatus::Unknown,
             Some(vertex) => {
-                if vertex.cumulative_weight >= crate::FINALITY_THRESHOLD {
+                let approvers = dag.approver_count(tx_id);
+                let (depth, newest) = dag.frontier();
+                let data = &vertex.transaction.data;
+                if vertex.is_final {
                     FinalityStatus::Final
-                } else if vertex.cumulative_weight > 1 {
+                } else if !data.unlocked_at(depth, newest) {
+                    FinalityStatus::Locked { until: data.valid_after }
+                } else if approvers > 0 {
                     FinalityStatus::Confirming {
-                        weight: vertex.cumulative_weight,
+                        approvers,
                         needed: crate::FINALITY_THRESHOLD,
                     }
                 } else {
@@ -46,21 +48,45 @@ impl FinalityChecker {
 pub enum FinalityStatus {
     /// Transaction not found in DAG
     Unknown,
-    /// Transaction is in DAG but has no confirmations yet
+    /// Transaction is in DAG but no other key has approved it yet
     Pending,
-    /// Transaction is being confirmed
-    Confirming { weight: u64, needed: u64 },
+    /// Time-locked payment whose lock the DAG hasn't reached; approvals
+    /// only count after it (see
+    /// [`valid_after`](crate::dag::transaction::TransactionData::valid_after))
+    Locked { until: u64 },
+    /// Transaction is being confirmed by distinct approvers
+    Confirming { approvers: u64, needed: u64 },
     /// Transaction has reached finality
     Final,
 }
 
+impl FinalityStatus {
+    /// Short machine-readable name, e.g. for APIs
+    pub fn name(&self) -> &'static str {
+        match self {
+            FinalityStatus::Unknown => "unknown",
+            FinalityStatus::Pending => "pending",
+            FinalityStatus::Locked { .. } => "locked",
+            FinalityStatus::Confirming { .. } => "confirming",
+            FinalityStatus::Final => "final",
+        }
+    }
+}
+
 impl std::fmt::Display for FinalityStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FinalityStatus::Unknown => write!(f, "❓ Unknown"),
             FinalityStatus::Pending => write!(f, "⏳ Pending"),
-            FinalityStatus::Confirming { weight, needed } => {
-                write!(f, "🔄 Confirming ({}/{})", weight, needed)
+            FinalityStatus::Locked { until } if *until < crate::dag::transaction::TIME_LOCK_THRESHOLD => {
+                write!(f, "🔒 Locked until depth {}", until)
+            }
+            FinalityStatus::Locked { until } => match chrono::DateTime::from_timestamp_millis(*until as i64) {
+                Some(time) => write!(f, "🔒 Locked until {}", time.format("%Y-%m-%d %H:%M:%S UTC")),
+                None => write!(f, "🔒 Locked until {} ms", until),
+            },
+            FinalityStatus::Confirming { approvers, needed } => {
+                write!(f, "🔄 Confirming ({}/{} approvers)", approvers, needed)
             }
             FinalityStatus::Final => write!(f, "✅ Final"),
         }
@@ -88,13 +114,36 @@ mod tests {
             FinalityStatus::Pending
         );
 
-        // Add transactions that reference genesis
+        // Its own sender piling weight on it doesn't confirm it
         let mut last_ids = [genesis_id, genesis_id];
-        for i in 1..=crate::FINALITY_THRESHOLD {
-            let tx = Transaction::relay_reward(&kp, 100, last_ids, i);
-            let tx_id = tx.id;
-            dag.insert(DagVertex::new(tx, i)).unwrap();
-            last_ids = [tx_id, tx_id];
+        let mut depth = 0;
+        for _ in 0..crate::FINALITY_THRESHOLD {
+            depth += 1;
+            let tx = Transaction::relay_reward(&kp, 100, last_ids, depth);
+            last_ids = [tx.id, tx.id];
+            dag.insert(DagVertex::new(tx, depth)).unwrap();
+        }
+        assert!(dag.get(&genesis_id).unwrap().cumulative_weight > crate::FINALITY_THRESHOLD);
+        assert_eq!(
+            FinalityChecker::finality_status(&dag, &genesis_id),
+            FinalityStatus::Pending
+        );
+
+        // Distinct approvers do, one key at a time however often each signs
+        let others: Vec<KeyPair> = (0..crate::FINALITY_THRESHOLD).map(|_| KeyPair::generate()).collect();
+        for (i, approver) in others.iter().enumerate() {
+            for _ in 0..2 {
+                depth += 1;
+                let tx = Transaction::relay_reward(approver, 100, last_ids, depth);
+                last_ids = [tx.id, tx.id];
+                dag.insert(DagVertex::new(tx, depth)).unwrap();
+            }
+            if (i as u64 + 1) < crate::FINALITY_THRESHOLD {
+                assert_eq!(
+                    FinalityChecker::finality_status(&dag, &genesis_id),
+                    FinalityStatus::Confirming { approvers: i as u64 + 1, needed: crate::FINALITY_THRESHOLD }
+                );
+            }
         }
 
         // Genesis should now be final
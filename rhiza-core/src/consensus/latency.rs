@@ -0,0 +1,170 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Upper bounds of the histogram buckets in milliseconds; slower samples
+/// land in a final open-ended bucket
+pub const LATENCY_BUCKETS_MS: [u64; 12] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000, 300_000, 600_000,
+];
+
+/// Most recent samples percentiles are computed over
+pub const LATENCY_WINDOW: usize = 1_000;
+
+/// Distribution of a latency, e.g. from first seeing a transaction to its
+/// finality
+///
+/// Bucket counts cover every sample since startup; percentiles only the
+/// last [`LATENCY_WINDOW`], so they follow the network as it speeds up or
+/// slows down.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    count: u64,
+    sum_ms: u128,
+    recent: VecDeque<u64>,
+}
+
+/// Samples in one histogram bucket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyBucket {
+    /// Upper bound in milliseconds, `None` for the open-ended last bucket
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+/// Percentiles over the recent samples
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    /// Samples the percentiles are taken over
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        LatencyHistogram {
+            counts: [0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0,
+            recent: VecDeque::with_capacity(LATENCY_WINDOW),
+        }
+    }
+
+    /// Record one sample
+    pub fn record(&mut self, latency_ms: u64) {
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += latency_ms as u128;
+
+        if self.recent.len() == LATENCY_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency_ms);
+    }
+
+    /// Samples recorded since startup
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of every sample since startup
+    pub fn mean_ms(&self) -> Option<u64> {
+        (self.count > 0).then(|| (self.sum_ms / self.count as u128) as u64)
+    }
+
+    /// Per-bucket counts since startup, fastest first
+    pub fn buckets(&self) -> Vec<LatencyBucket> {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| LatencyBucket {
+                le_ms: LATENCY_BUCKETS_MS.get(i).copied(),
+                count,
+            })
+            .collect()
+    }
+
+    /// p50/p95/p99 of the recent samples (nearest rank), if there are any
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = |q: f64| {
+            let index = (q * sorted.len() as f64).ceil() as usize;
+            sorted[index.clamp(1, sorted.len()) - 1]
+        };
+        Some(LatencyPercentiles {
+            samples: sorted.len(),
+            p50_ms: rank(0.50),
+            p95_ms: rank(0.95),
+            p99_ms: rank(0.99),
+        })
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_histogram() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.mean_ms(), None);
+        assert_eq!(histogram.percentiles(), None);
+        assert!(histogram.buckets().iter().all(|b| b.count == 0));
+    }
+
+    #[test]
+    fn test_percentiles_and_buckets() {
+        let mut histogram = LatencyHistogram::new();
+        for ms in 1..=100 {
+            histogram.record(ms * 10);
+        }
+
+        let p = histogram.percentiles().unwrap();
+        assert_eq!(p.samples, 100);
+        assert_eq!(p.p50_ms, 500);
+        assert_eq!(p.p95_ms, 950);
+        assert_eq!(p.p99_ms, 990);
+        assert_eq!(histogram.mean_ms(), Some(505));
+
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], LatencyBucket { le_ms: Some(100), count: 10 });
+        assert_eq!(buckets[1].count, 15);
+        assert_eq!(buckets[3].count, 50);
+        assert_eq!(buckets.last().unwrap().le_ms, None);
+        assert_eq!(buckets.iter().map(|b| b.count).sum::<u64>(), 100);
+    }
+
+    #[test]
+    fn test_percentiles_follow_recent_samples() {
+        let mut histogram = LatencyHistogram::new();
+        for _ in 0..LATENCY_WINDOW {
+            histogram.record(200);
+        }
+        for _ in 0..LATENCY_WINDOW {
+            histogram.record(700_000);
+        }
+
+        // Percentiles only see the slow samples, the buckets see both
+        let p = histogram.percentiles().unwrap();
+        assert_eq!(p.p50_ms, 700_000);
+        assert_eq!(histogram.count(), 2 * LATENCY_WINDOW as u64);
+        assert_eq!(histogram.buckets().last().unwrap().count, LATENCY_WINDOW as u64);
+    }
+}
@@ -1,5 +1,19 @@
 pub mod relay;
 pub mod weight;
 pub mod finality;
+pub mod latency;
+pub mod proof;
+pub mod sampling;
+pub mod supply;
+pub mod tip_selection;
+pub mod voting;
 
-pub use relay::{RelayProof, RelayTracker};
+pub use latency::{LatencyHistogram, LatencyPercentiles};
+pub use proof::{FinalityProof, ProofPolicy, ProofReport};
+pub use relay::{RelayCountStore, RelayProof, RelayTotals, RelayTracker};
+pub use sampling::{RelayProofSampler, SampleVerdict};
+pub use supply::SupplyLedger;
+pub use tip_selection::{
+    prove_selection, selection_output, selection_rng, TipSelection, TipSelector, WeightedRandomWalk,
+};
+pub use voting::{ConflictVoting, Opinion, VotingConfig};
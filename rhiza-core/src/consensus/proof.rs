@@ -0,0 +1,363 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey, Signature};
+use crate::dag::transaction::Transaction;
+use crate::dag::vertex::Dag;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// Domain separator of the hash attesters sign
+const PROOF_DOMAIN: &[u8] = b"rhiza finality proof v1";
+
+/// Most approvers a proof may carry
+pub const MAX_PROOF_APPROVERS: usize = 256;
+
+/// Evidence that a transaction is final, checkable without the DAG
+///
+/// The proof carries the transaction, approvers that reference it directly
+/// or through one another, and signatures from nodes that hold all of them
+/// as final history (the checkpoint). Checking one needs no clock,
+/// randomness or I/O, only signatures and hashes, so it runs as-is in
+/// WebAssembly (see `rhiza_core::wasm`).
+///
+/// Weight and witnesses alone can be forged: a payer can sign any number
+/// of approvals with throwaway keys. Only the attestations of nodes the
+/// verifier trusts tie the proof to the real network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityProof {
+    /// The transaction proven final
+    pub transaction: Transaction,
+    /// Its approvers, each listed after something it approves
+    pub approvers: Vec<Transaction>,
+    pub attestations: Vec<Attestation>,
+}
+
+/// A node's signature vouching that a proof matches its DAG
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attestation {
+    pub attester: PublicKey,
+    pub signature: Signature,
+}
+
+/// What a verifier requires of a proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProofPolicy {
+    /// Cumulative weight the transaction must show
+    pub min_weight: u64,
+    /// Distinct keys, other than the payer's, that must sign approvers
+    pub min_witnesses: usize,
+    /// Nodes whose attestations count
+    pub trusted_attesters: Vec<PublicKey>,
+    /// Valid attestations from distinct trusted nodes needed
+    pub required_attestations: usize,
+}
+
+impl Default for ProofPolicy {
+    fn default() -> Self {
+        ProofPolicy {
+            min_weight: crate::FINALITY_THRESHOLD,
+            min_witnesses: crate::FINALITY_THRESHOLD as usize,
+            trusted_attesters: Vec::new(),
+            required_attestations: 1,
+        }
+    }
+}
+
+/// What a proof that passed showed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProofReport {
+    pub transaction_id: Hash,
+    /// Cumulative weight the approvers give the transaction
+    pub weight: u64,
+    /// Distinct approver keys other than the payer's
+    pub witnesses: usize,
+    /// Trusted nodes that attested the proof
+    pub attesters: Vec<PublicKey>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ProofError {
+    #[error("malformed proof: {0}")]
+    Malformed(String),
+    #[error("{0} approvers in one proof, at most {MAX_PROOF_APPROVERS} allowed")]
+    TooManyApprovers(usize),
+    #[error("transaction {0} has a bad id or signature")]
+    InvalidTransaction(Hash),
+    #[error("approver {0} is listed twice")]
+    DuplicateApprover(Hash),
+    #[error("{0} doesn't approve the transaction or an approver listed before it")]
+    NotAnApprover(Hash),
+    #[error("weight {weight} is below the required {needed}")]
+    InsufficientWeight { weight: u64, needed: u64 },
+    #[error("{witnesses} witnesses, {needed} required")]
+    InsufficientWitnesses { witnesses: usize, needed: usize },
+    #[error("{valid} trusted attestations, {required} required")]
+    InsufficientAttestations { valid: usize, required: usize },
+}
+
+impl FinalityProof {
+    /// Unsigned proof for a final transaction in `dag`, carrying up to
+    /// [`MAX_PROOF_APPROVERS`] of its approvers, nearest first
+    pub fn build(dag: &Dag, id: &Hash) -> Option<Self> {
+        let vertex = dag.get(id).filter(|v| v.is_final)?;
+        let mut approvers = Vec::new();
+        let mut seen = HashSet::from([*id]);
+        let mut queue: VecDeque<Hash> = dag.children(id).iter().copied().collect();
+        while let Some(child) = queue.pop_front() {
+            if approvers.len() == MAX_PROOF_APPROVERS {
+                break;
+            }
+            if !seen.insert(child) {
+                continue;
+            }
+            if let Some(approver) = dag.get(&child) {
+                approvers.push(approver.transaction.clone());
+                queue.extend(dag.children(&child));
+            }
+        }
+        Some(FinalityProof {
+            transaction: vertex.transaction.clone(),
+            approvers,
+            attestations: Vec::new(),
+        })
+    }
+
+    /// Hash of everything attesters vouch for
+    pub fn content_hash(&self) -> Hash {
+        let ids: Vec<u8> = self.approvers.iter().flat_map(|tx| tx.id.as_bytes().to_vec()).collect();
+        Hash::digest_multi(&[PROOF_DOMAIN, self.transaction.id.as_bytes(), &ids])
+    }
+
+    /// Add `keypair`'s attestation, replacing an earlier one by the same key
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        let signature = keypair.sign(self.content_hash().as_bytes());
+        self.attestations.retain(|a| a.attester != keypair.public_key);
+        self.attestations.push(Attestation {
+            attester: keypair.public_key.clone(),
+            signature,
+        });
+    }
+
+    /// Encode as the blob handed to verifiers
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("finality proof serialization should not fail")
+    }
+
+    /// Decode a blob from [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ProofError> {
+        bincode::deserialize(data).map_err(|e| ProofError::Malformed(e.to_string()))
+    }
+
+    /// Check the proof against `policy`
+    pub fn verify(&self, policy: &ProofPolicy) -> Result<ProofReport, ProofError> {
+        if self.approvers.len() > MAX_PROOF_APPROVERS {
+            return Err(ProofError::TooManyApprovers(self.approvers.len()));
+        }
+        let authentic = |tx: &Transaction| tx.verify_id() && tx.verify_signature();
+        if !authentic(&self.transaction) {
+            return Err(ProofError::InvalidTransaction(self.transaction.id));
+        }
+
+        // Each approver must reach the transaction through ones already checked
+        let mut cone = HashSet::from([self.transaction.id]);
+        let mut witnesses = HashSet::new();
+        for approver in &self.approvers {
+            if !authentic(approver) {
+                return Err(ProofError::InvalidTransaction(approver.id));
+            }
+            if cone.contains(&approver.id) {
+                return Err(ProofError::DuplicateApprover(approver.id));
+            }
+            if !approver.data.parents.iter().any(|p| cone.contains(p)) {
+                return Err(ProofError::NotAnApprover(approver.id));
+            }
+            cone.insert(approver.id);
+            // Depths aren't in the proof, so a depth lock is left to the
+            // attesters; a time lock is checked against the approver's date
+            let unlocked = self.transaction.data.unlocked_at(u64::MAX, approver.data.timestamp);
+            if unlocked && approver.data.sender != self.transaction.data.sender {
+                witnesses.insert(&approver.data.sender);
+            }
+        }
+
+        let weight = cone.len() as u64;
+        if weight < policy.min_weight {
+            return Err(ProofError::InsufficientWeight { weight, needed: policy.min_weight });
+        }
+        if witnesses.len() < policy.min_witnesses {
+            return Err(ProofError::InsufficientWitnesses {
+                witnesses: witnesses.len(),
+                needed: policy.min_witnesses,
+            });
+        }
+
+        let hash = self.content_hash();
+        let mut attesters: Vec<PublicKey> = Vec::new();
+        for attestation in &self.attestations {
+            if policy.trusted_attesters.contains(&attestation.attester)
+                && !attesters.contains(&attestation.attester)
+                && attestation.attester.verify(hash.as_bytes(), &attestation.signature)
+            {
+                attesters.push(attestation.attester.clone());
+            }
+        }
+        if attesters.len() < policy.required_attestations {
+            return Err(ProofError::InsufficientAttestations {
+                valid: attesters.len(),
+                required: policy.required_attestations,
+            });
+        }
+
+        Ok(ProofReport {
+            transaction_id: self.transaction.id,
+            weight,
+            witnesses: witnesses.len(),
+            attesters,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dag::vertex::DagVertex;
+
+    /// A DAG where `payment` is approved by a chain of threshold + 1
+    /// transactions from `signers` in turn, starting after the payer
+    fn final_payment(signers: &[KeyPair]) -> (Dag, Hash) {
+        let payer = &signers[0];
+        let genesis = Transaction::genesis(payer);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+
+        let merchant = signers.last().unwrap().public_key.clone();
+        let payment = Transaction::transfer(payer, merchant, 1, [genesis_id, genesis_id], 1);
+        let payment_id = payment.id;
+        dag.insert(DagVertex::new(payment, 1)).unwrap();
+
+        let mut last = payment_id;
+        for i in 0..=crate::FINALITY_THRESHOLD {
+            let signer = &signers[(i as usize + 1) % signers.len()];
+            let tx = Transaction::key_announcement(signer, [last, last], i + 2);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i + 2)).unwrap();
+        }
+        (dag, payment_id)
+    }
+
+    fn policy(attester: &KeyPair) -> ProofPolicy {
+        ProofPolicy {
+            trusted_attesters: vec![attester.public_key.clone()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_proof_roundtrip_and_verify() {
+        let signers: Vec<KeyPair> = (0..=crate::FINALITY_THRESHOLD).map(|_| KeyPair::generate()).collect();
+        let node = KeyPair::generate();
+        let (dag, id) = final_payment(&signers);
+
+        let mut proof = FinalityProof::build(&dag, &id).unwrap();
+        assert_eq!(proof.approvers.len() as u64, crate::FINALITY_THRESHOLD + 1);
+        proof.sign(&node);
+
+        let proof = FinalityProof::from_bytes(&proof.to_bytes()).unwrap();
+        let report = proof.verify(&policy(&node)).unwrap();
+        assert_eq!(report.transaction_id, id);
+        assert_eq!(report.weight, crate::FINALITY_THRESHOLD + 2);
+        assert_eq!(report.witnesses, crate::FINALITY_THRESHOLD as usize);
+        assert_eq!(report.attesters, vec![node.public_key.clone()]);
+
+        // Pending transactions get no proof
+        assert!(FinalityProof::build(&dag, &dag.tips()[0]).is_none());
+    }
+
+    #[test]
+    fn test_attestations_must_be_trusted() {
+        let signers: Vec<KeyPair> = (0..=crate::FINALITY_THRESHOLD).map(|_| KeyPair::generate()).collect();
+        let node = KeyPair::generate();
+        let stranger = KeyPair::generate();
+        let (dag, id) = final_payment(&signers);
+
+        let mut proof = FinalityProof::build(&dag, &id).unwrap();
+        proof.sign(&stranger);
+        assert_eq!(
+            proof.verify(&policy(&node)),
+            Err(ProofError::InsufficientAttestations { valid: 0, required: 1 })
+        );
+
+        // An attestation doesn't carry over to different contents
+        proof.sign(&node);
+        assert!(proof.verify(&policy(&node)).is_ok());
+        proof.approvers.pop();
+        assert!(matches!(
+            proof.verify(&policy(&node)),
+            Err(ProofError::InsufficientAttestations { valid: 0, .. })
+        ));
+
+        // Without an attestation nothing ties the proof to the network
+        let trustless = ProofPolicy { required_attestations: 0, ..policy(&node) };
+        assert!(proof.verify(&trustless).is_ok());
+        proof.approvers.pop();
+        let needed = crate::FINALITY_THRESHOLD as usize;
+        assert_eq!(
+            proof.verify(&trustless),
+            Err(ProofError::InsufficientWitnesses { witnesses: needed - 1, needed })
+        );
+        let weightless = ProofPolicy { min_weight: crate::FINALITY_THRESHOLD + 1, min_witnesses: 0, ..trustless };
+        assert_eq!(
+            proof.verify(&weightless),
+            Err(ProofError::InsufficientWeight { weight: crate::FINALITY_THRESHOLD, needed: crate::FINALITY_THRESHOLD + 1 })
+        );
+    }
+
+    #[test]
+    fn test_forged_approvals_are_rejected() {
+        let signers: Vec<KeyPair> = (0..=crate::FINALITY_THRESHOLD).map(|_| KeyPair::generate()).collect();
+        let node = KeyPair::generate();
+        let (dag, id) = final_payment(&signers);
+        let proof = FinalityProof::build(&dag, &id).unwrap();
+
+        // The payer approving its own payment isn't a witness, nor does it
+        // make the payment final
+        let (solo, solo_id) = final_payment(&signers[..1]);
+        assert!(FinalityProof::build(&solo, &solo_id).is_none());
+        let mut alone = FinalityProof {
+            transaction: solo.get(&solo_id).unwrap().transaction.clone(),
+            approvers: solo.iter().filter(|v| v.depth > 1).map(|v| v.transaction.clone()).collect(),
+            attestations: Vec::new(),
+        };
+        alone.sign(&node);
+        assert_eq!(
+            alone.verify(&policy(&node)),
+            Err(ProofError::InsufficientWitnesses { witnesses: 0, needed: crate::FINALITY_THRESHOLD as usize })
+        );
+
+        // Approvers have to hang off the transaction
+        let mut detached = proof.clone();
+        detached.approvers.remove(0);
+        detached.sign(&node);
+        assert_eq!(
+            detached.verify(&policy(&node)),
+            Err(ProofError::NotAnApprover(proof.approvers[1].id))
+        );
+
+        let mut tampered = proof.clone();
+        tampered.approvers[2].data.nonce += 1;
+        tampered.sign(&node);
+        assert_eq!(
+            tampered.verify(&policy(&node)),
+            Err(ProofError::InvalidTransaction(proof.approvers[2].id))
+        );
+
+        let mut repeated = proof.clone();
+        repeated.approvers.push(proof.approvers[0].clone());
+        repeated.sign(&node);
+        assert_eq!(
+            repeated.verify(&policy(&node)),
+            Err(ProofError::DuplicateApprover(proof.approvers[0].id))
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use crate::crypto::{Hash, PublicKey, Signature};
 use crate::crypto::keys::KeyPair;
+use lru::LruCache;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+/// Most relay proofs a single reward claim may carry
+pub const MAX_RELAY_PROOFS_PER_CLAIM: usize = 256;
 
 /// Proof that a node relayed a transaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,54 +54,136 @@ impl RelayProof {
     }
 }
 
+/// Number of hot-cache shards in a `RelayTracker`
+pub const RELAY_TRACKER_SHARDS: usize = 16;
+
+/// Default number of relayers kept hot per shard when backed by a store
+pub const DEFAULT_HOT_RELAYERS_PER_SHARD: usize = 4_096;
+
+/// Cold storage for per-relayer counts
+///
+/// Counts are written through on every relay, so the hot cache can drop
+/// inactive relayers at any time without losing anything.
+pub trait RelayCountStore: Send + Sync {
+    /// Stored count for a relayer, if any
+    fn load(&self, relayer: &PublicKey) -> Option<u64>;
+    /// Persist a relayer's count
+    fn save(&mut self, relayer: &PublicKey, count: u64);
+}
+
 /// Tracks relay activity per node for reward calculation
-#[derive(Debug, Clone)]
+///
+/// Per-relayer counts live in sharded LRU caches. Without a store the caches
+/// are unbounded; with one they hold only recently active relayers and fall
+/// back to the store on a miss, so memory stays bounded however many keys
+/// have ever relayed and each `record_relay` is O(1).
 pub struct RelayTracker {
-    /// Total relays per node
-    relay_counts: HashMap<PublicKey, u64>,
+    /// Hot relay counts, sharded by the relayer key's first byte
+    shards: Vec<LruCache<PublicKey, u64>>,
+    /// Cold storage for counts evicted from (or never loaded into) the cache
+    store: Option<Box<dyn RelayCountStore>>,
     /// Total relays in the network
     total_relays: u64,
     /// Total rewards distributed
     total_rewards_distributed: u64,
 }
 
+/// Network-wide relay totals, persisted alongside the per-relayer counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayTotals {
+    pub total_relays: u64,
+    pub total_rewards_distributed: u64,
+}
+
 impl RelayTracker {
+    /// In-memory tracker that keeps every relayer hot
     pub fn new() -> Self {
         RelayTracker {
-            relay_counts: HashMap::new(),
+            shards: (0..RELAY_TRACKER_SHARDS).map(|_| LruCache::unbounded()).collect(),
+            store: None,
             total_relays: 0,
             total_rewards_distributed: 0,
         }
     }
 
+    /// Tracker backed by cold storage, keeping at most `hot_per_shard`
+    /// relayers per shard in memory
+    pub fn with_store(store: Box<dyn RelayCountStore>, hot_per_shard: usize, totals: RelayTotals) -> Self {
+        let capacity = NonZeroUsize::new(hot_per_shard).unwrap_or(NonZeroUsize::MIN);
+        RelayTracker {
+            shards: (0..RELAY_TRACKER_SHARDS).map(|_| LruCache::new(capacity)).collect(),
+            store: Some(store),
+            total_relays: totals.total_relays,
+            total_rewards_distributed: totals.total_rewards_distributed,
+        }
+    }
+
+    fn shard_index(relayer: &PublicKey) -> usize {
+        relayer.as_bytes()[0] as usize % RELAY_TRACKER_SHARDS
+    }
+
     /// Record a relay and calculate the reward
     pub fn record_relay(&mut self, relayer: &PublicKey) -> u64 {
-        let count = self.relay_counts.entry(relayer.clone()).or_insert(0);
-        *count += 1;
-        let current_count = *count;
-        self.total_relays += 1;
+        let shard = Self::shard_index(relayer);
+        let count = match self.shards[shard].get(relayer) {
+            Some(count) => *count,
+            None => self.store.as_ref().and_then(|s| s.load(relayer)).unwrap_or(0),
+        };
+        let current_count = count.saturating_add(1);
+        self.shards[shard].put(relayer.clone(), current_count);
+        if let Some(store) = self.store.as_mut() {
+            store.save(relayer, current_count);
+        }
+        self.total_relays = self.total_relays.saturating_add(1);
 
         let reward = self.calculate_reward(current_count);
 
         // Check max supply
-        if self.total_rewards_distributed + reward > crate::MAX_SUPPLY {
-            return 0; // No more rewards available
+        match self.total_rewards_distributed.checked_add(reward) {
+            Some(total) if total <= crate::MAX_SUPPLY => {
+                self.total_rewards_distributed = total;
+                reward
+            }
+            _ => 0, // No more rewards available
         }
-
-        self.total_rewards_distributed += reward;
-        reward
     }
 
     /// Calculate relay reward with diminishing returns
     /// reward = BASE_RELAY_REWARD / (1 + node_relays / RELAY_HALVING_INTERVAL)
     pub fn calculate_reward(&self, node_relay_count: u64) -> u64 {
-        let divisor = 1 + node_relay_count / crate::RELAY_HALVING_INTERVAL;
+        let divisor = (node_relay_count / crate::RELAY_HALVING_INTERVAL).saturating_add(1);
         crate::BASE_RELAY_REWARD / divisor
     }
 
     /// Get the relay count for a node
     pub fn get_relay_count(&self, relayer: &PublicKey) -> u64 {
-        self.relay_counts.get(relayer).copied().unwrap_or(0)
+        match self.shards[Self::shard_index(relayer)].peek(relayer) {
+            Some(count) => *count,
+            None => self.store.as_ref().and_then(|s| s.load(relayer)).unwrap_or(0),
+        }
+    }
+
+    /// Move `from`'s relay count onto `to`, e.g. when a node rotates its
+    /// identity key
+    ///
+    /// Counts are added, so a rotation never resets the reward curve.
+    pub fn transfer_count(&mut self, from: &PublicKey, to: &PublicKey) {
+        let moved = self.get_relay_count(from);
+        if moved == 0 || from == to {
+            return;
+        }
+        let total = self.get_relay_count(to).saturating_add(moved);
+        for (key, count) in [(from, 0), (to, total)] {
+            self.shards[Self::shard_index(key)].put(key.clone(), count);
+            if let Some(store) = self.store.as_mut() {
+                store.save(key, count);
+            }
+        }
+    }
+
+    /// Number of relayers currently held in memory
+    pub fn hot_relayers(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
     }
 
     /// Get total rewards distributed
@@ -109,6 +195,14 @@ impl RelayTracker {
     pub fn total_relays(&self) -> u64 {
         self.total_relays
     }
+
+    /// Network-wide totals, for persisting
+    pub fn totals(&self) -> RelayTotals {
+        RelayTotals {
+            total_relays: self.total_relays,
+            total_rewards_distributed: self.total_rewards_distributed,
+        }
+    }
 }
 
 impl Default for RelayTracker {
@@ -117,6 +211,17 @@ impl Default for RelayTracker {
     }
 }
 
+impl std::fmt::Debug for RelayTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelayTracker")
+            .field("hot_relayers", &self.hot_relayers())
+            .field("backed_by_store", &self.store.is_some())
+            .field("total_relays", &self.total_relays)
+            .field("total_rewards_distributed", &self.total_rewards_distributed)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,4 +293,104 @@ mod tests {
         // After 1000 relays: half reward
         assert_eq!(r1000, crate::BASE_RELAY_REWARD / 2);
     }
+
+    /// Store that shares its map with the test so writes can be observed
+    #[derive(Clone, Default)]
+    struct MemoryStore(std::sync::Arc<std::sync::Mutex<std::collections::HashMap<PublicKey, u64>>>);
+
+    impl RelayCountStore for MemoryStore {
+        fn load(&self, relayer: &PublicKey) -> Option<u64> {
+            self.0.lock().unwrap().get(relayer).copied()
+        }
+
+        fn save(&mut self, relayer: &PublicKey, count: u64) {
+            self.0.lock().unwrap().insert(relayer.clone(), count);
+        }
+    }
+
+    #[test]
+    fn test_reward_curve_extremes() {
+        let tracker = RelayTracker::new();
+        let counts = [0, 1, crate::RELAY_HALVING_INTERVAL - 1, crate::RELAY_HALVING_INTERVAL, u64::MAX - 1, u64::MAX];
+        let rewards: Vec<u64> = counts.iter().map(|c| tracker.calculate_reward(*c)).collect();
+        assert!(rewards.iter().all(|r| *r <= crate::BASE_RELAY_REWARD));
+        assert!(rewards.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(tracker.calculate_reward(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_relay_counters_saturate() {
+        let store = MemoryStore::default();
+        let veteran = KeyPair::generate();
+        let newcomer = KeyPair::generate();
+        store.0.lock().unwrap().insert(veteran.public_key.clone(), u64::MAX);
+        store.0.lock().unwrap().insert(newcomer.public_key.clone(), u64::MAX - 1);
+        let totals = RelayTotals {
+            total_relays: u64::MAX,
+            total_rewards_distributed: u64::MAX,
+        };
+        let mut tracker = RelayTracker::with_store(Box::new(store), 16, totals);
+
+        assert_eq!(tracker.record_relay(&veteran.public_key), 0);
+        assert_eq!(tracker.get_relay_count(&veteran.public_key), u64::MAX);
+        assert_eq!(tracker.total_relays(), u64::MAX);
+        assert_eq!(tracker.total_rewards(), u64::MAX);
+
+        tracker.transfer_count(&veteran.public_key, &newcomer.public_key);
+        assert_eq!(tracker.get_relay_count(&newcomer.public_key), u64::MAX);
+
+        // A full reward that would pass the supply cap is withheld
+        let totals = RelayTotals {
+            total_relays: 0,
+            total_rewards_distributed: crate::MAX_SUPPLY - 1,
+        };
+        let mut tracker = RelayTracker::with_store(Box::new(MemoryStore::default()), 16, totals);
+        assert_eq!(tracker.record_relay(&KeyPair::generate().public_key), 0);
+        assert_eq!(tracker.total_rewards(), crate::MAX_SUPPLY - 1);
+    }
+
+    #[test]
+    fn test_transfer_count() {
+        let store = MemoryStore::default();
+        let mut tracker = RelayTracker::with_store(Box::new(store.clone()), 1, RelayTotals::default());
+        let old = KeyPair::generate();
+        let new = KeyPair::generate();
+        for _ in 0..3 {
+            tracker.record_relay(&old.public_key);
+        }
+        tracker.record_relay(&new.public_key);
+
+        tracker.transfer_count(&old.public_key, &new.public_key);
+        assert_eq!(tracker.get_relay_count(&old.public_key), 0);
+        assert_eq!(tracker.get_relay_count(&new.public_key), 4);
+        assert_eq!(store.load(&new.public_key), Some(4));
+        assert_eq!(tracker.total_relays(), 4);
+    }
+
+    #[test]
+    fn test_evicted_relayers_reload_from_store() {
+        let store = MemoryStore::default();
+        let mut tracker = RelayTracker::with_store(Box::new(store.clone()), 1, RelayTotals::default());
+
+        let relayers: Vec<KeyPair> = (0..100).map(|_| KeyPair::generate()).collect();
+        for kp in &relayers {
+            tracker.record_relay(&kp.public_key);
+            tracker.record_relay(&kp.public_key);
+        }
+
+        // At most one hot relayer per shard, but nothing forgotten
+        assert!(tracker.hot_relayers() <= RELAY_TRACKER_SHARDS);
+        assert_eq!(store.0.lock().unwrap().len(), 100);
+        for kp in &relayers {
+            assert_eq!(tracker.get_relay_count(&kp.public_key), 2);
+        }
+        tracker.record_relay(&relayers[0].public_key);
+        assert_eq!(tracker.get_relay_count(&relayers[0].public_key), 3);
+        assert_eq!(tracker.total_relays(), 201);
+
+        // Totals survive a restart
+        let restored = RelayTracker::with_store(Box::new(store), 1, tracker.totals());
+        assert_eq!(restored.total_relays(), 201);
+        assert_eq!(restored.get_relay_count(&relayers[5].public_key), 2);
+    }
 }
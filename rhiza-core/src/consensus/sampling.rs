@@ -0,0 +1,219 @@
+use crate::consensus::relay::RelayProof;
+use crate::crypto::{Hash, PublicKey};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// Number of relay proofs checked per claim by default
+pub const DEFAULT_SAMPLE_SIZE: usize = 16;
+
+/// Peer score penalty applied per proof in a voided claim
+pub const INVALID_CLAIM_PENALTY_PER_PROOF: i64 = 5;
+
+/// Upper bound on the penalty for a single voided claim
+pub const MAX_INVALID_CLAIM_PENALTY: i64 = 1_000;
+
+/// Probabilistic verifier for relay reward claims
+///
+/// Instead of checking the signature on every proof in a claim, validators
+/// check a subset drawn from the claim's ID. A single bad proof in the sample
+/// voids the whole claim and the peer that sent it is penalized in proportion
+/// to the claim size, so padding a large claim with forged proofs is a losing
+/// bet. The claimer signs its own proofs, so one that slips through gains
+/// nothing it couldn't have signed; what each proof may claim is still
+/// checked in full.
+#[derive(Debug, Clone)]
+pub struct RelayProofSampler {
+    /// How many proofs to check per claim
+    sample_size: usize,
+}
+
+/// Result of sampling a relay claim
+#[derive(Debug, Clone, PartialEq)]
+pub enum SampleVerdict {
+    /// Every sampled proof verified
+    Accepted {
+        /// Number of proofs that were actually checked
+        checked: usize,
+    },
+    /// A sampled proof was invalid — the entire claim is void
+    Voided {
+        /// Transaction referenced by the offending proof
+        invalid_proof: Hash,
+        /// Peer score penalty for the claimer
+        penalty: i64,
+    },
+}
+
+impl SampleVerdict {
+    /// Whether the claim may be accepted
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, SampleVerdict::Accepted { .. })
+    }
+}
+
+impl RelayProofSampler {
+    /// Create a sampler checking `sample_size` proofs per claim
+    pub fn new(sample_size: usize) -> Self {
+        RelayProofSampler {
+            sample_size: sample_size.max(1),
+        }
+    }
+
+    /// Number of proofs checked per claim
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+
+    /// Verify a random subset of the proofs in a claim made by `claimer`
+    ///
+    /// Claims no larger than the sample size are verified in full.
+    pub fn verify_claim<R: Rng + ?Sized>(
+        &self,
+        claimer: &PublicKey,
+        proofs: &[RelayProof],
+        rng: &mut R,
+    ) -> SampleVerdict {
+        let indices: Vec<usize> = if proofs.len() <= self.sample_size {
+            (0..proofs.len()).collect()
+        } else {
+            rand::seq::index::sample(rng, proofs.len(), self.sample_size).into_vec()
+        };
+
+        for &i in &indices {
+            let proof = &proofs[i];
+            if proof.relayer != *claimer || !proof.verify() {
+                return SampleVerdict::Voided {
+                    invalid_proof: proof.transaction_id,
+                    penalty: Self::penalty_for(proofs.len()),
+                };
+            }
+        }
+
+        SampleVerdict::Accepted {
+            checked: indices.len(),
+        }
+    }
+
+    /// Verify the subset the claim `claim_id` itself selects
+    ///
+    /// Every validator draws the same proofs for the same claim, so they all
+    /// reach the same verdict.
+    pub fn verify_claim_seeded(
+        &self,
+        claim_id: &Hash,
+        claimer: &PublicKey,
+        proofs: &[RelayProof],
+    ) -> SampleVerdict {
+        let mut rng = ChaCha20Rng::from_seed(*claim_id.as_bytes());
+        self.verify_claim(claimer, proofs, &mut rng)
+    }
+
+    /// Penalty for a voided claim of `claim_size` proofs
+    pub fn penalty_for(claim_size: usize) -> i64 {
+        i64::try_from(claim_size)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(INVALID_CLAIM_PENALTY_PER_PROOF)
+            .clamp(INVALID_CLAIM_PENALTY_PER_PROOF, MAX_INVALID_CLAIM_PENALTY)
+    }
+
+    /// Probability that a claim with `invalid` bad proofs out of `total`
+    /// slips through sampling undetected
+    pub fn miss_probability(&self, total: usize, invalid: usize) -> f64 {
+        if invalid == 0 {
+            return 1.0;
+        }
+        let k = self.sample_size.min(total);
+        let valid = total - invalid.min(total);
+        // Hypergeometric: all k draws land on valid proofs
+        let mut p = 1.0;
+        for i in 0..k {
+            if valid < i + 1 {
+                return 0.0;
+            }
+            p *= (valid - i) as f64 / (total - i) as f64;
+        }
+        p
+    }
+}
+
+impl Default for RelayProofSampler {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn claim(kp: &KeyPair, n: usize) -> Vec<RelayProof> {
+        (0..n)
+            .map(|i| RelayProof::new(kp, Hash::digest(&i.to_le_bytes()), 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_claim_accepted() {
+        let kp = KeyPair::generate();
+        let proofs = claim(&kp, 100);
+        let sampler = RelayProofSampler::default();
+
+        let verdict = sampler.verify_claim(&kp.public_key, &proofs, &mut crate::testkit::seeded_rng());
+        assert_eq!(verdict, SampleVerdict::Accepted { checked: DEFAULT_SAMPLE_SIZE });
+    }
+
+    #[test]
+    fn test_small_claim_fully_checked() {
+        let kp = KeyPair::generate();
+        let mut proofs = claim(&kp, 4);
+        proofs[3].hop_count = 42; // Tamper
+        let sampler = RelayProofSampler::default();
+
+        let verdict = sampler.verify_claim(&kp.public_key, &proofs, &mut crate::testkit::seeded_rng());
+        assert!(!verdict.is_accepted());
+    }
+
+    #[test]
+    fn test_foreign_proof_voids_claim() {
+        let kp = KeyPair::generate();
+        let other = KeyPair::generate();
+        let proofs = claim(&other, 3);
+        let sampler = RelayProofSampler::default();
+
+        let verdict = sampler.verify_claim(&kp.public_key, &proofs, &mut crate::testkit::seeded_rng());
+        assert!(matches!(verdict, SampleVerdict::Voided { penalty: 15, .. }));
+    }
+
+    #[test]
+    fn test_seeded_draw_is_shared() {
+        let kp = KeyPair::generate();
+        let mut proofs = claim(&kp, 200);
+        for proof in proofs.iter_mut().step_by(2) {
+            proof.hop_count = 42; // Tamper with every other proof
+        }
+        let sampler = RelayProofSampler::default();
+        let id = Hash::digest(b"claim");
+
+        // Any two validators void it on the same proof
+        let first = sampler.verify_claim_seeded(&id, &kp.public_key, &proofs);
+        assert!(!first.is_accepted());
+        assert_eq!(sampler.verify_claim_seeded(&id, &kp.public_key, &proofs), first);
+    }
+
+    #[test]
+    fn test_penalty_scales_with_claim_size() {
+        assert_eq!(RelayProofSampler::penalty_for(0), INVALID_CLAIM_PENALTY_PER_PROOF);
+        assert!(RelayProofSampler::penalty_for(100) > RelayProofSampler::penalty_for(10));
+        assert_eq!(RelayProofSampler::penalty_for(usize::MAX), MAX_INVALID_CLAIM_PENALTY);
+    }
+
+    #[test]
+    fn test_miss_probability() {
+        let sampler = RelayProofSampler::new(16);
+        assert_eq!(sampler.miss_probability(100, 0), 1.0);
+        assert_eq!(sampler.miss_probability(10, 1), 0.0);
+        // Forging half of a large claim is almost always caught
+        assert!(sampler.miss_probability(1_000, 500) < 0.0001);
+    }
+}
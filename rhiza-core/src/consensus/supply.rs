@@ -0,0 +1,83 @@
+use crate::dag::transaction::{Transaction, TransactionType};
+use serde::{Deserialize, Serialize};
+
+/// Units minted so far, by where they came from
+///
+/// Derived from the DAG itself: the genesis, the founder allocation and
+/// every relay reward in it mint their amount, and nothing else does. The
+/// DAG keeps one ledger over everything it holds and another over its
+/// pruned history, which goes into its balance snapshot, so the total
+/// survives pruning and restarts. Reward claims that would take it past
+/// [`MAX_SUPPLY`](crate::MAX_SUPPLY) are refused by the validator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplyLedger {
+    pub genesis: u64,
+    pub founder_allocation: u64,
+    pub relay_rewards: u64,
+}
+
+impl SupplyLedger {
+    /// Units a transaction mints
+    pub fn minted_by(tx: &Transaction) -> u64 {
+        match tx.data.tx_type {
+            TransactionType::Genesis | TransactionType::FounderAllocation | TransactionType::RelayReward => {
+                tx.data.amount
+            }
+            _ => 0,
+        }
+    }
+
+    /// Count what a transaction mints
+    pub fn record(&mut self, tx: &Transaction) {
+        let amount = tx.data.amount;
+        let total = match tx.data.tx_type {
+            TransactionType::Genesis => &mut self.genesis,
+            TransactionType::FounderAllocation => &mut self.founder_allocation,
+            TransactionType::RelayReward => &mut self.relay_rewards,
+            _ => return,
+        };
+        *total = total.saturating_add(amount);
+    }
+
+    /// Units minted in all
+    pub fn minted(&self) -> u64 {
+        self.genesis
+            .saturating_add(self.founder_allocation)
+            .saturating_add(self.relay_rewards)
+    }
+
+    /// Units left to mint before the maximum supply
+    pub fn remaining(&self) -> u64 {
+        crate::MAX_SUPPLY.saturating_sub(self.minted())
+    }
+
+    /// Whether `amount` more can be minted within the maximum supply
+    pub fn can_mint(&self, amount: u64) -> bool {
+        amount <= self.remaining()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::crypto::Hash;
+
+    #[test]
+    fn test_counts_only_minting_transactions() {
+        let kp = KeyPair::generate();
+        let parents = [Hash::digest(b"a"), Hash::digest(b"b")];
+        let mut ledger = SupplyLedger::default();
+        ledger.record(&Transaction::genesis(&kp));
+        ledger.record(&Transaction::founder_allocation(&kp, crate::founder_public_key(), parents[0]));
+        ledger.record(&Transaction::relay_reward(&kp, 700, parents, 2));
+        ledger.record(&Transaction::transfer(&kp, kp.public_key.clone(), 5_000, parents, 3));
+
+        assert_eq!(ledger.founder_allocation, crate::FOUNDER_ALLOCATION);
+        assert_eq!(ledger.relay_rewards, 700);
+        assert_eq!(ledger.minted(), crate::FOUNDER_ALLOCATION + 700);
+        assert_eq!(ledger.remaining(), crate::MAX_SUPPLY - crate::FOUNDER_ALLOCATION - 700);
+        assert!(ledger.can_mint(ledger.remaining()));
+        assert!(!ledger.can_mint(ledger.remaining() + 1));
+    }
+}
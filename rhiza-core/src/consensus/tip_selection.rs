@@ -0,0 +1,373 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey, VrfProof};
+use crate::dag::transaction::TransactionData;
+use crate::dag::vertex::Dag;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Default randomness of the weighted walk (0 = uniform, larger = greedier)
+pub const DEFAULT_ALPHA: f64 = 0.1;
+
+/// How far behind the frontier (in depths) random walks start
+pub const DEFAULT_WALK_START_DEPTH: u64 = 20;
+
+/// Attempts at walking to each further tip distinct from those already
+/// chosen
+const DISTINCT_TIP_ATTEMPTS: usize = 4;
+
+/// Prefix of the VRF input a transaction's tip selection is seeded from
+const SELECTION_DOMAIN: &[u8] = b"rhiza-tip-selection-v1";
+
+/// VRF input whose output seeds the tip selection of the sender's
+/// transaction with `nonce` on network `network_id`
+///
+/// The output is fixed by the sender's key and the nonce, so a node can't
+/// reroll it without changing the nonce. Nodes use the size of their DAG
+/// as the nonce; one that grinds nonces for a more convenient draw stands
+/// out against the DAG it claims to have seen.
+pub fn selection_input(network_id: u32, sender: &PublicKey, nonce: u64) -> Vec<u8> {
+    let mut input = Vec::with_capacity(SELECTION_DOMAIN.len() + 44);
+    input.extend_from_slice(SELECTION_DOMAIN);
+    input.extend_from_slice(&network_id.to_le_bytes());
+    input.extend_from_slice(sender.as_bytes());
+    input.extend_from_slice(&nonce.to_le_bytes());
+    input
+}
+
+/// Randomness for the tip selection of a transaction, with the proof that
+/// goes into it
+pub fn prove_selection(keypair: &KeyPair, network_id: u32, nonce: u64) -> (VrfProof, ChaCha20Rng) {
+    let input = selection_input(network_id, &keypair.public_key, nonce);
+    let proof = VrfProof::prove(keypair, &input);
+    let output = proof
+        .verify(&keypair.public_key, &input)
+        .expect("a fresh proof verifies");
+    (proof, selection_rng(&output))
+}
+
+/// The VRF output a transaction's tip selection drew on, if it carries a
+/// proof and the proof holds
+///
+/// Anyone can replay the selection with [`selection_rng`] of this output
+/// against the DAG as it stood, and compare the parents the sender
+/// actually chose.
+pub fn selection_output(data: &TransactionData) -> Option<[u8; 64]> {
+    let input = selection_input(data.network_id, &data.sender, data.nonce);
+    data.selection_proof.as_ref()?.verify(&data.sender, &input)
+}
+
+/// The random stream tip selection reads, seeded from a VRF output
+pub fn selection_rng(output: &[u8; 64]) -> ChaCha20Rng {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&output[..32]);
+    ChaCha20Rng::from_seed(seed)
+}
+
+/// Chooses the parents a new transaction approves
+pub trait TipSelector: Send + Sync {
+    /// Pick `count` tips (repeating some if the DAG has fewer, zero hashes
+    /// if it is empty)
+    fn select(&self, dag: &Dag, count: usize, rng: &mut dyn RngCore) -> Vec<Hash>;
+}
+
+/// Always approve the deepest tips
+///
+/// Cheapest strategy, but the DAG degenerates into a chain and stale tips
+/// are never approved.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeepestTips;
+
+impl TipSelector for DeepestTips {
+    fn select(&self, dag: &Dag, count: usize, _rng: &mut dyn RngCore) -> Vec<Hash> {
+        dag.select_deepest_parents(count)
+    }
+}
+
+/// Approve tips chosen uniformly at random
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformTips;
+
+impl TipSelector for UniformTips {
+    fn select(&self, dag: &Dag, count: usize, rng: &mut dyn RngCore) -> Vec<Hash> {
+        dag.select_parents_random(count, rng)
+    }
+}
+
+/// Weighted random walk (MCMC) tip selection
+///
+/// Each walk starts some depths behind the frontier and steps towards the
+/// tips, moving to an approver with probability proportional to
+/// `exp(-alpha * (weight_here - weight_approver))`. Heavier branches attract
+/// walks, so lazy tips and conflicting branches are left behind, while a
+/// small `alpha` still spreads approvals across the frontier.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedRandomWalk {
+    alpha: f64,
+    start_depth: u64,
+}
+
+impl WeightedRandomWalk {
+    /// Create a walker with the given `alpha` (negative values are clamped to 0)
+    pub fn new(alpha: f64) -> Self {
+        WeightedRandomWalk {
+            alpha: alpha.max(0.0),
+            start_depth: DEFAULT_WALK_START_DEPTH,
+        }
+    }
+
+    /// Start walks this many depths behind the deepest tip
+    pub fn with_start_depth(mut self, start_depth: u64) -> Self {
+        self.start_depth = start_depth;
+        self
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Walk back from the deepest tip to the entry point of the random walks
+    fn entry_point(&self, dag: &Dag, rng: &mut dyn RngCore) -> Option<Hash> {
+        let mut current = dag.select_parents()[0];
+        let mut vertex = dag.get(&current)?;
+        let target = vertex.depth.saturating_sub(self.start_depth);
+        while vertex.depth > target {
+            let parents = vertex.parents();
+            let parent = parents[rng.gen_range(0..parents.len())];
+            match dag.get(&parent) {
+                Some(p) => {
+                    current = parent;
+                    vertex = p;
+                }
+                None => break,
+            }
+        }
+        Some(current)
+    }
+
+    /// Walk from `start` to a tip
+    fn walk(&self, dag: &Dag, start: Hash, rng: &mut dyn RngCore) -> Hash {
+        let mut current = start;
+        loop {
+            let mut approvers: Vec<Hash> = dag.children(&current).to_vec();
+            approvers.sort();
+            approvers.dedup();
+            let candidates: Vec<(Hash, f64)> = approvers
+                .into_iter()
+                .filter_map(|id| dag.get(&id).map(|v| (id, v.cumulative_weight as f64)))
+                .collect();
+            if candidates.is_empty() {
+                return current;
+            }
+
+            // exp(-alpha * (here - w)), divided through by the heaviest
+            // candidate's score so large weights don't underflow to zero
+            let heaviest = candidates.iter().map(|(_, w)| *w).fold(f64::MIN, f64::max);
+            let scores: Vec<f64> = candidates
+                .iter()
+                .map(|(_, w)| (self.alpha * (w - heaviest)).exp())
+                .collect();
+            let total: f64 = scores.iter().sum();
+
+            let mut pick = rng.gen::<f64>() * total;
+            current = candidates[candidates.len() - 1].0;
+            for ((id, _), score) in candidates.iter().zip(&scores) {
+                if pick < *score {
+                    current = *id;
+                    break;
+                }
+                pick -= score;
+            }
+        }
+    }
+}
+
+impl Default for WeightedRandomWalk {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALPHA)
+    }
+}
+
+impl TipSelector for WeightedRandomWalk {
+    fn select(&self, dag: &Dag, count: usize, rng: &mut dyn RngCore) -> Vec<Hash> {
+        let Some(entry) = self.entry_point(dag, rng) else {
+            return vec![Hash::zero(); count];
+        };
+        let mut tips = vec![self.walk(dag, entry, rng)];
+        while tips.len() < count {
+            let mut next = tips[0];
+            if dag.tip_count() > tips.len() {
+                for _ in 0..DISTINCT_TIP_ATTEMPTS {
+                    next = self.walk(dag, entry, rng);
+                    if !tips.contains(&next) {
+                        break;
+                    }
+                }
+            }
+            tips.push(next);
+        }
+        dag.prefer_replacements(tips)
+    }
+}
+
+/// Tip selection strategies a node can be configured with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "lowercase")]
+pub enum TipSelection {
+    Deepest,
+    Uniform,
+    Mcmc {
+        #[serde(default = "default_alpha")]
+        alpha: f64,
+    },
+}
+
+fn default_alpha() -> f64 {
+    DEFAULT_ALPHA
+}
+
+impl TipSelection {
+    /// Build the selector for this strategy
+    pub fn selector(&self) -> Box<dyn TipSelector> {
+        match *self {
+            TipSelection::Deepest => Box::new(DeepestTips),
+            TipSelection::Uniform => Box::new(UniformTips),
+            TipSelection::Mcmc { alpha } => Box::new(WeightedRandomWalk::new(alpha)),
+        }
+    }
+}
+
+impl Default for TipSelection {
+    fn default() -> Self {
+        TipSelection::Mcmc { alpha: DEFAULT_ALPHA }
+    }
+}
+
+impl fmt::Display for TipSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TipSelection::Deepest => write!(f, "deepest"),
+            TipSelection::Uniform => write!(f, "uniform"),
+            TipSelection::Mcmc { alpha } => write!(f, "mcmc:{}", alpha),
+        }
+    }
+}
+
+/// Parses `deepest`, `uniform`, `mcmc` or `mcmc:<alpha>`
+impl FromStr for TipSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "deepest" => Ok(TipSelection::Deepest),
+            None if s == "uniform" => Ok(TipSelection::Uniform),
+            None if s == "mcmc" => Ok(TipSelection::default()),
+            Some(("mcmc", alpha)) => alpha
+                .parse::<f64>()
+                .ok()
+                .filter(|a| a.is_finite() && *a >= 0.0)
+                .map(|alpha| TipSelection::Mcmc { alpha })
+                .ok_or_else(|| format!("invalid alpha: {}", alpha)),
+            _ => Err(format!("unknown tip selection strategy: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::DagVertex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Genesis, then a heavy branch of `heavy` vertices and a single light tip
+    fn forked_dag(heavy: u64) -> (Dag, Hash, Hash) {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+
+        let light = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1_000);
+        let light_id = light.id;
+        dag.insert(DagVertex::new(light, 1)).unwrap();
+
+        let mut last = genesis_id;
+        for i in 1..=heavy {
+            let tx = Transaction::relay_reward(&kp, 1, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        (dag, light_id, last)
+    }
+
+    #[test]
+    fn test_selection_replays_from_its_proof() {
+        let (dag, _, _) = forked_dag(10);
+        let kp = KeyPair::generate();
+        let selector = WeightedRandomWalk::default();
+        let nonce = dag.len() as u64;
+        let (proof, mut rng) = prove_selection(&kp, crate::MAINNET_ID, nonce);
+        let parents = selector.select(&dag, 3, &mut rng);
+        let tx = Transaction::key_announcement(&kp, parents.clone(), nonce).with_selection_proof(proof, &kp);
+
+        let output = selection_output(&tx.data).unwrap();
+        assert_eq!(selector.select(&dag, 3, &mut selection_rng(&output)), parents);
+
+        // The proof is bound to the nonce and the sender
+        let mut renonced = tx.data.clone();
+        renonced.nonce += 1;
+        assert_eq!(selection_output(&renonced), None);
+        let mut resent = tx.data.clone();
+        resent.sender = KeyPair::generate().public_key;
+        assert_eq!(selection_output(&resent), None);
+    }
+
+    #[test]
+    fn test_empty_dag() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let parents = WeightedRandomWalk::default().select(&Dag::new(), 2, &mut rng);
+        assert_eq!(parents, [Hash::zero(), Hash::zero()]);
+    }
+
+    #[test]
+    fn test_greedy_walk_follows_heavy_branch() {
+        let (dag, light, heavy) = forked_dag(10);
+        let mut rng = StdRng::seed_from_u64(7);
+        let selector = WeightedRandomWalk::new(5.0);
+        for _ in 0..20 {
+            let parents = selector.select(&dag, 2, &mut rng);
+            assert_eq!(parents, [heavy, heavy]);
+            assert!(!parents.contains(&light));
+        }
+    }
+
+    #[test]
+    fn test_unbiased_walk_reaches_every_tip() {
+        let (dag, light, heavy) = forked_dag(10);
+        let mut rng = StdRng::seed_from_u64(7);
+        let selector = WeightedRandomWalk::new(0.0);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..50 {
+            seen.extend(selector.select(&dag, 2, &mut rng));
+        }
+        assert!(seen.contains(&light));
+        assert!(seen.contains(&heavy));
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!("deepest".parse(), Ok(TipSelection::Deepest));
+        assert_eq!("uniform".parse(), Ok(TipSelection::Uniform));
+        assert_eq!("mcmc".parse(), Ok(TipSelection::default()));
+        assert_eq!("mcmc:0.5".parse(), Ok(TipSelection::Mcmc { alpha: 0.5 }));
+        assert!("mcmc:-1".parse::<TipSelection>().is_err());
+        assert!("chain".parse::<TipSelection>().is_err());
+        assert_eq!(TipSelection::Mcmc { alpha: 0.5 }.to_string().parse(), Ok(TipSelection::Mcmc { alpha: 0.5 }));
+    }
+}
@@ -0,0 +1,432 @@
+use crate::crypto::Hash;
+use crate::dag::vertex::Dag;
+use crate::network::peer::PeerId;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Conflict voting settings (`voting` in config.json)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VotingConfig {
+    pub enabled: bool,
+    /// Peers queried each round
+    pub query_size: usize,
+    /// How long a round waits for answers, in milliseconds
+    pub round_ms: u64,
+    /// Share of answers the other side needs in a vote's first round to
+    /// change its opinion
+    pub first_threshold: f64,
+    /// Bounds of the random threshold of later rounds
+    pub min_threshold: f64,
+    pub max_threshold: f64,
+    /// Rounds in a row an opinion must hold to be final
+    pub finalization_rounds: u32,
+    /// Rounds after which a vote is final whatever its opinion
+    pub max_rounds: u32,
+}
+
+impl Default for VotingConfig {
+    fn default() -> Self {
+        VotingConfig {
+            enabled: true,
+            query_size: 8,
+            round_ms: 1_000,
+            first_threshold: 0.67,
+            min_threshold: 0.5,
+            max_threshold: 0.67,
+            finalization_rounds: 5,
+            max_rounds: 30,
+        }
+    }
+}
+
+/// A peer's answer about one conflict: the member it prefers, if it knows
+/// the conflict
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opinion {
+    pub conflict: Hash,
+    pub preferred: Option<Hash>,
+}
+
+/// This node's vote on one conflict set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Vote {
+    /// Members of the set
+    pub members: Vec<Hash>,
+    /// Member this node currently prefers
+    pub opinion: Hash,
+    /// Rounds that got any answers
+    pub rounds: u32,
+    /// Rounds in a row the opinion has held
+    pub unchanged: u32,
+    /// Settled: no longer queried or changed
+    pub finalized: bool,
+}
+
+/// Answers gathered in the round in flight
+#[derive(Debug, Default)]
+struct Round {
+    queried: HashSet<PeerId>,
+    answered: HashSet<PeerId>,
+    /// Per conflict, answers per preferred member
+    tallies: HashMap<Hash, HashMap<Hash, usize>>,
+}
+
+/// Fast probabilistic consensus on which side of each double-spend wins
+///
+/// Cumulative weight alone lets nodes that saw a conflict's branches grow
+/// in a different order disagree for a while on who wins. Voting settles
+/// it: each round the node asks a random sample of peers which member of
+/// every open conflict they prefer, and takes up the side a large enough
+/// share of answers favour. The share needed is 0.67 in a vote's first
+/// round and random between the bounds in later ones, so an attacker can't
+/// keep the network split by steering answers to sit just at the threshold.
+/// An opinion that holds for enough rounds in a row is final.
+///
+/// Votes are named by their set's lowest member id. The preferred members
+/// feed conflict resolution ([`Dag::set_conflict_preferences`]), ranking
+/// ahead of weight; a member that became final settles its vote at once.
+#[derive(Debug, Default)]
+pub struct ConflictVoting {
+    config: VotingConfig,
+    votes: BTreeMap<Hash, Vote>,
+    round: Option<Round>,
+}
+
+impl ConflictVoting {
+    pub fn new(config: VotingConfig) -> Self {
+        ConflictVoting {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &VotingConfig {
+        &self.config
+    }
+
+    /// Every vote, by conflict
+    pub fn votes(&self) -> impl Iterator<Item = (&Hash, &Vote)> {
+        self.votes.iter()
+    }
+
+    /// Open votes on the DAG's new conflict sets and drop those on sets
+    /// that are gone
+    ///
+    /// A new vote starts out preferring the heaviest member.
+    pub fn sync(&mut self, dag: &Dag) {
+        let mut live = HashSet::new();
+        for set in dag.conflicts().sets() {
+            let members: Vec<Hash> = set
+                .members
+                .iter()
+                .filter(|m| !dag.conflicts().is_superseded(m))
+                .copied()
+                .collect();
+            let Some(key) = members.iter().min().copied() else {
+                continue;
+            };
+            if members.len() < 2 {
+                continue;
+            }
+            live.insert(key);
+            let heaviest = *members
+                .iter()
+                .min_by_key(|m| (std::cmp::Reverse(dag.get(m).map_or(0, |v| v.cumulative_weight)), *m.as_bytes()))
+                .expect("at least two members");
+            let vote = self.votes.entry(key).or_insert_with(|| Vote {
+                members: Vec::new(),
+                opinion: heaviest,
+                rounds: 0,
+                unchanged: 0,
+                finalized: false,
+            });
+            vote.members = members;
+            // Finality outranks any vote
+            if let Some(settled) = vote.members.iter().find(|m| dag.get(m).is_some_and(|v| v.is_final)) {
+                vote.opinion = *settled;
+                vote.finalized = true;
+            }
+        }
+        self.votes.retain(|key, _| live.contains(key));
+    }
+
+    /// Our answers to a peer's query
+    pub fn answer(&self, conflicts: &[Hash], dag: &Dag) -> Vec<Opinion> {
+        conflicts
+            .iter()
+            .map(|conflict| Opinion {
+                conflict: *conflict,
+                preferred: self.opinion_on(conflict, dag),
+            })
+            .collect()
+    }
+
+    /// The member we prefer in the conflict set containing `id`: our vote,
+    /// or before we have one the member currently winning
+    pub fn opinion_on(&self, id: &Hash, dag: &Dag) -> Option<Hash> {
+        let set = dag.conflicts().set_of(id)?;
+        let key = set.members.iter().filter(|m| !dag.conflicts().is_superseded(m)).min()?;
+        self.votes
+            .get(key)
+            .map(|vote| vote.opinion)
+            .or_else(|| set.accepted.first().copied())
+    }
+
+    /// Start a round: returns the peers to query and the conflicts to ask
+    /// them about, or `None` with nothing to ask or no one to ask it of
+    pub fn start_round<R: Rng + ?Sized>(
+        &mut self,
+        peers: &[PeerId],
+        rng: &mut R,
+    ) -> Option<(Vec<PeerId>, Vec<Hash>)> {
+        let open: Vec<Hash> = self.votes.iter().filter(|(_, v)| !v.finalized).map(|(k, _)| *k).collect();
+        if open.is_empty() || peers.is_empty() {
+            return None;
+        }
+        let sample: Vec<PeerId> = peers.choose_multiple(rng, self.config.query_size.max(1)).cloned().collect();
+        self.round = Some(Round {
+            queried: sample.iter().cloned().collect(),
+            ..Round::default()
+        });
+        Some((sample, open))
+    }
+
+    /// Count a peer's answers to the round in flight; answers nobody asked
+    /// for, second answers and votes for non-members are ignored
+    pub fn record(&mut self, peer: &PeerId, opinions: &[Opinion]) {
+        let Some(round) = &mut self.round else {
+            return;
+        };
+        if !round.queried.contains(peer) || !round.answered.insert(peer.clone()) {
+            return;
+        }
+        let mut counted = HashSet::new();
+        for opinion in opinions {
+            let (Some(vote), Some(preferred)) = (self.votes.get(&opinion.conflict), opinion.preferred) else {
+                continue;
+            };
+            if vote.finalized || !vote.members.contains(&preferred) || !counted.insert(opinion.conflict) {
+                continue;
+            }
+            *round.tallies.entry(opinion.conflict).or_default().entry(preferred).or_default() += 1;
+        }
+    }
+
+    /// Close the round in flight and update opinions from its answers;
+    /// returns whether any opinion changed
+    pub fn finish_round<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let Some(round) = self.round.take() else {
+            return false;
+        };
+        let config = self.config;
+        let threshold = if config.max_threshold > config.min_threshold {
+            rng.gen_range(config.min_threshold..=config.max_threshold)
+        } else {
+            config.min_threshold
+        };
+        let mut changed = false;
+        for (key, vote) in self.votes.iter_mut().filter(|(_, v)| !v.finalized) {
+            // A round nobody answered doesn't count
+            let Some(tally) = round.tallies.get(key) else {
+                continue;
+            };
+            let answers: usize = tally.values().sum();
+            let (leader, count) = tally
+                .iter()
+                .max_by_key(|(member, count)| (**count, **member == vote.opinion, std::cmp::Reverse(*member.as_bytes())))
+                .expect("a tally has answers");
+            let needed = if vote.rounds == 0 { config.first_threshold } else { threshold };
+            if *leader != vote.opinion && *count as f64 / answers as f64 > needed {
+                vote.opinion = *leader;
+                vote.unchanged = 0;
+                changed = true;
+            } else {
+                vote.unchanged += 1;
+            }
+            vote.rounds += 1;
+            vote.finalized = vote.unchanged >= config.finalization_rounds || vote.rounds >= config.max_rounds;
+        }
+        changed
+    }
+
+    /// Members preferred by every vote, for conflict resolution
+    pub fn preferred(&self) -> HashSet<Hash> {
+        self.votes.values().map(|vote| vote.opinion).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::DagVertex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A DAG where `kp` spends its 1_000_000 twice, to two payees; returns
+    /// the two spends
+    fn double_spend() -> (Dag, KeyPair, [Transaction; 2]) {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let reward = Transaction::relay_reward(&kp, 1_000_000, [genesis_id, genesis_id], 1);
+        let parents = [reward.id, reward.id];
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+        let spends = [2, 3].map(|nonce| {
+            let payee = KeyPair::generate().public_key;
+            Transaction::transfer(&kp, payee, 800_000, parents, nonce)
+        });
+        for spend in &spends {
+            dag.insert(DagVertex::new(spend.clone(), 2)).unwrap();
+        }
+        (dag, kp, spends)
+    }
+
+    fn peers(n: usize) -> Vec<PeerId> {
+        (0..n).map(|_| PeerId::new(KeyPair::generate().public_key)).collect()
+    }
+
+    fn say(conflict: Hash, preferred: Hash) -> Vec<Opinion> {
+        vec![Opinion { conflict, preferred: Some(preferred) }]
+    }
+
+    #[test]
+    fn test_votes_follow_the_dag() {
+        let (mut dag, _, [a, b]) = double_spend();
+        let mut voting = ConflictVoting::new(VotingConfig::default());
+        voting.sync(&dag);
+        let (key, vote) = voting.votes().next().unwrap();
+        assert_eq!(*key, a.id.min(b.id));
+        // Equal weights: the lowest id is heaviest
+        assert_eq!(vote.opinion, a.id.min(b.id));
+        assert_eq!(voting.opinion_on(&a.id, &dag), Some(vote.opinion));
+        assert_eq!(voting.answer(&[b.id], &dag)[0].preferred, Some(vote.opinion));
+
+        // Finality settles it
+        let loser = a.id.max(b.id);
+        let approvers: Vec<KeyPair> = (0..crate::FINALITY_THRESHOLD).map(|_| KeyPair::generate()).collect();
+        let mut last = loser;
+        for (i, kp) in approvers.iter().enumerate() {
+            let tx = Transaction::relay_reward(kp, 1, [last, last], i as u64);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, 3 + i as u64)).unwrap();
+        }
+        voting.sync(&dag);
+        let vote = voting.votes().next().unwrap().1;
+        assert!(vote.finalized);
+        assert_eq!(vote.opinion, loser);
+        assert!(voting.start_round(&peers(3), &mut crate::testkit::seeded_rng()).is_none());
+    }
+
+    #[test]
+    fn test_majority_turns_and_settles_the_vote() {
+        let (dag, _, [a, b]) = double_spend();
+        let config = VotingConfig { finalization_rounds: 2, ..VotingConfig::default() };
+        let mut voting = ConflictVoting::new(config);
+        voting.sync(&dag);
+        let key = *voting.votes().next().unwrap().0;
+        let (mine, theirs) = if key == a.id { (a.id, b.id) } else { (b.id, a.id) };
+        let mut rng = StdRng::seed_from_u64(7);
+        let all = peers(4);
+
+        // Three of four disagree: past the first round's 0.67
+        let (queried, conflicts) = voting.start_round(&all, &mut rng).unwrap();
+        assert_eq!((queried.len(), conflicts), (4, vec![key]));
+        for (i, peer) in all.iter().enumerate() {
+            voting.record(peer, &say(key, if i == 0 { mine } else { theirs }));
+        }
+        assert!(voting.finish_round(&mut rng));
+        assert_eq!(voting.opinion_on(&key, &dag), Some(theirs));
+        assert!(voting.preferred().contains(&theirs));
+
+        // It holds for two rounds and is final
+        for _ in 0..2 {
+            voting.start_round(&all, &mut rng).unwrap();
+            for peer in &all {
+                voting.record(peer, &say(key, theirs));
+            }
+            assert!(!voting.finish_round(&mut rng));
+        }
+        assert!(voting.votes().next().unwrap().1.finalized);
+        assert!(voting.start_round(&all, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_stray_answers_are_ignored() {
+        let (dag, _, [a, b]) = double_spend();
+        let mut voting = ConflictVoting::new(VotingConfig { query_size: 1, ..VotingConfig::default() });
+        voting.sync(&dag);
+        let key = *voting.votes().next().unwrap().0;
+        let other = if key == a.id { b.id } else { a.id };
+        let mut rng = StdRng::seed_from_u64(1);
+        let all = peers(3);
+
+        let (queried, _) = voting.start_round(&all, &mut rng).unwrap();
+        let asked = &queried[0];
+        let stranger = all.iter().find(|p| *p != asked).unwrap();
+        // Unasked, stuffed with repeats, or for something else entirely
+        voting.record(stranger, &say(key, other));
+        voting.record(asked, &[say(key, Hash::digest(b"not a member")), say(key, key), say(key, other)].concat());
+        voting.record(asked, &say(key, other));
+        assert!(!voting.finish_round(&mut rng));
+        assert_eq!(voting.opinion_on(&key, &dag), Some(key));
+        assert_eq!(voting.votes().next().unwrap().1.rounds, 1);
+
+        // A round with no answers doesn't count
+        voting.start_round(&all, &mut rng).unwrap();
+        voting.finish_round(&mut rng);
+        assert_eq!(voting.votes().next().unwrap().1.rounds, 1);
+    }
+
+    #[test]
+    fn test_split_network_agrees() {
+        let (dag, _, [a, b]) = double_spend();
+        let config = VotingConfig::default();
+        let mut rng = StdRng::seed_from_u64(42);
+        let ids = peers(30);
+        let mut nodes: Vec<ConflictVoting> = (0..ids.len()).map(|_| ConflictVoting::new(config)).collect();
+        let key = a.id.min(b.id);
+        // Seeing the branches in different orders left the network split
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.sync(&dag);
+            node.votes.get_mut(&key).unwrap().opinion = if i % 5 < 3 { a.id } else { b.id };
+        }
+
+        for _ in 0..config.max_rounds {
+            let opinions: Vec<Hash> = nodes.iter().map(|n| n.opinion_on(&key, &dag).unwrap()).collect();
+            for (i, node) in nodes.iter_mut().enumerate() {
+                let others: Vec<PeerId> = ids.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, p)| p.clone()).collect();
+                let Some((queried, conflicts)) = node.start_round(&others, &mut rng) else {
+                    continue;
+                };
+                for peer in &queried {
+                    let j = ids.iter().position(|p| p == peer).unwrap();
+                    node.record(peer, &say(conflicts[0], opinions[j]));
+                }
+                node.finish_round(&mut rng);
+            }
+        }
+        let settled: HashSet<Hash> = nodes.iter().map(|n| n.opinion_on(&key, &dag).unwrap()).collect();
+        assert_eq!(settled.len(), 1);
+        assert!(nodes.iter().all(|n| n.votes[&key].finalized));
+    }
+
+    #[test]
+    fn test_preference_feeds_resolution() {
+        let (mut dag, kp, [a, b]) = double_spend();
+        let winner = dag.conflicts().set_of(&a.id).unwrap().accepted[0];
+        let loser = if winner == a.id { b.id } else { a.id };
+        assert!(dag.conflicts().is_rejected(&loser));
+
+        dag.set_conflict_preferences([loser].into_iter().collect());
+        assert!(dag.conflicts().is_rejected(&winner));
+        assert!(!dag.conflicts().is_rejected(&loser));
+        assert_eq!(dag.get_balance(&kp.public_key), 200_000);
+    }
+}
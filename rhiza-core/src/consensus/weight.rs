@@ -1,5 +1,26 @@
 use crate::crypto::Hash;
 use crate::dag::vertex::Dag;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A vertex whose incremental cumulative weight disagrees with a
+/// recomputation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WeightDivergence {
+    pub id: Hash,
+    /// Weight the DAG holds
+    pub incremental: u64,
+    /// Weight recomputed from scratch
+    pub recomputed: u64,
+}
+
+/// Result of re-deriving the weights of one sub-DAG
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubDagCheck {
+    /// Vertices whose weight was recomputed
+    pub checked: usize,
+    pub divergences: Vec<WeightDivergence>,
+}
 
 /// Cumulative weight calculation for DAG vertices
 ///
@@ -30,7 +51,7 @@ impl WeightCalculator {
                     }
                     if current != id {
                         if let Some(w) = weights.get_mut(&current) {
-                            *w += 1;
+                            *w = w.saturating_add(1);
                         }
                     }
                     if let Some(v) = dag.get(&current) {
@@ -47,6 +68,56 @@ impl WeightCalculator {
         weights
     }
 
+    /// Recompute the weights of `root` and every vertex approving it, and
+    /// compare them with the DAG's incremental ones
+    ///
+    /// A vertex's weight only counts its approvers, so the approvers of
+    /// `root` are a sub-DAG whose weights can be checked on their own.
+    /// Returns `None` when it holds more than `max_vertices` vertices.
+    pub fn verify_subdag(dag: &Dag, root: &Hash, max_vertices: usize) -> Option<SubDagCheck> {
+        dag.get(root)?;
+        let mut cone = HashSet::from([*root]);
+        let mut queue = vec![*root];
+        while let Some(id) = queue.pop() {
+            for child in dag.children(&id) {
+                if cone.insert(*child) {
+                    if cone.len() > max_vertices {
+                        return None;
+                    }
+                    queue.push(*child);
+                }
+            }
+        }
+
+        let mut weights: HashMap<Hash, u64> = cone.iter().map(|id| (*id, 1)).collect();
+        for id in &cone {
+            let mut stack: Vec<Hash> = dag.get(id).map_or(Vec::new(), |v| v.parents().to_vec());
+            let mut visited = HashSet::new();
+            while let Some(current) = stack.pop() {
+                if !cone.contains(&current) || !visited.insert(current) {
+                    continue;
+                }
+                *weights.get_mut(&current).expect("cone members are weighed") += 1;
+                if let Some(v) = dag.get(&current) {
+                    stack.extend_from_slice(v.parents());
+                }
+            }
+        }
+
+        let mut divergences: Vec<WeightDivergence> = weights
+            .into_iter()
+            .filter_map(|(id, recomputed)| {
+                let incremental = dag.get(&id)?.cumulative_weight;
+                (incremental != recomputed).then_some(WeightDivergence { id, incremental, recomputed })
+            })
+            .collect();
+        divergences.sort_by_key(|d| d.id);
+        Some(SubDagCheck {
+            checked: cone.len(),
+            divergences,
+        })
+    }
+
     /// Get the confirmation score of a transaction (0.0 to 1.0)
     /// 1.0 means the transaction is fully confirmed (final)
     pub fn confirmation_score(cumulative_weight: u64) -> f64 {
@@ -78,6 +149,29 @@ mod tests {
         assert_eq!(*weights.get(&genesis_id).unwrap(), 2);
     }
 
+    #[test]
+    fn test_verify_subdag() {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        // A diamond: b and c approve a, d approves both
+        let a = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1);
+        let b = Transaction::relay_reward(&kp, 1, [a.id, a.id], 2);
+        let c = Transaction::relay_reward(&kp, 1, [a.id, genesis_id], 3);
+        let d = Transaction::relay_reward(&kp, 1, [b.id, c.id], 4);
+        for (depth, tx) in [&a, &b, &c, &d].into_iter().enumerate() {
+            dag.insert(DagVertex::new(tx.clone(), depth as u64 + 1)).unwrap();
+        }
+
+        let check = WeightCalculator::verify_subdag(&dag, &a.id, 10).unwrap();
+        assert_eq!(check, SubDagCheck { checked: 4, divergences: Vec::new() });
+        // Too big to check within the budget
+        assert_eq!(WeightCalculator::verify_subdag(&dag, &genesis_id, 4), None);
+        assert_eq!(WeightCalculator::verify_subdag(&dag, &Hash::digest(b"unknown"), 10), None);
+    }
+
     #[test]
     fn test_confirmation_score() {
         assert_eq!(WeightCalculator::confirmation_score(0), 0.0);
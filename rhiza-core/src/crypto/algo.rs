@@ -0,0 +1,126 @@
+use crate::crypto::{Hash, KeyPair, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Hash function a transaction's ID is computed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    #[default]
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Tag identifying the algorithm in signing bytes; fixed forever
+    pub fn signing_tag(self) -> u8 {
+        match self {
+            HashAlgo::Blake3 => 0,
+        }
+    }
+
+    pub fn from_signing_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(HashAlgo::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> Hash {
+        match self {
+            HashAlgo::Blake3 => Hash::digest(data),
+        }
+    }
+}
+
+/// Signature scheme a transaction's sender and fee payer sign with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigAlgo {
+    #[default]
+    Ed25519,
+}
+
+impl SigAlgo {
+    /// Tag identifying the scheme in signing bytes; fixed forever
+    pub fn signing_tag(self) -> u8 {
+        match self {
+            SigAlgo::Ed25519 => 0,
+        }
+    }
+
+    pub fn from_signing_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(SigAlgo::Ed25519),
+            _ => None,
+        }
+    }
+
+    pub fn sign(self, keypair: &KeyPair, message: &[u8]) -> Signature {
+        match self {
+            SigAlgo::Ed25519 => keypair.sign(message),
+        }
+    }
+
+    pub fn verify(self, key: &PublicKey, message: &[u8], signature: &Signature) -> bool {
+        match self {
+            SigAlgo::Ed25519 => key.verify(message, signature),
+        }
+    }
+}
+
+/// The hash and signature algorithms a transaction names in its signed data
+///
+/// Every transaction so far uses BLAKE3 and Ed25519, the only pair
+/// [`is_enabled`](Algorithms::is_enabled) accepts. Moving to others, say
+/// post-quantum signatures, means adding a variant and enabling it from
+/// some agreed point on; nothing else about the transaction format has to
+/// change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Algorithms {
+    pub hash: HashAlgo,
+    pub sig: SigAlgo,
+}
+
+impl Algorithms {
+    /// The algorithms new transactions are made with
+    pub const CURRENT: Algorithms = Algorithms { hash: HashAlgo::Blake3, sig: SigAlgo::Ed25519 };
+
+    /// Whether transactions may use these algorithms yet
+    pub fn is_enabled(self) -> bool {
+        matches!(self.hash, HashAlgo::Blake3) && matches!(self.sig, SigAlgo::Ed25519)
+    }
+}
+
+impl fmt::Display for Algorithms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hash = match self.hash {
+            HashAlgo::Blake3 => "BLAKE3",
+        };
+        let sig = match self.sig {
+            SigAlgo::Ed25519 => "Ed25519",
+        };
+        write!(f, "{}/{}", hash, sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dag::transaction::Transaction;
+
+    #[test]
+    fn test_transactions_default_to_current_algorithms() {
+        let tx = Transaction::genesis(&KeyPair::generate());
+        assert_eq!(tx.data.algorithms, Algorithms::CURRENT);
+        assert!(tx.data.algorithms.is_enabled());
+        assert_eq!(tx.data.algorithms.to_string(), "BLAKE3/Ed25519");
+
+        let mut json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["data"]["algorithms"], serde_json::json!({"hash": "blake3", "sig": "ed25519"}));
+        // JSON that doesn't name them reads as the current ones
+        json["data"].as_object_mut().unwrap().remove("algorithms");
+        let read: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(read.data.algorithms, Algorithms::CURRENT);
+        assert!(read.verify_id() && read.verify_signature());
+    }
+}
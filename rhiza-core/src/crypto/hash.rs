@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A BLAKE3 hash value (32 bytes)
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash(#[serde(with = "hash_serde")] pub(crate) [u8; 32]);
 
 impl Hash {
@@ -40,6 +40,13 @@ impl Hash {
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         Hash(bytes)
     }
+
+    /// Parse from a 64-character hex string
+    pub fn from_hex(s: &str) -> Result<Self, hex::FromHexError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(Hash(bytes))
+    }
 }
 
 impl fmt::Debug for Hash {
@@ -54,7 +61,7 @@ impl fmt::Display for Hash {
     }
 }
 
-mod hash_serde {
+pub(crate) mod hash_serde {
     use serde::{self, Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
@@ -117,6 +124,14 @@ mod tests {
         assert!(!h.is_zero());
     }
 
+    #[test]
+    fn test_hash_from_hex() {
+        let h = Hash::digest(b"test");
+        assert_eq!(Hash::from_hex(&h.to_string()).unwrap(), h);
+        assert!(Hash::from_hex("abcd").is_err());
+        assert!(Hash::from_hex(&"zz".repeat(32)).is_err());
+    }
+
     #[test]
     fn test_hash_serialization() {
         let h = Hash::digest(b"test");
@@ -2,16 +2,21 @@ use ed25519_dalek::{
     SigningKey, VerifyingKey, Signer, Verifier,
     Signature as DalekSignature,
 };
+use super::mnemonic::{Mnemonic, MnemonicError, DEFAULT_WORD_COUNT};
+use hmac::{Hmac, Mac};
 use rand::rngs::OsRng;
+use sha2::Sha512;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// Wrapper around Ed25519 public key
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PublicKey(#[serde(with = "pub_key_serde")] pub(crate) [u8; 32]);
 
-/// Wrapper around Ed25519 secret key bytes
-#[derive(Clone, Serialize, Deserialize)]
+/// Wrapper around Ed25519 secret key bytes (wiped from memory on drop)
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct SecretKey(#[serde(with = "hex_serde")] pub(crate) [u8; 32]);
 
 /// Wrapper around Ed25519 signature
@@ -19,6 +24,8 @@ pub struct SecretKey(#[serde(with = "hex_serde")] pub(crate) [u8; 32]);
 pub struct Signature(#[serde(with = "hex_serde_64")] pub(crate) [u8; 64]);
 
 /// A keypair consisting of a secret key and its corresponding public key
+///
+/// The signing key is wiped from memory when the keypair is dropped.
 #[derive(Clone)]
 pub struct KeyPair {
     signing_key: SigningKey,
@@ -46,9 +53,65 @@ impl KeyPair {
         }
     }
 
-    /// Get the secret key bytes
-    pub fn secret_bytes(&self) -> [u8; 32] {
-        self.signing_key.to_bytes()
+    /// Derive a keypair from a BIP39 mnemonic and optional passphrase
+    ///
+    /// The secret is the SLIP-0010 ed25519 master key of the BIP39 seed, so
+    /// the same phrase restores the same key in any compatible wallet.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, MnemonicError> {
+        let seed = Mnemonic::from_phrase(phrase)?.to_seed(passphrase)?;
+        Ok(Self::from_seed(&*seed))
+    }
+
+    /// Generate a new keypair together with the 24-word mnemonic that restores it
+    pub fn generate_with_mnemonic() -> (Self, Mnemonic) {
+        let mnemonic = Mnemonic::generate(DEFAULT_WORD_COUNT).expect("default word count is valid");
+        let seed = mnemonic.to_seed("").expect("empty passphrase is ASCII");
+        (Self::from_seed(&*seed), mnemonic)
+    }
+
+    /// Derive the keypair at `path` of a BIP39 mnemonic's SLIP-0010 tree,
+    /// e.g. to import the seed of another Ed25519 wallet
+    pub fn from_mnemonic_at(phrase: &str, passphrase: &str, path: &DerivationPath) -> Result<Self, MnemonicError> {
+        let seed = Mnemonic::from_phrase(phrase)?.to_seed(passphrase)?;
+        Ok(ExtendedKey::master(&*seed).derive(path.indices()).keypair())
+    }
+
+    /// SLIP-0010 ed25519 master key of a BIP39 seed
+    fn from_seed(seed: &[u8]) -> Self {
+        ExtendedKey::master(seed).keypair()
+    }
+
+    /// Derive a stable keypair from a human-readable seed, e.g. `"alice"`
+    ///
+    /// The secret is a public function of the seed, so anyone can recompute
+    /// it. Only for tests, documentation examples and devnets; available
+    /// with the `test-keys` feature.
+    #[cfg(any(test, feature = "test-keys"))]
+    pub fn from_seed_phrase_for_tests(seed: &str) -> Self {
+        let secret = Zeroizing::new(blake3::derive_key("rhiza test keys v1", seed.as_bytes()));
+        Self::from_secret_bytes(&secret)
+    }
+
+    /// Derive the `index`-th child keypair for `context`
+    ///
+    /// Deterministic, so children can always be re-derived from this key's
+    /// secret; different contexts yield unrelated keys.
+    pub fn derive_subkey(&self, context: &str, index: u64) -> Self {
+        let mut material = Zeroizing::new([0u8; 40]);
+        material[..32].copy_from_slice(&*self.secret_bytes());
+        material[32..].copy_from_slice(&index.to_le_bytes());
+        let secret = Zeroizing::new(blake3::derive_key(context, &*material));
+        Self::from_secret_bytes(&secret)
+    }
+
+    /// Get the secret key bytes (wiped when the returned value is dropped)
+    pub fn secret_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.signing_key.to_bytes())
+    }
+
+    /// Get the secret key as hex, for export (wiped when dropped)
+    pub fn secret_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(*self.secret_bytes()))
     }
 
     /// Sign a message
@@ -56,6 +119,219 @@ impl KeyPair {
         let sig = self.signing_key.sign(message);
         Signature(sig.to_bytes())
     }
+
+    /// Pin the memory holding the signing key so it is never swapped to disk
+    ///
+    /// The lock covers the keypair's current address, so call this once the
+    /// keypair has reached its final home (e.g. behind an `Arc`). Fails when
+    /// the platform has no `mlock` or `RLIMIT_MEMLOCK` is too low.
+    pub fn lock_in_memory(&self) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            let ptr = &self.signing_key as *const SigningKey as *const libc::c_void;
+            // SAFETY: mlock only changes paging of the range, which is a
+            // live field of `self`; it neither reads nor writes the memory
+            let ret = unsafe { libc::mlock(ptr, std::mem::size_of::<SigningKey>()) };
+            if ret == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "memory locking is not supported on this platform",
+            ))
+        }
+    }
+
+    /// Derive the X25519 secret for Diffie-Hellman key agreement
+    ///
+    /// This is the same scalar Ed25519 signs with, so the X25519 public key
+    /// matches `PublicKey::to_x25519` of this keypair.
+    pub fn x25519_secret(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.signing_key.to_scalar_bytes())
+    }
+}
+
+/// Marks a hardened derivation index (the only kind ed25519 supports)
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// BIP44 purpose field of Rhiza derivation paths
+const PURPOSE: u32 = 44;
+
+/// Coin type of Rhiza derivation paths (not registered with SLIP-0044)
+pub const COIN_TYPE: u32 = 7471;
+
+/// Derivation path of key `index` in `account`: `m/44'/7471'/account'/index'`
+pub fn account_path(account: u32, index: u32) -> [u32; 4] {
+    [PURPOSE | HARDENED, COIN_TYPE | HARDENED, account | HARDENED, index | HARDENED]
+}
+
+/// SLIP-0044 coin type of Solana
+const SOLANA_COIN_TYPE: u32 = 501;
+
+/// SLIP-0044 coin type of Stellar
+const STELLAR_COIN_TYPE: u32 = 148;
+
+/// Where in a seed's SLIP-0010 tree a wallet's main key sits
+///
+/// Rhiza wallets sign with the master key itself. Other Ed25519 wallets
+/// derive theirs under their own SLIP-0044 coin type, so a seed from one of
+/// them restores the same account here only at the same path. Every index is
+/// hardened, the only kind ed25519 derives.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The master key, `m`
+    pub fn master() -> Self {
+        DerivationPath(Vec::new())
+    }
+
+    /// Account `account` of Solana wallets (Phantom, Solflare, Ledger):
+    /// `m/44'/501'/account'/0'`
+    pub fn solana(account: u32) -> Self {
+        DerivationPath(vec![PURPOSE | HARDENED, SOLANA_COIN_TYPE | HARDENED, account | HARDENED, HARDENED])
+    }
+
+    /// Account `account` of Stellar wallets (SEP-0005): `m/44'/148'/account'`
+    pub fn stellar(account: u32) -> Self {
+        DerivationPath(vec![PURPOSE | HARDENED, STELLAR_COIN_TYPE | HARDENED, account | HARDENED])
+    }
+
+    /// Child indices from the master key, hardened bit set
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index & !HARDENED)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a path like `m/44'/501'/0'/0'` (`h` marks hardened too), or
+/// `solana`, `stellar`, `solana:<account>` or `stellar:<account>`
+impl FromStr for DerivationPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| {
+            n.parse::<u32>()
+                .ok()
+                .filter(|n| *n < HARDENED)
+                .ok_or_else(|| format!("invalid index: {}", n))
+        };
+        match s.split_once(':') {
+            None if s == "solana" => return Ok(DerivationPath::solana(0)),
+            None if s == "stellar" => return Ok(DerivationPath::stellar(0)),
+            Some(("solana", n)) => return number(n).map(DerivationPath::solana),
+            Some(("stellar", n)) => return number(n).map(DerivationPath::stellar),
+            _ => {}
+        }
+
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(format!("unknown derivation path: {}", s));
+        }
+        parts
+            .map(|part| {
+                let index = part
+                    .strip_suffix('\'')
+                    .or_else(|| part.strip_suffix('h'))
+                    .ok_or_else(|| format!("ed25519 only derives hardened keys, so {} needs a ' after it", part))?;
+                number(index).map(|index| index | HARDENED)
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl TryFrom<String> for DerivationPath {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<DerivationPath> for String {
+    fn from(path: DerivationPath) -> Self {
+        path.to_string()
+    }
+}
+
+/// A SLIP-0010 ed25519 extended key: a secret key plus the chain code that
+/// derives its children
+///
+/// Wiped from memory on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct ExtendedKey {
+    secret: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// Master key of a BIP39 seed
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(b"ed25519 seed", &[seed])
+    }
+
+    /// Derive the hardened child `index` (the hardened bit is implied)
+    pub fn child(&self, index: u32) -> Self {
+        let index = index | HARDENED;
+        Self::from_hmac(&self.chain_code, &[&[0u8], &self.secret, &index.to_be_bytes()])
+    }
+
+    /// Derive along a path of child indices from this key
+    pub fn derive(&self, path: &[u32]) -> Self {
+        path.iter().fold(self.clone(), |key, &index| key.child(index))
+    }
+
+    /// Keypair of the key at `account_path(account, index)` below this one
+    pub fn account_key(&self, account: u32, index: u32) -> KeyPair {
+        self.derive(&account_path(account, index)).keypair()
+    }
+
+    /// The signing keypair of this key
+    pub fn keypair(&self) -> KeyPair {
+        KeyPair::from_secret_bytes(&self.secret)
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length");
+        for chunk in data {
+            mac.update(chunk);
+        }
+        let mut digest = Zeroizing::new([0u8; 64]);
+        digest.copy_from_slice(&mac.finalize().into_bytes());
+        let mut extended = ExtendedKey {
+            secret: [0u8; 32],
+            chain_code: [0u8; 32],
+        };
+        extended.secret.copy_from_slice(&digest[..32]);
+        extended.chain_code.copy_from_slice(&digest[32..]);
+        extended
+    }
+}
+
+impl fmt::Debug for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExtendedKey(<redacted>)")
+    }
 }
 
 impl PublicKey {
@@ -77,6 +353,12 @@ impl PublicKey {
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         PublicKey(bytes)
     }
+
+    /// Convert to the equivalent X25519 (Montgomery) public key
+    pub fn to_x25519(&self) -> Option<[u8; 32]> {
+        let verifying_key = VerifyingKey::from_bytes(&self.0).ok()?;
+        Some(verifying_key.to_montgomery().to_bytes())
+    }
 }
 
 impl fmt::Debug for PublicKey {
@@ -91,6 +373,13 @@ impl fmt::Display for PublicKey {
     }
 }
 
+impl Signature {
+    /// Get the raw bytes
+    pub fn as_bytes(&self) -> &[u8; 64] {
+        &self.0
+    }
+}
+
 impl fmt::Debug for Signature {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Sig({}..)", hex::encode(&self.0[..8]))
@@ -128,25 +417,25 @@ mod pub_key_serde {
     }
 }
 
+/// Secret key hex encoding; intermediate strings are wiped after use
 mod hex_serde {
     use serde::{self, Deserialize, Deserializer, Serializer};
+    use zeroize::Zeroizing;
 
     pub fn serialize<S>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&hex::encode(bytes))
+        serializer.serialize_str(&Zeroizing::new(hex::encode(bytes)))
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
-        let arr: [u8; 32] = bytes
-            .try_into()
-            .map_err(|_| serde::de::Error::custom("invalid length"))?;
+        let s = Zeroizing::new(String::deserialize(deserializer)?);
+        let mut arr = [0u8; 32];
+        hex::decode_to_slice(s.as_bytes(), &mut arr).map_err(serde::de::Error::custom)?;
         Ok(arr)
     }
 }
@@ -184,6 +473,125 @@ mod tests {
         assert_ne!(kp.public_key.as_bytes(), &[0u8; 32]);
     }
 
+    #[test]
+    fn test_keypair_from_mnemonic() {
+        // SLIP-0010 ed25519 master key for the BIP39 seed of this phrase
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let a = KeyPair::from_mnemonic(phrase, "").unwrap();
+        let b = KeyPair::from_mnemonic(&phrase.to_uppercase(), "").unwrap();
+        assert_eq!(a.public_key, b.public_key);
+        let c = KeyPair::from_mnemonic(phrase, "TREZOR").unwrap();
+        assert_ne!(a.public_key, c.public_key);
+
+        let (kp, mnemonic) = KeyPair::generate_with_mnemonic();
+        let restored = KeyPair::from_mnemonic(mnemonic.phrase(), "").unwrap();
+        assert_eq!(kp.public_key, restored.public_key);
+    }
+
+    #[test]
+    fn test_seed_phrase_keys_are_stable() {
+        let alice = KeyPair::from_seed_phrase_for_tests("alice");
+        assert_eq!(alice.public_key, KeyPair::from_seed_phrase_for_tests("alice").public_key);
+        assert_ne!(alice.public_key, KeyPair::from_seed_phrase_for_tests("bob").public_key);
+        // Pinned: fixtures and docs rely on these identities never changing
+        assert_eq!(
+            alice.public_key.to_string(),
+            "2e023dc749bc585452d682769b09fb7f9b601adae2bbd590de88ffc4955de1a6"
+        );
+    }
+
+    #[test]
+    fn test_derive_subkey() {
+        let kp = KeyPair::generate();
+        let a = kp.derive_subkey("rhiza test", 0);
+        assert_eq!(a.public_key, kp.derive_subkey("rhiza test", 0).public_key);
+        assert_ne!(a.public_key, kp.derive_subkey("rhiza test", 1).public_key);
+        assert_ne!(a.public_key, kp.derive_subkey("rhiza other", 0).public_key);
+        assert_ne!(a.public_key, kp.public_key);
+    }
+
+    #[test]
+    fn test_slip10_master_key() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let kp = KeyPair::from_seed(&seed);
+        assert_eq!(
+            hex::encode(*kp.secret_bytes()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+    }
+
+    #[test]
+    fn test_slip10_child_keys() {
+        // SLIP-0010 test vector 1 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedKey::master(&seed);
+        let child = master.child(0);
+        assert_eq!(
+            hex::encode(child.secret),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(child.chain_code),
+            "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"
+        );
+        assert_eq!(
+            hex::encode(master.derive(&[HARDENED, 1 | HARDENED]).secret),
+            "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2"
+        );
+    }
+
+    #[test]
+    fn test_import_other_wallets() {
+        // SEP-0005 test 1: the first two Stellar accounts of this phrase
+        let phrase = "illness spike retreat truth genius clock brain pass fit cave bargain toe";
+        let stellar = |account| KeyPair::from_mnemonic_at(phrase, "", &DerivationPath::stellar(account)).unwrap();
+        assert_eq!(
+            stellar(0).public_key.to_string(),
+            "e3726830a0b60cb5f52c844cffcd4eed65eba5c155e89b26411562724e71e544"
+        );
+        assert_eq!(
+            stellar(1).public_key.to_string(),
+            "416edcd6746d5293579a7039ac67bcf1a8698efecf81183bbb0ac877da86ada3"
+        );
+
+        // SLIP-0010 test vector 1, chain m/0'/1'/2'
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let path: DerivationPath = "m/0'/1h/2'".parse().unwrap();
+        assert_eq!(
+            hex::encode(*ExtendedKey::master(&seed).derive(path.indices()).keypair().secret_bytes()),
+            "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9"
+        );
+    }
+
+    #[test]
+    fn test_derivation_paths() {
+        assert_eq!("solana".parse(), Ok(DerivationPath::solana(0)));
+        assert_eq!("solana:2".parse(), Ok(DerivationPath::solana(2)));
+        assert_eq!("stellar:1".parse(), Ok(DerivationPath::stellar(1)));
+        assert_eq!(DerivationPath::solana(3).to_string(), "m/44'/501'/3'/0'");
+        assert_eq!("m/44'/501'/3'/0'".parse(), Ok(DerivationPath::solana(3)));
+        assert_eq!("m".parse(), Ok(DerivationPath::master()));
+        assert!(DerivationPath::master().is_master());
+
+        for bad in ["", "44'/0'", "m/44'/0", "m/x'", "m/2147483648'", "solana:-1", "ethereum"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{}", bad);
+        }
+
+        let json = serde_json::to_string(&DerivationPath::stellar(0)).unwrap();
+        assert_eq!(json, "\"m/44'/148'/0'\"");
+        assert_eq!(serde_json::from_str::<DerivationPath>(&json).unwrap(), DerivationPath::stellar(0));
+    }
+
+    #[test]
+    fn test_account_keys() {
+        let master = ExtendedKey::master(&[7u8; 64]);
+        let key = master.account_key(0, 0);
+        assert_eq!(key.public_key, master.derive(&account_path(0, 0)).keypair().public_key);
+        assert_ne!(key.public_key, master.account_key(0, 1).public_key);
+        assert_ne!(key.public_key, master.account_key(1, 0).public_key);
+        assert_ne!(key.public_key, master.keypair().public_key);
+    }
+
     #[test]
     fn test_sign_and_verify() {
         let kp = KeyPair::generate();
@@ -215,6 +623,16 @@ mod tests {
         assert_eq!(kp.public_key, restored.public_key);
     }
 
+    #[test]
+    fn test_secret_key_serde_roundtrip() {
+        let secret = SecretKey([7u8; 32]);
+        let json = serde_json::to_string(&secret).unwrap();
+        assert_eq!(json, format!("\"{}\"", "07".repeat(32)));
+        let restored: SecretKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.0, [7u8; 32]);
+        assert!(serde_json::from_str::<SecretKey>("\"0707\"").is_err());
+    }
+
     #[test]
     fn test_pubkey_serialization() {
         let kp = KeyPair::generate();
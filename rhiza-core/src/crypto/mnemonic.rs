@@ -0,0 +1,227 @@
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::sync::OnceLock;
+use zeroize::Zeroizing;
+
+/// The BIP39 English wordlist
+const ENGLISH: &str = include_str!("wordlist/english.txt");
+
+/// PBKDF2 rounds used to stretch a mnemonic into a seed (fixed by BIP39)
+const SEED_ROUNDS: u32 = 2048;
+
+/// Word counts BIP39 allows (128 to 256 bits of entropy)
+pub const VALID_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Word count of newly generated mnemonics
+pub const DEFAULT_WORD_COUNT: usize = 24;
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| ENGLISH.lines().collect())
+}
+
+/// A BIP39 mnemonic phrase (English wordlist)
+///
+/// The phrase is wiped from memory on drop.
+#[derive(Clone)]
+pub struct Mnemonic {
+    phrase: Zeroizing<String>,
+}
+
+impl Mnemonic {
+    /// Generate a new random mnemonic with `word_count` words
+    pub fn generate(word_count: usize) -> Result<Self, MnemonicError> {
+        if !VALID_WORD_COUNTS.contains(&word_count) {
+            return Err(MnemonicError::WordCount(word_count));
+        }
+        let mut entropy = Zeroizing::new(vec![0u8; word_count / 3 * 4]);
+        OsRng.fill_bytes(&mut entropy);
+        Self::from_entropy(&entropy)
+    }
+
+    /// Encode 16–32 bytes of entropy (a multiple of 4) as a mnemonic
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, MnemonicError> {
+        if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+            return Err(MnemonicError::EntropyLength(entropy.len()));
+        }
+
+        // Entropy bits followed by the first ENT/32 bits of its SHA-256
+        let checksum = Sha256::digest(entropy)[0];
+        let bit_count = entropy.len() * 8 + entropy.len() / 4;
+        let bit = |i: usize| -> usize {
+            let byte = if i < entropy.len() * 8 { entropy[i / 8] } else { checksum };
+            ((byte >> (7 - i % 8)) & 1) as usize
+        };
+
+        let words = wordlist();
+        let mut phrase = Zeroizing::new(String::new());
+        for chunk in 0..bit_count / 11 {
+            let index = (0..11).fold(0, |acc, j| (acc << 1) | bit(chunk * 11 + j));
+            if chunk > 0 {
+                phrase.push(' ');
+            }
+            phrase.push_str(words[index]);
+        }
+        Ok(Mnemonic { phrase })
+    }
+
+    /// Parse and checksum-verify a phrase (case and spacing are normalized)
+    pub fn from_phrase(phrase: &str) -> Result<Self, MnemonicError> {
+        let normalized = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+        let words = wordlist();
+
+        let mut indices = Zeroizing::new(Vec::new());
+        for word in normalized.split(' ') {
+            let index = words
+                .binary_search(&word)
+                .map_err(|_| MnemonicError::UnknownWord(word.to_string()))?;
+            indices.push(index);
+        }
+        if !VALID_WORD_COUNTS.contains(&indices.len()) {
+            return Err(MnemonicError::WordCount(indices.len()));
+        }
+
+        // Unpack the 11-bit indices back into entropy + checksum bits
+        let bit_count = indices.len() * 11;
+        let entropy_len = bit_count * 32 / 33 / 8;
+        let mut bytes = Zeroizing::new(vec![0u8; bit_count.div_ceil(8)]);
+        for (n, index) in indices.iter().enumerate() {
+            for j in 0..11 {
+                if index & (1 << (10 - j)) != 0 {
+                    let i = n * 11 + j;
+                    bytes[i / 8] |= 1 << (7 - i % 8);
+                }
+            }
+        }
+        let expected = Self::from_entropy(&bytes[..entropy_len])?;
+        if *expected.phrase != *normalized {
+            return Err(MnemonicError::Checksum);
+        }
+        Ok(expected)
+    }
+
+    /// The space-separated phrase
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// Number of words in the phrase
+    pub fn word_count(&self) -> usize {
+        self.phrase.split(' ').count()
+    }
+
+    /// Stretch the phrase and an optional passphrase into a 64-byte seed
+    ///
+    /// BIP39 NFKD-normalizes both strings; only ASCII passphrases are
+    /// accepted so the seed always matches other BIP39 wallets.
+    pub fn to_seed(&self, passphrase: &str) -> Result<Zeroizing<[u8; 64]>, MnemonicError> {
+        if !passphrase.is_ascii() {
+            return Err(MnemonicError::NonAsciiPassphrase);
+        }
+        let salt = Zeroizing::new(format!("mnemonic{}", passphrase));
+        Ok(pbkdf2_sha512(self.phrase.as_bytes(), salt.as_bytes(), SEED_ROUNDS))
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mnemonic([REDACTED; {} words])", self.word_count())
+    }
+}
+
+/// PBKDF2-HMAC-SHA512 producing a single 64-byte block
+fn pbkdf2_sha512(password: &[u8], salt: &[u8], rounds: u32) -> Zeroizing<[u8; 64]> {
+    let prf = Hmac::<Sha512>::new_from_slice(password).expect("HMAC accepts any key length");
+
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block = Zeroizing::new([0u8; 64]);
+    block.copy_from_slice(&mac.finalize().into_bytes());
+
+    let mut output = block.clone();
+    for _ in 1..rounds {
+        let mut mac = prf.clone();
+        mac.update(&*block);
+        block.copy_from_slice(&mac.finalize().into_bytes());
+        for (out, b) in output.iter_mut().zip(block.iter()) {
+            *out ^= b;
+        }
+    }
+    output
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MnemonicError {
+    #[error("mnemonic must have 12, 15, 18, 21 or 24 words, got {0}")]
+    WordCount(usize),
+    #[error("entropy must be 16-32 bytes in steps of 4, got {0}")]
+    EntropyLength(usize),
+    #[error("unknown mnemonic word: {0}")]
+    UnknownWord(String),
+    #[error("mnemonic checksum mismatch")]
+    Checksum,
+    #[error("mnemonic passphrase must be ASCII")]
+    NonAsciiPassphrase,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wordlist() {
+        assert_eq!(wordlist().len(), 2048);
+        assert!(wordlist().windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_bip39_vectors() {
+        // From the reference BIP39 test vectors (passphrase "TREZOR")
+        let m = Mnemonic::from_entropy(&[0u8; 16]).unwrap();
+        assert_eq!(
+            m.phrase(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        assert_eq!(
+            hex::encode(*m.to_seed("TREZOR").unwrap()),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+
+        let m = Mnemonic::from_entropy(&[0xff; 32]).unwrap();
+        assert_eq!(
+            m.phrase(),
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote"
+        );
+    }
+
+    #[test]
+    fn test_generate_and_parse() {
+        let m = Mnemonic::generate(DEFAULT_WORD_COUNT).unwrap();
+        assert_eq!(m.word_count(), 24);
+        let parsed = Mnemonic::from_phrase(&format!("  {}  ", m.phrase().to_uppercase())).unwrap();
+        assert_eq!(parsed.phrase(), m.phrase());
+        assert!(Mnemonic::generate(13).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_phrases() {
+        let valid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert!(Mnemonic::from_phrase(valid).is_ok());
+        assert!(matches!(
+            Mnemonic::from_phrase(&valid.replace("about", "above")),
+            Err(MnemonicError::Checksum)
+        ));
+        assert!(matches!(
+            Mnemonic::from_phrase(&valid.replace("about", "rhiza")),
+            Err(MnemonicError::UnknownWord(_))
+        ));
+        assert!(matches!(
+            Mnemonic::from_phrase("abandon about"),
+            Err(MnemonicError::WordCount(2))
+        ));
+    }
+}
@@ -1,5 +1,13 @@
+pub mod algo;
 pub mod keys;
 pub mod hash;
+pub mod mnemonic;
+pub mod multisig;
+pub mod vrf;
 
-pub use keys::{KeyPair, PublicKey, SecretKey, Signature};
+pub use algo::{Algorithms, HashAlgo, SigAlgo};
+pub use keys::{DerivationPath, KeyPair, PublicKey, SecretKey, Signature};
 pub use hash::Hash;
+pub use mnemonic::{Mnemonic, MnemonicError};
+pub use multisig::{MultisigPolicy, MultisigWitness, PartialSignature};
+pub use vrf::VrfProof;
@@ -0,0 +1,276 @@
+use super::keys::{KeyPair, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+/// Most members a multisig account may have
+pub const MAX_MULTISIG_MEMBERS: usize = 16;
+
+/// Key derivation context of multisig account keys
+const ACCOUNT_CONTEXT: &str = "rhiza multisig account v1";
+
+/// Who controls a multisig account: any `threshold` of its `members`
+///
+/// The account's key is a hash of the policy, not a point anyone holds the
+/// secret of, so nothing signs as the account itself; a spend from it
+/// carries the policy and the signatures of enough members instead (see
+/// [`MultisigWitness`]). Members are kept sorted, so the same keys and
+/// threshold always make the same account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    pub threshold: u8,
+    /// Member keys, sorted by their bytes
+    pub members: Vec<PublicKey>,
+}
+
+/// One member's signature over a multisig spend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub signer: PublicKey,
+    pub signature: Signature,
+}
+
+/// What authorizes a spend from a multisig account: its policy and the
+/// members' signatures gathered so far, in member order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigWitness {
+    pub policy: MultisigPolicy,
+    pub signatures: Vec<PartialSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MultisigError {
+    #[error("threshold {threshold} doesn't fit {members} members")]
+    InvalidThreshold { threshold: u8, members: usize },
+    #[error("a multisig account has at most {MAX_MULTISIG_MEMBERS} members, not {0}")]
+    TooManyMembers(usize),
+    #[error("members must be distinct and sorted")]
+    UnsortedMembers,
+    #[error("{0} is not a member")]
+    NotAMember(PublicKey),
+    #[error("{0} signed twice")]
+    DuplicateSignature(PublicKey),
+    #[error("signatures must be in member order")]
+    UnsortedSignatures,
+    #[error("invalid signature from {0}")]
+    InvalidSignature(PublicKey),
+    #[error("{have} of {need} member signatures")]
+    NotEnoughSignatures { have: usize, need: usize },
+    #[error("the policy isn't the sender's")]
+    WrongAccount,
+    #[error("not a multisig spend")]
+    NotMultisig,
+}
+
+impl MultisigPolicy {
+    /// Policy requiring `threshold` of `members`, in any order
+    pub fn new(threshold: u8, mut members: Vec<PublicKey>) -> Result<Self, MultisigError> {
+        members.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        let policy = MultisigPolicy { threshold, members };
+        policy.check()?;
+        Ok(policy)
+    }
+
+    /// Check the rules [`new`](Self::new) enforces, for a policy that
+    /// arrived from elsewhere
+    pub fn check(&self) -> Result<(), MultisigError> {
+        let n = self.members.len();
+        if n > MAX_MULTISIG_MEMBERS {
+            return Err(MultisigError::TooManyMembers(n));
+        }
+        if self.threshold == 0 || self.threshold as usize > n {
+            return Err(MultisigError::InvalidThreshold { threshold: self.threshold, members: n });
+        }
+        if self.members.windows(2).any(|pair| pair[0].as_bytes() >= pair[1].as_bytes()) {
+            return Err(MultisigError::UnsortedMembers);
+        }
+        Ok(())
+    }
+
+    /// The account's key, which funds are sent to
+    pub fn account(&self) -> PublicKey {
+        let mut material = Vec::with_capacity(1 + 32 * self.members.len());
+        material.push(self.threshold);
+        for member in &self.members {
+            material.extend_from_slice(member.as_bytes());
+        }
+        PublicKey::from_bytes(blake3::derive_key(ACCOUNT_CONTEXT, &material))
+    }
+
+    pub fn is_member(&self, key: &PublicKey) -> bool {
+        self.position(key).is_some()
+    }
+
+    fn position(&self, key: &PublicKey) -> Option<usize> {
+        self.members.binary_search_by(|m| m.as_bytes().cmp(key.as_bytes())).ok()
+    }
+}
+
+impl PartialSignature {
+    pub fn sign(keypair: &KeyPair, message: &[u8]) -> Self {
+        PartialSignature {
+            signer: keypair.public_key.clone(),
+            signature: keypair.sign(message),
+        }
+    }
+
+    pub fn verify(&self, message: &[u8]) -> bool {
+        self.signer.verify(message, &self.signature)
+    }
+}
+
+impl MultisigWitness {
+    /// A witness without signatures yet
+    pub fn new(policy: MultisigPolicy) -> Self {
+        MultisigWitness { policy, signatures: Vec::new() }
+    }
+
+    /// Add a member's signature over `message`, replacing any earlier one
+    /// of theirs; returns how many members have signed
+    pub fn add(&mut self, message: &[u8], partial: PartialSignature) -> Result<usize, MultisigError> {
+        let position = self
+            .policy
+            .position(&partial.signer)
+            .ok_or_else(|| MultisigError::NotAMember(partial.signer.clone()))?;
+        if !partial.verify(message) {
+            return Err(MultisigError::InvalidSignature(partial.signer));
+        }
+        self.signatures.retain(|s| s.signer != partial.signer);
+        let at = self
+            .signatures
+            .partition_point(|s| self.policy.position(&s.signer).is_some_and(|p| p < position));
+        self.signatures.insert(at, partial);
+        Ok(self.signatures.len())
+    }
+
+    /// Sign `message` as the member `keypair`
+    pub fn sign(&mut self, keypair: &KeyPair, message: &[u8]) -> Result<usize, MultisigError> {
+        self.add(message, PartialSignature::sign(keypair, message))
+    }
+
+    /// Members still needed to reach the threshold
+    pub fn missing(&self) -> usize {
+        (self.policy.threshold as usize).saturating_sub(self.signatures.len())
+    }
+
+    /// Check the policy and that enough distinct members validly signed
+    /// `message`
+    ///
+    /// Every signature carried must be good, not just the threshold's
+    /// worth, so a relay can't pad a spend with junk.
+    pub fn verify(&self, message: &[u8]) -> Result<(), MultisigError> {
+        self.policy.check()?;
+        let mut last = None;
+        for partial in &self.signatures {
+            let position = self
+                .policy
+                .position(&partial.signer)
+                .ok_or_else(|| MultisigError::NotAMember(partial.signer.clone()))?;
+            // In member order, so a repeat sits right after the first
+            match last {
+                Some(last) if position == last => {
+                    return Err(MultisigError::DuplicateSignature(partial.signer.clone()))
+                }
+                Some(last) if position < last => return Err(MultisigError::UnsortedSignatures),
+                _ => {}
+            }
+            last = Some(position);
+            if !partial.verify(message) {
+                return Err(MultisigError::InvalidSignature(partial.signer.clone()));
+            }
+        }
+        let need = self.policy.threshold as usize;
+        if self.signatures.len() < need {
+            return Err(MultisigError::NotEnoughSignatures { have: self.signatures.len(), need });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(n: usize) -> Vec<KeyPair> {
+        (0..n).map(|_| KeyPair::generate()).collect()
+    }
+
+    fn policy(threshold: u8, members: &[KeyPair]) -> MultisigPolicy {
+        MultisigPolicy::new(threshold, members.iter().map(|kp| kp.public_key.clone()).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_policy_rules() {
+        let members = keys(3);
+        let a = policy(2, &members);
+        let reversed = policy(2, &members.iter().rev().cloned().collect::<Vec<_>>());
+        assert_eq!(a, reversed);
+        assert_eq!(a.account(), reversed.account());
+        assert_ne!(a.account(), policy(3, &members).account());
+        assert_ne!(a.account(), policy(2, &members[..2]).account());
+        assert!(members.iter().all(|kp| a.is_member(&kp.public_key)));
+
+        let key = |kp: &KeyPair| kp.public_key.clone();
+        assert!(matches!(
+            MultisigPolicy::new(0, vec![key(&members[0])]),
+            Err(MultisigError::InvalidThreshold { .. })
+        ));
+        assert!(matches!(
+            MultisigPolicy::new(3, members[..2].iter().map(key).collect()),
+            Err(MultisigError::InvalidThreshold { threshold: 3, members: 2 })
+        ));
+        assert_eq!(
+            MultisigPolicy::new(1, vec![key(&members[0]), key(&members[0])]),
+            Err(MultisigError::UnsortedMembers)
+        );
+        let crowd = keys(MAX_MULTISIG_MEMBERS + 1).iter().map(key).collect();
+        assert_eq!(MultisigPolicy::new(2, crowd), Err(MultisigError::TooManyMembers(17)));
+
+        // A policy from elsewhere must already be in order
+        let mut shuffled = a.clone();
+        shuffled.members.reverse();
+        assert_eq!(shuffled.check(), Err(MultisigError::UnsortedMembers));
+    }
+
+    #[test]
+    fn test_witness_assembly() {
+        let members = keys(3);
+        let message = b"spend";
+        let mut witness = MultisigWitness::new(policy(2, &members));
+        assert_eq!(witness.verify(message), Err(MultisigError::NotEnoughSignatures { have: 0, need: 2 }));
+
+        // Signing again replaces, and order doesn't depend on who was first
+        assert_eq!(witness.sign(&members[2], message), Ok(1));
+        assert_eq!(witness.sign(&members[2], message), Ok(1));
+        assert_eq!(witness.missing(), 1);
+        let partial = PartialSignature::sign(&members[0], message);
+        assert_eq!(witness.add(message, partial), Ok(2));
+        assert_eq!(witness.missing(), 0);
+        assert!(witness.verify(message).is_ok());
+        assert!(witness.verify(b"another spend").is_err());
+        let mut other = MultisigWitness::new(witness.policy.clone());
+        other.sign(&members[0], message).unwrap();
+        other.sign(&members[2], message).unwrap();
+        assert_eq!(other, witness);
+
+        let outsider = KeyPair::generate();
+        assert_eq!(
+            witness.sign(&outsider, message),
+            Err(MultisigError::NotAMember(outsider.public_key.clone()))
+        );
+        let forged = PartialSignature {
+            signer: members[1].public_key.clone(),
+            signature: outsider.sign(message),
+        };
+        assert!(matches!(witness.add(message, forged.clone()), Err(MultisigError::InvalidSignature(_))));
+
+        // Signatures slipped in around the checks are caught
+        let mut tampered = witness.clone();
+        tampered.signatures[0].signature = forged.signature;
+        assert!(matches!(tampered.verify(message), Err(MultisigError::InvalidSignature(_))));
+        let mut repeated = witness.clone();
+        repeated.signatures.insert(1, repeated.signatures[0].clone());
+        assert!(matches!(repeated.verify(message), Err(MultisigError::DuplicateSignature(_))));
+        let mut reversed = witness.clone();
+        reversed.signatures.reverse();
+        assert_eq!(reversed.verify(message), Err(MultisigError::UnsortedSignatures));
+    }
+}
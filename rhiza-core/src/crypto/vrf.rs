@@ -0,0 +1,200 @@
+use super::keys::{KeyPair, PublicKey};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{clamp_integer, Scalar};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Suite byte of ECVRF-EDWARDS25519-SHA512-TAI (RFC 9381, section 5.5)
+const SUITE: u8 = 0x03;
+
+/// Length of the challenge in a proof, in bytes
+const CHALLENGE_LEN: usize = 16;
+
+/// Length of an encoded proof: Gamma, the challenge and the response
+pub const PROOF_LEN: usize = 32 + CHALLENGE_LEN + 32;
+
+/// Proof that a VRF output was computed with a key's secret, checkable with
+/// the public key alone
+///
+/// The VRF is ECVRF-EDWARDS25519-SHA512-TAI from RFC 9381, run with the
+/// same Ed25519 key the account signs with. For a given key and input there
+/// is exactly one output, so whoever holds the key can't pick among several,
+/// yet no one else can predict it before the proof is published.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VrfProof(#[serde(with = "proof_serde")] pub(crate) [u8; PROOF_LEN]);
+
+impl VrfProof {
+    /// Prove the VRF output of `keypair` on `input`
+    pub fn prove(keypair: &KeyPair, input: &[u8]) -> Self {
+        let expanded = Zeroizing::new(<[u8; 64]>::from(Sha512::digest(*keypair.secret_bytes())));
+        let mut scalar_bytes = Zeroizing::new([0u8; 32]);
+        scalar_bytes.copy_from_slice(&expanded[..32]);
+        let x = Scalar::from_bytes_mod_order(clamp_integer(*scalar_bytes));
+
+        let public = keypair.public_key.as_bytes();
+        let h = encode_to_curve(public, input).expect("a point is found well within 256 tries");
+        let gamma = x * h;
+        let h_bytes = h.compress().to_bytes();
+        let k = Scalar::from_hash(Sha512::new().chain_update(&expanded[32..]).chain_update(h_bytes));
+        let c = challenge(public, &h, &gamma, &(k * ED25519_BASEPOINT_POINT), &(k * h));
+        let s = k + c * x;
+
+        let mut proof = [0u8; PROOF_LEN];
+        proof[..32].copy_from_slice(gamma.compress().as_bytes());
+        proof[32..32 + CHALLENGE_LEN].copy_from_slice(&c.as_bytes()[..CHALLENGE_LEN]);
+        proof[32 + CHALLENGE_LEN..].copy_from_slice(s.as_bytes());
+        VrfProof(proof)
+    }
+
+    /// The VRF output of `key` on `input`, if this proves it
+    pub fn verify(&self, key: &PublicKey, input: &[u8]) -> Option<[u8; 64]> {
+        let y = decode_point(key.as_bytes())?;
+        if y.is_small_order() {
+            return None;
+        }
+        let gamma = decode_point(self.0[..32].try_into().unwrap())?;
+        let mut c_bytes = [0u8; 32];
+        c_bytes[..CHALLENGE_LEN].copy_from_slice(&self.0[32..32 + CHALLENGE_LEN]);
+        let c = Scalar::from_bytes_mod_order(c_bytes);
+        let s_bytes = self.0[32 + CHALLENGE_LEN..].try_into().unwrap();
+        let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(s_bytes))?;
+
+        let h = encode_to_curve(key.as_bytes(), input)?;
+        let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-c, &y, &s);
+        let v = s * h - c * gamma;
+        if challenge(key.as_bytes(), &h, &gamma, &u, &v) != c {
+            return None;
+        }
+        Some(proof_to_hash(&gamma))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; PROOF_LEN] {
+        &self.0
+    }
+
+    pub fn from_bytes(bytes: [u8; PROOF_LEN]) -> Self {
+        VrfProof(bytes)
+    }
+}
+
+impl fmt::Debug for VrfProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VrfProof({}..)", hex::encode(&self.0[..8]))
+    }
+}
+
+/// A canonically encoded Edwards point (RFC 8032, section 5.1.3)
+fn decode_point(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+    let point = CompressedEdwardsY(*bytes).decompress()?;
+    (point.compress().as_bytes() == bytes).then_some(point)
+}
+
+/// Hash `input` to a point of the prime-order subgroup by try-and-increment
+fn encode_to_curve(public: &[u8; 32], input: &[u8]) -> Option<EdwardsPoint> {
+    (0..=u8::MAX).find_map(|counter| {
+        let digest = Sha512::new()
+            .chain_update([SUITE, 0x01])
+            .chain_update(public)
+            .chain_update(input)
+            .chain_update([counter, 0x00])
+            .finalize();
+        decode_point(digest[..32].try_into().unwrap()).map(|point| point.mul_by_cofactor())
+    })
+}
+
+fn challenge(
+    public: &[u8; 32],
+    h: &EdwardsPoint,
+    gamma: &EdwardsPoint,
+    u: &EdwardsPoint,
+    v: &EdwardsPoint,
+) -> Scalar {
+    let mut hasher = Sha512::new().chain_update([SUITE, 0x02]).chain_update(public);
+    for point in [h, gamma, u, v] {
+        hasher.update(point.compress().as_bytes());
+    }
+    let digest = hasher.chain_update([0x00]).finalize();
+    let mut c = [0u8; 32];
+    c[..CHALLENGE_LEN].copy_from_slice(&digest[..CHALLENGE_LEN]);
+    Scalar::from_bytes_mod_order(c)
+}
+
+fn proof_to_hash(gamma: &EdwardsPoint) -> [u8; 64] {
+    Sha512::new()
+        .chain_update([SUITE, 0x03])
+        .chain_update(gamma.mul_by_cofactor().compress().as_bytes())
+        .chain_update([0x00])
+        .finalize()
+        .into()
+}
+
+mod proof_serde {
+    use super::PROOF_LEN;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(bytes: &[u8; PROOF_LEN], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; PROOF_LEN], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let mut arr = [0u8; PROOF_LEN];
+        hex::decode_to_slice(s.as_bytes(), &mut arr).map_err(serde::de::Error::custom)?;
+        Ok(arr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc9381_vector() {
+        // RFC 9381, appendix B.3, example 16
+        let secret = hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+        let kp = KeyPair::from_secret_bytes(&secret.try_into().unwrap());
+        let proof = VrfProof::prove(&kp, b"");
+        assert_eq!(
+            hex::encode(proof.as_bytes()),
+            concat!(
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f",
+                "26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab12",
+                "68a1b0db10836d9826a528ca76567805"
+            )
+        );
+        assert_eq!(
+            hex::encode(proof.verify(&kp.public_key, b"").unwrap()),
+            concat!(
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff",
+                "66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae"
+            )
+        );
+    }
+
+    #[test]
+    fn test_proofs_bind_key_and_input() {
+        let kp = KeyPair::generate();
+        let proof = VrfProof::prove(&kp, b"input");
+        let output = proof.verify(&kp.public_key, b"input").unwrap();
+        // Deterministic: proving again gives the same output
+        assert_eq!(VrfProof::prove(&kp, b"input").verify(&kp.public_key, b"input"), Some(output));
+
+        assert_eq!(proof.verify(&kp.public_key, b"other input"), None);
+        assert_eq!(proof.verify(&KeyPair::generate().public_key, b"input"), None);
+        let mut tampered = proof.clone();
+        tampered.0[40] ^= 1;
+        assert_eq!(tampered.verify(&kp.public_key, b"input"), None);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<VrfProof>(&json).unwrap(), proof);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::snapshot::PublisherSignature;
+use crate::dag::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Domain separator of the hash a bundle's exporter signs
+const BUNDLE_DOMAIN: &[u8] = b"rhiza dag bundle v1";
+
+/// Leading bytes of a bundle file
+const BUNDLE_MAGIC: &[u8; 4] = b"RHZB";
+
+/// Version of the bundle file layout
+const BUNDLE_VERSION: u8 = 1;
+
+/// Largest bundle accepted once decompressed, so a small file can't expand
+/// without bound
+pub const MAX_BUNDLE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Shortest repeat the compressor encodes as a back-reference
+const MIN_MATCH: usize = 4;
+
+/// Longest repeat one back-reference covers
+const MAX_MATCH: usize = MIN_MATCH + u8::MAX as usize;
+
+/// How far back a back-reference may point
+const WINDOW: usize = u16::MAX as usize;
+
+/// A slice of the DAG carried between nodes that can't reach each other,
+/// on a USB stick or over a radio file transfer
+///
+/// It holds the transactions from some depth on, parents before children.
+/// Relay work travels in the reward claims among them, each carrying its
+/// signed relay proofs. The exporting node signs the
+/// [`content_hash`](Self::content_hash), so a file damaged or altered on the
+/// way is refused; the transactions are still validated one by one on
+/// import, so whoever signed it can't plant anything invalid.
+///
+/// On disk a bundle is compressed: public keys and parent ids repeat from
+/// one transaction to the next, and a back-reference is much shorter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub network_id: u32,
+    /// When the bundle was made (unix ms)
+    pub created_ms: u64,
+    /// Shallowest depth exported; the importer needs what lies below
+    pub since_depth: u64,
+    /// Transactions, parents before children
+    pub transactions: Vec<Transaction>,
+    pub signature: Option<PublisherSignature>,
+}
+
+/// Why a bundle file can't be read
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BundleError {
+    #[error("not a Rhiza bundle file")]
+    NotABundle,
+    #[error("bundle version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("bundle expands to {0} bytes, more than allowed")]
+    TooLarge(u64),
+    #[error("bundle is damaged")]
+    Corrupt,
+}
+
+impl Bundle {
+    /// Unsigned bundle of `transactions`, parents first, made now
+    pub fn new(network_id: u32, since_depth: u64, transactions: Vec<Transaction>) -> Self {
+        Bundle {
+            network_id,
+            created_ms: chrono::Utc::now().timestamp_millis() as u64,
+            since_depth,
+            transactions,
+            signature: None,
+        }
+    }
+
+    /// Relay proofs carried by the reward claims in the bundle
+    pub fn relay_proof_count(&self) -> usize {
+        self.transactions.iter().map(|tx| tx.data.relay_proofs.len()).sum()
+    }
+
+    /// Hash of everything the exporter vouches for
+    pub fn content_hash(&self) -> Hash {
+        let transactions = bincode::serialize(&self.transactions).expect("transactions serialize");
+        Hash::digest_multi(&[
+            BUNDLE_DOMAIN,
+            &self.network_id.to_le_bytes(),
+            &self.created_ms.to_le_bytes(),
+            &self.since_depth.to_le_bytes(),
+            &transactions,
+        ])
+    }
+
+    /// Sign as the exporter, replacing any earlier signature
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        self.signature = Some(PublisherSignature {
+            publisher: keypair.public_key.clone(),
+            signature: keypair.sign(self.content_hash().as_bytes()),
+        });
+    }
+
+    /// The exporter, if the bundle is signed and its contents match
+    pub fn signer(&self) -> Option<&PublicKey> {
+        let signature = self.signature.as_ref()?;
+        signature
+            .publisher
+            .verify(self.content_hash().as_bytes(), &signature.signature)
+            .then_some(&signature.publisher)
+    }
+
+    /// The file contents: a short header, then the compressed bundle
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let encoded = bincode::serialize(self).expect("bundle serializes");
+        let mut bytes = Vec::with_capacity(BUNDLE_MAGIC.len() + 9 + encoded.len() / 2);
+        bytes.extend_from_slice(BUNDLE_MAGIC);
+        bytes.push(BUNDLE_VERSION);
+        bytes.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&compress(&encoded));
+        bytes
+    }
+
+    /// Read a bundle file written by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BundleError> {
+        let rest = bytes.strip_prefix(BUNDLE_MAGIC).ok_or(BundleError::NotABundle)?;
+        let (&version, rest) = rest.split_first().ok_or(BundleError::NotABundle)?;
+        if version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+        let (len, compressed) = rest.split_first_chunk::<8>().ok_or(BundleError::NotABundle)?;
+        let len = u64::from_le_bytes(*len);
+        if len > MAX_BUNDLE_BYTES as u64 {
+            return Err(BundleError::TooLarge(len));
+        }
+        let encoded = decompress(compressed, len as usize).ok_or(BundleError::Corrupt)?;
+        bincode::deserialize(&encoded).map_err(|_| BundleError::Corrupt)
+    }
+}
+
+/// LZ77-style compression: each flag byte says which of the next eight
+/// tokens are literal bytes and which are back-references (a two-byte
+/// distance and a one-byte length)
+fn compress(input: &[u8]) -> Vec<u8> {
+    let key = |at: usize| -> Option<[u8; MIN_MATCH]> { input.get(at..at + MIN_MATCH)?.try_into().ok() };
+    let mut latest: HashMap<[u8; MIN_MATCH], usize> = HashMap::new();
+    let mut out = Vec::with_capacity(input.len() / 2);
+    let (mut flags_at, mut token) = (0, 8);
+    let mut pos = 0;
+    while pos < input.len() {
+        if token == 8 {
+            flags_at = out.len();
+            out.push(0);
+            token = 0;
+        }
+        let earlier = key(pos)
+            .and_then(|k| latest.insert(k, pos))
+            .filter(|&start| pos - start <= WINDOW);
+        match earlier {
+            Some(start) => {
+                let length = input[start..]
+                    .iter()
+                    .zip(&input[pos..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                out[flags_at] |= 1 << token;
+                out.extend_from_slice(&((pos - start) as u16).to_le_bytes());
+                out.push((length - MIN_MATCH) as u8);
+                for at in pos + 1..pos + length {
+                    if let Some(k) = key(at) {
+                        latest.insert(k, at);
+                    }
+                }
+                pos += length;
+            }
+            None => {
+                out.push(input[pos]);
+                pos += 1;
+            }
+        }
+        token += 1;
+    }
+    out
+}
+
+/// Undo [`compress`], refusing input that doesn't expand to exactly `len`
+/// bytes
+fn decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut rest = input;
+    while let Some((&flags, tokens)) = rest.split_first() {
+        rest = tokens;
+        for token in 0..8 {
+            if rest.is_empty() {
+                break;
+            }
+            if flags & (1 << token) == 0 {
+                out.push(rest[0]);
+                rest = &rest[1..];
+            } else {
+                let (reference, tokens) = rest.split_first_chunk::<3>()?;
+                rest = tokens;
+                let distance = u16::from_le_bytes([reference[0], reference[1]]) as usize;
+                let length = reference[2] as usize + MIN_MATCH;
+                if distance == 0 || distance > out.len() || out.len() + length > len {
+                    return None;
+                }
+                // Byte by byte, since a repeat may overlap what it copies
+                let start = out.len() - distance;
+                for at in start..start + length {
+                    out.push(out[at]);
+                }
+            }
+            if out.len() > len {
+                return None;
+            }
+        }
+    }
+    (out.len() == len).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::relay::RelayProof;
+    use rand::{Rng, SeedableRng};
+
+    fn transactions() -> Vec<Transaction> {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let mut last = genesis.id;
+        let mut transactions = vec![genesis];
+        for nonce in 1..=50 {
+            let tx = if nonce % 10 == 0 {
+                let proofs = vec![RelayProof::new(&kp, last, 1)];
+                Transaction::relay_reward_with_proofs(&kp, 100, proofs, [last, last], nonce)
+            } else {
+                Transaction::transfer(&kp, KeyPair::generate().public_key, nonce, [last, last], nonce)
+            };
+            last = tx.id;
+            transactions.push(tx);
+        }
+        transactions
+    }
+
+    #[test]
+    fn test_compression_round_trips() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let noise: Vec<u8> = (0..10_000).map(|_| rng.gen()).collect();
+        let runs = [vec![0u8; 5_000], b"abcabcabcabd".repeat(300)].concat();
+        for input in [Vec::new(), vec![9], b"abcd".to_vec(), noise, runs.clone()] {
+            assert_eq!(decompress(&compress(&input), input.len()).as_deref(), Some(&input[..]));
+        }
+        assert!(compress(&runs).len() < runs.len() / 20);
+
+        // Damage and a wrong length are caught
+        let packed = compress(&runs);
+        assert!(decompress(&packed, runs.len() - 1).is_none());
+        assert!(decompress(&packed[..packed.len() - 2], runs.len()).is_none());
+        assert!(decompress(&[0b1, 0xff, 0xff, 0], 100).is_none());
+    }
+
+    #[test]
+    fn test_bundle_file_round_trips() {
+        let exporter = KeyPair::generate();
+        let mut bundle = Bundle::new(crate::MAINNET_ID, 0, transactions());
+        assert_eq!(bundle.relay_proof_count(), 5);
+        assert!(bundle.signer().is_none());
+        bundle.sign(&exporter);
+
+        let bytes = bundle.to_bytes();
+        assert!(bytes.len() < bincode::serialize(&bundle).unwrap().len());
+        let read = Bundle::from_bytes(&bytes).unwrap();
+        assert_eq!(read.signer(), Some(&exporter.public_key));
+        assert_eq!(read.content_hash(), bundle.content_hash());
+        assert_eq!(read.transactions.len(), 51);
+
+        // An altered bundle no longer matches its signature
+        let mut altered = read.clone();
+        altered.transactions.pop();
+        assert!(altered.signer().is_none());
+
+        assert_eq!(Bundle::from_bytes(b"RHZS\x01").unwrap_err(), BundleError::NotABundle);
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert_eq!(Bundle::from_bytes(&future).unwrap_err(), BundleError::UnsupportedVersion(2));
+        let mut damaged = bytes;
+        let end = damaged.len() - 1;
+        damaged.truncate(end);
+        assert_eq!(Bundle::from_bytes(&damaged).unwrap_err(), BundleError::Corrupt);
+    }
+}
@@ -0,0 +1,501 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey, Signature};
+use crate::dag::transaction::{Transaction, TransactionType};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Domain separator of the hash channel parties sign
+const CHANNEL_STATE_DOMAIN: &[u8] = b"rhiza channel state v1";
+
+/// How long the payee has to answer a funder's close with a later state,
+/// in transaction time (ms)
+///
+/// Long enough to ride out a partition that lasts a day.
+pub const CHANNEL_DISPUTE_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Most channels a routed payment may cross
+pub const MAX_ROUTE_HOPS: usize = 8;
+
+/// What a channel's funder owes its payee, as of one update
+///
+/// Payments move off the DAG as ever higher states signed by the funder;
+/// only the one the channel closes with is settled on the DAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelState {
+    pub channel_id: Hash,
+    /// Counts up with every payment
+    pub sequence: u64,
+    /// Total paid to the payee so far
+    pub paid: u64,
+}
+
+impl ChannelState {
+    /// The state a channel opens in, which needs no signature
+    pub fn opening(channel_id: Hash) -> Self {
+        ChannelState {
+            channel_id,
+            sequence: 0,
+            paid: 0,
+        }
+    }
+
+    /// The next state, paying `amount` more
+    pub fn pay(&self, amount: u64) -> Option<Self> {
+        Some(ChannelState {
+            channel_id: self.channel_id,
+            sequence: self.sequence.checked_add(1)?,
+            paid: self.paid.checked_add(amount)?,
+        })
+    }
+
+    /// Hash of the state as the parties sign it
+    pub fn signing_hash(&self) -> Hash {
+        Hash::digest_multi(&[
+            CHANNEL_STATE_DOMAIN,
+            self.channel_id.as_bytes(),
+            &self.sequence.to_le_bytes(),
+            &self.paid.to_le_bytes(),
+        ])
+    }
+
+    /// Sign the state with `keypair`
+    pub fn sign(self, keypair: &KeyPair) -> SignedChannelState {
+        SignedChannelState {
+            signature: Some(keypair.sign(self.signing_hash().as_bytes())),
+            state: self,
+        }
+    }
+}
+
+/// A channel state with the signature of a party to the channel
+///
+/// A close carries the state signed by the party that isn't closing, so
+/// neither side can settle on a state the other never agreed to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedChannelState {
+    pub state: ChannelState,
+    /// Absent only for the opening state
+    pub signature: Option<Signature>,
+}
+
+impl SignedChannelState {
+    /// The opening state, unsigned
+    pub fn opening(channel_id: Hash) -> Self {
+        SignedChannelState {
+            state: ChannelState::opening(channel_id),
+            signature: None,
+        }
+    }
+
+    /// Whether `signer` signed the state (the opening state needs nobody)
+    pub fn verify(&self, signer: &PublicKey) -> bool {
+        match &self.signature {
+            Some(signature) => signer.verify(self.state.signing_hash().as_bytes(), signature),
+            None => self.state == ChannelState::opening(self.state.channel_id),
+        }
+    }
+}
+
+/// A channel as final history has it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Channel {
+    /// Id of the transaction that opened it
+    pub id: Hash,
+    pub funder: PublicKey,
+    pub payee: PublicKey,
+    /// Locked by the funder when the channel opened
+    pub deposit: u64,
+    pub status: ChannelStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChannelStatus {
+    Open,
+    /// The funder closed; settles on `state` once final history passes
+    /// `deadline_ms`, unless a close by the payee becomes final first
+    Closing {
+        close: Hash,
+        state: ChannelState,
+        deadline_ms: u64,
+    },
+    /// Paid out by the close `close`
+    Settled { close: Hash, paid: u64 },
+}
+
+impl Channel {
+    /// The other party to the channel, if `key` is one
+    pub fn counterparty(&self, key: &PublicKey) -> Option<&PublicKey> {
+        if *key == self.funder {
+            Some(&self.payee)
+        } else if *key == self.payee {
+            Some(&self.funder)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status == ChannelStatus::Open
+    }
+
+    pub fn is_settled(&self) -> bool {
+        matches!(self.status, ChannelStatus::Settled { .. })
+    }
+
+    /// What the settlement pays `key`: the payee what it was paid, the
+    /// funder the rest of the deposit
+    pub fn credit_for(&self, key: &PublicKey) -> u64 {
+        let ChannelStatus::Settled { paid, .. } = self.status else {
+            return 0;
+        };
+        let mut credit = 0;
+        if *key == self.payee {
+            credit += paid;
+        }
+        if *key == self.funder {
+            credit += self.deposit.saturating_sub(paid);
+        }
+        credit
+    }
+}
+
+/// An off-DAG payment routed over channels
+///
+/// The funder of `route[hop]` sends it to that channel's payee with the
+/// channel's next state; the payee acknowledges, then passes the payment on
+/// over the next channel, which it funds. Hops trust each other: a payment
+/// a later hop refuses stays with the hop before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelPayment {
+    pub payment_id: Hash,
+    /// Channels from payer to recipient
+    pub route: Vec<Hash>,
+    /// Index of the channel this message crosses
+    pub hop: usize,
+    pub amount: u64,
+    /// Next state of `route[hop]`, signed by its funder
+    pub update: SignedChannelState,
+}
+
+/// Open channels and those closing or settled, as final history has them
+///
+/// Channels change only when transactions become final, so every node
+/// settles them the same way.
+#[derive(Debug, Clone, Default)]
+pub struct Channels {
+    channels: HashMap<Hash, Channel>,
+    /// Settling close -> its channel
+    settled_by: HashMap<Hash, Hash>,
+}
+
+impl Channels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Channel> {
+        self.channels.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Channel> {
+        self.channels.values()
+    }
+
+    /// Channels `key` is a party to
+    pub fn of<'a>(&'a self, key: &'a PublicKey) -> impl Iterator<Item = &'a Channel> {
+        self.channels.values().filter(move |c| c.counterparty(key).is_some())
+    }
+
+    /// The channel a close settled, if it did
+    pub fn settled_by(&self, close: &Hash) -> Option<&Channel> {
+        self.settled_by.get(close).and_then(|id| self.channels.get(id))
+    }
+
+    /// Put back a channel from a balance snapshot
+    pub(crate) fn restore(&mut self, channel: Channel) {
+        if let ChannelStatus::Settled { close, .. } = channel.status {
+            self.settled_by.insert(close, channel.id);
+        }
+        self.channels.insert(channel.id, channel);
+    }
+
+    /// Apply a transaction that just became final, returning the channel it
+    /// settled, if any
+    ///
+    /// Recording the same transaction twice changes nothing.
+    pub(crate) fn record(&mut self, tx: &Transaction) -> Option<&Channel> {
+        let data = &tx.data;
+        match data.tx_type {
+            TransactionType::ChannelOpen => {
+                self.channels.entry(tx.id).or_insert_with(|| Channel {
+                    id: tx.id,
+                    funder: data.sender.clone(),
+                    payee: data.recipient.clone(),
+                    deposit: data.amount,
+                    status: ChannelStatus::Open,
+                });
+                None
+            }
+            TransactionType::ChannelClose => {
+                let update = data.channel.as_ref()?;
+                let channel = self.channels.get_mut(&update.state.channel_id)?;
+                let state = update.state;
+                let paid = match &channel.status {
+                    ChannelStatus::Settled { .. } => return None,
+                    // A funder's close waits out the payee's dispute window
+                    ChannelStatus::Open if data.sender == channel.funder => {
+                        channel.status = ChannelStatus::Closing {
+                            close: tx.id,
+                            state,
+                            deadline_ms: data.timestamp.saturating_add(CHANNEL_DISPUTE_WINDOW_MS),
+                        };
+                        return None;
+                    }
+                    ChannelStatus::Open => state.paid,
+                    ChannelStatus::Closing { .. } if data.sender == channel.funder => return None,
+                    // The payee answers with whichever state is later
+                    ChannelStatus::Closing { state: closing, .. } => {
+                        if closing.sequence > state.sequence {
+                            closing.paid
+                        } else {
+                            state.paid
+                        }
+                    }
+                };
+                channel.status = ChannelStatus::Settled { close: tx.id, paid };
+                self.settled_by.insert(tx.id, channel.id);
+                Some(&*channel)
+            }
+            _ => None,
+        }
+    }
+
+    /// Settle the funders' closes whose dispute window ended by `now_ms`
+    /// (the timestamp of the latest final transaction), returning them
+    pub(crate) fn expire(&mut self, now_ms: u64) -> Vec<Channel> {
+        let mut settled = Vec::new();
+        for channel in self.channels.values_mut() {
+            if let ChannelStatus::Closing { close, state, deadline_ms } = channel.status {
+                if deadline_ms <= now_ms {
+                    channel.status = ChannelStatus::Settled { close, paid: state.paid };
+                    self.settled_by.insert(close, channel.id);
+                    settled.push(channel.clone());
+                }
+            }
+        }
+        settled
+    }
+
+    /// Drop settled channels whose close is in `pruned`; their payouts are
+    /// in pruned balances now
+    pub(crate) fn forget(&mut self, pruned: &HashSet<Hash>) {
+        self.settled_by.retain(|close, _| !pruned.contains(close));
+        let settled_by = &self.settled_by;
+        self.channels.retain(|_, channel| match channel.status {
+            ChannelStatus::Settled { close, .. } => settled_by.contains_key(&close),
+            _ => true,
+        });
+    }
+
+    /// Shortest route of open channels from `from` to `to` that can carry
+    /// `amount`, each funded by the payee of the one before
+    ///
+    /// `capacity` is what a channel can still pay; only its parties know
+    /// that, so the caller estimates it for channels it isn't part of.
+    pub fn route(
+        &self,
+        from: &PublicKey,
+        to: &PublicKey,
+        amount: u64,
+        capacity: impl Fn(&Channel) -> u64,
+    ) -> Option<Vec<Hash>> {
+        let mut by_funder: HashMap<&PublicKey, Vec<&Channel>> = HashMap::new();
+        for channel in self.channels.values().filter(|c| c.is_open() && capacity(c) >= amount) {
+            by_funder.entry(&channel.funder).or_default().push(channel);
+        }
+        for channels in by_funder.values_mut() {
+            channels.sort_by_key(|c| c.id);
+        }
+
+        let mut reached: HashMap<&PublicKey, Option<&Channel>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([(from, 0)]);
+        while let Some((key, hops)) = queue.pop_front() {
+            if key == to {
+                let mut route = Vec::new();
+                let mut at = key;
+                while let Some(Some(channel)) = reached.get(at) {
+                    route.push(channel.id);
+                    at = &channel.funder;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            if hops == MAX_ROUTE_HOPS {
+                continue;
+            }
+            for channel in by_funder.get(key).into_iter().flatten() {
+                if !reached.contains_key(&channel.payee) {
+                    reached.insert(&channel.payee, Some(channel));
+                    queue.push_back((&channel.payee, hops + 1));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dag::validator::{TransactionValidator, ValidationError};
+    use crate::dag::vertex::{Dag, DagVertex};
+
+    /// A DAG built one transaction per depth, where `funder` starts with
+    /// 1_000_000
+    struct Fixture {
+        dag: Dag,
+        last: Hash,
+        depth: u64,
+    }
+
+    impl Fixture {
+        fn new(funder: &KeyPair) -> Self {
+            let mut dag = Dag::new();
+            let genesis = Transaction::genesis(&KeyPair::generate());
+            let last = genesis.id;
+            dag.insert(DagVertex::new(genesis, 0)).unwrap();
+            let mut fixture = Fixture { dag, last, depth: 0 };
+            fixture.add(Transaction::relay_reward(funder, 1_000_000, [last, last], 1));
+            fixture
+        }
+
+        fn parents(&self) -> [Hash; 2] {
+            [self.last, self.last]
+        }
+
+        fn add(&mut self, tx: Transaction) -> Hash {
+            self.depth += 1;
+            self.last = tx.id;
+            self.dag.insert(DagVertex::new(tx, self.depth)).unwrap();
+            self.last
+        }
+
+        /// Enough transactions from distinct keys on top to make everything
+        /// so far final, and the first of them
+        fn finalize(&mut self) {
+            for _ in 0..=crate::FINALITY_THRESHOLD {
+                let tx = Transaction::relay_reward(&KeyPair::generate(), 1, self.parents(), self.depth);
+                self.add(tx);
+            }
+        }
+    }
+
+    fn close(closer: &KeyPair, other: &PublicKey, update: SignedChannelState, parents: [Hash; 2]) -> Transaction {
+        Transaction::channel_close(closer, other.clone(), update, parents, 0)
+    }
+
+    #[test]
+    fn test_payee_close_settles_at_finality() {
+        let (funder, payee) = (KeyPair::generate(), KeyPair::generate());
+        let mut f = Fixture::new(&funder);
+        let open = Transaction::channel_open(&funder, payee.public_key.clone(), 600_000, f.parents(), 1);
+        let id = f.add(open);
+        assert_eq!(f.dag.get_balance(&funder.public_key), 400_000);
+        assert_eq!(f.dag.get_balance(&payee.public_key), 0);
+
+        // A close before the open is final has nothing to close
+        let update = ChannelState::opening(id).pay(1_000).unwrap().pay(2_000).unwrap().sign(&funder);
+        let early = close(&payee, &funder.public_key, update.clone(), f.parents());
+        assert!(matches!(
+            TransactionValidator::validate(&early, &f.dag),
+            Err(ValidationError::InvalidChannel(_))
+        ));
+        f.finalize();
+        assert!(f.dag.channels().get(&id).unwrap().is_open());
+
+        // Only the funder's signature lets the payee close
+        let forged = close(&payee, &funder.public_key, update.state.sign(&payee), f.parents());
+        assert!(TransactionValidator::validate(&forged, &f.dag).is_err());
+        let settle = close(&payee, &funder.public_key, update, f.parents());
+        TransactionValidator::validate(&settle, &f.dag).unwrap();
+        let settle_id = f.add(settle);
+        f.finalize();
+
+        let channel = f.dag.channels().get(&id).unwrap();
+        assert_eq!(channel.status, ChannelStatus::Settled { close: settle_id, paid: 3_000 });
+        assert_eq!(f.dag.get_balance(&payee.public_key), 3_000);
+        assert_eq!(f.dag.get_balance(&funder.public_key), 997_000);
+        let again = close(&payee, &funder.public_key, SignedChannelState::opening(id), f.parents());
+        assert!(TransactionValidator::validate(&again, &f.dag).is_err());
+    }
+
+    #[test]
+    fn test_payee_disputes_stale_funder_close() {
+        let (funder, payee) = (KeyPair::generate(), KeyPair::generate());
+        let mut f = Fixture::new(&funder);
+        let id = f.add(Transaction::channel_open(&funder, payee.public_key.clone(), 500_000, f.parents(), 1));
+        f.finalize();
+
+        // The funder closes on an old state the payee acknowledged
+        let old = ChannelState::opening(id).pay(100).unwrap();
+        let new = old.pay(400).unwrap();
+        let stale = close(&funder, &payee.public_key, old.sign(&payee), f.parents());
+        TransactionValidator::validate(&stale, &f.dag).unwrap();
+        f.add(stale);
+        f.finalize();
+        assert!(matches!(f.dag.channels().get(&id).unwrap().status, ChannelStatus::Closing { .. }));
+        assert_eq!(f.dag.get_balance(&payee.public_key), 0);
+
+        let dispute = close(&payee, &funder.public_key, new.sign(&funder), f.parents());
+        TransactionValidator::validate(&dispute, &f.dag).unwrap();
+        f.add(dispute);
+        f.finalize();
+        assert_eq!(f.dag.get_balance(&payee.public_key), 500);
+        assert_eq!(f.dag.get_balance(&funder.public_key), 1_000_000 - 500);
+    }
+
+    #[test]
+    fn test_funder_close_settles_after_window() {
+        let (funder, payee) = (KeyPair::generate(), KeyPair::generate());
+        let mut f = Fixture::new(&funder);
+        let id = f.add(Transaction::channel_open(&funder, payee.public_key.clone(), 500_000, f.parents(), 1));
+        f.finalize();
+
+        // Nothing was paid, so the opening state needs no signature
+        let mut refund = close(&funder, &payee.public_key, SignedChannelState::opening(id), f.parents());
+        refund.data.timestamp -= CHANNEL_DISPUTE_WINDOW_MS;
+        let refund = Transaction::new(refund.data, &funder);
+        let refund_id = f.add(refund);
+        f.finalize();
+        assert_eq!(
+            f.dag.channels().get(&id).unwrap().status,
+            ChannelStatus::Settled { close: refund_id, paid: 0 }
+        );
+        assert_eq!(f.dag.get_balance(&funder.public_key), 1_000_000);
+    }
+
+    #[test]
+    fn test_routes_over_open_channels() {
+        let keys: Vec<KeyPair> = (0..4).map(|_| KeyPair::generate()).collect();
+        let mut f = Fixture::new(&keys[0]);
+        for (i, pair) in keys.windows(2).enumerate() {
+            let reward = Transaction::relay_reward(&pair[0], 10_000, f.parents(), 100 + i as u64);
+            f.add(reward);
+            let open = Transaction::channel_open(&pair[0], pair[1].public_key.clone(), 1_000, f.parents(), 1);
+            f.add(open);
+        }
+        f.finalize();
+
+        let channels = f.dag.channels();
+        let full = |c: &Channel| c.deposit;
+        let route = channels.route(&keys[0].public_key, &keys[3].public_key, 1_000, full).unwrap();
+        assert_eq!(route.len(), 3);
+        let funders: Vec<&PublicKey> = route.iter().map(|id| &channels.get(id).unwrap().funder).collect();
+        assert_eq!(funders, vec![&keys[0].public_key, &keys[1].public_key, &keys[2].public_key]);
+
+        // Channels only pay one way, and not past their capacity
+        assert!(channels.route(&keys[3].public_key, &keys[0].public_key, 1, full).is_none());
+        assert!(channels.route(&keys[0].public_key, &keys[3].public_key, 1_001, full).is_none());
+        assert_eq!(channels.route(&keys[0].public_key, &keys[0].public_key, 1, full), Some(vec![]));
+    }
+}
@@ -0,0 +1,469 @@
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::vertex::Dag;
+use std::collections::{HashMap, HashSet};
+
+/// A group of concurrent spends from one sender that can't all be funded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSet {
+    /// The account whose funds are double-spent (sender or fee payer)
+    pub sender: PublicKey,
+    /// Every spend in the set
+    pub members: Vec<Hash>,
+    /// Members currently winning, chosen by cumulative weight
+    pub accepted: Vec<Hash>,
+}
+
+impl ConflictSet {
+    /// Members currently losing (excluded from balances)
+    pub fn rejected(&self) -> impl Iterator<Item = &Hash> {
+        self.members.iter().filter(|m| !self.accepted.contains(m))
+    }
+}
+
+/// Tracks double-spends in the DAG and which side of each currently wins
+///
+/// Two spends from the same sender conflict when neither is in the other's
+/// past cone and the sender's credits can't cover both. Members of a conflict
+/// set are accepted greedily while funds allow: members conflict voting
+/// prefers first (see [`crate::consensus::voting`]), then by cumulative
+/// weight, ties broken by id. The rest are excluded from balance calculation
+/// until the vote or the weights change in their favour.
+///
+/// Transfers from one sender with the same nonce replace each other instead:
+/// a final one stands if there is one, otherwise the one offering the highest
+/// priority (then the latest timestamp, then the highest id). The others are
+/// superseded and excluded like conflict losers, whatever order they arrived
+/// in.
+#[derive(Debug, Clone, Default)]
+pub struct ConflictTracker {
+    sets: Vec<ConflictSet>,
+    rejected: HashSet<Hash>,
+    /// Transfers by sender and nonce
+    by_nonce: HashMap<(PublicKey, u64), Vec<Hash>>,
+    /// Sender and nonce pairs used by more than one transfer
+    replaced: Vec<(PublicKey, u64)>,
+    /// Superseded transfers and the transfer standing in for each
+    replaced_by: HashMap<Hash, Hash>,
+    /// Members conflict voting prefers
+    preferred: HashSet<Hash>,
+}
+
+impl ConflictTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All conflict sets
+    pub fn sets(&self) -> &[ConflictSet] {
+        &self.sets
+    }
+
+    /// Whether a transaction is on the losing side of a conflict or was
+    /// replaced
+    pub fn is_rejected(&self, id: &Hash) -> bool {
+        self.rejected.contains(id)
+    }
+
+    /// Whether a transfer was replaced by another with the same nonce
+    pub fn is_superseded(&self, id: &Hash) -> bool {
+        self.replaced_by.contains_key(id)
+    }
+
+    /// The transfer that currently replaces a superseded one
+    pub fn replacement_of(&self, id: &Hash) -> Option<Hash> {
+        self.replaced_by.get(id).copied()
+    }
+
+    /// Transfers signed by `sender` with `nonce`, in arrival order
+    pub fn same_nonce(&self, sender: &PublicKey, nonce: u64) -> &[Hash] {
+        self.by_nonce
+            .get(&(sender.clone(), nonce))
+            .map_or(&[], |ids| ids.as_slice())
+    }
+
+    /// The transfer standing for `sender`'s `nonce`, if there is one
+    pub fn current(&self, sender: &PublicKey, nonce: u64) -> Option<Hash> {
+        self.same_nonce(sender, nonce)
+            .iter()
+            .find(|id| !self.replaced_by.contains_key(id))
+            .copied()
+    }
+
+    /// The conflict set containing a transaction, if any
+    pub fn set_of(&self, id: &Hash) -> Option<&ConflictSet> {
+        self.sets.iter().find(|s| s.members.contains(id))
+    }
+
+    /// Members conflict voting prefers
+    pub fn preferred(&self) -> &HashSet<Hash> {
+        &self.preferred
+    }
+
+    pub(crate) fn set_preferred(&mut self, preferred: HashSet<Hash>) {
+        self.preferred = preferred;
+    }
+
+    /// Check a freshly inserted vertex for conflicts with earlier spends
+    pub(crate) fn on_insert(&mut self, dag: &Dag, id: Hash) {
+        let Some(vertex) = dag.get(&id) else {
+            return;
+        };
+        if vertex.parents()[0].is_zero() {
+            return;
+        }
+        let data = &vertex.transaction.data;
+        if data.tx_type.is_payment() {
+            let key = (data.sender.clone(), data.nonce);
+            let members = self.by_nonce.entry(key.clone()).or_default();
+            members.push(id);
+            if members.len() == 2 {
+                self.replaced.push(key.clone());
+            }
+            self.pick_replacement(dag, &key);
+            // A replacement that lost on arrival spends nothing
+            if self.is_superseded(&id) {
+                return;
+            }
+        }
+
+        // A sponsored transfer spends from both the sender and the fee payer
+        let mut payers = vec![data.sender.clone()];
+        if let Some(fee_payer) = &data.fee_payer {
+            payers.push(fee_payer.clone());
+        }
+
+        for payer in payers {
+            if data.debit_for(&payer) > 0 {
+                self.check_payer(dag, id, vertex.parents(), payer);
+            }
+        }
+    }
+
+    fn check_payer(&mut self, dag: &Dag, id: Hash, parents: &[Hash], payer: PublicKey) {
+        // Only worth a closer look if the payer is overdrawn overall
+        let credits = credits_of(dag, &payer);
+        let spends = spends_of(dag, &payer);
+        let debits: u128 = spends
+            .iter()
+            .filter(|s| !self.rejected.contains(s) && !self.is_superseded(s))
+            .map(|s| debit_of(dag, s, &payer))
+            .sum();
+        if debits <= credits {
+            return;
+        }
+
+        // Spends this one couldn't have seen are the ones it conflicts with
+        let cone = dag.past_cone(parents);
+        let concurrent: Vec<Hash> = spends
+            .into_iter()
+            .filter(|s| *s != id && !cone.contains(s) && !self.is_superseded(s))
+            .collect();
+        if concurrent.is_empty() {
+            return;
+        }
+
+        // Merge with any existing sets that share members
+        let mut members = vec![id];
+        members.extend(concurrent);
+        let mut i = 0;
+        while i < self.sets.len() {
+            let set = &self.sets[i];
+            if set.sender == payer && set.members.iter().any(|m| members.contains(m)) {
+                let set = self.sets.remove(i);
+                for m in set.members {
+                    if !members.contains(&m) {
+                        members.push(m);
+                    }
+                }
+            } else {
+                i += 1;
+            }
+        }
+        self.sets.push(ConflictSet {
+            sender: payer,
+            members,
+            accepted: Vec::new(),
+        });
+    }
+
+    /// Pick which of the transfers sharing a sender and nonce stands
+    fn pick_replacement(&mut self, dag: &Dag, key: &(PublicKey, u64)) {
+        let Some(members) = self.by_nonce.get(key) else {
+            return;
+        };
+        if members.len() < 2 {
+            return;
+        }
+        let head = members
+            .iter()
+            .copied()
+            .max_by_key(|m| {
+                let rank = dag.get(m).map(|v| (v.is_final, v.transaction.data.priority(), v.transaction.data.timestamp));
+                // A pruned member was final, so it stands
+                (dag.pruned().contains(m), rank, *m.as_bytes())
+            })
+            .expect("at least two members");
+        for m in members {
+            if *m == head {
+                self.replaced_by.remove(m);
+            } else {
+                self.replaced_by.insert(*m, head);
+            }
+        }
+    }
+
+    /// Drop pruned transactions, leaving the conflicts they were in settled
+    /// as they stand
+    ///
+    /// Pruned winners' spends are already in the pruned balances, so members
+    /// left behind are re-checked against what remains. Pruned members of a
+    /// same-nonce group stay listed, so the others remain superseded.
+    pub(crate) fn forget(&mut self, pruned: &HashSet<Hash>) {
+        for set in &mut self.sets {
+            set.members.retain(|m| !pruned.contains(m));
+            set.accepted.retain(|m| !pruned.contains(m));
+        }
+        self.sets.retain(|set| !set.members.is_empty());
+        self.rejected.retain(|id| !pruned.contains(id));
+        self.preferred.retain(|id| !pruned.contains(id));
+        self.replaced_by.retain(|id, _| !pruned.contains(id));
+        self.by_nonce
+            .retain(|_, ids| ids.len() > 1 || !ids.iter().all(|id| pruned.contains(id)));
+        let by_nonce = &self.by_nonce;
+        self.replaced.retain(|key| by_nonce.contains_key(key));
+    }
+
+    /// Re-pick the winners of every conflict set from current weights
+    pub(crate) fn resolve(&mut self, dag: &Dag) {
+        // Finality can settle a replacement the other way round
+        for key in self.replaced.clone() {
+            self.pick_replacement(dag, &key);
+        }
+        self.rejected.clear();
+        self.rejected.extend(self.replaced_by.keys().copied());
+
+        for set in &mut self.sets {
+            let credits = credits_of(dag, &set.sender);
+            let outside: u128 = spends_of(dag, &set.sender)
+                .iter()
+                .filter(|s| !set.members.contains(s) && !self.replaced_by.contains_key(s))
+                .map(|s| debit_of(dag, s, &set.sender))
+                .sum();
+            let mut available = credits.saturating_sub(outside);
+
+            let mut ranked: Vec<Hash> = set
+                .members
+                .iter()
+                .filter(|m| !self.replaced_by.contains_key(m))
+                .copied()
+                .collect();
+            ranked.sort_by_key(|m| {
+                let weight = dag.get(m).map_or(0, |v| v.cumulative_weight);
+                (!self.preferred.contains(m), std::cmp::Reverse(weight), *m.as_bytes())
+            });
+
+            set.accepted.clear();
+            for m in ranked {
+                let amount = debit_of(dag, &m, &set.sender);
+                if amount <= available {
+                    available -= amount;
+                    set.accepted.push(m);
+                } else {
+                    self.rejected.insert(m);
+                }
+            }
+        }
+    }
+}
+
+/// Amount a transaction debits from `key`
+fn debit_of(dag: &Dag, id: &Hash, key: &PublicKey) -> u128 {
+    dag.get(id)
+        .filter(|v| !v.parents()[0].is_zero())
+        .map_or(0, |v| v.transaction.data.debit_for(key))
+}
+
+/// Every transaction that debits `key`
+fn spends_of(dag: &Dag, key: &PublicKey) -> Vec<Hash> {
+    dag.transaction_ids()
+        .into_iter()
+        .filter(|id| debit_of(dag, id, key) > 0)
+        .collect()
+}
+
+/// Total credited to a key anywhere in the DAG, pruned spends already
+/// deducted; time-locked credits count once the DAG is past their lock
+fn credits_of(dag: &Dag, key: &PublicKey) -> u128 {
+    let (depth, newest) = dag.frontier();
+    let credits: u128 = dag
+        .transaction_ids()
+        .iter()
+        .filter_map(|id| dag.get(id))
+        .filter(|v| v.transaction.data.unlocked_at(depth, newest))
+        .map(|v| v.transaction.data.credit_for(key))
+        .sum();
+    credits + dag.pruned().balance(key) as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::{Dag, DagVertex};
+    use crate::dag::validator::TransactionValidator;
+
+    /// Genesis plus a 1_000_000 reward to `kp`; returns the reward id
+    fn funded_dag(kp: &KeyPair) -> (Dag, crate::crypto::Hash) {
+        let genesis = Transaction::genesis(kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let reward = Transaction::relay_reward(kp, 1_000_000, [genesis_id, genesis_id], 1);
+        let reward_id = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+        (dag, reward_id)
+    }
+
+    #[test]
+    fn test_double_spend_forms_conflict_set() {
+        let kp = KeyPair::generate();
+        let (mut dag, reward_id) = funded_dag(&kp);
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+
+        let tx1 = Transaction::transfer(&kp, alice.public_key.clone(), 800_000, [reward_id, reward_id], 2);
+        let tx2 = Transaction::transfer(&kp, bob.public_key.clone(), 800_000, [reward_id, reward_id], 3);
+
+        // Each spend is valid against its own past cone
+        assert!(TransactionValidator::validate(&tx1, &dag).is_ok());
+        assert!(TransactionValidator::validate(&tx2, &dag).is_ok());
+
+        dag.insert(DagVertex::new(tx1.clone(), 2)).unwrap();
+        dag.insert(DagVertex::new(tx2.clone(), 2)).unwrap();
+
+        let set = dag.conflicts().set_of(&tx1.id).unwrap();
+        assert_eq!(set.members.len(), 2);
+        assert_eq!(set.accepted.len(), 1);
+
+        // Only the winning spend counts
+        assert_eq!(dag.get_balance(&kp.public_key), 200_000);
+        let alice_balance = dag.get_balance(&alice.public_key);
+        let bob_balance = dag.get_balance(&bob.public_key);
+        assert_eq!(alice_balance + bob_balance, 800_000);
+    }
+
+    #[test]
+    fn test_heavier_branch_wins() {
+        let kp = KeyPair::generate();
+        let (mut dag, reward_id) = funded_dag(&kp);
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+
+        let tx1 = Transaction::transfer(&kp, alice.public_key.clone(), 800_000, [reward_id, reward_id], 2);
+        let tx2 = Transaction::transfer(&kp, bob.public_key.clone(), 800_000, [reward_id, reward_id], 3);
+        let tx2_id = tx2.id;
+        dag.insert(DagVertex::new(tx1, 2)).unwrap();
+        dag.insert(DagVertex::new(tx2, 2)).unwrap();
+
+        // Pile approvals onto tx2
+        let approver = KeyPair::generate();
+        let mut last = tx2_id;
+        for i in 0..5 {
+            let tx = Transaction::relay_reward(&approver, 1, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, 3 + i)).unwrap();
+        }
+
+        assert!(!dag.conflicts().is_rejected(&tx2_id));
+        assert_eq!(dag.get_balance(&bob.public_key), 800_000);
+        assert_eq!(dag.get_balance(&alice.public_key), 0);
+    }
+
+    #[test]
+    fn test_sequential_spends_do_not_conflict() {
+        let kp = KeyPair::generate();
+        let (mut dag, reward_id) = funded_dag(&kp);
+        let alice = KeyPair::generate();
+
+        let tx1 = Transaction::transfer(&kp, alice.public_key.clone(), 400_000, [reward_id, reward_id], 2);
+        let tx1_id = tx1.id;
+        dag.insert(DagVertex::new(tx1, 2)).unwrap();
+        let tx2 = Transaction::transfer(&kp, alice.public_key.clone(), 400_000, [tx1_id, tx1_id], 3);
+        dag.insert(DagVertex::new(tx2, 3)).unwrap();
+
+        assert!(dag.conflicts().sets().is_empty());
+        assert_eq!(dag.get_balance(&alice.public_key), 800_000);
+    }
+
+    #[test]
+    fn test_replacement_supersedes_original() {
+        let kp = KeyPair::generate();
+        let (mut dag, reward_id) = funded_dag(&kp);
+        let alice = KeyPair::generate();
+
+        let original = Transaction::transfer_with_memo(
+            &kp,
+            alice.public_key.clone(),
+            900_000,
+            Some("invoice 41".into()),
+            [reward_id, reward_id],
+            2,
+        );
+        dag.insert(DagVertex::new(original.clone(), 2)).unwrap();
+
+        // Fix the memo and bump the tip; the original's funds come back
+        let replacement = Transaction::replacement(&original, &kp, Some("invoice 42".into()), 10, dag.select_parents());
+        assert!(TransactionValidator::validate(&replacement, &dag).is_ok());
+        dag.insert(DagVertex::new(replacement.clone(), 3)).unwrap();
+
+        let conflicts = dag.conflicts();
+        assert!(conflicts.is_superseded(&original.id));
+        assert!(conflicts.is_rejected(&original.id));
+        assert_eq!(conflicts.replacement_of(&original.id), Some(replacement.id));
+        assert_eq!(conflicts.current(&kp.public_key, 2), Some(replacement.id));
+        assert!(conflicts.sets().is_empty());
+
+        assert_eq!(dag.get_balance(&alice.public_key), 900_000);
+        assert_eq!(dag.get_balance(&kp.public_key), 1_000_000 - 900_010);
+    }
+
+    #[test]
+    fn test_replacement_wins_in_any_order() {
+        let kp = KeyPair::generate();
+        let (mut dag_a, reward_id) = funded_dag(&kp);
+        let mut dag_b = dag_a.clone();
+        let alice = KeyPair::generate();
+
+        let original = Transaction::transfer(&kp, alice.public_key.clone(), 500_000, [reward_id, reward_id], 2);
+        let bumped = Transaction::replacement(&original, &kp, None, 5, [reward_id, reward_id]);
+
+        dag_a.insert(DagVertex::new(original.clone(), 2)).unwrap();
+        dag_a.insert(DagVertex::new(bumped.clone(), 2)).unwrap();
+        dag_b.insert(DagVertex::new(bumped.clone(), 2)).unwrap();
+        dag_b.insert(DagVertex::new(original.clone(), 2)).unwrap();
+
+        for dag in [&dag_a, &dag_b] {
+            assert!(dag.conflicts().is_superseded(&original.id));
+            assert!(!dag.conflicts().is_superseded(&bumped.id));
+            assert_eq!(dag.get_balance(&alice.public_key), 500_000);
+        }
+    }
+
+    #[test]
+    fn test_tip_selection_prefers_replacement() {
+        let kp = KeyPair::generate();
+        let (mut dag, reward_id) = funded_dag(&kp);
+        let alice = KeyPair::generate();
+
+        let original = Transaction::transfer(&kp, alice.public_key.clone(), 500_000, [reward_id, reward_id], 2);
+        let bumped = Transaction::replacement(&original, &kp, None, 5, [reward_id, reward_id]);
+        dag.insert(DagVertex::new(original.clone(), 2)).unwrap();
+        dag.insert(DagVertex::new(bumped.clone(), 2)).unwrap();
+
+        // Both are tips, but new transactions approve only the replacement
+        assert_eq!(dag.tip_count(), 2);
+        assert_eq!(dag.select_parents(), [bumped.id, bumped.id]);
+        let mut rng = crate::testkit::seeded_rng();
+        assert_eq!(dag.select_parents_random(2, &mut rng), [bumped.id, bumped.id]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::transaction::TransactionType;
+use crate::dag::vertex::Dag;
+use crate::wallet::address::Address;
+use serde::Serialize;
+
+/// The founder grant and everything spent from it, as recorded in the DAG
+///
+/// Everything here is derived from the DAG, so any node can produce it and
+/// anyone can check one node's report against another's.
+#[derive(Debug, Clone, Serialize)]
+pub struct FounderReport {
+    pub public_key: PublicKey,
+    pub address: String,
+    /// Size of the grant the protocol allows
+    pub allocation: u64,
+    /// The allocation transaction, once the DAG has one
+    pub allocation_id: Option<Hash>,
+    pub vesting: VestingStatus,
+    pub balance: u64,
+    pub confirmed_balance: u64,
+    /// Sum of the spends that count (not on the losing side of a double-spend
+    /// or replaced)
+    pub total_spent: u64,
+    /// Every transaction debiting the founder key, oldest first (spends
+    /// pruned from the DAG are only reflected in the balances)
+    pub spends: Vec<FounderSpend>,
+}
+
+/// How much of the grant can be spent
+///
+/// The protocol has no vesting schedule: the whole allocation is spendable
+/// from the moment it is minted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VestingStatus {
+    pub schedule: &'static str,
+    pub vested: u64,
+    pub locked: u64,
+}
+
+/// A transaction that debited the founder key
+#[derive(Debug, Clone, Serialize)]
+pub struct FounderSpend {
+    pub id: Hash,
+    pub tx_type: TransactionType,
+    pub recipient: PublicKey,
+    /// Everything it took from the founder key: amount, fee and relay tip
+    pub debited: u64,
+    pub timestamp: u64,
+    pub is_final: bool,
+    /// Lost a double-spend or was replaced, so the debit never happened
+    pub rejected: bool,
+}
+
+impl FounderReport {
+    /// Build the report for the pinned founder key
+    pub fn analyze(dag: &Dag) -> Self {
+        Self::for_key(dag, &crate::founder_public_key())
+    }
+
+    /// Build the report for `founder`
+    pub fn for_key(dag: &Dag, founder: &PublicKey) -> Self {
+        let allocation = dag.founder_allocation_id.and_then(|id| dag.get(&id));
+
+        let mut spends: Vec<FounderSpend> = dag
+            .iter()
+            .filter_map(|vertex| {
+                let data = &vertex.transaction.data;
+                let debited = data.debit_for(founder);
+                (debited > 0).then(|| FounderSpend {
+                    id: vertex.id(),
+                    tx_type: data.tx_type.clone(),
+                    recipient: data.recipient.clone(),
+                    debited: debited.min(u64::MAX as u128) as u64,
+                    timestamp: data.timestamp,
+                    is_final: vertex.is_final,
+                    rejected: dag.conflicts().is_rejected(&vertex.id()),
+                })
+            })
+            .collect();
+        spends.sort_by_key(|s| (s.timestamp, s.id));
+        let total_spent = spends
+            .iter()
+            .filter(|s| !s.rejected)
+            .fold(0u64, |total, s| total.saturating_add(s.debited));
+
+        // A pruned allocation was validated, so it minted the fixed amount
+        let minted = match allocation {
+            Some(vertex) => vertex.transaction.data.amount,
+            None if dag.founder_allocation_id.is_some() => crate::FOUNDER_ALLOCATION,
+            None => 0,
+        };
+        FounderReport {
+            public_key: founder.clone(),
+            address: Address::from_public_key(founder).to_string(),
+            allocation: crate::FOUNDER_ALLOCATION,
+            allocation_id: dag.founder_allocation_id,
+            vesting: VestingStatus {
+                schedule: "none",
+                vested: minted,
+                locked: 0,
+            },
+            balance: dag.get_balance(founder),
+            confirmed_balance: dag.get_confirmed_balance(founder),
+            total_spent,
+            spends,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::DagVertex;
+
+    #[test]
+    fn test_founder_report() {
+        let genesis_kp = KeyPair::generate();
+        let founder = KeyPair::generate();
+        let merchant = KeyPair::generate();
+        let genesis = Transaction::genesis(&genesis_kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+
+        let report = FounderReport::for_key(&dag, &founder.public_key);
+        assert_eq!(report.allocation_id, None);
+        assert_eq!(report.vesting.vested, 0);
+        assert!(report.spends.is_empty());
+
+        let grant = Transaction::founder_allocation(&genesis_kp, founder.public_key.clone(), genesis_id);
+        let grant_id = grant.id;
+        dag.insert(DagVertex::new(grant, 1)).unwrap();
+        let spend = Transaction::transfer_with_tip(
+            &founder,
+            merchant.public_key.clone(),
+            500,
+            None,
+            7,
+            [grant_id, grant_id],
+            1,
+        );
+        let spend_id = spend.id;
+        dag.insert(DagVertex::new(spend, 2)).unwrap();
+        // Unrelated traffic isn't listed
+        let other = Transaction::key_announcement(&merchant, [spend_id, spend_id], 1);
+        dag.insert(DagVertex::new(other, 3)).unwrap();
+
+        let report = FounderReport::for_key(&dag, &founder.public_key);
+        assert_eq!(report.allocation_id, Some(grant_id));
+        assert_eq!(report.vesting.vested, crate::FOUNDER_ALLOCATION);
+        assert_eq!(report.spends.len(), 1);
+        assert_eq!(report.spends[0].id, spend_id);
+        assert_eq!(report.spends[0].debited, 507);
+        assert!(!report.spends[0].rejected);
+        assert_eq!(report.total_spent, 507);
+        assert_eq!(report.balance, crate::FOUNDER_ALLOCATION - 507);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::dag::vertex::Dag;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Tips this many depths behind the frontier are considered orphaned
+pub const ORPHAN_DEPTH_WINDOW: u64 = 10;
+
+/// Tips whose newest parent is this many depths behind the frontier are lazy
+pub const LAZY_TIP_DEPTH_GAP: u64 = 5;
+
+/// Number of most recent depths included in the width histogram
+const WIDTH_HISTORY: usize = 50;
+
+/// Structural metrics describing how healthy the DAG's shape is
+#[derive(Debug, Clone, Serialize)]
+pub struct DagHealth {
+    pub vertices: usize,
+    pub depth: u64,
+    pub tips: usize,
+    /// Unapproved vertices left behind the frontier, as a fraction of all vertices
+    pub orphan_rate: f64,
+    /// Mean distance between a vertex's depth and its parents' depths
+    pub avg_parent_depth_gap: f64,
+    /// Fraction of tips that approve stale parents
+    pub lazy_tip_ratio: f64,
+    pub width: WidthStats,
+    pub weight: WeightPercentiles,
+}
+
+/// Vertices per depth
+#[derive(Debug, Clone, Serialize)]
+pub struct WidthStats {
+    pub mean: f64,
+    pub max: usize,
+    /// Width of the most recent depths, oldest first
+    pub recent: Vec<DepthWidth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DepthWidth {
+    pub depth: u64,
+    pub width: usize,
+}
+
+/// Distribution of cumulative weights
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WeightPercentiles {
+    pub p10: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl DagHealth {
+    /// Compute health metrics for the whole DAG
+    pub fn analyze(dag: &Dag) -> Self {
+        let depth = dag.depth();
+        let tips = dag.tips();
+
+        let mut widths: BTreeMap<u64, usize> = BTreeMap::new();
+        let mut weights = Vec::with_capacity(dag.len());
+        let mut gap_sum = 0.0;
+        let mut gap_count = 0usize;
+
+        for vertex in dag.iter() {
+            *widths.entry(vertex.depth).or_default() += 1;
+            weights.push(vertex.cumulative_weight);
+
+            let parent_depths: Vec<u64> = vertex
+                .parents()
+                .iter()
+                .filter_map(|p| dag.get(p).map(|v| v.depth))
+                .collect();
+            if !parent_depths.is_empty() {
+                let mean = parent_depths.iter().sum::<u64>() as f64 / parent_depths.len() as f64;
+                gap_sum += vertex.depth as f64 - mean;
+                gap_count += 1;
+            }
+        }
+
+        let frontier = depth.saturating_sub(ORPHAN_DEPTH_WINDOW);
+        let orphans = tips
+            .iter()
+            .filter_map(|t| dag.get(t))
+            .filter(|v| v.depth < frontier)
+            .count();
+
+        let lazy_threshold = depth.saturating_sub(LAZY_TIP_DEPTH_GAP);
+        let lazy = tips
+            .iter()
+            .filter_map(|t| dag.get(t))
+            .filter(|v| {
+                let newest_parent = v
+                    .parents()
+                    .iter()
+                    .filter_map(|p| dag.get(p).map(|p| p.depth))
+                    .max();
+                newest_parent.is_some_and(|d| d < lazy_threshold)
+            })
+            .count();
+
+        DagHealth {
+            vertices: dag.len(),
+            depth,
+            tips: tips.len(),
+            orphan_rate: ratio(orphans, dag.len()),
+            avg_parent_depth_gap: if gap_count == 0 { 0.0 } else { gap_sum / gap_count as f64 },
+            lazy_tip_ratio: ratio(lazy, tips.len()),
+            width: WidthStats {
+                mean: ratio(dag.len(), widths.len()),
+                max: widths.values().copied().max().unwrap_or(0),
+                recent: widths
+                    .iter()
+                    .rev()
+                    .take(WIDTH_HISTORY)
+                    .rev()
+                    .map(|(&depth, &width)| DepthWidth { depth, width })
+                    .collect(),
+            },
+            weight: WeightPercentiles::from_weights(weights),
+        }
+    }
+}
+
+impl WeightPercentiles {
+    fn from_weights(mut weights: Vec<u64>) -> Self {
+        if weights.is_empty() {
+            return Self::default();
+        }
+        weights.sort_unstable();
+        let at = |p: usize| weights[(weights.len() - 1) * p / 100];
+        WeightPercentiles {
+            p10: at(10),
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: weights[weights.len() - 1],
+        }
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::DagVertex;
+
+    #[test]
+    fn test_empty_dag() {
+        let health = DagHealth::analyze(&Dag::new());
+        assert_eq!(health.vertices, 0);
+        assert_eq!(health.orphan_rate, 0.0);
+        assert_eq!(health.weight.max, 0);
+    }
+
+    #[test]
+    fn test_orphaned_and_lazy_tips() {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+
+        // A side branch that is never approved again
+        let stale = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 100);
+        dag.insert(DagVertex::new(stale, 1)).unwrap();
+
+        let mut last = genesis_id;
+        for i in 1..=20 {
+            let tx = Transaction::relay_reward(&kp, 1, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        // A tip at the frontier that approves the genesis
+        let lazy = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 200);
+        dag.insert(DagVertex::new(lazy, 21)).unwrap();
+
+        let health = DagHealth::analyze(&dag);
+        assert_eq!(health.tips, 3);
+        assert_eq!(health.orphan_rate, 1.0 / 23.0);
+        // The orphaned branch approves stale parents too
+        assert!((health.lazy_tip_ratio - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(health.width.max, 2);
+        assert!(health.avg_parent_depth_gap > 1.0);
+        assert!(health.weight.p50 <= health.weight.p90);
+        assert_eq!(health.weight.max, dag.get(&genesis_id).unwrap().cumulative_weight);
+    }
+}
@@ -0,0 +1,380 @@
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::transaction::{lock_passed, Transaction, TransactionType};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// The secret an HTLC is claimed with
+///
+/// A lock carries its SHA-256, the hash scripts on Bitcoin and most other
+/// chains check, so one secret can open both sides of a swap. It is always
+/// 32 bytes: a chain taking longer secrets could be claimed with one the
+/// other chain refuses.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preimage(#[serde(with = "crate::crypto::hash::hash_serde")] [u8; 32]);
+
+impl Preimage {
+    /// A fresh random secret
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        Preimage(bytes)
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Preimage(bytes)
+    }
+
+    /// Parse from a 64-character hex string
+    pub fn from_hex(s: &str) -> Result<Self, hex::FromHexError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(Preimage(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The hash a lock this secret opens carries
+    pub fn hash_lock(&self) -> Hash {
+        Hash::from_bytes(Sha256::digest(self.0).into())
+    }
+}
+
+/// Kept out of logs until a claim reveals it
+impl fmt::Debug for Preimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Preimage(..)")
+    }
+}
+
+impl fmt::Display for Preimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// The part of an HTLC transaction that isn't a plain payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HtlcTerms {
+    /// On a lock: the hash of the secret that claims it, and from when the
+    /// locker may take it back, a DAG depth or Unix ms as for
+    /// [`valid_after`](crate::dag::TransactionData::valid_after)
+    Lock { hash_lock: Hash, timeout: u64 },
+    /// On a claim: the lock and the secret that opens it
+    Claim { lock: Hash, preimage: Preimage },
+    /// On a refund: the lock
+    Refund { lock: Hash },
+}
+
+/// A hash-time-locked contract as final history has it
+///
+/// The locker's funds go to the claimer on a claim with the secret, or
+/// back to the locker on a refund once the timeout has passed; whichever
+/// becomes final first settles it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Htlc {
+    /// Id of the transaction that locked the funds
+    pub id: Hash,
+    pub locker: PublicKey,
+    pub claimer: PublicKey,
+    pub amount: u64,
+    /// SHA-256 of the secret that claims it
+    pub hash_lock: Hash,
+    pub timeout: u64,
+    pub status: HtlcStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HtlcStatus {
+    Open,
+    /// Paid to the claimer by `claim`, which revealed `preimage`
+    Claimed { claim: Hash, preimage: Preimage },
+    /// Paid back to the locker by `refund`
+    Refunded { refund: Hash },
+}
+
+impl Htlc {
+    pub fn is_open(&self) -> bool {
+        self.status == HtlcStatus::Open
+    }
+
+    /// Whether a refund at `depth`, dated `timestamp`, is past the timeout
+    pub fn expired_at(&self, depth: u64, timestamp: u64) -> bool {
+        lock_passed(self.timeout, depth, timestamp)
+    }
+
+    /// What settling the HTLC paid `key`
+    pub fn credit_for(&self, key: &PublicKey) -> u64 {
+        match self.status {
+            HtlcStatus::Claimed { .. } if *key == self.claimer => self.amount,
+            HtlcStatus::Refunded { .. } if *key == self.locker => self.amount,
+            _ => 0,
+        }
+    }
+}
+
+/// Open and settled HTLCs, as final history has them
+///
+/// Like [`Channels`](crate::dag::Channels), they change only when
+/// transactions become final, so a claim and a refund racing each other
+/// settle the same way on every node.
+#[derive(Debug, Clone, Default)]
+pub struct Htlcs {
+    htlcs: HashMap<Hash, Htlc>,
+    /// Settling claim or refund -> its lock
+    settled_by: HashMap<Hash, Hash>,
+}
+
+impl Htlcs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Htlc> {
+        self.htlcs.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Htlc> {
+        self.htlcs.values()
+    }
+
+    /// The HTLC a claim or refund settled, if it did
+    pub fn settled_by(&self, id: &Hash) -> Option<&Htlc> {
+        self.settled_by.get(id).and_then(|lock| self.htlcs.get(lock))
+    }
+
+    /// Put back an HTLC from a balance snapshot
+    pub(crate) fn restore(&mut self, htlc: Htlc) {
+        match htlc.status {
+            HtlcStatus::Claimed { claim: by, .. } | HtlcStatus::Refunded { refund: by } => {
+                self.settled_by.insert(by, htlc.id);
+            }
+            HtlcStatus::Open => {}
+        }
+        self.htlcs.insert(htlc.id, htlc);
+    }
+
+    /// Apply a transaction that just became final, returning the HTLC it
+    /// settled, if any
+    ///
+    /// A claim or refund for an HTLC already settled changes nothing.
+    pub(crate) fn record(&mut self, tx: &Transaction) -> Option<&Htlc> {
+        let data = &tx.data;
+        match (&data.tx_type, data.htlc.as_ref()?) {
+            (TransactionType::HtlcLock, HtlcTerms::Lock { hash_lock, timeout }) => {
+                self.htlcs.entry(tx.id).or_insert_with(|| Htlc {
+                    id: tx.id,
+                    locker: data.sender.clone(),
+                    claimer: data.recipient.clone(),
+                    amount: data.amount,
+                    hash_lock: *hash_lock,
+                    timeout: *timeout,
+                    status: HtlcStatus::Open,
+                });
+                None
+            }
+            (TransactionType::HtlcClaim, HtlcTerms::Claim { lock, preimage }) => {
+                let htlc = self.htlcs.get_mut(lock)?;
+                if !htlc.is_open() || data.sender != htlc.claimer || preimage.hash_lock() != htlc.hash_lock {
+                    return None;
+                }
+                htlc.status = HtlcStatus::Claimed { claim: tx.id, preimage: *preimage };
+                self.settled_by.insert(tx.id, htlc.id);
+                Some(&*htlc)
+            }
+            (TransactionType::HtlcRefund, HtlcTerms::Refund { lock }) => {
+                let htlc = self.htlcs.get_mut(lock)?;
+                if !htlc.is_open() || data.sender != htlc.locker {
+                    return None;
+                }
+                htlc.status = HtlcStatus::Refunded { refund: tx.id };
+                self.settled_by.insert(tx.id, htlc.id);
+                Some(&*htlc)
+            }
+            _ => None,
+        }
+    }
+
+    /// Drop HTLCs settled by a transaction in `pruned`; their payouts are in
+    /// pruned balances now
+    pub(crate) fn forget(&mut self, pruned: &HashSet<Hash>) {
+        self.settled_by.retain(|by, _| !pruned.contains(by));
+        let settled_by = &self.settled_by;
+        self.htlcs.retain(|_, htlc| match &htlc.status {
+            HtlcStatus::Claimed { claim: by, .. } | HtlcStatus::Refunded { refund: by } => {
+                settled_by.contains_key(by)
+            }
+            HtlcStatus::Open => true,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::validator::{TransactionValidator, ValidationError};
+    use crate::dag::vertex::{Dag, DagVertex};
+
+    /// A DAG built one transaction per depth, where `locker` starts with
+    /// 1_000_000
+    struct Fixture {
+        dag: Dag,
+        last: Hash,
+        depth: u64,
+    }
+
+    impl Fixture {
+        fn new(locker: &KeyPair) -> Self {
+            let mut dag = Dag::new();
+            let genesis = Transaction::genesis(&KeyPair::generate());
+            let last = genesis.id;
+            dag.insert(DagVertex::new(genesis, 0)).unwrap();
+            let mut fixture = Fixture { dag, last, depth: 0 };
+            fixture.add(Transaction::relay_reward(locker, 1_000_000, [last, last], 1));
+            fixture
+        }
+
+        fn parents(&self) -> [Hash; 2] {
+            [self.last, self.last]
+        }
+
+        fn add(&mut self, tx: Transaction) -> Hash {
+            self.depth += 1;
+            self.last = tx.id;
+            self.dag.insert(DagVertex::new(tx, self.depth)).unwrap();
+            self.last
+        }
+
+        /// Enough transactions from distinct keys on top to make everything
+        /// so far final
+        fn finalize(&mut self) {
+            for _ in 0..=crate::FINALITY_THRESHOLD {
+                let tx = Transaction::relay_reward(&KeyPair::generate(), 1, self.parents(), self.depth);
+                self.add(tx);
+            }
+        }
+    }
+
+    fn is_invalid_htlc(result: Result<(), ValidationError>) -> bool {
+        matches!(result, Err(ValidationError::InvalidHtlc(_)))
+    }
+
+    #[test]
+    fn test_preimage_hash_is_sha256() {
+        // SHA-256 of 32 zero bytes, as other chains compute it
+        let hash = Preimage::from_bytes([0; 32]).hash_lock();
+        assert_eq!(hash.to_string(), "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925");
+        let secret = Preimage::generate();
+        assert_eq!(Preimage::from_hex(&secret.to_string()).unwrap(), secret);
+        assert_eq!(format!("{:?}", secret), "Preimage(..)");
+    }
+
+    #[test]
+    fn test_claim_with_preimage() {
+        let (locker, claimer) = (KeyPair::generate(), KeyPair::generate());
+        let mut f = Fixture::new(&locker);
+        let secret = Preimage::generate();
+        let timeout = 1_000;
+        let lock = Transaction::htlc_lock(
+            &locker,
+            claimer.public_key.clone(),
+            300_000,
+            secret.hash_lock(),
+            timeout,
+            f.parents(),
+            2,
+        );
+        TransactionValidator::validate(&lock, &f.dag).unwrap();
+        let id = f.add(lock);
+        assert_eq!(f.dag.get_balance(&locker.public_key), 700_000);
+        assert_eq!(f.dag.get_balance(&claimer.public_key), 0);
+
+        // Nothing to claim until the lock is final
+        let claim = |f: &Fixture, preimage| {
+            Transaction::htlc_claim(&claimer, locker.public_key.clone(), id, preimage, f.parents(), 0)
+        };
+        assert!(is_invalid_htlc(TransactionValidator::validate(&claim(&f, secret), &f.dag)));
+        f.finalize();
+        assert!(f.dag.htlcs().get(&id).unwrap().is_open());
+
+        // Only the secret opens it, and only for the claimer
+        let wrong = claim(&f, Preimage::generate());
+        assert!(is_invalid_htlc(TransactionValidator::validate(&wrong, &f.dag)));
+        let thief = KeyPair::generate();
+        let stolen = Transaction::htlc_claim(&thief, locker.public_key.clone(), id, secret, f.parents(), 0);
+        assert!(is_invalid_htlc(TransactionValidator::validate(&stolen, &f.dag)));
+        // Nor may the locker take it back early
+        let refund = Transaction::htlc_refund(&locker, claimer.public_key.clone(), id, f.parents(), 0);
+        assert!(is_invalid_htlc(TransactionValidator::validate(&refund, &f.dag)));
+
+        let claim = claim(&f, secret);
+        TransactionValidator::validate(&claim, &f.dag).unwrap();
+        let claim_id = f.add(claim);
+        // Paid out once the claim is final
+        assert_eq!(f.dag.get_balance(&claimer.public_key), 0);
+        f.finalize();
+        let htlc = f.dag.htlcs().get(&id).unwrap();
+        assert_eq!(htlc.status, HtlcStatus::Claimed { claim: claim_id, preimage: secret });
+        assert_eq!(f.dag.get_balance(&claimer.public_key), 300_000);
+        assert_eq!(f.dag.get_balance(&locker.public_key), 700_000);
+
+        let again = Transaction::htlc_claim(&claimer, locker.public_key.clone(), id, secret, f.parents(), 0);
+        assert!(is_invalid_htlc(TransactionValidator::validate(&again, &f.dag)));
+    }
+
+    #[test]
+    fn test_refund_after_timeout() {
+        let (locker, claimer) = (KeyPair::generate(), KeyPair::generate());
+        let mut f = Fixture::new(&locker);
+        let secret = Preimage::generate();
+        // Already past at the lock's own depth
+        let stale = Transaction::htlc_lock(
+            &locker,
+            claimer.public_key.clone(),
+            1_000,
+            secret.hash_lock(),
+            f.depth + 1,
+            f.parents(),
+            2,
+        );
+        assert!(is_invalid_htlc(TransactionValidator::validate(&stale, &f.dag)));
+
+        let timeout = f.depth + 2 * (crate::FINALITY_THRESHOLD + 1) + 1;
+        let lock = Transaction::htlc_lock(
+            &locker,
+            claimer.public_key.clone(),
+            1_000,
+            secret.hash_lock(),
+            timeout,
+            f.parents(),
+            2,
+        );
+        let id = f.add(lock);
+        f.finalize();
+        let refund =
+            |f: &Fixture| Transaction::htlc_refund(&locker, claimer.public_key.clone(), id, f.parents(), 0);
+        assert!(is_invalid_htlc(TransactionValidator::validate(&refund(&f), &f.dag)));
+        f.finalize();
+        assert!(f.depth + 1 >= timeout);
+
+        let refund = refund(&f);
+        TransactionValidator::validate(&refund, &f.dag).unwrap();
+        // A claim racing the refund is valid too, but only one can settle
+        let late = Transaction::htlc_claim(&claimer, locker.public_key.clone(), id, secret, f.parents(), 0);
+        TransactionValidator::validate(&late, &f.dag).unwrap();
+        let refund_id = f.add(refund);
+        f.finalize();
+        assert_eq!(f.dag.htlcs().get(&id).unwrap().status, HtlcStatus::Refunded { refund: refund_id });
+        assert_eq!(f.dag.get_balance(&locker.public_key), 1_000_000);
+
+        f.add(late);
+        f.finalize();
+        assert_eq!(f.dag.get_balance(&claimer.public_key), 0);
+        assert_eq!(f.dag.get_balance(&locker.public_key), 1_000_000);
+    }
+}
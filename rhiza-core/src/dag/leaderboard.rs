@@ -0,0 +1,202 @@
+use crate::crypto::PublicKey;
+use crate::dag::transaction::TransactionType;
+use crate::dag::vertex::Dag;
+use crate::wallet::address::Address;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// How far back a leaderboard looks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    #[default]
+    Week,
+    Month,
+    /// Everything the DAG still holds
+    All,
+}
+
+impl Period {
+    /// Length of the window, or `None` for all retained history
+    pub fn window_ms(self) -> Option<u64> {
+        match self {
+            Period::Day => Some(DAY_MS),
+            Period::Week => Some(7 * DAY_MS),
+            Period::Month => Some(30 * DAY_MS),
+            Period::All => None,
+        }
+    }
+}
+
+/// One relayer's contribution over a period
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelayerStanding {
+    /// 1 for the relayer with the most relays
+    pub rank: usize,
+    pub relayer: PublicKey,
+    pub address: String,
+    /// Final reward claims
+    pub claims: u64,
+    /// Relay proofs those claims carried
+    pub relays: u64,
+    /// Units minted to the relayer by its claims
+    pub rewards: u64,
+    /// Relay tips its claims collected
+    pub tips: u64,
+    /// Timestamp of its newest claim
+    pub last_claim: u64,
+}
+
+/// Relayers ranked by the relay work their final reward claims proved
+///
+/// Like the founder report, this is derived from the DAG alone, so every
+/// node ranks the same relayers the same way once it has the same final
+/// history. Claims that aren't final yet don't count, and neither do those
+/// already pruned.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayLeaderboard {
+    pub period: Period,
+    /// Claims older than this are left out (0 for `Period::All`)
+    pub since: u64,
+    /// Relayers with at least one claim in the period
+    pub relayers: usize,
+    pub total_claims: u64,
+    pub total_relays: u64,
+    pub total_rewards: u64,
+    /// Best first, at most the requested number
+    pub top: Vec<RelayerStanding>,
+    /// Where the key asked about stands, if it claimed in the period
+    pub local: Option<RelayerStanding>,
+}
+
+impl RelayLeaderboard {
+    /// Rank relayers by their final claims made in `period` before `now_ms`
+    ///
+    /// Ties on relays go to the relayer with more rewards, then to the
+    /// lower key, so the order is the same on every node.
+    pub fn analyze(dag: &Dag, period: Period, now_ms: u64, limit: usize, local: Option<&PublicKey>) -> Self {
+        let since = period.window_ms().map_or(0, |window| now_ms.saturating_sub(window));
+
+        let mut by_relayer: HashMap<&PublicKey, RelayerStanding> = HashMap::new();
+        for vertex in dag.iter() {
+            let data = &vertex.transaction.data;
+            // Claims validated on the DAG always carry proofs; ones without
+            // were put there directly and prove no relay work
+            if data.tx_type != TransactionType::RelayReward || data.relay_proofs.is_empty() {
+                continue;
+            }
+            if !vertex.is_final || data.timestamp < since {
+                continue;
+            }
+            let tips = dag.relay_tips().credit_for(&vertex.id(), dag.conflicts());
+            let standing = by_relayer.entry(&data.sender).or_insert_with(|| RelayerStanding {
+                rank: 0,
+                relayer: data.sender.clone(),
+                address: Address::from_public_key(&data.sender).to_string(),
+                claims: 0,
+                relays: 0,
+                rewards: 0,
+                tips: 0,
+                last_claim: 0,
+            });
+            standing.claims += 1;
+            standing.relays += data.relay_proofs.len() as u64;
+            standing.rewards = standing.rewards.saturating_add(data.amount);
+            standing.tips = standing.tips.saturating_add(tips.min(u64::MAX as u128) as u64);
+            standing.last_claim = standing.last_claim.max(data.timestamp);
+        }
+
+        let mut ranked: Vec<RelayerStanding> = by_relayer.into_values().collect();
+        ranked.sort_by(|a, b| {
+            (Reverse(a.relays), Reverse(a.rewards), a.relayer.as_bytes())
+                .cmp(&(Reverse(b.relays), Reverse(b.rewards), b.relayer.as_bytes()))
+        });
+        for (i, standing) in ranked.iter_mut().enumerate() {
+            standing.rank = i + 1;
+        }
+
+        let local = local.and_then(|key| ranked.iter().find(|s| &s.relayer == key).cloned());
+        RelayLeaderboard {
+            period,
+            since,
+            relayers: ranked.len(),
+            total_claims: ranked.iter().map(|s| s.claims).sum(),
+            total_relays: ranked.iter().map(|s| s.relays).sum(),
+            total_rewards: ranked.iter().fold(0u64, |total, s| total.saturating_add(s.rewards)),
+            top: ranked.into_iter().take(limit).collect(),
+            local,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::relay::RelayProof;
+    use crate::crypto::keys::KeyPair;
+    use crate::crypto::Hash;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::DagVertex;
+
+    /// A claim by `relayer` for relaying `relayed`, approving `parent`
+    fn claim(dag: &mut Dag, relayer: &KeyPair, relayed: &[Hash], parent: Hash, depth: u64) -> Hash {
+        let proofs = relayed.iter().map(|id| RelayProof::new(relayer, *id, 1)).collect();
+        let reward = 10 * relayed.len() as u64;
+        let tx = Transaction::relay_reward_with_proofs(relayer, reward, proofs, [parent, parent], depth);
+        let id = tx.id;
+        dag.insert(DagVertex::new(tx, depth)).unwrap();
+        id
+    }
+
+    /// Approve `id` until it is final
+    fn finalize(dag: &mut Dag, id: Hash, depth: u64) {
+        let mut last = id;
+        for i in 0..crate::FINALITY_THRESHOLD {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 0, [last, last], 1_000 + depth + i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, depth + 1 + i)).unwrap();
+        }
+        assert!(dag.get(&id).unwrap().is_final);
+    }
+
+    #[test]
+    fn test_ranks_final_claims_by_relays() {
+        let genesis_kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&genesis_kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+
+        let (busy, quiet, late) = (KeyPair::generate(), KeyPair::generate(), KeyPair::generate());
+        let first = claim(&mut dag, &quiet, &[genesis_id], genesis_id, 1);
+        let second = claim(&mut dag, &busy, &[genesis_id, first], first, 2);
+        let third = claim(&mut dag, &busy, &[second], second, 3);
+        finalize(&mut dag, third, 3);
+        // Not final yet, so it doesn't count
+        let tip = dag.tips()[0];
+        claim(&mut dag, &late, &[third], tip, 100);
+
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let board = RelayLeaderboard::analyze(&dag, Period::Week, now, 10, Some(&quiet.public_key));
+        assert_eq!(board.relayers, 2);
+        assert_eq!((board.total_claims, board.total_relays, board.total_rewards), (3, 4, 40));
+        assert_eq!(board.top[0].relayer, busy.public_key);
+        assert_eq!((board.top[0].rank, board.top[0].claims, board.top[0].relays), (1, 2, 3));
+        assert_eq!(board.top[1].relayer, quiet.public_key);
+        assert_eq!(board.local.as_ref().map(|s| s.rank), Some(2));
+
+        // The limit trims the list but not the local standing
+        let board = RelayLeaderboard::analyze(&dag, Period::All, now, 1, Some(&quiet.public_key));
+        assert_eq!(board.top.len(), 1);
+        assert_eq!(board.local.map(|s| s.rank), Some(2));
+
+        // A day on, today's claims have left the day window
+        let board = RelayLeaderboard::analyze(&dag, Period::Day, now + DAY_MS + 60_000, 10, None);
+        assert_eq!(board.relayers, 0);
+        assert!(board.local.is_none());
+    }
+}
@@ -1,7 +1,35 @@
+pub mod bundle;
+pub mod channels;
+pub mod conflicts;
+pub mod founder;
+pub mod health;
+pub mod htlc;
+pub mod leaderboard;
+pub mod proofs;
+pub mod pruning;
+pub mod relay_tips;
+pub mod snapshot;
+pub mod tips;
 pub mod transaction;
+pub mod validation_cache;
 pub mod vertex;
 pub mod validator;
+pub mod work;
 
+pub use bundle::Bundle;
+pub use channels::{Channel, ChannelState, Channels};
+pub use conflicts::{ConflictSet, ConflictTracker};
+pub use founder::FounderReport;
+pub use health::DagHealth;
+pub use htlc::{Htlc, HtlcTerms, Htlcs, Preimage};
+pub use leaderboard::RelayLeaderboard;
+pub use proofs::InclusionProof;
+pub use pruning::{BalanceSnapshot, PrunedHistory};
+pub use relay_tips::RelayTips;
+pub use snapshot::{RelaySnapshot, Snapshot};
+pub use tips::TipSet;
 pub use transaction::{Transaction, TransactionData, TransactionType};
 pub use vertex::DagVertex;
-pub use validator::TransactionValidator;
+pub use validator::{TimestampRules, TransactionValidator};
+pub use validation_cache::ValidationCache;
+pub use work::WorkPuzzle;
@@ -0,0 +1,269 @@
+use crate::crypto::Hash;
+use crate::dag::transaction::{Transaction, TransactionData};
+use crate::dag::vertex::Dag;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Most steps an inclusion proof may carry
+pub const MAX_INCLUSION_STEPS: usize = 4096;
+
+/// Evidence that a transaction is in the past cone of a set of final tips,
+/// checkable with only those tips and the genesis id
+///
+/// The proof is one path of parent references from a tip down to the
+/// transaction. Each step carries the data its id is the hash of, so the
+/// verifier recomputes every id on the way down and needs no DAG, clock or
+/// I/O: a wallet that trusts a handful of final tips (from nodes it asked,
+/// or a checkpoint it shipped with) can check any payment below them.
+///
+/// Anything an honest node holds as final is an ancestor of its final tips,
+/// so a transaction that hangs off one of them is final too. The weights are
+/// what the serving node reported; the verifier only checks that they grow
+/// towards the transaction, as cumulative weights must.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Genesis of the DAG the proof was built from
+    pub genesis_id: Hash,
+    /// The transaction proven included
+    pub transaction: Transaction,
+    /// Its cumulative weight when the proof was built
+    pub weight: u64,
+    /// From a tip down to a child of the transaction; empty when the
+    /// transaction is a tip itself
+    pub path: Vec<ProofStep>,
+}
+
+/// One transaction on the path, as much of it as recomputing its id needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub data: TransactionData,
+    /// Cumulative weight when the proof was built
+    pub weight: u64,
+}
+
+/// What a proof that passed showed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InclusionReport {
+    pub transaction_id: Hash,
+    /// The trusted tip the path starts at
+    pub tip: Hash,
+    /// Parent references between the tip and the transaction
+    pub hops: usize,
+    /// Cumulative weight the serving node reported
+    pub weight: u64,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum InclusionError {
+    #[error("malformed proof: {0}")]
+    Malformed(String),
+    #[error("{0} steps in one proof, at most {MAX_INCLUSION_STEPS} allowed")]
+    TooManySteps(usize),
+    #[error("proof is for the DAG with genesis {0}")]
+    WrongGenesis(Hash),
+    #[error("transaction {0} has a bad id or signature")]
+    InvalidTransaction(Hash),
+    #[error("{0} is on a different network than the transaction")]
+    WrongNetwork(Hash),
+    #[error("{0} doesn't reference the step below it")]
+    BrokenLink(Hash),
+    #[error("{0} is not one of the trusted tips")]
+    UnknownTip(Hash),
+    #[error("weight of {0} doesn't grow towards the transaction")]
+    InconsistentWeight(Hash),
+}
+
+impl InclusionProof {
+    /// Proof that `id` is in the past cone of `tips`, along the shortest
+    /// path from any of them
+    ///
+    /// `None` if the transaction isn't in memory, the DAG has no genesis, or
+    /// no tip reaches it within [`MAX_INCLUSION_STEPS`].
+    pub fn build(dag: &Dag, id: &Hash, tips: &[Hash]) -> Option<Self> {
+        let genesis_id = dag.genesis_id?;
+        let vertex = dag.get(id)?;
+        let tips: HashSet<&Hash> = tips.iter().collect();
+
+        // Breadth-first up the approvers, so the first tip found is nearest
+        let mut below: HashMap<Hash, Hash> = HashMap::new();
+        let mut queue = VecDeque::from([(*id, 0)]);
+        let mut tip = None;
+        while let Some((current, hops)) = queue.pop_front() {
+            if tips.contains(&current) {
+                tip = Some(current);
+                break;
+            }
+            if hops == MAX_INCLUSION_STEPS {
+                continue;
+            }
+            for child in dag.children(&current) {
+                if !below.contains_key(child) {
+                    below.insert(*child, current);
+                    queue.push_back((*child, hops + 1));
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut current = tip?;
+        while current != *id {
+            let step = dag.get(&current)?;
+            path.push(ProofStep {
+                data: step.transaction.data.clone(),
+                weight: step.cumulative_weight,
+            });
+            current = below[&current];
+        }
+        Some(InclusionProof {
+            genesis_id,
+            transaction: vertex.transaction.clone(),
+            weight: vertex.cumulative_weight,
+            path,
+        })
+    }
+
+    /// Encode as the blob handed to verifiers
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("inclusion proof serialization should not fail")
+    }
+
+    /// Decode a blob from [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Self, InclusionError> {
+        bincode::deserialize(data).map_err(|e| InclusionError::Malformed(e.to_string()))
+    }
+
+    /// Check that the path leads from one of the trusted `tips` down to the
+    /// transaction, in the DAG that started at `genesis_id`
+    pub fn verify(&self, tips: &[Hash], genesis_id: &Hash) -> Result<InclusionReport, InclusionError> {
+        if self.path.len() > MAX_INCLUSION_STEPS {
+            return Err(InclusionError::TooManySteps(self.path.len()));
+        }
+        if self.genesis_id != *genesis_id {
+            return Err(InclusionError::WrongGenesis(self.genesis_id));
+        }
+        let transaction = &self.transaction;
+        if !transaction.verify_id() || !transaction.verify_signature() {
+            return Err(InclusionError::InvalidTransaction(transaction.id));
+        }
+
+        // Walk up from the transaction, recomputing each step's id
+        let (mut below, mut below_weight) = (transaction.id, self.weight);
+        for step in self.path.iter().rev() {
+            let id = Hash::digest(&step.data.to_signing_bytes());
+            if step.data.network_id != transaction.data.network_id {
+                return Err(InclusionError::WrongNetwork(id));
+            }
+            if !step.data.parents.contains(&below) {
+                return Err(InclusionError::BrokenLink(id));
+            }
+            if step.weight >= below_weight {
+                return Err(InclusionError::InconsistentWeight(id));
+            }
+            (below, below_weight) = (id, step.weight);
+        }
+        if !tips.contains(&below) {
+            return Err(InclusionError::UnknownTip(below));
+        }
+
+        Ok(InclusionReport {
+            transaction_id: transaction.id,
+            tip: below,
+            hops: self.path.len(),
+            weight: self.weight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::vertex::DagVertex;
+
+    /// Genesis, a payment, then a chain of rewards on top with a side branch
+    /// off the payment; returns the DAG and the payment's id
+    fn paid_dag(kp: &KeyPair) -> (Dag, Hash) {
+        let mut dag = Dag::new();
+        let genesis = Transaction::genesis(kp);
+        let genesis_id = genesis.id;
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let payment = Transaction::transfer(kp, KeyPair::generate().public_key, 1, [genesis_id, genesis_id], 1);
+        let payment_id = payment.id;
+        dag.insert(DagVertex::new(payment, 1)).unwrap();
+        let side = Transaction::relay_reward(kp, 10, [payment_id, genesis_id], 100);
+        dag.insert(DagVertex::new(side, 2)).unwrap();
+
+        let mut last = payment_id;
+        for i in 2..=crate::FINALITY_THRESHOLD + 5 {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 10, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        (dag, payment_id)
+    }
+
+    #[test]
+    fn test_proof_roundtrip_and_verify() {
+        let kp = KeyPair::generate();
+        let (dag, id) = paid_dag(&kp);
+        let genesis = dag.genesis_id.unwrap();
+        let tips = dag.final_tips();
+        assert!(!tips.is_empty());
+
+        let proof = InclusionProof::build(&dag, &id, &tips).unwrap();
+        let proof = InclusionProof::from_bytes(&proof.to_bytes()).unwrap();
+        let report = proof.verify(&tips, &genesis).unwrap();
+        assert_eq!(report.transaction_id, id);
+        assert!(tips.contains(&report.tip));
+        assert_eq!(report.hops, proof.path.len());
+
+        // The shortest path wins: the side branch is one hop from the payment
+        let all_tips = dag.tips();
+        let short = InclusionProof::build(&dag, &id, &all_tips).unwrap();
+        assert_eq!(short.path.len(), 1);
+        assert!(short.verify(&all_tips, &genesis).is_ok());
+
+        // A tip is included in itself
+        let tip = InclusionProof::build(&dag, &tips[0], &tips).unwrap();
+        assert!(tip.path.is_empty());
+        assert_eq!(tip.verify(&tips, &genesis).unwrap().hops, 0);
+
+        // Nothing reaches a transaction from below it
+        assert!(InclusionProof::build(&dag, &genesis, &[id]).is_some());
+        assert!(InclusionProof::build(&dag, &all_tips[0], &[id]).is_none());
+    }
+
+    #[test]
+    fn test_forged_paths_are_rejected() {
+        let kp = KeyPair::generate();
+        let (dag, id) = paid_dag(&kp);
+        let genesis = dag.genesis_id.unwrap();
+        let tips = dag.final_tips();
+        let proof = InclusionProof::build(&dag, &id, &tips).unwrap();
+        assert!(proof.path.len() > 2);
+
+        assert_eq!(
+            proof.verify(&tips, &Hash::digest(b"other")),
+            Err(InclusionError::WrongGenesis(genesis))
+        );
+        assert!(matches!(proof.verify(&dag.tips(), &genesis), Err(InclusionError::UnknownTip(_))));
+
+        // Altering a step changes its id, so the step above no longer links
+        let mut tampered = proof.clone();
+        tampered.path[2].data.amount += 1;
+        let above = Hash::digest(&proof.path[1].data.to_signing_bytes());
+        assert_eq!(tampered.verify(&tips, &genesis), Err(InclusionError::BrokenLink(above)));
+
+        let mut skipped = proof.clone();
+        skipped.path.remove(1);
+        assert!(matches!(skipped.verify(&tips, &genesis), Err(InclusionError::BrokenLink(_))));
+
+        let mut forged = proof.clone();
+        forged.transaction.data.amount += 1;
+        assert_eq!(forged.verify(&tips, &genesis), Err(InclusionError::InvalidTransaction(id)));
+
+        let mut heavy = proof.clone();
+        heavy.path[0].weight = proof.weight;
+        assert!(matches!(heavy.verify(&tips, &genesis), Err(InclusionError::InconsistentWeight(_))));
+    }
+}
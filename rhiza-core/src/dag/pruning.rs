@@ -0,0 +1,270 @@
+use crate::consensus::supply::SupplyLedger;
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::channels::Channel;
+use crate::dag::htlc::Htlc;
+use crate::dag::snapshot::PublisherSignature;
+use crate::wallet::alias::AliasRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Domain separator of the hash a balance snapshot's signer signs
+const BALANCE_SNAPSHOT_DOMAIN: &[u8] = b"rhiza balance snapshot v1";
+
+/// What the DAG still knows about the vertices it pruned
+///
+/// Pruned vertices are final, so their effect on balances can't change any
+/// more: it is kept as one net balance per key. Their ids are kept so they
+/// are recognised as known parents and never inserted twice.
+#[derive(Debug, Clone, Default)]
+pub struct PrunedHistory {
+    /// Net balance each key holds from pruned vertices
+    pub(crate) balances: HashMap<PublicKey, u64>,
+    /// Relay tips of pruned transfers whose collecting claim, if any, is
+    /// still in the DAG
+    pub(crate) tips: HashMap<Hash, u64>,
+    pub(crate) ids: HashSet<Hash>,
+    /// Depth at or below which final vertices have been pruned
+    pub(crate) horizon: Option<u64>,
+    /// Units minted by pruned vertices
+    pub(crate) supply: SupplyLedger,
+}
+
+impl PrunedHistory {
+    /// Balance `key` carries over from pruned history
+    pub fn balance(&self, key: &PublicKey) -> u64 {
+        self.balances.get(key).copied().unwrap_or(0)
+    }
+
+    /// Tip of a pruned transfer that can still be collected
+    pub fn tip_of(&self, transfer: &Hash) -> Option<u64> {
+        self.tips.get(transfer).copied()
+    }
+
+    /// Whether a transaction was pruned
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Number of pruned vertices
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Depth at or below which final vertices have been pruned
+    pub fn horizon(&self) -> Option<u64> {
+        self.horizon
+    }
+
+    /// Keys with a balance carried over from pruned history
+    pub fn accounts(&self) -> usize {
+        self.balances.len()
+    }
+}
+
+/// Finalized history collapsed into the state later transactions are
+/// validated against, standing in for the vertices pruned from the DAG
+///
+/// Besides balances it keeps everything validation derives from history:
+/// confirmed nonces, alias bindings, known keys, claimed relays, tips not
+/// yet settled, payment channels and HTLCs not settled by a pruned
+/// transaction and what was minted. A node signs its
+/// [`content_hash`](Self::content_hash) with its own key, so a snapshot
+/// altered on disk is refused on restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub network_id: Option<u32>,
+    pub genesis_id: Option<Hash>,
+    pub founder_allocation_id: Option<Hash>,
+    /// Depth at or below which final vertices were pruned
+    pub horizon: u64,
+    /// When the snapshot was taken (unix ms)
+    pub created_ms: u64,
+    /// Net balance of every key with funds in the pruned history
+    pub balances: Vec<(PublicKey, u64)>,
+    /// Highest final transfer nonce per sender
+    pub nonces: Vec<(PublicKey, u64)>,
+    pub aliases: Vec<AliasRecord>,
+    pub known_keys: Vec<PublicKey>,
+    /// (relayer, relayed transaction) pairs already rewarded
+    pub claimed_relays: Vec<(PublicKey, Hash)>,
+    /// Tips of pruned transfers not yet settled by a pruned claim
+    pub tips: Vec<(Hash, u64)>,
+    /// Every pruned transaction id
+    pub pruned: Vec<Hash>,
+    pub channels: Vec<Channel>,
+    pub htlcs: Vec<Htlc>,
+    /// Units minted by the pruned transactions
+    pub supply: SupplyLedger,
+    pub signature: Option<PublisherSignature>,
+}
+
+impl BalanceSnapshot {
+    /// Hash of everything the signer vouches for
+    ///
+    /// Every list is kept sorted, so equal state always hashes the same.
+    pub fn content_hash(&self) -> Hash {
+        let content = bincode::serialize(&(
+            self.network_id,
+            &self.genesis_id,
+            &self.founder_allocation_id,
+            self.horizon,
+            self.created_ms,
+            &self.balances,
+            &self.nonces,
+            &self.aliases,
+            &self.known_keys,
+            &self.claimed_relays,
+            &self.tips,
+            &self.pruned,
+            &self.channels,
+            &self.htlcs,
+            &self.supply,
+        ))
+        .expect("snapshot serializes");
+        Hash::digest_multi(&[BALANCE_SNAPSHOT_DOMAIN, &content])
+    }
+
+    /// Sign the snapshot with `keypair`, replacing any earlier signature
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        self.signature = Some(PublisherSignature {
+            publisher: keypair.public_key.clone(),
+            signature: keypair.sign(self.content_hash().as_bytes()),
+        });
+    }
+
+    /// Whether one of the `trusted` keys signed exactly this content
+    pub fn verify(&self, trusted: &[PublicKey]) -> bool {
+        self.signature.as_ref().is_some_and(|s| {
+            trusted.contains(&s.publisher) && s.publisher.verify(self.content_hash().as_bytes(), &s.signature)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::validator::{TransactionValidator, ValidationError};
+    use crate::dag::vertex::{Dag, DagVertex};
+
+    /// Genesis, a reward to `kp`, a payment to `bob`, then enough rewards
+    /// to other keys on top to make the early ones final
+    fn busy_dag(kp: &KeyPair, bob: &KeyPair) -> (Dag, crate::crypto::Hash) {
+        let mut dag = Dag::new();
+        let genesis = Transaction::genesis(kp);
+        let mut last = genesis.id;
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let reward = Transaction::relay_reward(kp, 1_000_000, [last, last], 1);
+        last = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+        let payment =
+            Transaction::transfer_with_tip(kp, bob.public_key.clone(), 300_000, None, 5_000, [last, last], 1);
+        last = payment.id;
+        dag.insert(DagVertex::new(payment, 2)).unwrap();
+        for i in 3..=30 {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 10, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        (dag, last)
+    }
+
+    #[test]
+    fn test_prune_keeps_balances() {
+        let (kp, bob) = (KeyPair::generate(), KeyPair::generate());
+        let (mut dag, last) = busy_dag(&kp, &bob);
+        let before = (dag.get_balance(&kp.public_key), dag.get_balance(&bob.public_key));
+        let confirmed = dag.get_confirmed_balance(&kp.public_key);
+        let supply = *dag.supply();
+        let len = dag.len();
+
+        let removed = dag.prune(10);
+        assert_eq!(removed.len(), 11);
+        assert!(removed.windows(2).all(|w| w[0].depth <= w[1].depth));
+        assert_eq!(dag.len(), len - 11);
+        assert_eq!(dag.pruned().horizon(), Some(10));
+        assert!(removed.iter().all(|v| dag.get(&v.id()).is_none() && dag.contains(&v.id())));
+
+        assert_eq!((dag.get_balance(&kp.public_key), dag.get_balance(&bob.public_key)), before);
+        assert_eq!(dag.get_confirmed_balance(&kp.public_key), confirmed);
+        assert_eq!(dag.balance_seen_by(&[last, last], &bob.public_key), 300_000);
+        assert_eq!(*dag.supply(), supply);
+        assert_eq!(supply.relay_rewards, 1_000_000 + 28 * 10);
+        // Only final vertices go
+        assert!(dag.prune(1_000).iter().all(|v| v.is_final));
+        assert_eq!(dag.get_balance(&kp.public_key), before.0);
+    }
+
+    #[test]
+    fn test_validates_against_pruned_history() {
+        let (kp, bob) = (KeyPair::generate(), KeyPair::generate());
+        let (mut dag, last) = busy_dag(&kp, &bob);
+        let removed = dag.prune(10);
+
+        // Pruned transactions are known, not re-inserted
+        let old = removed[3].clone();
+        assert!(dag.insert(old).is_err());
+
+        // A nonce settled in pruned history stays spent
+        let replay = Transaction::transfer(&kp, bob.public_key.clone(), 1, [last, last], 1);
+        assert!(matches!(
+            TransactionValidator::validate(&replay, &dag),
+            Err(ValidationError::StaleNonce { .. })
+        ));
+
+        // Bob can spend what he was paid, but not more
+        let spend = Transaction::transfer(&bob, kp.public_key.clone(), 290_000, [last, last], 1);
+        assert!(TransactionValidator::validate(&spend, &dag).is_ok());
+        let overspend = Transaction::transfer(&bob, kp.public_key.clone(), 300_001, [last, last], 1);
+        assert!(matches!(
+            TransactionValidator::validate(&overspend, &dag),
+            Err(ValidationError::InsufficientBalance { .. })
+        ));
+
+        // Parents that were pruned still count as known
+        let pruned_parent = removed.last().unwrap().id();
+        let late = Transaction::transfer(&bob, kp.public_key.clone(), 1_000, [pruned_parent, last], 1);
+        assert!(TransactionValidator::validate(&late, &dag).is_ok());
+        dag.insert(DagVertex::new(late, 31)).unwrap();
+        assert_eq!(dag.get_balance(&bob.public_key), 299_000);
+    }
+
+    #[test]
+    fn test_signed_snapshot_restores_pruned_state() {
+        let (kp, bob) = (KeyPair::generate(), KeyPair::generate());
+        let (mut dag, last) = busy_dag(&kp, &bob);
+        let removed = dag.prune(10);
+
+        let mut snapshot = dag.balance_snapshot();
+        assert!(!snapshot.verify(std::slice::from_ref(&kp.public_key)));
+        snapshot.sign(&kp);
+        assert!(snapshot.verify(std::slice::from_ref(&kp.public_key)));
+        assert!(!snapshot.verify(std::slice::from_ref(&bob.public_key)));
+        let mut tampered = snapshot.clone();
+        tampered.balances[0].1 += 1;
+        assert!(!tampered.verify(std::slice::from_ref(&kp.public_key)));
+
+        // The kept vertices replay on top of the snapshot
+        let mut restored = Dag::from_balance_snapshot(&snapshot);
+        let mut kept: Vec<DagVertex> = dag.iter().cloned().collect();
+        kept.sort_by_key(|v| v.depth);
+        for vertex in kept {
+            restored.insert_restored(vertex).unwrap();
+        }
+        assert_eq!(restored.len(), dag.len());
+        assert_eq!(restored.pruned().len(), removed.len());
+        assert_eq!(restored.network_id(), dag.network_id());
+        for key in [&kp.public_key, &bob.public_key] {
+            assert_eq!(restored.get_balance(key), dag.get_balance(key));
+        }
+        assert_eq!(restored.last_confirmed_nonce(&kp.public_key), Some(1));
+        // Minted units are counted once, pruned or not
+        assert_eq!(restored.supply(), dag.supply());
+        assert_eq!(restored.tips(), vec![last]);
+    }
+}
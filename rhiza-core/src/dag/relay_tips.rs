@@ -0,0 +1,149 @@
+use crate::crypto::Hash;
+use crate::dag::conflicts::ConflictTracker;
+use crate::dag::transaction::{Transaction, TransactionType};
+use std::collections::HashMap;
+
+/// Relay tips and the reward claims that collected them
+///
+/// A transfer's `relay_tip` is debited from its sender when the transfer
+/// is made and goes to the first relay reward claim carrying a proof for
+/// that transfer to become final, for good. Later claims for the same
+/// transfer still earn their relay reward, but not the tip. A tip nobody
+/// claims stays with no one.
+#[derive(Debug, Clone, Default)]
+pub struct RelayTips {
+    /// Tipped transfer -> the claim that collected its tip
+    winners: HashMap<Hash, Hash>,
+    /// Claim -> the tipped transfers it collected, with their tips
+    collected: HashMap<Hash, Vec<(Hash, u64)>>,
+}
+
+impl RelayTips {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a relay reward claim that just became final
+    ///
+    /// `tip_of` looks up the tip of a relayed transaction. Returns whether
+    /// the claim collected any tips.
+    pub(crate) fn record(&mut self, claim: &Transaction, tip_of: impl Fn(&Hash) -> Option<u64>) -> bool {
+        if claim.data.tx_type != TransactionType::RelayReward {
+            return false;
+        }
+        let mut collected = Vec::new();
+        for proof in &claim.data.relay_proofs {
+            let transfer = proof.transaction_id;
+            let tip = tip_of(&transfer).unwrap_or(0);
+            if tip > 0 && !self.winners.contains_key(&transfer) {
+                self.winners.insert(transfer, claim.id);
+                collected.push((transfer, tip));
+            }
+        }
+        if collected.is_empty() {
+            return false;
+        }
+        self.collected.entry(claim.id).or_default().extend(collected);
+        true
+    }
+
+    /// The claim that collected a transfer's tip
+    pub fn winner(&self, transfer: &Hash) -> Option<&Hash> {
+        self.winners.get(transfer)
+    }
+
+    /// Tips a claim collected, leaving out transfers that lost a
+    /// double-spend or were replaced (their sender never paid)
+    pub fn credit_for(&self, claim: &Hash, conflicts: &ConflictTracker) -> u128 {
+        self.collected.get(claim).map_or(0, |tips| {
+            tips.iter()
+                .filter(|(transfer, _)| !conflicts.is_rejected(transfer))
+                .map(|(_, tip)| *tip as u128)
+                .sum()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::relay::RelayProof;
+    use crate::crypto::keys::KeyPair;
+    use crate::crypto::Hash;
+    use crate::dag::transaction::Transaction;
+    use crate::dag::vertex::{Dag, DagVertex};
+
+    const TIP: u64 = 50;
+
+    /// A funded sender's tipped transfer, and two relayers' claims for it
+    fn tipped_dag() -> (Dag, KeyPair, [KeyPair; 2], Hash, [Hash; 2]) {
+        let sender = KeyPair::generate();
+        let mut dag = Dag::new();
+        let genesis = Transaction::genesis(&sender);
+        let genesis_id = genesis.id;
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let funding = Transaction::relay_reward(&sender, 1_000, [genesis_id, genesis_id], 1);
+        let funding_id = funding.id;
+        dag.insert(DagVertex::new(funding, 1)).unwrap();
+
+        let recipient = KeyPair::generate().public_key;
+        let transfer =
+            Transaction::transfer_with_tip(&sender, recipient, 100, None, TIP, [funding_id, funding_id], 2);
+        let transfer_id = transfer.id;
+        dag.insert(DagVertex::new(transfer, 2)).unwrap();
+
+        let relayers = [KeyPair::generate(), KeyPair::generate()];
+        let claims = [0, 1].map(|i| {
+            let proofs = vec![RelayProof::new(&relayers[i], transfer_id, 1)];
+            let claim = Transaction::relay_reward_with_proofs(&relayers[i], 10, proofs, [transfer_id, transfer_id], 3);
+            let id = claim.id;
+            dag.insert(DagVertex::new(claim, 3)).unwrap();
+            id
+        });
+        (dag, sender, relayers, transfer_id, claims)
+    }
+
+    /// Approve `id` until it is final
+    fn finalize(dag: &mut Dag, id: Hash) {
+        let mut last = id;
+        for i in 0..crate::FINALITY_THRESHOLD {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 0, [last, last], 100 + i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, 4 + i)).unwrap();
+        }
+        assert!(dag.get(&id).unwrap().is_final);
+    }
+
+    #[test]
+    fn test_first_final_claim_collects_the_tip() {
+        let (mut dag, sender, relayers, transfer, claims) = tipped_dag();
+        assert_eq!(dag.get_balance(&sender.public_key), 1_000 - 100 - TIP);
+        // Nobody has the tip while the claims are pending
+        assert_eq!(dag.get_balance(&relayers[0].public_key), 10);
+        assert_eq!(dag.get_balance(&relayers[1].public_key), 10);
+
+        finalize(&mut dag, claims[1]);
+        assert_eq!(dag.relay_tips().winner(&transfer), Some(&claims[1]));
+        assert_eq!(dag.get_balance(&relayers[1].public_key), 10 + TIP);
+
+        // A later claim keeps its reward but not the tip
+        finalize(&mut dag, claims[0]);
+        assert_eq!(dag.relay_tips().winner(&transfer), Some(&claims[1]));
+        assert_eq!(dag.get_balance(&relayers[0].public_key), 10);
+        assert_eq!(dag.get_balance(&relayers[1].public_key), 10 + TIP);
+    }
+
+    #[test]
+    fn test_restored_final_claim_keeps_its_tip() {
+        let (mut dag, _, relayers, transfer, claims) = tipped_dag();
+        finalize(&mut dag, claims[0]);
+
+        let mut stored: Vec<DagVertex> = dag.iter().cloned().collect();
+        stored.sort_by_key(|v| v.depth);
+        let mut restored = Dag::new();
+        for vertex in stored {
+            restored.insert_restored(vertex).unwrap();
+        }
+        assert_eq!(restored.relay_tips().winner(&transfer), Some(&claims[0]));
+        assert_eq!(restored.get_balance(&relayers[0].public_key), 10 + TIP);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::consensus::relay::RelayTotals;
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey, Signature};
+use crate::dag::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Domain separator of the hash snapshot publishers sign
+const SNAPSHOT_DOMAIN: &[u8] = b"rhiza dag snapshot v2";
+
+/// Domain separator of a segment's link in the hash chain
+const SEGMENT_DOMAIN: &[u8] = b"rhiza snapshot segment v1";
+
+/// Transactions per segment of a snapshot's hash chain
+pub const SNAPSHOT_SEGMENT_LEN: usize = 1_024;
+
+/// A copy of the DAG's transactions and relay state for bootstrapping a
+/// new node
+///
+/// Transactions are split into segments, each naming the hash of the one
+/// before, so a reader can check the chain segment by segment and tell
+/// exactly where a damaged file breaks it. Publishers vouch for a snapshot
+/// by signing its [`content_hash`](Self::content_hash), which covers the
+/// head of the chain. A node imports one only when enough of the publishers
+/// it trusts have signed it, so no single download source or signer decides
+/// what history a new node starts from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// When the snapshot was taken (unix ms)
+    pub created_ms: u64,
+    /// Transactions, parents before children
+    pub segments: Vec<SnapshotSegment>,
+    pub relay: RelaySnapshot,
+    pub signatures: Vec<PublisherSignature>,
+}
+
+/// One link of a snapshot's hash chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSegment {
+    /// Hash of the previous segment, zero for the first
+    pub prev: Hash,
+    pub transactions: Vec<Transaction>,
+}
+
+impl SnapshotSegment {
+    pub fn hash(&self) -> Hash {
+        link(&self.prev, &self.transactions)
+    }
+}
+
+/// Hash of a segment holding `transactions` that follows `prev`
+fn link(prev: &Hash, transactions: &[Transaction]) -> Hash {
+    let transactions = bincode::serialize(transactions).expect("transactions serialize");
+    Hash::digest_multi(&[SEGMENT_DOMAIN, prev.as_bytes(), &transactions])
+}
+
+/// Relay accounting carried over to the importing node
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelaySnapshot {
+    pub totals: RelayTotals,
+    /// Relay count per relayer, sorted by key
+    pub counts: Vec<(PublicKey, u64)>,
+}
+
+/// Why a snapshot's hash chain doesn't hold together
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("segment {0} doesn't link to the segment before it")]
+    BrokenChain(usize),
+    #[error("segment {0} is empty")]
+    EmptySegment(usize),
+    #[error("transaction {id} in segment {segment} doesn't match its id")]
+    TransactionId { segment: usize, id: Hash },
+}
+
+/// A publisher's signature over a snapshot's content hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublisherSignature {
+    pub publisher: PublicKey,
+    pub signature: Signature,
+}
+
+/// Outcome of checking one signature on a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// A trusted publisher signed this snapshot
+    Valid,
+    /// The signature doesn't match the snapshot's contents
+    Invalid,
+    /// Signed by a key that isn't a trusted publisher
+    Untrusted,
+}
+
+/// Every signature on a snapshot, checked against the trusted publishers
+#[derive(Debug, Clone)]
+pub struct SignatureReport {
+    pub checks: Vec<(PublicKey, SignatureCheck)>,
+    /// Valid signatures from distinct trusted publishers needed
+    pub required: usize,
+}
+
+impl SignatureReport {
+    /// Distinct trusted publishers with a valid signature
+    pub fn valid(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|(_, check)| *check == SignatureCheck::Valid)
+            .map(|(publisher, _)| publisher)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Whether enough trusted publishers signed
+    pub fn is_accepted(&self) -> bool {
+        self.valid() >= self.required
+    }
+}
+
+impl Snapshot {
+    /// Unsigned snapshot of `transactions`, parents first, taken now
+    pub fn new(transactions: Vec<Transaction>, relay: RelaySnapshot) -> Self {
+        Self::segmented(transactions, relay, SNAPSHOT_SEGMENT_LEN)
+    }
+
+    fn segmented(transactions: Vec<Transaction>, relay: RelaySnapshot, segment_len: usize) -> Self {
+        let mut segments: Vec<SnapshotSegment> = Vec::new();
+        let mut transactions = transactions.into_iter().peekable();
+        while transactions.peek().is_some() {
+            let prev = segments.last().map_or(Hash::zero(), |s| s.hash());
+            segments.push(SnapshotSegment {
+                prev,
+                transactions: transactions.by_ref().take(segment_len).collect(),
+            });
+        }
+        Snapshot {
+            created_ms: chrono::Utc::now().timestamp_millis() as u64,
+            segments,
+            relay,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Number of transactions across all segments
+    pub fn transaction_count(&self) -> usize {
+        self.segments.iter().map(|s| s.transactions.len()).sum()
+    }
+
+    /// Transactions in chain order, parents before children
+    pub fn into_transactions(self) -> impl Iterator<Item = Transaction> {
+        self.segments.into_iter().flat_map(|s| s.transactions)
+    }
+
+    /// Head of the hash chain, zero for an empty snapshot
+    ///
+    /// Worked out from the transactions alone, ignoring the stored links,
+    /// so it covers every segment whether or not the links were forged.
+    pub fn head(&self) -> Hash {
+        self.segments.iter().fold(Hash::zero(), |prev, s| link(&prev, &s.transactions))
+    }
+
+    /// Check that every segment links to the one before and every
+    /// transaction matches its id
+    pub fn verify_chain(&self) -> Result<(), SnapshotError> {
+        let mut prev = Hash::zero();
+        for (index, segment) in self.segments.iter().enumerate() {
+            if segment.prev != prev {
+                return Err(SnapshotError::BrokenChain(index));
+            }
+            if segment.transactions.is_empty() {
+                return Err(SnapshotError::EmptySegment(index));
+            }
+            if let Some(tx) = segment.transactions.iter().find(|tx| !tx.verify_id()) {
+                return Err(SnapshotError::TransactionId { segment: index, id: tx.id });
+            }
+            prev = segment.hash();
+        }
+        Ok(())
+    }
+
+    /// Hash of everything the publishers vouch for: the chain head and the
+    /// relay state
+    pub fn content_hash(&self) -> Hash {
+        let relay = bincode::serialize(&self.relay).expect("relay state serializes");
+        Hash::digest_multi(&[SNAPSHOT_DOMAIN, &self.created_ms.to_le_bytes(), self.head().as_bytes(), &relay])
+    }
+
+    /// Add `keypair`'s signature, replacing an earlier one by the same key
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        let signature = keypair.sign(self.content_hash().as_bytes());
+        self.signatures.retain(|s| s.publisher != keypair.public_key);
+        self.signatures.push(PublisherSignature {
+            publisher: keypair.public_key.clone(),
+            signature,
+        });
+    }
+
+    /// Check every signature against the `trusted` publishers, `required`
+    /// of which must have signed
+    pub fn verify(&self, trusted: &[PublicKey], required: usize) -> SignatureReport {
+        let hash = self.content_hash();
+        let checks = self
+            .signatures
+            .iter()
+            .map(|s| {
+                let check = if !trusted.contains(&s.publisher) {
+                    SignatureCheck::Untrusted
+                } else if s.publisher.verify(hash.as_bytes(), &s.signature) {
+                    SignatureCheck::Valid
+                } else {
+                    SignatureCheck::Invalid
+                };
+                (s.publisher.clone(), check)
+            })
+            .collect();
+        SignatureReport { checks, required }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        let genesis = Transaction::genesis(&KeyPair::generate());
+        Snapshot::new(vec![genesis], RelaySnapshot::default())
+    }
+
+    fn chain(len: usize) -> Snapshot {
+        let transactions = (0..len).map(|_| Transaction::genesis(&KeyPair::generate())).collect();
+        Snapshot::segmented(transactions, RelaySnapshot::default(), 3)
+    }
+
+    #[test]
+    fn test_threshold_of_trusted_publishers() {
+        let publishers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate()).collect();
+        let trusted: Vec<PublicKey> = publishers.iter().map(|k| k.public_key.clone()).collect();
+        let mut snapshot = snapshot();
+
+        snapshot.sign(&publishers[0]);
+        assert!(!snapshot.verify(&trusted, 2).is_accepted());
+
+        snapshot.sign(&publishers[2]);
+        let report = snapshot.verify(&trusted, 2);
+        assert_eq!(report.valid(), 2);
+        assert!(report.is_accepted());
+    }
+
+    #[test]
+    fn test_untrusted_and_repeated_signatures_dont_count() {
+        let publisher = KeyPair::generate();
+        let outsider = KeyPair::generate();
+        let mut snapshot = snapshot();
+        snapshot.sign(&publisher);
+        snapshot.sign(&outsider);
+        // A publisher signing twice still counts once
+        snapshot.signatures.push(snapshot.signatures[0].clone());
+
+        let report = snapshot.verify(std::slice::from_ref(&publisher.public_key), 2);
+        assert_eq!(report.valid(), 1);
+        assert!(!report.is_accepted());
+        assert!(report
+            .checks
+            .contains(&(outsider.public_key.clone(), SignatureCheck::Untrusted)));
+    }
+
+    #[test]
+    fn test_tampering_invalidates_signatures() {
+        let publisher = KeyPair::generate();
+        let mut snapshot = snapshot();
+        snapshot.sign(&publisher);
+        snapshot.segments[0].transactions.push(Transaction::genesis(&KeyPair::generate()));
+
+        let report = snapshot.verify(std::slice::from_ref(&publisher.public_key), 1);
+        assert_eq!(report.checks, vec![(publisher.public_key.clone(), SignatureCheck::Invalid)]);
+        assert!(!report.is_accepted());
+    }
+
+    #[test]
+    fn test_segments_form_a_hash_chain() {
+        let snapshot = chain(7);
+        assert_eq!(snapshot.segments.len(), 3);
+        assert_eq!(snapshot.transaction_count(), 7);
+        assert_eq!(snapshot.segments[0].prev, Hash::zero());
+        assert_eq!(snapshot.segments[2].prev, snapshot.segments[1].hash());
+        assert_eq!(snapshot.verify_chain(), Ok(()));
+        let ids: Vec<Hash> = snapshot.segments.iter().flat_map(|s| s.transactions.iter().map(|tx| tx.id)).collect();
+        assert_eq!(snapshot.clone().into_transactions().map(|tx| tx.id).collect::<Vec<_>>(), ids);
+
+        // Swapping a transaction out breaks the link to the next segment
+        let mut swapped = snapshot.clone();
+        swapped.segments[1].transactions[0] = Transaction::genesis(&KeyPair::generate());
+        assert_eq!(swapped.verify_chain(), Err(SnapshotError::BrokenChain(2)));
+        assert_ne!(swapped.content_hash(), snapshot.content_hash());
+
+        // A transaction altered without its id is caught too
+        let mut altered = snapshot.clone();
+        altered.segments[2].transactions[0].data.timestamp += 1;
+        assert!(matches!(
+            altered.verify_chain(),
+            Err(SnapshotError::TransactionId { segment: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_relay_state_is_signed() {
+        let publisher = KeyPair::generate();
+        let mut snapshot = snapshot();
+        snapshot.sign(&publisher);
+        snapshot.relay.totals.total_rewards_distributed += 1;
+        assert!(!snapshot.verify(std::slice::from_ref(&publisher.public_key), 1).is_accepted());
+    }
+}
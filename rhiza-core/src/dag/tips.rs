@@ -0,0 +1,160 @@
+use crate::crypto::Hash;
+use rand::seq::index;
+use rand::Rng;
+use std::collections::{BTreeSet, HashMap};
+
+/// The DAG frontier, indexed for cheap parent selection
+///
+/// Tips are kept both in a depth-ordered set (deepest-first selection in
+/// `O(log n)`) and in a dense slot vector (uniform random picks in `O(1)`).
+/// Insertion and removal touch both in `O(log n)`.
+#[derive(Debug, Clone, Default)]
+pub struct TipSet {
+    /// Tips ordered by (depth, id)
+    by_depth: BTreeSet<(u64, Hash)>,
+    /// Tips in arbitrary order for random access
+    slots: Vec<Hash>,
+    /// Slot index and depth of each tip
+    index: HashMap<Hash, (usize, u64)>,
+}
+
+impl TipSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tip at the given depth (no-op if already present)
+    pub fn insert(&mut self, id: Hash, depth: u64) {
+        if self.index.contains_key(&id) {
+            return;
+        }
+        self.index.insert(id, (self.slots.len(), depth));
+        self.slots.push(id);
+        self.by_depth.insert((depth, id));
+    }
+
+    /// Remove a tip; returns whether it was present
+    pub fn remove(&mut self, id: &Hash) -> bool {
+        let Some((slot, depth)) = self.index.remove(id) else {
+            return false;
+        };
+        self.by_depth.remove(&(depth, *id));
+        self.slots.swap_remove(slot);
+        if let Some(moved) = self.slots.get(slot) {
+            if let Some(entry) = self.index.get_mut(moved) {
+                entry.0 = slot;
+            }
+        }
+        true
+    }
+
+    /// Whether `id` is a tip
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Number of tips
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether there are no tips
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Tips from deepest to shallowest
+    pub fn iter_deepest(&self) -> impl Iterator<Item = &Hash> {
+        self.by_depth.iter().rev().map(|(_, id)| id)
+    }
+
+    /// The `count` deepest tips, the deepest repeated to make up the number
+    /// if there are fewer
+    pub fn deepest(&self, count: usize) -> Option<Vec<Hash>> {
+        let mut tips: Vec<Hash> = self.iter_deepest().take(count).copied().collect();
+        let first = *tips.first()?;
+        tips.resize(count, first);
+        Some(tips)
+    }
+
+    /// `count` distinct tips chosen uniformly at random, the first repeated
+    /// to make up the number if there are fewer
+    pub fn random<R: Rng + ?Sized>(&self, count: usize, rng: &mut R) -> Option<Vec<Hash>> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let picks = count.min(self.slots.len());
+        let mut tips: Vec<Hash> = index::sample(rng, self.slots.len(), picks)
+            .into_iter()
+            .map(|slot| self.slots[slot])
+            .collect();
+        tips.resize(count, tips[0]);
+        Some(tips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u8) -> Hash {
+        Hash::from_bytes([n; 32])
+    }
+
+    #[test]
+    fn test_deepest() {
+        let mut tips = TipSet::new();
+        assert_eq!(tips.deepest(2), None);
+
+        tips.insert(id(1), 3);
+        assert_eq!(tips.deepest(2), Some(vec![id(1), id(1)]));
+
+        tips.insert(id(2), 7);
+        tips.insert(id(3), 5);
+        assert_eq!(tips.deepest(2), Some(vec![id(2), id(3)]));
+        assert_eq!(tips.deepest(4), Some(vec![id(2), id(3), id(1), id(2)]));
+
+        tips.remove(&id(2));
+        assert_eq!(tips.deepest(2), Some(vec![id(3), id(1)]));
+    }
+
+    #[test]
+    fn test_remove_keeps_slots_consistent() {
+        let mut tips = TipSet::new();
+        for n in 0..10 {
+            tips.insert(id(n), n as u64);
+        }
+        assert!(tips.remove(&id(0)));
+        assert!(!tips.remove(&id(0)));
+        assert!(tips.remove(&id(5)));
+        assert_eq!(tips.len(), 8);
+
+        for (slot, tip) in tips.slots.iter().enumerate() {
+            assert_eq!(tips.index[tip].0, slot);
+        }
+    }
+
+    #[test]
+    fn test_random_is_distinct() {
+        let mut rng = crate::testkit::seeded_rng();
+        let mut tips = TipSet::new();
+        assert_eq!(tips.random(2, &mut rng), None);
+        tips.insert(id(1), 1);
+        tips.insert(id(2), 1);
+        tips.insert(id(3), 2);
+
+        for count in 2..=3 {
+            for _ in 0..100 {
+                let mut picked = tips.random(count, &mut rng).unwrap();
+                assert!(picked.iter().all(|tip| tips.contains(tip)));
+                picked.sort();
+                picked.dedup();
+                assert_eq!(picked.len(), count);
+            }
+        }
+        // Fewer tips than asked for: all of them, then repeats
+        let picked = tips.random(5, &mut rng).unwrap();
+        assert_eq!(picked.len(), 5);
+        assert_eq!(picked[3..], [picked[0], picked[0]]);
+    }
+}
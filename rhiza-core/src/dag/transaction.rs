@@ -1,5 +1,11 @@
-use crate::crypto::{Hash, PublicKey, Signature};
+use crate::consensus::relay::RelayProof;
+use crate::crypto::{Algorithms, Hash, PublicKey, Signature, VrfProof};
 use crate::crypto::keys::KeyPair;
+use crate::crypto::multisig::{MultisigError, MultisigPolicy, MultisigWitness};
+use crate::dag::channels::SignedChannelState;
+use crate::dag::htlc::{HtlcTerms, Preimage};
+use crate::dag::work::{self, WorkPuzzle, MIN_WORK_BITS};
+use crate::wallet::alias::Alias;
 use serde::{Deserialize, Serialize};
 
 /// The type of transaction
@@ -13,22 +19,141 @@ pub enum TransactionType {
     RelayReward,
     /// One-time founder allocation at genesis
     FounderAllocation,
+    /// Opt-in announcement binding an alias (in the memo) to the sender,
+    /// with the recipient as its preferred receive key
+    AliasAnnouncement,
+    /// Publishes the sender's public key so its address can be paid
+    KeyAnnouncement,
+    /// Locks the amount in a payment channel from the sender to the
+    /// recipient (see [`Channels`](crate::dag::Channels))
+    ChannelOpen,
+    /// Settles a payment channel on the state it carries
+    ChannelClose,
+    /// Transfer out of a multisig account, signed by enough of its members
+    /// (see [`MultisigPolicy`])
+    Multisig,
+    /// Locks the amount for the recipient until it's claimed with the
+    /// secret behind a hash, or refunded after a timeout (see
+    /// [`Htlcs`](crate::dag::Htlcs))
+    HtlcLock,
+    /// Pays a hash-time-locked amount to its recipient, revealing the secret
+    HtlcClaim,
+    /// Pays a hash-time-locked amount back to its sender after the timeout
+    HtlcRefund,
+    /// Pays each of its outputs from the sender at once; the amount is
+    /// their total and the recipient the sender
+    Batch,
+}
+
+impl TransactionType {
+    /// Tag identifying the type in signing bytes; fixed forever, whatever
+    /// order the variants are declared in
+    fn signing_tag(&self) -> u8 {
+        match self {
+            TransactionType::Transfer => 0,
+            TransactionType::Genesis => 1,
+            TransactionType::RelayReward => 2,
+            TransactionType::FounderAllocation => 3,
+            TransactionType::AliasAnnouncement => 4,
+            TransactionType::KeyAnnouncement => 5,
+            TransactionType::ChannelOpen => 6,
+            TransactionType::ChannelClose => 7,
+            TransactionType::Multisig => 8,
+            TransactionType::HtlcLock => 9,
+            TransactionType::HtlcClaim => 10,
+            TransactionType::HtlcRefund => 11,
+            TransactionType::Batch => 12,
+        }
+    }
+
+    pub(crate) fn from_signing_tag(tag: u8) -> Option<Self> {
+        let tx_type = match tag {
+            0 => TransactionType::Transfer,
+            1 => TransactionType::Genesis,
+            2 => TransactionType::RelayReward,
+            3 => TransactionType::FounderAllocation,
+            4 => TransactionType::AliasAnnouncement,
+            5 => TransactionType::KeyAnnouncement,
+            6 => TransactionType::ChannelOpen,
+            7 => TransactionType::ChannelClose,
+            8 => TransactionType::Multisig,
+            9 => TransactionType::HtlcLock,
+            10 => TransactionType::HtlcClaim,
+            11 => TransactionType::HtlcRefund,
+            12 => TransactionType::Batch,
+            _ => return None,
+        };
+        Some(tx_type)
+    }
+
+    /// Transfers, batches and multisig spends: payments ordered by the
+    /// sender's nonce, where reusing a nonce replaces
+    pub fn is_payment(&self) -> bool {
+        matches!(self, TransactionType::Transfer | TransactionType::Batch | TransactionType::Multisig)
+    }
+
+    pub fn is_htlc(&self) -> bool {
+        matches!(self, TransactionType::HtlcLock | TransactionType::HtlcClaim | TransactionType::HtlcRefund)
+    }
+}
+
+/// Prefix of every transaction's signing bytes, so a transaction signature
+/// can't be passed off as a signature over any other kind of message
+pub const SIGNING_DOMAIN: &[u8] = b"RHIZA_TX";
+
+/// Version of the signing encoding, written after [`SIGNING_DOMAIN`]
+pub const SIGNING_VERSION: u8 = 9;
+
+/// Most outputs a batch may pay
+pub const MAX_BATCH_OUTPUTS: usize = 256;
+
+/// [`valid_after`](TransactionData::valid_after) values below this are DAG
+/// depths, the rest Unix timestamps in milliseconds
+pub const TIME_LOCK_THRESHOLD: u64 = 500_000_000;
+
+/// Whether a transaction at `depth`, dated `timestamp`, is past `lock`: a
+/// DAG depth below [`TIME_LOCK_THRESHOLD`], Unix ms from it, or 0 for none
+pub fn lock_passed(lock: u64, depth: u64, timestamp: u64) -> bool {
+    match lock {
+        0 => true,
+        lock if lock < TIME_LOCK_THRESHOLD => depth >= lock,
+        lock => timestamp >= lock,
+    }
+}
+
+fn default_network_id() -> u32 {
+    crate::MAINNET_ID
+}
+
+/// One payment in a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchOutput {
+    pub recipient: PublicKey,
+    /// Amount in smallest units
+    pub amount: u64,
 }
 
 /// The data payload of a transaction (what gets signed)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionData {
+    /// Network the transaction is valid on (see [`MAINNET_ID`](crate::MAINNET_ID))
+    #[serde(default = "default_network_id")]
+    pub network_id: u32,
     /// Type of this transaction
     pub tx_type: TransactionType,
-    /// References to 2 parent transactions (DAG structure)
-    pub parents: [Hash; 2],
+    /// Parent transactions this one approves (DAG structure); how many may
+    /// depend on the network (see [`parent_counts`](crate::parent_counts))
+    pub parents: Vec<Hash>,
     /// Sender's public key
     pub sender: PublicKey,
     /// Recipient's public key (same as sender for relay rewards)
     pub recipient: PublicKey,
     /// Amount in smallest units (1 RHZ = 10^8)
     pub amount: u64,
-    /// Optional fee (0 in current protocol)
+    /// Fee, burned: debited from the fee payer and credited to no one
+    ///
+    /// It only buys priority, in pending pools and on the wire; a tip for
+    /// whoever relays the transfer is `relay_tip`.
     pub fee: u64,
     /// Unix timestamp in milliseconds
     pub timestamp: u64,
@@ -36,6 +161,41 @@ pub struct TransactionData {
     pub nonce: u64,
     /// Optional memo/data field
     pub memo: Option<String>,
+    /// Account that pays the fee instead of the sender (sponsored
+    /// transactions); it must co-sign the transaction
+    #[serde(default)]
+    pub fee_payer: Option<PublicKey>,
+    /// Relay work backing a relay reward claim (empty for other types)
+    #[serde(default)]
+    pub relay_proofs: Vec<RelayProof>,
+    /// Paid by the sender of a transfer to the relayer whose reward claim
+    /// for it becomes final first (see [`RelayTips`](crate::dag::RelayTips))
+    #[serde(default)]
+    pub relay_tip: u64,
+    /// State a channel close settles on, signed by the other party (only
+    /// on channel closes)
+    #[serde(default)]
+    pub channel: Option<SignedChannelState>,
+    /// VRF proof of the randomness the sender's tip selection drew on (see
+    /// [`selection_input`](crate::consensus::tip_selection::selection_input))
+    #[serde(default)]
+    pub selection_proof: Option<VrfProof>,
+    /// Time lock on a payment: the DAG depth, or Unix ms at or above
+    /// [`TIME_LOCK_THRESHOLD`], before which it can't become final or
+    /// credit the recipient (0 for none)
+    #[serde(default)]
+    pub valid_after: u64,
+    /// Hash lock and timeout, or the lock settled (only on HTLC
+    /// transactions)
+    #[serde(default)]
+    pub htlc: Option<HtlcTerms>,
+    /// Hash function the ID is computed with and signature scheme the
+    /// sender and fee payer sign with (see [`Algorithms`])
+    #[serde(default)]
+    pub algorithms: Algorithms,
+    /// Recipients a batch pays and how much each (only on batches)
+    #[serde(default)]
+    pub outputs: Vec<BatchOutput>,
 }
 
 /// A complete transaction with id and signature
@@ -45,15 +205,260 @@ pub struct Transaction {
     pub id: Hash,
     /// The transaction data
     pub data: TransactionData,
-    /// Ed25519 signature over the serialized data
+    /// Ed25519 signature over the serialized data; all zeros on a multisig
+    /// spend, which no single key signs
     pub signature: Signature,
+    /// Fee payer's signature over the transaction ID, when sponsored
+    #[serde(default)]
+    pub fee_payer_signature: Option<Signature>,
+    /// The sending account's policy and its members' signatures over the
+    /// transaction ID, on a multisig spend
+    #[serde(default)]
+    pub multisig: Option<MultisigWitness>,
+    /// Proof of work over the ID (see [`work`](crate::dag::work)); outside
+    /// the signed data, so anyone can add work without a new signature
+    #[serde(default)]
+    pub work_nonce: u64,
+    /// Puzzle the work solves
+    #[serde(default)]
+    pub work_puzzle: WorkPuzzle,
 }
 
 impl TransactionData {
-    /// Serialize the transaction data for signing
+    /// Canonical encoding of the transaction data, which is signed and
+    /// hashed into the transaction ID
+    ///
+    /// Written field by field rather than through serde, so changing the
+    /// struct or a serializer can't silently change signatures and IDs:
+    /// [`SIGNING_DOMAIN`], the [`SIGNING_VERSION`] byte, then every field in
+    /// declaration order. Integers are little-endian and fixed width, keys
+    /// and hashes their raw bytes, an `Option` a 0/1 byte followed by the
+    /// value if present, and the parents, memo, relay proofs and outputs
+    /// are prefixed with their length as a `u32`. HTLC terms start with a
+    /// byte for their kind, and the algorithms are a tag byte each.
     pub fn to_signing_bytes(&self) -> Vec<u8> {
-        // Use bincode for deterministic serialization
-        bincode::serialize(self).expect("serialization should not fail")
+        let mut out = Vec::with_capacity(256);
+        out.extend_from_slice(SIGNING_DOMAIN);
+        out.push(SIGNING_VERSION);
+        out.extend_from_slice(&self.network_id.to_le_bytes());
+        out.push(self.tx_type.signing_tag());
+        out.extend_from_slice(&(self.parents.len() as u32).to_le_bytes());
+        for parent in &self.parents {
+            out.extend_from_slice(parent.as_bytes());
+        }
+        out.extend_from_slice(self.sender.as_bytes());
+        out.extend_from_slice(self.recipient.as_bytes());
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.extend_from_slice(&self.fee.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.extend_from_slice(&self.nonce.to_le_bytes());
+        match &self.memo {
+            Some(memo) => {
+                out.push(1);
+                out.extend_from_slice(&(memo.len() as u32).to_le_bytes());
+                out.extend_from_slice(memo.as_bytes());
+            }
+            None => out.push(0),
+        }
+        match &self.fee_payer {
+            Some(payer) => {
+                out.push(1);
+                out.extend_from_slice(payer.as_bytes());
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&(self.relay_proofs.len() as u32).to_le_bytes());
+        for proof in &self.relay_proofs {
+            out.extend_from_slice(proof.relayer.as_bytes());
+            out.extend_from_slice(proof.transaction_id.as_bytes());
+            out.push(proof.hop_count);
+            out.extend_from_slice(&proof.timestamp.to_le_bytes());
+            out.extend_from_slice(proof.signature.as_bytes());
+        }
+        out.extend_from_slice(&self.relay_tip.to_le_bytes());
+        match &self.channel {
+            Some(update) => {
+                out.push(1);
+                out.extend_from_slice(update.state.channel_id.as_bytes());
+                out.extend_from_slice(&update.state.sequence.to_le_bytes());
+                out.extend_from_slice(&update.state.paid.to_le_bytes());
+                match &update.signature {
+                    Some(signature) => {
+                        out.push(1);
+                        out.extend_from_slice(signature.as_bytes());
+                    }
+                    None => out.push(0),
+                }
+            }
+            None => out.push(0),
+        }
+        match &self.selection_proof {
+            Some(proof) => {
+                out.push(1);
+                out.extend_from_slice(proof.as_bytes());
+            }
+            None => out.push(0),
+        }
+        out.extend_from_slice(&self.valid_after.to_le_bytes());
+        match &self.htlc {
+            Some(HtlcTerms::Lock { hash_lock, timeout }) => {
+                out.extend_from_slice(&[1, 0]);
+                out.extend_from_slice(hash_lock.as_bytes());
+                out.extend_from_slice(&timeout.to_le_bytes());
+            }
+            Some(HtlcTerms::Claim { lock, preimage }) => {
+                out.extend_from_slice(&[1, 1]);
+                out.extend_from_slice(lock.as_bytes());
+                out.extend_from_slice(preimage.as_bytes());
+            }
+            Some(HtlcTerms::Refund { lock }) => {
+                out.extend_from_slice(&[1, 2]);
+                out.extend_from_slice(lock.as_bytes());
+            }
+            None => out.push(0),
+        }
+        out.push(self.algorithms.hash.signing_tag());
+        out.push(self.algorithms.sig.signing_tag());
+        out.extend_from_slice(&(self.outputs.len() as u32).to_le_bytes());
+        for output in &self.outputs {
+            out.extend_from_slice(output.recipient.as_bytes());
+            out.extend_from_slice(&output.amount.to_le_bytes());
+        }
+        out
+    }
+
+    /// An unsigned mainnet transfer from `sender`, dated now
+    ///
+    /// For keys kept off the machine that builds the transfer: the data
+    /// travels to wherever the key is, and [`Transaction::new`] signs it
+    /// there.
+    pub fn transfer(
+        sender: PublicKey,
+        recipient: PublicKey,
+        amount: u64,
+        memo: Option<String>,
+        relay_tip: u64,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::Transfer,
+            parents: parents.into(),
+            sender,
+            recipient,
+            amount,
+            fee: 0,
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            nonce,
+            memo,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// An unsigned mainnet batch from `sender` paying every output, dated
+    /// now
+    pub fn batch(
+        sender: PublicKey,
+        outputs: Vec<BatchOutput>,
+        memo: Option<String>,
+        relay_tip: u64,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        // An overflowing total can't be valid; saturating keeps it so
+        let amount = outputs.iter().fold(0u64, |total, output| total.saturating_add(output.amount));
+        TransactionData {
+            tx_type: TransactionType::Batch,
+            outputs,
+            ..Self::transfer(sender.clone(), sender, amount, memo, relay_tip, parents, nonce)
+        }
+    }
+
+    /// Every key the transaction touches: sender, recipient, fee payer and
+    /// the recipients of a batch, each once
+    pub fn parties(&self) -> Vec<&PublicKey> {
+        let mut keys = vec![&self.sender];
+        let others = [&self.recipient].into_iter().chain(&self.fee_payer);
+        for key in others.chain(self.outputs.iter().map(|output| &output.recipient)) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Whether the time lock has passed for a transaction at `depth`, dated
+    /// `timestamp`
+    pub fn unlocked_at(&self, depth: u64, timestamp: u64) -> bool {
+        lock_passed(self.valid_after, depth, timestamp)
+    }
+
+    /// Who pays the fee: the sponsor if there is one, otherwise the sender
+    pub fn fee_payer(&self) -> &PublicKey {
+        self.fee_payer.as_ref().unwrap_or(&self.sender)
+    }
+
+    /// Amount credited to `key` by this transaction
+    ///
+    /// Channel deposits and HTLCs are locked rather than paid to the
+    /// recipient, and a batch pays its outputs instead.
+    pub fn credit_for(&self, key: &PublicKey) -> u128 {
+        if self.tx_type == TransactionType::Batch {
+            return self
+                .outputs
+                .iter()
+                .filter(|output| output.recipient == *key)
+                .map(|output| output.amount as u128)
+                .sum();
+        }
+        let locked = matches!(self.tx_type, TransactionType::ChannelOpen | TransactionType::HtlcLock);
+        if self.recipient == *key && !locked {
+            self.amount as u128
+        } else {
+            0
+        }
+    }
+
+    /// Amount debited from `key` by this transaction
+    ///
+    /// Self-payments only credit, and the founder allocation is minted rather
+    /// than paid by the genesis key. A batch, addressed to its sender, still
+    /// debits its total. The fee is charged to the fee payer.
+    pub fn debit_for(&self, key: &PublicKey) -> u128 {
+        if self.tx_type == TransactionType::FounderAllocation {
+            return 0;
+        }
+        let pays_out = self.recipient != *key || self.tx_type == TransactionType::Batch;
+        let mut debit = 0;
+        if self.sender == *key && pays_out {
+            debit += self.amount as u128;
+            if self.fee_payer.is_none() {
+                debit += self.fee as u128;
+            }
+        }
+        if self.fee_payer.as_ref() == Some(key) {
+            debit += self.fee as u128;
+        }
+        if self.sender == *key {
+            debit += self.relay_tip as u128;
+        }
+        debit
+    }
+
+    /// What the transaction offers to get included: its fee plus relay tip
+    ///
+    /// A replacement has to offer more than the transfer it replaces.
+    pub fn priority(&self) -> u128 {
+        self.fee as u128 + self.relay_tip as u128
     }
 }
 
@@ -61,21 +466,112 @@ impl Transaction {
     /// Create and sign a new transaction
     pub fn new(data: TransactionData, keypair: &KeyPair) -> Self {
         let signing_bytes = data.to_signing_bytes();
-        let signature = keypair.sign(&signing_bytes);
-        let id = Hash::digest(&signing_bytes);
+        let signature = data.algorithms.sig.sign(keypair, &signing_bytes);
+        let id = data.algorithms.hash.digest(&signing_bytes);
 
         Transaction {
             id,
             data,
             signature,
+            fee_payer_signature: None,
+            multisig: None,
+            work_nonce: work::solve(&id, MIN_WORK_BITS),
+            work_puzzle: WorkPuzzle::Blake3,
+        }
+    }
+
+    /// A multisig spend of `data` from the account of `policy`, with no
+    /// member signatures yet
+    fn unsigned_multisig(data: TransactionData, policy: MultisigPolicy) -> Self {
+        let id = data.algorithms.hash.digest(&data.to_signing_bytes());
+        Transaction {
+            id,
+            data,
+            signature: Signature([0; 64]),
+            fee_payer_signature: None,
+            multisig: Some(MultisigWitness::new(policy)),
+            work_nonce: work::solve(&id, MIN_WORK_BITS),
+            work_puzzle: WorkPuzzle::Blake3,
+        }
+    }
+
+    /// `data` in place of the transaction's own, signed again; a multisig
+    /// spend comes back without its members' signatures
+    fn with_data(self, data: TransactionData, keypair: &KeyPair) -> Self {
+        match self.multisig {
+            Some(witness) => Self::unsigned_multisig(data, witness.policy),
+            None => Transaction::new(data, keypair),
         }
     }
 
+    /// Work done on the transaction, in bits of its puzzle
+    pub fn work_bits(&self) -> u32 {
+        self.work_puzzle.work_bits(&self.id, self.work_nonce)
+    }
+
+    /// The transaction with at least `bits` of work, counted in BLAKE3
+    /// bits, redoing it on its own puzzle if it has less
+    pub fn with_work(self, bits: u32) -> Self {
+        let puzzle = self.work_puzzle;
+        self.with_puzzle(puzzle, bits)
+    }
+
+    /// The transaction with at least `bits` of work, counted in BLAKE3
+    /// bits, redoing it on `puzzle` if it has less
+    pub fn with_puzzle(mut self, puzzle: WorkPuzzle, bits: u32) -> Self {
+        if self.work_bits() < self.work_puzzle.bits_for(bits) {
+            self.work_puzzle = puzzle;
+            self.work_nonce = puzzle.solve(&self.id, puzzle.bits_for(bits));
+        }
+        self
+    }
+
+    /// The same transaction for the network `network_id`, signed again
+    ///
+    /// The constructors below make mainnet transactions. Call this before a
+    /// fee payer or multisig members sign; a transaction already on
+    /// `network_id` is returned as is.
+    pub fn for_network(self, network_id: u32, keypair: &KeyPair) -> Self {
+        if self.data.network_id == network_id {
+            return self;
+        }
+        let mut data = self.data.clone();
+        data.network_id = network_id;
+        self.with_data(data, keypair)
+    }
+
+    /// The same transaction carrying the VRF proof its parents were chosen
+    /// with, signed again
+    pub fn with_selection_proof(self, proof: VrfProof, keypair: &KeyPair) -> Self {
+        let mut data = self.data.clone();
+        data.selection_proof = Some(proof);
+        self.with_data(data, keypair)
+    }
+
+    /// The same transaction offering `fee`, signed again
+    ///
+    /// A sponsored transaction needs its fee payer's signature again after,
+    /// and a multisig spend its members'.
+    pub fn with_fee(self, fee: u64, keypair: &KeyPair) -> Self {
+        let mut data = self.data.clone();
+        data.fee = fee;
+        self.with_data(data, keypair)
+    }
+
+    /// The same payment locked until `valid_after` (see
+    /// [`TransactionData::valid_after`]), signed again
+    pub fn with_valid_after(self, valid_after: u64, keypair: &KeyPair) -> Self {
+        let mut data = self.data.clone();
+        data.valid_after = valid_after;
+        self.with_data(data, keypair)
+    }
+
     /// Create a genesis transaction
     pub fn genesis(keypair: &KeyPair) -> Self {
         let data = TransactionData {
+            network_id: crate::MAINNET_ID,
             tx_type: TransactionType::Genesis,
-            parents: [Hash::zero(), Hash::zero()],
+            parents: vec![Hash::zero(); crate::PARENT_COUNT],
             sender: keypair.public_key.clone(),
             recipient: keypair.public_key.clone(),
             amount: 0,
@@ -83,6 +579,15 @@ impl Transaction {
             timestamp: 0,
             nonce: 0,
             memo: Some("Rhiza Genesis — The root of true decentralization".to_string()),
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
         };
         Transaction::new(data, keypair)
     }
@@ -94,8 +599,9 @@ impl Transaction {
         genesis_id: Hash,
     ) -> Self {
         let data = TransactionData {
+            network_id: crate::MAINNET_ID,
             tx_type: TransactionType::FounderAllocation,
-            parents: [genesis_id, genesis_id],
+            parents: vec![genesis_id; crate::PARENT_COUNT],
             sender: genesis_keypair.public_key.clone(),
             recipient: founder_pubkey,
             amount: crate::FOUNDER_ALLOCATION,
@@ -103,6 +609,15 @@ impl Transaction {
             timestamp: 0,
             nonce: 1,
             memo: Some("Rhiza Founder Allocation — 5% genesis grant".to_string()),
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
         };
         Transaction::new(data, genesis_keypair)
     }
@@ -112,35 +627,430 @@ impl Transaction {
         sender_keypair: &KeyPair,
         recipient: PublicKey,
         amount: u64,
-        parents: [Hash; 2],
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        Self::transfer_with_memo(sender_keypair, recipient, amount, None, parents, nonce)
+    }
+
+    /// Create a transfer transaction carrying an optional memo
+    pub fn transfer_with_memo(
+        sender_keypair: &KeyPair,
+        recipient: PublicKey,
+        amount: u64,
+        memo: Option<String>,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        Self::transfer_with_tip(sender_keypair, recipient, amount, memo, 0, parents, nonce)
+    }
+
+    /// Create a transfer that tips the relayer who first gets it finalized
+    pub fn transfer_with_tip(
+        sender_keypair: &KeyPair,
+        recipient: PublicKey,
+        amount: u64,
+        memo: Option<String>,
+        relay_tip: u64,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let data = TransactionData::transfer(
+            sender_keypair.public_key.clone(),
+            recipient,
+            amount,
+            memo,
+            relay_tip,
+            parents,
+            nonce,
+        );
+        Transaction::new(data, sender_keypair)
+    }
+
+    /// Create a batch paying every output from the sender at once
+    pub fn batch(
+        sender_keypair: &KeyPair,
+        outputs: Vec<BatchOutput>,
+        memo: Option<String>,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let sender = sender_keypair.public_key.clone();
+        let data = TransactionData::batch(sender, outputs, memo, 0, parents, nonce);
+        Transaction::new(data, sender_keypair)
+    }
+
+    /// Create a transfer whose fee is paid by a sponsor
+    ///
+    /// The sender signs here; the sponsor must then add its signature with
+    /// `sign_as_fee_payer` before the transaction is valid.
+    pub fn sponsored_transfer(
+        sender_keypair: &KeyPair,
+        recipient: PublicKey,
+        amount: u64,
+        fee: u64,
+        fee_payer: PublicKey,
+        parents: impl Into<Vec<Hash>>,
         nonce: u64,
     ) -> Self {
         let now = chrono::Utc::now().timestamp_millis() as u64;
         let data = TransactionData {
+            network_id: crate::MAINNET_ID,
             tx_type: TransactionType::Transfer,
-            parents,
+            parents: parents.into(),
             sender: sender_keypair.public_key.clone(),
             recipient,
             amount,
-            fee: 0,
+            fee,
             timestamp: now,
             nonce,
             memo: None,
+            fee_payer: Some(fee_payer),
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, sender_keypair)
+    }
+
+    /// Create a replacement for an unfinalized transfer
+    ///
+    /// The replacement keeps the original's sender, recipient, amount, fee
+    /// and nonce, and swaps in a new memo and relay tip; the tip has to raise
+    /// the priority above the original's for validators to take it. A
+    /// sponsored replacement needs the fee payer's signature again.
+    pub fn replacement(
+        original: &Transaction,
+        sender_keypair: &KeyPair,
+        memo: Option<String>,
+        relay_tip: u64,
+        parents: impl Into<Vec<Hash>>,
+    ) -> Self {
+        let data = TransactionData {
+            parents: parents.into(),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            memo,
+            relay_tip,
+            ..original.data.clone()
         };
         Transaction::new(data, sender_keypair)
     }
 
-    /// Create a relay reward transaction
+    /// Create a spend from the multisig account of `policy`
+    ///
+    /// Nobody has signed it yet: enough members must add their signatures
+    /// with `sign_as_multisig_member` before the transaction is valid.
+    pub fn multisig_spend(
+        policy: MultisigPolicy,
+        recipient: PublicKey,
+        amount: u64,
+        memo: Option<String>,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::Multisig,
+            parents: parents.into(),
+            sender: policy.account(),
+            recipient,
+            amount,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Self::unsigned_multisig(data, policy)
+    }
+
+    /// Add a multisig member's signature; returns how many members have
+    /// signed
+    pub fn sign_as_multisig_member(&mut self, keypair: &KeyPair) -> Result<usize, MultisigError> {
+        let signing_bytes = Self::multisig_signing_bytes(&self.id);
+        let witness = self.multisig.as_mut().ok_or(MultisigError::NotMultisig)?;
+        witness.sign(keypair, &signing_bytes)
+    }
+
+    /// Check that a multisig spend's policy is its sender's and enough
+    /// members signed it
+    pub fn verify_multisig(&self) -> Result<(), MultisigError> {
+        let witness = self.multisig.as_ref().ok_or(MultisigError::NotMultisig)?;
+        if self.data.tx_type != TransactionType::Multisig {
+            return Err(MultisigError::NotMultisig);
+        }
+        if witness.policy.account() != self.data.sender {
+            return Err(MultisigError::WrongAccount);
+        }
+        witness.verify(&Self::multisig_signing_bytes(&self.id))
+    }
+
+    fn multisig_signing_bytes(id: &Hash) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"MULTISIG:");
+        data.extend_from_slice(id.as_bytes());
+        data
+    }
+
+    /// Add the fee payer's co-signature
+    pub fn sign_as_fee_payer(&mut self, keypair: &KeyPair) {
+        let signing_bytes = Self::fee_payer_signing_bytes(&self.id);
+        self.fee_payer_signature = Some(self.data.algorithms.sig.sign(keypair, &signing_bytes));
+    }
+
+    fn fee_payer_signing_bytes(id: &Hash) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"FEE_PAYER:");
+        data.extend_from_slice(id.as_bytes());
+        data
+    }
+
+    /// Announce `alias` for the signing key, with payments directed to
+    /// `receive_key`
+    ///
+    /// Moves no funds; the alias is bound once the announcement is final.
+    pub fn alias_announcement(
+        keypair: &KeyPair,
+        alias: &Alias,
+        receive_key: PublicKey,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::AliasAnnouncement,
+            parents: parents.into(),
+            sender: keypair.public_key.clone(),
+            recipient: receive_key,
+            amount: 0,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo: Some(alias.name().to_string()),
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, keypair)
+    }
+
+    /// Publish the signing key, so payments to its `rhz1` address can be
+    /// resolved before it has transacted
+    pub fn key_announcement(keypair: &KeyPair, parents: impl Into<Vec<Hash>>, nonce: u64) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::KeyAnnouncement,
+            parents: parents.into(),
+            sender: keypair.public_key.clone(),
+            recipient: keypair.public_key.clone(),
+            amount: 0,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo: None,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, keypair)
+    }
+
+    /// Open a payment channel to `payee`, locking `deposit` until it closes
+    pub fn channel_open(
+        funder: &KeyPair,
+        payee: PublicKey,
+        deposit: u64,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::ChannelOpen,
+            parents: parents.into(),
+            sender: funder.public_key.clone(),
+            recipient: payee,
+            amount: deposit,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo: None,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, funder)
+    }
+
+    /// Close a channel on `update`, which `counterparty` signed
+    pub fn channel_close(
+        keypair: &KeyPair,
+        counterparty: PublicKey,
+        update: SignedChannelState,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::ChannelClose,
+            parents: parents.into(),
+            sender: keypair.public_key.clone(),
+            recipient: counterparty,
+            amount: 0,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo: None,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: Some(update),
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, keypair)
+    }
+
+    /// Lock `amount` for `claimer`, to be claimed with the secret whose
+    /// SHA-256 is `hash_lock`, or refunded once past `timeout` (a DAG depth
+    /// or Unix ms, as for [`TransactionData::valid_after`])
+    pub fn htlc_lock(
+        locker: &KeyPair,
+        claimer: PublicKey,
+        amount: u64,
+        hash_lock: Hash,
+        timeout: u64,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let terms = HtlcTerms::Lock { hash_lock, timeout };
+        Self::htlc(locker, TransactionType::HtlcLock, claimer, amount, terms, parents, nonce)
+    }
+
+    /// Claim the HTLC locked by `lock` with its secret
+    pub fn htlc_claim(
+        claimer: &KeyPair,
+        locker: PublicKey,
+        lock: Hash,
+        preimage: Preimage,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let terms = HtlcTerms::Claim { lock, preimage };
+        Self::htlc(claimer, TransactionType::HtlcClaim, locker, 0, terms, parents, nonce)
+    }
+
+    /// Take back the funds of the HTLC locked by `lock`, past its timeout
+    pub fn htlc_refund(
+        locker: &KeyPair,
+        claimer: PublicKey,
+        lock: Hash,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let terms = HtlcTerms::Refund { lock };
+        Self::htlc(locker, TransactionType::HtlcRefund, claimer, 0, terms, parents, nonce)
+    }
+
+    fn htlc(
+        keypair: &KeyPair,
+        tx_type: TransactionType,
+        counterparty: PublicKey,
+        amount: u64,
+        terms: HtlcTerms,
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let data = TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type,
+            parents: parents.into(),
+            sender: keypair.public_key.clone(),
+            recipient: counterparty,
+            amount,
+            fee: 0,
+            timestamp: now,
+            nonce,
+            memo: None,
+            fee_payer: None,
+            relay_proofs: Vec::new(),
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: Some(terms),
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
+        };
+        Transaction::new(data, keypair)
+    }
+
+    /// Create a relay reward transaction without relay proofs
+    ///
+    /// Such a claim doesn't pass validation; it is only useful for building
+    /// DAGs directly (tests, tooling). Use `relay_reward_with_proofs` for
+    /// real claims.
     pub fn relay_reward(
         keypair: &KeyPair,
         reward_amount: u64,
-        parents: [Hash; 2],
+        parents: impl Into<Vec<Hash>>,
+        nonce: u64,
+    ) -> Self {
+        Self::relay_reward_with_proofs(keypair, reward_amount, Vec::new(), parents, nonce)
+    }
+
+    /// Create a relay reward transaction claiming the given relay work
+    pub fn relay_reward_with_proofs(
+        keypair: &KeyPair,
+        reward_amount: u64,
+        relay_proofs: Vec<RelayProof>,
+        parents: impl Into<Vec<Hash>>,
         nonce: u64,
     ) -> Self {
         let now = chrono::Utc::now().timestamp_millis() as u64;
         let data = TransactionData {
+            network_id: crate::MAINNET_ID,
             tx_type: TransactionType::RelayReward,
-            parents,
+            parents: parents.into(),
             sender: keypair.public_key.clone(),
             recipient: keypair.public_key.clone(),
             amount: reward_amount,
@@ -148,20 +1058,46 @@ impl Transaction {
             timestamp: now,
             nonce,
             memo: None,
+            fee_payer: None,
+            relay_proofs,
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: Vec::new(),
         };
         Transaction::new(data, keypair)
     }
 
-    /// Verify the transaction's signature
+    /// Verify the transaction's signature (and the fee payer's, if sponsored)
+    ///
+    /// A multisig spend is signed by its members instead of its sender.
     pub fn verify_signature(&self) -> bool {
-        let signing_bytes = self.data.to_signing_bytes();
-        self.data.sender.verify(&signing_bytes, &self.signature)
+        let sig = self.data.algorithms.sig;
+        let signed = match (&self.data.tx_type, &self.multisig) {
+            (TransactionType::Multisig, _) => self.verify_multisig().is_ok(),
+            (_, Some(_)) => false,
+            _ => sig.verify(&self.data.sender, &self.data.to_signing_bytes(), &self.signature),
+        };
+        if !signed {
+            return false;
+        }
+
+        match (&self.data.fee_payer, &self.fee_payer_signature) {
+            (None, None) => true,
+            (Some(payer), Some(signature)) => {
+                sig.verify(payer, &Self::fee_payer_signing_bytes(&self.id), signature)
+            }
+            _ => false,
+        }
     }
 
     /// Verify the transaction ID matches the data
     pub fn verify_id(&self) -> bool {
         let signing_bytes = self.data.to_signing_bytes();
-        let expected_id = Hash::digest(&signing_bytes);
+        let expected_id = self.data.algorithms.hash.digest(&signing_bytes);
         self.id == expected_id
     }
 }
@@ -202,6 +1138,32 @@ mod tests {
         assert!(tx.verify_id());
     }
 
+    #[test]
+    fn test_unsigned_transfer_signed_elsewhere() {
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        let genesis = Transaction::genesis(&sender);
+
+        let mut data = TransactionData::transfer(
+            sender.public_key.clone(),
+            recipient.public_key.clone(),
+            5_000,
+            Some("cold".to_string()),
+            10,
+            [genesis.id, genesis.id],
+            1,
+        );
+        data.fee = 3;
+        // Carried to the signing machine as JSON
+        let carried: TransactionData = serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert_eq!(carried.to_signing_bytes(), data.to_signing_bytes());
+
+        let tx = Transaction::new(carried, &sender);
+        assert!(tx.verify_signature() && tx.verify_id());
+        assert_eq!((tx.data.amount, tx.data.fee, tx.data.relay_tip), (5_000, 3, 10));
+        assert!(!Transaction::new(data, &recipient).verify_signature());
+    }
+
     #[test]
     fn test_relay_reward_transaction() {
         let kp = KeyPair::generate();
@@ -239,6 +1201,187 @@ mod tests {
         assert!(!tx.verify_id());
     }
 
+    #[test]
+    fn test_sponsored_transfer_signatures() {
+        let sender = KeyPair::generate();
+        let sponsor = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        let genesis = Transaction::genesis(&sender);
+
+        let mut tx = Transaction::sponsored_transfer(
+            &sender,
+            recipient.public_key.clone(),
+            1_000,
+            10,
+            sponsor.public_key.clone(),
+            [genesis.id, genesis.id],
+            1,
+        );
+        // Not valid until the sponsor co-signs
+        assert!(!tx.verify_signature());
+
+        let mut forged = tx.clone();
+        forged.sign_as_fee_payer(&recipient);
+        assert!(!forged.verify_signature());
+
+        tx.sign_as_fee_payer(&sponsor);
+        assert!(tx.verify_signature());
+        assert!(tx.verify_id());
+
+        assert_eq!(tx.data.debit_for(&sender.public_key), 1_000);
+        assert_eq!(tx.data.debit_for(&sponsor.public_key), 10);
+        assert_eq!(tx.data.credit_for(&recipient.public_key), 1_000);
+    }
+
+    #[test]
+    fn test_multisig_spend_signatures() {
+        let members: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate()).collect();
+        let policy = MultisigPolicy::new(2, members.iter().map(|kp| kp.public_key.clone()).collect()).unwrap();
+        let recipient = KeyPair::generate().public_key;
+        let genesis = Transaction::genesis(&members[0]);
+
+        let parents = [genesis.id, genesis.id];
+        let mut tx = Transaction::multisig_spend(policy.clone(), recipient, 1_000, None, parents, 1);
+        assert_eq!(tx.data.sender, policy.account());
+        assert!(tx.verify_id());
+        assert_eq!(tx.sign_as_multisig_member(&members[1]), Ok(1));
+        assert!(!tx.verify_signature());
+        assert_eq!(tx.sign_as_multisig_member(&members[2]), Ok(2));
+        assert!(tx.verify_signature());
+        assert_eq!(tx.data.debit_for(&policy.account()), 1_000);
+
+        // A policy that isn't the sender's doesn't authorize anything
+        let mut swapped = tx.clone();
+        let outsider = KeyPair::generate();
+        let other = MultisigPolicy::new(1, vec![outsider.public_key.clone()]).unwrap();
+        swapped.multisig = Some(MultisigWitness::new(other));
+        swapped.sign_as_multisig_member(&outsider).unwrap();
+        assert_eq!(swapped.verify_multisig(), Err(MultisigError::WrongAccount));
+        assert!(!swapped.verify_signature());
+
+        // Nor does a witness on another type, or none on a multisig spend
+        let mut transfer = Transaction::transfer(&members[0], policy.account(), 5, parents, 1);
+        transfer.multisig = tx.multisig.clone();
+        assert!(!transfer.verify_signature());
+        let mut bare = tx.clone();
+        bare.multisig = None;
+        assert!(!bare.verify_signature());
+
+        // Changing the data drops the signatures, which no longer cover it
+        let moved = tx.for_network(crate::MAINNET_ID + 1, &members[0]);
+        assert_eq!(moved.multisig.as_ref().map(|w| w.signatures.len()), Some(0));
+        assert!(!moved.verify_signature());
+    }
+
+    fn vector_data(alice: &KeyPair, bob: &KeyPair) -> TransactionData {
+        TransactionData {
+            network_id: crate::MAINNET_ID,
+            tx_type: TransactionType::Transfer,
+            parents: vec![Hash::digest(b"parent a"), Hash::digest(b"parent b")],
+            sender: alice.public_key.clone(),
+            recipient: bob.public_key.clone(),
+            amount: 150_000_000,
+            fee: 0,
+            timestamp: 1_700_000_000_000,
+            nonce: 42,
+            memo: Some("coffee".into()),
+            fee_payer: None,
+            relay_proofs: vec![],
+            relay_tip: 0,
+            channel: None,
+            selection_proof: None,
+            valid_after: 0,
+            htlc: None,
+            algorithms: Algorithms::CURRENT,
+            outputs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_signing_bytes_vectors() {
+        let alice = KeyPair::from_seed_phrase_for_tests("alice");
+        let bob = KeyPair::from_seed_phrase_for_tests("bob");
+
+        let data = vector_data(&alice, &bob);
+        let tx = Transaction::new(data.clone(), &alice);
+        assert_eq!(
+            hex::encode(data.to_signing_bytes()),
+            concat!(
+                // domain, version, network, type
+                "5248495a415f5458090100000000",
+                // parent count, parents
+                "02000000",
+                "a0f72e85a73f8a1f22ada56179b246269330733ec2edb081d8cdad70081064e4",
+                "8e36172f78f1dd238dbbb1e93ae1c73bf6cd2be4a396250ca8187c21a61afc10",
+                // sender
+                "2e023dc749bc585452d682769b09fb7f9b601adae2bbd590de88ffc4955de1a6",
+                // recipient
+                "6637066d7030e1d96484b3394d088c4e73e5c6fb9c87ca67ff4443b355a05606",
+                // amount, fee
+                "80d1f008000000000000000000000000",
+                // timestamp, nonce
+                "0068e5cf8b0100002a00000000000000",
+                // memo
+                "0106000000636f66666565",
+                // fee payer, relay proofs, relay tip, channel, selection proof
+                "000000000000000000000000000000",
+                // valid after, htlc
+                "000000000000000000",
+                // hash and signature algorithms, output count
+                "000000000000",
+            )
+        );
+        assert_eq!(tx.id.to_string(), "19c6595008e12cfddf54a158b326c17f7d81385ed557cebe91031569d45e6acc");
+        assert_eq!(hex::encode(tx.signature.as_bytes()), "3cc5632a7c92034a0c807de70b9e748df5367e67dfea78e517a88b02ce62383a1b5cc613e00781a6ce5197e07f118ddb921509e8cc56b2b0ba3aef354f72200a");
+
+        // Every optional part present
+        let mut data = vector_data(&alice, &bob);
+        data.tx_type = TransactionType::RelayReward;
+        data.parents.push(Hash::digest(b"parent c"));
+        data.fee = 3;
+        data.fee_payer = Some(bob.public_key.clone());
+        data.relay_tip = 7;
+        data.relay_proofs.push(RelayProof {
+            relayer: bob.public_key.clone(),
+            transaction_id: Hash::digest(b"relayed"),
+            hop_count: 2,
+            timestamp: 1_700_000_000_500,
+            signature: bob.sign(b"relayed"),
+        });
+        data.channel = Some(
+            crate::dag::channels::ChannelState {
+                channel_id: Hash::digest(b"channel"),
+                sequence: 3,
+                paid: 900,
+            }
+            .sign(&bob),
+        );
+        data.selection_proof = Some(VrfProof::prove(&alice, b"selection"));
+        data.valid_after = 1_700_000_600_000;
+        data.htlc = Some(HtlcTerms::Claim {
+            lock: Hash::digest(b"lock"),
+            preimage: Preimage::from_bytes([7; 32]),
+        });
+        data.outputs.push(BatchOutput { recipient: bob.public_key.clone(), amount: 5 });
+        assert_eq!(Transaction::new(data, &alice).id.to_string(), "096bb3f25389668f079b52ec230b20a14ea98d906839c7a3be82103c5b999fd6");
+    }
+
+    #[test]
+    fn test_signing_bytes_are_domain_separated() {
+        let alice = KeyPair::from_seed_phrase_for_tests("alice");
+        let bob = KeyPair::from_seed_phrase_for_tests("bob");
+        let bytes = vector_data(&alice, &bob).to_signing_bytes();
+        assert!(bytes.starts_with(SIGNING_DOMAIN));
+        assert_eq!(bytes[SIGNING_DOMAIN.len()], SIGNING_VERSION);
+
+        // A missing memo and an empty one encode differently
+        let mut empty = vector_data(&alice, &bob);
+        empty.memo = Some(String::new());
+        let mut none = vector_data(&alice, &bob);
+        none.memo = None;
+        assert_ne!(empty.to_signing_bytes(), none.to_signing_bytes());
+    }
+
     #[test]
     fn test_transaction_serialization() {
         let kp = KeyPair::generate();
@@ -0,0 +1,191 @@
+use crate::crypto::Hash;
+use crate::dag::transaction::Transaction;
+use crate::dag::validator::{TransactionValidator, ValidationError};
+use crate::dag::vertex::Dag;
+use std::collections::HashMap;
+
+/// How long a validation outcome is reused
+pub const DEFAULT_VALIDATION_TTL_MS: u64 = 30_000;
+
+/// Most outcomes kept at once
+pub const DEFAULT_VALIDATION_CACHE_CAPACITY: usize = 16_384;
+
+/// Recent validation outcomes, keyed by transaction ID
+///
+/// The same transaction usually reaches a node from several peers at once;
+/// this spares re-checking its signature and balances every time. Failures
+/// are reused until they expire, except a missing parent, which clears up
+/// as soon as the parent arrives. A success is only reused while the DAG is
+/// the size it was validated against, since anything inserted since may
+/// conflict with it.
+pub struct ValidationCache {
+    entries: HashMap<Hash, CachedOutcome>,
+    ttl_ms: u64,
+    capacity: usize,
+    hits: u64,
+}
+
+struct CachedOutcome {
+    result: Result<(), ValidationError>,
+    /// DAG size the outcome was computed against
+    dag_len: usize,
+    expires_ms: u64,
+}
+
+impl ValidationCache {
+    pub fn new(ttl_ms: u64, capacity: usize) -> Self {
+        ValidationCache {
+            entries: HashMap::new(),
+            ttl_ms,
+            capacity: capacity.max(1),
+            hits: 0,
+        }
+    }
+
+    /// Validate `tx` against `dag`, reusing a recent outcome when possible
+    pub fn validate(&mut self, tx: &Transaction, dag: &Dag, now_ms: u64) -> Result<(), ValidationError> {
+        // The work isn't part of the ID, so a copy with less of it can't
+        // ride on an outcome cached for another
+        TransactionValidator::validate_work(tx, dag)?;
+        if let Some(result) = self.lookup(&tx.id, dag, now_ms) {
+            self.hits += 1;
+            return result;
+        }
+
+        let result = TransactionValidator::validate(tx, dag);
+        if !matches!(result, Err(ValidationError::ParentNotFound)) {
+            self.insert(tx.id, result.clone(), dag.len(), now_ms);
+        }
+        result
+    }
+
+    /// Why `id` was last rejected, while the outcome is cached
+    pub fn rejection(&self, id: &Hash) -> Option<&ValidationError> {
+        self.entries.get(id).and_then(|entry| entry.result.as_ref().err())
+    }
+
+    /// A still-usable outcome for `id`
+    fn lookup(&self, id: &Hash, dag: &Dag, now_ms: u64) -> Option<Result<(), ValidationError>> {
+        let entry = self.entries.get(id)?;
+        if entry.expires_ms <= now_ms || (entry.result.is_ok() && entry.dag_len != dag.len()) {
+            return None;
+        }
+        Some(entry.result.clone())
+    }
+
+    fn insert(&mut self, id: Hash, result: Result<(), ValidationError>, dag_len: usize, now_ms: u64) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&id) {
+            self.entries.retain(|_, e| e.expires_ms > now_ms);
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&id) {
+            // Still full of live entries: drop the one closest to expiring
+            if let Some(oldest) = self.entries.iter().min_by_key(|(_, e)| e.expires_ms).map(|(id, _)| *id) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            id,
+            CachedOutcome {
+                result,
+                dag_len,
+                expires_ms: now_ms + self.ttl_ms,
+            },
+        );
+    }
+
+    /// Forget the outcome for `id`
+    pub fn invalidate(&mut self, id: &Hash) {
+        self.entries.remove(id);
+    }
+
+    /// Number of cached outcomes (expired ones included until evicted)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Validations answered from the cache so far
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+}
+
+impl Default for ValidationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_VALIDATION_TTL_MS, DEFAULT_VALIDATION_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::vertex::DagVertex;
+
+    fn dag_with_balance() -> (Dag, KeyPair, Hash) {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        let reward = Transaction::relay_reward(&kp, 1_000_000, [genesis_id, genesis_id], 1);
+        let reward_id = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+        (dag, kp, reward_id)
+    }
+
+    #[test]
+    fn test_failures_are_reused_until_expiry() {
+        let (dag, kp, tip) = dag_with_balance();
+        let mut tx = Transaction::transfer(&kp, KeyPair::generate().public_key, 100, [tip, tip], 2);
+        tx.data.amount = 200;
+        let mut cache = ValidationCache::new(1_000, 16);
+
+        assert!(matches!(cache.validate(&tx, &dag, 0), Err(ValidationError::InvalidId)));
+        assert!(matches!(cache.validate(&tx, &dag, 999), Err(ValidationError::InvalidId)));
+        assert_eq!(cache.hits(), 1);
+        cache.validate(&tx, &dag, 1_000).unwrap_err();
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_success_is_dropped_when_the_dag_changes() {
+        let (mut dag, kp, tip) = dag_with_balance();
+        let tx = Transaction::transfer(&kp, KeyPair::generate().public_key, 100, [tip, tip], 2);
+        let mut cache = ValidationCache::default();
+
+        cache.validate(&tx, &dag, 0).unwrap();
+        cache.validate(&tx, &dag, 1).unwrap();
+        assert_eq!(cache.hits(), 1);
+
+        let other = Transaction::transfer(&kp, KeyPair::generate().public_key, 5, [tip, tip], 3);
+        dag.insert(DagVertex::new(other, 2)).unwrap();
+        cache.validate(&tx, &dag, 2).unwrap();
+        assert_eq!(cache.hits(), 1);
+    }
+
+    #[test]
+    fn test_missing_parent_is_not_cached() {
+        let (dag, kp, _) = dag_with_balance();
+        let orphan = Hash::from_bytes([7u8; 32]);
+        let tx = Transaction::transfer(&kp, KeyPair::generate().public_key, 100, [orphan, orphan], 2);
+        let mut cache = ValidationCache::default();
+
+        assert!(matches!(cache.validate(&tx, &dag, 0), Err(ValidationError::ParentNotFound)));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        let (dag, kp, tip) = dag_with_balance();
+        let mut cache = ValidationCache::new(1_000, 2);
+        for nonce in 0..5 {
+            let tx = Transaction::transfer(&kp, KeyPair::generate().public_key, 100, [tip, tip], nonce + 2);
+            cache.validate(&tx, &dag, nonce).unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+    }
+}
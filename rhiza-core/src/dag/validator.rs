@@ -1,10 +1,42 @@
-use crate::dag::transaction::{Transaction, TransactionType};
+use crate::consensus::relay::MAX_RELAY_PROOFS_PER_CLAIM;
+use crate::consensus::sampling::{RelayProofSampler, SampleVerdict};
+use crate::consensus::tip_selection::selection_output;
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::channels::ChannelStatus;
+use crate::dag::htlc::HtlcTerms;
+use crate::dag::transaction::{lock_passed, Transaction, TransactionType, MAX_BATCH_OUTPUTS};
 use crate::dag::vertex::Dag;
+use crate::dag::work::{MIN_WORK_BITS, WORK_SLACK_BITS};
+use crate::wallet::address::Address;
+use crate::wallet::alias::Alias;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 /// Validates transactions before they are added to the DAG
 pub struct TransactionValidator;
 
-#[derive(Debug, thiserror::Error)]
+/// How far a transaction's timestamp may stray from its parents' and from
+/// the validating node's clock (`timestamps` in config.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampRules {
+    /// How far before its newest parent a transaction may be dated, for
+    /// senders whose clock is behind the parent's sender's
+    pub parent_tolerance_ms: u64,
+    /// How far ahead of the local clock a transaction may be dated
+    pub max_drift_ms: u64,
+}
+
+impl Default for TimestampRules {
+    fn default() -> Self {
+        TimestampRules {
+            parent_tolerance_ms: 10 * 60 * 1_000,
+            max_drift_ms: 2 * 60 * 1_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum ValidationError {
     #[error("invalid signature")]
     InvalidSignature,
@@ -20,48 +52,250 @@ pub enum ValidationError {
     ParentNotFound,
     #[error("self-referencing parents")]
     SelfReference,
+    #[error("{count} parents, network {network_id} allows {min} to {max}")]
+    ParentCount { count: usize, network_id: u32, min: usize, max: usize },
     #[error("relay reward exceeds allowed amount")]
     InvalidRelayReward,
+    #[error("relay reward must carry between 1 and {0} relay proofs")]
+    RelayProofCount(usize),
+    #[error("invalid relay proof for {0}")]
+    InvalidRelayProof(crate::crypto::Hash),
+    #[error("forged relay proof for {proof} voids the claim")]
+    ForgedRelayClaim { proof: crate::crypto::Hash, penalty: i64 },
+    #[error("relay of {0} already claimed")]
+    RelayAlreadyClaimed(crate::crypto::Hash),
     #[error("invalid timestamp: {0}")]
     InvalidTimestamp(String),
     #[error("invalid founder allocation")]
     InvalidFounderAllocation,
+    #[error("fee payer cannot cover the fee: have {have}, need {need}")]
+    InsufficientFeePayerBalance { have: u64, need: u64 },
+    #[error("only transfers with a fee can be sponsored")]
+    InvalidFeePayer,
+    #[error("stale nonce {nonce}: sender's last confirmed nonce is {last}")]
+    StaleNonce { nonce: u64, last: u64 },
+    #[error("invalid alias announcement: {0}")]
+    InvalidAlias(#[from] crate::wallet::alias::AliasError),
+    #[error("announcements move no funds")]
+    AnnouncementWithAmount,
+    #[error("key is already known to the network")]
+    KeyAlreadyKnown,
+    #[error("a key announcement must be addressed to its sender")]
+    KeyAnnouncementNotSelf,
+    #[error("only transfers can carry a relay tip")]
+    InvalidRelayTip,
+    #[error("transaction is for network {got}, this is network {expected}")]
+    WrongNetwork { expected: u32, got: u32 },
+    #[error("replacement offers priority {offered}, must exceed {replaced}")]
+    ReplacementUnderpriced { offered: u128, replaced: u128 },
+    #[error("invalid channel transaction: {0}")]
+    InvalidChannel(&'static str),
+    #[error("tip selection proof doesn't hold for the sender and nonce")]
+    InvalidSelectionProof,
+    #[error("not enough work: {have} bits, need {need}")]
+    InsufficientWork { have: u32, need: u32 },
+    #[error("arithmetic overflow in {0}")]
+    Overflow(&'static str),
+    #[error("only payments can be time-locked")]
+    InvalidTimeLock,
+    #[error("invalid HTLC transaction: {0}")]
+    InvalidHtlc(&'static str),
+    #[error("batch must pay between 2 and {0} outputs")]
+    BatchOutputCount(usize),
+    #[error("invalid batch: {0}")]
+    InvalidBatch(&'static str),
+    #[error("{0} transactions aren't enabled")]
+    UnsupportedAlgorithms(crate::crypto::Algorithms),
+    #[error("multisig spend not authorized: {0}")]
+    Multisig(#[from] crate::crypto::multisig::MultisigError),
 }
 
 impl TransactionValidator {
     /// Validate a transaction against the current DAG state
     pub fn validate(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
-        // 1. Verify transaction ID
+        // 1. Verify transaction ID, made with algorithms in use and as many
+        // parents as its network allows
+        if !tx.data.algorithms.is_enabled() {
+            return Err(ValidationError::UnsupportedAlgorithms(tx.data.algorithms));
+        }
+        let allowed = crate::parent_counts(tx.data.network_id);
+        if !allowed.contains(&tx.data.parents.len()) {
+            return Err(ValidationError::ParentCount {
+                count: tx.data.parents.len(),
+                network_id: tx.data.network_id,
+                min: *allowed.start(),
+                max: *allowed.end(),
+            });
+        }
         if !tx.verify_id() {
             return Err(ValidationError::InvalidId);
         }
 
-        // 2. Verify signature
+        // 2. Verify signature; for a multisig spend, say what's amiss
+        if tx.data.tx_type == TransactionType::Multisig {
+            tx.verify_multisig()?;
+        }
         if !tx.verify_signature() {
             return Err(ValidationError::InvalidSignature);
         }
 
-        // 3. Type-specific validation
+        // 3. Must carry the work the load up to its parents asks for
+        Self::validate_work(tx, dag)?;
+
+        // 4. Must not be dated well before its parents or ahead of our clock
+        Self::validate_timestamp(tx, dag, chrono::Utc::now().timestamp_millis() as u64)?;
+
+        // 5. Must be made for the network this DAG belongs to
+        if let Some(network_id) = dag.network_id() {
+            if tx.data.network_id != network_id {
+                return Err(ValidationError::WrongNetwork {
+                    expected: network_id,
+                    got: tx.data.network_id,
+                });
+            }
+        }
+
+        // 6. Only transfers may name a fee payer
+        if tx.data.fee_payer.is_some() && tx.data.tx_type != TransactionType::Transfer {
+            return Err(ValidationError::InvalidFeePayer);
+        }
+
+        // 7. Only transfers and batches may tip relayers
+        let tips = matches!(tx.data.tx_type, TransactionType::Transfer | TransactionType::Batch);
+        if tx.data.relay_tip > 0 && !tips {
+            return Err(ValidationError::InvalidRelayTip);
+        }
+
+        // 8. Only channel closes carry a channel state
+        if tx.data.channel.is_some() && tx.data.tx_type != TransactionType::ChannelClose {
+            return Err(ValidationError::InvalidChannel("only a close carries a channel state"));
+        }
+
+        // 9. A tip selection proof must hold for the sender and nonce
+        if tx.data.selection_proof.is_some() && selection_output(&tx.data).is_none() {
+            return Err(ValidationError::InvalidSelectionProof);
+        }
+
+        // 10. Only payments may be time-locked
+        if tx.data.valid_after != 0 && !tx.data.tx_type.is_payment() {
+            return Err(ValidationError::InvalidTimeLock);
+        }
+
+        // 11. Only HTLC transactions carry HTLC terms
+        if tx.data.htlc.is_some() && !tx.data.tx_type.is_htlc() {
+            return Err(ValidationError::InvalidHtlc("only HTLC transactions carry HTLC terms"));
+        }
+
+        // 12. Only batches carry outputs
+        if !tx.data.outputs.is_empty() && tx.data.tx_type != TransactionType::Batch {
+            return Err(ValidationError::InvalidBatch("only a batch carries outputs"));
+        }
+
+        // 13. Type-specific validation
         match tx.data.tx_type {
             TransactionType::Genesis => Self::validate_genesis(tx, dag),
             TransactionType::Transfer => Self::validate_transfer(tx, dag),
             TransactionType::RelayReward => Self::validate_relay_reward(tx, dag),
             TransactionType::FounderAllocation => Self::validate_founder_allocation(tx, dag),
+            TransactionType::AliasAnnouncement => Self::validate_alias_announcement(tx, dag),
+            TransactionType::KeyAnnouncement => Self::validate_key_announcement(tx, dag),
+            TransactionType::ChannelOpen => Self::validate_channel_open(tx, dag),
+            TransactionType::ChannelClose => Self::validate_channel_close(tx, dag),
+            // Spent like a transfer once its members have signed
+            TransactionType::Multisig => Self::validate_transfer(tx, dag),
+            TransactionType::HtlcLock => Self::validate_htlc_lock(tx, dag),
+            TransactionType::HtlcClaim | TransactionType::HtlcRefund => {
+                Self::validate_htlc_settlement(tx, dag)
+            }
+            TransactionType::Batch => Self::validate_batch(tx, dag),
         }
     }
 
+    /// Check a transaction's proof of work against the load its parents saw,
+    /// allowing [`WORK_SLACK_BITS`] for transactions its sender hadn't seen
+    ///
+    /// Bits are counted in the transaction's own puzzle.
+    pub fn validate_work(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        let need = dag
+            .required_work(&tx.data.parents)
+            .saturating_sub(WORK_SLACK_BITS)
+            .max(MIN_WORK_BITS);
+        let need = tx.work_puzzle.bits_for(need);
+        let have = tx.work_bits();
+        if have < need {
+            return Err(ValidationError::InsufficientWork { have, need });
+        }
+        Ok(())
+    }
+
+    /// Check a transaction's timestamp against its parents' and the clock
+    /// reading `now_ms`, within the DAG's [`TimestampRules`]
+    ///
+    /// Parents that aren't in the DAG (missing or pruned) don't bound it.
+    pub fn validate_timestamp(tx: &Transaction, dag: &Dag, now_ms: u64) -> Result<(), ValidationError> {
+        let rules = dag.timestamp_rules();
+        let timestamp = tx.data.timestamp;
+        if timestamp > now_ms.saturating_add(rules.max_drift_ms) {
+            return Err(ValidationError::InvalidTimestamp(format!(
+                "{} is more than {} ms ahead of the local clock ({})",
+                timestamp, rules.max_drift_ms, now_ms
+            )));
+        }
+        let newest_parent = tx
+            .data
+            .parents
+            .iter()
+            .filter_map(|parent| dag.get(parent))
+            .map(|vertex| vertex.transaction.data.timestamp)
+            .max();
+        if let Some(newest) = newest_parent {
+            if timestamp < newest.saturating_sub(rules.parent_tolerance_ms) {
+                return Err(ValidationError::InvalidTimestamp(format!(
+                    "{} is more than {} ms before its newest parent ({})",
+                    timestamp, rules.parent_tolerance_ms, newest
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn validate_genesis(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
         // Genesis is only valid if there's no existing genesis
         if dag.genesis_id.is_some() {
             return Err(ValidationError::InvalidId);
         }
         // Genesis must reference zero hashes
-        if !tx.data.parents[0].is_zero() || !tx.data.parents[1].is_zero() {
+        if !tx.data.parents.iter().all(Hash::is_zero) {
             return Err(ValidationError::ParentNotFound);
         }
         Ok(())
     }
 
+    fn validate_batch(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        let outputs = &tx.data.outputs;
+        if !(2..=MAX_BATCH_OUTPUTS).contains(&outputs.len()) {
+            return Err(ValidationError::BatchOutputCount(MAX_BATCH_OUTPUTS));
+        }
+        if tx.data.recipient != tx.data.sender {
+            return Err(ValidationError::InvalidBatch("recipient isn't the sender"));
+        }
+        let mut total = 0u64;
+        for output in outputs {
+            if output.amount == 0 {
+                return Err(ValidationError::ZeroAmount);
+            }
+            if output.recipient == tx.data.sender {
+                return Err(ValidationError::InvalidBatch("pays the sender"));
+            }
+            total = total.checked_add(output.amount).ok_or(ValidationError::Overflow("batch total"))?;
+        }
+        if total != tx.data.amount {
+            return Err(ValidationError::InvalidBatch("amount isn't the outputs' total"));
+        }
+        // Otherwise spent like a transfer of the total
+        Self::validate_transfer(tx, dag)
+    }
+
     fn validate_transfer(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
         // Amount must be > 0
         if tx.data.amount == 0 {
@@ -69,20 +303,72 @@ impl TransactionValidator {
         }
 
         // Amount must not exceed max supply
-        if tx.data.amount > crate::MAX_SUPPLY {
+        if [tx.data.amount, tx.data.fee, tx.data.relay_tip]
+            .iter()
+            .any(|value| *value > crate::MAX_SUPPLY)
+        {
             return Err(ValidationError::ExceedsMaxSupply);
         }
 
         // Parents must exist in DAG
         for parent in &tx.data.parents {
-            if dag.get(parent).is_none() {
+            if !dag.contains(parent) {
                 return Err(ValidationError::ParentNotFound);
             }
         }
 
-        // Check balance
-        let balance = dag.get_balance(&tx.data.sender);
-        let total_needed = tx.data.amount + tx.data.fee;
+        // Nonce must move past the sender's last confirmed transfer, so a
+        // replayed transaction can't be re-included
+        if let Some(last) = dag.last_confirmed_nonce(&tx.data.sender) {
+            if tx.data.nonce <= last {
+                return Err(ValidationError::StaleNonce {
+                    nonce: tx.data.nonce,
+                    last,
+                });
+            }
+        }
+
+        // Reusing a pending nonce replaces the transfers with that nonce the
+        // parents can see, so it has to outbid them; concurrent ones are
+        // settled by the conflict tracker
+        let mut replaced = Vec::new();
+        let same_nonce = dag.conflicts().same_nonce(&tx.data.sender, tx.data.nonce);
+        if !same_nonce.is_empty() {
+            let cone = dag.past_cone(&tx.data.parents);
+            replaced.extend(
+                same_nonce
+                    .iter()
+                    .filter(|id| cone.contains(id))
+                    .filter_map(|id| dag.get(id))
+                    .map(|v| &v.transaction),
+            );
+        }
+        if let Some(highest) = replaced.iter().map(|r| r.data.priority()).max() {
+            if tx.data.priority() <= highest {
+                return Err(ValidationError::ReplacementUnderpriced {
+                    offered: tx.data.priority(),
+                    replaced: highest,
+                });
+            }
+        }
+        // What the replaced transfers spend comes back once they're superseded
+        let refund = |key: &PublicKey| -> Result<u64, ValidationError> {
+            let refund: u128 = replaced
+                .iter()
+                .filter(|r| !dag.conflicts().is_rejected(&r.id))
+                .map(|r| r.data.debit_for(key))
+                .sum();
+            u64::try_from(refund).map_err(|_| ValidationError::Overflow("replacement refund"))
+        };
+
+        // Check balance against what the parents could see; concurrent
+        // overspends are settled by the conflict tracker instead
+        let balance = dag
+            .balance_seen_by(&tx.data.parents, &tx.data.sender)
+            .checked_add(refund(&tx.data.sender)?)
+            .ok_or(ValidationError::Overflow("sender balance"))?;
+        let total_needed = u64::try_from(tx.data.debit_for(&tx.data.sender))
+            .map_err(|_| ValidationError::Overflow("transfer debit"))?;
         if balance < total_needed {
             return Err(ValidationError::InsufficientBalance {
                 have: balance,
@@ -90,6 +376,23 @@ impl TransactionValidator {
             });
         }
 
+        // A sponsor must be able to cover the fee on its own
+        if let Some(payer) = &tx.data.fee_payer {
+            if tx.data.fee == 0 || *payer == tx.data.sender {
+                return Err(ValidationError::InvalidFeePayer);
+            }
+            let payer_balance = dag
+                .balance_seen_by(&tx.data.parents, payer)
+                .checked_add(refund(payer)?)
+                .ok_or(ValidationError::Overflow("fee payer balance"))?;
+            if payer_balance < tx.data.fee {
+                return Err(ValidationError::InsufficientFeePayerBalance {
+                    have: payer_balance,
+                    need: tx.data.fee,
+                });
+            }
+        }
+
         Ok(())
     }
 
@@ -101,17 +404,257 @@ impl TransactionValidator {
 
         // Parents must exist
         for parent in &tx.data.parents {
-            if dag.get(parent).is_none() {
+            if !dag.contains(parent) {
                 return Err(ValidationError::ParentNotFound);
             }
         }
 
-        // Reward amount must be within allowed range
-        let max_reward = crate::BASE_RELAY_REWARD;
+        // The claim must be backed by relay work
+        let proofs = &tx.data.relay_proofs;
+        if proofs.is_empty() || proofs.len() > MAX_RELAY_PROOFS_PER_CLAIM {
+            return Err(ValidationError::RelayProofCount(MAX_RELAY_PROOFS_PER_CLAIM));
+        }
+
+        // Each proof is worth at most one base reward
+        let max_reward = crate::BASE_RELAY_REWARD
+            .checked_mul(proofs.len() as u64)
+            .ok_or(ValidationError::Overflow("relay reward"))?;
         if tx.data.amount > max_reward {
             return Err(ValidationError::InvalidRelayReward);
         }
 
+        // Nor may it mint past the maximum supply
+        if !dag.supply().can_mint(tx.data.amount) {
+            return Err(ValidationError::ExceedsMaxSupply);
+        }
+
+        // Proofs must be the claimer's own, for transactions the claim can
+        // see, and not rewarded before
+        let cone = dag.past_cone(&tx.data.parents);
+        let mut seen = HashSet::new();
+        for proof in proofs {
+            let relayed = proof.transaction_id;
+            let visible = cone.contains(&relayed) || dag.pruned().contains(&relayed);
+            if proof.relayer != tx.data.sender || !visible {
+                return Err(ValidationError::InvalidRelayProof(relayed));
+            }
+            if !seen.insert(relayed) || dag.is_relay_claimed(&proof.relayer, &relayed) {
+                return Err(ValidationError::RelayAlreadyClaimed(relayed));
+            }
+        }
+
+        // Signatures are the costly part: check the sample the claim selects
+        let verdict = RelayProofSampler::default().verify_claim_seeded(&tx.id, &tx.data.sender, proofs);
+        if let SampleVerdict::Voided { invalid_proof, penalty } = verdict {
+            return Err(ValidationError::ForgedRelayClaim { proof: invalid_proof, penalty });
+        }
+
+        Ok(())
+    }
+
+    fn validate_alias_announcement(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        if tx.data.amount != 0 || tx.data.fee != 0 {
+            return Err(ValidationError::AnnouncementWithAmount);
+        }
+
+        // Parents must exist
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // The name must be well-formed and not finalized for another key.
+        // Competing announcements that aren't final yet are all accepted;
+        // the first to become final takes the name.
+        let alias: Alias = tx.data.memo.as_deref().unwrap_or_default().parse()?;
+        dag.aliases().check_claim(&tx.data.sender, &alias)?;
+
+        Ok(())
+    }
+
+    fn validate_key_announcement(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        if tx.data.amount != 0 || tx.data.fee != 0 || tx.data.memo.is_some() {
+            return Err(ValidationError::AnnouncementWithAmount);
+        }
+        if tx.data.recipient != tx.data.sender {
+            return Err(ValidationError::KeyAnnouncementNotSelf);
+        }
+
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // Only keys the DAG hasn't seen need announcing; this also stops
+        // a key from filling the DAG with repeats
+        let address = Address::from_public_key(&tx.data.sender);
+        if dag.find_public_key(&address).is_some() {
+            return Err(ValidationError::KeyAlreadyKnown);
+        }
+
+        Ok(())
+    }
+
+    fn validate_channel_open(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        if tx.data.amount == 0 {
+            return Err(ValidationError::ZeroAmount);
+        }
+        if tx.data.amount > crate::MAX_SUPPLY || tx.data.fee > crate::MAX_SUPPLY {
+            return Err(ValidationError::ExceedsMaxSupply);
+        }
+        if tx.data.recipient == tx.data.sender {
+            return Err(ValidationError::InvalidChannel("a channel needs two parties"));
+        }
+
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // The deposit is spent like a transfer; concurrent overspends are
+        // settled by the conflict tracker
+        let balance = dag.balance_seen_by(&tx.data.parents, &tx.data.sender);
+        let needed = u64::try_from(tx.data.debit_for(&tx.data.sender))
+            .map_err(|_| ValidationError::Overflow("channel deposit"))?;
+        if balance < needed {
+            return Err(ValidationError::InsufficientBalance { have: balance, need: needed });
+        }
+
+        Ok(())
+    }
+
+    fn validate_channel_close(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        if tx.data.amount != 0 || tx.data.fee != 0 {
+            return Err(ValidationError::InvalidChannel("a close moves no funds of its own"));
+        }
+
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // The channel must be open in final history, and the closer a party
+        // to it closing towards the other
+        let update = tx.data.channel.as_ref().ok_or(ValidationError::InvalidChannel("no channel state"))?;
+        let channel = dag
+            .channels()
+            .get(&update.state.channel_id)
+            .ok_or(ValidationError::InvalidChannel("channel isn't open in final history"))?;
+        let counterparty = channel
+            .counterparty(&tx.data.sender)
+            .ok_or(ValidationError::InvalidChannel("sender isn't a party to the channel"))?;
+        if tx.data.recipient != *counterparty {
+            return Err(ValidationError::InvalidChannel("recipient isn't the other party"));
+        }
+        match channel.status {
+            ChannelStatus::Settled { .. } => return Err(ValidationError::InvalidChannel("channel is settled")),
+            ChannelStatus::Closing { .. } if tx.data.sender == channel.funder => {
+                return Err(ValidationError::InvalidChannel("funder already closed the channel"))
+            }
+            _ => {}
+        }
+
+        // Settling on a state needs the other party's word for it
+        if update.state.paid > channel.deposit {
+            return Err(ValidationError::InvalidChannel("state pays more than the deposit"));
+        }
+        if !update.verify(counterparty) {
+            return Err(ValidationError::InvalidChannel("state isn't signed by the other party"));
+        }
+
+        Ok(())
+    }
+
+    fn validate_htlc_lock(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        let Some(HtlcTerms::Lock { timeout, .. }) = tx.data.htlc else {
+            return Err(ValidationError::InvalidHtlc("a lock needs a hash lock and timeout"));
+        };
+        if tx.data.amount == 0 {
+            return Err(ValidationError::ZeroAmount);
+        }
+        if tx.data.amount > crate::MAX_SUPPLY || tx.data.fee > crate::MAX_SUPPLY {
+            return Err(ValidationError::ExceedsMaxSupply);
+        }
+        if tx.data.recipient == tx.data.sender {
+            return Err(ValidationError::InvalidHtlc("an HTLC needs two parties"));
+        }
+
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // A lock its locker could refund at once protects nobody
+        let (depth, _) = dag.frontier_of(&tx.data.parents);
+        if timeout == 0 || lock_passed(timeout, depth, tx.data.timestamp) {
+            return Err(ValidationError::InvalidHtlc("timeout has already passed"));
+        }
+
+        // The amount is spent like a transfer; concurrent overspends are
+        // settled by the conflict tracker
+        let balance = dag.balance_seen_by(&tx.data.parents, &tx.data.sender);
+        let needed = u64::try_from(tx.data.debit_for(&tx.data.sender))
+            .map_err(|_| ValidationError::Overflow("HTLC amount"))?;
+        if balance < needed {
+            return Err(ValidationError::InsufficientBalance { have: balance, need: needed });
+        }
+
+        Ok(())
+    }
+
+    /// Check a claim or refund against the HTLC it settles
+    fn validate_htlc_settlement(tx: &Transaction, dag: &Dag) -> Result<(), ValidationError> {
+        if tx.data.amount != 0 || tx.data.fee != 0 {
+            return Err(ValidationError::InvalidHtlc("a claim or refund moves no funds of its own"));
+        }
+
+        for parent in &tx.data.parents {
+            if !dag.contains(parent) {
+                return Err(ValidationError::ParentNotFound);
+            }
+        }
+
+        // The HTLC must be open in final history. Claims and refunds that
+        // aren't final yet are all accepted; the first to become final
+        // settles it.
+        let lock = match (&tx.data.tx_type, &tx.data.htlc) {
+            (TransactionType::HtlcClaim, Some(HtlcTerms::Claim { lock, .. })) => lock,
+            (TransactionType::HtlcRefund, Some(HtlcTerms::Refund { lock })) => lock,
+            _ => return Err(ValidationError::InvalidHtlc("terms don't match the transaction type")),
+        };
+        let htlc = dag
+            .htlcs()
+            .get(lock)
+            .ok_or(ValidationError::InvalidHtlc("lock isn't in final history"))?;
+        if !htlc.is_open() {
+            return Err(ValidationError::InvalidHtlc("HTLC is already settled"));
+        }
+
+        match &tx.data.htlc {
+            Some(HtlcTerms::Claim { preimage, .. }) => {
+                if tx.data.sender != htlc.claimer || tx.data.recipient != htlc.locker {
+                    return Err(ValidationError::InvalidHtlc("only the recipient can claim"));
+                }
+                if preimage.hash_lock() != htlc.hash_lock {
+                    return Err(ValidationError::InvalidHtlc("preimage doesn't match the hash lock"));
+                }
+            }
+            _ => {
+                if tx.data.sender != htlc.locker || tx.data.recipient != htlc.claimer {
+                    return Err(ValidationError::InvalidHtlc("only the sender can refund"));
+                }
+                let (depth, _) = dag.frontier_of(&tx.data.parents);
+                if !htlc.expired_at(depth, tx.data.timestamp) {
+                    return Err(ValidationError::InvalidHtlc("timeout hasn't passed"));
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -122,29 +665,25 @@ impl TransactionValidator {
         }
 
         // Verify the recipient is the founder
-        let founder_key_bytes = hex::decode(crate::FOUNDER_PUBLIC_KEY)
-            .map_err(|_| ValidationError::InvalidFounderAllocation)?;
-        let mut key_arr = [0u8; 32];
-        key_arr.copy_from_slice(&founder_key_bytes);
-        let expected_founder = crate::crypto::PublicKey::from_bytes(key_arr);
-        if tx.data.recipient != expected_founder {
+        if tx.data.recipient != crate::founder_public_key() {
             return Err(ValidationError::InvalidFounderAllocation);
         }
 
-        // Parents must exist
-        for parent in &tx.data.parents {
-            if dag.get(parent).is_none() {
-                return Err(ValidationError::ParentNotFound);
-            }
+        // Every parent must be the genesis transaction
+        let genesis_id = dag.genesis_id.ok_or(ValidationError::ParentNotFound)?;
+        if tx.data.parents.iter().any(|parent| *parent != genesis_id) {
+            return Err(ValidationError::InvalidFounderAllocation);
+        }
+
+        // Signed by the genesis key (the signature was checked against the sender)
+        let genesis = dag.get(&genesis_id).ok_or(ValidationError::ParentNotFound)?;
+        if tx.data.sender != genesis.transaction.data.sender {
+            return Err(ValidationError::InvalidFounderAllocation);
         }
 
         // Only one founder allocation ever
-        for id in dag.transaction_ids() {
-            if let Some(vertex) = dag.get(&id) {
-                if vertex.transaction.data.tx_type == TransactionType::FounderAllocation {
-                    return Err(ValidationError::InvalidFounderAllocation);
-                }
-            }
+        if dag.founder_allocation_id.is_some() {
+            return Err(ValidationError::InvalidFounderAllocation);
         }
 
         Ok(())
@@ -154,8 +693,11 @@ impl TransactionValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::consensus::relay::RelayProof;
     use crate::crypto::keys::KeyPair;
     use crate::dag::vertex::DagVertex;
+    use crate::dag::work::{self, WorkPuzzle};
+    use crate::wallet::alias::AliasError;
 
     fn create_dag_with_balance() -> (Dag, KeyPair) {
         let kp = KeyPair::generate();
@@ -207,6 +749,144 @@ mod tests {
         assert!(TransactionValidator::validate(&tx, &dag).is_ok());
     }
 
+    #[test]
+    fn test_validate_stale_nonce() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+
+        let tx = Transaction::transfer(&sender, recipient.public_key.clone(), 100, dag.select_parents(), 5);
+        let mut last = tx.id;
+        dag.insert(DagVertex::new(tx, 2)).unwrap();
+
+        // Not final yet, so the nonce isn't confirmed and can be replaced
+        let early = Transaction::transfer_with_tip(&sender, recipient.public_key.clone(), 200, None, 1, dag.select_parents(), 5);
+        assert!(TransactionValidator::validate(&early, &dag).is_ok());
+
+        for i in 0..crate::FINALITY_THRESHOLD {
+            let approver = Transaction::relay_reward(&KeyPair::generate(), 1, [last, last], 100 + i);
+            last = approver.id;
+            dag.insert(DagVertex::new(approver, 3 + i)).unwrap();
+        }
+        assert_eq!(dag.last_confirmed_nonce(&sender.public_key), Some(5));
+
+        let replay = Transaction::transfer(&sender, recipient.public_key.clone(), 200, dag.select_parents(), 5);
+        assert!(matches!(
+            TransactionValidator::validate(&replay, &dag),
+            Err(ValidationError::StaleNonce { nonce: 5, last: 5 })
+        ));
+
+        let next = Transaction::transfer(&sender, recipient.public_key, 200, dag.select_parents(), 6);
+        assert!(TransactionValidator::validate(&next, &dag).is_ok());
+    }
+
+    #[test]
+    fn test_validate_replacement() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+
+        // Spends everything, so only the refund lets a replacement through
+        let original = Transaction::transfer_with_tip(
+            &sender,
+            recipient.public_key.clone(),
+            999_990,
+            None,
+            10,
+            dag.select_parents(),
+            2,
+        );
+        dag.insert(DagVertex::new(original.clone(), 2)).unwrap();
+
+        let same_tip = Transaction::replacement(&original, &sender, Some("fixed".into()), 10, dag.select_parents());
+        assert!(matches!(
+            TransactionValidator::validate(&same_tip, &dag),
+            Err(ValidationError::ReplacementUnderpriced { offered: 10, replaced: 10 })
+        ));
+
+        let bumped = Transaction::replacement(&original, &sender, Some("fixed".into()), 11, dag.select_parents());
+        assert!(matches!(
+            TransactionValidator::validate(&bumped, &dag),
+            Err(ValidationError::InsufficientBalance { have: 1_000_000, need: 1_000_001 })
+        ));
+
+        let mut smaller = original.data.clone();
+        smaller.amount = 999_980;
+        smaller.relay_tip = 20;
+        smaller.parents = dag.select_parents();
+        let smaller = Transaction::new(smaller, &sender);
+        assert!(TransactionValidator::validate(&smaller, &dag).is_ok());
+    }
+
+    #[test]
+    fn test_validate_alias_announcement() {
+        let (mut dag, alice) = create_dag_with_balance();
+        let mallory = KeyPair::generate();
+        let alias: Alias = "alice".parse().unwrap();
+
+        let tx = Transaction::alias_announcement(&alice, &alias, alice.public_key.clone(), dag.select_parents(), 0);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+        let mut last = tx.id;
+        dag.insert(DagVertex::new(tx, 2)).unwrap();
+
+        // Competing claims are fine until one is final
+        let squat = Transaction::alias_announcement(&mallory, &alias, mallory.public_key.clone(), dag.select_parents(), 0);
+        assert!(TransactionValidator::validate(&squat, &dag).is_ok());
+
+        for i in 0..crate::FINALITY_THRESHOLD {
+            let approver = Transaction::relay_reward(&KeyPair::generate(), 1, [last, last], 100 + i);
+            last = approver.id;
+            dag.insert(DagVertex::new(approver, 3 + i)).unwrap();
+        }
+        assert_eq!(dag.aliases().resolve(&alias).unwrap().owner, alice.public_key);
+
+        let squat = Transaction::alias_announcement(&mallory, &alias, mallory.public_key.clone(), dag.select_parents(), 0);
+        assert!(matches!(
+            TransactionValidator::validate(&squat, &dag),
+            Err(ValidationError::InvalidAlias(AliasError::Taken(_)))
+        ));
+
+        let mut bad = Transaction::alias_announcement(&mallory, &alias, mallory.public_key.clone(), dag.select_parents(), 0);
+        bad.data.memo = Some("no spaces".to_string());
+        let bad = Transaction::new(bad.data, &mallory);
+        assert!(matches!(
+            TransactionValidator::validate(&bad, &dag),
+            Err(ValidationError::InvalidAlias(AliasError::InvalidCharacter(' ')))
+        ));
+    }
+
+    #[test]
+    fn test_validate_key_announcement() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let newcomer = KeyPair::generate();
+        let address = Address::from_public_key(&newcomer.public_key);
+        assert!(dag.find_public_key(&address).is_none());
+
+        let tx = Transaction::key_announcement(&newcomer, dag.select_parents(), 0);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+        dag.insert(DagVertex::new(tx, 2)).unwrap();
+        assert_eq!(dag.find_public_key(&address), Some(newcomer.public_key.clone()));
+
+        let again = Transaction::key_announcement(&newcomer, dag.select_parents(), 1);
+        assert!(matches!(
+            TransactionValidator::validate(&again, &dag),
+            Err(ValidationError::KeyAlreadyKnown)
+        ));
+        let known = Transaction::key_announcement(&sender, dag.select_parents(), 1);
+        assert!(matches!(
+            TransactionValidator::validate(&known, &dag),
+            Err(ValidationError::KeyAlreadyKnown)
+        ));
+
+        // Signed properly, but announcing to someone else
+        let stranger = KeyPair::generate();
+        let mut data = Transaction::key_announcement(&stranger, dag.select_parents(), 0).data;
+        data.recipient = newcomer.public_key.clone();
+        let misdirected = Transaction::new(data, &stranger);
+        assert!(matches!(
+            TransactionValidator::validate(&misdirected, &dag),
+            Err(ValidationError::KeyAnnouncementNotSelf)
+        ));
+    }
+
     #[test]
     fn test_validate_insufficient_balance() {
         let (dag, sender) = create_dag_with_balance();
@@ -229,13 +909,175 @@ mod tests {
 
     #[test]
     fn test_validate_relay_reward() {
+        let (mut dag, kp) = create_dag_with_balance();
+        let parents = dag.select_parents();
+        let proofs = vec![RelayProof::new(&kp, parents[0], 1)];
+
+        // Unbacked claims are rejected
+        let tx = Transaction::relay_reward(&kp, 500_000, parents.clone(), 3);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::RelayProofCount(_))
+        ));
+
+        let tx = Transaction::relay_reward_with_proofs(&kp, 500_000, proofs.clone(), parents, 3);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+        dag.insert(DagVertex::new(tx.clone(), 2)).unwrap();
+
+        // The same relay can't be claimed twice
+        let again = Transaction::relay_reward_with_proofs(&kp, 500_000, proofs, [tx.id, tx.id], 4);
+        assert!(matches!(
+            TransactionValidator::validate(&again, &dag),
+            Err(ValidationError::RelayAlreadyClaimed(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_relay_reward_samples_signatures() {
+        let (mut dag, kp) = create_dag_with_balance();
+        let mut relayed = Vec::new();
+        for nonce in 2..42 {
+            let parents = dag.select_parents();
+            let tx = Transaction::relay_reward(&kp, 1, parents, nonce);
+            relayed.push(tx.id);
+            dag.insert(DagVertex::new(tx, nonce)).unwrap();
+        }
+        let parents = dag.select_parents();
+        let mut proofs: Vec<_> = relayed.iter().map(|id| RelayProof::new(&kp, *id, 1)).collect();
+
+        // One forged proof is caught only by the claims whose draw includes it
+        proofs[0].hop_count = 42;
+        let outcomes: Vec<_> = (100..130)
+            .map(|nonce| {
+                let proofs = proofs.clone();
+                let tx = Transaction::relay_reward_with_proofs(&kp, 1, proofs, parents.clone(), nonce);
+                TransactionValidator::validate(&tx, &dag)
+            })
+            .collect();
+        assert!(outcomes.iter().any(|outcome| outcome.is_ok()));
+        assert!(outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, Err(ValidationError::ForgedRelayClaim { penalty: 200, .. }))));
+
+        // Forging them all can't dodge the draw
+        for proof in &mut proofs {
+            proof.hop_count = 42;
+        }
+        let tx = Transaction::relay_reward_with_proofs(&kp, 1, proofs, parents, 100);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::ForgedRelayClaim { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_relay_reward_rejects_bad_proofs() {
         let (dag, kp) = create_dag_with_balance();
         let parents = dag.select_parents();
+        let other = KeyPair::generate();
 
-        let tx = Transaction::relay_reward(&kp, 500_000, parents, 3);
+        // Someone else's relay work
+        let proofs = vec![RelayProof::new(&other, parents[0], 1)];
+        let tx = Transaction::relay_reward_with_proofs(&kp, 1, proofs, parents.clone(), 3);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InvalidRelayProof(_))
+        ));
+
+        // A transaction that isn't in the DAG
+        let unknown = Transaction::genesis(&other).id;
+        let proofs = vec![RelayProof::new(&kp, unknown, 1)];
+        let tx = Transaction::relay_reward_with_proofs(&kp, 1, proofs, parents.clone(), 3);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InvalidRelayProof(_))
+        ));
+
+        // More than one base reward per proof
+        let proofs = vec![RelayProof::new(&kp, parents[0], 1)];
+        let tx = Transaction::relay_reward_with_proofs(&kp, crate::BASE_RELAY_REWARD + 1, proofs, parents, 3);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InvalidRelayReward)
+        ));
+    }
+
+    #[test]
+    fn test_validate_relay_reward_within_max_supply() {
+        let (mut dag, kp) = create_dag_with_balance();
+        // Mint all but 100 units
+        let parents = dag.select_parents();
+        let minted = dag.supply().remaining() - 100;
+        let bulk = Transaction::relay_reward(&KeyPair::generate(), minted, parents, 2);
+        dag.insert(DagVertex::new(bulk, 2)).unwrap();
+        assert_eq!(dag.supply().remaining(), 100);
+
+        let parents = dag.select_parents();
+        let proofs = vec![RelayProof::new(&kp, parents[0], 1)];
+        let tx = Transaction::relay_reward_with_proofs(&kp, 101, proofs.clone(), parents.clone(), 3);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::ExceedsMaxSupply)
+        ));
+        let tx = Transaction::relay_reward_with_proofs(&kp, 100, proofs, parents, 3);
         assert!(TransactionValidator::validate(&tx, &dag).is_ok());
     }
 
+    #[test]
+    fn test_validate_relay_tip_only_on_transfers() {
+        let (dag, kp) = create_dag_with_balance();
+        let parents = dag.select_parents();
+        let recipient = KeyPair::generate().public_key;
+
+        let tipped =
+            Transaction::transfer_with_tip(&kp, recipient.clone(), 100, None, 10, parents.clone(), 2);
+        assert!(TransactionValidator::validate(&tipped, &dag).is_ok());
+
+        // The tip counts against the sender's balance
+        let too_much = Transaction::transfer_with_tip(&kp, recipient, 999_995, None, 10, parents.clone(), 2);
+        assert!(matches!(
+            TransactionValidator::validate(&too_much, &dag),
+            Err(ValidationError::InsufficientBalance { .. })
+        ));
+
+        let announcer = KeyPair::generate();
+        let mut data = Transaction::key_announcement(&announcer, parents, 2).data;
+        data.relay_tip = 10;
+        let announcement = Transaction::new(data, &announcer);
+        assert!(matches!(
+            TransactionValidator::validate(&announcement, &dag),
+            Err(ValidationError::InvalidRelayTip)
+        ));
+    }
+
+    #[test]
+    fn test_validate_time_lock() {
+        let (mut dag, kp) = create_dag_with_balance();
+        let parents = dag.select_parents();
+        let bob = KeyPair::generate();
+
+        let locked = Transaction::transfer(&kp, bob.public_key.clone(), 1_000, parents.clone(), 2)
+            .with_valid_after(10, &kp);
+        assert!(TransactionValidator::validate(&locked, &dag).is_ok());
+        let locked_id = locked.id;
+        dag.insert(DagVertex::new(locked, 2)).unwrap();
+
+        // The recipient can't spend it before the lock passes
+        let carol = KeyPair::generate().public_key;
+        let early = Transaction::transfer(&bob, carol, 500, [locked_id, locked_id], 1);
+        assert!(matches!(
+            TransactionValidator::validate(&early, &dag),
+            Err(ValidationError::InsufficientBalance { have: 0, .. })
+        ));
+
+        let announcer = KeyPair::generate();
+        let announcement = Transaction::key_announcement(&announcer, parents, 2).with_valid_after(10, &announcer);
+        assert!(matches!(
+            TransactionValidator::validate(&announcement, &dag),
+            Err(ValidationError::InvalidTimeLock)
+        ));
+    }
+
     #[test]
     fn test_validate_tampered_transaction() {
         let (dag, sender) = create_dag_with_balance();
@@ -252,4 +1094,431 @@ mod tests {
         tx.data.amount = 999_999; // Tamper
         assert!(TransactionValidator::validate(&tx, &dag).is_err());
     }
+
+    #[test]
+    fn test_validate_rejects_other_networks() {
+        let (dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let parents = dag.select_parents();
+        assert_eq!(dag.network_id(), Some(crate::MAINNET_ID));
+
+        let tx = Transaction::transfer(&sender, recipient.public_key, 100, parents, 2);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        // The same payment signed for testnet can't be replayed here
+        let replayed = tx.for_network(crate::TESTNET_ID, &sender);
+        assert!(replayed.verify_signature());
+        assert!(matches!(
+            TransactionValidator::validate(&replayed, &dag),
+            Err(ValidationError::WrongNetwork { expected: crate::MAINNET_ID, got: crate::TESTNET_ID })
+        ));
+    }
+
+    #[test]
+    fn test_validate_parent_count() {
+        // Mainnet takes exactly the default count
+        let (dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate().public_key;
+        let wide = Transaction::transfer(&sender, recipient.clone(), 100, dag.select_deepest_parents(3), 2);
+        assert!(matches!(
+            TransactionValidator::validate(&wide, &dag),
+            Err(ValidationError::ParentCount { count: 3, min: 2, max: 2, .. })
+        ));
+
+        // A development network takes wider parent sets, up to the maximum
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp).for_network(crate::DEVNET_ID, &kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        for nonce in 1..=3 {
+            let reward = Transaction::relay_reward(&kp, 1_000, [genesis_id, genesis_id], nonce)
+                .for_network(crate::DEVNET_ID, &kp);
+            dag.insert(DagVertex::new(reward, 1)).unwrap();
+        }
+        for count in [2, 3, crate::MAX_PARENT_COUNT] {
+            let tx = Transaction::transfer(&kp, recipient.clone(), 100, dag.select_deepest_parents(count), 4)
+                .for_network(crate::DEVNET_ID, &kp);
+            assert!(TransactionValidator::validate(&tx, &dag).is_ok(), "{} parents", count);
+        }
+        for count in [1, crate::MAX_PARENT_COUNT + 1] {
+            let tx = Transaction::transfer(&kp, recipient.clone(), 100, dag.select_deepest_parents(count), 4)
+                .for_network(crate::DEVNET_ID, &kp);
+            assert!(matches!(
+                TransactionValidator::validate(&tx, &dag),
+                Err(ValidationError::ParentCount { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_selection_proof() {
+        let (dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let parents = dag.select_parents();
+        let tx = Transaction::transfer(&sender, recipient.public_key, 100, parents, 2);
+
+        let (proof, _) = crate::consensus::prove_selection(&sender, crate::MAINNET_ID, 2);
+        let proven = tx.clone().with_selection_proof(proof, &sender);
+        assert!(TransactionValidator::validate(&proven, &dag).is_ok());
+
+        // A proof for another nonce doesn't hold for this one
+        let (stale, _) = crate::consensus::prove_selection(&sender, crate::MAINNET_ID, 1);
+        let forged = tx.with_selection_proof(stale, &sender);
+        assert!(forged.verify_signature());
+        assert!(matches!(
+            TransactionValidator::validate(&forged, &dag),
+            Err(ValidationError::InvalidSelectionProof)
+        ));
+    }
+
+    #[test]
+    fn test_validate_work() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let mut tx = Transaction::transfer(&sender, recipient.public_key.clone(), 100, dag.select_parents(), 2);
+        assert!(tx.work_bits() >= MIN_WORK_BITS);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        // Without the work it is turned away, and a cached success for the
+        // same ID doesn't let it through
+        let mut cache = crate::dag::ValidationCache::default();
+        assert!(cache.validate(&tx, &dag, 0).is_ok());
+        let worked = tx.work_nonce;
+        tx.work_nonce = (0..).find(|n| work::work_bits(&tx.id, *n) < MIN_WORK_BITS).unwrap();
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InsufficientWork { need: MIN_WORK_BITS, .. })
+        ));
+        assert!(cache.validate(&tx, &dag, 0).is_err());
+        tx.work_nonce = worked;
+
+        // A busy network asks for more, measured up to the parents
+        let busy = 4 * work::LOAD_STEP;
+        for i in 0..busy {
+            let nonce = 3 + i as u64;
+            let filler = Transaction::transfer(&sender, recipient.public_key.clone(), 1, dag.select_parents(), nonce);
+            dag.insert(DagVertex::new(filler, nonce)).unwrap();
+        }
+        let parents = dag.select_parents();
+        assert_eq!(dag.required_work(&parents), MIN_WORK_BITS + 2);
+        let tx = Transaction::transfer(&sender, recipient.public_key, 100, parents, 3 + busy as u64);
+        let need = MIN_WORK_BITS + 2 - work::WORK_SLACK_BITS;
+        if tx.work_bits() < need {
+            assert!(matches!(
+                TransactionValidator::validate(&tx, &dag),
+                Err(ValidationError::InsufficientWork { need: n, .. }) if n == need
+            ));
+        }
+        let tx = tx.with_work(need);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+    }
+
+    #[test]
+    fn test_validate_memory_hard_work() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let tx = Transaction::transfer(&sender, recipient.public_key.clone(), 100, dag.select_parents(), 2);
+        let mut tx = tx.with_puzzle(WorkPuzzle::Argon2, MIN_WORK_BITS);
+        // The BLAKE3 work it was made with already suffices, so none is redone
+        assert_eq!(tx.work_puzzle, WorkPuzzle::Blake3);
+
+        // Switching puzzles needs the other puzzle's work
+        tx.work_puzzle = WorkPuzzle::Argon2;
+        let need = WorkPuzzle::Argon2.bits_for(MIN_WORK_BITS);
+        tx.work_nonce = (0..).find(|n| work::memory_hard_work_bits(&tx.id, *n) < need).unwrap();
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InsufficientWork { need: n, .. }) if n == need
+        ));
+        let tx = tx.with_work(MIN_WORK_BITS);
+        assert_eq!(tx.work_puzzle, WorkPuzzle::Argon2);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        // The memory-hard puzzle asks for more under load too
+        let busy = 4 * work::LOAD_STEP;
+        for i in 0..busy {
+            let nonce = 3 + i as u64;
+            let parents = dag.select_parents();
+            let filler = Transaction::transfer(&sender, recipient.public_key.clone(), 1, parents, nonce);
+            dag.insert(DagVertex::new(filler, nonce)).unwrap();
+        }
+        let parents = dag.select_parents();
+        let need = WorkPuzzle::Argon2.bits_for(MIN_WORK_BITS + 2 - work::WORK_SLACK_BITS);
+        let mut tx = Transaction::transfer(&sender, recipient.public_key, 100, parents, 3 + busy as u64);
+        tx.work_puzzle = WorkPuzzle::Argon2;
+        let mut tx = tx.with_work(MIN_WORK_BITS + 2);
+        assert!(tx.work_bits() >= need);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+        tx.work_nonce = (0..).find(|n| work::memory_hard_work_bits(&tx.id, *n) < need).unwrap();
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InsufficientWork { need: n, .. }) if n == need
+        ));
+    }
+
+    #[test]
+    fn test_validate_timestamp() {
+        let (mut dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let parents = dag.select_parents();
+        let tx = Transaction::transfer(&sender, recipient.public_key, 100, parents.clone(), 2);
+        let newest = dag.get(&parents[0]).unwrap().transaction.data.timestamp;
+        let dated = |timestamp| {
+            let mut data = tx.data.clone();
+            data.timestamp = timestamp;
+            Transaction::new(data, &sender)
+        };
+        let rules = TimestampRules::default();
+        let now = newest + 1_000;
+
+        assert!(TransactionValidator::validate_timestamp(&dated(now), &dag, now).is_ok());
+        // A sender's clock may be behind the parents' or ahead of ours, a little
+        let behind = dated(newest - rules.parent_tolerance_ms);
+        assert!(TransactionValidator::validate_timestamp(&behind, &dag, now).is_ok());
+        let ahead = dated(now + rules.max_drift_ms);
+        assert!(TransactionValidator::validate_timestamp(&ahead, &dag, now).is_ok());
+
+        // But not by more
+        let before_parents = dated(newest - rules.parent_tolerance_ms - 1);
+        assert!(matches!(
+            TransactionValidator::validate_timestamp(&before_parents, &dag, now),
+            Err(ValidationError::InvalidTimestamp(_))
+        ));
+        let future = dated(now + rules.max_drift_ms + 1);
+        assert!(matches!(
+            TransactionValidator::validate_timestamp(&future, &dag, now),
+            Err(ValidationError::InvalidTimestamp(_))
+        ));
+        // A transaction from an hour ahead is turned away by full validation
+        assert!(matches!(
+            TransactionValidator::validate(&dated(newest + 60 * 60 * 1_000), &dag),
+            Err(ValidationError::InvalidTimestamp(_))
+        ));
+
+        // The tolerance is the DAG's to set
+        dag.set_timestamp_rules(TimestampRules { parent_tolerance_ms: 0, ..rules });
+        assert!(TransactionValidator::validate_timestamp(&dated(newest - 1), &dag, now).is_err());
+        assert!(TransactionValidator::validate_timestamp(&dated(newest), &dag, now).is_ok());
+    }
+
+    #[test]
+    fn test_validate_sponsored_transfer() {
+        let (mut dag, sponsor) = create_dag_with_balance();
+        let user = KeyPair::generate();
+        let recipient = KeyPair::generate();
+
+        // Give the user funds for the amount but nothing for the fee
+        let funding = Transaction::transfer(&sponsor, user.public_key.clone(), 1_000, dag.select_parents(), 2);
+        dag.insert(DagVertex::new(funding, 2)).unwrap();
+
+        let mut tx = Transaction::sponsored_transfer(
+            &user,
+            recipient.public_key.clone(),
+            1_000,
+            50,
+            sponsor.public_key.clone(),
+            dag.select_parents(),
+            3,
+        );
+        tx.sign_as_fee_payer(&sponsor);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        dag.insert(DagVertex::new(tx, 3)).unwrap();
+        assert_eq!(dag.get_balance(&user.public_key), 0);
+        assert_eq!(dag.get_balance(&sponsor.public_key), 1_000_000 - 1_000 - 50);
+    }
+
+    #[test]
+    fn test_validate_unfunded_sponsor() {
+        let (dag, sender) = create_dag_with_balance();
+        let sponsor = KeyPair::generate();
+
+        let mut tx = Transaction::sponsored_transfer(
+            &sender,
+            KeyPair::generate().public_key,
+            100,
+            50,
+            sponsor.public_key.clone(),
+            dag.select_parents(),
+            2,
+        );
+        // Missing co-signature
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InvalidSignature)
+        ));
+
+        tx.sign_as_fee_payer(&sponsor);
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::InsufficientFeePayerBalance { .. })
+        ));
+    }
+
+    fn genesis_dag() -> (Dag, KeyPair, crate::crypto::Hash) {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let genesis_id = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        (dag, kp, genesis_id)
+    }
+
+    #[test]
+    fn test_validate_founder_allocation() {
+        let (mut dag, kp, genesis_id) = genesis_dag();
+        let tx = Transaction::founder_allocation(&kp, crate::founder_public_key(), genesis_id);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        // At most once
+        dag.insert(DagVertex::new(tx, 1)).unwrap();
+        assert_eq!(dag.get_balance(&crate::founder_public_key()), crate::FOUNDER_ALLOCATION);
+        let mut data = Transaction::founder_allocation(&kp, crate::founder_public_key(), genesis_id).data;
+        data.nonce = 2;
+        let again = Transaction::new(data, &kp);
+        assert!(matches!(
+            TransactionValidator::validate(&again, &dag),
+            Err(ValidationError::InvalidFounderAllocation)
+        ));
+    }
+
+    #[test]
+    fn test_validate_transfer_extreme_values() {
+        let (dag, sender) = create_dag_with_balance();
+        let recipient = KeyPair::generate();
+        let balance = dag.get_balance(&sender.public_key);
+        let parents = dag.select_parents();
+        let extremes = [0, 1, balance, crate::MAX_SUPPLY, crate::MAX_SUPPLY + 1, u64::MAX - 1, u64::MAX];
+
+        for amount in extremes {
+            for fee in extremes {
+                for relay_tip in extremes {
+                    let recipient = recipient.public_key.clone();
+                    let mut data = Transaction::transfer(&sender, recipient, 1, parents.clone(), 2).data;
+                    data.amount = amount;
+                    data.fee = fee;
+                    data.relay_tip = relay_tip;
+                    let tx = Transaction::new(data, &sender);
+                    let debit = amount as u128 + fee as u128 + relay_tip as u128;
+
+                    match TransactionValidator::validate(&tx, &dag) {
+                        Ok(()) => assert!(amount > 0 && debit <= balance as u128),
+                        Err(ValidationError::ZeroAmount) => assert_eq!(amount, 0),
+                        Err(ValidationError::ExceedsMaxSupply) => {
+                            assert!(amount.max(fee).max(relay_tip) > crate::MAX_SUPPLY)
+                        }
+                        Err(ValidationError::InsufficientBalance { have, need }) => {
+                            assert_eq!(have, balance);
+                            assert_eq!(need as u128, debit);
+                        }
+                        Err(e) => panic!("amount {} fee {} tip {}: {}", amount, fee, relay_tip, e),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_founder_allocation_rejects_forgeries() {
+        let (mut dag, kp, genesis_id) = genesis_dag();
+
+        // Not signed by the genesis key
+        let other = KeyPair::generate();
+        let tx = Transaction::founder_allocation(&other, crate::founder_public_key(), genesis_id);
+        assert!(TransactionValidator::validate(&tx, &dag).is_err());
+
+        // Wrong recipient
+        let tx = Transaction::founder_allocation(&kp, other.public_key.clone(), genesis_id);
+        assert!(TransactionValidator::validate(&tx, &dag).is_err());
+
+        // Wrong amount
+        let mut data = Transaction::founder_allocation(&kp, crate::founder_public_key(), genesis_id).data;
+        data.amount += 1;
+        assert!(TransactionValidator::validate(&Transaction::new(data, &kp), &dag).is_err());
+
+        // Doesn't reference the genesis transaction
+        let reward = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1);
+        let reward_id = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+        let mut data = Transaction::founder_allocation(&kp, crate::founder_public_key(), genesis_id).data;
+        data.parents = vec![reward_id, genesis_id];
+        assert!(TransactionValidator::validate(&Transaction::new(data, &kp), &dag).is_err());
+    }
+
+    #[test]
+    fn test_validate_multisig_spend() {
+        use crate::crypto::multisig::{MultisigError, MultisigPolicy};
+
+        let (mut dag, kp) = create_dag_with_balance();
+        let members: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate()).collect();
+        let policy = MultisigPolicy::new(2, members.iter().map(|m| m.public_key.clone()).collect()).unwrap();
+        let funding = Transaction::transfer(&kp, policy.account(), 10_000, dag.select_parents(), 2);
+        dag.insert(DagVertex::new(funding, 2)).unwrap();
+        let payee = KeyPair::generate().public_key;
+
+        let parents = dag.select_parents();
+        let mut tx =
+            Transaction::multisig_spend(policy.clone(), payee.clone(), 4_000, None, parents.clone(), 1);
+        tx.sign_as_multisig_member(&members[0]).unwrap();
+        assert!(matches!(
+            TransactionValidator::validate(&tx, &dag),
+            Err(ValidationError::Multisig(MultisigError::NotEnoughSignatures { have: 1, need: 2 }))
+        ));
+        tx.sign_as_multisig_member(&members[2]).unwrap();
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+
+        // The account's balance bounds it like any sender's
+        let mut greedy = Transaction::multisig_spend(policy, payee.clone(), 20_000, None, parents, 1);
+        greedy.sign_as_multisig_member(&members[0]).unwrap();
+        greedy.sign_as_multisig_member(&members[1]).unwrap();
+        assert!(matches!(
+            TransactionValidator::validate(&greedy, &dag),
+            Err(ValidationError::InsufficientBalance { have: 10_000, need: 20_000 })
+        ));
+
+        // Only a multisig spend carries a witness
+        let mut transfer = Transaction::transfer(&kp, payee, 100, dag.select_parents(), 3);
+        transfer.multisig = tx.multisig.clone();
+        assert!(matches!(TransactionValidator::validate(&transfer, &dag), Err(ValidationError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        use crate::dag::transaction::BatchOutput;
+
+        let (mut dag, kp) = create_dag_with_balance();
+        let [bob, carol] = [KeyPair::generate().public_key, KeyPair::generate().public_key];
+        let pay = |recipient: &PublicKey, amount| BatchOutput { recipient: recipient.clone(), amount };
+        let parents = dag.select_parents();
+
+        let outputs = vec![pay(&bob, 300_000), pay(&carol, 200_000)];
+        let tx = Transaction::batch(&kp, outputs, None, parents, 2);
+        assert_eq!(tx.data.amount, 500_000);
+        assert!(TransactionValidator::validate(&tx, &dag).is_ok());
+        dag.insert(DagVertex::new(tx, 2)).unwrap();
+        assert_eq!(dag.get_balance(&kp.public_key), 500_000);
+        assert_eq!((dag.get_balance(&bob), dag.get_balance(&carol)), (300_000, 200_000));
+
+        let batch = |outputs| Transaction::batch(&kp, outputs, None, dag.select_parents(), 3);
+        let invalid = |tx: &Transaction| TransactionValidator::validate(tx, &dag).unwrap_err();
+        assert!(matches!(invalid(&batch(vec![pay(&bob, 1)])), ValidationError::BatchOutputCount(_)));
+        assert!(matches!(invalid(&batch(vec![pay(&bob, 1), pay(&carol, 0)])), ValidationError::ZeroAmount));
+        assert!(matches!(
+            invalid(&batch(vec![pay(&bob, 1), pay(&kp.public_key, 1)])),
+            ValidationError::InvalidBatch("pays the sender")
+        ));
+        assert!(matches!(
+            invalid(&batch(vec![pay(&bob, 400_000), pay(&carol, 200_000)])),
+            ValidationError::InsufficientBalance { have: 500_000, need: 600_000 }
+        ));
+
+        // The amount has to be the outputs' total, and only a batch has outputs
+        let mut data = batch(vec![pay(&bob, 1), pay(&carol, 1)]).data;
+        data.amount = 1;
+        assert!(matches!(invalid(&Transaction::new(data.clone(), &kp)), ValidationError::InvalidBatch(_)));
+        data.tx_type = TransactionType::Transfer;
+        data.recipient = bob.clone();
+        assert!(matches!(invalid(&Transaction::new(data, &kp)), ValidationError::InvalidBatch(_)));
+    }
 }
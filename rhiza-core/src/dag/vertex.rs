@@ -1,7 +1,19 @@
-use crate::crypto::Hash;
-use crate::dag::transaction::Transaction;
+use crate::consensus::supply::SupplyLedger;
+use crate::consensus::weight::WeightCalculator;
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::channels::{ChannelStatus, Channels};
+use crate::dag::conflicts::ConflictTracker;
+use crate::dag::htlc::Htlcs;
+use crate::dag::pruning::{BalanceSnapshot, PrunedHistory};
+use crate::dag::relay_tips::RelayTips;
+use crate::dag::tips::TipSet;
+use crate::dag::transaction::{Transaction, TransactionType};
+use crate::dag::validator::TimestampRules;
+use crate::dag::work::{self, LOAD_WINDOW_MS};
+use crate::wallet::address::Address;
+use crate::wallet::alias::AliasRegistry;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 /// A vertex in the DAG — wraps a transaction with DAG metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,7 +48,7 @@ impl DagVertex {
     }
 
     /// Get the parent references
-    pub fn parents(&self) -> &[Hash; 2] {
+    pub fn parents(&self) -> &[Hash] {
         &self.transaction.data.parents
     }
 }
@@ -46,12 +58,49 @@ impl DagVertex {
 pub struct Dag {
     /// All vertices indexed by transaction ID
     vertices: HashMap<Hash, DagVertex>,
+    /// Every vertex as (depth, id): the one order listings and exports walk,
+    /// so the same DAG always lists the same way
+    order: BTreeSet<(u64, Hash)>,
+    /// Number of vertices per transaction timestamp, to measure the load
+    timestamps: BTreeMap<u64, usize>,
     /// Mapping from vertex ID to IDs of vertices that reference it (children/approvers)
     children: HashMap<Hash, Vec<Hash>>,
+    /// Distinct keys, other than its sender's, approving each vertex not yet final
+    approvers: HashMap<Hash, HashSet<PublicKey>>,
     /// Tips: vertices with no children (frontier of the DAG)
-    tips: Vec<Hash>,
+    tips: TipSet,
     /// The genesis transaction ID
     pub genesis_id: Option<Hash>,
+    /// The founder allocation transaction ID (there is at most one)
+    pub founder_allocation_id: Option<Hash>,
+    /// Network the genesis was made for
+    network_id: Option<u32>,
+    /// Bounds on the timestamps of transactions validated against this DAG
+    timestamp_rules: TimestampRules,
+    /// Vertices that crossed the finality threshold since the last drain
+    newly_final: Vec<Hash>,
+    /// Double-spend conflict sets and their current winners
+    conflicts: ConflictTracker,
+    /// (relayer, relayed transaction) pairs already claimed by a relay reward
+    claimed_relays: HashSet<(PublicKey, Hash)>,
+    /// Highest transfer nonce per sender among final transactions
+    confirmed_nonces: HashMap<PublicKey, u64>,
+    /// Aliases bound by final announcements
+    aliases: AliasRegistry,
+    /// Relay tips collected by final reward claims
+    relay_tips: RelayTips,
+    /// Payment channels opened, closing or settled in final history
+    channels: Channels,
+    /// Hash-time-locked contracts open or settled in final history
+    htlcs: Htlcs,
+    /// Every key seen in a transaction, by address
+    known_keys: HashMap<Address, PublicKey>,
+    /// Units minted by every transaction the DAG holds or pruned
+    supply: SupplyLedger,
+    /// Balances and ids of the final vertices pruned from memory
+    pruned: PrunedHistory,
+    /// Cross-check incremental weights against a full recomputation on insert
+    check_weights: bool,
 }
 
 impl Dag {
@@ -59,24 +108,82 @@ impl Dag {
     pub fn new() -> Self {
         Dag {
             vertices: HashMap::new(),
+            order: BTreeSet::new(),
+            timestamps: BTreeMap::new(),
             children: HashMap::new(),
-            tips: Vec::new(),
+            approvers: HashMap::new(),
+            tips: TipSet::new(),
             genesis_id: None,
+            founder_allocation_id: None,
+            network_id: None,
+            timestamp_rules: TimestampRules::default(),
+            newly_final: Vec::new(),
+            conflicts: ConflictTracker::new(),
+            claimed_relays: HashSet::new(),
+            confirmed_nonces: HashMap::new(),
+            aliases: AliasRegistry::new(),
+            relay_tips: RelayTips::new(),
+            channels: Channels::new(),
+            htlcs: Htlcs::new(),
+            known_keys: HashMap::new(),
+            supply: SupplyLedger::default(),
+            pruned: PrunedHistory::default(),
+            check_weights: false,
         }
     }
 
+    /// An empty DAG standing on a snapshot of pruned history
+    ///
+    /// The vertices kept past the snapshot are restored on top of it with
+    /// [`insert_restored`](Self::insert_restored).
+    pub fn from_balance_snapshot(snapshot: &BalanceSnapshot) -> Self {
+        let mut dag = Dag::new();
+        dag.genesis_id = snapshot.genesis_id;
+        dag.founder_allocation_id = snapshot.founder_allocation_id;
+        dag.network_id = snapshot.network_id;
+        dag.confirmed_nonces = snapshot.nonces.iter().cloned().collect();
+        for record in &snapshot.aliases {
+            dag.aliases.restore(record.clone());
+        }
+        dag.known_keys = snapshot
+            .known_keys
+            .iter()
+            .map(|key| (Address::from_public_key(key), key.clone()))
+            .collect();
+        dag.claimed_relays = snapshot.claimed_relays.iter().cloned().collect();
+        for channel in &snapshot.channels {
+            dag.channels.restore(channel.clone());
+        }
+        for htlc in &snapshot.htlcs {
+            dag.htlcs.restore(htlc.clone());
+        }
+        dag.pruned = PrunedHistory {
+            balances: snapshot.balances.iter().cloned().collect(),
+            tips: snapshot.tips.iter().copied().collect(),
+            ids: snapshot.pruned.iter().copied().collect(),
+            horizon: Some(snapshot.horizon),
+            supply: snapshot.supply,
+        };
+        dag.supply = snapshot.supply;
+        dag
+    }
+
     /// Insert a vertex into the DAG
     pub fn insert(&mut self, vertex: DagVertex) -> Result<(), DagError> {
         let id = vertex.id();
 
         // Check for duplicates
-        if self.vertices.contains_key(&id) {
+        if self.contains(&id) {
             return Err(DagError::DuplicateTransaction);
         }
 
         // For non-genesis transactions, verify parents exist
-        if !vertex.parents()[0].is_zero() {
+        let is_genesis = vertex.parents().iter().all(Hash::is_zero);
+        if !is_genesis {
             for parent in vertex.parents() {
+                if self.pruned.contains(parent) {
+                    continue;
+                }
                 if !self.vertices.contains_key(parent) {
                     return Err(DagError::MissingParent(*parent));
                 }
@@ -87,53 +194,182 @@ impl Dag {
                     .push(id);
 
                 // Parent is no longer a tip
-                self.tips.retain(|t| t != parent);
+                self.tips.remove(parent);
             }
         }
 
         // Track genesis
-        if vertex.transaction.data.parents[0].is_zero() && self.genesis_id.is_none() {
+        if is_genesis && self.genesis_id.is_none() {
             self.genesis_id = Some(id);
+            self.network_id = Some(vertex.transaction.data.network_id);
+        }
+        if vertex.transaction.data.tx_type == TransactionType::FounderAllocation
+            && self.founder_allocation_id.is_none()
+        {
+            self.founder_allocation_id = Some(id);
+        }
+        self.supply.record(&vertex.transaction);
+        for key in vertex.transaction.data.parties() {
+            self.known_keys
+                .entry(Address::from_public_key(key))
+                .or_insert_with(|| key.clone());
+        }
+        for proof in &vertex.transaction.data.relay_proofs {
+            self.claimed_relays
+                .insert((proof.relayer.clone(), proof.transaction_id));
         }
 
         // New vertex is a tip
-        self.tips.push(id);
+        self.tips.insert(id, vertex.depth);
 
+        self.order.insert((vertex.depth, id));
+        *self.timestamps.entry(vertex.transaction.data.timestamp).or_default() += 1;
         self.vertices.insert(id, vertex);
 
         // Update cumulative weights
         self.update_weights(id);
+        if self.check_weights {
+            self.assert_weights_consistent();
+        }
+
+        // Detect double-spends and re-pick winners with the new weights
+        let mut conflicts = std::mem::take(&mut self.conflicts);
+        conflicts.on_insert(self, id);
+        conflicts.resolve(self);
+        self.conflicts = conflicts;
 
         Ok(())
     }
 
+    /// Re-insert a vertex loaded from storage
+    ///
+    /// Depth and finality are kept as stored (finality is never revoked);
+    /// cumulative weight is recomputed as its approvers are restored, so
+    /// vertices must be restored parents-first.
+    pub fn insert_restored(&mut self, mut vertex: DagVertex) -> Result<(), DagError> {
+        let id = vertex.id();
+        let is_final = vertex.is_final;
+        vertex.cumulative_weight = vertex.own_weight;
+        if is_final {
+            record_nonce(&mut self.confirmed_nonces, &vertex.transaction);
+            self.aliases.record(&vertex.transaction);
+        }
+        self.insert(vertex)?;
+        if is_final {
+            self.record_tips(&id);
+            self.record_channels(&id);
+            self.record_htlcs(&id);
+        }
+        Ok(())
+    }
+
+    /// Network the DAG belongs to: the one its genesis was made for
+    pub fn network_id(&self) -> Option<u32> {
+        self.network_id
+    }
+
+    /// Bounds on the timestamps of transactions validated against this DAG
+    pub fn timestamp_rules(&self) -> TimestampRules {
+        self.timestamp_rules
+    }
+
+    pub fn set_timestamp_rules(&mut self, rules: TimestampRules) {
+        self.timestamp_rules = rules;
+    }
+
     /// Get a vertex by ID
     pub fn get(&self, id: &Hash) -> Option<&DagVertex> {
         self.vertices.get(id)
     }
 
-    /// Get current tips (for selecting parents for new transactions)
-    pub fn tips(&self) -> &[Hash] {
-        &self.tips
+    /// Overwrite a vertex's cumulative weight with a recomputed one
+    ///
+    /// Returns the weight it held, or `None` if the vertex isn't in memory.
+    pub fn repair_weight(&mut self, id: &Hash, weight: u64) -> Option<u64> {
+        let vertex = self.vertices.get_mut(id)?;
+        Some(std::mem::replace(&mut vertex.cumulative_weight, weight))
     }
 
-    /// Select 2 tips for a new transaction's parents
-    pub fn select_parents(&self) -> [Hash; 2] {
-        match self.tips.len() {
-            0 => [Hash::zero(), Hash::zero()],
-            1 => [self.tips[0], self.tips[0]],
-            _ => {
-                // Select 2 most recent tips (by depth)
-                let mut sorted_tips: Vec<_> = self.tips.iter()
-                    .filter_map(|t| self.vertices.get(t).map(|v| (t, v.depth)))
-                    .collect();
-                sorted_tips.sort_by(|a, b| b.1.cmp(&a.1));
+    /// Whether a transaction is in the DAG or was pruned from it
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.vertices.contains_key(id) || self.pruned.contains(id)
+    }
 
-                [*sorted_tips[0].0, *sorted_tips[1].0]
-            }
+    /// What is left of the vertices pruned from memory
+    pub fn pruned(&self) -> &PrunedHistory {
+        &self.pruned
+    }
+
+    /// Distinct keys, other than its sender's, whose transactions approve
+    /// `id`, counted up to [`FINALITY_THRESHOLD`](crate::FINALITY_THRESHOLD)
+    pub fn approver_count(&self, id: &Hash) -> u64 {
+        match self.vertices.get(id) {
+            Some(vertex) if vertex.is_final => crate::FINALITY_THRESHOLD,
+            Some(_) => self.approvers.get(id).map_or(0, |keys| keys.len() as u64),
+            None if self.pruned.contains(id) => crate::FINALITY_THRESHOLD,
+            None => 0,
         }
     }
 
+    /// IDs of the vertices that approve `id`
+    pub fn children(&self, id: &Hash) -> &[Hash] {
+        self.children.get(id).map_or(&[], |c| c.as_slice())
+    }
+
+    /// Get current tips, deepest first
+    pub fn tips(&self) -> Vec<Hash> {
+        self.tips.iter_deepest().copied().collect()
+    }
+
+    /// Final vertices with no final child: the edge of final history, which
+    /// holds every other final vertex in its past cone
+    pub fn final_tips(&self) -> Vec<Hash> {
+        let is_final = |id: &Hash| self.vertices.get(id).is_some_and(|v| v.is_final);
+        self.iter()
+            .filter(|v| v.is_final && !self.children(&v.id()).iter().any(is_final))
+            .map(|v| v.id())
+            .collect()
+    }
+
+    /// Number of current tips
+    pub fn tip_count(&self) -> usize {
+        self.tips.len()
+    }
+
+    /// Select [`PARENT_COUNT`](crate::PARENT_COUNT) tips for a new
+    /// transaction's parents
+    pub fn select_parents(&self) -> Vec<Hash> {
+        self.select_deepest_parents(crate::PARENT_COUNT)
+    }
+
+    /// Select the `count` most recent tips (by depth)
+    pub fn select_deepest_parents(&self, count: usize) -> Vec<Hash> {
+        let tips = self.tips.deepest(count).unwrap_or_else(|| vec![Hash::zero(); count]);
+        self.prefer_replacements(tips)
+    }
+
+    /// Select `count` distinct tips uniformly at random
+    ///
+    /// Spreads approvals across the frontier instead of always extending the
+    /// deepest branch, which keeps the tip count bounded under load.
+    pub fn select_parents_random<R: rand::Rng + ?Sized>(&self, count: usize, rng: &mut R) -> Vec<Hash> {
+        let tips = self.tips.random(count, rng).unwrap_or_else(|| vec![Hash::zero(); count]);
+        self.prefer_replacements(tips)
+    }
+
+    /// Swap superseded tips for the transfers replacing them, so new
+    /// transactions build on the replacement instead of the original
+    pub fn prefer_replacements(&self, tips: Vec<Hash>) -> Vec<Hash> {
+        tips.into_iter()
+            .map(|tip| {
+                self.conflicts
+                    .replacement_of(&tip)
+                    .filter(|r| self.vertices.contains_key(r))
+                    .unwrap_or(tip)
+            })
+            .collect()
+    }
+
     /// Number of vertices in the DAG
     pub fn len(&self) -> usize {
         self.vertices.len()
@@ -146,66 +382,470 @@ impl Dag {
 
     /// Get the current depth (max depth of any vertex)
     pub fn depth(&self) -> u64 {
-        self.vertices.values().map(|v| v.depth).max().unwrap_or(0)
+        self.order.last().map_or(0, |(depth, _)| *depth)
     }
 
-    /// Get all transaction IDs
+    /// Transactions in the DAG timestamped in the [`LOAD_WINDOW_MS`] up to `at`
+    pub fn load_at(&self, at: u64) -> usize {
+        self.timestamps
+            .range(at.saturating_sub(LOAD_WINDOW_MS)..=at)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Work (in bits) asked of a transaction with these parents: more the
+    /// busier the network was up to the newest of them
+    ///
+    /// Measured from the parents rather than the clock, so a transaction
+    /// synced long after it was made is held to what was asked at the time.
+    pub fn required_work(&self, parents: &[Hash]) -> u32 {
+        let newest = parents
+            .iter()
+            .filter_map(|parent| self.get(parent))
+            .map(|vertex| vertex.transaction.data.timestamp)
+            .max();
+        work::bits_for_load(newest.map_or(0, |at| self.load_at(at)))
+    }
+
+    /// Iterate over all vertices by ascending depth, ties broken by id
+    pub fn iter(&self) -> impl Iterator<Item = &DagVertex> {
+        self.order.iter().map(|(_, id)| &self.vertices[id])
+    }
+
+    /// All transaction IDs, in the order of [`iter`](Self::iter)
     pub fn transaction_ids(&self) -> Vec<Hash> {
-        self.vertices.keys().copied().collect()
+        self.order.iter().map(|(_, id)| *id).collect()
     }
 
-    /// Update cumulative weights after inserting a vertex
-    fn update_weights(&mut self, new_vertex_id: Hash) {
-        // Walk back through parents and increment their cumulative weight
-        let mut stack = vec![new_vertex_id];
-        let mut visited = std::collections::HashSet::new();
+    /// Get vertices with depth in `from..=to`, in ascending depth order
+    ///
+    /// Whole depths are returned until `limit` would be exceeded (at least one
+    /// depth is always returned, so a wide level can't stall a sync). The
+    /// second value is the depth to continue from when the range was cut short.
+    pub fn range_by_depth(&self, from: u64, to: u64, limit: usize) -> (Vec<&DagVertex>, Option<u64>) {
+        if from > to {
+            return (Vec::new(), None);
+        }
+        let mut in_range: Vec<&DagVertex> = self
+            .order
+            .range((from, Hash::zero())..=(to, Hash::from_bytes([0xff; 32])))
+            .map(|(_, id)| &self.vertices[id])
+            .collect();
+
+        let mut end = 0;
+        while end < in_range.len() {
+            let depth = in_range[end].depth;
+            let level_end = in_range[end..]
+                .iter()
+                .position(|v| v.depth != depth)
+                .map_or(in_range.len(), |p| end + p);
+            if end > 0 && level_end > limit {
+                break;
+            }
+            end = level_end;
+        }
+
+        let next = in_range.get(end).map(|v| v.depth);
+        in_range.truncate(end);
+        (in_range, next)
+    }
+
+    /// Whether `relayer` has already been rewarded for relaying `tx_id`
+    pub fn is_relay_claimed(&self, relayer: &PublicKey, tx_id: &Hash) -> bool {
+        self.claimed_relays.contains(&(relayer.clone(), *tx_id))
+    }
+
+    /// Units minted by the transactions in the DAG and its pruned history
+    pub fn supply(&self) -> &SupplyLedger {
+        &self.supply
+    }
+
+    /// Double-spend conflict sets
+    pub fn conflicts(&self) -> &ConflictTracker {
+        &self.conflicts
+    }
 
+    /// Let conflict voting's preferred members win their conflict sets
+    /// ahead of heavier ones
+    pub fn set_conflict_preferences(&mut self, preferred: HashSet<Hash>) {
+        let mut conflicts = std::mem::take(&mut self.conflicts);
+        conflicts.set_preferred(preferred);
+        conflicts.resolve(self);
+        self.conflicts = conflicts;
+    }
+
+    /// IDs of every vertex reachable from `roots` through parent links,
+    /// including the roots themselves
+    pub fn past_cone(&self, roots: &[Hash]) -> HashSet<Hash> {
+        let mut cone = HashSet::new();
+        let mut stack: Vec<Hash> = roots.to_vec();
         while let Some(id) = stack.pop() {
-            if !visited.insert(id) {
+            if id.is_zero() || !cone.insert(id) {
                 continue;
             }
-
             if let Some(vertex) = self.vertices.get(&id) {
-                let parents = *vertex.parents();
-                for parent in &parents {
-                    if !parent.is_zero() {
-                        if let Some(parent_vertex) = self.vertices.get_mut(parent) {
-                            parent_vertex.cumulative_weight += 1;
-                            // Check finality
-                            if parent_vertex.cumulative_weight >= crate::FINALITY_THRESHOLD {
-                                parent_vertex.is_final = true;
-                            }
-                        }
-                        stack.push(*parent);
-                    }
+                stack.extend_from_slice(vertex.parents());
+            }
+        }
+        cone
+    }
+
+    /// Highest nonce of a final payment from `sender`
+    pub fn last_confirmed_nonce(&self, sender: &PublicKey) -> Option<u64> {
+        self.confirmed_nonces.get(sender).copied()
+    }
+
+    /// Aliases bound by final announcements
+    pub fn aliases(&self) -> &AliasRegistry {
+        &self.aliases
+    }
+
+    /// Enable or disable weight consistency checking
+    ///
+    /// When enabled, every insert recomputes all weights with
+    /// [`WeightCalculator`] and panics on the first disagreement. This is
+    /// quadratic in the DAG size and meant for tests and debugging.
+    pub fn set_weight_checks(&mut self, enabled: bool) {
+        self.check_weights = enabled;
+    }
+
+    fn assert_weights_consistent(&self) {
+        for (id, expected) in WeightCalculator::calculate_all_weights(self) {
+            let actual = self.vertices[&id].cumulative_weight;
+            assert_eq!(
+                actual, expected,
+                "cumulative weight of {} is {}, recomputed {}",
+                id, actual, expected
+            );
+        }
+    }
+
+    /// Take the IDs of vertices that became final since the last call
+    pub fn take_newly_final(&mut self) -> Vec<Hash> {
+        std::mem::take(&mut self.newly_final)
+    }
+
+    /// Update cumulative weights and approvers after inserting a vertex
+    ///
+    /// Every vertex in the new vertex's past cone gains exactly one unit of
+    /// weight, however many paths lead to it, and the new vertex's sender as
+    /// an approver unless it sent that vertex too. A vertex becomes final
+    /// once [`FINALITY_THRESHOLD`](crate::FINALITY_THRESHOLD) distinct keys
+    /// approve it: weight alone can be piled up by one key approving its
+    /// own payment.
+    fn update_weights(&mut self, new_vertex_id: Hash) {
+        let Some(vertex) = self.vertices.get(&new_vertex_id) else {
+            return;
+        };
+        let approver = vertex.transaction.data.sender.clone();
+        let (depth, timestamp) = (vertex.depth, vertex.transaction.data.timestamp);
+        let mut finalized = Vec::new();
+        for ancestor in self.past_cone(vertex.parents()) {
+            let Some(ancestor_vertex) = self.vertices.get_mut(&ancestor) else {
+                continue;
+            };
+            ancestor_vertex.cumulative_weight = ancestor_vertex.cumulative_weight.saturating_add(1);
+            if ancestor_vertex.is_final || ancestor_vertex.transaction.data.sender == approver {
+                continue;
+            }
+            // Approving a time-locked payment early doesn't count
+            if !ancestor_vertex.transaction.data.unlocked_at(depth, timestamp) {
+                continue;
+            }
+            let keys = self.approvers.entry(ancestor).or_default();
+            keys.insert(approver.clone());
+            // Check finality
+            if keys.len() as u64 >= crate::FINALITY_THRESHOLD {
+                self.approvers.remove(&ancestor);
+                ancestor_vertex.is_final = true;
+                self.newly_final.push(ancestor);
+                record_nonce(&mut self.confirmed_nonces, &ancestor_vertex.transaction);
+                self.aliases.record(&ancestor_vertex.transaction);
+                finalized.push(ancestor);
+            }
+        }
+        // Claims finalizing together settle in the same order on every node
+        finalized.sort_by_key(|id| (self.vertices.get(id).map_or(0, |v| v.transaction.data.timestamp), *id));
+        for id in finalized {
+            self.record_tips(&id);
+            self.record_channels(&id);
+            self.record_htlcs(&id);
+        }
+    }
+
+    /// Hand out the tips a claim that just became final collected
+    fn record_tips(&mut self, claim: &Hash) {
+        let Some(vertex) = self.vertices.get(claim) else {
+            return;
+        };
+        let (vertices, pruned) = (&self.vertices, &self.pruned);
+        self.relay_tips.record(&vertex.transaction, |transfer| {
+            vertices
+                .get(transfer)
+                .map(|v| v.transaction.data.relay_tip)
+                .or_else(|| pruned.tip_of(transfer))
+        });
+    }
+
+    /// Relay tips and the claims that collected them
+    pub fn relay_tips(&self) -> &RelayTips {
+        &self.relay_tips
+    }
+
+    /// Apply a channel transaction that just became final, and settle the
+    /// funders' closes whose dispute window its timestamp ends
+    fn record_channels(&mut self, id: &Hash) {
+        let Some(vertex) = self.vertices.get(id) else {
+            return;
+        };
+        self.channels.record(&vertex.transaction);
+        for channel in self.channels.expire(vertex.transaction.data.timestamp) {
+            // A close pruned while its window ran pays out into pruned history
+            let ChannelStatus::Settled { close, .. } = channel.status else {
+                continue;
+            };
+            if self.pruned.contains(&close) {
+                for key in [&channel.funder, &channel.payee] {
+                    let balance = self.pruned.balances.entry(key.clone()).or_default();
+                    *balance = balance.saturating_add(channel.credit_for(key));
                 }
             }
         }
     }
 
+    /// Payment channels as final history has them
+    pub fn channels(&self) -> &Channels {
+        &self.channels
+    }
+
+    /// Apply an HTLC transaction that just became final
+    fn record_htlcs(&mut self, id: &Hash) {
+        if let Some(vertex) = self.vertices.get(id) {
+            self.htlcs.record(&vertex.transaction);
+        }
+    }
+
+    /// Hash-time-locked contracts as final history has them
+    pub fn htlcs(&self) -> &Htlcs {
+        &self.htlcs
+    }
+
     /// Get the balance of a public key by traversing the DAG
+    ///
+    /// Spends on the losing side of a double-spend are not counted.
     pub fn get_balance(&self, pubkey: &crate::crypto::PublicKey) -> u64 {
-        let mut balance: i128 = 0;
+        self.balance_over(self.vertices.values(), pubkey, self.frontier())
+    }
 
-        for vertex in self.vertices.values() {
-            let tx = &vertex.transaction;
+    /// Balance of a public key counting only final transactions
+    pub fn get_confirmed_balance(&self, pubkey: &crate::crypto::PublicKey) -> u64 {
+        self.balance_over(self.vertices.values().filter(|v| v.is_final), pubkey, self.frontier())
+    }
 
-            // Add received amounts
-            if tx.data.recipient == *pubkey {
-                balance += tx.data.amount as i128;
-            }
+    /// Depth a new transaction would join at, and the newest timestamp in
+    /// the DAG: how far time locks have run for the DAG as a whole
+    pub fn frontier(&self) -> (u64, u64) {
+        let newest = self.timestamps.keys().next_back().copied().unwrap_or(0);
+        (self.depth() + 1, newest)
+    }
+
+    /// Find the public key behind an address among the DAG's participants
+    ///
+    /// Addresses are one-way hashes, so only keys that have signed, received
+    /// or announced themselves in a transaction can be found.
+    pub fn find_public_key(&self, address: &Address) -> Option<PublicKey> {
+        self.known_keys.get(address).cloned()
+    }
+
+    /// Balance of a public key as seen by a transaction with these parents
+    ///
+    /// Only the parents' past cone counts, so two spends that can't see each
+    /// other are each checked against the funds they could observe. Pruned
+    /// history is final and lies in every new transaction's past cone.
+    ///
+    /// A time-locked credit counts once a transaction on these parents would
+    /// be past the lock: at least as deep, or dated no earlier than the
+    /// newest parent.
+    pub fn balance_seen_by(&self, parents: &[Hash], pubkey: &crate::crypto::PublicKey) -> u64 {
+        let cone = self.past_cone(parents);
+        let at = self.frontier_of(parents);
+        self.balance_over(cone.iter().filter_map(|id| self.vertices.get(id)), pubkey, at)
+    }
 
-            // Subtract sent amounts (only for transfers, not self-payments)
-            if tx.data.sender == *pubkey
-                && tx.data.recipient != *pubkey
-            {
-                balance -= tx.data.amount as i128;
-                balance -= tx.data.fee as i128;
+    /// Depth a transaction on these parents joins at, and the newest
+    /// parent's timestamp; parents pruned or missing count as depth 0
+    pub fn frontier_of(&self, parents: &[Hash]) -> (u64, u64) {
+        let parents = parents.iter().filter_map(|id| self.vertices.get(id));
+        parents.fold((0, 0), |(depth, newest), parent| {
+            (depth.max(parent.depth + 1), newest.max(parent.transaction.data.timestamp))
+        })
+    }
+
+    /// Balance from pruned history plus the effect of `vertices`, with time
+    /// locks run as far as the depth and timestamp `at`
+    fn balance_over<'a>(
+        &self,
+        vertices: impl Iterator<Item = &'a DagVertex>,
+        pubkey: &crate::crypto::PublicKey,
+        at: (u64, u64),
+    ) -> u64 {
+        let mut balance = self.pruned.balance(pubkey) as i128;
+        for vertex in vertices {
+            balance = balance.saturating_add(self.effect_on(&vertex.transaction, pubkey, at));
+        }
+
+        // Balances past u64::MAX can only come from unvalidated history
+        u64::try_from(balance.max(0)).unwrap_or(u64::MAX)
+    }
+
+    /// Net change a transaction makes to a key's balance, as of the depth
+    /// and timestamp `at`
+    ///
+    /// Spends on the losing side of a double-spend change nothing. A
+    /// time-locked payment debits its sender at once but credits the
+    /// recipient only past the lock.
+    fn effect_on(&self, tx: &Transaction, pubkey: &PublicKey, at: (u64, u64)) -> i128 {
+        if self.conflicts.is_rejected(&tx.id) {
+            return 0;
+        }
+        let credit = if tx.data.unlocked_at(at.0, at.1) { tx.data.credit_for(pubkey) } else { 0 };
+        // Each term is below 2^66, so i128 overflow is out of reach
+        let mut effect = credit as i128 - tx.data.debit_for(pubkey) as i128;
+        if tx.data.recipient == *pubkey {
+            effect += self.relay_tips.credit_for(&tx.id, &self.conflicts) as i128;
+        }
+        if let Some(channel) = self.channels.settled_by(&tx.id) {
+            effect += channel.credit_for(pubkey) as i128;
+        }
+        if let Some(htlc) = self.htlcs.settled_by(&tx.id) {
+            effect += htlc.credit_for(pubkey) as i128;
+        }
+        effect
+    }
+
+    /// Collapse final vertices at or below depth `horizon` into the pruned
+    /// history and drop them from memory
+    ///
+    /// Ancestors of a final vertex are final and shallower, so what is
+    /// pruned is closed under parents and the vertices kept keep their
+    /// weights. Conflicts settled among pruned vertices stay settled as
+    /// they stand. Returns the dropped vertices, shallowest first.
+    pub fn prune(&mut self, horizon: u64) -> Vec<DagVertex> {
+        let mut ids: Vec<Hash> = self
+            .vertices
+            .values()
+            .filter(|v| v.is_final && v.depth <= horizon)
+            .map(|v| v.id())
+            .collect();
+        if ids.is_empty() {
+            return Vec::new();
+        }
+        let pruned: HashSet<Hash> = ids.iter().copied().collect();
+
+        // Everything pruned is final, so past any time lock it carries
+        let at = self.frontier();
+        let mut net: HashMap<PublicKey, i128> = HashMap::new();
+        for id in &ids {
+            let tx = &self.vertices[id].transaction;
+            let data = &tx.data;
+            for key in data.parties() {
+                *net.entry(key.clone()).or_default() += self.effect_on(tx, key, at);
+            }
+            // A claim still in the DAG may collect (or have collected) the tip
+            let winner = self.relay_tips.winner(id);
+            if data.relay_tip > 0 && winner.is_none_or(|claim| !pruned.contains(claim)) {
+                self.pruned.tips.insert(*id, data.relay_tip);
+            }
+        }
+        // Tips collected by pruned claims are in those claims' balances now
+        let relay_tips = &self.relay_tips;
+        self.pruned
+            .tips
+            .retain(|transfer, _| relay_tips.winner(transfer).is_none_or(|claim| !pruned.contains(claim)));
+        for (key, effect) in net {
+            let balance = (self.pruned.balance(&key) as i128 + effect).max(0);
+            let balance = u64::try_from(balance).unwrap_or(u64::MAX);
+            if balance == 0 {
+                self.pruned.balances.remove(&key);
+            } else {
+                self.pruned.balances.insert(key, balance);
             }
         }
 
-        balance.max(0) as u64
+        ids.sort_by_key(|id| (self.vertices[id].depth, *id.as_bytes()));
+        let removed = ids
+            .iter()
+            .filter_map(|id| {
+                self.children.remove(id);
+                self.approvers.remove(id);
+                self.tips.remove(id);
+                let vertex = self.vertices.remove(id)?;
+                self.order.remove(&(vertex.depth, *id));
+                let timestamp = vertex.transaction.data.timestamp;
+                if let Some(count) = self.timestamps.get_mut(&timestamp) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.timestamps.remove(&timestamp);
+                    }
+                }
+                self.pruned.supply.record(&vertex.transaction);
+                Some(vertex)
+            })
+            .collect();
+        self.conflicts.forget(&pruned);
+        self.pruned.ids.extend(pruned);
+        self.channels.forget(&self.pruned.ids);
+        self.htlcs.forget(&self.pruned.ids);
+        self.pruned.horizon = Some(self.pruned.horizon.map_or(horizon, |h| h.max(horizon)));
+        removed
     }
+
+    /// Unsigned snapshot of the pruned history and what validation needs
+    /// of it, for restoring the DAG without the pruned vertices
+    pub fn balance_snapshot(&self) -> BalanceSnapshot {
+        fn sorted<T, K: Ord>(mut items: Vec<T>, key: impl Fn(&T) -> K) -> Vec<T> {
+            items.sort_by_key(key);
+            items
+        }
+
+        BalanceSnapshot {
+            network_id: self.network_id,
+            genesis_id: self.genesis_id,
+            founder_allocation_id: self.founder_allocation_id,
+            horizon: self.pruned.horizon.unwrap_or(0),
+            created_ms: chrono::Utc::now().timestamp_millis() as u64,
+            balances: sorted(
+                self.pruned.balances.iter().map(|(k, b)| (k.clone(), *b)).collect(),
+                |(k, _)| *k.as_bytes(),
+            ),
+            nonces: sorted(
+                self.confirmed_nonces.iter().map(|(k, n)| (k.clone(), *n)).collect(),
+                |(k, _)| *k.as_bytes(),
+            ),
+            aliases: sorted(self.aliases.records().cloned().collect(), |r| r.alias.clone()),
+            known_keys: sorted(self.known_keys.values().cloned().collect(), |k| *k.as_bytes()),
+            claimed_relays: sorted(self.claimed_relays.iter().cloned().collect(), |(k, id)| {
+                (*k.as_bytes(), *id.as_bytes())
+            }),
+            tips: sorted(self.pruned.tips.iter().map(|(id, tip)| (*id, *tip)).collect(), |(id, _)| {
+                *id.as_bytes()
+            }),
+            pruned: sorted(self.pruned.ids.iter().copied().collect(), |id| *id.as_bytes()),
+            channels: sorted(self.channels.iter().cloned().collect(), |c| c.id),
+            htlcs: sorted(self.htlcs.iter().cloned().collect(), |h| h.id),
+            supply: self.pruned.supply,
+            signature: None,
+        }
+    }
+}
+
+/// Remember a final payment's nonce as its sender's latest
+fn record_nonce(nonces: &mut HashMap<PublicKey, u64>, tx: &Transaction) {
+    if !tx.data.tx_type.is_payment() {
+        return;
+    }
+    let last = nonces.entry(tx.data.sender.clone()).or_insert(tx.data.nonce);
+    *last = (*last).max(tx.data.nonce);
 }
 
 impl Default for Dag {
@@ -227,6 +867,7 @@ pub enum DagError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::consensus::weight::WeightDivergence;
     use crate::crypto::keys::KeyPair;
     use crate::dag::transaction::Transaction;
 
@@ -279,9 +920,33 @@ mod tests {
         );
         dag.insert(DagVertex::new(tx1, 1)).unwrap();
 
-        // Genesis should now have cumulative_weight 2 (1 own + 1 from child)
+        // Genesis should now have cumulative_weight 2 (1 own + 1 from child),
+        // even though the child names it as both parents
         let genesis = dag.get(&genesis_id).unwrap();
-        assert!(genesis.cumulative_weight > 1);
+        assert_eq!(genesis.cumulative_weight, 2);
+    }
+
+    #[test]
+    fn test_weight_counts_each_descendant_once() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        dag.set_weight_checks(true);
+
+        // Diamond: genesis <- a, b <- c, then d approves c and a
+        let a = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1);
+        let b = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 2);
+        let (a_id, b_id) = (a.id, b.id);
+        dag.insert(DagVertex::new(a, 1)).unwrap();
+        dag.insert(DagVertex::new(b, 1)).unwrap();
+        let c = Transaction::relay_reward(&kp, 1, [a_id, b_id], 3);
+        let c_id = c.id;
+        dag.insert(DagVertex::new(c, 2)).unwrap();
+        let d = Transaction::relay_reward(&kp, 1, [c_id, a_id], 4);
+        dag.insert(DagVertex::new(d, 3)).unwrap();
+
+        assert_eq!(dag.get(&genesis_id).unwrap().cumulative_weight, 5);
+        assert_eq!(dag.get(&a_id).unwrap().cumulative_weight, 3);
+        assert_eq!(dag.get(&b_id).unwrap().cumulative_weight, 3);
+        assert_eq!(dag.get(&c_id).unwrap().cumulative_weight, 2);
     }
 
     #[test]
@@ -315,6 +980,262 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_take_newly_final() {
+        let (mut dag, _, genesis_id) = setup_dag_with_genesis();
+        assert!(dag.take_newly_final().is_empty());
+
+        let mut last = genesis_id;
+        for i in 1..=crate::FINALITY_THRESHOLD {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 100, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        assert_eq!(dag.approver_count(&genesis_id), crate::FINALITY_THRESHOLD);
+
+        let finalized = dag.take_newly_final();
+        assert!(finalized.contains(&genesis_id));
+        // Drained — reported only once
+        assert!(!dag.take_newly_final().contains(&genesis_id));
+    }
+
+    #[test]
+    fn test_confirmed_balance_and_address_lookup() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let first = Transaction::relay_reward(&kp, 100, [genesis_id, genesis_id], 1);
+        let mut last = first.id;
+        dag.insert(DagVertex::new(first, 1)).unwrap();
+        for i in 2..=crate::FINALITY_THRESHOLD + 1 {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 100, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        let second = Transaction::relay_reward(&kp, 100, [last, last], 100);
+        dag.insert(DagVertex::new(second, crate::FINALITY_THRESHOLD + 2)).unwrap();
+
+        // Only the first reward has gathered enough approvers
+        let total = dag.get_balance(&kp.public_key);
+        let confirmed = dag.get_confirmed_balance(&kp.public_key);
+        assert_eq!(total - confirmed, 100);
+
+        let address = Address::from_public_key(&kp.public_key);
+        assert_eq!(dag.find_public_key(&address), Some(kp.public_key.clone()));
+        let stranger = Address::from_public_key(&KeyPair::generate().public_key);
+        assert_eq!(dag.find_public_key(&stranger), None);
+    }
+
+    #[test]
+    fn test_fees_are_burned() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let reward = Transaction::relay_reward(&kp, 1_000, [genesis_id, genesis_id], 1);
+        let reward_id = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+
+        let bob = KeyPair::generate();
+        let tx = Transaction::transfer(&kp, bob.public_key.clone(), 600, [reward_id, reward_id], 2).with_fee(30, &kp);
+        dag.insert(DagVertex::new(tx, 2)).unwrap();
+        assert_eq!(dag.get_balance(&kp.public_key), 370);
+        assert_eq!(dag.get_balance(&bob.public_key), 600);
+    }
+
+    #[test]
+    fn test_time_locked_payment() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let reward = Transaction::relay_reward(&kp, 1_000, [genesis_id, genesis_id], 1);
+        let reward_id = reward.id;
+        dag.insert(DagVertex::new(reward, 1)).unwrap();
+
+        // Locked until depth 5: the sender pays now, the recipient later
+        let bob = KeyPair::generate();
+        let locked = Transaction::transfer(&kp, bob.public_key.clone(), 600, [reward_id, reward_id], 2)
+            .with_valid_after(5, &kp);
+        let locked_id = locked.id;
+        dag.insert(DagVertex::new(locked, 2)).unwrap();
+        assert_eq!(dag.get_balance(&kp.public_key), 400);
+        assert_eq!(dag.get_balance(&bob.public_key), 0);
+
+        // Approvals below the lock don't count towards finality
+        let mut last = locked_id;
+        for depth in 3..=4 {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 100, [last, last], depth);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, depth)).unwrap();
+        }
+        assert_eq!(dag.approver_count(&locked_id), 0);
+        assert_eq!(dag.balance_seen_by(&[locked_id, locked_id], &bob.public_key), 0);
+        // A transaction on the newest tip would be at depth 5
+        assert_eq!(dag.balance_seen_by(&[last, last], &bob.public_key), 600);
+        assert_eq!(dag.get_balance(&bob.public_key), 600);
+        assert_eq!(dag.get_confirmed_balance(&bob.public_key), 0);
+
+        for depth in 5..5 + crate::FINALITY_THRESHOLD {
+            let tx = Transaction::relay_reward(&KeyPair::generate(), 100, [last, last], depth);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, depth)).unwrap();
+        }
+        assert!(dag.get(&locked_id).unwrap().is_final);
+        assert_eq!(dag.get_confirmed_balance(&bob.public_key), 600);
+
+        // Large values lock until a time instead
+        let data = &dag.get(&locked_id).unwrap().transaction.data;
+        let mut timed = data.clone();
+        timed.valid_after = 1_700_000_000_000;
+        assert!(!timed.unlocked_at(u64::MAX, 1_699_999_999_999));
+        assert!(timed.unlocked_at(0, 1_700_000_000_000));
+        assert!(!data.unlocked_at(4, u64::MAX));
+    }
+
+    #[test]
+    fn test_balance_saturates_on_extreme_history() {
+        // Unvalidated history can credit more than fits in a u64
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let recipient = KeyPair::generate();
+        let mut last = genesis_id;
+        for i in 1..=3 {
+            let tx = Transaction::transfer(&kp, recipient.public_key.clone(), u64::MAX, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+
+        assert_eq!(dag.get_balance(&recipient.public_key), u64::MAX);
+        assert_eq!(dag.get_balance(&kp.public_key), 0);
+        assert_eq!(dag.balance_seen_by(&[last, last], &recipient.public_key), u64::MAX);
+    }
+
+    #[test]
+    fn test_verify_subdag_catches_divergence() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let a = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1);
+        let b = Transaction::relay_reward(&kp, 1, [a.id, a.id], 2);
+        dag.insert(DagVertex::new(a.clone(), 1)).unwrap();
+        dag.insert(DagVertex::new(b, 2)).unwrap();
+        assert!(WeightCalculator::verify_subdag(&dag, &genesis_id, 10).unwrap().divergences.is_empty());
+
+        // An incremental slip is caught
+        dag.vertices.get_mut(&a.id).unwrap().cumulative_weight += 1;
+        let check = WeightCalculator::verify_subdag(&dag, &genesis_id, 10).unwrap();
+        assert_eq!(check.checked, 3);
+        assert_eq!(
+            check.divergences,
+            vec![WeightDivergence { id: a.id, incremental: 3, recomputed: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_repair_weight() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let a = Transaction::relay_reward(&kp, 1, [genesis_id, genesis_id], 1);
+        dag.insert(DagVertex::new(a.clone(), 1)).unwrap();
+        dag.vertices.get_mut(&genesis_id).unwrap().cumulative_weight = 7;
+
+        let expected = WeightCalculator::calculate_all_weights(&dag)[&genesis_id];
+        assert_eq!(dag.repair_weight(&genesis_id, expected), Some(7));
+        assert_eq!(dag.get(&genesis_id).unwrap().cumulative_weight, 2);
+        assert!(WeightCalculator::verify_subdag(&dag, &genesis_id, 10).unwrap().divergences.is_empty());
+        assert_eq!(dag.repair_weight(&Hash::digest(b"absent"), 1), None);
+    }
+
+    #[test]
+    fn test_insert_restored_keeps_finality() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let tx = Transaction::relay_reward(&kp, 100, [genesis_id, genesis_id], 1);
+        let tx_id = tx.id;
+        dag.insert(DagVertex::new(tx, 1)).unwrap();
+
+        let mut stored: Vec<DagVertex> = dag.vertices.values().cloned().collect();
+        stored.sort_by_key(|v| v.depth);
+        stored[0].is_final = true;
+        stored[0].cumulative_weight = 99;
+
+        let mut restored = Dag::new();
+        for vertex in stored {
+            restored.insert_restored(vertex).unwrap();
+        }
+        let genesis = restored.get(&genesis_id).unwrap();
+        assert!(genesis.is_final);
+        // Stored weight is discarded and recomputed from approvers
+        assert_eq!(genesis.cumulative_weight, dag.get(&genesis_id).unwrap().cumulative_weight);
+        assert_eq!(restored.tips(), vec![tx_id]);
+        assert!(restored.take_newly_final().is_empty());
+    }
+
+    #[test]
+    fn test_range_by_depth() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let mut last = genesis_id;
+        for i in 1..=5 {
+            let tx = Transaction::relay_reward(&kp, 100, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+
+        let (all, next) = dag.range_by_depth(0, 5, 100);
+        assert_eq!(all.len(), 6);
+        assert_eq!(next, None);
+        assert!(all.windows(2).all(|w| w[0].depth <= w[1].depth));
+
+        let (page, next) = dag.range_by_depth(1, 5, 2);
+        assert_eq!(page.iter().map(|v| v.depth).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(next, Some(3));
+    }
+
+    #[test]
+    fn test_listing_order_is_stable() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let mut other = dag.clone();
+        // Siblings at depth 1, then one child at depth 2
+        let siblings: Vec<Transaction> = (1..=4)
+            .map(|i| Transaction::relay_reward(&kp, 100, [genesis_id, genesis_id], i))
+            .collect();
+        let child = Transaction::relay_reward(&kp, 100, [siblings[0].id, siblings[1].id], 5);
+        for tx in &siblings {
+            dag.insert(DagVertex::new(tx.clone(), 1)).unwrap();
+        }
+        for tx in siblings.iter().rev() {
+            other.insert(DagVertex::new(tx.clone(), 1)).unwrap();
+        }
+        dag.insert(DagVertex::new(child.clone(), 2)).unwrap();
+        other.insert(DagVertex::new(child.clone(), 2)).unwrap();
+
+        let ids = dag.transaction_ids();
+        assert_eq!(ids, other.transaction_ids());
+        assert_eq!(ids, dag.iter().map(|v| v.id()).collect::<Vec<_>>());
+        assert_eq!(ids[0], genesis_id);
+        assert_eq!(ids[5], child.id);
+        assert!(ids[1..5].windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(dag.depth(), 2);
+    }
+
+    #[test]
+    fn test_select_parents_prefers_deepest_tips() {
+        let (mut dag, kp, genesis_id) = setup_dag_with_genesis();
+        let mut ids = Vec::new();
+        for i in 1..=3 {
+            let tx = Transaction::relay_reward(&kp, 100, [genesis_id, genesis_id], i);
+            ids.push(tx.id);
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+
+        assert_eq!(dag.tip_count(), 3);
+        assert_eq!(dag.select_parents(), [ids[2], ids[1]]);
+
+        let mut rng = crate::testkit::seeded_rng();
+        let [a, b] = dag.select_parents_random(2, &mut rng)[..] else {
+            panic!("two parents");
+        };
+        assert_ne!(a, b);
+        assert!(ids.contains(&a) && ids.contains(&b));
+
+        // Wider parent sets take the next deepest tips, then repeat
+        assert_eq!(dag.select_deepest_parents(3), [ids[2], ids[1], ids[0]]);
+        assert_eq!(dag.select_deepest_parents(4), [ids[2], ids[1], ids[0], ids[2]]);
+        let mut wide = dag.select_parents_random(3, &mut rng);
+        wide.sort();
+        let mut all = ids.clone();
+        all.sort();
+        assert_eq!(wide, all);
+    }
+
     #[test]
     fn test_select_parents() {
         let (dag, _, genesis_id) = setup_dag_with_genesis();
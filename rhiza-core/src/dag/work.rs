@@ -0,0 +1,153 @@
+use crate::crypto::Hash;
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+
+/// Domain separating work hashes from every other BLAKE3 use
+const WORK_DOMAIN: &[u8] = b"rhiza-work-v1";
+
+/// Domain separating memory-hard work from the keystore's Argon2 use
+const MEMORY_WORK_DOMAIN: &[u8] = b"rhiza-work-argon2-v1";
+
+/// Memory each memory-hard attempt fills, in KiB: more than a GPU core's
+/// share of fast memory, little for a phone
+pub const MEMORY_COST_KIB: u32 = 256;
+
+/// How many bits fewer the memory-hard puzzle asks for than BLAKE3 at the
+/// same load, as one attempt costs about 2^8 BLAKE3 hashes on a phone
+pub const MEMORY_HARD_DISCOUNT_BITS: u32 = 8;
+
+/// Least work every transaction carries, in leading zero bits of its work
+/// hash: about a thousand hashes, a millisecond or so even on a phone
+pub const MIN_WORK_BITS: u32 = 10;
+
+/// Most work the network load can ask for: some sixteen million hashes
+pub const MAX_WORK_BITS: u32 = 24;
+
+/// Window the network load is measured over, ending at a transaction's
+/// newest parent
+pub const LOAD_WINDOW_MS: u64 = 60_000;
+
+/// Transactions per window the minimum work is meant for; each doubling of
+/// the load beyond it asks for one more bit
+pub const LOAD_STEP: usize = 120;
+
+/// Bits a transaction may fall short of what its validator asks for, since
+/// the validator may have seen a few more transactions in the window than
+/// the sender had
+pub const WORK_SLACK_BITS: u32 = 1;
+
+/// Puzzle a transaction's proof of work solves
+///
+/// BLAKE3 is cheapest to check but runs far faster on servers and GPUs.
+/// The memory-hard puzzle runs Argon2id over [`MEMORY_COST_KIB`] per
+/// attempt, which memory bandwidth bounds about alike on phones, laptops
+/// and servers; it asks for [`MEMORY_HARD_DISCOUNT_BITS`] fewer bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkPuzzle {
+    #[default]
+    Blake3,
+    Argon2,
+}
+
+impl WorkPuzzle {
+    /// Leading zero bits of this puzzle's work hash of `id` and `nonce`
+    pub fn work_bits(self, id: &Hash, nonce: u64) -> u32 {
+        match self {
+            WorkPuzzle::Blake3 => work_bits(id, nonce),
+            WorkPuzzle::Argon2 => memory_hard_work_bits(id, nonce),
+        }
+    }
+
+    /// Bits of this puzzle standing for `bits` of BLAKE3 work
+    pub fn bits_for(self, bits: u32) -> u32 {
+        match self {
+            WorkPuzzle::Blake3 => bits,
+            WorkPuzzle::Argon2 => bits.saturating_sub(MEMORY_HARD_DISCOUNT_BITS),
+        }
+    }
+
+    /// First nonce that gives `id` at least `bits` of this puzzle's work
+    pub fn solve(self, id: &Hash, bits: u32) -> u64 {
+        (0..=u64::MAX)
+            .find(|nonce| self.work_bits(id, *nonce) >= bits)
+            .expect("some nonce meets any reachable difficulty")
+    }
+}
+
+/// Leading zero bits of the work hash of a transaction ID and nonce
+pub fn work_bits(id: &Hash, nonce: u64) -> u32 {
+    let digest = Hash::digest_multi(&[WORK_DOMAIN, id.as_bytes(), &nonce.to_le_bytes()]);
+    leading_zero_bits(digest.as_bytes())
+}
+
+/// First nonce that gives `id` at least `bits` of work
+pub fn solve(id: &Hash, bits: u32) -> u64 {
+    WorkPuzzle::Blake3.solve(id, bits)
+}
+
+/// Leading zero bits of the memory-hard work hash of a transaction ID and
+/// nonce
+pub fn memory_hard_work_bits(id: &Hash, nonce: u64) -> u32 {
+    let params = Params::new(MEMORY_COST_KIB, 1, 1, Some(32)).expect("memory-hard work parameters are valid");
+    let mut digest = [0u8; 32];
+    let mut input = MEMORY_WORK_DOMAIN.to_vec();
+    input.extend_from_slice(&nonce.to_le_bytes());
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(&input, id.as_bytes(), &mut digest)
+        .expect("a 32-byte ID is a valid salt");
+    leading_zero_bits(&digest)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Work asked of a transaction when `load` transactions were made in the
+/// window before it
+pub fn bits_for_load(load: usize) -> u32 {
+    (MIN_WORK_BITS + (load / LOAD_STEP).max(1).ilog2()).min(MAX_WORK_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_meets_the_target() {
+        let id = Hash::digest(b"some transaction");
+        let nonce = solve(&id, MIN_WORK_BITS);
+        assert!(work_bits(&id, nonce) >= MIN_WORK_BITS);
+        assert!((0..nonce).all(|n| work_bits(&id, n) < MIN_WORK_BITS));
+        assert_eq!(solve(&id, 0), 0);
+    }
+
+    #[test]
+    fn test_memory_hard_puzzle() {
+        let id = Hash::digest(b"some transaction");
+        let puzzle = WorkPuzzle::Argon2;
+        let bits = puzzle.bits_for(MIN_WORK_BITS);
+        assert_eq!(bits, MIN_WORK_BITS - MEMORY_HARD_DISCOUNT_BITS);
+        let nonce = puzzle.solve(&id, bits);
+        assert!(memory_hard_work_bits(&id, nonce) >= bits);
+        assert_eq!(puzzle.work_bits(&id, nonce), memory_hard_work_bits(&id, nonce));
+        assert!((0..nonce).all(|n| memory_hard_work_bits(&id, n) < bits));
+        assert_eq!(WorkPuzzle::Blake3.bits_for(MAX_WORK_BITS), MAX_WORK_BITS);
+    }
+
+    #[test]
+    fn test_difficulty_follows_load() {
+        assert_eq!(bits_for_load(0), MIN_WORK_BITS);
+        assert_eq!(bits_for_load(2 * LOAD_STEP - 1), MIN_WORK_BITS);
+        assert_eq!(bits_for_load(2 * LOAD_STEP), MIN_WORK_BITS + 1);
+        assert_eq!(bits_for_load(8 * LOAD_STEP), MIN_WORK_BITS + 3);
+        assert_eq!(bits_for_load(usize::MAX), MAX_WORK_BITS);
+    }
+}
@@ -2,8 +2,15 @@ pub mod crypto;
 pub mod dag;
 pub mod consensus;
 pub mod network;
+pub mod release;
 pub mod wallet;
 
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+#[cfg(test)]
+mod testkit;
+
 /// The human-readable prefix for Rhiza addresses
 pub const ADDRESS_HRP: &str = "rhz";
 
@@ -13,9 +20,15 @@ pub const UNITS_PER_RHZ: u64 = 100_000_000;
 /// Maximum supply: 21,000,000 RHZ
 pub const MAX_SUPPLY: u64 = 21_000_000 * UNITS_PER_RHZ;
 
-/// Number of parent references each transaction must have
+/// Number of parents new transactions reference unless configured otherwise
 pub const PARENT_COUNT: usize = 2;
 
+/// Fewest parents a transaction may reference on any network
+pub const MIN_PARENT_COUNT: usize = 2;
+
+/// Most parents a transaction may reference on any network
+pub const MAX_PARENT_COUNT: usize = 8;
+
 /// Minimum cumulative weight for finality
 pub const FINALITY_THRESHOLD: u64 = 10;
 
@@ -25,6 +38,51 @@ pub const BASE_RELAY_REWARD: u64 = 1_000_000;
 /// Relay count at which reward halves
 pub const RELAY_HALVING_INTERVAL: u64 = 1_000;
 
+/// Network ID of the main network
+///
+/// Every transaction signs the ID of the network it was made for, and peers
+/// mix it into their handshake, so nothing crosses between networks.
+pub const MAINNET_ID: u32 = 1;
+
+/// Network ID of the public test network
+pub const TESTNET_ID: u32 = 2;
+
+/// Network ID of local development networks
+pub const DEVNET_ID: u32 = 3;
+
+/// Parse a network name (`mainnet`, `testnet`, `devnet`) or numeric ID
+pub fn parse_network_id(s: &str) -> Option<u32> {
+    match s {
+        "mainnet" => Some(MAINNET_ID),
+        "testnet" => Some(TESTNET_ID),
+        "devnet" => Some(DEVNET_ID),
+        _ => s.parse().ok(),
+    }
+}
+
+/// Name of a well-known network, for display
+pub fn network_name(network_id: u32) -> Option<&'static str> {
+    match network_id {
+        MAINNET_ID => Some("mainnet"),
+        TESTNET_ID => Some("testnet"),
+        DEVNET_ID => Some("devnet"),
+        _ => None,
+    }
+}
+
+/// Parent counts transactions on a network may have
+///
+/// Wider parent sets approve more of the frontier at once, which helps a
+/// busy mesh converge. Mainnet keeps exactly [`PARENT_COUNT`] until wider
+/// sets are agreed there; test and development networks take anything from
+/// [`MIN_PARENT_COUNT`] to [`MAX_PARENT_COUNT`].
+pub fn parent_counts(network_id: u32) -> std::ops::RangeInclusive<usize> {
+    match network_id {
+        MAINNET_ID => PARENT_COUNT..=PARENT_COUNT,
+        _ => MIN_PARENT_COUNT..=MAX_PARENT_COUNT,
+    }
+}
+
 /// Founder allocation: 5% of max supply (1,050,000 RHZ)
 /// This is a one-time genesis allocation to the protocol creator
 pub const FOUNDER_ALLOCATION: u64 = MAX_SUPPLY / 20;
@@ -32,3 +90,12 @@ pub const FOUNDER_ALLOCATION: u64 = MAX_SUPPLY / 20;
 /// Founder's public key (Ed25519, hex-encoded)
 /// Address: rhz1hh8kfkldmn37t35wqqaz9t9rtrhnk4e9qlkz5z
 pub const FOUNDER_PUBLIC_KEY: &str = "cd3f2d882dd11f282e13f641b6aa751a3d46b3ff5a9efbccebea9a0131c0dfdd";
+
+/// The pinned founder public key, parsed from `FOUNDER_PUBLIC_KEY`
+pub fn founder_public_key() -> crypto::PublicKey {
+    let bytes: [u8; 32] = hex::decode(FOUNDER_PUBLIC_KEY)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .expect("FOUNDER_PUBLIC_KEY is a valid 32-byte hex key");
+    crypto::PublicKey::from_bytes(bytes)
+}
@@ -0,0 +1,187 @@
+use btleplug::api::{
+    Central, CentralEvent, Characteristic, Manager as _, Peripheral as _, ScanFilter, ValueNotification, WriteType,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::prelude::*;
+use std::pin::Pin;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tracing::debug;
+use uuid::Uuid;
+
+pub use btleplug::platform::PeripheralId;
+
+/// GATT service a device advertises to offer Rhiza links
+pub const RHIZA_SERVICE: Uuid = Uuid::from_u128(0x7268_0001_7a61_4d65_8a73_6863_7572_7265);
+
+/// Characteristic the central writes its half of the stream to
+pub const RX_CHARACTERISTIC: Uuid = Uuid::from_u128(0x7268_0002_7a61_4d65_8a73_6863_7572_7265);
+
+/// Characteristic the peripheral notifies its half of the stream on
+pub const TX_CHARACTERISTIC: Uuid = Uuid::from_u128(0x7268_0003_7a61_4d65_8a73_6863_7572_7265);
+
+/// Largest value written in one go: the longest an attribute can hold. The
+/// stack splits it into long writes when the negotiated MTU is smaller.
+pub const MAX_WRITE: usize = 512;
+
+/// Bytes buffered each way between a link and the session reading it
+const LINK_BUFFER: usize = 64 * 1024;
+
+/// Central side of Bluetooth LE links to nearby Rhiza devices
+///
+/// A link is an ordered byte stream: the central's bytes are cut into
+/// writes to [`RX_CHARACTERISTIC`] and the peripheral's arrive as
+/// notifications on [`TX_CHARACTERISTIC`]. The node runs the same Noise
+/// handshake and frames over it as over TCP, so chunks carry parts of
+/// encrypted gossip frames and nothing else about the link is trusted.
+///
+/// btleplug only implements the central role, so the node scans and
+/// connects; the peripherals it finds (phone wallets, mostly) are the ones
+/// advertising [`RHIZA_SERVICE`].
+pub struct BluetoothTransport {
+    adapter: Adapter,
+}
+
+/// An open link to a nearby device
+pub struct BleLink {
+    /// Bluetooth device address of the peripheral
+    pub address: String,
+    /// Both directions of the link
+    pub stream: DuplexStream,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BluetoothError {
+    #[error("bluetooth: {0}")]
+    Ble(#[from] btleplug::Error),
+    #[error("no bluetooth adapter found")]
+    NoAdapter,
+    #[error("{0} doesn't offer the Rhiza service")]
+    NotRhiza(String),
+}
+
+impl BluetoothTransport {
+    /// Use the first Bluetooth adapter of the system
+    pub async fn new() -> Result<Self, BluetoothError> {
+        let manager = Manager::new().await?;
+        let adapter = manager
+            .adapters()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(BluetoothError::NoAdapter)?;
+        Ok(BluetoothTransport { adapter })
+    }
+
+    /// Start scanning, yielding devices as they are seen advertising
+    /// [`RHIZA_SERVICE`]
+    pub async fn discover(&self) -> Result<Pin<Box<dyn Stream<Item = PeripheralId> + Send>>, BluetoothError> {
+        let events = self.adapter.events().await?;
+        self.adapter
+            .start_scan(ScanFilter {
+                services: vec![RHIZA_SERVICE],
+            })
+            .await?;
+        Ok(Box::pin(events.filter_map(|event| async move {
+            match event {
+                CentralEvent::DeviceDiscovered(id) => Some(id),
+                CentralEvent::ServicesAdvertisement { id, services } if services.contains(&RHIZA_SERVICE) => {
+                    Some(id)
+                }
+                _ => None,
+            }
+        })))
+    }
+
+    /// Devices seen advertising [`RHIZA_SERVICE`] so far, whether or not
+    /// they were reported when first found
+    pub async fn nearby(&self) -> Result<Vec<PeripheralId>, BluetoothError> {
+        let mut found = Vec::new();
+        for peripheral in self.adapter.peripherals().await? {
+            let properties = peripheral.properties().await?;
+            if properties.is_some_and(|p| p.services.contains(&RHIZA_SERVICE)) {
+                found.push(peripheral.id());
+            }
+        }
+        Ok(found)
+    }
+
+    /// Connect to a device and open a link over its Rhiza service
+    pub async fn connect(&self, id: &PeripheralId) -> Result<BleLink, BluetoothError> {
+        let peripheral = self.adapter.peripheral(id).await?;
+        let address = peripheral.address().to_string();
+        if !peripheral.is_connected().await? {
+            peripheral.connect().await?;
+        }
+        peripheral.discover_services().await?;
+
+        let characteristics = peripheral.characteristics();
+        let find = |uuid| {
+            characteristics
+                .iter()
+                .find(|c| c.service_uuid == RHIZA_SERVICE && c.uuid == uuid)
+        };
+        let (Some(rx), Some(tx)) = (find(RX_CHARACTERISTIC), find(TX_CHARACTERISTIC)) else {
+            let _ = peripheral.disconnect().await;
+            return Err(BluetoothError::NotRhiza(address));
+        };
+        let rx = rx.clone();
+        peripheral.subscribe(tx).await?;
+        let notifications = peripheral.notifications().await?;
+
+        let (stream, link) = tokio::io::duplex(LINK_BUFFER);
+        tokio::spawn(pump(peripheral, rx, notifications, link, address.clone()));
+        Ok(BleLink { address, stream })
+    }
+}
+
+/// Move bytes between the GATT link and the node's end of the stream until
+/// either side closes, then disconnect
+async fn pump(
+    peripheral: Peripheral,
+    rx: Characteristic,
+    mut notifications: Pin<Box<dyn Stream<Item = ValueNotification> + Send>>,
+    link: DuplexStream,
+    address: String,
+) {
+    let (mut reader, mut writer) = tokio::io::split(link);
+    let mut buf = vec![0u8; MAX_WRITE];
+    loop {
+        tokio::select! {
+            notification = notifications.next() => match notification {
+                Some(notification) if notification.uuid == TX_CHARACTERISTIC => {
+                    if writer.write_all(&notification.value).await.is_err() {
+                        break;
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            },
+            read = reader.read(&mut buf) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if let Err(e) = peripheral.write(&rx, &buf[..n], WriteType::WithResponse).await {
+                        debug!("Write to {} failed: {}", address, e);
+                        break;
+                    }
+                }
+            },
+        }
+    }
+    let _ = peripheral.disconnect().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_uuids() {
+        let uuids = [RHIZA_SERVICE, RX_CHARACTERISTIC, TX_CHARACTERISTIC];
+        for (i, a) in uuids.iter().enumerate() {
+            // One base, told apart by the first group
+            assert_eq!(a.as_fields().1, 0x7a61);
+            assert!(uuids[i + 1..].iter().all(|b| a != b));
+        }
+        assert_eq!(RHIZA_SERVICE.to_string(), "72680001-7a61-4d65-8a73-686375727265");
+    }
+}
@@ -0,0 +1,302 @@
+use crate::network::mesh::TransportType;
+use crate::network::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+/// Network group an address belongs to
+///
+/// Addresses someone can get in bulk tend to share a prefix, so inbound
+/// connections are capped per group rather than per address: IPv4 groups by
+/// /16 and IPv6 by /32. Loopback, private and link-local addresses all fall
+/// in the local group, which isn't capped so LAN meshes keep working.
+/// Peers reached over a short-range radio have no address worth grouping
+/// (one device can claim any number), so they share one capped group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NetGroup {
+    Ipv4([u8; 2]),
+    Ipv6([u8; 4]),
+    Local,
+    Radio,
+}
+
+impl NetGroup {
+    pub fn of(ip: IpAddr) -> Self {
+        match ip.to_canonical() {
+            IpAddr::V4(v4) => {
+                if v4.is_loopback() || v4.is_private() || v4.is_link_local() || v4.is_unspecified() {
+                    NetGroup::Local
+                } else {
+                    let [a, b, _, _] = v4.octets();
+                    NetGroup::Ipv4([a, b])
+                }
+            }
+            IpAddr::V6(v6) => {
+                if v6.is_loopback() || v6.is_unique_local() || v6.is_unicast_link_local() || v6.is_unspecified() {
+                    NetGroup::Local
+                } else {
+                    let [a, b, c, d, ..] = v6.octets();
+                    NetGroup::Ipv6([a, b, c, d])
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for NetGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetGroup::Ipv4([a, b]) => write!(f, "{}.{}.0.0/16", a, b),
+            NetGroup::Ipv6([a, b, c, d]) => {
+                write!(f, "{:x}:{:x}::/32", u16::from_be_bytes([*a, *b]), u16::from_be_bytes([*c, *d]))
+            }
+            NetGroup::Local => f.write_str("local"),
+            NetGroup::Radio => f.write_str("radio"),
+        }
+    }
+}
+
+/// Bucket a connection is counted in: its transport and network group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AddressBucket {
+    pub transport: TransportType,
+    pub group: NetGroup,
+}
+
+impl AddressBucket {
+    pub fn of(transport: TransportType, address: &SocketAddr) -> Self {
+        AddressBucket {
+            transport,
+            group: NetGroup::of(address.ip()),
+        }
+    }
+
+    /// The one bucket of peers reached over a radio `transport`
+    pub fn radio(transport: TransportType) -> Self {
+        AddressBucket {
+            transport,
+            group: NetGroup::Radio,
+        }
+    }
+
+    /// Whether the bucket is exempt from the per-bucket cap
+    pub fn is_local(&self) -> bool {
+        self.group == NetGroup::Local
+    }
+}
+
+impl fmt::Display for AddressBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.transport, self.group)
+    }
+}
+
+/// A connection as the admission policy sees it
+#[derive(Debug, Clone)]
+pub struct PeerSlot {
+    pub id: PeerId,
+    pub bucket: AddressBucket,
+    pub inbound: bool,
+    /// When the connection was registered (unix ms)
+    pub connected_since: u64,
+}
+
+/// Why an inbound peer was turned away
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AdmissionError {
+    #[error("{bucket} already holds {count} inbound peers")]
+    BucketFull { bucket: AddressBucket, count: usize },
+    #[error("all {0} inbound slots are taken")]
+    InboundFull(usize),
+}
+
+/// Who may hold a node's connection slots (`peer_selection` in config.json)
+///
+/// Inbound peers are cheap for an attacker to make, so they are capped per
+/// bucket and may only ever displace other inbound peers. Outbound peers the
+/// node chose itself are never evicted, and the longest-lived of them are
+/// kept as anchors that are redialed first after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerSelection {
+    /// Most inbound connections; once reached, a new inbound peer evicts one
+    pub max_inbound: usize,
+    /// Most inbound connections from one bucket
+    pub max_inbound_per_bucket: usize,
+    /// Outbound connections remembered as anchors across restarts
+    pub anchors: usize,
+    /// How long an outbound connection must last to become an anchor
+    pub anchor_min_age_secs: u64,
+}
+
+impl Default for PeerSelection {
+    fn default() -> Self {
+        PeerSelection {
+            max_inbound: 40,
+            max_inbound_per_bucket: 4,
+            anchors: 2,
+            anchor_min_age_secs: 10 * 60,
+        }
+    }
+}
+
+impl PeerSelection {
+    /// Whether an inbound peer from `bucket` may join the `slots`, and which
+    /// inbound peer it evicts to make room
+    ///
+    /// A full node evicts the newest peer of its most crowded bucket, so a
+    /// flood of connections from one range displaces its own, never the
+    /// long-lived peers from elsewhere or any outbound peer.
+    pub fn admit_inbound(
+        &self,
+        slots: &[PeerSlot],
+        bucket: &AddressBucket,
+    ) -> Result<Option<PeerId>, AdmissionError> {
+        let inbound: Vec<&PeerSlot> = slots.iter().filter(|slot| slot.inbound).collect();
+        if !bucket.is_local() {
+            let count = inbound.iter().filter(|slot| slot.bucket == *bucket).count();
+            if count >= self.max_inbound_per_bucket {
+                return Err(AdmissionError::BucketFull { bucket: *bucket, count });
+            }
+        }
+        if inbound.len() < self.max_inbound {
+            return Ok(None);
+        }
+
+        let mut by_bucket: HashMap<AddressBucket, Vec<&PeerSlot>> = HashMap::new();
+        for slot in inbound {
+            by_bucket.entry(slot.bucket).or_default().push(slot);
+        }
+        by_bucket
+            .into_values()
+            .max_by_key(|peers| (peers.len(), peers.iter().map(|p| p.connected_since).max()))
+            .and_then(|peers| peers.into_iter().max_by_key(|p| p.connected_since))
+            .map(|slot| Some(slot.id.clone()))
+            .ok_or(AdmissionError::InboundFull(self.max_inbound))
+    }
+
+    /// Outbound peers to keep as anchors: the longest-connected ones that
+    /// have lasted `anchor_min_age_secs`, at most one per bucket
+    pub fn anchors(&self, slots: &[PeerSlot], now_ms: u64) -> Vec<PeerId> {
+        let min_age_ms = self.anchor_min_age_secs.saturating_mul(1_000);
+        let mut candidates: Vec<&PeerSlot> = slots
+            .iter()
+            .filter(|slot| !slot.inbound && now_ms.saturating_sub(slot.connected_since) >= min_age_ms)
+            .collect();
+        candidates.sort_by_key(|slot| slot.connected_since);
+
+        let mut anchors = Vec::new();
+        let mut buckets = Vec::new();
+        for slot in candidates {
+            if anchors.len() == self.anchors {
+                break;
+            }
+            if slot.bucket.is_local() || !buckets.contains(&slot.bucket) {
+                buckets.push(slot.bucket);
+                anchors.push(slot.id.clone());
+            }
+        }
+        anchors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn slot(address: &str, inbound: bool, connected_since: u64) -> PeerSlot {
+        PeerSlot {
+            id: PeerId::new(KeyPair::generate().public_key),
+            bucket: bucket(address),
+            inbound,
+            connected_since,
+        }
+    }
+
+    fn bucket(address: &str) -> AddressBucket {
+        AddressBucket::of(TransportType::Tcp, &address.parse().unwrap())
+    }
+
+    #[test]
+    fn test_groups_by_prefix() {
+        assert_eq!(bucket("203.0.113.7:7470"), bucket("203.0.200.1:1"));
+        assert_ne!(bucket("203.0.113.7:7470"), bucket("203.1.113.7:7470"));
+        assert_eq!(bucket("[2001:db8:1::1]:7470"), bucket("[2001:db8:ffff::2]:7470"));
+        assert_eq!(bucket("[::ffff:203.0.113.7]:7470"), bucket("203.0.1.1:7470"));
+        assert!(bucket("127.0.0.1:7470").is_local());
+        assert!(bucket("192.168.1.20:7470").is_local());
+        assert_eq!(bucket("203.0.113.7:7470").to_string(), "tcp 203.0.0.0/16");
+        assert_eq!(bucket("[2001:db8::1]:7470").to_string(), "tcp 2001:db8::/32");
+        assert_ne!(
+            AddressBucket::of(TransportType::LoRa, &"203.0.113.7:7470".parse().unwrap()),
+            bucket("203.0.113.7:7470")
+        );
+        let radio = AddressBucket::radio(TransportType::Bluetooth);
+        assert!(!radio.is_local());
+        assert_eq!(radio.to_string(), "bluetooth radio");
+    }
+
+    #[test]
+    fn test_caps_inbound_per_bucket() {
+        let selection = PeerSelection::default();
+        let slots: Vec<PeerSlot> = (0..4).map(|i| slot(&format!("198.51.{}.1:7470", i), true, i)).collect();
+        assert!(matches!(
+            selection.admit_inbound(&slots, &bucket("198.51.9.9:7470")),
+            Err(AdmissionError::BucketFull { count: 4, .. })
+        ));
+        assert_eq!(selection.admit_inbound(&slots, &bucket("198.52.0.1:7470")), Ok(None));
+        // Local peers aren't bucketed
+        let local: Vec<PeerSlot> = (0..10).map(|i| slot("127.0.0.1:7470", true, i)).collect();
+        assert_eq!(selection.admit_inbound(&local, &bucket("127.0.0.1:7470")), Ok(None));
+    }
+
+    #[test]
+    fn test_full_node_evicts_newest_of_crowded_bucket() {
+        let selection = PeerSelection {
+            max_inbound: 4,
+            ..PeerSelection::default()
+        };
+        let mut slots = vec![
+            slot("192.0.2.1:7470", true, 10),
+            slot("198.51.0.1:7470", true, 20),
+            slot("198.51.0.2:7470", true, 30),
+            slot("198.51.0.3:7470", true, 5),
+            // Outbound peers are never candidates, however new
+            slot("198.51.0.4:7470", false, 99),
+        ];
+        let evicted = selection.admit_inbound(&slots, &bucket("203.0.113.1:7470")).unwrap();
+        assert_eq!(evicted, Some(slots[2].id.clone()));
+
+        let outbound_only: Vec<PeerSlot> = slots.drain(4..).collect();
+        let none_inbound = PeerSelection {
+            max_inbound: 0,
+            ..selection
+        };
+        assert_eq!(
+            none_inbound.admit_inbound(&outbound_only, &bucket("203.0.113.1:7470")),
+            Err(AdmissionError::InboundFull(0))
+        );
+    }
+
+    #[test]
+    fn test_anchors_are_old_outbound_peers_from_distinct_buckets() {
+        let selection = PeerSelection::default();
+        let now = 1_000_000_000;
+        let slots = vec![
+            slot("198.51.0.1:7470", false, 1_000),
+            slot("198.51.0.2:7470", false, 500),
+            slot("192.0.2.1:7470", false, 2_000),
+            slot("203.0.113.1:7470", true, 0),
+            // Too young
+            slot("203.0.114.1:7470", false, now - 1_000),
+        ];
+        assert_eq!(selection.anchors(&slots, now), vec![slots[1].id.clone(), slots[2].id.clone()]);
+        let one = PeerSelection {
+            anchors: 1,
+            ..selection
+        };
+        assert_eq!(one.anchors(&slots, now), vec![slots[1].id.clone()]);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::crypto::Hash;
+use crate::dag::transaction::Transaction;
+use crate::network::peer::PeerId;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most stem transactions held under embargo at once
+const MAX_EMBARGOED: usize = 10_000;
+
+/// Dandelion settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DandelionConfig {
+    /// Send our own transactions down a stem first; when off they are
+    /// flooded straight away
+    pub enabled: bool,
+    /// Chance a node relaying a stem transaction ends the stem and floods it
+    pub fluff_probability: f64,
+    /// How long a stem transaction may go without showing up as a flood
+    /// before the node holding it floods it itself
+    pub embargo_ms: u64,
+    /// How long the same stem successor is kept before picking a new one
+    pub epoch_ms: u64,
+}
+
+impl Default for DandelionConfig {
+    fn default() -> Self {
+        DandelionConfig {
+            enabled: true,
+            // Stems average ten hops
+            fluff_probability: 0.1,
+            embargo_ms: 30_000,
+            epoch_ms: 10 * 60 * 1_000,
+        }
+    }
+}
+
+/// Where a stem transaction goes next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// Pass it on to this one peer
+    Stem(PeerId),
+    /// Flood it to every peer
+    Fluff,
+}
+
+/// Dandelion-style propagation of new transactions
+///
+/// Instead of flooding a new transaction from the node that made it, where
+/// a well-connected observer sees it arrive first, it is passed along a
+/// stem: each node forwards it to a single successor and, with
+/// [`DandelionConfig::fluff_probability`], ends the stem by flooding it. By
+/// the time the transaction spreads it has moved a few hops from its
+/// origin. The successor stays the same for an epoch, so an observer can't
+/// learn much by watching many transactions from one node.
+///
+/// Every node on the stem holds the transaction under an embargo; if it
+/// hasn't come back as a flood when the embargo runs out (a stem node
+/// dropped it, say), the node floods it itself. A transaction that loops
+/// back to a node already holding it is flooded at once.
+#[derive(Debug)]
+pub struct Dandelion {
+    config: DandelionConfig,
+    successor: Option<(PeerId, u64)>,
+    embargoed: HashMap<Hash, (Transaction, u64)>,
+}
+
+impl Dandelion {
+    pub fn new(config: DandelionConfig) -> Self {
+        Dandelion {
+            config,
+            successor: None,
+            embargoed: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &DandelionConfig {
+        &self.config
+    }
+
+    /// Decide where a stem transaction goes next among the connected `peers`
+    ///
+    /// The node that made the transaction always stems it; relaying nodes
+    /// fluff it with the configured probability.
+    pub fn route<R: Rng + ?Sized>(&mut self, origin: bool, peers: &[PeerId], now_ms: u64, rng: &mut R) -> Route {
+        if !self.config.enabled || peers.is_empty() {
+            return Route::Fluff;
+        }
+        if !origin && rng.gen_bool(self.config.fluff_probability.clamp(0.0, 1.0)) {
+            return Route::Fluff;
+        }
+
+        let current = self.successor.as_ref().filter(|(peer, chosen)| {
+            peers.contains(peer) && now_ms.saturating_sub(*chosen) < self.config.epoch_ms
+        });
+        let peer = match current {
+            Some((peer, _)) => peer.clone(),
+            None => {
+                let peer = peers[rng.gen_range(0..peers.len())].clone();
+                self.successor = Some((peer.clone(), now_ms));
+                peer
+            }
+        };
+        Route::Stem(peer)
+    }
+
+    /// Hold a stem transaction until it's seen flooding or the embargo ends
+    ///
+    /// Returns false if it was already held (it looped back) or too many are.
+    pub fn embargo(&mut self, tx: Transaction, now_ms: u64) -> bool {
+        if self.embargoed.contains_key(&tx.id) || self.embargoed.len() >= MAX_EMBARGOED {
+            return false;
+        }
+        self.embargoed.insert(tx.id, (tx, now_ms + self.config.embargo_ms));
+        true
+    }
+
+    /// Whether a transaction is held under embargo
+    pub fn is_embargoed(&self, id: &Hash) -> bool {
+        self.embargoed.contains_key(id)
+    }
+
+    /// A transaction was seen flooding, so its embargo is over; returns
+    /// whether it was held
+    pub fn fluffed(&mut self, id: &Hash) -> bool {
+        self.embargoed.remove(id).is_some()
+    }
+
+    /// Take the transactions whose embargo ran out, to be flooded
+    pub fn expired(&mut self, now_ms: u64) -> Vec<Transaction> {
+        let ids: Vec<Hash> = self
+            .embargoed
+            .iter()
+            .filter(|(_, (_, until))| *until <= now_ms)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.iter()
+            .filter_map(|id| self.embargoed.remove(id).map(|(tx, _)| tx))
+            .collect()
+    }
+}
+
+impl Default for Dandelion {
+    fn default() -> Self {
+        Self::new(DandelionConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn peer() -> PeerId {
+        PeerId::new(KeyPair::generate().public_key)
+    }
+
+    #[test]
+    fn test_origin_always_stems_to_one_successor() {
+        let mut dandelion = Dandelion::default();
+        let peers = vec![peer(), peer(), peer()];
+        let mut rng = crate::testkit::seeded_rng();
+
+        let Route::Stem(first) = dandelion.route(true, &peers, 0, &mut rng) else {
+            panic!("origin fluffed its own transaction");
+        };
+        for now in 1..50 {
+            assert_eq!(dandelion.route(true, &peers, now, &mut rng), Route::Stem(first.clone()));
+        }
+
+        // A successor that disconnected is replaced
+        let others: Vec<PeerId> = peers.iter().filter(|p| **p != first).cloned().collect();
+        let Route::Stem(next) = dandelion.route(true, &others, 100, &mut rng) else {
+            panic!("origin fluffed its own transaction");
+        };
+        assert_ne!(next, first);
+    }
+
+    #[test]
+    fn test_relays_fluff_with_probability() {
+        let peers = vec![peer()];
+        let mut rng = crate::testkit::seeded_rng();
+
+        let always = DandelionConfig { fluff_probability: 1.0, ..Default::default() };
+        assert_eq!(Dandelion::new(always).route(false, &peers, 0, &mut rng), Route::Fluff);
+        let never = DandelionConfig { fluff_probability: 0.0, ..Default::default() };
+        assert!(matches!(Dandelion::new(never).route(false, &peers, 0, &mut rng), Route::Stem(_)));
+
+        let off = DandelionConfig { enabled: false, ..Default::default() };
+        assert_eq!(Dandelion::new(off).route(true, &peers, 0, &mut rng), Route::Fluff);
+        assert_eq!(Dandelion::default().route(true, &[], 0, &mut rng), Route::Fluff);
+    }
+
+    #[test]
+    fn test_embargo() {
+        let kp = KeyPair::generate();
+        let tx = Transaction::genesis(&kp);
+        let mut dandelion = Dandelion::default();
+        let embargo_ms = dandelion.config().embargo_ms;
+
+        assert!(dandelion.embargo(tx.clone(), 0));
+        // Looped back
+        assert!(!dandelion.embargo(tx.clone(), 10));
+        assert!(dandelion.expired(embargo_ms - 1).is_empty());
+        assert_eq!(dandelion.expired(embargo_ms)[0].id, tx.id);
+        assert!(!dandelion.is_embargoed(&tx.id));
+
+        dandelion.embargo(tx.clone(), 0);
+        assert!(dandelion.fluffed(&tx.id));
+        assert!(!dandelion.fluffed(&tx.id));
+        assert!(dandelion.expired(embargo_ms).is_empty());
+    }
+}
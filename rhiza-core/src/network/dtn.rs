@@ -0,0 +1,318 @@
+use crate::network::gossip::GossipMessage;
+use crate::network::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Store-and-forward settings (`dtn` in config.json)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DtnConfig {
+    pub enabled: bool,
+    /// How long a message is held for a peer, and how long a peer that
+    /// stays away is remembered at all
+    pub ttl_ms: u64,
+    /// Most messages held for one peer; the oldest go first
+    pub max_messages_per_peer: usize,
+    /// Most encoded bytes held for one peer; the oldest go first
+    pub max_bytes_per_peer: usize,
+    /// Most absent peers held for; the one away longest is forgotten first
+    pub max_peers: usize,
+}
+
+impl Default for DtnConfig {
+    fn default() -> Self {
+        DtnConfig {
+            enabled: true,
+            ttl_ms: 24 * 60 * 60 * 1_000,
+            max_messages_per_peer: 1_000,
+            max_bytes_per_peer: 4 * 1024 * 1024,
+            max_peers: 64,
+        }
+    }
+}
+
+/// A gossip message waiting for a peer to come back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldMessage {
+    pub message: GossipMessage,
+    /// When it was held (unix ms)
+    pub held_at: u64,
+    /// Encoded size
+    pub size: usize,
+}
+
+/// What is held for one absent peer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeerHold {
+    /// When the peer went away (unix ms)
+    pub away_since: u64,
+    pub messages: VecDeque<HeldMessage>,
+    pub bytes: usize,
+}
+
+impl PeerHold {
+    fn drop_oldest(&mut self) {
+        if let Some(old) = self.messages.pop_front() {
+            self.bytes -= old.size;
+        }
+    }
+
+    fn expire(&mut self, cutoff: u64) {
+        while self.messages.front().is_some_and(|held| held.held_at < cutoff) {
+            self.drop_oldest();
+        }
+    }
+}
+
+/// Store-and-forward queue for peers that come and go (delay-tolerant
+/// networking)
+///
+/// Mesh peers, phones over Bluetooth especially, are often out of reach
+/// for a while. When a peer disconnects the node starts holding the gossip
+/// it would have flooded to it; when the peer reconnects it gets that
+/// backlog first, oldest first, instead of waiting for a sync to find the
+/// gap. Only gossip worth carrying is held: new transactions and identity
+/// rotations, not stems, sync traffic or keepalives.
+///
+/// Holds are bounded per peer by [`DtnConfig`] and forgotten after
+/// `ttl_ms`, so a peer that never returns costs nothing for long. The
+/// whole queue is serializable, for the node to persist across restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DtnQueue {
+    #[serde(skip)]
+    config: DtnConfig,
+    holds: HashMap<PeerId, PeerHold>,
+    /// Bumped on every change, so a copy on disk can tell it's stale
+    #[serde(skip)]
+    changes: u64,
+}
+
+impl DtnQueue {
+    pub fn new(config: DtnConfig) -> Self {
+        DtnQueue {
+            config,
+            holds: HashMap::new(),
+            changes: 0,
+        }
+    }
+
+    /// Carry on with holds restored from storage, under `config`
+    pub fn with_config(mut self, config: DtnConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &DtnConfig {
+        &self.config
+    }
+
+    /// Whether a message is worth holding for an absent peer
+    pub fn is_held(message: &GossipMessage) -> bool {
+        matches!(message, GossipMessage::NewTransaction(_) | GossipMessage::IdentityRotation(_))
+    }
+
+    /// Start holding gossip for a peer that disconnected
+    pub fn peer_left(&mut self, peer: &PeerId, now_ms: u64) {
+        if !self.config.enabled || self.config.max_peers == 0 {
+            return;
+        }
+        if !self.holds.contains_key(peer) && self.holds.len() >= self.config.max_peers {
+            let longest = self
+                .holds
+                .iter()
+                .min_by_key(|(_, hold)| hold.away_since)
+                .map(|(id, _)| id.clone());
+            if let Some(longest) = longest {
+                self.holds.remove(&longest);
+            }
+        }
+        self.holds.entry(peer.clone()).or_insert_with(|| PeerHold {
+            away_since: now_ms,
+            ..PeerHold::default()
+        });
+        self.changes += 1;
+    }
+
+    /// Hold a message for every absent peer, returning how many it was held for
+    pub fn hold(&mut self, message: &GossipMessage, now_ms: u64) -> usize {
+        if self.holds.is_empty() || !Self::is_held(message) {
+            return 0;
+        }
+        let size = message.to_bytes().len();
+        if size > self.config.max_bytes_per_peer {
+            return 0;
+        }
+        for hold in self.holds.values_mut() {
+            while hold.messages.len() >= self.config.max_messages_per_peer.max(1)
+                || hold.bytes + size > self.config.max_bytes_per_peer
+            {
+                hold.drop_oldest();
+            }
+            hold.messages.push_back(HeldMessage {
+                message: message.clone(),
+                held_at: now_ms,
+                size,
+            });
+            hold.bytes += size;
+        }
+        self.changes += 1;
+        self.holds.len()
+    }
+
+    /// Stop holding for a peer that reconnected, returning what was held
+    /// for it that hasn't expired, oldest first
+    pub fn peer_returned(&mut self, peer: &PeerId, now_ms: u64) -> Vec<HeldMessage> {
+        let Some(mut hold) = self.holds.remove(peer) else {
+            return Vec::new();
+        };
+        self.changes += 1;
+        hold.expire(now_ms.saturating_sub(self.config.ttl_ms));
+        hold.messages.into()
+    }
+
+    /// Drop expired messages, and peers away for longer than the TTL
+    pub fn expire(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.config.ttl_ms);
+        let before = (self.holds.len(), self.len());
+        self.holds.retain(|_, hold| {
+            hold.expire(cutoff);
+            hold.away_since >= cutoff
+        });
+        if (self.holds.len(), self.len()) != before {
+            self.changes += 1;
+        }
+    }
+
+    /// Count of changes so far, to tell whether the queue needs saving again
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    /// Absent peers and what is held for each
+    pub fn holds(&self) -> impl Iterator<Item = (&PeerId, &PeerHold)> {
+        self.holds.iter()
+    }
+
+    /// Number of messages held, over all peers
+    pub fn len(&self) -> usize {
+        self.holds.values().map(|hold| hold.messages.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+
+    fn peer() -> PeerId {
+        PeerId::new(KeyPair::generate().public_key)
+    }
+
+    fn tx_message() -> GossipMessage {
+        GossipMessage::NewTransaction(Transaction::genesis(&KeyPair::generate()))
+    }
+
+    #[test]
+    fn test_holds_for_absent_peers_until_they_return() {
+        let mut queue = DtnQueue::new(DtnConfig::default());
+        let (a, b) = (peer(), peer());
+        // Nobody is away yet
+        assert_eq!(queue.hold(&tx_message(), 0), 0);
+
+        queue.peer_left(&a, 10);
+        queue.peer_left(&b, 20);
+        let second = tx_message();
+        assert_eq!(queue.hold(&second, 30), 2);
+        // Only gossip worth carrying is held
+        assert_eq!(queue.hold(&GossipMessage::Ping { timestamp: 1 }, 30), 0);
+        assert_eq!(queue.hold(&GossipMessage::StemTransaction(Transaction::genesis(&KeyPair::generate())), 30), 0);
+        assert_eq!(queue.len(), 2);
+
+        let held = queue.peer_returned(&a, 40);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].message.to_bytes(), second.to_bytes());
+        assert!(queue.peer_returned(&a, 40).is_empty());
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_caps_drop_the_oldest() {
+        let message = tx_message();
+        let size = message.to_bytes().len();
+        let config = DtnConfig {
+            max_messages_per_peer: 3,
+            max_bytes_per_peer: size * 10,
+            max_peers: 2,
+            ..DtnConfig::default()
+        };
+        let mut queue = DtnQueue::new(config);
+        let a = peer();
+        queue.peer_left(&a, 0);
+        for at in 0..5 {
+            queue.hold(&message, at);
+        }
+        let held = queue.peer_returned(&a, 5);
+        assert_eq!(held.iter().map(|h| h.held_at).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        // By bytes too
+        let mut queue = DtnQueue::new(DtnConfig { max_bytes_per_peer: size * 2, ..config });
+        queue.peer_left(&a, 0);
+        for at in 0..3 {
+            queue.hold(&message, at);
+        }
+        assert_eq!(queue.holds().next().unwrap().1.bytes, size * 2);
+
+        // The peer away longest makes room for a new one
+        let mut queue = DtnQueue::new(config);
+        let (b, c) = (peer(), peer());
+        queue.peer_left(&a, 0);
+        queue.peer_left(&b, 1);
+        queue.peer_left(&c, 2);
+        let absent: Vec<_> = queue.holds().map(|(id, _)| id.clone()).collect();
+        assert_eq!(absent.len(), 2);
+        assert!(!absent.contains(&a));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let config = DtnConfig { ttl_ms: 100, ..DtnConfig::default() };
+        let mut queue = DtnQueue::new(config);
+        let (a, b) = (peer(), peer());
+        queue.peer_left(&a, 0);
+        queue.hold(&tx_message(), 10);
+        queue.hold(&tx_message(), 60);
+        // The first message has expired by the time the peer is back
+        assert_eq!(queue.peer_returned(&a, 150).len(), 1);
+
+        queue.peer_left(&b, 200);
+        queue.hold(&tx_message(), 200);
+        queue.expire(250);
+        assert_eq!(queue.len(), 1);
+        // Away longer than the TTL: forgotten
+        queue.expire(301);
+        assert_eq!(queue.holds().count(), 0);
+
+        // Disabled: nothing is held
+        let mut queue = DtnQueue::new(DtnConfig { enabled: false, ..config });
+        queue.peer_left(&a, 0);
+        assert_eq!(queue.hold(&tx_message(), 0), 0);
+    }
+
+    #[test]
+    fn test_survives_serialization() {
+        let mut queue = DtnQueue::new(DtnConfig::default());
+        let a = peer();
+        queue.peer_left(&a, 0);
+        queue.hold(&tx_message(), 1);
+        let bytes = bincode::serialize(&queue).unwrap();
+        let mut restored = bincode::deserialize::<DtnQueue>(&bytes)
+            .unwrap()
+            .with_config(DtnConfig::default());
+        assert_eq!(restored.peer_returned(&a, 2).len(), 1);
+    }
+}
@@ -1,10 +1,51 @@
-use crate::consensus::relay::RelayProof;
+use crate::consensus::relay::{RelayProof, MAX_RELAY_PROOFS_PER_CLAIM};
+use crate::consensus::voting::Opinion;
+use crate::crypto::multisig::MAX_MULTISIG_MEMBERS;
 use crate::crypto::Hash;
+use crate::dag::channels::{ChannelPayment, SignedChannelState, MAX_ROUTE_HOPS};
 use crate::dag::transaction::Transaction;
+use crate::network::identity::IdentityRotation;
 use serde::{Deserialize, Serialize};
 
+/// Longest memo peers accept by default, in bytes
+pub const DEFAULT_MAX_MEMO_BYTES: usize = 256;
+
+/// Size and shape limits a message from a peer must respect
+///
+/// Checked right after a message is decoded, so oversized or overstuffed
+/// messages are thrown out (and held against the sender) before any of
+/// their contents reach the DAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GossipLimits {
+    /// Largest encrypted frame read off the wire, in bytes
+    pub max_frame_bytes: usize,
+    /// Most transactions in a sync response
+    pub max_sync_transactions: usize,
+    /// Most transaction ids in a sync request, tip announcement or opinion
+    /// query, and most opinions in an answer
+    pub max_ids: usize,
+    /// Longest transaction memo, in bytes
+    pub max_memo_bytes: usize,
+}
+
+impl Default for GossipLimits {
+    fn default() -> Self {
+        GossipLimits {
+            max_frame_bytes: 16 * 1024 * 1024,
+            // Ranged sync pages are soft-capped at 2,000, plus the rest of
+            // the last depth
+            max_sync_transactions: 4_096,
+            max_ids: 1_024,
+            max_memo_bytes: DEFAULT_MAX_MEMO_BYTES,
+        }
+    }
+}
+
 /// Messages exchanged between peers via gossip protocol
 #[derive(Debug, Clone, Serialize, Deserialize)]
+// Messages are moved straight into the outbound queue, so boxing buys nothing
+#[allow(clippy::large_enum_variant)]
 pub enum GossipMessage {
     /// A new transaction to propagate
     NewTransaction(Transaction),
@@ -24,6 +65,24 @@ pub enum GossipMessage {
         transactions: Vec<Transaction>,
     },
 
+    /// Request a slice of the DAG by depth (for bootstrapping from nothing)
+    SyncRangeRequest {
+        /// First depth to include
+        from_depth: u64,
+        /// Last depth to include
+        to_depth: u64,
+        /// Soft cap on the number of transactions in the response
+        limit: u32,
+    },
+
+    /// Response to a ranged sync request, ordered by depth
+    SyncRangeResponse {
+        /// Transactions in ascending depth order
+        transactions: Vec<Transaction>,
+        /// Depth to request next, if the range was truncated by the limit
+        next_depth: Option<u64>,
+    },
+
     /// Announce our tip set (for DAG synchronization)
     TipAnnounce {
         /// Our current tip transaction IDs
@@ -41,6 +100,38 @@ pub enum GossipMessage {
     Pong {
         timestamp: u64,
     },
+
+    /// A node moved to a new identity key
+    IdentityRotation(IdentityRotation),
+
+    /// A new transaction in its stem phase, for one peer to pass on
+    /// before it is flooded (see [`crate::network::dandelion`])
+    StemTransaction(Transaction),
+
+    /// An off-DAG payment over a channel the receiving peer is payee of
+    ChannelPayment(ChannelPayment),
+
+    /// The payee's signature over a channel state it accepted
+    ChannelAck(SignedChannelState),
+
+    /// The payee turned down a channel state
+    ChannelRefused {
+        channel_id: Hash,
+        sequence: u64,
+        reason: String,
+    },
+
+    /// Ask which member of each conflict set the peer prefers (see
+    /// [`crate::consensus::voting`])
+    OpinionQuery {
+        /// A member of each conflict set asked about
+        conflicts: Vec<Hash>,
+    },
+
+    /// Answer to an opinion query, one opinion per conflict asked about
+    OpinionResponse {
+        opinions: Vec<Opinion>,
+    },
 }
 
 impl GossipMessage {
@@ -54,6 +145,78 @@ impl GossipMessage {
         bincode::deserialize(data).map_err(|e| GossipError::DeserializationError(e.to_string()))
     }
 
+    /// Whether this is bulk DAG sync traffic rather than latency-sensitive gossip
+    pub fn is_sync(&self) -> bool {
+        matches!(
+            self,
+            GossipMessage::SyncRequest { .. }
+                | GossipMessage::SyncResponse { .. }
+                | GossipMessage::SyncRangeRequest { .. }
+                | GossipMessage::SyncRangeResponse { .. }
+        )
+    }
+
+    /// What the message offers to go first among queued gossip: a
+    /// transaction's fee plus relay tip, nothing for anything else
+    pub fn priority(&self) -> u128 {
+        match self {
+            GossipMessage::NewTransaction(tx) | GossipMessage::StemTransaction(tx) => tx.data.priority(),
+            _ => 0,
+        }
+    }
+
+    /// Check the message against `limits`
+    pub fn check_limits(&self, limits: &GossipLimits) -> Result<(), GossipError> {
+        let check_count = |what: &'static str, count: usize, max: usize| {
+            if count > max {
+                return Err(GossipError::TooMany { what, count, max });
+            }
+            Ok(())
+        };
+        let check_tx = |tx: &Transaction| {
+            let memo = tx.data.memo.as_ref().map_or(0, |m| m.len());
+            if memo > limits.max_memo_bytes {
+                return Err(GossipError::MemoTooLong { len: memo, max: limits.max_memo_bytes });
+            }
+            check_count("relay proofs", tx.data.relay_proofs.len(), MAX_RELAY_PROOFS_PER_CLAIM)?;
+            if let Some(witness) = &tx.multisig {
+                check_count("multisig members", witness.policy.members.len(), MAX_MULTISIG_MEMBERS)?;
+                check_count("multisig signatures", witness.signatures.len(), MAX_MULTISIG_MEMBERS)?;
+            }
+            Ok(())
+        };
+
+        match self {
+            GossipMessage::NewTransaction(tx) | GossipMessage::StemTransaction(tx) => check_tx(tx),
+            GossipMessage::SyncRequest { missing } => check_count("ids", missing.len(), limits.max_ids),
+            GossipMessage::TipAnnounce { tips, .. } => check_count("tips", tips.len(), limits.max_ids),
+            GossipMessage::OpinionQuery { conflicts } => check_count("conflicts", conflicts.len(), limits.max_ids),
+            GossipMessage::OpinionResponse { opinions } => check_count("opinions", opinions.len(), limits.max_ids),
+            GossipMessage::SyncResponse { transactions }
+            | GossipMessage::SyncRangeResponse { transactions, .. } => {
+                check_count("transactions", transactions.len(), limits.max_sync_transactions)?;
+                transactions.iter().try_for_each(check_tx)
+            }
+            GossipMessage::ChannelPayment(payment) => {
+                check_count("route hops", payment.route.len(), MAX_ROUTE_HOPS)?;
+                if payment.hop >= payment.route.len() {
+                    return Err(GossipError::InvalidMessage);
+                }
+                Ok(())
+            }
+            GossipMessage::ChannelRefused { reason, .. } if reason.len() > limits.max_memo_bytes => {
+                Err(GossipError::MemoTooLong { len: reason.len(), max: limits.max_memo_bytes })
+            }
+            GossipMessage::RelayAnnounce(_)
+            | GossipMessage::ChannelAck(_)
+            | GossipMessage::ChannelRefused { .. }
+            | GossipMessage::SyncRangeRequest { .. }
+            | GossipMessage::Ping { .. }
+            | GossipMessage::Pong { .. }
+            | GossipMessage::IdentityRotation(_) => Ok(()),
+        }
+    }
+
     /// Get a human-readable type name for logging
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -61,9 +224,18 @@ impl GossipMessage {
             GossipMessage::RelayAnnounce(_) => "RelayAnnounce",
             GossipMessage::SyncRequest { .. } => "SyncRequest",
             GossipMessage::SyncResponse { .. } => "SyncResponse",
+            GossipMessage::SyncRangeRequest { .. } => "SyncRangeRequest",
+            GossipMessage::SyncRangeResponse { .. } => "SyncRangeResponse",
             GossipMessage::TipAnnounce { .. } => "TipAnnounce",
             GossipMessage::Ping { .. } => "Ping",
             GossipMessage::Pong { .. } => "Pong",
+            GossipMessage::IdentityRotation(_) => "IdentityRotation",
+            GossipMessage::StemTransaction(_) => "StemTransaction",
+            GossipMessage::ChannelPayment(_) => "ChannelPayment",
+            GossipMessage::ChannelAck(_) => "ChannelAck",
+            GossipMessage::ChannelRefused { .. } => "ChannelRefused",
+            GossipMessage::OpinionQuery { .. } => "OpinionQuery",
+            GossipMessage::OpinionResponse { .. } => "OpinionResponse",
         }
     }
 }
@@ -74,6 +246,10 @@ pub enum GossipError {
     DeserializationError(String),
     #[error("invalid message")]
     InvalidMessage,
+    #[error("{count} {what} in one message, at most {max} allowed")]
+    TooMany { what: &'static str, count: usize, max: usize },
+    #[error("memo of {len} bytes, at most {max} allowed")]
+    MemoTooLong { len: usize, max: usize },
 }
 
 #[cfg(test)]
@@ -103,6 +279,49 @@ mod tests {
         let bytes = msg.to_bytes();
         let decoded = GossipMessage::from_bytes(&bytes).unwrap();
         assert_eq!(decoded.type_name(), "TipAnnounce");
+        assert!(!decoded.is_sync());
+    }
+
+    #[test]
+    fn test_limits() {
+        let kp = KeyPair::generate();
+        let limits = GossipLimits { max_sync_transactions: 2, max_ids: 3, max_memo_bytes: 8, ..Default::default() };
+        let parents = [Hash::digest(b"a"), Hash::digest(b"b")];
+        let tx = |memo: &str| {
+            Transaction::transfer_with_memo(&kp, kp.public_key.clone(), 1, Some(memo.to_string()), parents, 1)
+        };
+
+        assert!(GossipMessage::NewTransaction(tx("12345678")).check_limits(&limits).is_ok());
+        assert!(matches!(
+            GossipMessage::NewTransaction(tx("123456789")).check_limits(&limits),
+            Err(GossipError::MemoTooLong { len: 9, max: 8 })
+        ));
+
+        let tips = GossipMessage::TipAnnounce { tips: vec![parents[0]; 4], depth: 1 };
+        assert!(matches!(tips.check_limits(&limits), Err(GossipError::TooMany { what: "tips", .. })));
+
+        let response = GossipMessage::SyncResponse { transactions: vec![tx("ok"); 3] };
+        assert!(matches!(
+            response.check_limits(&limits),
+            Err(GossipError::TooMany { what: "transactions", count: 3, max: 2 })
+        ));
+        // Every transaction in a response is checked
+        let response = GossipMessage::SyncRangeResponse {
+            transactions: vec![tx("ok"), tx("far too long")],
+            next_depth: None,
+        };
+        assert!(matches!(response.check_limits(&limits), Err(GossipError::MemoTooLong { .. })));
+    }
+
+    #[test]
+    fn test_sync_messages_are_bulk() {
+        let response = GossipMessage::SyncRangeResponse {
+            transactions: Vec::new(),
+            next_depth: None,
+        };
+        assert!(response.is_sync());
+        assert!(GossipMessage::SyncRequest { missing: Vec::new() }.is_sync());
+        assert!(!GossipMessage::Ping { timestamp: 1 }.is_sync());
     }
 
     #[test]
@@ -0,0 +1,113 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator of the signed rotation statement
+const ROTATION_DOMAIN: &[u8] = b"rhiza identity rotation v1";
+
+/// A node's announcement that it moved from one identity key to another
+///
+/// Both keys sign the same statement: the old key vouches for the new one,
+/// and the new key proves it is held by the same operator. Peers that
+/// accept a rotation carry what they know about the old key (its relay
+/// count) over to the new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityRotation {
+    pub old_key: PublicKey,
+    pub new_key: PublicKey,
+    /// When the rotation was made (unix ms)
+    pub timestamp: u64,
+    /// Signature of the statement by the old key
+    pub old_signature: Signature,
+    /// Signature of the statement by the new key
+    pub new_signature: Signature,
+}
+
+impl IdentityRotation {
+    /// Cross-sign a move from `old` to `new`
+    pub fn new(old: &KeyPair, new: &KeyPair) -> Self {
+        let timestamp = chrono::Utc::now().timestamp_millis() as u64;
+        let statement = Self::statement(&old.public_key, &new.public_key, timestamp);
+        IdentityRotation {
+            old_key: old.public_key.clone(),
+            new_key: new.public_key.clone(),
+            timestamp,
+            old_signature: old.sign(&statement),
+            new_signature: new.sign(&statement),
+        }
+    }
+
+    /// Check both signatures
+    pub fn verify(&self) -> Result<(), IdentityError> {
+        if self.old_key == self.new_key {
+            return Err(IdentityError::SameKey);
+        }
+        let statement = Self::statement(&self.old_key, &self.new_key, self.timestamp);
+        if !self.old_key.verify(&statement, &self.old_signature) {
+            return Err(IdentityError::BadOldSignature);
+        }
+        if !self.new_key.verify(&statement, &self.new_signature) {
+            return Err(IdentityError::BadNewSignature);
+        }
+        Ok(())
+    }
+
+    fn statement(old_key: &PublicKey, new_key: &PublicKey, timestamp: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(ROTATION_DOMAIN.len() + 72);
+        data.extend_from_slice(ROTATION_DOMAIN);
+        data.extend_from_slice(old_key.as_bytes());
+        data.extend_from_slice(new_key.as_bytes());
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    #[error("rotation must move to a different key")]
+    SameKey,
+    #[error("old key's signature is invalid")]
+    BadOldSignature,
+    #[error("new key's signature is invalid")]
+    BadNewSignature,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_roundtrip() {
+        let old = KeyPair::generate();
+        let new = KeyPair::generate();
+        let rotation = IdentityRotation::new(&old, &new);
+        assert!(rotation.verify().is_ok());
+
+        let bytes = bincode::serialize(&rotation).unwrap();
+        let decoded: IdentityRotation = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, rotation);
+    }
+
+    #[test]
+    fn test_rotation_needs_both_keys() {
+        let old = KeyPair::generate();
+        let new = KeyPair::generate();
+        let attacker = KeyPair::generate();
+
+        // Claiming someone else's identity without their key
+        let mut hijack = IdentityRotation::new(&attacker, &new);
+        hijack.old_key = old.public_key.clone();
+        assert!(matches!(hijack.verify(), Err(IdentityError::BadOldSignature)));
+
+        // Pointing an identity at a key that didn't sign for it
+        let mut forged = IdentityRotation::new(&old, &new);
+        forged.new_signature = IdentityRotation::new(&old, &attacker).new_signature;
+        assert!(matches!(forged.verify(), Err(IdentityError::BadNewSignature)));
+
+        let mut replayed = IdentityRotation::new(&old, &new);
+        replayed.timestamp += 1;
+        assert!(replayed.verify().is_err());
+
+        assert!(matches!(IdentityRotation::new(&old, &old).verify(), Err(IdentityError::SameKey)));
+    }
+}
@@ -0,0 +1,360 @@
+use crate::crypto::Hash;
+use crate::network::mesh::TransportType;
+use crate::network::peer::PeerId;
+use crate::network::reputation::FULL_RELAY_CREDIT;
+use std::collections::{HashMap, VecDeque};
+
+/// How long the deliveries of a transaction are remembered after its first
+/// copy arrives
+pub const INTAKE_TTL_MS: u64 = 60_000;
+
+/// How long after a transaction is processed later copies still earn credit
+pub const LATE_CREDIT_WINDOW_MS: u64 = 10_000;
+
+/// Later deliverers credited per transaction, each with half the credit of
+/// the one before
+pub const MAX_LATE_CREDITS: u32 = 3;
+
+/// Most transactions tracked at once; the oldest are forgotten first
+pub const MAX_TRACKED: usize = 16_384;
+
+/// What to do with a copy of a transaction a peer delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intake {
+    /// Process this copy
+    Process,
+    /// Another peer's copy is being processed (say, waiting for its
+    /// parents); this delivery is credited once that one lands
+    Pending,
+    /// Already processed; the peer earns `credit` thousandths of a relay
+    Late { credit: u32 },
+    /// The peer already delivered this transaction, over this or another
+    /// transport
+    Repeat,
+}
+
+/// Relay credit owed to a peer once the transaction it delivered landed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryCredit {
+    pub peer: PeerId,
+    pub transport: TransportType,
+    /// Thousandths of a relay: [`FULL_RELAY_CREDIT`] for the first complete
+    /// delivery
+    pub credit: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Delivery {
+    peer: PeerId,
+    transport: TransportType,
+}
+
+#[derive(Debug, Clone)]
+struct Deliveries {
+    first_ms: u64,
+    /// When a copy was processed
+    completed_ms: Option<u64>,
+    /// Peer whose copy is being processed
+    processing: Option<PeerId>,
+    /// Every peer that delivered a copy, in order of arrival
+    deliveries: Vec<Delivery>,
+    /// Late deliveries credited so far
+    late: u32,
+}
+
+impl Deliveries {
+    fn delivered_by(&self, peer: &PeerId) -> bool {
+        self.deliveries.iter().any(|d| &d.peer == peer)
+    }
+
+    /// Credit for the next late delivery, if it comes soon enough to earn any
+    fn late_credit(&mut self, completed_ms: u64, now_ms: u64) -> u32 {
+        if self.late >= MAX_LATE_CREDITS || now_ms.saturating_sub(completed_ms) > LATE_CREDIT_WINDOW_MS {
+            return 0;
+        }
+        self.late += 1;
+        FULL_RELAY_CREDIT >> self.late
+    }
+}
+
+/// De-duplication of transactions at gossip intake, across transports
+///
+/// A transaction often reaches a node several times at once: from
+/// different peers, or from one peer over Bluetooth and TCP both. Only the
+/// first copy is processed; copies arriving while it is in flight (parked
+/// for missing parents, say) wait on it instead of being processed again.
+/// Once a copy lands, the peer that delivered it earns full relay credit,
+/// and peers whose copies came after it earn partial credit, halving with
+/// each, for the first [`MAX_LATE_CREDITS`] within
+/// [`LATE_CREDIT_WINDOW_MS`]. A peer is credited once per transaction
+/// however many transports it used. A copy that fails to process holds
+/// nothing back: the next copy from anyone is processed afresh.
+#[derive(Debug, Default)]
+pub struct GossipIntake {
+    entries: HashMap<Hash, Deliveries>,
+    /// (first arrival, id), oldest first, for expiry
+    order: VecDeque<(u64, Hash)>,
+}
+
+impl GossipIntake {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a copy of transaction `id` from `peer` over `transport`;
+    /// `processed` tells whether the node already holds the transaction
+    pub fn receive(
+        &mut self,
+        id: Hash,
+        peer: &PeerId,
+        transport: TransportType,
+        processed: bool,
+        now_ms: u64,
+    ) -> Intake {
+        self.expire(now_ms);
+        let delivery = Delivery { peer: peer.clone(), transport };
+        let Some(entry) = self.entries.get_mut(&id) else {
+            // It reached us some other way, a sync or our own wallet
+            if processed {
+                return Intake::Late { credit: 0 };
+            }
+            self.entries.insert(
+                id,
+                Deliveries {
+                    first_ms: now_ms,
+                    completed_ms: None,
+                    processing: Some(peer.clone()),
+                    deliveries: vec![delivery],
+                    late: 0,
+                },
+            );
+            self.order.push_back((now_ms, id));
+            while self.entries.len() > MAX_TRACKED {
+                self.forget_oldest();
+            }
+            return Intake::Process;
+        };
+        if entry.delivered_by(peer) {
+            return Intake::Repeat;
+        }
+        entry.deliveries.push(delivery);
+        match entry.completed_ms {
+            Some(completed_ms) => Intake::Late {
+                credit: entry.late_credit(completed_ms, now_ms),
+            },
+            None if processed => {
+                // Landed by another route while the copy was in flight
+                entry.completed_ms = Some(now_ms);
+                entry.processing = None;
+                Intake::Late {
+                    credit: entry.late_credit(now_ms, now_ms),
+                }
+            }
+            None if entry.processing.is_some() => Intake::Pending,
+            None => {
+                entry.processing = Some(peer.clone());
+                Intake::Process
+            }
+        }
+    }
+
+    /// The copy being processed landed: credit its deliverer in full and
+    /// the ones that waited on it in part
+    ///
+    /// Returns nothing for a transaction that isn't tracked or was already
+    /// credited, so a transaction landing twice isn't credited twice.
+    pub fn complete(&mut self, id: &Hash, now_ms: u64) -> Vec<DeliveryCredit> {
+        let Some(entry) = self.entries.get_mut(id) else {
+            return Vec::new();
+        };
+        if entry.completed_ms.is_some() {
+            return Vec::new();
+        }
+        entry.completed_ms = Some(now_ms);
+        let first = entry.processing.take();
+        let mut credits = Vec::new();
+        if let Some(delivery) = entry.deliveries.iter().find(|d| Some(&d.peer) == first.as_ref()) {
+            credits.push(DeliveryCredit {
+                peer: delivery.peer.clone(),
+                transport: delivery.transport,
+                credit: FULL_RELAY_CREDIT,
+            });
+        }
+        let waiting: Vec<Delivery> = entry
+            .deliveries
+            .iter()
+            .filter(|d| Some(&d.peer) != first.as_ref())
+            .cloned()
+            .collect();
+        for delivery in waiting {
+            let credit = entry.late_credit(now_ms, now_ms);
+            if credit > 0 {
+                credits.push(DeliveryCredit {
+                    peer: delivery.peer,
+                    transport: delivery.transport,
+                    credit,
+                });
+            }
+        }
+        credits
+    }
+
+    /// The copy being processed was rejected: forget every delivery, so the
+    /// next copy is processed on its own merits
+    pub fn failed(&mut self, id: &Hash) {
+        if self.entries.get(id).is_some_and(|entry| entry.completed_ms.is_none()) {
+            self.entries.remove(id);
+        }
+    }
+
+    /// Number of transactions tracked
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn expire(&mut self, now_ms: u64) {
+        while self
+            .order
+            .front()
+            .is_some_and(|(first_ms, _)| now_ms.saturating_sub(*first_ms) >= INTAKE_TTL_MS)
+        {
+            self.forget_oldest();
+        }
+    }
+
+    fn forget_oldest(&mut self) {
+        let Some((first_ms, id)) = self.order.pop_front() else {
+            return;
+        };
+        // The id may have been forgotten and tracked again since
+        if self.entries.get(&id).is_some_and(|entry| entry.first_ms == first_ms) {
+            self.entries.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use TransportType::{Bluetooth, Tcp};
+
+    fn peer() -> PeerId {
+        PeerId::new(KeyPair::generate().public_key)
+    }
+
+    fn id(n: u8) -> Hash {
+        Hash::digest(&[n])
+    }
+
+    #[test]
+    fn test_one_peer_over_two_transports_counts_once() {
+        let mut intake = GossipIntake::new();
+        let a = peer();
+        assert_eq!(intake.receive(id(1), &a, Bluetooth, false, 0), Intake::Process);
+        // The TCP copy races in before the Bluetooth one is done
+        assert_eq!(intake.receive(id(1), &a, Tcp, false, 1), Intake::Repeat);
+        let credits = intake.complete(&id(1), 2);
+        assert_eq!(credits.len(), 1);
+        assert_eq!((credits[0].transport, credits[0].credit), (Bluetooth, FULL_RELAY_CREDIT));
+        // And after
+        assert_eq!(intake.receive(id(1), &a, Tcp, true, 3), Intake::Repeat);
+    }
+
+    #[test]
+    fn test_copies_wait_on_the_one_in_flight() {
+        let mut intake = GossipIntake::new();
+        let (a, b, c) = (peer(), peer(), peer());
+        // A's copy is parked for its parents while B's and C's arrive
+        assert_eq!(intake.receive(id(1), &a, Bluetooth, false, 0), Intake::Process);
+        assert_eq!(intake.receive(id(1), &b, Tcp, false, 5), Intake::Pending);
+        assert_eq!(intake.receive(id(1), &c, Tcp, false, 6), Intake::Pending);
+
+        let credits = intake.complete(&id(1), 100);
+        let owed: Vec<(&PeerId, u32)> = credits.iter().map(|c| (&c.peer, c.credit)).collect();
+        assert_eq!(owed, vec![(&a, FULL_RELAY_CREDIT), (&b, FULL_RELAY_CREDIT / 2), (&c, FULL_RELAY_CREDIT / 4)]);
+        // Landing again, through a sync say, credits no one twice
+        assert!(intake.complete(&id(1), 101).is_empty());
+    }
+
+    #[test]
+    fn test_late_credit_halves_and_runs_out() {
+        let mut intake = GossipIntake::new();
+        let first = peer();
+        intake.receive(id(1), &first, Tcp, false, 0);
+        intake.complete(&id(1), 10);
+        let credits: Vec<Intake> = (0..4).map(|i| intake.receive(id(1), &peer(), Tcp, true, 20 + i)).collect();
+        assert_eq!(
+            credits,
+            vec![
+                Intake::Late { credit: 500 },
+                Intake::Late { credit: 250 },
+                Intake::Late { credit: 125 },
+                Intake::Late { credit: 0 },
+            ]
+        );
+
+        // Too late to count for anything
+        intake.receive(id(2), &first, Tcp, false, 0);
+        intake.complete(&id(2), 10);
+        let late = 10 + LATE_CREDIT_WINDOW_MS + 1;
+        assert_eq!(intake.receive(id(2), &peer(), Tcp, true, late), Intake::Late { credit: 0 });
+    }
+
+    #[test]
+    fn test_rejected_copy_holds_nothing_back() {
+        let mut intake = GossipIntake::new();
+        let (a, b, c) = (peer(), peer(), peer());
+        // A's copy is forged, and B's arrived while it was checked
+        intake.receive(id(1), &a, Bluetooth, false, 0);
+        assert_eq!(intake.receive(id(1), &b, Tcp, false, 1), Intake::Pending);
+        intake.failed(&id(1));
+
+        // The next good copy is processed and wins
+        assert_eq!(intake.receive(id(1), &c, Tcp, false, 2), Intake::Process);
+        let credits = intake.complete(&id(1), 3);
+        assert_eq!(credits.len(), 1);
+        assert_eq!((&credits[0].peer, credits[0].credit), (&c, FULL_RELAY_CREDIT));
+        // Failing after it landed forgets nothing
+        intake.failed(&id(1));
+        assert_eq!(intake.receive(id(1), &c, Tcp, true, 4), Intake::Repeat);
+    }
+
+    #[test]
+    fn test_landing_another_way_while_in_flight() {
+        let mut intake = GossipIntake::new();
+        let (a, b) = (peer(), peer());
+        // Held by us already, but never tracked: nothing to credit
+        assert_eq!(intake.receive(id(1), &a, Tcp, true, 0), Intake::Late { credit: 0 });
+        assert!(intake.is_empty());
+
+        // A's copy is parked, then a sync brings the transaction in
+        intake.receive(id(2), &a, Bluetooth, false, 0);
+        assert_eq!(intake.receive(id(2), &b, Tcp, true, 5), Intake::Late { credit: FULL_RELAY_CREDIT / 2 });
+        // A's parked copy landing later credits no one again
+        assert!(intake.complete(&id(2), 6).is_empty());
+    }
+
+    #[test]
+    fn test_expiry_and_capacity() {
+        let mut intake = GossipIntake::new();
+        let a = peer();
+        intake.receive(id(1), &a, Tcp, false, 0);
+        intake.complete(&id(1), 1);
+        assert_eq!(intake.receive(id(1), &a, Tcp, true, INTAKE_TTL_MS - 1), Intake::Repeat);
+        // Forgotten once the TTL is up
+        assert_eq!(intake.receive(id(1), &a, Tcp, true, INTAKE_TTL_MS), Intake::Late { credit: 0 });
+        assert!(intake.is_empty());
+
+        for n in 0..=MAX_TRACKED as u32 {
+            intake.receive(Hash::digest(&n.to_le_bytes()), &a, Tcp, false, INTAKE_TTL_MS);
+        }
+        assert_eq!(intake.len(), MAX_TRACKED);
+        // The oldest went first
+        assert_eq!(intake.receive(Hash::digest(&0u32.to_le_bytes()), &a, Tcp, false, INTAKE_TTL_MS), Intake::Process);
+    }
+}
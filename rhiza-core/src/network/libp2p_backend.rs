@@ -0,0 +1,536 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::PublicKey;
+use crate::network::gossip::{GossipLimits, GossipMessage};
+use crate::network::mesh::MeshConfig;
+use crate::network::peer::{PeerId, PeerInfo, AGENT_VERSION, PROTOCOL_VERSION};
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::ConnectedPoint;
+use libp2p::multiaddr::Protocol;
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
+use lru::LruCache;
+use libp2p::{gossipsub, identify, identity, kad, noise, tcp, yamux, Multiaddr, StreamProtocol, Swarm};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+/// Kademlia protocol of Rhiza's DHT, kept apart from the public IPFS one
+const KAD_PROTOCOL: StreamProtocol = StreamProtocol::new("/rhiza/kad/1.0.0");
+
+/// Protocol carrying messages meant for one peer
+const DIRECT_PROTOCOL: StreamProtocol = StreamProtocol::new("/rhiza/direct/1.0.0");
+
+/// How long a connection with nothing to do is kept open
+const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Flooded messages remembered, so copies queued for other peers (or
+/// passed back on after arriving) aren't handed to gossipsub again
+const SEEN_FLOODS: NonZeroUsize = NonZeroUsize::new(4096).unwrap();
+
+/// Behaviours the swarm runs
+///
+/// Gossipsub floods what every node should see, Kademlia finds peers beyond
+/// the bootstrap ones, identify fills in [`PeerInfo`], and `direct` carries
+/// sync traffic, stems and channel payments to a single peer.
+#[derive(NetworkBehaviour)]
+struct RhizaBehaviour {
+    gossipsub: gossipsub::Behaviour,
+    kad: kad::Behaviour<kad::store::MemoryStore>,
+    identify: identify::Behaviour,
+    direct: request_response::Behaviour<DirectCodec>,
+}
+
+/// What the backend reports to the node
+#[derive(Debug)]
+// Events are handled as soon as they are returned, so boxing buys nothing
+#[allow(clippy::large_enum_variant)]
+pub enum BackendEvent {
+    /// The first connection to a peer opened
+    Connected {
+        peer: PeerId,
+        address: Option<SocketAddr>,
+        inbound: bool,
+    },
+    /// The last connection to a peer closed
+    Disconnected(PeerId),
+    /// A peer on the same network said who it is
+    Identified(PeerInfo),
+    /// A message arrived, flooded or sent to this node alone
+    Message { from: PeerId, message: GossipMessage },
+    /// A peer sent something that isn't a message within the limits
+    Malformed { from: PeerId, reason: String },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("could not set up libp2p: {0}")]
+    Setup(String),
+    #[error("invalid peer address: {0}")]
+    InvalidAddress(String),
+    #[error("could not dial {0}: {1}")]
+    Dial(String, String),
+}
+
+/// libp2p in place of the built-in transport
+///
+/// Peers are the same Ed25519 identities as on the native transport, so
+/// bans, reputation and channels carry over. Messages every node should see
+/// ([`floods`]) go out over gossipsub on a topic per network; the rest go to
+/// one peer over a request-response protocol. Both are bincode-encoded and
+/// checked against the [`GossipLimits`] before they are reported.
+pub struct Libp2pBackend {
+    swarm: Swarm<RhizaBehaviour>,
+    topic: gossipsub::IdentTopic,
+    network_id: u32,
+    limits: GossipLimits,
+    max_peers: usize,
+    /// Connected peers, when they connected (unix ms) and from where
+    connected: HashMap<libp2p::PeerId, (u64, Option<SocketAddr>)>,
+    /// Ids of the flooded messages published or received lately
+    seen: LruCache<gossipsub::MessageId, ()>,
+}
+
+impl Libp2pBackend {
+    /// Start listening on `config.tcp_port` under the node's own key
+    ///
+    /// Needs to run inside a tokio runtime.
+    pub fn new(
+        keypair: &KeyPair,
+        config: &MeshConfig,
+        network_id: u32,
+        limits: GossipLimits,
+    ) -> Result<Self, BackendError> {
+        let setup = |e: &dyn std::fmt::Display| BackendError::Setup(e.to_string());
+        let mut secret = keypair.secret_bytes();
+        let key = identity::Keypair::ed25519_from_bytes(&mut *secret).map_err(|e| setup(&e))?;
+
+        let gossip_config = gossipsub::ConfigBuilder::default()
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            .message_id_fn(|message| message_id(&message.data))
+            .max_transmit_size(limits.max_frame_bytes)
+            .build()
+            .map_err(|e| setup(&e))?;
+        let mut swarm = libp2p::SwarmBuilder::with_existing_identity(key)
+            .with_tokio()
+            .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+            .map_err(|e| setup(&e))?
+            .with_behaviour(|key| {
+                let local = key.public().to_peer_id();
+                let authenticity = gossipsub::MessageAuthenticity::Signed(key.clone());
+                let gossipsub = gossipsub::Behaviour::new(authenticity, gossip_config)?;
+                let mut kad = kad::Behaviour::with_config(
+                    local,
+                    kad::store::MemoryStore::new(local),
+                    kad::Config::new(KAD_PROTOCOL),
+                );
+                // Nodes listen publicly, so every one of them serves the DHT
+                kad.set_mode(Some(kad::Mode::Server));
+                let identify = identify::Behaviour::new(
+                    identify::Config::new(identify_protocol(network_id), key.public())
+                        .with_agent_version(AGENT_VERSION.to_string()),
+                );
+                let direct = request_response::Behaviour::with_codec(
+                    DirectCodec { max_bytes: limits.max_frame_bytes },
+                    [(DIRECT_PROTOCOL, ProtocolSupport::Full)],
+                    request_response::Config::default(),
+                );
+                Ok(RhizaBehaviour { gossipsub, kad, identify, direct })
+            })
+            .map_err(|e| setup(&e))?
+            .with_swarm_config(|c| c.with_idle_connection_timeout(IDLE_CONNECTION_TIMEOUT))
+            .build();
+
+        let topic = gossipsub::IdentTopic::new(format!("rhiza/gossip/{}", network_id));
+        swarm.behaviour_mut().gossipsub.subscribe(&topic).map_err(|e| setup(&e))?;
+        let listen: Multiaddr = format!("/ip4/0.0.0.0/tcp/{}", config.tcp_port)
+            .parse()
+            .map_err(|e| setup(&e))?;
+        swarm.listen_on(listen).map_err(|e| setup(&e))?;
+
+        Ok(Libp2pBackend {
+            swarm,
+            topic,
+            network_id,
+            limits,
+            max_peers: config.max_peers,
+            connected: HashMap::new(),
+            seen: LruCache::new(SEEN_FLOODS),
+        })
+    }
+
+    /// Dial a bootstrap peer, given as `host:port` or a multiaddr
+    pub fn dial(&mut self, address: &str) -> Result<(), BackendError> {
+        let address = parse_address(address)?;
+        self.swarm
+            .dial(address.clone())
+            .map_err(|e| BackendError::Dial(address.to_string(), e.to_string()))
+    }
+
+    /// Look for more peers through the DHT
+    ///
+    /// Does nothing until at least one peer is known.
+    pub fn discover(&mut self) {
+        let _ = self.swarm.behaviour_mut().kad.bootstrap();
+    }
+
+    /// Send `message` on behalf of a connection to `peer`
+    ///
+    /// Messages that flood are published once however many peers they are
+    /// queued for (gossipsub drops the copies), and reach the peers the
+    /// node isn't connected to as well; until the gossipsub mesh has formed
+    /// they go to `peer` directly. False if a direct message can't be sent
+    /// because `peer` isn't a Rhiza identity.
+    pub fn send(&mut self, peer: &PeerId, message: GossipMessage) -> bool {
+        if !floods(&message) {
+            return self.send_direct(peer, message);
+        }
+        let Ok(data) = bincode::serialize(&message) else {
+            return false;
+        };
+        let id = message_id(&data);
+        if self.seen.contains(&id) {
+            return true;
+        }
+        let topic = self.topic.clone();
+        match self.swarm.behaviour_mut().gossipsub.publish(topic, data) {
+            Ok(_) | Err(gossipsub::PublishError::Duplicate) => {
+                self.seen.put(id, ());
+                true
+            }
+            Err(gossipsub::PublishError::InsufficientPeers) => self.send_direct(peer, message),
+            Err(e) => {
+                tracing::debug!("Could not publish {}: {}", message.type_name(), e);
+                false
+            }
+        }
+    }
+
+    /// Send `message` to `peer` alone, whatever it is
+    ///
+    /// For what only concerns that peer, such as the tips announced when a
+    /// connection opens: gossipsub would drop it as a copy of one already
+    /// flooded.
+    pub fn send_direct(&mut self, peer: &PeerId, message: GossipMessage) -> bool {
+        match libp2p_peer(peer) {
+            Some(peer) => {
+                self.swarm.behaviour_mut().direct.send_request(&peer, message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop every connection to `peer`
+    pub fn disconnect(&mut self, peer: &PeerId) {
+        if let Some(peer) = libp2p_peer(peer) {
+            let _ = self.swarm.disconnect_peer_id(peer);
+        }
+    }
+
+    /// Drive the swarm until there is something to report
+    ///
+    /// Cancel-safe: nothing is lost if the future is dropped between events.
+    pub async fn next_event(&mut self) -> BackendEvent {
+        loop {
+            let event = self.swarm.select_next_some().await;
+            if let Some(event) = self.on_swarm_event(event) {
+                return event;
+            }
+        }
+    }
+
+    fn on_swarm_event(&mut self, event: SwarmEvent<RhizaBehaviourEvent>) -> Option<BackendEvent> {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                tracing::info!("🔗 P2P (libp2p) listening on {}", address);
+                None
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, endpoint, num_established, .. } => {
+                let Some(peer) = rhiza_peer(&peer_id) else {
+                    // Rhiza identities are Ed25519 keys
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return None;
+                };
+                if num_established.get() > 1 {
+                    return None;
+                }
+                let address = socket_addr(endpoint.get_remote_address());
+                self.connected.insert(peer_id, (now_ms(), address));
+                Some(BackendEvent::Connected {
+                    peer,
+                    address,
+                    inbound: matches!(endpoint, ConnectedPoint::Listener { .. }),
+                })
+            }
+            SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                if num_established > 0 || self.connected.remove(&peer_id).is_none() {
+                    return None;
+                }
+                rhiza_peer(&peer_id).map(BackendEvent::Disconnected)
+            }
+            SwarmEvent::Behaviour(RhizaBehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => {
+                self.seen.put(message_id, ());
+                // Strict validation means the source signed it
+                let source = message.source.unwrap_or(propagation_source);
+                self.decode(&source, &message.data)
+            }
+            SwarmEvent::Behaviour(RhizaBehaviourEvent::Direct(request_response::Event::Message {
+                peer,
+                message: request_response::Message::Request { request, channel, .. },
+            })) => {
+                let _ = self.swarm.behaviour_mut().direct.send_response(channel, ());
+                let from = rhiza_peer(&peer)?;
+                Some(self.checked(from, request))
+            }
+            SwarmEvent::Behaviour(RhizaBehaviourEvent::Direct(request_response::Event::OutboundFailure {
+                peer,
+                error,
+                ..
+            })) => {
+                tracing::debug!("Direct message to {} failed: {}", peer, error);
+                None
+            }
+            SwarmEvent::Behaviour(RhizaBehaviourEvent::Identify(identify::Event::Received {
+                peer_id,
+                info,
+                ..
+            })) => self.identified(peer_id, info),
+            SwarmEvent::Behaviour(RhizaBehaviourEvent::Kad(kad::Event::RoutingUpdated {
+                peer,
+                is_new_peer: true,
+                ..
+            })) => {
+                if self.connected.len() < self.max_peers && !self.swarm.is_connected(&peer) {
+                    let _ = self.swarm.dial(peer);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Keep a peer on the same network in the DHT and report it; drop one
+    /// on another network
+    fn identified(&mut self, peer_id: libp2p::PeerId, info: identify::Info) -> Option<BackendEvent> {
+        let peer = rhiza_peer(&peer_id)?;
+        let protocol_version = match parse_identify_protocol(&info.protocol_version) {
+            Some((version, network_id)) if network_id == self.network_id => version,
+            _ => {
+                tracing::debug!("Dropping {}: it runs {}", peer, info.protocol_version);
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                return None;
+            }
+        };
+        for address in info.listen_addrs {
+            self.swarm.behaviour_mut().kad.add_address(&peer_id, address);
+        }
+        let (connected_since, address) = self.connected.get(&peer_id).copied().unwrap_or((now_ms(), None));
+        Some(BackendEvent::Identified(PeerInfo {
+            id: peer,
+            address,
+            protocol_version,
+            agent_version: info.agent_version,
+            connected_since,
+            last_seen: now_ms(),
+            messages_relayed: 0,
+        }))
+    }
+
+    fn decode(&self, source: &libp2p::PeerId, data: &[u8]) -> Option<BackendEvent> {
+        let from = rhiza_peer(source)?;
+        Some(match bincode::deserialize::<GossipMessage>(data) {
+            Ok(message) => self.checked(from, message),
+            Err(e) => BackendEvent::Malformed { from, reason: e.to_string() },
+        })
+    }
+
+    fn checked(&self, from: PeerId, message: GossipMessage) -> BackendEvent {
+        match message.check_limits(&self.limits) {
+            Ok(()) => BackendEvent::Message { from, message },
+            Err(e) => BackendEvent::Malformed { from, reason: e.to_string() },
+        }
+    }
+}
+
+/// Gossipsub id of a flooded message: the hash of its bytes, so the same
+/// transaction published by two nodes is one message
+fn message_id(data: &[u8]) -> gossipsub::MessageId {
+    gossipsub::MessageId::new(blake3::hash(data).as_bytes())
+}
+
+/// Whether a message is for every node, and so goes over gossipsub
+pub fn floods(message: &GossipMessage) -> bool {
+    matches!(
+        message,
+        GossipMessage::NewTransaction(_)
+            | GossipMessage::RelayAnnounce(_)
+            | GossipMessage::TipAnnounce { .. }
+            | GossipMessage::IdentityRotation(_)
+    )
+}
+
+/// The Rhiza identity behind a libp2p peer id, if it is an Ed25519 key
+///
+/// Keys that short are inlined in the id rather than hashed, so no
+/// handshake is needed to recover them.
+pub fn rhiza_peer(peer: &libp2p::PeerId) -> Option<PeerId> {
+    let bytes = peer.to_bytes();
+    // Identity multihash of the 36-byte protobuf encoding
+    let encoded = bytes.strip_prefix(&[0x00, 0x24])?;
+    let key = identity::PublicKey::try_decode_protobuf(encoded).ok()?.try_into_ed25519().ok()?;
+    Some(PeerId::new(PublicKey::from_bytes(key.to_bytes())))
+}
+
+/// The libp2p peer id of a Rhiza identity
+pub fn libp2p_peer(peer: &PeerId) -> Option<libp2p::PeerId> {
+    let key = identity::ed25519::PublicKey::try_from_bytes(peer.public_key.as_bytes()).ok()?;
+    Some(identity::PublicKey::from(key).to_peer_id())
+}
+
+/// Identify protocol string, which carries the protocol version and network
+fn identify_protocol(network_id: u32) -> String {
+    format!("/rhiza/{}/{}", PROTOCOL_VERSION, network_id)
+}
+
+fn parse_identify_protocol(protocol: &str) -> Option<(u32, u32)> {
+    let mut parts = protocol.strip_prefix("/rhiza/")?.split('/');
+    let version = parts.next()?.parse().ok()?;
+    let network_id = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some((version, network_id))
+}
+
+/// A bootstrap address as a multiaddr; `host:port` must be an IP address
+fn parse_address(address: &str) -> Result<Multiaddr, BackendError> {
+    if let Ok(socket) = address.parse::<SocketAddr>() {
+        let ip = match socket.ip() {
+            IpAddr::V4(ip) => Protocol::Ip4(ip),
+            IpAddr::V6(ip) => Protocol::Ip6(ip),
+        };
+        return Ok(Multiaddr::empty().with(ip).with(Protocol::Tcp(socket.port())));
+    }
+    address
+        .parse()
+        .map_err(|_| BackendError::InvalidAddress(address.to_string()))
+}
+
+fn socket_addr(address: &Multiaddr) -> Option<SocketAddr> {
+    let mut ip = None;
+    for protocol in address.iter() {
+        match protocol {
+            Protocol::Ip4(v4) => ip = Some(IpAddr::V4(v4)),
+            Protocol::Ip6(v6) => ip = Some(IpAddr::V6(v6)),
+            Protocol::Tcp(port) => return ip.map(|ip| SocketAddr::new(ip, port)),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Bincode-encoded [`GossipMessage`]s, one per stream, acknowledged with
+/// an empty response
+#[derive(Debug, Clone)]
+struct DirectCodec {
+    max_bytes: usize,
+}
+
+#[async_trait]
+impl request_response::Codec for DirectCodec {
+    type Protocol = StreamProtocol;
+    type Request = GossipMessage;
+    type Response = ();
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<GossipMessage>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut data = Vec::new();
+        io.take(self.max_bytes as u64 + 1).read_to_end(&mut data).await?;
+        if data.len() > self.max_bytes {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message over the size limit"));
+        }
+        bincode::deserialize(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, _: &mut T) -> io::Result<()>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Ok(())
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        request: GossipMessage,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let data = bincode::serialize(&request).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        io.write_all(&data).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(&mut self, _: &StreamProtocol, io: &mut T, _: ()) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Hash;
+
+    #[test]
+    fn test_peer_ids_map_both_ways() {
+        let keypair = KeyPair::generate();
+        let peer = PeerId::new(keypair.public_key.clone());
+        let libp2p = libp2p_peer(&peer).unwrap();
+        assert_eq!(rhiza_peer(&libp2p), Some(peer));
+
+        // The swarm's own id is the node key's
+        let mut secret = keypair.secret_bytes();
+        let key = identity::Keypair::ed25519_from_bytes(&mut *secret).unwrap();
+        assert_eq!(key.public().to_peer_id(), libp2p);
+        assert_eq!(rhiza_peer(&libp2p::PeerId::random()), None);
+    }
+
+    #[test]
+    fn test_addresses_and_protocols() {
+        assert_eq!(parse_address("127.0.0.1:7470").unwrap().to_string(), "/ip4/127.0.0.1/tcp/7470");
+        assert_eq!(parse_address("[::1]:7470").unwrap().to_string(), "/ip6/::1/tcp/7470");
+        let dns = "/dns4/seed.example/tcp/7470";
+        assert_eq!(parse_address(dns).unwrap().to_string(), dns);
+        assert!(parse_address("seed.example:7470").is_err());
+        assert_eq!(
+            socket_addr(&parse_address("10.0.0.2:7470").unwrap()),
+            Some("10.0.0.2:7470".parse().unwrap())
+        );
+
+        assert_eq!(parse_identify_protocol(&identify_protocol(3)), Some((PROTOCOL_VERSION, 3)));
+        assert_eq!(parse_identify_protocol("/ipfs/0.1.0"), None);
+        assert_eq!(parse_identify_protocol("/rhiza/1/3/x"), None);
+    }
+
+    #[test]
+    fn test_only_broadcasts_flood() {
+        assert!(floods(&GossipMessage::TipAnnounce { tips: Vec::new(), depth: 0 }));
+        assert!(!floods(&GossipMessage::SyncRequest { missing: vec![Hash::digest(b"a")] }));
+        assert!(!floods(&GossipMessage::Ping { timestamp: 0 }));
+    }
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Mesh transport layer abstraction
 /// Supports multiple transport types for true censorship resistance
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TransportType {
     /// Standard TCP/IP over internet
     Tcp,
@@ -16,6 +17,40 @@ pub enum TransportType {
     Mdns,
 }
 
+impl fmt::Display for TransportType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TransportType::Tcp => "tcp",
+            TransportType::WifiDirect => "wifi-direct",
+            TransportType::Bluetooth => "bluetooth",
+            TransportType::LoRa => "lora",
+            TransportType::Mdns => "mdns",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which stack carries a node's peer-to-peer traffic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkBackend {
+    /// Rhiza's own Noise handshake over TCP
+    #[default]
+    Native,
+    /// Gossipsub, Kademlia and identify over libp2p (needs the `libp2p`
+    /// feature)
+    Libp2p,
+}
+
+impl fmt::Display for NetworkBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NetworkBackend::Native => "native",
+            NetworkBackend::Libp2p => "libp2p",
+        })
+    }
+}
+
 /// Configuration for mesh networking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MeshConfig {
@@ -29,6 +64,9 @@ pub struct MeshConfig {
     pub enable_mdns: bool,
     /// Bootstrap peers (TCP addresses)
     pub bootstrap_peers: Vec<String>,
+    /// Stack the peers are reached over
+    #[serde(default)]
+    pub backend: NetworkBackend,
 }
 
 impl Default for MeshConfig {
@@ -39,6 +77,7 @@ impl Default for MeshConfig {
             tcp_port: 7470, // R=7, H=4, Z=7, 0
             enable_mdns: true,
             bootstrap_peers: Vec::new(),
+            backend: NetworkBackend::Native,
         }
     }
 }
@@ -52,6 +91,7 @@ impl MeshConfig {
             tcp_port: port,
             enable_mdns: true,
             bootstrap_peers: Vec::new(),
+            backend: NetworkBackend::Native,
         }
     }
 }
@@ -66,6 +106,7 @@ mod tests {
         assert_eq!(config.tcp_port, 7470);
         assert!(config.enable_mdns);
         assert_eq!(config.max_peers, 50);
+        assert_eq!(config.backend, NetworkBackend::Native);
     }
 
     #[test]
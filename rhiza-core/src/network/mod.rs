@@ -1,6 +1,27 @@
 pub mod peer;
+pub mod identity;
 pub mod gossip;
 pub mod mesh;
+pub mod transport;
+pub mod sync;
+pub mod reputation;
+pub mod dandelion;
+pub mod buckets;
+pub mod dtn;
+pub mod intake;
+#[cfg(feature = "libp2p")]
+pub mod libp2p_backend;
+#[cfg(feature = "bluetooth")]
+pub mod bluetooth;
 
 pub use peer::PeerId;
-pub use gossip::GossipMessage;
+pub use identity::IdentityRotation;
+pub use gossip::{GossipLimits, GossipMessage};
+pub use transport::{Handshake, SecureSession};
+pub use sync::RangeSync;
+pub use reputation::{Behavior, ReputationTracker};
+pub use dandelion::{Dandelion, DandelionConfig};
+pub use buckets::{AddressBucket, PeerSelection};
+pub use dtn::{DtnConfig, DtnQueue};
+pub use intake::{GossipIntake, Intake};
+pub use mesh::NetworkBackend;
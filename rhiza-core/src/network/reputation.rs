@@ -0,0 +1,342 @@
+use crate::network::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Score at or below which a peer is disconnected and banned
+pub const BAN_THRESHOLD: i64 = -100;
+
+/// Highest score good behavior can build up, so a peer can't bank credit
+/// for misbehaving later
+pub const MAX_SCORE: i64 = 100;
+
+/// Length of a first ban; each repeat ban lasts twice as long as the last
+pub const BAN_DURATION_MS: u64 = 60 * 60 * 1_000;
+
+/// Longest a ban can last
+pub const MAX_BAN_DURATION_MS: u64 = 7 * 24 * 60 * 60 * 1_000;
+
+/// Window the message rate is measured over
+pub const RATE_WINDOW_MS: u64 = 1_000;
+
+/// Messages a peer may send per window before it counts as spam
+pub const RATE_LIMIT: u32 = 500;
+
+/// Relay credit for being first to bring a transaction, in the thousandths
+/// later deliveries are credited in
+pub const FULL_RELAY_CREDIT: u32 = 1_000;
+
+/// Something a peer did that changes its score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// Sent a transaction or identity rotation whose signature doesn't verify
+    InvalidSignature,
+    /// Sent a message that doesn't decode or breaks the protocol's limits
+    MalformedMessage,
+    /// Sent messages faster than [`RATE_LIMIT`] per window
+    Spam,
+    /// Passed on a relay reward claim with a forged proof, costing what the
+    /// sampler that caught it charges for a claim that size
+    ForgedRelayClaim { penalty: i64 },
+    /// Was first to bring us a new, valid transaction
+    UsefulRelay,
+}
+
+impl Behavior {
+    /// Change to the peer's score
+    pub fn delta(self) -> i64 {
+        match self {
+            Behavior::InvalidSignature => -50,
+            Behavior::MalformedMessage => -25,
+            Behavior::Spam => -10,
+            Behavior::ForgedRelayClaim { penalty } => -penalty,
+            Behavior::UsefulRelay => 1,
+        }
+    }
+}
+
+/// A peer's standing and what it has been seen doing
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeerScore {
+    pub score: i64,
+    pub useful_relays: u64,
+    /// Transactions it brought after another peer had, for partial credit
+    pub late_relays: u64,
+    /// Relay credit earned, in thousandths of a relay; each whole relay of
+    /// it adds a point to the score
+    pub relay_credit: u64,
+    pub invalid_signatures: u64,
+    pub malformed_messages: u64,
+    pub spam: u64,
+    pub forged_relay_claims: u64,
+    #[serde(skip)]
+    window_start_ms: u64,
+    #[serde(skip)]
+    window_messages: u32,
+}
+
+/// A peer that isn't allowed to connect until `until_ms`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ban {
+    /// Address it was connected from, banned along with its key
+    pub address: Option<IpAddr>,
+    pub until_ms: u64,
+    pub reason: String,
+    /// How many times this peer has been banned
+    pub count: u32,
+}
+
+/// Scores connected peers and keeps the ban list
+///
+/// Misbehavior costs a peer points and useful relays earn a few back. A
+/// peer whose score sinks to [`BAN_THRESHOLD`] is banned, by key and by the
+/// address it connected from, for [`BAN_DURATION_MS`], doubling with every
+/// repeat. Scores outlive connections, so reconnecting doesn't wipe the
+/// slate, but not the process; bans are meant to be persisted (see
+/// [`ReputationTracker::bans`]).
+#[derive(Debug, Default)]
+pub struct ReputationTracker {
+    scores: HashMap<PeerId, PeerScore>,
+    bans: HashMap<PeerId, Ban>,
+}
+
+impl ReputationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracker starting from a persisted ban list
+    pub fn with_bans(bans: HashMap<PeerId, Ban>) -> Self {
+        ReputationTracker { scores: HashMap::new(), bans }
+    }
+
+    /// Record a peer's behavior, returning its ban if this got it banned
+    pub fn record(
+        &mut self,
+        peer: &PeerId,
+        address: Option<IpAddr>,
+        behavior: Behavior,
+        now_ms: u64,
+    ) -> Option<&Ban> {
+        let score = self.scores.entry(peer.clone()).or_default();
+        match behavior {
+            Behavior::InvalidSignature => score.invalid_signatures += 1,
+            Behavior::MalformedMessage => score.malformed_messages += 1,
+            Behavior::Spam => score.spam += 1,
+            Behavior::ForgedRelayClaim { .. } => score.forged_relay_claims += 1,
+            Behavior::UsefulRelay => {
+                score.useful_relays += 1;
+                score.relay_credit += u64::from(FULL_RELAY_CREDIT);
+            }
+        }
+        score.score = (score.score + behavior.delta()).min(MAX_SCORE);
+        if score.score > BAN_THRESHOLD {
+            return None;
+        }
+        Some(self.ban(peer, address, None, format!("{:?}", behavior), now_ms))
+    }
+
+    /// Credit a peer with a copy of a transaction another peer brought
+    /// first, `credit` in thousandths of a relay; the score goes up once the
+    /// credit adds up to whole relays
+    pub fn credit_late_relay(&mut self, peer: &PeerId, credit: u32) {
+        let score = self.scores.entry(peer.clone()).or_default();
+        let full = u64::from(FULL_RELAY_CREDIT);
+        let before = score.relay_credit / full;
+        score.late_relays += 1;
+        score.relay_credit += u64::from(credit);
+        let gained = (score.relay_credit / full - before) as i64;
+        score.score = (score.score + gained).min(MAX_SCORE);
+    }
+
+    /// Ban a peer for `duration_ms`, or by default for as long as its
+    /// misbehaving would have: [`BAN_DURATION_MS`], doubling with every repeat
+    pub fn ban(
+        &mut self,
+        peer: &PeerId,
+        address: Option<IpAddr>,
+        duration_ms: Option<u64>,
+        reason: String,
+        now_ms: u64,
+    ) -> &Ban {
+        self.scores.remove(peer);
+        let count = self.bans.get(peer).map_or(0, |b| b.count) + 1;
+        let duration = duration_ms.unwrap_or_else(|| {
+            BAN_DURATION_MS
+                .saturating_mul(1 << (count - 1).min(16))
+                .min(MAX_BAN_DURATION_MS)
+        });
+        let ban = Ban {
+            address,
+            until_ms: now_ms.saturating_add(duration),
+            reason,
+            count,
+        };
+        self.bans.insert(peer.clone(), ban);
+        &self.bans[peer]
+    }
+
+    /// Count a message from a peer, recording spam once it exceeds the rate
+    /// limit; returns its ban if this got it banned
+    pub fn message_received(&mut self, peer: &PeerId, address: Option<IpAddr>, now_ms: u64) -> Option<&Ban> {
+        let score = self.scores.entry(peer.clone()).or_default();
+        if now_ms.saturating_sub(score.window_start_ms) >= RATE_WINDOW_MS {
+            score.window_start_ms = now_ms;
+            score.window_messages = 0;
+        }
+        score.window_messages += 1;
+        if score.window_messages <= RATE_LIMIT {
+            return None;
+        }
+        self.record(peer, address, Behavior::Spam, now_ms)
+    }
+
+    /// A connected peer's score
+    pub fn score(&self, peer: &PeerId) -> Option<&PeerScore> {
+        self.scores.get(peer)
+    }
+
+    /// The ban keeping this peer out, if any
+    pub fn ban_for(&self, peer: &PeerId, now_ms: u64) -> Option<&Ban> {
+        self.bans.get(peer).filter(|b| b.until_ms > now_ms)
+    }
+
+    /// Whether an address belongs to a banned peer
+    pub fn is_address_banned(&self, address: IpAddr, now_ms: u64) -> bool {
+        self.bans
+            .values()
+            .any(|b| b.address == Some(address) && b.until_ms > now_ms)
+    }
+
+    /// Every ban, including expired ones (which still lengthen a repeat ban)
+    pub fn bans(&self) -> &HashMap<PeerId, Ban> {
+        &self.bans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn peer() -> PeerId {
+        PeerId::new(KeyPair::generate().public_key)
+    }
+
+    #[test]
+    fn test_misbehavior_leads_to_ban() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+
+        assert!(tracker.record(&peer, Some(ip), Behavior::InvalidSignature, 0).is_none());
+        assert_eq!(tracker.score(&peer).unwrap().score, -50);
+        let ban = tracker.record(&peer, Some(ip), Behavior::InvalidSignature, 0).unwrap().clone();
+        assert_eq!(ban.until_ms, BAN_DURATION_MS);
+        assert_eq!(ban.count, 1);
+
+        assert!(tracker.ban_for(&peer, 1).is_some());
+        assert!(tracker.is_address_banned(ip, 1));
+        assert!(tracker.ban_for(&peer, BAN_DURATION_MS).is_none());
+        assert!(!tracker.is_address_banned(ip, BAN_DURATION_MS));
+    }
+
+    #[test]
+    fn test_repeat_bans_get_longer() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        for _ in 0..4 {
+            tracker.record(&peer, None, Behavior::MalformedMessage, 0);
+        }
+        assert_eq!(tracker.ban_for(&peer, 0).unwrap().until_ms, BAN_DURATION_MS);
+
+        let later = 2 * BAN_DURATION_MS;
+        for _ in 0..4 {
+            tracker.record(&peer, None, Behavior::MalformedMessage, later);
+        }
+        let ban = tracker.ban_for(&peer, later).unwrap();
+        assert_eq!(ban.count, 2);
+        assert_eq!(ban.until_ms, later + 2 * BAN_DURATION_MS);
+    }
+
+    #[test]
+    fn test_useful_relays_are_capped() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        for _ in 0..1_000 {
+            tracker.record(&peer, None, Behavior::UsefulRelay, 0);
+        }
+        let score = tracker.score(&peer).unwrap();
+        assert_eq!(score.score, MAX_SCORE);
+        assert_eq!(score.useful_relays, 1_000);
+
+        // Banked credit only goes so far
+        for _ in 0..4 {
+            tracker.record(&peer, None, Behavior::InvalidSignature, 0);
+        }
+        assert!(tracker.ban_for(&peer, 0).is_some());
+    }
+
+    #[test]
+    fn test_late_relays_add_up() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        tracker.credit_late_relay(&peer, FULL_RELAY_CREDIT / 2);
+        assert_eq!(tracker.score(&peer).unwrap().score, 0);
+        tracker.credit_late_relay(&peer, FULL_RELAY_CREDIT / 4);
+        tracker.credit_late_relay(&peer, FULL_RELAY_CREDIT / 4);
+        let score = tracker.score(&peer).unwrap();
+        assert_eq!((score.score, score.late_relays, score.useful_relays), (1, 3, 0));
+
+        // On top of first deliveries
+        tracker.record(&peer, None, Behavior::UsefulRelay, 0);
+        tracker.credit_late_relay(&peer, FULL_RELAY_CREDIT / 2);
+        let score = tracker.score(&peer).unwrap();
+        assert_eq!((score.score, score.relay_credit), (2, 2_500));
+    }
+
+    #[test]
+    fn test_forged_relay_claim() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        assert!(tracker.record(&peer, None, Behavior::ForgedRelayClaim { penalty: 40 }, 0).is_none());
+        assert_eq!(tracker.score(&peer).unwrap().score, -40);
+
+        // A big enough forged claim gets a peer banned outright
+        let ban = tracker.record(&peer, None, Behavior::ForgedRelayClaim { penalty: 1_000 }, 0);
+        assert!(ban.is_some());
+    }
+
+    #[test]
+    fn test_manual_ban() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        tracker.record(&peer, None, Behavior::Spam, 0);
+        let ban = tracker.ban(&peer, None, Some(1_000), "operator".to_string(), 10).clone();
+        assert_eq!(ban.until_ms, 1_010);
+        assert_eq!(ban.reason, "operator");
+        assert!(tracker.score(&peer).is_none());
+
+        // Without a duration it counts as the next repeat ban
+        let ban = tracker.ban(&peer, None, None, "operator".to_string(), 2_000);
+        assert_eq!(ban.count, 2);
+        assert_eq!(ban.until_ms, 2_000 + 2 * BAN_DURATION_MS);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut tracker = ReputationTracker::new();
+        let peer = peer();
+        for _ in 0..RATE_LIMIT {
+            assert!(tracker.message_received(&peer, None, 10).is_none());
+        }
+        assert_eq!(tracker.score(&peer).unwrap().spam, 0);
+
+        tracker.message_received(&peer, None, 10);
+        assert_eq!(tracker.score(&peer).unwrap().spam, 1);
+
+        // A new window starts clean
+        tracker.message_received(&peer, None, 10 + RATE_WINDOW_MS);
+        assert_eq!(tracker.score(&peer).unwrap().spam, 1);
+    }
+}
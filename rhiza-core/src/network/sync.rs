@@ -0,0 +1,217 @@
+use crate::dag::transaction::Transaction;
+use crate::network::gossip::GossipMessage;
+
+/// Default number of transactions requested per ranged sync page
+pub const DEFAULT_SYNC_PAGE_LIMIT: u32 = 500;
+
+/// Phase of a ranged DAG synchronization with one peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncPhase {
+    /// Ready to request the next page
+    Idle,
+    /// Waiting for a response starting at this depth
+    Requesting { from_depth: u64 },
+    /// Caught up with the remote tips
+    Complete,
+}
+
+/// State machine that walks a remote DAG from genesis to its tips
+///
+/// Pages are requested in ascending depth order, so every transaction's
+/// parents have already been applied by the time it arrives.
+#[derive(Debug, Clone)]
+pub struct RangeSync {
+    /// Next depth to request
+    next_depth: u64,
+    /// Remote DAG depth we are syncing towards
+    target_depth: u64,
+    /// Page size limit
+    limit: u32,
+    phase: SyncPhase,
+}
+
+impl RangeSync {
+    /// Start a sync from genesis towards a remote DAG of the given depth
+    pub fn new(target_depth: u64) -> Self {
+        Self::with_limit(target_depth, DEFAULT_SYNC_PAGE_LIMIT)
+    }
+
+    /// Start a sync with a custom page limit
+    pub fn with_limit(target_depth: u64, limit: u32) -> Self {
+        RangeSync {
+            next_depth: 0,
+            target_depth,
+            limit: limit.max(1),
+            phase: SyncPhase::Idle,
+        }
+    }
+
+    /// Resume a sync from a known depth (e.g. the local DAG depth)
+    pub fn starting_at(mut self, depth: u64) -> Self {
+        self.next_depth = depth;
+        if depth > self.target_depth {
+            self.phase = SyncPhase::Complete;
+        }
+        self
+    }
+
+    /// Current phase
+    pub fn phase(&self) -> &SyncPhase {
+        &self.phase
+    }
+
+    /// Whether the sync has reached the remote tips
+    pub fn is_complete(&self) -> bool {
+        self.phase == SyncPhase::Complete
+    }
+
+    /// Depth the next page starts at
+    pub fn next_depth(&self) -> u64 {
+        self.next_depth
+    }
+
+    /// Remote depth being synced towards
+    pub fn target_depth(&self) -> u64 {
+        self.target_depth
+    }
+
+    /// Raise the target when the remote announces a deeper DAG
+    pub fn retarget(&mut self, remote_depth: u64) {
+        if remote_depth > self.target_depth {
+            self.target_depth = remote_depth;
+            if self.phase == SyncPhase::Complete {
+                self.phase = SyncPhase::Idle;
+            }
+        }
+    }
+
+    /// The next request to send, if one is due
+    pub fn next_request(&mut self) -> Option<GossipMessage> {
+        if self.phase != SyncPhase::Idle {
+            return None;
+        }
+        self.phase = SyncPhase::Requesting {
+            from_depth: self.next_depth,
+        };
+        Some(GossipMessage::SyncRangeRequest {
+            from_depth: self.next_depth,
+            to_depth: self.target_depth,
+            limit: self.limit,
+        })
+    }
+
+    /// Handle a range response; returns the transactions to apply, in order
+    ///
+    /// Responses that don't match an outstanding request are ignored.
+    pub fn on_response(
+        &mut self,
+        transactions: Vec<Transaction>,
+        next_depth: Option<u64>,
+    ) -> Vec<Transaction> {
+        let SyncPhase::Requesting { from_depth } = self.phase else {
+            return Vec::new();
+        };
+
+        match next_depth {
+            // Only move forward, so a misbehaving peer can't loop us
+            Some(next) if next > from_depth && next <= self.target_depth => {
+                self.next_depth = next;
+                self.phase = SyncPhase::Idle;
+            }
+            _ => {
+                self.next_depth = self.target_depth + 1;
+                self.phase = SyncPhase::Complete;
+            }
+        }
+
+        transactions
+    }
+
+    /// Abandon the outstanding request (e.g. on timeout) so it is re-sent
+    pub fn reset_request(&mut self) {
+        if matches!(self.phase, SyncPhase::Requesting { .. }) {
+            self.phase = SyncPhase::Idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::vertex::{Dag, DagVertex};
+
+    fn remote_dag(len: u64) -> Dag {
+        let kp = KeyPair::generate();
+        let genesis = Transaction::genesis(&kp);
+        let mut last = genesis.id;
+        let mut dag = Dag::new();
+        dag.insert(DagVertex::new(genesis, 0)).unwrap();
+        for i in 1..=len {
+            let tx = Transaction::relay_reward(&kp, 100, [last, last], i);
+            last = tx.id;
+            dag.insert(DagVertex::new(tx, i)).unwrap();
+        }
+        dag
+    }
+
+    fn serve(dag: &Dag, request: GossipMessage) -> (Vec<Transaction>, Option<u64>) {
+        let GossipMessage::SyncRangeRequest { from_depth, to_depth, limit } = request else {
+            panic!("expected a range request");
+        };
+        let (vertices, next) = dag.range_by_depth(from_depth, to_depth, limit as usize);
+        (vertices.into_iter().map(|v| v.transaction.clone()).collect(), next)
+    }
+
+    #[test]
+    fn test_sync_from_genesis() {
+        let remote = remote_dag(25);
+        let mut local = Dag::new();
+        let mut sync = RangeSync::with_limit(remote.depth(), 10);
+
+        let mut rounds = 0;
+        while let Some(request) = sync.next_request() {
+            let (txs, next) = serve(&remote, request);
+            for tx in sync.on_response(txs, next) {
+                let depth = local.depth() + if local.is_empty() { 0 } else { 1 };
+                local.insert(DagVertex::new(tx, depth)).unwrap();
+            }
+            rounds += 1;
+        }
+
+        assert!(sync.is_complete());
+        assert_eq!(local.len(), remote.len());
+        assert_eq!(rounds, 3);
+    }
+
+    #[test]
+    fn test_single_outstanding_request() {
+        let mut sync = RangeSync::new(10);
+        assert!(sync.next_request().is_some());
+        assert!(sync.next_request().is_none());
+        sync.reset_request();
+        assert!(sync.next_request().is_some());
+    }
+
+    #[test]
+    fn test_non_advancing_response_completes() {
+        let mut sync = RangeSync::new(10).starting_at(4);
+        sync.next_request().unwrap();
+        sync.on_response(Vec::new(), Some(2));
+        assert!(sync.is_complete());
+    }
+
+    #[test]
+    fn test_retarget_resumes() {
+        let mut sync = RangeSync::new(3);
+        sync.next_request().unwrap();
+        sync.on_response(Vec::new(), None);
+        assert!(sync.is_complete());
+
+        sync.retarget(8);
+        assert!(matches!(
+            sync.next_request(),
+            Some(GossipMessage::SyncRangeRequest { from_depth: 4, to_depth: 8, .. })
+        ));
+    }
+}
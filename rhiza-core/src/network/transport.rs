@@ -0,0 +1,340 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::PublicKey;
+use crate::network::gossip::GossipMessage;
+use crate::network::peer::PeerId;
+
+/// Noise protocol used for peer sessions
+///
+/// XX gives mutual authentication without either side knowing the other's
+/// static key in advance, which matches how mesh peers meet.
+pub const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// Maximum size of a single Noise message (spec limit)
+pub const MAX_NOISE_MESSAGE: usize = 65_535;
+
+/// Size of the AEAD tag appended to every encrypted chunk
+const TAG_LEN: usize = 16;
+
+/// Largest plaintext chunk that fits in one Noise message
+const MAX_CHUNK_PLAINTEXT: usize = MAX_NOISE_MESSAGE - TAG_LEN;
+
+/// Prologue mixed into the handshake hash so other protocols can't be confused
+/// with Rhiza sessions; the network ID follows it, so peers on different
+/// networks fail the handshake
+const PROLOGUE: &[u8] = b"rhiza-noise-v1";
+
+/// An in-progress Noise handshake
+///
+/// The handshake is transport-agnostic: callers move the bytes produced by
+/// `write_message` to the remote side (TCP, BLE, ...) and feed whatever comes
+/// back into `read_message` until `is_finished` returns true.
+pub struct Handshake {
+    state: snow::HandshakeState,
+    identity: PublicKey,
+    remote: Option<PeerId>,
+    messages: usize,
+}
+
+/// An established, mutually authenticated and encrypted peer session
+pub struct SecureSession {
+    transport: snow::TransportState,
+    remote: PeerId,
+}
+
+impl Handshake {
+    /// Start a handshake as the connecting side on network `network_id`
+    pub fn initiator(keypair: &KeyPair, network_id: u32) -> Result<Self, TransportError> {
+        Self::build(keypair, network_id, true)
+    }
+
+    /// Start a handshake as the accepting side on network `network_id`
+    pub fn responder(keypair: &KeyPair, network_id: u32) -> Result<Self, TransportError> {
+        Self::build(keypair, network_id, false)
+    }
+
+    fn build(keypair: &KeyPair, network_id: u32, initiator: bool) -> Result<Self, TransportError> {
+        let params = NOISE_PARAMS.parse().map_err(TransportError::Noise)?;
+        let secret = keypair.x25519_secret();
+        let prologue = [PROLOGUE, &network_id.to_le_bytes()].concat();
+        let builder = snow::Builder::new(params)
+            .local_private_key(&secret[..])
+            .prologue(&prologue);
+        let state = if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        }
+        .map_err(TransportError::Noise)?;
+
+        Ok(Handshake {
+            state,
+            identity: keypair.public_key.clone(),
+            remote: None,
+            messages: 0,
+        })
+    }
+
+    /// Produce the next handshake message to send
+    pub fn write_message(&mut self) -> Result<Vec<u8>, TransportError> {
+        // The first message travels before any key agreement, so our identity
+        // is only revealed once it can be encrypted
+        let payload: &[u8] = if self.messages == 0 {
+            &[]
+        } else {
+            self.identity.as_bytes()
+        };
+        let mut buf = vec![0u8; MAX_NOISE_MESSAGE];
+        let len = self
+            .state
+            .write_message(payload, &mut buf)
+            .map_err(TransportError::Noise)?;
+        buf.truncate(len);
+        self.messages += 1;
+        Ok(buf)
+    }
+
+    /// Consume a handshake message received from the remote side
+    pub fn read_message(&mut self, message: &[u8]) -> Result<(), TransportError> {
+        let mut payload = vec![0u8; MAX_NOISE_MESSAGE];
+        let len = self
+            .state
+            .read_message(message, &mut payload)
+            .map_err(TransportError::Noise)?;
+        self.messages += 1;
+
+        if len > 0 {
+            let claimed: [u8; 32] = payload[..len]
+                .try_into()
+                .map_err(|_| TransportError::InvalidIdentity)?;
+            let claimed = PublicKey::from_bytes(claimed);
+            let remote_static = self
+                .state
+                .get_remote_static()
+                .ok_or(TransportError::InvalidIdentity)?;
+            verify_identity(&claimed, remote_static)?;
+            self.remote = Some(PeerId::new(claimed));
+        }
+        Ok(())
+    }
+
+    /// Whether the handshake has completed
+    pub fn is_finished(&self) -> bool {
+        self.state.is_handshake_finished()
+    }
+
+    /// The authenticated remote peer, once its identity has been received
+    pub fn remote_peer(&self) -> Option<&PeerId> {
+        self.remote.as_ref()
+    }
+
+    /// Switch to transport mode after a completed handshake
+    pub fn into_session(self) -> Result<SecureSession, TransportError> {
+        if !self.is_finished() {
+            return Err(TransportError::HandshakeIncomplete);
+        }
+        let remote = self.remote.ok_or(TransportError::InvalidIdentity)?;
+        let transport = self
+            .state
+            .into_transport_mode()
+            .map_err(TransportError::Noise)?;
+        Ok(SecureSession { transport, remote })
+    }
+}
+
+impl SecureSession {
+    /// The authenticated identity of the remote peer
+    pub fn remote_peer(&self) -> &PeerId {
+        &self.remote
+    }
+
+    /// Encrypt a gossip message into a single length-prefixed frame
+    ///
+    /// Frame layout: `u32` big-endian body length, followed by one or more
+    /// chunks of `u16` big-endian length plus Noise ciphertext.
+    pub fn encrypt(&mut self, message: &GossipMessage) -> Result<Vec<u8>, TransportError> {
+        let plaintext = message.to_bytes();
+        let mut body = Vec::with_capacity(plaintext.len() + TAG_LEN + 2);
+        let mut buf = vec![0u8; MAX_NOISE_MESSAGE];
+
+        // An empty plaintext still gets one (tag-only) chunk
+        let chunks: Vec<&[u8]> = if plaintext.is_empty() {
+            vec![&[]]
+        } else {
+            plaintext.chunks(MAX_CHUNK_PLAINTEXT).collect()
+        };
+        for chunk in chunks {
+            let len = self
+                .transport
+                .write_message(chunk, &mut buf)
+                .map_err(TransportError::Noise)?;
+            body.extend_from_slice(&(len as u16).to_be_bytes());
+            body.extend_from_slice(&buf[..len]);
+        }
+
+        let mut frame = Vec::with_capacity(body.len() + 4);
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&body);
+        Ok(frame)
+    }
+
+    /// Decrypt a frame body (without its `u32` length prefix)
+    pub fn decrypt(&mut self, body: &[u8]) -> Result<GossipMessage, TransportError> {
+        let mut plaintext = Vec::with_capacity(body.len());
+        let mut buf = vec![0u8; MAX_NOISE_MESSAGE];
+        let mut rest = body;
+
+        while !rest.is_empty() {
+            if rest.len() < 2 {
+                return Err(TransportError::MalformedFrame);
+            }
+            let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            let chunk = rest
+                .get(2..2 + len)
+                .ok_or(TransportError::MalformedFrame)?;
+            let n = self
+                .transport
+                .read_message(chunk, &mut buf)
+                .map_err(TransportError::Noise)?;
+            plaintext.extend_from_slice(&buf[..n]);
+            rest = &rest[2 + len..];
+        }
+
+        GossipMessage::from_bytes(&plaintext).map_err(|_| TransportError::MalformedFrame)
+    }
+
+    /// Read the body length from a frame's `u32` prefix
+    pub fn frame_len(prefix: [u8; 4]) -> usize {
+        u32::from_be_bytes(prefix) as usize
+    }
+}
+
+/// Check that a claimed Ed25519 identity owns the Noise static key
+fn verify_identity(claimed: &PublicKey, remote_static: &[u8]) -> Result<(), TransportError> {
+    match claimed.to_x25519() {
+        Some(expected) if expected.as_slice() == remote_static => Ok(()),
+        _ => Err(TransportError::InvalidIdentity),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("noise error: {0}")]
+    Noise(snow::Error),
+    #[error("remote identity does not match its session key")]
+    InvalidIdentity,
+    #[error("handshake not finished")]
+    HandshakeIncomplete,
+    #[error("malformed frame")]
+    MalformedFrame,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dag::transaction::Transaction;
+
+    fn handshake(a: &KeyPair, b: &KeyPair) -> (SecureSession, SecureSession) {
+        let mut initiator = Handshake::initiator(a, crate::MAINNET_ID).unwrap();
+        let mut responder = Handshake::responder(b, crate::MAINNET_ID).unwrap();
+
+        let m1 = initiator.write_message().unwrap();
+        responder.read_message(&m1).unwrap();
+        let m2 = responder.write_message().unwrap();
+        initiator.read_message(&m2).unwrap();
+        let m3 = initiator.write_message().unwrap();
+        responder.read_message(&m3).unwrap();
+
+        (
+            initiator.into_session().unwrap(),
+            responder.into_session().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_mutual_authentication() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let (sa, sb) = handshake(&a, &b);
+
+        assert_eq!(sa.remote_peer().public_key, b.public_key);
+        assert_eq!(sb.remote_peer().public_key, a.public_key);
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let (mut sa, mut sb) = handshake(&a, &b);
+
+        let msg = GossipMessage::NewTransaction(Transaction::genesis(&a));
+        let frame = sa.encrypt(&msg).unwrap();
+        let len = SecureSession::frame_len(frame[..4].try_into().unwrap());
+        assert_eq!(len, frame.len() - 4);
+
+        let decoded = sb.decrypt(&frame[4..]).unwrap();
+        assert_eq!(decoded.type_name(), "NewTransaction");
+    }
+
+    #[test]
+    fn test_large_message_chunked() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let (mut sa, mut sb) = handshake(&a, &b);
+
+        let tx = Transaction::genesis(&a);
+        let msg = GossipMessage::SyncResponse {
+            transactions: vec![tx; 1_000],
+        };
+        let frame = sa.encrypt(&msg).unwrap();
+        assert!(frame.len() > MAX_NOISE_MESSAGE);
+
+        match sb.decrypt(&frame[4..]).unwrap() {
+            GossipMessage::SyncResponse { transactions } => assert_eq!(transactions.len(), 1_000),
+            other => panic!("unexpected message: {}", other.type_name()),
+        }
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_rejected() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let (mut sa, mut sb) = handshake(&a, &b);
+
+        let mut frame = sa.encrypt(&GossipMessage::Ping { timestamp: 1 }).unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 0xff;
+        assert!(sb.decrypt(&frame[4..]).is_err());
+    }
+
+    #[test]
+    fn test_network_mismatch_rejected() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let mut initiator = Handshake::initiator(&a, crate::MAINNET_ID).unwrap();
+        let mut responder = Handshake::responder(&b, crate::TESTNET_ID).unwrap();
+
+        let m1 = initiator.write_message().unwrap();
+        responder.read_message(&m1).unwrap();
+        let m2 = responder.write_message().unwrap();
+        assert!(initiator.read_message(&m2).is_err());
+    }
+
+    #[test]
+    fn test_identity_mismatch_rejected() {
+        let a = KeyPair::generate();
+        let b = KeyPair::generate();
+        let static_a = a.public_key.to_x25519().unwrap();
+        assert!(verify_identity(&a.public_key, &static_a).is_ok());
+        assert!(verify_identity(&b.public_key, &static_a).is_err());
+    }
+
+    #[test]
+    fn test_session_requires_finished_handshake() {
+        let a = KeyPair::generate();
+        let handshake = Handshake::initiator(&a, crate::MAINNET_ID).unwrap();
+        assert!(matches!(
+            handshake.into_session(),
+            Err(TransportError::HandshakeIncomplete)
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::crypto::keys::KeyPair;
+use crate::crypto::{Hash, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Domain separator of the hash release keys sign
+const RELEASE_DOMAIN: &[u8] = b"rhiza release manifest v1";
+
+/// Keys whose signatures on a release manifest are trusted (Ed25519, hex)
+///
+/// Releases are signed with the pinned founder key; operators can trust
+/// further keys in their node config.
+pub const RELEASE_PUBLIC_KEYS: &[&str] = &[crate::FOUNDER_PUBLIC_KEY];
+
+/// The embedded release keys, parsed from [`RELEASE_PUBLIC_KEYS`]
+pub fn release_public_keys() -> Vec<PublicKey> {
+    RELEASE_PUBLIC_KEYS
+        .iter()
+        .map(|key| {
+            let bytes: [u8; 32] = hex::decode(key)
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .expect("RELEASE_PUBLIC_KEYS are valid 32-byte hex keys");
+            PublicKey::from_bytes(bytes)
+        })
+        .collect()
+}
+
+/// Errors checking a release manifest
+#[derive(Debug, Error)]
+pub enum ReleaseError {
+    #[error("invalid version {0:?}, expected MAJOR.MINOR.PATCH")]
+    InvalidVersion(String),
+    #[error("signed by {valid} trusted release keys, {required} required")]
+    NotSigned { valid: usize, required: usize },
+}
+
+/// A `MAJOR.MINOR.PATCH` release version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl FromStr for Version {
+    type Err = ReleaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ReleaseError::InvalidVersion(s.to_string());
+        let mut parts = s.strip_prefix('v').unwrap_or(s).split('.');
+        let mut next = || -> Result<u64, ReleaseError> {
+            parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)
+        };
+        let version = Version {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A build of a release for one platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseDownload {
+    /// Platform as `<os>-<arch>`, e.g. `linux-x86_64`
+    pub target: String,
+    pub url: String,
+    /// BLAKE3 hash of the file
+    pub blake3: Hash,
+}
+
+impl ReleaseDownload {
+    /// Whether `bytes` are this download
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        Hash::digest(bytes) == self.blake3
+    }
+}
+
+/// A release key's signature over a manifest's content hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseSignature {
+    pub key: PublicKey,
+    pub signature: Signature,
+}
+
+/// Announcement of the latest node release, published as JSON
+///
+/// Release keys sign its [`content_hash`](Self::content_hash), which covers
+/// every field but the signatures. The downloads are pinned by hash, so a
+/// signed manifest vouches for the binaries too, wherever they are served
+/// from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    pub version: String,
+    /// When the release was published (unix ms)
+    pub released_ms: u64,
+    /// Fixes a security or consensus problem; operators should upgrade now
+    #[serde(default)]
+    pub critical: bool,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub downloads: Vec<ReleaseDownload>,
+    #[serde(default)]
+    pub signatures: Vec<ReleaseSignature>,
+}
+
+impl ReleaseManifest {
+    /// Hash of everything the release keys vouch for
+    ///
+    /// Strings are length-prefixed and integers little-endian, so the hash
+    /// doesn't depend on how the JSON was formatted.
+    pub fn content_hash(&self) -> Hash {
+        fn put_str(out: &mut Vec<u8>, s: &str) {
+            out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+
+        let mut out = RELEASE_DOMAIN.to_vec();
+        put_str(&mut out, &self.version);
+        out.extend_from_slice(&self.released_ms.to_le_bytes());
+        out.push(self.critical as u8);
+        put_str(&mut out, &self.notes);
+        out.extend_from_slice(&(self.downloads.len() as u32).to_le_bytes());
+        for download in &self.downloads {
+            put_str(&mut out, &download.target);
+            put_str(&mut out, &download.url);
+            out.extend_from_slice(download.blake3.as_bytes());
+        }
+        Hash::digest(&out)
+    }
+
+    /// Add `keypair`'s signature, replacing an earlier one by the same key
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        let signature = keypair.sign(self.content_hash().as_bytes());
+        self.signatures.retain(|s| s.key != keypair.public_key);
+        self.signatures.push(ReleaseSignature {
+            key: keypair.public_key.clone(),
+            signature,
+        });
+    }
+
+    /// Check the manifest is signed by `required` of the `trusted` keys and
+    /// return the version it announces
+    pub fn verify(&self, trusted: &[PublicKey], required: usize) -> Result<Version, ReleaseError> {
+        let version: Version = self.version.parse()?;
+        let hash = self.content_hash();
+        let valid = self
+            .signatures
+            .iter()
+            .filter(|s| trusted.contains(&s.key) && s.key.verify(hash.as_bytes(), &s.signature))
+            .map(|s| &s.key)
+            .collect::<HashSet<_>>()
+            .len();
+        if valid < required {
+            return Err(ReleaseError::NotSigned { valid, required });
+        }
+        Ok(version)
+    }
+
+    /// The build for `target`, if the release has one
+    pub fn download_for(&self, target: &str) -> Option<&ReleaseDownload> {
+        self.downloads.iter().find(|d| d.target == target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> ReleaseManifest {
+        ReleaseManifest {
+            version: "0.2.0".into(),
+            released_ms: 1_700_000_000_000,
+            critical: true,
+            notes: "Fixes a double-spend check".into(),
+            downloads: vec![ReleaseDownload {
+                target: "linux-x86_64".into(),
+                url: "https://example.org/rhiza-node".into(),
+                blake3: Hash::digest(b"binary"),
+            }],
+            signatures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        assert!(v("0.10.0") > v("0.9.9"));
+        assert!(v("1.0.0") > v("0.99.99"));
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+        assert_eq!(v("1.2.3").to_string(), "1.2.3");
+        for bad in ["1.2", "1.2.3.4", "1.x.3", ""] {
+            assert!(bad.parse::<Version>().is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn test_signed_manifest() {
+        let release_key = KeyPair::generate();
+        let outsider = KeyPair::generate();
+        let trusted = vec![release_key.public_key.clone()];
+        let mut manifest = manifest();
+
+        assert!(matches!(manifest.verify(&trusted, 1), Err(ReleaseError::NotSigned { valid: 0, .. })));
+        manifest.sign(&outsider);
+        assert!(manifest.verify(&trusted, 1).is_err());
+
+        manifest.sign(&release_key);
+        assert_eq!(manifest.verify(&trusted, 1).unwrap(), "0.2.0".parse().unwrap());
+
+        // Survives a round trip through JSON
+        let json = serde_json::to_string_pretty(&manifest).unwrap();
+        let parsed: ReleaseManifest = serde_json::from_str(&json).unwrap();
+        assert!(parsed.verify(&trusted, 1).is_ok());
+
+        // Any change to what was signed breaks the signature
+        let mut tampered = manifest.clone();
+        tampered.downloads[0].url = "https://evil.example/rhiza-node".into();
+        assert!(tampered.verify(&trusted, 1).is_err());
+        let mut tampered = manifest.clone();
+        tampered.critical = false;
+        assert!(tampered.verify(&trusted, 1).is_err());
+    }
+
+    #[test]
+    fn test_download_for_target() {
+        let manifest = manifest();
+        let download = manifest.download_for("linux-x86_64").unwrap();
+        assert!(download.matches(b"binary"));
+        assert!(!download.matches(b"something else"));
+        assert!(manifest.download_for("windows-x86_64").is_none());
+    }
+
+    #[test]
+    fn test_embedded_release_keys_parse() {
+        assert_eq!(release_public_keys().len(), RELEASE_PUBLIC_KEYS.len());
+    }
+}
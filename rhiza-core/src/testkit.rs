@@ -0,0 +1,20 @@
+//! Helpers shared by the crate's tests
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Environment variable fixing the seed of [`seeded_rng`]
+pub const SEED_VAR: &str = "RHIZA_TEST_SEED";
+
+/// Randomness for a test, seeded from [`SEED_VAR`] or at random
+///
+/// The seed is printed, and the test harness shows it when the test fails,
+/// so `RHIZA_TEST_SEED=<seed> cargo test <name>` replays the same draws.
+pub fn seeded_rng() -> StdRng {
+    let seed = match std::env::var(SEED_VAR) {
+        Ok(seed) => seed.parse().unwrap_or_else(|_| panic!("{} must be a u64, got {:?}", SEED_VAR, seed)),
+        Err(_) => rand::random(),
+    };
+    println!("{}={}", SEED_VAR, seed);
+    StdRng::seed_from_u64(seed)
+}
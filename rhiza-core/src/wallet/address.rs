@@ -2,6 +2,10 @@ use crate::crypto::{Hash, PublicKey};
 use bech32::{Bech32m, Hrp};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
+
+/// Characters of bech32m checksum an address ends with
+pub const CHECKSUM_LEN: usize = 6;
 
 /// A Rhiza address in bech32m format (e.g., rhz1...)
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -21,8 +25,23 @@ impl Address {
         Address(encoded)
     }
 
+    /// Get the raw string representation
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The bech32m checksum closing the address, which a mistyped or
+    /// swapped address almost surely gets wrong; worth comparing by eye
+    pub fn checksum(&self) -> &str {
+        &self.0[self.0.len() - CHECKSUM_LEN..]
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
     /// Parse an address from string
-    pub fn from_str(s: &str) -> Result<Self, AddressError> {
+    fn from_str(s: &str) -> Result<Self, AddressError> {
         let hrp = Hrp::parse(crate::ADDRESS_HRP).map_err(|_| AddressError::InvalidHrp)?;
         let (decoded_hrp, data) =
             bech32::decode(s).map_err(|_| AddressError::InvalidEncoding)?;
@@ -37,11 +56,6 @@ impl Address {
 
         Ok(Address(s.to_string()))
     }
-
-    /// Get the raw string representation
-    pub fn as_str(&self) -> &str {
-        &self.0
-    }
 }
 
 impl fmt::Debug for Address {
@@ -0,0 +1,257 @@
+use crate::crypto::{Hash, PublicKey};
+use crate::dag::transaction::{Transaction, TransactionType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Suffix of the human-readable alias form (`alice@rhz`)
+pub const ALIAS_SUFFIX: &str = "@rhz";
+
+/// Shortest allowed alias name
+pub const MIN_ALIAS_LEN: usize = 3;
+
+/// Longest allowed alias name
+pub const MAX_ALIAS_LEN: usize = 32;
+
+/// A human-readable account alias, e.g. `alice@rhz`
+///
+/// Names are 3–32 characters of lowercase ASCII letters, digits, `-` and
+/// `_`, starting with a letter or digit. Parsing accepts the name with or
+/// without the `@rhz` suffix and in any case.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Alias(String);
+
+impl Alias {
+    /// The bare name, without the `@rhz` suffix
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Alias {
+    type Err = AliasError;
+
+    fn from_str(s: &str) -> Result<Self, AliasError> {
+        let s = s.trim().to_ascii_lowercase();
+        let name = s.strip_suffix(ALIAS_SUFFIX).unwrap_or(&s);
+
+        if name.len() < MIN_ALIAS_LEN || name.len() > MAX_ALIAS_LEN {
+            return Err(AliasError::InvalidLength);
+        }
+        if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            return Err(AliasError::InvalidStart);
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Err(AliasError::InvalidCharacter(c));
+        }
+        Ok(Alias(name.to_string()))
+    }
+}
+
+impl fmt::Debug for Alias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Alias({}{})", self.0, ALIAS_SUFFIX)
+    }
+}
+
+impl fmt::Display for Alias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.0, ALIAS_SUFFIX)
+    }
+}
+
+/// A finalized alias binding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasRecord {
+    pub alias: Alias,
+    /// The key that announced the alias first; only it may update it
+    pub owner: PublicKey,
+    /// Where payments to the alias should go
+    pub receive_key: PublicKey,
+    /// The announcement currently in effect
+    pub announcement: Hash,
+    /// Timestamp of that announcement
+    pub timestamp: u64,
+}
+
+/// Alias bindings built from final announcement transactions
+///
+/// The first announcement of a name to become final wins it for good;
+/// later announcements by other keys are ignored. The owner can re-announce
+/// to point the alias at a new receive key. Each key owns at most one alias.
+#[derive(Debug, Clone, Default)]
+pub struct AliasRegistry {
+    by_alias: HashMap<Alias, AliasRecord>,
+    by_owner: HashMap<PublicKey, Alias>,
+}
+
+impl AliasRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up an alias
+    pub fn resolve(&self, alias: &Alias) -> Option<&AliasRecord> {
+        self.by_alias.get(alias)
+    }
+
+    /// The alias a key owns, if any
+    pub fn alias_of(&self, owner: &PublicKey) -> Option<&AliasRecord> {
+        self.by_owner.get(owner).and_then(|alias| self.by_alias.get(alias))
+    }
+
+    /// Whether `owner` may announce `alias`: it is unclaimed, or already
+    /// theirs, and they don't own a different one
+    pub fn check_claim(&self, owner: &PublicKey, alias: &Alias) -> Result<(), AliasError> {
+        if let Some(record) = self.by_alias.get(alias) {
+            if record.owner != *owner {
+                return Err(AliasError::Taken(alias.clone()));
+            }
+        }
+        if let Some(owned) = self.by_owner.get(owner) {
+            if owned != alias {
+                return Err(AliasError::AlreadyOwns(owned.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an announcement that just became final
+    ///
+    /// Returns whether it changed the registry.
+    pub fn record(&mut self, tx: &Transaction) -> bool {
+        if tx.data.tx_type != TransactionType::AliasAnnouncement {
+            return false;
+        }
+        let Some(alias) = tx.data.memo.as_deref().and_then(|m| m.parse::<Alias>().ok()) else {
+            return false;
+        };
+        if self.check_claim(&tx.data.sender, &alias).is_err() {
+            return false;
+        }
+        if let Some(current) = self.by_alias.get(&alias) {
+            // Finality order isn't timestamp order; keep the newest update
+            if current.timestamp > tx.data.timestamp {
+                return false;
+            }
+        }
+
+        self.by_owner.insert(tx.data.sender.clone(), alias.clone());
+        self.by_alias.insert(
+            alias.clone(),
+            AliasRecord {
+                alias,
+                owner: tx.data.sender.clone(),
+                receive_key: tx.data.recipient.clone(),
+                announcement: tx.id,
+                timestamp: tx.data.timestamp,
+            },
+        );
+        true
+    }
+
+    /// Every binding, in no particular order
+    pub fn records(&self) -> impl Iterator<Item = &AliasRecord> {
+        self.by_alias.values()
+    }
+
+    /// Put back a binding saved from this registry
+    pub fn restore(&mut self, record: AliasRecord) {
+        self.by_owner.insert(record.owner.clone(), record.alias.clone());
+        self.by_alias.insert(record.alias.clone(), record);
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_alias.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_alias.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum AliasError {
+    #[error("alias must be {MIN_ALIAS_LEN}-{MAX_ALIAS_LEN} characters")]
+    InvalidLength,
+    #[error("alias must start with a letter or digit")]
+    InvalidStart,
+    #[error("invalid character in alias: {0:?}")]
+    InvalidCharacter(char),
+    #[error("alias {0} is already taken")]
+    Taken(Alias),
+    #[error("key already owns alias {0}")]
+    AlreadyOwns(Alias),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn announce(kp: &KeyPair, alias: &str, receive: &PublicKey, timestamp: u64) -> Transaction {
+        let mut tx = Transaction::alias_announcement(
+            kp,
+            &alias.parse().unwrap(),
+            receive.clone(),
+            [Hash::zero(), Hash::zero()],
+            0,
+        );
+        tx.data.timestamp = timestamp;
+        Transaction::new(tx.data, kp)
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        let alias: Alias = "Alice@RHZ".parse().unwrap();
+        assert_eq!(alias.name(), "alice");
+        assert_eq!(alias.to_string(), "alice@rhz");
+        assert_eq!("alice".parse::<Alias>().unwrap(), alias);
+        assert!(matches!("al".parse::<Alias>(), Err(AliasError::InvalidLength)));
+        assert!(matches!("-alice".parse::<Alias>(), Err(AliasError::InvalidStart)));
+        assert!(matches!("al ice".parse::<Alias>(), Err(AliasError::InvalidCharacter(' '))));
+        assert!(matches!("alice@btc".parse::<Alias>(), Err(AliasError::InvalidCharacter('@'))));
+    }
+
+    #[test]
+    fn test_first_finalized_wins() {
+        let alice = KeyPair::generate();
+        let mallory = KeyPair::generate();
+        let mut registry = AliasRegistry::new();
+
+        assert!(registry.record(&announce(&alice, "alice", &alice.public_key, 10)));
+        assert!(!registry.record(&announce(&mallory, "alice", &mallory.public_key, 5)));
+
+        let alias = "alice".parse().unwrap();
+        let record = registry.resolve(&alias).unwrap();
+        assert_eq!(record.owner, alice.public_key);
+        assert!(matches!(
+            registry.check_claim(&mallory.public_key, &alias),
+            Err(AliasError::Taken(_))
+        ));
+        assert_eq!(registry.alias_of(&alice.public_key).unwrap().alias, alias);
+    }
+
+    #[test]
+    fn test_owner_updates_receive_key() {
+        let alice = KeyPair::generate();
+        let cold = KeyPair::generate();
+        let mut registry = AliasRegistry::new();
+
+        registry.record(&announce(&alice, "alice", &alice.public_key, 10));
+        assert!(registry.record(&announce(&alice, "alice", &cold.public_key, 20)));
+        // An older announcement finalizing late doesn't roll the update back
+        assert!(!registry.record(&announce(&alice, "alice", &alice.public_key, 15)));
+
+        let record = registry.resolve(&"alice".parse().unwrap()).unwrap();
+        assert_eq!(record.receive_key, cold.public_key);
+
+        // One alias per key
+        assert!(!registry.record(&announce(&alice, "alice2", &alice.public_key, 30)));
+        assert_eq!(registry.len(), 1);
+    }
+}
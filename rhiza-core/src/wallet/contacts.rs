@@ -0,0 +1,194 @@
+use crate::crypto::PublicKey;
+use crate::wallet::address::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File name of the address book, next to `wallet.json`
+pub const CONTACTS_FILE: &str = "contacts.json";
+
+/// Longest allowed contact name
+pub const MAX_CONTACT_NAME_LEN: usize = 32;
+
+/// Someone the wallet pays, by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contact {
+    pub name: String,
+    pub address: Address,
+    /// Known when the contact was added by key; an address alone has to be
+    /// looked up on a node before it can be paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<PublicKey>,
+}
+
+/// The wallet's address book: names mapped to addresses or public keys
+///
+/// Kept as plain JSON next to the keystore. Names are case-insensitive and
+/// can't be mistaken for an address, key or `@rhz` alias, so a recipient
+/// that is one of those is never read as a contact.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Contacts {
+    contacts: BTreeMap<String, Contact>,
+}
+
+impl Contacts {
+    /// Load the address book, or an empty one if there's no file yet
+    pub fn load(path: &Path) -> Result<Self, ContactsError> {
+        if !path.exists() {
+            return Ok(Contacts::default());
+        }
+        let data = fs::read_to_string(path).map_err(ContactsError::Io)?;
+        serde_json::from_str(&data).map_err(ContactsError::Deserialize)
+    }
+
+    /// Save the address book, written next to the target and renamed over it
+    pub fn save(&self, path: &Path) -> Result<(), ContactsError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(ContactsError::Io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(ContactsError::Serialize)?;
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp).map_err(ContactsError::Io)?;
+        file.write_all(json.as_bytes()).map_err(ContactsError::Io)?;
+        file.sync_all().map_err(ContactsError::Io)?;
+        fs::rename(&tmp, path).map_err(ContactsError::Io)?;
+        Ok(())
+    }
+
+    /// Add a contact for `target`: an `rhz1...` address or hex public key
+    pub fn add(&mut self, name: &str, target: &str) -> Result<&Contact, ContactsError> {
+        let name = normalize_name(name)?;
+        if self.contacts.contains_key(&name) {
+            return Err(ContactsError::Exists(name));
+        }
+        let target = target.trim();
+        let contact = if is_public_key(target) {
+            let public_key =
+                parse_public_key(target).ok_or_else(|| ContactsError::InvalidTarget(target.into()))?;
+            Contact {
+                name: name.clone(),
+                address: Address::from_public_key(&public_key),
+                public_key: Some(public_key),
+            }
+        } else {
+            let address = Address::from_str(&target.to_ascii_lowercase())
+                .map_err(|_| ContactsError::InvalidTarget(target.into()))?;
+            Contact {
+                name: name.clone(),
+                address,
+                public_key: None,
+            }
+        };
+        Ok(self.contacts.entry(name).or_insert(contact))
+    }
+
+    /// Remove a contact, returning it if there was one
+    pub fn remove(&mut self, name: &str) -> Option<Contact> {
+        self.contacts.remove(&name.trim().to_ascii_lowercase())
+    }
+
+    /// The contact a recipient names, if it is a contact's name
+    pub fn get(&self, name: &str) -> Option<&Contact> {
+        self.contacts.get(&name.trim().to_ascii_lowercase())
+    }
+
+    /// Contacts in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contacts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+}
+
+/// Lowercase a name and check it can't be read as anything else
+fn normalize_name(name: &str) -> Result<String, ContactsError> {
+    let name = name.trim().to_ascii_lowercase();
+    let well_formed = !name.is_empty()
+        && name.len() <= MAX_CONTACT_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    let address_like = name.starts_with(&format!("{}1", crate::ADDRESS_HRP));
+    if !well_formed || address_like || is_public_key(&name) {
+        return Err(ContactsError::InvalidName(name));
+    }
+    Ok(name)
+}
+
+fn is_public_key(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn parse_public_key(s: &str) -> Option<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(s).ok()?.try_into().ok()?;
+    Some(PublicKey::from_bytes(bytes))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContactsError {
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+    #[error("serialization error: {0}")]
+    Serialize(serde_json::Error),
+    #[error("deserialization error: {0}")]
+    Deserialize(serde_json::Error),
+    #[error(
+        "invalid contact name {0:?}: use up to 32 letters, digits, '-', '_' or '.', \
+         not an address or key"
+    )]
+    InvalidName(String),
+    #[error("{0} is not an rhz1 address or hex public key")]
+    InvalidTarget(String),
+    #[error("there is already a contact named {0}")]
+    Exists(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_save_load() {
+        let alice = KeyPair::generate().public_key;
+        let bob = Address::from_public_key(&KeyPair::generate().public_key);
+        let mut contacts = Contacts::default();
+        contacts.add("Alice", &alice.to_string()).unwrap();
+        contacts.add("bob", &bob.to_string().to_ascii_uppercase()).unwrap();
+        assert!(matches!(contacts.add("ALICE", &bob.to_string()), Err(ContactsError::Exists(_))));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(CONTACTS_FILE);
+        contacts.save(&path).unwrap();
+        let mut loaded = Contacts::load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let found = loaded.get("alice").unwrap();
+        assert_eq!(found.public_key, Some(alice.clone()));
+        assert_eq!(found.address, Address::from_public_key(&alice));
+        assert_eq!(loaded.get(" Bob ").unwrap().address, bob);
+        assert_eq!(loaded.get("bob").unwrap().public_key, None);
+
+        assert!(loaded.remove("alice").is_some());
+        assert!(loaded.get("alice").is_none());
+        assert!(Contacts::load(&dir.path().join("missing.json")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_names_that_read_as_something_else_are_refused() {
+        let key = KeyPair::generate().public_key.to_string();
+        let mut contacts = Contacts::default();
+        for name in ["", "rhz1alice", "alice@rhz", key.as_str(), "-dash", "a b"] {
+            assert!(matches!(contacts.add(name, &key), Err(ContactsError::InvalidName(_))), "{}", name);
+        }
+        assert!(matches!(contacts.add("carol", "rhz1nope"), Err(ContactsError::InvalidTarget(_))));
+    }
+}
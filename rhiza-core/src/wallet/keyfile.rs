@@ -0,0 +1,132 @@
+use super::keystore::{KdfParams, KeyStore, KeyStoreError};
+use crate::crypto::keys::{DerivationPath, KeyPair};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Failure to get a password or to read or write a keystore file
+#[derive(Debug, thiserror::Error)]
+pub enum KeyFileError {
+    #[error("Could not read password file {0}: {1}")]
+    PasswordFile(PathBuf, std::io::Error),
+    #[error("No terminal to ask for the wallet password; use --password-file")]
+    NoTerminal,
+    #[error("Could not read the password: {0}")]
+    Prompt(std::io::Error),
+    #[error("Passwords do not match")]
+    Mismatch,
+    #[error("The wallet password can't be empty")]
+    Empty,
+    #[error(transparent)]
+    KeyStore(#[from] KeyStoreError),
+}
+
+/// Read the wallet password from `password_file`, or prompt for it
+pub fn read_password(password_file: Option<&Path>, prompt: &str) -> Result<Zeroizing<String>, KeyFileError> {
+    if let Some(path) = password_file {
+        let contents = Zeroizing::new(
+            std::fs::read_to_string(path).map_err(|e| KeyFileError::PasswordFile(path.to_path_buf(), e))?,
+        );
+        // Secret mounts usually end in a newline that isn't part of the password
+        return Ok(Zeroizing::new(contents.trim_end_matches(['\r', '\n']).to_string()));
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(KeyFileError::NoTerminal);
+    }
+    Ok(Zeroizing::new(rpassword::prompt_password(prompt).map_err(KeyFileError::Prompt)?))
+}
+
+/// Choose a password for a wallet (asked twice when prompting)
+pub fn new_password(password_file: Option<&Path>) -> Result<Zeroizing<String>, KeyFileError> {
+    let password = read_password(password_file, "  New wallet password: ")?;
+    if password_file.is_none() {
+        let confirm = read_password(None, "  Repeat password: ")?;
+        if *confirm != *password {
+            return Err(KeyFileError::Mismatch);
+        }
+    }
+    if password.is_empty() {
+        return Err(KeyFileError::Empty);
+    }
+    Ok(password)
+}
+
+/// Encrypt `keypair` into a new keystore at `path`
+pub fn create(path: &Path, keypair: &KeyPair, password_file: Option<&Path>) -> Result<(), KeyFileError> {
+    let password = new_password(password_file)?;
+    KeyStore::encrypt(keypair, &password)?.save(path)?;
+    Ok(())
+}
+
+/// Encrypt a BIP39 seed into a new HD keystore whose main key is the one
+/// at `key_path`
+pub fn create_hd(
+    path: &Path,
+    seed: &[u8; 64],
+    key_path: DerivationPath,
+    password_file: Option<&Path>,
+) -> Result<(), KeyFileError> {
+    let password = new_password(password_file)?;
+    KeyStore::encrypt_seed_at(seed, key_path, &password, KdfParams::default())?.save(path)?;
+    Ok(())
+}
+
+/// Decrypt an encrypted keystore with the password from `password_file`
+/// or the terminal
+pub fn unlock(keystore: &KeyStore, password_file: Option<&Path>) -> Result<KeyPair, KeyFileError> {
+    let password = read_password(password_file, "  Wallet password: ")?;
+    Ok(keystore.unlock(&password)?)
+}
+
+/// Encrypt a legacy keystore in place under a new password and return its
+/// keypair
+pub fn encrypt(
+    path: &Path,
+    keystore: KeyStore,
+    password_file: Option<&Path>,
+) -> Result<KeyPair, KeyFileError> {
+    let password = new_password(password_file)?;
+    let upgraded = keystore.upgrade(&password)?;
+    upgraded.save(path)?;
+    Ok(upgraded.unlock(&password)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_password_file_round_trip() {
+        let dir = tempdir().unwrap();
+        let password_file = dir.path().join("password");
+        std::fs::write(&password_file, "hunter2\n").unwrap();
+        let path = dir.path().join("wallet.json");
+
+        let keypair = KeyPair::generate();
+        create(&path, &keypair, Some(&password_file)).unwrap();
+        let keystore = KeyStore::load(&path).unwrap();
+        assert!(keystore.is_encrypted());
+        assert_eq!(keystore.unlock("hunter2").unwrap().public_key, keypair.public_key);
+        assert_eq!(unlock(&keystore, Some(&password_file)).unwrap().public_key, keypair.public_key);
+
+        std::fs::write(&password_file, "\n").unwrap();
+        assert!(matches!(create(&path, &keypair, Some(&password_file)), Err(KeyFileError::Empty)));
+    }
+
+    #[test]
+    fn test_encrypt_legacy_keystore() {
+        let dir = tempdir().unwrap();
+        let password_file = dir.path().join("password");
+        std::fs::write(&password_file, "hunter2").unwrap();
+        let path = dir.path().join("wallet.json");
+
+        let keypair = KeyPair::generate();
+        let legacy = KeyStore::from_keypair(&keypair);
+        legacy.save(&path).unwrap();
+        assert_eq!(encrypt(&path, legacy, Some(&password_file)).unwrap().public_key, keypair.public_key);
+        let keystore = KeyStore::load(&path).unwrap();
+        assert!(keystore.is_encrypted());
+        assert!(keystore.unlock("wrong").is_err());
+    }
+}
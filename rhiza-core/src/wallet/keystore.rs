@@ -1,60 +1,457 @@
-use crate::crypto::keys::KeyPair;
+use crate::crypto::keys::{DerivationPath, ExtendedKey, KeyPair, PublicKey};
+use crate::crypto::mnemonic::MnemonicError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use zeroize::Zeroizing;
 
-/// Encrypted keystore for wallet management
+/// Current keystore format: secret encrypted under a password
+pub const KEYSTORE_VERSION: u32 = 2;
+
+/// Legacy format: secret key stored as plain hex
+pub const LEGACY_KEYSTORE_VERSION: u32 = 1;
+
+const KDF_ARGON2ID: &str = "argon2id";
+const CIPHER_XCHACHA20_POLY1305: &str = "xchacha20-poly1305";
+const SALT_LEN: usize = 16;
+
+/// Additional data distinguishing the two encrypted envelopes
+const SECRET_AAD: &[u8] = b"";
+const SEED_AAD: &[u8] = b"seed";
+
+/// Label of the account HD keystores start with
+pub const DEFAULT_ACCOUNT_LABEL: &str = "default";
+
+/// Wallet keystore, saved as JSON
+///
+/// Version 2 keystores encrypt the secret key with XChaCha20-Poly1305
+/// under a key stretched from the password with argon2id. Version 1
+/// (legacy) keystores hold the secret as plain hex; they still load, and
+/// [`KeyStore::upgrade`] re-encrypts them.
+///
+/// HD keystores additionally hold the encrypted BIP39 seed and the
+/// accounts derived from it, so fresh receive keys never need a backup of
+/// their own. Their main key is the seed's SLIP-0010 master key, or the
+/// key at `path` for a seed imported from another Ed25519 wallet; receive
+/// keys always follow Rhiza's own paths.
 #[derive(Serialize, Deserialize)]
 pub struct KeyStore {
-    /// Hex-encoded encrypted secret key (for simplicity, using plain encoding in prototype)
-    secret_key_hex: String,
+    /// Format version (absent in legacy files)
+    #[serde(default = "legacy_version")]
+    version: u32,
+    /// Hex-encoded secret key (legacy plaintext keystores only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret_key_hex: Option<Zeroizing<String>>,
+    /// The encrypted secret key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crypto: Option<EncryptedSecret>,
+    /// The encrypted BIP39 seed (HD keystores only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<EncryptedSecret>,
+    /// Where the main key sits in the seed's tree (HD keystores only)
+    #[serde(default, skip_serializing_if = "DerivationPath::is_master")]
+    path: DerivationPath,
+    /// Accounts derived from the seed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    accounts: Vec<Account>,
     /// The public key hex for identification
     public_key_hex: String,
     /// Creation timestamp
     created_at: String,
 }
 
+/// An HD account and the receive keys handed out from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    /// Account number in the derivation path
+    pub index: u32,
+    pub label: String,
+    /// Public keys of the receive keys derived so far, in index order
+    pub receive_keys: Vec<PublicKey>,
+}
+
+fn legacy_version() -> u32 {
+    LEGACY_KEYSTORE_VERSION
+}
+
+/// Password-encrypted secret key envelope
+#[derive(Clone, Serialize, Deserialize)]
+struct EncryptedSecret {
+    cipher: String,
+    /// Hex XChaCha20-Poly1305 nonce (24 bytes)
+    nonce: String,
+    /// Hex ciphertext of the secret key, tag included
+    ciphertext: String,
+    kdf: String,
+    kdf_params: KdfParams,
+    /// Hex argon2id salt
+    salt: String,
+}
+
+/// argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    /// Number of passes
+    pub t_cost: u32,
+    /// Degree of parallelism
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 passes, 1 lane
+    fn default() -> Self {
+        KdfParams {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Stretch `password` into a 256-bit key
+    fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, KeyStoreError> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|_| KeyStoreError::InvalidKdfParams)?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, key.as_mut())
+            .map_err(|_| KeyStoreError::InvalidKdfParams)?;
+        Ok(key)
+    }
+}
+
 impl KeyStore {
-    /// Create a new keystore from a keypair
+    /// Create a legacy, unencrypted keystore from a keypair
+    ///
+    /// Prefer [`KeyStore::encrypt`]; this exists for tooling and tests.
     pub fn from_keypair(keypair: &KeyPair) -> Self {
         KeyStore {
-            secret_key_hex: hex::encode(keypair.secret_bytes()),
+            version: LEGACY_KEYSTORE_VERSION,
+            secret_key_hex: Some(keypair.secret_hex()),
+            crypto: None,
+            seed: None,
+            path: DerivationPath::master(),
+            accounts: Vec::new(),
             public_key_hex: keypair.public_key.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
 
+    /// Create a legacy keystore for the key a BIP39 mnemonic restores
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, KeyStoreError> {
+        let keypair = KeyPair::from_mnemonic(phrase, passphrase).map_err(KeyStoreError::Mnemonic)?;
+        Ok(Self::from_keypair(&keypair))
+    }
+
+    /// Create an encrypted keystore with the default KDF parameters
+    pub fn encrypt(keypair: &KeyPair, password: &str) -> Result<Self, KeyStoreError> {
+        Self::encrypt_with_params(keypair, password, KdfParams::default())
+    }
+
+    /// Create an encrypted keystore with explicit KDF parameters
+    pub fn encrypt_with_params(
+        keypair: &KeyPair,
+        password: &str,
+        kdf_params: KdfParams,
+    ) -> Result<Self, KeyStoreError> {
+        let mut keystore = Self::from_keypair(keypair);
+        keystore.seal(keypair, password, kdf_params)?;
+        Ok(keystore)
+    }
+
+    /// Create an encrypted HD keystore from a BIP39 seed
+    pub fn encrypt_seed(seed: &[u8; 64], password: &str) -> Result<Self, KeyStoreError> {
+        Self::encrypt_seed_with_params(seed, password, KdfParams::default())
+    }
+
+    /// Create an encrypted HD keystore with explicit KDF parameters
+    ///
+    /// It starts with a single, empty account 0.
+    pub fn encrypt_seed_with_params(
+        seed: &[u8; 64],
+        password: &str,
+        kdf_params: KdfParams,
+    ) -> Result<Self, KeyStoreError> {
+        Self::encrypt_seed_at(seed, DerivationPath::master(), password, kdf_params)
+    }
+
+    /// Create an encrypted HD keystore whose main key is the one at `path`,
+    /// for a seed imported from another wallet
+    pub fn encrypt_seed_at(
+        seed: &[u8; 64],
+        path: DerivationPath,
+        password: &str,
+        kdf_params: KdfParams,
+    ) -> Result<Self, KeyStoreError> {
+        let keypair = ExtendedKey::master(seed).derive(path.indices()).keypair();
+        let mut keystore = Self::encrypt_with_params(&keypair, password, kdf_params)?;
+        keystore.path = path;
+        keystore.seed = Some(keystore.seal_bytes(seed, password, kdf_params, SEED_AAD)?);
+        keystore.accounts.push(Account {
+            index: 0,
+            label: DEFAULT_ACCOUNT_LABEL.to_string(),
+            receive_keys: Vec::new(),
+        });
+        Ok(keystore)
+    }
+
+    /// Encrypt a legacy keystore under `password`, keeping its metadata
+    ///
+    /// Already encrypted keystores are returned unchanged.
+    pub fn upgrade(mut self, password: &str) -> Result<Self, KeyStoreError> {
+        if self.is_encrypted() {
+            return Ok(self);
+        }
+        let keypair = self.to_keypair()?;
+        self.seal(&keypair, password, KdfParams::default())?;
+        Ok(self)
+    }
+
+    /// Replace the stored secret with its encryption under `password`
+    fn seal(&mut self, keypair: &KeyPair, password: &str, kdf_params: KdfParams) -> Result<(), KeyStoreError> {
+        self.version = KEYSTORE_VERSION;
+        self.crypto = Some(self.seal_bytes(keypair.secret_bytes().as_ref(), password, kdf_params, SECRET_AAD)?);
+        self.secret_key_hex = None;
+        Ok(())
+    }
+
+    /// Encrypt `plaintext` under `password`, bound to this keystore's header
+    fn seal_bytes(
+        &self,
+        plaintext: &[u8],
+        password: &str,
+        kdf_params: KdfParams,
+        purpose: &[u8],
+    ) -> Result<EncryptedSecret, KeyStoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = kdf_params.derive_key(password, &salt)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let aad = self.associated_data(purpose);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+            .encrypt(&nonce, Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| KeyStoreError::InvalidKey)?;
+
+        Ok(EncryptedSecret {
+            cipher: CIPHER_XCHACHA20_POLY1305.to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+            kdf: KDF_ARGON2ID.to_string(),
+            kdf_params,
+            salt: hex::encode(salt),
+        })
+    }
+
+    /// Decrypt an envelope sealed by [`KeyStore::seal_bytes`]
+    fn open_bytes(
+        &self,
+        crypto: &EncryptedSecret,
+        password: &str,
+        purpose: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, KeyStoreError> {
+        if crypto.kdf != KDF_ARGON2ID || crypto.cipher != CIPHER_XCHACHA20_POLY1305 {
+            return Err(KeyStoreError::UnsupportedCipher);
+        }
+
+        let salt = hex::decode(&crypto.salt).map_err(|_| KeyStoreError::InvalidKey)?;
+        let nonce = hex::decode(&crypto.nonce).map_err(|_| KeyStoreError::InvalidKey)?;
+        let ciphertext = hex::decode(&crypto.ciphertext).map_err(|_| KeyStoreError::InvalidKey)?;
+        if nonce.len() != 24 {
+            return Err(KeyStoreError::InvalidKey);
+        }
+
+        let key = crypto.kdf_params.derive_key(password, &salt)?;
+        let aad = self.associated_data(purpose);
+        let plaintext = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KeyStoreError::WrongPassword)?;
+        Ok(Zeroizing::new(plaintext))
+    }
+
+    /// Header fields bound to the ciphertext, so they can't be swapped
+    ///
+    /// `purpose` keeps the secret key and seed envelopes from being
+    /// swapped with each other.
+    fn associated_data(&self, purpose: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::new();
+        aad.extend_from_slice(&self.version.to_be_bytes());
+        aad.extend_from_slice(self.public_key_hex.as_bytes());
+        aad.extend_from_slice(purpose);
+        aad
+    }
+
     /// Save the keystore to a file
+    ///
+    /// The file is written next to the target and renamed over it, so a
+    /// crash never leaves a truncated wallet; on Unix it is readable by the
+    /// owner only.
     pub fn save(&self, path: &Path) -> Result<(), KeyStoreError> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).map_err(KeyStoreError::Io)?;
         }
-        let json = serde_json::to_string_pretty(self).map_err(KeyStoreError::Serialize)?;
-        fs::write(path, json).map_err(KeyStoreError::Io)?;
+        let json = Zeroizing::new(serde_json::to_string_pretty(self).map_err(KeyStoreError::Serialize)?);
+
+        let tmp = path.with_extension("json.tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp).map_err(KeyStoreError::Io)?;
+        file.write_all(json.as_bytes()).map_err(KeyStoreError::Io)?;
+        file.sync_all().map_err(KeyStoreError::Io)?;
+        fs::rename(&tmp, path).map_err(KeyStoreError::Io)?;
         Ok(())
     }
 
     /// Load a keystore from a file
     pub fn load(path: &Path) -> Result<Self, KeyStoreError> {
-        let data = fs::read_to_string(path).map_err(KeyStoreError::Io)?;
+        let data = Zeroizing::new(fs::read_to_string(path).map_err(KeyStoreError::Io)?);
         let ks: KeyStore = serde_json::from_str(&data).map_err(KeyStoreError::Deserialize)?;
+        if ks.version > KEYSTORE_VERSION {
+            return Err(KeyStoreError::UnsupportedVersion(ks.version));
+        }
         Ok(ks)
     }
 
-    /// Recover the keypair from stored data
+    /// Whether the secret key is password-encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.crypto.is_some()
+    }
+
+    /// Format version of this keystore
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Recover the keypair from a legacy, unencrypted keystore
     pub fn to_keypair(&self) -> Result<KeyPair, KeyStoreError> {
-        let bytes = hex::decode(&self.secret_key_hex)
+        let secret_hex = self.secret_key_hex.as_ref().ok_or(KeyStoreError::Locked)?;
+        let mut secret = Zeroizing::new([0u8; 32]);
+        hex::decode_to_slice(secret_hex.as_bytes(), secret.as_mut())
             .map_err(|_| KeyStoreError::InvalidKey)?;
-        let arr: [u8; 32] = bytes
+        self.checked(KeyPair::from_secret_bytes(&secret))
+    }
+
+    /// Recover the keypair, decrypting with `password` if the keystore is
+    /// encrypted (legacy keystores ignore the password)
+    pub fn unlock(&self, password: &str) -> Result<KeyPair, KeyStoreError> {
+        let Some(crypto) = &self.crypto else {
+            return self.to_keypair();
+        };
+        let plaintext = self.open_bytes(crypto, password, SECRET_AAD)?;
+        let secret: &[u8; 32] = plaintext
+            .as_slice()
             .try_into()
             .map_err(|_| KeyStoreError::InvalidKey)?;
-        Ok(KeyPair::from_secret_bytes(&arr))
+        self.checked(KeyPair::from_secret_bytes(secret))
+    }
+
+    /// Whether the keystore holds an HD seed
+    pub fn is_hd(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    /// Decrypt the HD seed's master key
+    pub fn unlock_seed(&self, password: &str) -> Result<ExtendedKey, KeyStoreError> {
+        let crypto = self.seed.as_ref().ok_or(KeyStoreError::NotHd)?;
+        let seed = self.open_bytes(crypto, password, SEED_AAD)?;
+        let master = ExtendedKey::master(&seed);
+        self.checked(master.derive(self.path.indices()).keypair())?;
+        Ok(master)
+    }
+
+    /// Where the main key sits in the seed's tree
+    pub fn path(&self) -> &DerivationPath {
+        &self.path
+    }
+
+    /// Re-encrypt the keystore under `new_password`, keeping its accounts
+    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), KeyStoreError> {
+        let keypair = self.unlock(old_password)?;
+        let seed = match &self.seed {
+            Some(crypto) => Some(self.open_bytes(crypto, old_password, SEED_AAD)?),
+            None => None,
+        };
+        let kdf_params = self.crypto.as_ref().map(|c| c.kdf_params).unwrap_or_default();
+
+        self.seal(&keypair, new_password, kdf_params)?;
+        if let Some(seed) = seed {
+            self.seed = Some(self.seal_bytes(&seed, new_password, kdf_params, SEED_AAD)?);
+        }
+        Ok(())
+    }
+
+    /// Accounts derived from the HD seed
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Add an account with the next free number and return that number
+    pub fn add_account(&mut self, label: &str) -> Result<u32, KeyStoreError> {
+        if !self.is_hd() {
+            return Err(KeyStoreError::NotHd);
+        }
+        let index = self.accounts.iter().map(|a| a.index + 1).max().unwrap_or(0);
+        self.accounts.push(Account {
+            index,
+            label: label.to_string(),
+            receive_keys: Vec::new(),
+        });
+        Ok(index)
+    }
+
+    /// Derive the next unused receive key of `account` and record it
+    ///
+    /// Save the keystore afterwards so the key isn't handed out twice.
+    pub fn next_receive_key(&mut self, password: &str, account: u32) -> Result<KeyPair, KeyStoreError> {
+        let master = self.unlock_seed(password)?;
+        let entry = self
+            .accounts
+            .iter_mut()
+            .find(|a| a.index == account)
+            .ok_or(KeyStoreError::UnknownAccount(account))?;
+        let keypair = master.account_key(account, entry.receive_keys.len() as u32);
+        entry.receive_keys.push(keypair.public_key.clone());
+        Ok(keypair)
+    }
+
+    /// Make sure a recovered keypair matches the recorded public key
+    fn checked(&self, keypair: KeyPair) -> Result<KeyPair, KeyStoreError> {
+        if keypair.public_key.to_string() != self.public_key_hex {
+            return Err(KeyStoreError::InvalidKey);
+        }
+        Ok(keypair)
     }
 
     /// Get the public key hex
     pub fn public_key_hex(&self) -> &str {
         &self.public_key_hex
     }
+
+    /// The wallet's public key, readable without the password
+    pub fn public_key(&self) -> Result<PublicKey, KeyStoreError> {
+        let bytes: [u8; 32] = hex::decode(&self.public_key_hex)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or(KeyStoreError::InvalidKey)?;
+        Ok(PublicKey::from_bytes(bytes))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -67,6 +464,22 @@ pub enum KeyStoreError {
     Deserialize(serde_json::Error),
     #[error("invalid key data")]
     InvalidKey,
+    #[error("invalid mnemonic: {0}")]
+    Mnemonic(MnemonicError),
+    #[error("wrong password")]
+    WrongPassword,
+    #[error("keystore is encrypted; a password is required")]
+    Locked,
+    #[error("unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unsupported keystore cipher or KDF")]
+    UnsupportedCipher,
+    #[error("invalid KDF parameters")]
+    InvalidKdfParams,
+    #[error("keystore has no HD seed")]
+    NotHd,
+    #[error("unknown account {0}")]
+    UnknownAccount(u32),
 }
 
 #[cfg(test)]
@@ -88,4 +501,134 @@ mod tests {
 
         assert_eq!(kp.public_key, recovered.public_key);
     }
+
+    /// Cheap parameters so tests stay fast
+    const TEST_KDF: KdfParams = KdfParams {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn test_encrypted_keystore_roundtrip() {
+        let kp = KeyPair::generate();
+        let ks = KeyStore::encrypt_with_params(&kp, "hunter2", TEST_KDF).unwrap();
+        assert!(ks.is_encrypted());
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("wallet.json");
+        ks.save(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains(kp.secret_hex().as_str()));
+        assert!(!saved.contains("secret_key_hex"));
+
+        let loaded = KeyStore::load(&path).unwrap();
+        assert_eq!(loaded.version(), KEYSTORE_VERSION);
+        assert_eq!(loaded.public_key().unwrap(), kp.public_key);
+        assert_eq!(loaded.unlock("hunter2").unwrap().public_key, kp.public_key);
+        assert!(matches!(loaded.unlock("hunter3"), Err(KeyStoreError::WrongPassword)));
+        assert!(matches!(loaded.to_keypair(), Err(KeyStoreError::Locked)));
+    }
+
+    #[test]
+    fn test_tampered_header_is_rejected() {
+        let kp = KeyPair::generate();
+        let mut ks = KeyStore::encrypt_with_params(&kp, "pw", TEST_KDF).unwrap();
+        ks.public_key_hex = KeyPair::generate().public_key.to_string();
+        assert!(matches!(ks.unlock("pw"), Err(KeyStoreError::WrongPassword)));
+    }
+
+    #[test]
+    fn test_legacy_keystore_upgrade() {
+        let kp = KeyPair::generate();
+        let legacy = format!(
+            r#"{{"secret_key_hex":"{}","public_key_hex":"{}","created_at":"2024-01-01T00:00:00Z"}}"#,
+            kp.secret_hex().as_str(),
+            kp.public_key
+        );
+        let ks: KeyStore = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(ks.version(), LEGACY_KEYSTORE_VERSION);
+        assert!(!ks.is_encrypted());
+        assert_eq!(ks.unlock("").unwrap().public_key, kp.public_key);
+
+        let upgraded = ks.upgrade("pw").unwrap();
+        assert!(upgraded.is_encrypted());
+        assert_eq!(upgraded.created_at, "2024-01-01T00:00:00Z");
+        assert_eq!(upgraded.unlock("pw").unwrap().public_key, kp.public_key);
+    }
+
+    #[test]
+    fn test_keystore_from_mnemonic() {
+        let (kp, mnemonic) = KeyPair::generate_with_mnemonic();
+        let ks = KeyStore::from_mnemonic(mnemonic.phrase(), "").unwrap();
+        assert_eq!(ks.public_key_hex(), kp.public_key.to_string());
+        assert!(matches!(
+            KeyStore::from_mnemonic("not a mnemonic", ""),
+            Err(KeyStoreError::Mnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn test_hd_keystore_accounts() {
+        let seed = [9u8; 64];
+        let mut ks = KeyStore::encrypt_seed_with_params(&seed, "pw", TEST_KDF).unwrap();
+        assert!(ks.is_hd());
+        assert_eq!(ks.public_key().unwrap(), ExtendedKey::master(&seed).keypair().public_key);
+        assert_eq!(ks.accounts().len(), 1);
+
+        let first = ks.next_receive_key("pw", 0).unwrap();
+        let second = ks.next_receive_key("pw", 0).unwrap();
+        assert_ne!(first.public_key, second.public_key);
+        assert_eq!(first.public_key, ExtendedKey::master(&seed).account_key(0, 0).public_key);
+        assert_eq!(
+            ks.accounts()[0].receive_keys,
+            vec![first.public_key.clone(), second.public_key.clone()]
+        );
+
+        assert_eq!(ks.add_account("savings").unwrap(), 1);
+        let savings = ks.next_receive_key("pw", 1).unwrap();
+        assert_eq!(savings.public_key, ExtendedKey::master(&seed).account_key(1, 0).public_key);
+        assert!(matches!(ks.next_receive_key("pw", 5), Err(KeyStoreError::UnknownAccount(5))));
+        assert!(matches!(ks.next_receive_key("nope", 0), Err(KeyStoreError::WrongPassword)));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("wallet.json");
+        ks.save(&path).unwrap();
+        let mut loaded = KeyStore::load(&path).unwrap();
+        assert_eq!(loaded.accounts(), ks.accounts());
+
+        loaded.change_password("pw", "pw2").unwrap();
+        assert!(matches!(loaded.unlock_seed("pw"), Err(KeyStoreError::WrongPassword)));
+        let third = loaded.next_receive_key("pw2", 0).unwrap();
+        assert_eq!(third.public_key, ExtendedKey::master(&seed).account_key(0, 2).public_key);
+    }
+
+    #[test]
+    fn test_imported_seed_keeps_its_path() {
+        let seed = [9u8; 64];
+        let path = DerivationPath::solana(0);
+        let ks = KeyStore::encrypt_seed_at(&seed, path.clone(), "pw", TEST_KDF).unwrap();
+        let expected = ExtendedKey::master(&seed).derive(path.indices()).keypair();
+        assert_eq!(ks.public_key().unwrap(), expected.public_key);
+
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("wallet.json");
+        ks.save(&file).unwrap();
+        let mut loaded = KeyStore::load(&file).unwrap();
+        assert_eq!(loaded.path(), &path);
+        assert_eq!(loaded.unlock("pw").unwrap().public_key, expected.public_key);
+        assert!(loaded.unlock_seed("pw").is_ok());
+        // Receive keys still follow Rhiza's paths
+        let receive = loaded.next_receive_key("pw", 0).unwrap();
+        assert_eq!(receive.public_key, ExtendedKey::master(&seed).account_key(0, 0).public_key);
+    }
+
+    #[test]
+    fn test_plain_keystore_is_not_hd() {
+        let mut ks = KeyStore::encrypt_with_params(&KeyPair::generate(), "pw", TEST_KDF).unwrap();
+        assert!(!ks.is_hd());
+        assert!(matches!(ks.add_account("x"), Err(KeyStoreError::NotHd)));
+        assert!(matches!(ks.unlock_seed("pw"), Err(KeyStoreError::NotHd)));
+    }
 }
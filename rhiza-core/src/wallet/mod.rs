@@ -1,4 +1,14 @@
 pub mod address;
+pub mod alias;
+pub mod contacts;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod keyfile;
 pub mod keystore;
+pub mod summary;
+pub mod uri;
 
 pub use address::Address;
+pub use alias::{Alias, AliasRecord, AliasRegistry};
+pub use contacts::{Contact, Contacts};
+pub use summary::SigningSummary;
+pub use uri::PaymentUri;
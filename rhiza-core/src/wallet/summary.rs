@@ -0,0 +1,385 @@
+use crate::crypto::vrf::PROOF_LEN;
+use crate::crypto::{Algorithms, Hash, HashAlgo, PublicKey, SigAlgo};
+use crate::dag::htlc::{HtlcTerms, Preimage};
+use crate::dag::transaction::{
+    BatchOutput, TransactionType, SIGNING_DOMAIN, SIGNING_VERSION, TIME_LOCK_THRESHOLD,
+};
+use crate::wallet::address::Address;
+use crate::wallet::uri::format_amount;
+
+/// Relayer, transaction ID, hop count, timestamp and signature
+const RELAY_PROOF_LEN: usize = 32 + 32 + 1 + 8 + 64;
+
+/// Channel ID, sequence and amount paid
+const CHANNEL_STATE_LEN: usize = 32 + 8 + 8;
+
+/// What a transaction commits its signer to, read back from the bytes the
+/// signature covers
+///
+/// A wallet shows this before it signs, rather than what the app that
+/// asked for the signature says it built: a compromised frontend can claim
+/// one recipient and hand over bytes paying another, but it can't make the
+/// bytes read differently here.
+#[derive(Debug, Clone)]
+pub struct SigningSummary {
+    /// The transaction ID the bytes hash to
+    pub id: Hash,
+    pub network_id: u32,
+    pub tx_type: TransactionType,
+    pub parents: usize,
+    pub sender: PublicKey,
+    pub recipient: PublicKey,
+    pub amount: u64,
+    pub fee: u64,
+    pub timestamp: u64,
+    pub memo: Option<String>,
+    pub fee_payer: Option<PublicKey>,
+    pub relay_tip: u64,
+    pub valid_after: u64,
+    pub htlc: Option<HtlcTerms>,
+    pub algorithms: Algorithms,
+    pub outputs: Vec<BatchOutput>,
+}
+
+impl SigningSummary {
+    /// Decode [`to_signing_bytes`](crate::dag::TransactionData::to_signing_bytes)
+    /// output, refusing anything that isn't exactly one transaction in the
+    /// current encoding
+    pub fn from_signing_bytes(bytes: &[u8]) -> Result<Self, SummaryError> {
+        let mut reader = Reader { bytes };
+        if reader.take(SIGNING_DOMAIN.len())? != SIGNING_DOMAIN {
+            return Err(SummaryError::Domain);
+        }
+        let version = reader.u8()?;
+        if version != SIGNING_VERSION {
+            return Err(SummaryError::Version(version));
+        }
+        let network_id = reader.u32()?;
+        let tag = reader.u8()?;
+        let tx_type = TransactionType::from_signing_tag(tag).ok_or(SummaryError::Tag("type", tag))?;
+        let parents = reader.u32()? as usize;
+        reader.skip(parents, 32)?;
+        let sender = PublicKey::from_bytes(reader.array()?);
+        let recipient = PublicKey::from_bytes(reader.array()?);
+        let amount = reader.u64()?;
+        let fee = reader.u64()?;
+        let timestamp = reader.u64()?;
+        let _nonce = reader.u64()?;
+        let memo = match reader.flag()? {
+            true => {
+                let len = reader.u32()? as usize;
+                let memo = std::str::from_utf8(reader.take(len)?).map_err(|_| SummaryError::Memo)?;
+                Some(memo.to_string())
+            }
+            false => None,
+        };
+        let fee_payer = match reader.flag()? {
+            true => Some(PublicKey::from_bytes(reader.array()?)),
+            false => None,
+        };
+        let proofs = reader.u32()? as usize;
+        reader.skip(proofs, RELAY_PROOF_LEN)?;
+        let relay_tip = reader.u64()?;
+        if reader.flag()? {
+            reader.skip(1, CHANNEL_STATE_LEN)?;
+            if reader.flag()? {
+                reader.skip(1, 64)?;
+            }
+        }
+        if reader.flag()? {
+            reader.skip(1, PROOF_LEN)?;
+        }
+        let valid_after = reader.u64()?;
+        let htlc = match reader.flag()? {
+            true => Some(match reader.u8()? {
+                0 => HtlcTerms::Lock { hash_lock: Hash::from_bytes(reader.array()?), timeout: reader.u64()? },
+                1 => HtlcTerms::Claim {
+                    lock: Hash::from_bytes(reader.array()?),
+                    preimage: Preimage::from_bytes(reader.array()?),
+                },
+                2 => HtlcTerms::Refund { lock: Hash::from_bytes(reader.array()?) },
+                kind => return Err(SummaryError::Tag("HTLC", kind)),
+            }),
+            false => None,
+        };
+        let tag = reader.u8()?;
+        let hash = HashAlgo::from_signing_tag(tag).ok_or(SummaryError::Tag("hash", tag))?;
+        let tag = reader.u8()?;
+        let sig = SigAlgo::from_signing_tag(tag).ok_or(SummaryError::Tag("signature", tag))?;
+        let mut outputs = Vec::new();
+        for _ in 0..reader.u32()? {
+            outputs.push(BatchOutput {
+                recipient: PublicKey::from_bytes(reader.array()?),
+                amount: reader.u64()?,
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(SummaryError::Trailing(reader.bytes.len()));
+        }
+
+        Ok(SigningSummary {
+            id: hash.digest(bytes),
+            network_id,
+            tx_type,
+            parents,
+            sender,
+            recipient,
+            amount,
+            fee,
+            timestamp,
+            memo,
+            fee_payer,
+            relay_tip,
+            valid_after,
+            htlc,
+            algorithms: Algorithms { hash, sig },
+            outputs,
+        })
+    }
+
+    /// What the sender's balance goes down by: amount, fee unless someone
+    /// else pays it, and relay tip
+    pub fn total_debit(&self) -> u128 {
+        let fee = if self.fee_payer.is_none() { self.fee } else { 0 };
+        self.amount as u128 + fee as u128 + self.relay_tip as u128
+    }
+
+    /// Labelled lines to show, always in this order and wording
+    ///
+    /// Keys are shown as addresses, amounts in RHZ; a batch has a To and
+    /// Amount line for each output, and always its total. The memo has
+    /// control and invisible characters escaped, so it can't hide text or
+    /// pose as other lines.
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        let address = |key: &PublicKey| Address::from_public_key(key).to_string();
+        let rhz = |units: u64| format!("{} RHZ", format_amount(units));
+
+        let network = match crate::network_name(self.network_id) {
+            Some(name) => format!("{} ({})", name, self.network_id),
+            None => format!("network {}", self.network_id),
+        };
+        let batch = self.tx_type == TransactionType::Batch;
+        let mut lines = vec![
+            ("Network", network),
+            ("Type", format!("{:?}", self.tx_type)),
+            ("From", address(&self.sender)),
+        ];
+        if batch {
+            lines.push(("Recipients", self.outputs.len().to_string()));
+            for output in &self.outputs {
+                lines.push(("To", address(&output.recipient)));
+                lines.push(("Amount", rhz(output.amount)));
+            }
+        } else {
+            lines.push(("To", address(&self.recipient)));
+            lines.push(("Amount", rhz(self.amount)));
+        }
+        if self.fee > 0 {
+            lines.push(("Fee", format!("{} (burned)", rhz(self.fee))));
+        }
+        if let Some(payer) = &self.fee_payer {
+            lines.push(("Fee payer", address(payer)));
+        }
+        if self.relay_tip > 0 {
+            lines.push(("Relay tip", rhz(self.relay_tip)));
+        }
+        if batch || self.total_debit() != self.amount as u128 {
+            let total = u64::try_from(self.total_debit()).unwrap_or(u64::MAX);
+            lines.push(("Total", rhz(total)));
+        }
+        if let Some(memo) = &self.memo {
+            lines.push(("Memo", memo.escape_debug().to_string()));
+        }
+        if self.valid_after > 0 {
+            lines.push(("Locked until", describe_lock(self.valid_after)));
+        }
+        match &self.htlc {
+            Some(HtlcTerms::Lock { hash_lock, timeout }) => {
+                lines.push(("Hash lock", hash_lock.to_string()));
+                lines.push(("Refundable", format!("from {}", describe_lock(*timeout))));
+            }
+            Some(HtlcTerms::Claim { lock, .. }) => lines.push(("Claims lock", lock.to_string())),
+            Some(HtlcTerms::Refund { lock }) => lines.push(("Refunds lock", lock.to_string())),
+            None => {}
+        }
+        if self.algorithms != Algorithms::CURRENT {
+            lines.push(("Algorithms", self.algorithms.to_string()));
+        }
+        lines.push(("Transaction", self.id.to_string()));
+        lines
+    }
+}
+
+/// A time lock as a DAG depth or UTC time
+fn describe_lock(lock: u64) -> String {
+    if lock < TIME_LOCK_THRESHOLD {
+        return format!("depth {}", lock);
+    }
+    match chrono::DateTime::from_timestamp_millis(lock as i64) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => format!("{} ms", lock),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SummaryError> {
+        if self.bytes.len() < len {
+            return Err(SummaryError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    /// Pass over `count` items of `len` bytes
+    fn skip(&mut self, count: usize, len: usize) -> Result<(), SummaryError> {
+        self.take(count.checked_mul(len).ok_or(SummaryError::Truncated)?).map(|_| ())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SummaryError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, SummaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SummaryError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, SummaryError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// An `Option`'s presence byte
+    fn flag(&mut self) -> Result<bool, SummaryError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(SummaryError::Tag("presence", byte)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SummaryError {
+    #[error("not the signing bytes of a transaction")]
+    Domain,
+    #[error("signing encoding version {0} is unknown; this wallet reads version {SIGNING_VERSION}")]
+    Version(u8),
+    #[error("the signing bytes end early")]
+    Truncated,
+    #[error("{0} bytes follow the transaction")]
+    Trailing(usize),
+    #[error("unknown {0} tag {1}")]
+    Tag(&'static str, u8),
+    #[error("the memo isn't valid UTF-8")]
+    Memo,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+    use crate::dag::transaction::Transaction;
+
+    fn line<'a>(lines: &'a [(&str, String)], label: &str) -> Option<&'a str> {
+        lines.iter().find(|(l, _)| *l == label).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_summary_reads_what_is_signed() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let parents = [Hash::digest(b"a"), Hash::digest(b"b"), Hash::digest(b"c")];
+        let tx = Transaction::transfer_with_tip(
+            &alice,
+            bob.public_key.clone(),
+            150_000_000,
+            Some("rent\u{202e}gnp.exe\nFee: 0".to_string()),
+            5,
+            parents,
+            7,
+        )
+        .for_network(crate::DEVNET_ID, &alice)
+        .with_fee(1_000, &alice)
+        .with_valid_after(1_000, &alice);
+
+        let summary = SigningSummary::from_signing_bytes(&tx.data.to_signing_bytes()).unwrap();
+        assert_eq!(summary.id, tx.id);
+        assert_eq!((summary.network_id, &summary.tx_type), (crate::DEVNET_ID, &TransactionType::Transfer));
+        assert_eq!((&summary.sender, &summary.recipient), (&alice.public_key, &bob.public_key));
+        assert_eq!((summary.amount, summary.fee, summary.relay_tip), (150_000_000, 1_000, 5));
+        assert_eq!((summary.parents, summary.valid_after, summary.timestamp), (3, 1_000, tx.data.timestamp));
+        assert_eq!(summary.total_debit(), 150_001_005);
+
+        let lines = summary.lines();
+        assert_eq!(line(&lines, "Network"), Some("devnet (3)"));
+        assert_eq!(line(&lines, "To"), Some(Address::from_public_key(&bob.public_key).as_str()));
+        assert_eq!(line(&lines, "Amount"), Some("1.5 RHZ"));
+        assert_eq!(line(&lines, "Total"), Some("1.50001005 RHZ"));
+        assert_eq!(line(&lines, "Memo"), Some("rent\\u{202e}gnp.exe\\nFee: 0"));
+        assert_eq!(line(&lines, "Locked until"), Some("depth 1000"));
+        assert_eq!(line(&lines, "Transaction"), Some(tx.id.to_string().as_str()));
+        assert_eq!(line(&lines, "Algorithms"), None);
+
+        let lock = Transaction::htlc_lock(&alice, bob.public_key, 10, Hash::digest(b"s"), 99, parents, 1);
+        let summary = SigningSummary::from_signing_bytes(&lock.data.to_signing_bytes()).unwrap();
+        assert!(matches!(summary.htlc, Some(HtlcTerms::Lock { timeout: 99, .. })));
+        assert_eq!(line(&summary.lines(), "Refundable"), Some("from depth 99"));
+    }
+
+    #[test]
+    fn test_summary_lists_batch_outputs() {
+        let alice = KeyPair::generate();
+        let [bob, carol] = [KeyPair::generate().public_key, KeyPair::generate().public_key];
+        let outputs = vec![
+            BatchOutput { recipient: bob.clone(), amount: 100_000_000 },
+            BatchOutput { recipient: carol.clone(), amount: 50_000_000 },
+        ];
+        let tx = Transaction::batch(&alice, outputs.clone(), None, [Hash::zero(), Hash::zero()], 1);
+
+        let summary = SigningSummary::from_signing_bytes(&tx.data.to_signing_bytes()).unwrap();
+        assert_eq!(summary.id, tx.id);
+        assert_eq!(summary.outputs, outputs);
+
+        let lines = summary.lines();
+        let labels: Vec<&str> = lines.iter().map(|(label, _)| *label).collect();
+        assert_eq!(
+            labels,
+            ["Network", "Type", "From", "Recipients", "To", "Amount", "To", "Amount", "Total", "Transaction"]
+        );
+        assert_eq!(lines[6].1, Address::from_public_key(&carol).to_string());
+        assert_eq!(lines[7].1, "0.5 RHZ");
+        assert_eq!(line(&lines, "Total"), Some("1.5 RHZ"));
+    }
+
+    #[test]
+    fn test_summary_refuses_malformed_bytes() {
+        let kp = KeyPair::generate();
+        let tx = Transaction::transfer(&kp, kp.public_key.clone(), 1, [Hash::zero(), Hash::zero()], 1);
+        let bytes = tx.data.to_signing_bytes();
+
+        let read = |bytes: &[u8]| SigningSummary::from_signing_bytes(bytes);
+        assert!(matches!(read(&bytes[..bytes.len() - 1]), Err(SummaryError::Truncated)));
+        assert!(matches!(read(&[bytes.as_slice(), &[0]].concat()), Err(SummaryError::Trailing(1))));
+        let mut other = bytes.clone();
+        other[0] = b'X';
+        assert!(matches!(read(&other), Err(SummaryError::Domain)));
+        let mut other = bytes.clone();
+        other[SIGNING_DOMAIN.len()] = SIGNING_VERSION + 1;
+        assert!(matches!(read(&other), Err(SummaryError::Version(_))));
+        let mut other = bytes.clone();
+        other[SIGNING_DOMAIN.len() + 5] = 200;
+        assert!(matches!(read(&other), Err(SummaryError::Tag("type", 200))));
+        // A parent count claiming more than there is
+        let mut other = bytes;
+        other[SIGNING_DOMAIN.len() + 6..SIGNING_DOMAIN.len() + 10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(read(&other), Err(SummaryError::Truncated)));
+    }
+}
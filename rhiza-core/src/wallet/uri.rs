@@ -0,0 +1,193 @@
+use crate::wallet::address::{Address, AddressError};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt;
+use std::str::FromStr;
+
+/// Scheme of payment URIs
+pub const URI_SCHEME: &str = "rhiza";
+
+/// Escaped in parameter values: everything but RFC 3986 unreserved characters
+const VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// A payment request as a `rhiza:` URI, in the manner of BIP 21
+///
+/// `rhiza:rhz1...?amount=1.5&memo=invoice42` names the address, and
+/// optionally the amount in RHZ and a memo for the payment. `message` is
+/// read as the memo too, as wallets used to `bitcoin:` URIs write it. Other
+/// parameters are ignored, unless they start with `req-`: those mark
+/// something the payer must understand, so the URI is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentUri {
+    pub address: Address,
+    /// In smallest units
+    pub amount: Option<u64>,
+    pub memo: Option<String>,
+}
+
+impl PaymentUri {
+    pub fn new(address: Address) -> Self {
+        PaymentUri { address, amount: None, memo: None }
+    }
+
+    pub fn with_amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Whether `s` is meant as a payment URI, rather than an address, key or
+    /// name
+    pub fn is_uri(s: &str) -> bool {
+        s.split_once(':').is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(URI_SCHEME))
+    }
+}
+
+impl fmt::Display for PaymentUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", URI_SCHEME, self.address)?;
+        let mut separator = '?';
+        if let Some(amount) = self.amount {
+            write!(f, "{}amount={}", separator, format_amount(amount))?;
+            separator = '&';
+        }
+        if let Some(memo) = &self.memo {
+            write!(f, "{}memo={}", separator, utf8_percent_encode(memo, VALUE))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PaymentUri {
+    type Err = UriError;
+
+    /// Parse a URI; scheme and address may be in upper case, as QR codes
+    /// encode them most compactly
+    fn from_str(s: &str) -> Result<Self, UriError> {
+        let rest = match s.split_once(':') {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case(URI_SCHEME) => rest,
+            _ => return Err(UriError::Scheme),
+        };
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let address = Address::from_str(&address.to_ascii_lowercase()).map_err(UriError::Address)?;
+
+        let mut uri = PaymentUri::new(address);
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode_str(value)
+                .decode_utf8()
+                .map_err(|_| UriError::Encoding(key.to_string()))?
+                .into_owned();
+            match key {
+                "amount" => {
+                    if uri.amount.is_some() {
+                        return Err(UriError::Duplicate(key.to_string()));
+                    }
+                    uri.amount = Some(parse_amount(&value).ok_or(UriError::Amount(value))?);
+                }
+                "memo" | "message" => {
+                    if uri.memo.is_some() {
+                        return Err(UriError::Duplicate(key.to_string()));
+                    }
+                    uri.memo = Some(value);
+                }
+                key if key.starts_with("req-") => return Err(UriError::Required(key.to_string())),
+                _ => {}
+            }
+        }
+        Ok(uri)
+    }
+}
+
+/// Whole RHZ with as many decimals as the amount needs
+pub(crate) fn format_amount(units: u64) -> String {
+    let whole = units / crate::UNITS_PER_RHZ;
+    let fraction = units % crate::UNITS_PER_RHZ;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:08}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// RHZ with up to 8 decimals, in smallest units; `None` unless it is more
+/// than zero and fits
+fn parse_amount(amount: &str) -> Option<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() || fraction.len() > 8 {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fraction: u64 = format!("{:0<8}", fraction).parse().ok()?;
+    let units = whole.parse::<u64>().ok()?.checked_mul(crate::UNITS_PER_RHZ)?.checked_add(fraction)?;
+    (units > 0).then_some(units)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UriError {
+    #[error("not a {}: URI", URI_SCHEME)]
+    Scheme,
+    #[error("invalid address: {0}")]
+    Address(AddressError),
+    #[error("invalid amount {0:?}")]
+    Amount(String),
+    #[error("{0} isn't valid UTF-8 once decoded")]
+    Encoding(String),
+    #[error("{0} is given more than once")]
+    Duplicate(String),
+    #[error("unsupported required parameter {0}")]
+    Required(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyPair;
+
+    fn address() -> Address {
+        Address::from_public_key(&KeyPair::generate().public_key)
+    }
+
+    #[test]
+    fn test_build_and_parse() {
+        let address = address();
+        let bare = PaymentUri::new(address.clone());
+        assert_eq!(bare.to_string(), format!("rhiza:{}", address));
+        assert_eq!(bare.to_string().parse::<PaymentUri>().unwrap(), bare);
+
+        let uri = PaymentUri::new(address.clone()).with_amount(150_000_000).with_memo("invoice 42 & tip ☕");
+        let text = uri.to_string();
+        assert_eq!(
+            text,
+            format!("rhiza:{}?amount=1.5&memo=invoice%2042%20%26%20tip%20%E2%98%95", address)
+        );
+        assert_eq!(text.parse::<PaymentUri>().unwrap(), uri);
+
+        // Upper case, as a QR code carries it, and a BIP 21 style message
+        let shouted = format!("RHIZA:{}?message=hi&label=shop", address.to_string().to_ascii_uppercase());
+        let parsed: PaymentUri = shouted.parse().unwrap();
+        assert_eq!(parsed.address, address);
+        assert_eq!((parsed.amount, parsed.memo.as_deref()), (None, Some("hi")));
+    }
+
+    #[test]
+    fn test_bad_uris_are_refused() {
+        let address = address();
+        let parse = |query: &str| format!("rhiza:{}?{}", address, query).parse::<PaymentUri>();
+        assert!(matches!(address.to_string().parse::<PaymentUri>(), Err(UriError::Scheme)));
+        assert!(matches!("rhiza:rhz1nope".parse::<PaymentUri>(), Err(UriError::Address(_))));
+        for amount in ["0", "1.123456789", "-1", ".5", "1e3", "184467440737.1"] {
+            assert!(matches!(parse(&format!("amount={}", amount)), Err(UriError::Amount(_))), "{}", amount);
+        }
+        assert!(matches!(parse("amount=1&amount=2"), Err(UriError::Duplicate(_))));
+        assert!(matches!(parse("memo=a&message=b"), Err(UriError::Duplicate(_))));
+        assert!(matches!(parse("req-expires=1"), Err(UriError::Required(_))));
+        assert!(matches!(parse("memo=%FF"), Err(UriError::Encoding(_))));
+        assert_eq!(parse("amount=0.00000001").unwrap().amount, Some(1));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::consensus::proof::{FinalityProof, ProofPolicy};
+use crate::crypto::Hash;
+use crate::dag::proofs::InclusionProof;
+use wasm_bindgen::prelude::*;
+
+/// Check a hex-encoded finality proof (as served by a node's
+/// `/tx/:id/proof`) against a JSON [`ProofPolicy`], returning the
+/// [`ProofReport`](crate::consensus::proof::ProofReport) as JSON
+///
+/// Throws when the proof doesn't hold up.
+#[wasm_bindgen(js_name = verifyFinalityProof)]
+pub fn verify_finality_proof(proof_hex: &str, policy_json: &str) -> Result<String, JsError> {
+    let policy: ProofPolicy =
+        serde_json::from_str(policy_json).map_err(|e| JsError::new(&format!("invalid policy: {}", e)))?;
+    let bytes = hex::decode(proof_hex.trim()).map_err(|e| JsError::new(&format!("invalid proof hex: {}", e)))?;
+    let report = FinalityProof::from_bytes(&bytes)
+        .and_then(|proof| proof.verify(&policy))
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(serde_json::to_string(&report).expect("proof report serializes"))
+}
+
+/// Check a hex-encoded inclusion proof (as served by a node's
+/// `/tx/:id/inclusion`) against a JSON array of trusted tip ids and the
+/// genesis id, returning the
+/// [`InclusionReport`](crate::dag::proofs::InclusionReport) as JSON
+///
+/// Throws when the proof doesn't hold up.
+#[wasm_bindgen(js_name = verifyInclusionProof)]
+pub fn verify_inclusion_proof(proof_hex: &str, tips_json: &str, genesis_hex: &str) -> Result<String, JsError> {
+    let tips: Vec<String> =
+        serde_json::from_str(tips_json).map_err(|e| JsError::new(&format!("invalid tips: {}", e)))?;
+    let tips = tips
+        .iter()
+        .map(|tip| Hash::from_hex(tip.trim()))
+        .collect::<Result<Vec<Hash>, _>>()
+        .map_err(|e| JsError::new(&format!("invalid tip: {}", e)))?;
+    let genesis = Hash::from_hex(genesis_hex.trim()).map_err(|e| JsError::new(&format!("invalid genesis: {}", e)))?;
+    let bytes = hex::decode(proof_hex.trim()).map_err(|e| JsError::new(&format!("invalid proof hex: {}", e)))?;
+    let report = InclusionProof::from_bytes(&bytes)
+        .and_then(|proof| proof.verify(&tips, &genesis))
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(serde_json::to_string(&report).expect("inclusion report serializes"))
+}
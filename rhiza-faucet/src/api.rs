@@ -0,0 +1,172 @@
+use crate::queue::DripStatus;
+use crate::{now_ms, SharedState};
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Html;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rhiza_core::wallet::address::Address;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use tracing::info;
+
+#[derive(Serialize)]
+struct StatusResponse {
+    network_id: u32,
+    address: String,
+    public_key: String,
+    /// Faucet balance, if the node could be asked
+    balance: Option<u64>,
+    drip_amount: u64,
+    queued: usize,
+    given_today: u64,
+    daily_limit: u64,
+    recipient_cooldown_secs: u64,
+    captcha: bool,
+}
+
+#[derive(Serialize)]
+struct ChallengeResponse {
+    id: String,
+    question: String,
+}
+
+#[derive(Deserialize)]
+struct DripRequest {
+    /// Public key, `rhz1...` address or alias
+    recipient: String,
+    challenge_id: Option<String>,
+    answer: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DripResponse {
+    id: u64,
+    /// Drips ahead of this one
+    position: usize,
+}
+
+pub async fn serve(state: SharedState, listen: SocketAddr) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/api/status", get(status))
+        .route("/api/challenge", get(challenge))
+        .route("/api/drip", post(request_drip))
+        .route("/api/drip/:id", get(drip_status))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!("🚰 Faucet listening on http://{}", listen);
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+async fn index() -> Html<&'static str> {
+    Html(include_str!("../static/index.html"))
+}
+
+async fn status(State(state): State<SharedState>) -> Json<StatusResponse> {
+    let (node, public_key) = {
+        let state = state.lock().await;
+        (state.node.clone(), state.keypair.public_key.clone())
+    };
+    let balance = node.balance(&public_key).await.ok().map(|b| b.confirmed);
+
+    let state = state.lock().await;
+    Json(StatusResponse {
+        network_id: state.network_id,
+        address: Address::from_public_key(&public_key).to_string(),
+        public_key: public_key.to_string(),
+        balance,
+        drip_amount: state.config.drip_amount,
+        queued: state.queue.len(),
+        given_today: state.limiter.given_today(),
+        daily_limit: state.config.daily_limit,
+        recipient_cooldown_secs: state.config.recipient_cooldown_secs,
+        captcha: state.config.captcha,
+    })
+}
+
+async fn challenge(
+    State(state): State<SharedState>,
+) -> Result<Json<ChallengeResponse>, (StatusCode, String)> {
+    let mut state = state.lock().await;
+    if !state.config.captcha {
+        return Err((StatusCode::NOT_FOUND, "This faucet doesn't ask questions".to_string()));
+    }
+    let (id, question) = state
+        .captcha
+        .issue(now_ms())
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, "Too many open challenges; try again shortly".to_string()))?;
+    Ok(Json(ChallengeResponse { id, question }))
+}
+
+async fn request_drip(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<DripRequest>,
+) -> Result<(StatusCode, Json<DripResponse>), (StatusCode, String)> {
+    let (node, faucet_key) = {
+        let mut state = state.lock().await;
+        if state.config.captcha {
+            let (Some(id), Some(answer)) = (&request.challenge_id, &request.answer) else {
+                return Err((StatusCode::BAD_REQUEST, "Answer the challenge first".to_string()));
+            };
+            if !state.captcha.solve(id, answer, now_ms()) {
+                let message = "Wrong or expired answer; try a new question".to_string();
+                return Err((StatusCode::BAD_REQUEST, message));
+            }
+        }
+        if state.queue.is_full() {
+            return Err((StatusCode::SERVICE_UNAVAILABLE, "The faucet is busy; try again later".to_string()));
+        }
+        (state.node.clone(), state.keypair.public_key.clone())
+    };
+
+    let recipient = node
+        .resolve_recipient(request.recipient.trim())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if recipient == faucet_key {
+        return Err((StatusCode::BAD_REQUEST, "That's the faucet's own key".to_string()));
+    }
+
+    let mut state = state.lock().await;
+    let ip = client_ip(peer, &headers, state.config.behind_proxy);
+    if state.queue.is_full() {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "The faucet is busy; try again later".to_string()));
+    }
+    let now = now_ms();
+    let amount = state.config.drip_amount;
+    state
+        .limiter
+        .reserve(&recipient, ip, amount, now)
+        .map_err(|reason| (StatusCode::TOO_MANY_REQUESTS, reason))?;
+    let (id, position) = state.queue.push(recipient, ip, now);
+    Ok((StatusCode::ACCEPTED, Json(DripResponse { id, position })))
+}
+
+async fn drip_status(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Json<DripStatus>, (StatusCode, String)> {
+    let state = state.lock().await;
+    state
+        .queue
+        .status(id)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, format!("No drip {}", id)))
+}
+
+/// The address cooldowns are tracked for: the connecting one, or behind a
+/// proxy the first address it forwarded
+fn client_ip(peer: SocketAddr, headers: &HeaderMap, behind_proxy: bool) -> IpAddr {
+    let forwarded = behind_proxy
+        .then(|| headers.get("x-forwarded-for"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|first| first.trim().parse().ok());
+    forwarded.unwrap_or_else(|| peer.ip())
+}
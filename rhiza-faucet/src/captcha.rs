@@ -0,0 +1,52 @@
+use rand::Rng;
+use std::collections::HashMap;
+
+/// How long a challenge can be answered for
+const CHALLENGE_TTL_MS: u64 = 5 * 60 * 1000;
+
+/// Challenges outstanding at once; past this, new ones are refused until
+/// old ones are answered or expire
+const MAX_CHALLENGES: usize = 10_000;
+
+/// Arithmetic questions asked before a drip
+///
+/// This only makes each request cost a round trip and a little parsing; it
+/// keeps casual scripts from draining the faucet, not a determined bot, so
+/// the cooldowns and daily limit are what actually bound the outflow.
+/// Each challenge can be answered once.
+#[derive(Default)]
+pub struct Captcha {
+    /// Answer and expiry of each outstanding challenge
+    challenges: HashMap<String, (u32, u64)>,
+}
+
+impl Captcha {
+    /// A new challenge's id and question
+    pub fn issue(&mut self, now_ms: u64) -> Option<(String, String)> {
+        if self.challenges.len() >= MAX_CHALLENGES {
+            self.challenges.retain(|_, (_, expires)| *expires > now_ms);
+            if self.challenges.len() >= MAX_CHALLENGES {
+                return None;
+            }
+        }
+        let mut rng = rand::thread_rng();
+        let (a, b): (u32, u32) = (rng.gen_range(2..=20), rng.gen_range(2..=20));
+        let (question, answer) = match rng.gen_range(0..3) {
+            0 => (format!("What is {} plus {}?", a, b), a + b),
+            1 => (format!("What is {} times {}?", a, b), a * b),
+            _ => (format!("What is {} minus {}?", a.max(b), a.min(b)), a.max(b) - a.min(b)),
+        };
+        // Hex, since JSON numbers this large lose digits in a browser
+        let id = format!("{:016x}", rng.gen::<u64>());
+        self.challenges.insert(id.clone(), (answer, now_ms + CHALLENGE_TTL_MS));
+        Some((id, question))
+    }
+
+    /// Check an answer, using up the challenge either way
+    pub fn solve(&mut self, id: &str, answer: &str, now_ms: u64) -> bool {
+        match self.challenges.remove(id) {
+            Some((expected, expires)) => expires > now_ms && answer.trim().parse() == Ok(expected),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Faucet settings (`faucet.json` in the data directory)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaucetConfig {
+    /// REST API of the node drips are sent through
+    pub node_url: String,
+    /// API key token, for nodes that require one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_api_key: Option<String>,
+    /// Address the web form and API listen on
+    pub listen: String,
+    /// Units sent per drip
+    pub drip_amount: u64,
+    /// How long a recipient waits between drips, in seconds
+    pub recipient_cooldown_secs: u64,
+    /// How long a client IP waits between drips, in seconds
+    pub ip_cooldown_secs: u64,
+    /// Most units given away per UTC day, to everyone together
+    pub daily_limit: u64,
+    /// Drips waiting to be sent before new requests are turned away
+    pub max_queue: usize,
+    /// Seconds between drips, so the faucet's own payments don't crowd the
+    /// node's pending pool
+    pub drip_interval_secs: u64,
+    /// Ask an arithmetic question before each drip, so a request takes more
+    /// than a bare POST
+    pub captcha: bool,
+    /// Take the client IP from the first `X-Forwarded-For` address, for a
+    /// faucet behind a reverse proxy
+    pub behind_proxy: bool,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        FaucetConfig {
+            node_url: "http://127.0.0.1:7471".to_string(),
+            node_api_key: None,
+            listen: "127.0.0.1:7480".to_string(),
+            drip_amount: 10 * rhiza_core::UNITS_PER_RHZ,
+            recipient_cooldown_secs: 24 * 60 * 60,
+            ip_cooldown_secs: 60 * 60,
+            daily_limit: 1_000 * rhiza_core::UNITS_PER_RHZ,
+            max_queue: 100,
+            drip_interval_secs: 2,
+            captcha: true,
+            behind_proxy: false,
+        }
+    }
+}
+
+impl FaucetConfig {
+    /// Load the config from `path`, or the defaults if it doesn't exist
+    pub fn load_or_default(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::{Context, Result};
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::wallet::keyfile;
+use rhiza_core::wallet::keystore::KeyStore;
+use std::path::Path;
+
+pub use rhiza_core::wallet::keyfile::create;
+
+/// Unlock the faucet wallet
+///
+/// Only encrypted wallets are accepted: the faucet holds funds on a host
+/// that serves the public.
+pub fn unlock(path: &Path, password_file: Option<&Path>) -> Result<KeyPair> {
+    let keystore = KeyStore::load(path).with_context(|| format!("Could not load wallet {}", path.display()))?;
+    if !keystore.is_encrypted() {
+        anyhow::bail!(
+            "{} stores its secret key unencrypted; create the faucet wallet with 'init'",
+            path.display()
+        );
+    }
+    Ok(keyfile::unlock(&keystore, password_file)?)
+}
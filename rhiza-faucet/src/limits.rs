@@ -0,0 +1,117 @@
+use rhiza_core::crypto::keys::PublicKey;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Entries kept per table before expired ones are swept out
+const SWEEP_AT: usize = 10_000;
+
+/// Who may get a drip right now
+///
+/// A drip is reserved when it's queued, so a burst of requests can't all
+/// slip past the checks before the first is sent, and released again if
+/// sending it fails. Nothing is persisted: restarting the faucet forgets
+/// the cooldowns.
+pub struct Limiter {
+    recipient_cooldown_ms: u64,
+    ip_cooldown_ms: u64,
+    daily_limit: u64,
+    /// When each recipient last had a drip reserved
+    recipients: HashMap<PublicKey, u64>,
+    /// When each client IP last had a drip reserved
+    ips: HashMap<IpAddr, u64>,
+    /// UTC day `given_today` counts for
+    day: u64,
+    given_today: u64,
+}
+
+impl Limiter {
+    pub fn new(recipient_cooldown_secs: u64, ip_cooldown_secs: u64, daily_limit: u64) -> Self {
+        Limiter {
+            recipient_cooldown_ms: recipient_cooldown_secs.saturating_mul(1000),
+            ip_cooldown_ms: ip_cooldown_secs.saturating_mul(1000),
+            daily_limit,
+            recipients: HashMap::new(),
+            ips: HashMap::new(),
+            day: 0,
+            given_today: 0,
+        }
+    }
+
+    /// Reserve a drip of `amount` to `recipient` for the client at `ip`, or
+    /// say why not
+    pub fn reserve(
+        &mut self,
+        recipient: &PublicKey,
+        ip: IpAddr,
+        amount: u64,
+        now_ms: u64,
+    ) -> Result<(), String> {
+        if now_ms / DAY_MS != self.day {
+            self.day = now_ms / DAY_MS;
+            self.given_today = 0;
+        }
+        let last = self.recipients.get(recipient);
+        if let Some(wait) = Self::remaining(last, self.recipient_cooldown_ms, now_ms) {
+            return Err(format!("This recipient already had a drip; try again in {}", format_wait(wait)));
+        }
+        if let Some(wait) = Self::remaining(self.ips.get(&ip), self.ip_cooldown_ms, now_ms) {
+            return Err(format!("You already asked for a drip; try again in {}", format_wait(wait)));
+        }
+        if self.given_today.saturating_add(amount) > self.daily_limit {
+            let wait = DAY_MS - now_ms % DAY_MS;
+            return Err(format!("Today's allowance is used up; try again in {}", format_wait(wait)));
+        }
+
+        self.sweep(now_ms);
+        self.recipients.insert(recipient.clone(), now_ms);
+        self.ips.insert(ip, now_ms);
+        self.given_today += amount;
+        Ok(())
+    }
+
+    /// Hand back a reservation whose drip was never sent
+    pub fn release(&mut self, recipient: &PublicKey, ip: IpAddr, amount: u64, reserved_ms: u64) {
+        if self.recipients.get(recipient) == Some(&reserved_ms) {
+            self.recipients.remove(recipient);
+        }
+        if self.ips.get(&ip) == Some(&reserved_ms) {
+            self.ips.remove(&ip);
+        }
+        if reserved_ms / DAY_MS == self.day {
+            self.given_today = self.given_today.saturating_sub(amount);
+        }
+    }
+
+    /// Units given away (or about to be) today
+    pub fn given_today(&self) -> u64 {
+        self.given_today
+    }
+
+    fn remaining(last: Option<&u64>, cooldown_ms: u64, now_ms: u64) -> Option<u64> {
+        let until = last?.saturating_add(cooldown_ms);
+        (until > now_ms).then(|| until - now_ms)
+    }
+
+    fn sweep(&mut self, now_ms: u64) {
+        if self.recipients.len() >= SWEEP_AT {
+            let cooldown = self.recipient_cooldown_ms;
+            self.recipients.retain(|_, at| at.saturating_add(cooldown) > now_ms);
+        }
+        if self.ips.len() >= SWEEP_AT {
+            let cooldown = self.ip_cooldown_ms;
+            self.ips.retain(|_, at| at.saturating_add(cooldown) > now_ms);
+        }
+    }
+}
+
+/// A wait as hours, minutes or seconds
+fn format_wait(ms: u64) -> String {
+    let secs = ms.div_ceil(1000);
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs.div_ceil(60)),
+        _ => format!("{}h {}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
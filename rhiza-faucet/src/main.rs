@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use clap::builder::BoolishValueParser;
+use clap::{Parser, Subcommand};
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::wallet::address::Address;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+mod api;
+mod captcha;
+mod config;
+mod keyfile;
+mod limits;
+mod node;
+mod queue;
+
+use captcha::Captcha;
+use config::FaucetConfig;
+use limits::Limiter;
+use node::NodeClient;
+use queue::DripQueue;
+
+/// Faucet state shared by the API and the drip worker
+///
+/// The lock is never held across a request to the node, so a slow node
+/// doesn't stall the web form.
+pub struct FaucetState {
+    pub config: FaucetConfig,
+    /// Network of the node, checked at startup
+    pub network_id: u32,
+    pub keypair: KeyPair,
+    pub node: NodeClient,
+    pub limiter: Limiter,
+    pub captcha: Captcha,
+    pub queue: DripQueue,
+    /// Nonce of the last drip sent
+    pub last_nonce: Option<u64>,
+}
+
+pub type SharedState = Arc<Mutex<FaucetState>>;
+
+#[derive(Parser)]
+#[command(name = "rhiza-faucet", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Data directory (faucet wallet and faucet.json)
+    #[arg(long, env = "RHIZA_FAUCET_DIR", default_value = "~/.rhiza-faucet", global = true)]
+    data_dir: String,
+
+    /// Log to stdout only, without colors (for containers)
+    #[arg(long, env = "RHIZA_PLAIN_LOGS", global = true, value_parser = BoolishValueParser::new())]
+    plain_logs: bool,
+
+    /// Read the wallet password from this file instead of prompting
+    #[arg(long, env = "RHIZA_PASSWORD_FILE", global = true)]
+    password_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Create the faucet wallet and a default faucet.json
+    Init,
+
+    /// Serve the web form and API, and send drips
+    Run {
+        /// REST API of the node to send drips through (overrides faucet.json)
+        #[arg(long, env = "RHIZA_NODE_URL")]
+        node_url: Option<String>,
+
+        /// Address to listen on (overrides faucet.json)
+        #[arg(long, env = "RHIZA_FAUCET_LISTEN")]
+        listen: Option<String>,
+
+        /// Run against a mainnet node anyway
+        #[arg(long)]
+        allow_mainnet: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stdout)
+        .with_ansi(!cli.plain_logs)
+        .init();
+    let data_path = PathBuf::from(shellexpand::tilde(&cli.data_dir).to_string());
+    let wallet_path = data_path.join("wallet.json");
+    let config_path = data_path.join("faucet.json");
+    let password_file = cli.password_file.as_deref();
+
+    match cli.command {
+        Commands::Init => {
+            if wallet_path.exists() {
+                anyhow::bail!("{} already exists", wallet_path.display());
+            }
+            std::fs::create_dir_all(&data_path)
+                .with_context(|| format!("Could not create {}", data_path.display()))?;
+            let keypair = KeyPair::generate();
+            keyfile::create(&wallet_path, &keypair, password_file)?;
+            if !config_path.exists() {
+                FaucetConfig::default().save(&config_path)?;
+            }
+
+            println!();
+            println!("  🚰 Faucet wallet created in {}", data_path.display());
+            println!("  Address:    {}", Address::from_public_key(&keypair.public_key));
+            println!("  Public key: {}", keypair.public_key);
+            println!();
+            println!("  Fund it from another wallet, review {}, then run:", config_path.display());
+            println!("    rhiza-faucet run");
+            println!();
+            Ok(())
+        }
+
+        Commands::Run { node_url, listen, allow_mainnet } => {
+            let mut config = FaucetConfig::load_or_default(&config_path)
+                .with_context(|| format!("Could not read {}", config_path.display()))?;
+            if let Some(node_url) = node_url {
+                config.node_url = node_url;
+            }
+            if let Some(listen) = listen {
+                config.listen = listen;
+            }
+            run(config, &wallet_path, password_file, allow_mainnet).await
+        }
+    }
+}
+
+async fn run(
+    config: FaucetConfig,
+    wallet_path: &Path,
+    password_file: Option<&Path>,
+    allow_mainnet: bool,
+) -> Result<()> {
+    let listen: SocketAddr = config
+        .listen
+        .parse()
+        .with_context(|| format!("Invalid listen address {}", config.listen))?;
+    if config.drip_amount == 0 {
+        anyhow::bail!("drip_amount must be more than 0");
+    }
+    let keypair = keyfile::unlock(wallet_path, password_file)?;
+
+    let node = NodeClient::new(&config.node_url, config.node_api_key.as_deref());
+    let info = node
+        .info()
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .with_context(|| format!("Could not ask {} which network it's on", config.node_url))?;
+    if info.network_id == rhiza_core::MAINNET_ID && !allow_mainnet {
+        anyhow::bail!(
+            "{} is a mainnet node and a faucet gives away real funds; pass --allow-mainnet if you mean it",
+            config.node_url
+        );
+    }
+    match node.balance(&keypair.public_key).await {
+        Ok(balance) if balance.total == 0 => {
+            warn!("The faucet wallet is empty; fund {}", Address::from_public_key(&keypair.public_key))
+        }
+        Ok(balance) => info!("Faucet balance: {} units", balance.total),
+        Err(e) => warn!("Could not read the faucet balance: {}", e),
+    }
+    info!("Dripping {} units on network {} through {}", config.drip_amount, info.network_id, config.node_url);
+
+    let interval = Duration::from_secs(config.drip_interval_secs.max(1));
+    let state = Arc::new(Mutex::new(FaucetState {
+        limiter: Limiter::new(config.recipient_cooldown_secs, config.ip_cooldown_secs, config.daily_limit),
+        queue: DripQueue::new(config.max_queue),
+        captcha: Captcha::default(),
+        network_id: info.network_id,
+        keypair,
+        node,
+        last_nonce: None,
+        config,
+    }));
+    tokio::spawn(queue::run(state.clone(), interval));
+    api::serve(state, listen).await
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
@@ -0,0 +1,172 @@
+use anyhow::Result;
+use rhiza_core::crypto::keys::PublicKey;
+use rhiza_core::crypto::Hash;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Minimal async client for the node drips go through
+#[derive(Clone)]
+pub struct NodeClient {
+    base_url: String,
+    http: reqwest::Client,
+    /// Bearer token for nodes that require API keys
+    api_key: Option<String>,
+}
+
+/// What a request to the node failed with
+#[derive(Debug)]
+pub enum NodeError {
+    /// The node couldn't be reached, or answered with a server error
+    Unavailable(String),
+    /// The node turned the request down
+    Refused(String),
+}
+
+impl std::fmt::Display for NodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeError::Unavailable(message) | NodeError::Refused(message) => f.write_str(message),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeInfo {
+    pub network_id: u32,
+    pub dag_size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddressBalance {
+    pub public_key: Option<String>,
+    pub confirmed: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AliasEntry {
+    receive_public_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionResponse {
+    pub id: String,
+}
+
+impl NodeClient {
+    pub fn new(base_url: &str, api_key: Option<&str>) -> Self {
+        NodeClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(15))
+                .build()
+                .expect("HTTP client"),
+            api_key: api_key.map(str::to_string),
+        }
+    }
+
+    pub async fn info(&self) -> Result<NodeInfo, NodeError> {
+        self.get("/info").await
+    }
+
+    pub async fn balance(&self, key: &PublicKey) -> Result<AddressBalance, NodeError> {
+        self.get(&format!("/address/{}/balance", key)).await
+    }
+
+    /// Two of the node's tips for a new transaction to approve
+    pub async fn parents(&self) -> Result<[Hash; 2], NodeError> {
+        let tips: Vec<String> = self.get("/dag/tips").await?;
+        let parse = |tip: &String| {
+            Hash::from_hex(tip)
+                .map_err(|e| NodeError::Unavailable(format!("Node sent a bad tip {}: {}", tip, e)))
+        };
+        match tips.as_slice() {
+            [] => {
+                let message = "The node has no tips to approve; is it synced?".to_string();
+                Err(NodeError::Unavailable(message))
+            }
+            [only] => Ok([parse(only)?, parse(only)?]),
+            [first, second, ..] => Ok([parse(first)?, parse(second)?]),
+        }
+    }
+
+    /// Turn a hex key, `rhz1...` address or alias into the key to pay
+    ///
+    /// Addresses and aliases are looked up on the node.
+    pub async fn resolve_recipient(&self, recipient: &str) -> Result<PublicKey> {
+        if recipient.len() == 64 && recipient.chars().all(|c| c.is_ascii_hexdigit()) {
+            return parse_public_key(recipient);
+        }
+        if recipient.to_ascii_lowercase().starts_with("rhz1") {
+            let balance = self.balance_of(recipient).await?;
+            let Some(key) = balance.public_key else {
+                anyhow::bail!(
+                    "The key behind {} hasn't appeared on the DAG yet; enter its public key instead",
+                    recipient
+                );
+            };
+            return parse_public_key(&key);
+        }
+        let alias_like = recipient.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if recipient.is_empty() || !alias_like {
+            anyhow::bail!("Enter a public key, rhz1 address or alias");
+        }
+        let entry: AliasEntry = self
+            .get(&format!("/alias/{}", recipient))
+            .await
+            .map_err(|e| anyhow::anyhow!("Unknown alias {}: {}", recipient, e))?;
+        parse_public_key(&entry.receive_public_key)
+    }
+
+    async fn balance_of(&self, address: &str) -> Result<AddressBalance> {
+        self.get(&format!("/address/{}/balance", address))
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not look up {}: {}", address, e))
+    }
+
+    pub async fn submit<B: Serialize>(&self, tx: &B) -> Result<TransactionResponse, NodeError> {
+        let request = self.request(reqwest::Method::POST, "/tx/submit").json(tx);
+        self.send(request).await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, NodeError> {
+        self.send(self.request(reqwest::Method::GET, path)).await
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, NodeError> {
+        let response = request.send().await.map_err(|e| {
+            NodeError::Unavailable(format!("Could not reach node at {}: {}", self.base_url, e))
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            let message = format!("Node returned {}: {}", status, message);
+            // Being rate limited passes like an outage does
+            return Err(if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                NodeError::Unavailable(message)
+            } else {
+                NodeError::Refused(message)
+            });
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| NodeError::Unavailable(format!("Unexpected answer from node: {}", e)))
+    }
+}
+
+pub fn parse_public_key(hex_key: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid public key {}", hex_key))?;
+    Ok(PublicKey::from_bytes(bytes))
+}
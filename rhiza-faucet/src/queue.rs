@@ -0,0 +1,197 @@
+use crate::node::{NodeClient, NodeError};
+use crate::SharedState;
+use rhiza_core::crypto::keys::{KeyPair, PublicKey};
+use rhiza_core::dag::transaction::Transaction;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Finished drips whose outcome can still be looked up
+const MAX_FINISHED: usize = 10_000;
+
+/// How long the worker backs off after the node couldn't take a drip
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Refusals that pass once the node's pending pool drains: it's full, or
+/// holds as many of the faucet's payments as it allows per sender
+const TRANSIENT_REFUSALS: [&str; 2] = ["Pending pool is full", "pending transactions; wait"];
+
+/// Memo on every drip, so the recipient can tell where funds came from
+const DRIP_MEMO: &str = "Rhiza faucet";
+
+/// A drip waiting to be sent
+pub struct Drip {
+    pub id: u64,
+    pub recipient: PublicKey,
+    pub ip: IpAddr,
+    /// When it was reserved with the limiter
+    pub requested_ms: u64,
+}
+
+/// Where a drip is
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DripStatus {
+    /// Waiting behind `position` other drips
+    Queued { position: usize },
+    Sending,
+    Sent { transaction: String },
+    Failed { reason: String },
+}
+
+/// Drips in the order they're sent, and what became of recent ones
+pub struct DripQueue {
+    max_queue: usize,
+    pending: VecDeque<Drip>,
+    /// The drip the worker is sending
+    sending: Option<u64>,
+    finished: HashMap<u64, DripStatus>,
+    /// Finished ids, oldest first
+    finished_order: VecDeque<u64>,
+    next_id: u64,
+}
+
+impl DripQueue {
+    pub fn new(max_queue: usize) -> Self {
+        DripQueue {
+            max_queue,
+            pending: VecDeque::new(),
+            sending: None,
+            finished: HashMap::new(),
+            finished_order: VecDeque::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.max_queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queue a drip; returns its id and how many drips are ahead of it
+    pub fn push(&mut self, recipient: PublicKey, ip: IpAddr, requested_ms: u64) -> (u64, usize) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.push_back(Drip { id, recipient, ip, requested_ms });
+        (id, self.pending.len() - 1)
+    }
+
+    pub fn status(&self, id: u64) -> Option<DripStatus> {
+        if self.sending == Some(id) {
+            return Some(DripStatus::Sending);
+        }
+        if let Some(position) = self.pending.iter().position(|drip| drip.id == id) {
+            return Some(DripStatus::Queued { position });
+        }
+        self.finished.get(&id).cloned()
+    }
+
+    /// Take the next drip to send
+    fn next(&mut self) -> Option<Drip> {
+        let drip = self.pending.pop_front()?;
+        self.sending = Some(drip.id);
+        Some(drip)
+    }
+
+    /// Put a drip that couldn't be sent yet back at the front
+    fn retry(&mut self, drip: Drip) {
+        self.sending = None;
+        self.pending.push_front(drip);
+    }
+
+    fn finish(&mut self, id: u64, status: DripStatus) {
+        self.sending = None;
+        if self.finished_order.len() >= MAX_FINISHED {
+            if let Some(oldest) = self.finished_order.pop_front() {
+                self.finished.remove(&oldest);
+            }
+        }
+        self.finished_order.push_back(id);
+        self.finished.insert(id, status);
+    }
+}
+
+/// Send queued drips one at a time, `interval` apart
+///
+/// Drips the node can't take right now (it's unreachable, or the faucet
+/// already has as many payments pending as the node allows per sender)
+/// stay at the front of the queue and are tried again; drips it refuses
+/// outright fail, and their reservation is handed back to the limiter.
+pub async fn run(state: SharedState, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let (drip, node, keypair, amount, network_id, last_nonce) = {
+            let mut state = state.lock().await;
+            let Some(drip) = state.queue.next() else {
+                continue;
+            };
+            (
+                drip,
+                state.node.clone(),
+                state.keypair.clone(),
+                state.config.drip_amount,
+                state.network_id,
+                state.last_nonce,
+            )
+        };
+
+        let result = send(&node, &keypair, &drip.recipient, amount, network_id, last_nonce).await;
+        let mut state = state.lock().await;
+        match result {
+            Ok((id, nonce)) => {
+                info!("💧 Sent drip {} to {} in {}", drip.id, drip.recipient, id);
+                state.last_nonce = Some(nonce);
+                state.queue.finish(drip.id, DripStatus::Sent { transaction: id });
+            }
+            Err(NodeError::Refused(reason)) if !TRANSIENT_REFUSALS.iter().any(|r| reason.contains(r)) => {
+                warn!("Drip {} to {} refused: {}", drip.id, drip.recipient, reason);
+                state.limiter.release(&drip.recipient, drip.ip, amount, drip.requested_ms);
+                state.queue.finish(drip.id, DripStatus::Failed { reason });
+            }
+            Err(e) => {
+                warn!("Drip {} to {} not sent yet: {}", drip.id, drip.recipient, e);
+                state.queue.retry(drip);
+                drop(state);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Sign and submit one drip; returns its transaction id and nonce
+async fn send(
+    node: &NodeClient,
+    keypair: &KeyPair,
+    recipient: &PublicKey,
+    amount: u64,
+    network_id: u32,
+    last_nonce: Option<u64>,
+) -> Result<(String, u64), NodeError> {
+    let info = node.info().await?;
+    if info.network_id != network_id {
+        return Err(NodeError::Unavailable(format!(
+            "The node switched to network {} (the faucet started on {})",
+            info.network_id, network_id
+        )));
+    }
+    let parents = node.parents().await?;
+    // Same nonce scheme as the node's own transactions, but never reused
+    // while the DAG hasn't grown past the previous drip
+    let nonce = last_nonce.map_or(info.dag_size, |last| info.dag_size.max(last + 1));
+    let tx = Transaction::transfer_with_memo(
+        keypair,
+        recipient.clone(),
+        amount,
+        Some(DRIP_MEMO.to_string()),
+        parents,
+        nonce,
+    )
+    .for_network(network_id, keypair);
+    let response = node.submit(&tx).await?;
+    Ok((response.id, nonce))
+}
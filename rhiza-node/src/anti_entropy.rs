@@ -0,0 +1,124 @@
+use crate::SharedState;
+use rhiza_core::crypto::Hash;
+use rhiza_core::dag::transaction::Transaction;
+use rhiza_core::dag::vertex::Dag;
+use rhiza_core::network::gossip::GossipMessage;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::debug;
+
+/// How often tips are re-announced to every peer
+pub const ANTI_ENTROPY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most tips sent in, or read from, one announcement (deepest first)
+pub const MAX_ANNOUNCED_TIPS: usize = 64;
+
+/// Most transactions asked for, or served, per sync request
+pub const MAX_SYNC_REQUEST: usize = 256;
+
+/// Most synced transactions held back waiting for their parents
+const MAX_PARKED: usize = 10_000;
+
+/// Fetches the ancestry of transactions a peer knows and we don't
+///
+/// Tips announced by a peer are requested by id. Whatever arrives without
+/// its parents is parked and the parents requested in turn, until the
+/// missing history has been walked back to something already in the DAG.
+/// Ids asked for are remembered until the next anti-entropy round, so one
+/// gap isn't requested from every peer that announces it.
+#[derive(Default)]
+pub struct AncestryFetch {
+    parked: HashMap<Hash, Transaction>,
+    requested: HashSet<Hash>,
+}
+
+impl AncestryFetch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for the ids neither in the DAG, parked nor already requested
+    pub fn request(&mut self, ids: impl IntoIterator<Item = Hash>, dag: &Dag) -> Option<GossipMessage> {
+        let mut missing = Vec::new();
+        for id in ids {
+            if missing.len() == MAX_SYNC_REQUEST {
+                break;
+            }
+            if id.is_zero() || dag.contains(&id) || self.parked.contains_key(&id) {
+                continue;
+            }
+            if self.requested.insert(id) {
+                missing.push(id);
+            }
+        }
+        (!missing.is_empty()).then_some(GossipMessage::SyncRequest { missing })
+    }
+
+    /// Hold a transaction until its parents are in the DAG, false if full
+    pub fn park(&mut self, tx: Transaction) -> bool {
+        if self.parked.len() >= MAX_PARKED {
+            return false;
+        }
+        self.requested.remove(&tx.id);
+        self.parked.insert(tx.id, tx);
+        true
+    }
+
+    /// Take the parked transactions whose parents have all arrived, oldest first
+    pub fn take_ready(&mut self, dag: &Dag) -> Vec<Transaction> {
+        let ready: Vec<Hash> = self
+            .parked
+            .values()
+            .filter(|tx| missing_parents(tx, dag).is_empty())
+            .map(|tx| tx.id)
+            .collect();
+        let mut ready: Vec<Transaction> = ready.iter().filter_map(|id| self.parked.remove(id)).collect();
+        ready.sort_by_key(|tx| (tx.data.timestamp, tx.id));
+        ready
+    }
+
+    /// Number of transactions waiting for their parents
+    pub fn parked_len(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// Start a new round: forget what was requested, so gaps a peer
+    /// couldn't fill are asked for again
+    pub fn new_round(&mut self) {
+        self.requested.clear();
+    }
+}
+
+/// Parents of `tx` that aren't in the DAG yet
+pub fn missing_parents(tx: &Transaction, dag: &Dag) -> Vec<Hash> {
+    let mut missing: Vec<Hash> = tx
+        .data
+        .parents
+        .iter()
+        .filter(|p| !p.is_zero() && !dag.contains(p))
+        .copied()
+        .collect();
+    missing.dedup();
+    missing
+}
+
+/// Re-announce tips to every peer until the node shuts down
+///
+/// Peers that find unknown tips in the announcement fetch the missing
+/// ancestry, and ones that are behind start a ranged sync, so nodes that
+/// were partitioned converge once they can reach each other again.
+pub async fn run(state: SharedState) {
+    let mut interval = tokio::time::interval(ANTI_ENTROPY_INTERVAL);
+    // The first tick fires at once; peers got our tips when they connected
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let mut state = state.write().await;
+        if state.ancestry.parked_len() > 0 {
+            debug!("{} synced transactions still waiting for parents", state.ancestry.parked_len());
+        }
+        state.ancestry.new_round();
+        let announce = state.tip_announce();
+        state.network.broadcast(&announce);
+    }
+}
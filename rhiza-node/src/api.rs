@@ -1,27 +1,87 @@
-use crate::NodeState;
+use crate::{NodeState, SharedState};
+use crate::api_audit::{self, ApiAccess, ApiAuditConfig, ApiAuditEntry, ApiAuditQuery, ExportFormat};
+use crate::audit::{PeerAuditConfig, PeerAuditEntry, PeerAuditQuery};
+use crate::events::{EventBatch, EventBus, NodeEvent};
+use crate::mempool::{FeeEstimate, MAX_PENDING, MAX_PENDING_PER_SENDER};
+use crate::network::{HeldSummary, PeerSummary};
+use crate::payment_links::{self, LinkStatus, PaymentLink};
+use crate::pruning::PruningConfig;
+use crate::snapshot::{self, SnapshotJob};
+use crate::tags::{self, TagTotals};
+use crate::trace::{TraceStage, TraceStep};
+use crate::updater::UpdateStatus;
+use crate::webhooks::{Delivery, DeliveryReceipt};
+use crate::weight_audit::WeightAudit;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{get, post},
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{Html, IntoResponse, Json, Response},
+    routing::{get, post, put},
     Router,
 };
+use rhiza_core::consensus::finality::FinalityChecker;
+use rhiza_core::consensus::latency::{LatencyBucket, LatencyPercentiles};
+use rhiza_core::consensus::proof::FinalityProof;
+use rhiza_core::consensus::tip_selection::selection_output;
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::channels::ChannelStatus;
+use rhiza_core::dag::founder::FounderReport;
+use rhiza_core::dag::health::DagHealth;
+use rhiza_core::dag::htlc::{HtlcStatus, HtlcTerms};
+use rhiza_core::dag::leaderboard::{Period, RelayLeaderboard};
+use rhiza_core::dag::proofs::InclusionProof;
+use rhiza_core::dag::transaction::{lock_passed, BatchOutput, Transaction, TransactionType};
+use rhiza_core::dag::vertex::Dag;
+use rhiza_core::network::peer::PeerId;
+use rhiza_core::network::reputation::PeerScore;
+use rhiza_core::network::sync::SyncPhase;
+use rhiza_core::wallet::address::Address;
+use rhiza_core::wallet::alias::{Alias, AliasRecord};
+use rhiza_core::wallet::keystore::{KeyStore, KeyStoreError};
+use futures_util::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use zeroize::Zeroizing;
 
-type SharedState = Arc<Mutex<NodeState>>;
+/// How long the liveness probe waits for the state lock before reporting
+/// the node stuck
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// API response for node info
 #[derive(Serialize)]
 struct NodeInfoResponse {
     address: String,
     public_key: String,
+    network_id: u32,
     dag_size: usize,
     dag_depth: u64,
     balance: u64,
     balance_rhz: f64,
     total_relays: u64,
     tips_count: usize,
+    peers: usize,
+}
+
+/// API response for the minted supply
+#[derive(Serialize)]
+struct SupplyResponse {
+    max_supply: u64,
+    /// Units minted so far, by the genesis, founder allocation and relay rewards
+    minted: u64,
+    minted_rhz: f64,
+    genesis: u64,
+    founder_allocation: u64,
+    relay_rewards: u64,
+    /// Units relay rewards can still mint
+    remaining: u64,
+    remaining_rhz: f64,
 }
 
 /// API response for balance
@@ -32,18 +92,414 @@ struct BalanceResponse {
     balance_rhz: f64,
 }
 
+/// API response for an arbitrary account's balance
+#[derive(Serialize)]
+struct AddressBalanceResponse {
+    address: String,
+    /// Hex public key, if the account has appeared in the DAG
+    public_key: Option<String>,
+    /// Balance from final transactions only
+    confirmed: u64,
+    /// Balance including transactions that aren't final yet
+    total: u64,
+    /// Net change from transactions that aren't final yet
+    pending: i128,
+    confirmed_rhz: f64,
+    total_rhz: f64,
+}
+
+/// API response for a finalized alias binding
+#[derive(Serialize)]
+struct AliasResponse {
+    alias: String,
+    owner_address: String,
+    owner_public_key: String,
+    /// Where payments to the alias should go
+    receive_address: String,
+    receive_public_key: String,
+    announcement: String,
+}
+
+impl From<&AliasRecord> for AliasResponse {
+    fn from(record: &AliasRecord) -> Self {
+        AliasResponse {
+            alias: record.alias.to_string(),
+            owner_address: Address::from_public_key(&record.owner).to_string(),
+            owner_public_key: record.owner.to_string(),
+            receive_address: Address::from_public_key(&record.receive_key).to_string(),
+            receive_public_key: record.receive_key.to_string(),
+            announcement: record.announcement.to_string(),
+        }
+    }
+}
+
+/// API request to announce an alias for the node wallet
+#[derive(Deserialize)]
+struct AliasRequest {
+    alias: String,
+    /// Preferred receive key (defaults to the node wallet's own key)
+    #[serde(default)]
+    receive_pubkey_hex: Option<String>,
+}
+
 /// API request to send a transaction
 #[derive(Deserialize)]
 struct SendRequest {
-    recipient_pubkey_hex: String,
+    /// Hex public key, `rhz1...` address or `name@rhz` alias
+    #[serde(alias = "recipient_pubkey_hex")]
+    recipient: String,
+    amount: u64,
+    #[serde(default)]
+    memo: Option<String>,
+    /// Fee, burned, for priority in pending pools and gossip
+    #[serde(default)]
+    fee: u64,
+    /// Tip for the relayer whose claim for this transfer finalizes first
+    #[serde(default)]
+    relay_tip: u64,
+    /// Split the payment across subkeys with randomized timing and parents
+    #[serde(default)]
+    privacy: bool,
+    /// Time lock: a DAG depth, or Unix ms from 500,000,000 up, before which
+    /// the payment can't credit the recipient
+    #[serde(default)]
+    valid_after: u64,
+}
+
+/// API request to pay several recipients in one batch transaction
+#[derive(Deserialize)]
+struct SendBatchRequest {
+    outputs: Vec<SendBatchOutput>,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    fee: u64,
+    #[serde(default)]
+    relay_tip: u64,
+}
+
+#[derive(Deserialize)]
+struct SendBatchOutput {
+    /// Hex public key, `rhz1...` address or `name@rhz` alias
+    recipient: String,
+    amount: u64,
+}
+
+/// API request to unlock the node wallet for a session
+#[derive(Deserialize)]
+struct UnlockRequest {
+    password: Zeroizing<String>,
+    /// How long the session lasts (defaults to `default_secs` in config.json)
+    timeout_secs: Option<u64>,
+}
+
+/// Whether the node wallet is unlocked
+#[derive(Serialize)]
+struct WalletSessionResponse {
+    /// Whether API spends need an unlocked session at all
+    required: bool,
+    unlocked: bool,
+    /// When the session ends (unix ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unlocked_until: Option<u64>,
+}
+
+/// API request for a payment link
+#[derive(Deserialize)]
+struct PaymentLinkRequest {
+    /// Amount to collect, in smallest units
     amount: u64,
+    /// Shown to the payer and put in the `rhiza:` URI
+    #[serde(default)]
+    memo: Option<String>,
+    /// How long the link stays open (an hour if absent)
+    #[serde(default)]
+    expires_in_secs: Option<u64>,
+}
+
+/// A payment link and what it has received
+#[derive(Serialize)]
+struct PaymentLinkResponse {
+    id: String,
+    /// Where payments go: a key derived for this link alone
+    address: String,
+    public_key: String,
+    amount: u64,
+    amount_rhz: f64,
+    memo: Option<String>,
+    /// `rhiza:` URI for wallets, also encoded in the QR code
+    uri: String,
+    /// Status page to share with the payer
+    url: String,
+    /// SVG QR code of the URI
+    qr_url: String,
+    created_ms: u64,
+    expires_ms: u64,
+    status: LinkStatus,
+    /// Pending payments included
+    received: u64,
+    confirmed: u64,
+}
+
+/// API request to replace an unfinalized transfer
+#[derive(Deserialize)]
+struct ReplaceRequest {
+    /// New relay tip; must raise the transfer's priority
+    relay_tip: u64,
+    /// Corrected memo (empty clears it); the original's is kept if absent
+    #[serde(default)]
+    memo: Option<String>,
+}
+
+/// API response for a privacy send
+#[derive(Serialize)]
+struct PrivateSendResponse {
+    /// Transactions moving the pieces onto subkeys
+    funding: Vec<String>,
+    /// Number of pieces the payment was split into
+    pieces: usize,
+    /// Latest moment the last piece is forwarded (unix ms, before retries)
+    forwarded_by: u64,
+    status: String,
 }
 
 /// API response for a transaction
 #[derive(Serialize)]
 struct TransactionResponse {
     id: String,
-    status: String,
+    /// "pending", "locked", "confirming" or "final"
+    status: &'static str,
+}
+
+/// The pending pool
+#[derive(Serialize)]
+struct MempoolResponse {
+    count: usize,
+    capacity: usize,
+    per_sender_limit: usize,
+    /// Oldest first
+    transactions: Vec<PendingTransaction>,
+}
+
+/// A transaction waiting for finality
+#[derive(Serialize)]
+struct PendingTransaction {
+    id: String,
+    sender: String,
+    recipient: String,
+    amount: u64,
+    fee: u64,
+    /// When this node took it in (unix ms)
+    received_ms: u64,
+    /// "pending", "locked" or "confirming"
+    status: &'static str,
+    weight: u64,
+}
+
+/// Node metrics
+#[derive(Serialize)]
+struct MetricsResponse {
+    finality: FinalityLatencyMetrics,
+    /// Background checks of cumulative weights
+    weights: WeightAudit,
+}
+
+/// Time from first seeing a transaction to its finality
+#[derive(Serialize)]
+struct FinalityLatencyMetrics {
+    /// Transactions measured since startup
+    finalized: u64,
+    mean_ms: Option<u64>,
+    /// Over the most recent transactions
+    recent: Option<LatencyPercentiles>,
+    /// Since startup, fastest first
+    buckets: Vec<LatencyBucket>,
+}
+
+/// Overall node health, judged against the configured objectives
+#[derive(Serialize)]
+struct HealthResponse {
+    /// "ok" or "degraded"
+    status: &'static str,
+    /// Objectives currently missed
+    alerts: Vec<String>,
+    finality: Option<LatencyPercentiles>,
+    /// How long the oldest pending transaction has been waiting
+    oldest_pending_ms: Option<u64>,
+}
+
+/// A connected peer and its standing
+#[derive(Serialize)]
+struct PeerResponse {
+    #[serde(flatten)]
+    peer: PeerSummary,
+    reputation: PeerScore,
+}
+
+/// A banned peer
+#[derive(Serialize)]
+struct BanResponse {
+    peer_id: String,
+    address: Option<String>,
+    /// Unix ms
+    until_ms: u64,
+    reason: String,
+    /// How many times the peer has been banned
+    count: u32,
+}
+
+/// A finality proof signed by this node
+#[derive(Serialize)]
+struct FinalityProofResponse {
+    transaction_id: String,
+    /// Key of the node that attested the proof
+    attester: String,
+    approvers: usize,
+    /// Hex-encoded proof blob, checked with `FinalityProof::verify`
+    proof: String,
+}
+
+/// API response with a proof that a transaction is below a set of tips
+#[derive(Serialize)]
+struct InclusionProofResponse {
+    transaction_id: String,
+    genesis_id: String,
+    /// Tip the proof's path starts at
+    tip: String,
+    steps: usize,
+    /// Hex-encoded proof blob, checked with `InclusionProof::verify`
+    proof: String,
+}
+
+/// Query parameters for an inclusion proof
+#[derive(Deserialize)]
+struct InclusionQuery {
+    /// Comma-separated tips the verifier trusts; the node's final tips if
+    /// left out
+    tips: Option<String>,
+}
+
+/// One of the node's payment channels, with its off-DAG state
+#[derive(Serialize)]
+struct ChannelResponse {
+    id: String,
+    /// "funder" or "payee"
+    role: &'static str,
+    counterparty: String,
+    deposit: u64,
+    /// Paid to the payee so far, as of the latest state both parties hold
+    paid: u64,
+    sequence: u64,
+    /// Total paid by a state the payee hasn't acknowledged yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_flight: Option<u64>,
+    /// "open", "closing" or "settled"
+    status: &'static str,
+    /// When a funder's close settles unless the payee answers it (unix ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settles_at: Option<u64>,
+    /// The close that settled or is settling the channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    close: Option<String>,
+}
+
+/// API request to open a payment channel
+#[derive(Deserialize)]
+struct ChannelOpenRequest {
+    /// Hex public key, `rhz1...` address or `name@rhz` alias of the payee
+    counterparty: String,
+    deposit: u64,
+}
+
+/// API request to pay over channels
+#[derive(Deserialize)]
+struct ChannelPayRequest {
+    /// Hex public key, `rhz1...` address or `name@rhz` alias
+    recipient: String,
+    amount: u64,
+}
+
+/// API response for a payment sent over channels
+#[derive(Serialize)]
+struct ChannelPayResponse {
+    payment_id: String,
+    /// Channels the payment crosses, from this node to the recipient
+    route: Vec<String>,
+}
+
+/// A hash-time-locked contract, looked up by its lock
+#[derive(Serialize)]
+struct HtlcResponse {
+    id: String,
+    locker: String,
+    claimer: String,
+    amount: u64,
+    /// SHA-256 of the secret that claims it
+    hash_lock: String,
+    /// DAG depth, or Unix ms from 500,000,000 on
+    timeout: u64,
+    /// "locking" (the lock isn't final yet), "open", "claimed" or "refunded"
+    status: &'static str,
+    /// Whether a refund made now would be past the timeout
+    expired: bool,
+    /// The claim or refund that settled it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settled_by: Option<String>,
+    /// The secret, once a claim in the DAG has revealed it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preimage: Option<String>,
+}
+
+/// API response listing the edge of final history
+#[derive(Serialize)]
+struct FinalTipsResponse {
+    genesis_id: Option<String>,
+    tips: Vec<String>,
+}
+
+/// A transaction and how far along it is towards finality
+#[derive(Serialize)]
+struct TransactionStatusResponse {
+    transaction: Transaction,
+    /// "pending", "locked", "confirming", "final" or "pruned" (final and only in
+    /// cold storage)
+    status: &'static str,
+    /// Cumulative weight so far
+    weight: u64,
+    /// Distinct keys other than the sender's approving it so far
+    approvers: u64,
+    /// Distinct approvers at which it becomes final
+    needed: u64,
+    is_final: bool,
+    depth: u64,
+    /// Relayer that collected the transfer's relay tip, once one has
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tip_collected_by: Option<String>,
+    /// Transfer with the same nonce that currently replaces this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replaced_by: Option<String>,
+    /// VRF output the sender's tip selection was seeded from, when the
+    /// transaction carries a proof of it; replaying the selection with it
+    /// shows whether the parents were drawn honestly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selection_seed: Option<String>,
+    /// Proof of work the transaction carries, in bits
+    work_bits: u32,
+}
+
+/// API response for a transaction's trace
+#[derive(Serialize)]
+struct TransactionTraceResponse {
+    id: String,
+    /// As for `GET /tx/:id`; before it is in the DAG, "rejected" or
+    /// "waiting" (on a Dandelion stem, or for its parents to arrive)
+    status: &'static str,
+    /// Distinct keys other than the sender's approving it so far
+    approvers: u64,
+    needed: u64,
+    /// What this node saw happen to it, oldest first; empty for transactions
+    /// seen before the node's last restart or too long ago
+    timeline: Vec<TraceStep>,
 }
 
 /// Transaction list item
@@ -55,49 +511,267 @@ struct TransactionListItem {
     recipient: String,
     amount: u64,
     amount_rhz: f64,
+    fee: u64,
+    /// Set when someone other than the sender pays the fee
+    fee_payer: Option<String>,
+    relay_tip: u64,
     memo: Option<String>,
     is_incoming: bool,
+    is_final: bool,
     timestamp: u64,
+    tags: Vec<String>,
+    /// What a batch pays each recipient
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<OutputItem>,
 }
 
-pub async fn run_api_server(state: SharedState, port: u16) {
+#[derive(Serialize)]
+struct OutputItem {
+    recipient: String,
+    amount: u64,
+}
+
+/// Query parameters for the transaction list
+#[derive(Deserialize)]
+struct TransactionQuery {
+    /// Only include transactions carrying this tag
+    tag: Option<String>,
+    /// Only include transactions this public key sends, receives or pays
+    /// the fee of
+    key: Option<String>,
+}
+
+/// Relayers listed by `/relayers/top` when no limit is given
+const DEFAULT_LEADERBOARD_SIZE: usize = 10;
+
+/// Most relayers `/relayers/top` lists
+const MAX_LEADERBOARD_SIZE: usize = 100;
+
+/// Query parameters for the relay leaderboard
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    /// day, week (the default), month or all
+    #[serde(default)]
+    period: Period,
+    limit: Option<usize>,
+}
+
+/// API request to tag a transaction
+#[derive(Deserialize)]
+struct TagRequest {
+    tags: Vec<String>,
+}
+
+/// API response for a tagged transaction
+#[derive(Serialize)]
+struct TagResponse {
+    id: String,
+    tags: Vec<String>,
+}
+
+/// Longest a single long-poll request may wait for new events
+const MAX_POLL_WAIT_MS: u64 = 30_000;
+
+/// Query parameters for event long-polling
+#[derive(Deserialize)]
+struct EventPollQuery {
+    /// Return events after this cursor (0 = from the oldest retained event)
+    #[serde(default)]
+    cursor: u64,
+    /// How long to wait for new events before returning an empty batch
+    wait_ms: Option<u64>,
+}
+
+/// Query parameters for the WebSocket and server-sent event streams
+#[derive(Deserialize)]
+struct EventStreamQuery {
+    /// Resume after this cursor (defaults to only new events)
+    cursor: Option<u64>,
+    /// Comma-separated `rhz1...` addresses or hex public keys; when given,
+    /// only transaction events involving one of them are delivered
+    address: Option<String>,
+}
+
+pub async fn run_api_server(state: SharedState, addr: SocketAddr, access: ApiAccess) {
     let app = Router::new()
         .route("/", get(serve_wallet_ui))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        .route("/health", get(health))
+        .route("/metrics", get(get_metrics))
         .route("/info", get(get_info))
+        .route("/supply", get(get_supply))
         .route("/balance", get(get_balance))
+        .route("/address/:address/balance", get(get_address_balance))
+        .route("/address/:address/alias", get(get_address_alias))
+        .route("/announce", post(announce_key))
+        .route("/alias", post(announce_alias))
+        .route("/alias/:alias", get(resolve_alias))
         .route("/transactions", get(get_transactions))
+        .route("/transactions/:id/tags", put(tag_transaction))
+        .route("/tx/:id", get(get_transaction_status))
+        .route("/tx/:id/proof", get(get_finality_proof))
+        .route("/tx/:id/inclusion", get(get_inclusion_proof))
+        .route("/tx/:id/trace", get(get_transaction_trace))
+        .route("/tx/:id/replace", post(replace_transaction))
+        .route("/tags/report", get(get_tag_report))
+        .route("/wallet/session", get(get_wallet_session))
+        .route("/wallet/unlock", post(unlock_wallet))
+        .route("/wallet/lock", post(lock_wallet))
         .route("/send", post(send_transaction))
+        .route("/send-batch", post(send_batch))
+        .route("/sponsor", post(sponsor_transaction))
+        .route("/tx/submit", post(submit_transaction))
+        .route("/channels", get(get_channels))
+        .route("/channels/open", post(open_channel))
+        .route("/channels/pay", post(pay_over_channels))
+        .route("/channels/:id/close", post(close_channel))
+        .route("/htlc/:id", get(get_htlc))
+        .route("/payment-links", get(get_payment_links).post(create_payment_link))
+        .route("/payment-links/:id", get(get_payment_link))
+        .route("/payment-links/:id/qr.svg", get(get_payment_link_qr))
+        .route("/pay/:id", get(serve_payment_page))
+        .route("/mempool", get(get_mempool))
+        .route("/fees/estimate", get(get_fee_estimate))
         .route("/relay-reward", post(claim_relay_reward))
+        .route("/relayers/top", get(get_top_relayers))
         .route("/dag/tips", get(get_tips))
+        .route("/dag/final-tips", get(get_final_tips))
+        .route("/dag/health", get(get_dag_health))
+        .route("/founder", get(get_founder))
+        .route("/update", get(get_update))
+        .route("/events/poll", get(poll_events))
+        .route("/events/stream", get(stream_events))
+        .route("/ws", get(ws_events))
+        .route("/peers", get(get_peers))
+        .route("/peers/bans", get(get_peer_bans))
+        .route("/peers/held", get(get_held_gossip))
+        .route("/admin/audit/peers", get(get_peer_audit))
+        .route("/admin/audit/api", get(get_api_audit))
+        .route("/admin/audit/api/export", get(export_api_audit))
+        .route("/admin/pruning", get(get_pruning))
+        .route("/admin/status", get(get_admin_status))
+        .route("/admin/sync", get(get_sync_status))
+        .route("/admin/peers/ban", post(ban_peer))
+        .route("/admin/shutdown", post(shutdown))
+        .route("/admin/snapshot", get(get_snapshot_job).post(take_snapshot))
+        .route("/snapshot/latest", get(get_published_snapshot))
+        .route("/webhooks/queue", get(get_webhook_queue))
+        .route("/webhooks/dead-letters", get(get_webhook_dead_letters))
+        .route("/webhooks/dead-letters/:id/replay", post(replay_webhook_delivery))
+        .route("/webhooks/receipts/:id", get(get_webhook_receipt))
+        .route_layer(axum::middleware::from_fn_with_state(access, api_audit::audit_requests))
         .with_state(state);
 
-    let addr = format!("127.0.0.1:{}", port);
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     tracing::info!("🌐 API server listening on http://{}", addr);
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
 
 async fn serve_wallet_ui() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
 }
 
+/// Hosted status page of a payment link, for the payer
+async fn serve_payment_page() -> Html<&'static str> {
+    Html(include_str!("../static/pay.html"))
+}
+
+/// Liveness: the node's state lock can still be taken
+async fn livez(State(state): State<SharedState>) -> (StatusCode, &'static str) {
+    match tokio::time::timeout(LIVENESS_TIMEOUT, state.read()).await {
+        Ok(_) => (StatusCode::OK, "ok"),
+        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "state lock stuck"),
+    }
+}
+
+/// Readiness: the node has a DAG (restored, created or synced) to serve
+async fn readyz(State(state): State<SharedState>) -> (StatusCode, &'static str) {
+    match tokio::time::timeout(LIVENESS_TIMEOUT, state.read()).await {
+        Ok(state) if state.dag.genesis_id.is_some() => (StatusCode::OK, "ready"),
+        Ok(_) => (StatusCode::SERVICE_UNAVAILABLE, "waiting for DAG sync"),
+        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "state lock stuck"),
+    }
+}
+
+/// Health: whether finality latency meets the configured objectives, and
+/// cumulative weights hold up to recomputation
+async fn health(State(state): State<SharedState>) -> (StatusCode, Json<HealthResponse>) {
+    let state = state.read().await;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let latency = state.finality.latency();
+    let oldest_pending_ms = state.mempool.oldest_age_ms(now);
+    let mut alerts = state
+        .finality_slo
+        .as_ref()
+        .map(|slo| slo.breaches(latency, oldest_pending_ms))
+        .unwrap_or_default();
+    alerts.extend(state.weight_audit.alarm());
+
+    let (code, status) = if alerts.is_empty() {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+    (code, Json(HealthResponse {
+        status,
+        alerts,
+        finality: latency.percentiles(),
+        oldest_pending_ms,
+    }))
+}
+
+async fn get_metrics(State(state): State<SharedState>) -> Json<MetricsResponse> {
+    let state = state.read().await;
+    let latency = state.finality.latency();
+    Json(MetricsResponse {
+        finality: FinalityLatencyMetrics {
+            finalized: latency.count(),
+            mean_ms: latency.mean_ms(),
+            recent: latency.percentiles(),
+            buckets: latency.buckets(),
+        },
+        weights: state.weight_audit.clone(),
+    })
+}
+
 async fn get_info(State(state): State<SharedState>) -> Json<NodeInfoResponse> {
-    let state = state.lock().unwrap();
+    let state = state.read().await;
     let balance = state.balance();
     Json(NodeInfoResponse {
         address: state.address().to_string(),
-        public_key: state.keypair.public_key.to_string(),
+        public_key: state.wallet_key.to_string(),
+        network_id: state.network_id,
         dag_size: state.dag.len(),
         dag_depth: state.dag.depth(),
         balance,
         balance_rhz: balance as f64 / rhiza_core::UNITS_PER_RHZ as f64,
         total_relays: state.relay_tracker.total_relays(),
-        tips_count: state.dag.tips().len(),
+        tips_count: state.dag.tip_count(),
+        peers: state.network.peer_count(),
+    })
+}
+
+async fn get_supply(State(state): State<SharedState>) -> Json<SupplyResponse> {
+    let state = state.read().await;
+    let supply = *state.dag.supply();
+    let rhz = |units: u64| units as f64 / rhiza_core::UNITS_PER_RHZ as f64;
+    Json(SupplyResponse {
+        max_supply: rhiza_core::MAX_SUPPLY,
+        minted: supply.minted(),
+        minted_rhz: rhz(supply.minted()),
+        genesis: supply.genesis,
+        founder_allocation: supply.founder_allocation,
+        relay_rewards: supply.relay_rewards,
+        remaining: supply.remaining(),
+        remaining_rhz: rhz(supply.remaining()),
     })
 }
 
 async fn get_balance(State(state): State<SharedState>) -> Json<BalanceResponse> {
-    let state = state.lock().unwrap();
+    let state = state.read().await;
     let balance = state.balance();
     Json(BalanceResponse {
         address: state.address().to_string(),
@@ -106,22 +780,261 @@ async fn get_balance(State(state): State<SharedState>) -> Json<BalanceResponse>
     })
 }
 
-async fn get_transactions(State(state): State<SharedState>) -> Json<Vec<TransactionListItem>> {
-    let state = state.lock().unwrap();
-    let my_pubkey = state.keypair.public_key.to_string();
+/// Refuse a spend while the wallet waits for an unlock
+fn wallet_unlocked(state: &NodeState) -> Result<(), (StatusCode, String)> {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    if state.wallet_session.may_spend(now) {
+        return Ok(());
+    }
+    Err((StatusCode::LOCKED, "The wallet is locked; unlock it with POST /wallet/unlock".to_string()))
+}
+
+fn wallet_session_response(state: &NodeState) -> Json<WalletSessionResponse> {
+    let until = state.wallet_session.unlocked_until(chrono::Utc::now().timestamp_millis() as u64);
+    let required = state.wallet_session.config().required;
+    Json(WalletSessionResponse {
+        required,
+        unlocked: until.is_some(),
+        // Without sessions the wallet stays unlocked for good
+        unlocked_until: until.filter(|_| required),
+    })
+}
+
+/// Refuse unlocking and locking a wallet that was unlocked at startup
+fn sessions_enabled(state: &NodeState) -> Result<(), (StatusCode, String)> {
+    if state.wallet_session.config().required {
+        return Ok(());
+    }
+    let reason = "The wallet was unlocked at startup; set wallet_session.required to unlock it per session";
+    Err((StatusCode::CONFLICT, reason.to_string()))
+}
+
+async fn get_wallet_session(State(state): State<SharedState>) -> Json<WalletSessionResponse> {
+    wallet_session_response(&*state.read().await)
+}
+
+/// Decrypt the node wallet with its password and keep it unlocked for a
+/// while, locking it again when the time is up
+async fn unlock_wallet(
+    State(shared): State<SharedState>,
+    Json(req): Json<UnlockRequest>,
+) -> Result<Json<WalletSessionResponse>, (StatusCode, String)> {
+    let (path, duration_ms, wallet_key) = {
+        let state = shared.read().await;
+        sessions_enabled(&state)?;
+        let session = &state.wallet_session;
+        (session.keystore_path().clone(), session.duration_ms(req.timeout_secs), state.wallet_key.clone())
+    };
+    // The key derivation is slow on purpose; keep it off the async workers
+    let unlocked = tokio::task::spawn_blocking(move || {
+        let keystore = KeyStore::load(&path)?;
+        if !keystore.is_encrypted() {
+            return Ok(None);
+        }
+        keystore.unlock(&req.password).map(Some)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| match e {
+        KeyStoreError::WrongPassword => (StatusCode::UNAUTHORIZED, "Wrong wallet password".to_string()),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+    let Some(keypair) = unlocked else {
+        let reason = "The wallet has no password; restart the node with one to encrypt it";
+        return Err((StatusCode::CONFLICT, reason.to_string()));
+    };
+    if keypair.public_key != wallet_key {
+        let reason = "wallet.json no longer holds the key the node started with";
+        return Err((StatusCode::CONFLICT, reason.to_string()));
+    }
+
+    let mut state = shared.write().await;
+    let until = chrono::Utc::now().timestamp_millis() as u64 + duration_ms;
+    state.wallet_session.unlock(keypair, until);
+    tracing::info!("🔓 Wallet unlocked for {}s", duration_ms / 1_000);
+    let relock = shared.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+        // A later unlock may have extended the session; expire() leaves that be
+        relock.write().await.wallet_session.expire(chrono::Utc::now().timestamp_millis() as u64);
+    });
+    Ok(wallet_session_response(&state))
+}
+
+/// Lock the node wallet, wiping its decrypted key
+async fn lock_wallet(
+    State(state): State<SharedState>,
+) -> Result<Json<WalletSessionResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    sessions_enabled(&state)?;
+    if state.wallet_session.lock() {
+        tracing::info!("🔒 Wallet locked");
+    }
+    Ok(wallet_session_response(&state))
+}
+
+async fn get_address_balance(
+    State(state): State<SharedState>,
+    Path(account): Path<String>,
+) -> Result<Json<AddressBalanceResponse>, (StatusCode, String)> {
+    let state = state.read().await;
+
+    // A 64-character hex string is a public key, anything else an address
+    let (address, public_key) = if account.len() == 64 {
+        let pubkey = parse_public_key(&account)?;
+        (Address::from_public_key(&pubkey), Some(pubkey))
+    } else {
+        let address = Address::from_str(&account.to_ascii_lowercase())
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid address: {}", e)))?;
+        let pubkey = state.dag.find_public_key(&address);
+        (address, pubkey)
+    };
+
+    let (confirmed, total) = match &public_key {
+        Some(pk) => (state.dag.get_confirmed_balance(pk), state.dag.get_balance(pk)),
+        None => (0, 0),
+    };
+    Ok(Json(AddressBalanceResponse {
+        address: address.to_string(),
+        public_key: public_key.map(|pk| pk.to_string()),
+        confirmed,
+        total,
+        pending: total as i128 - confirmed as i128,
+        confirmed_rhz: confirmed as f64 / rhiza_core::UNITS_PER_RHZ as f64,
+        total_rhz: total as f64 / rhiza_core::UNITS_PER_RHZ as f64,
+    }))
+}
+
+/// Resolve `name@rhz` to its owner and preferred receive address
+async fn resolve_alias(
+    State(state): State<SharedState>,
+    Path(alias): Path<String>,
+) -> Result<Json<AliasResponse>, (StatusCode, String)> {
+    let alias = Alias::from_str(&alias)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid alias: {}", e)))?;
+
+    let state = state.read().await;
+    let record = state
+        .dag
+        .aliases()
+        .resolve(&alias)
+        .ok_or((StatusCode::NOT_FOUND, format!("{} is not registered", alias)))?;
+    Ok(Json(record.into()))
+}
+
+/// Reverse lookup: the alias owned by an address or hex public key
+async fn get_address_alias(
+    State(state): State<SharedState>,
+    Path(account): Path<String>,
+) -> Result<Json<AliasResponse>, (StatusCode, String)> {
+    let state = state.read().await;
+    let public_key = if account.len() == 64 {
+        Some(parse_public_key(&account)?)
+    } else {
+        let address = Address::from_str(&account.to_ascii_lowercase())
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid address: {}", e)))?;
+        state.dag.find_public_key(&address)
+    };
+
+    let record = public_key
+        .and_then(|pk| state.dag.aliases().alias_of(&pk))
+        .ok_or((StatusCode::NOT_FOUND, "No alias announced".to_string()))?;
+    Ok(Json(record.into()))
+}
+
+/// Publish the node wallet's key so its address can be paid
+async fn announce_key(
+    State(state): State<SharedState>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let tx = state
+        .announce_key()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(accepted(&state.dag, &tx))
+}
+
+/// Announce an alias for the node wallet
+async fn announce_alias(
+    State(state): State<SharedState>,
+    Json(req): Json<AliasRequest>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let alias = Alias::from_str(&req.alias)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid alias: {}", e)))?;
+
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let receive_key = match &req.receive_pubkey_hex {
+        Some(hex_key) => parse_public_key(hex_key)?,
+        None => state.wallet_key.clone(),
+    };
+    let tx = state
+        .announce_alias(&alias, receive_key)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(accepted(&state.dag, &tx))
+}
+
+async fn get_transactions(
+    State(state): State<SharedState>,
+    Query(query): Query<TransactionQuery>,
+) -> Result<Json<Vec<TransactionListItem>>, (StatusCode, String)> {
+    let state = state.read().await;
+    let my_pubkey = state.wallet_key.to_string();
+
+    let tag_filter = query
+        .tag
+        .as_deref()
+        .map(tags::normalize_tag)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let key_filter = query.key.as_deref().map(parse_public_key).transpose()?;
+    let mut all_tags = state
+        .storage
+        .all_tags()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let mut txs: Vec<TransactionListItem> = state.dag.transaction_ids().iter().filter_map(|id| {
+        let tx_tags = all_tags.remove(id).unwrap_or_default();
+        if let Some(tag) = &tag_filter {
+            if !tx_tags.contains(tag) {
+                return None;
+            }
+        }
+
         let vertex = state.dag.get(id)?;
         let tx = &vertex.transaction;
+        if let Some(key) = &key_filter {
+            if !tx.data.parties().contains(&key) {
+                return None;
+            }
+        }
         let tx_type = match tx.data.tx_type {
             rhiza_core::dag::transaction::TransactionType::Genesis => "Genesis",
             rhiza_core::dag::transaction::TransactionType::Transfer => "Transfer",
             rhiza_core::dag::transaction::TransactionType::RelayReward => "RelayReward",
             rhiza_core::dag::transaction::TransactionType::FounderAllocation => "FounderAllocation",
+            rhiza_core::dag::transaction::TransactionType::AliasAnnouncement => "AliasAnnouncement",
+            rhiza_core::dag::transaction::TransactionType::KeyAnnouncement => "KeyAnnouncement",
+            rhiza_core::dag::transaction::TransactionType::ChannelOpen => "ChannelOpen",
+            rhiza_core::dag::transaction::TransactionType::ChannelClose => "ChannelClose",
+            rhiza_core::dag::transaction::TransactionType::Multisig => "Multisig",
+            rhiza_core::dag::transaction::TransactionType::HtlcLock => "HtlcLock",
+            rhiza_core::dag::transaction::TransactionType::HtlcClaim => "HtlcClaim",
+            rhiza_core::dag::transaction::TransactionType::HtlcRefund => "HtlcRefund",
+            rhiza_core::dag::transaction::TransactionType::Batch => "Batch",
         };
         let recipient_str = tx.data.recipient.to_string();
         let sender_str = tx.data.sender.to_string();
-        let is_incoming = recipient_str == my_pubkey && sender_str != my_pubkey;
+        let outputs: Vec<OutputItem> = tx
+            .data
+            .outputs
+            .iter()
+            .map(|output| OutputItem { recipient: output.recipient.to_string(), amount: output.amount })
+            .collect();
+        let paid_to_me = recipient_str == my_pubkey || outputs.iter().any(|o| o.recipient == my_pubkey);
+        let is_incoming = paid_to_me && sender_str != my_pubkey;
 
         Some(TransactionListItem {
             id: tx.id.to_string(),
@@ -130,56 +1043,1201 @@ async fn get_transactions(State(state): State<SharedState>) -> Json<Vec<Transact
             recipient: recipient_str,
             amount: tx.data.amount,
             amount_rhz: tx.data.amount as f64 / rhiza_core::UNITS_PER_RHZ as f64,
+            fee: tx.data.fee,
+            fee_payer: tx.data.fee_payer.as_ref().map(|k| k.to_string()),
+            relay_tip: tx.data.relay_tip,
             memo: tx.data.memo.clone(),
             is_incoming,
+            is_final: vertex.is_final,
             timestamp: tx.data.timestamp,
+            tags: tx_tags,
+            outputs,
         })
     }).collect();
 
-    // Sort: newest first (by timestamp, then by type for genesis)
-    txs.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    // Newest first; the sort is stable, so transactions with the same
+    // timestamp keep the DAG's depth-then-id order
+    txs.sort_by_key(|tx| std::cmp::Reverse(tx.timestamp));
+
+    Ok(Json(txs))
+}
+
+async fn get_transaction_status(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionStatusResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
+
+    let state = state.read().await;
+    let pruned;
+    let (vertex, status) = match state.dag.get(&id) {
+        Some(vertex) => (vertex, FinalityChecker::finality_status(&state.dag, &id).name()),
+        None if state.dag.pruned().contains(&id) => {
+            pruned = state
+                .storage
+                .get_vertex(&id)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or_else(|| (StatusCode::GONE, format!("Transaction {} was pruned", id)))?;
+            (&pruned, "pruned")
+        }
+        None => return Err((StatusCode::NOT_FOUND, format!("Transaction {} not found", id))),
+    };
+
+    let tip_collected_by = state
+        .dag
+        .relay_tips()
+        .winner(&id)
+        .and_then(|claim| state.dag.get(claim))
+        .map(|claim| claim.transaction.data.sender.to_string());
+
+    Ok(Json(TransactionStatusResponse {
+        transaction: vertex.transaction.clone(),
+        status,
+        weight: vertex.cumulative_weight,
+        approvers: state.dag.approver_count(&id),
+        needed: rhiza_core::FINALITY_THRESHOLD,
+        is_final: vertex.is_final,
+        depth: vertex.depth,
+        tip_collected_by,
+        replaced_by: state.dag.conflicts().replacement_of(&id).map(|r| r.to_string()),
+        selection_seed: selection_output(&vertex.transaction.data).map(hex::encode),
+        work_bits: vertex.transaction.work_bits(),
+    }))
+}
+
+/// Step-by-step account of what this node saw happen to a transaction, to
+/// answer where a payment got to
+async fn get_transaction_trace(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionTraceResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
 
-    Json(txs)
+    let state = state.read().await;
+    let timeline = state.trace.get(&id).map(<[_]>::to_vec);
+    let status = if state.dag.get(&id).is_some() {
+        FinalityChecker::finality_status(&state.dag, &id).name()
+    } else if state.dag.pruned().contains(&id) {
+        "pruned"
+    } else if let Some(timeline) = &timeline {
+        match timeline.last().map(|step| &step.stage) {
+            Some(TraceStage::Rejected { .. }) => "rejected",
+            _ => "waiting",
+        }
+    } else {
+        return Err((StatusCode::NOT_FOUND, format!("Transaction {} not found", id)));
+    };
+
+    Ok(Json(TransactionTraceResponse {
+        id: id.to_string(),
+        status,
+        approvers: state.dag.approver_count(&id),
+        needed: rhiza_core::FINALITY_THRESHOLD,
+        timeline: timeline.unwrap_or_default(),
+    }))
+}
+
+/// Proof that a transaction is final, attested by this node, for clients
+/// that verify finality without running a node
+async fn get_finality_proof(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<FinalityProofResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
+
+    let state = state.read().await;
+    if state.dag.get(&id).is_none() {
+        return Err((StatusCode::NOT_FOUND, format!("Transaction {} not found", id)));
+    }
+    let mut proof = FinalityProof::build(&state.dag, &id)
+        .ok_or_else(|| (StatusCode::CONFLICT, format!("Transaction {} is not final yet", id)))?;
+    proof.sign(&state.keypair);
+
+    Ok(Json(FinalityProofResponse {
+        transaction_id: id.to_string(),
+        attester: state.keypair.public_key.to_string(),
+        approvers: proof.approvers.len(),
+        proof: hex::encode(proof.to_bytes()),
+    }))
+}
+
+/// Path from final tips down to a transaction, for light clients that
+/// hold the tips but not the DAG
+async fn get_inclusion_proof(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Query(query): Query<InclusionQuery>,
+) -> Result<Json<InclusionProofResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
+    let tips = match query.tips.as_deref() {
+        Some(tips) => tips
+            .split(',')
+            .map(|tip| Hash::from_hex(tip.trim()))
+            .collect::<Result<Vec<Hash>, _>>()
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid tip: {}", e)))?,
+        None => Vec::new(),
+    };
+
+    let state = state.read().await;
+    if state.dag.get(&id).is_none() {
+        return Err((StatusCode::NOT_FOUND, format!("Transaction {} not found", id)));
+    }
+    let tips = if tips.is_empty() { state.dag.final_tips() } else { tips };
+    let proof = InclusionProof::build(&state.dag, &id, &tips)
+        .ok_or_else(|| (StatusCode::CONFLICT, format!("Transaction {} is not below the given tips", id)))?;
+    let tip = proof
+        .path
+        .first()
+        .map_or(id, |step| Hash::digest(&step.data.to_signing_bytes()));
+
+    Ok(Json(InclusionProofResponse {
+        transaction_id: id.to_string(),
+        genesis_id: proof.genesis_id.to_string(),
+        tip: tip.to_string(),
+        steps: proof.path.len(),
+        proof: hex::encode(proof.to_bytes()),
+    }))
+}
+
+async fn tag_transaction(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Json(req): Json<TagRequest>,
+) -> Result<Json<TagResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
+
+    let mut state = state.write().await;
+    let tags = state
+        .tag_transaction(&id, &req.tags)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(TagResponse {
+        id: id.to_string(),
+        tags,
+    }))
+}
+
+async fn get_tag_report(
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<TagTotals>>, (StatusCode, String)> {
+    let state = state.read().await;
+    let all_tags = state
+        .storage
+        .all_tags()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(tags::tag_report(&state.dag, &state.wallet_key, &all_tags)))
 }
 
 async fn send_transaction(
     State(state): State<SharedState>,
     Json(req): Json<SendRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let memo = req.memo.filter(|m| !m.trim().is_empty());
+
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let recipient = resolve_recipient(&state, &req.recipient)?;
+    if req.privacy {
+        if memo.is_some() {
+            // A memo repeated on every piece would link them back together
+            return Err((StatusCode::BAD_REQUEST, "Privacy sends can't carry a memo".to_string()));
+        }
+        if req.relay_tip > 0 || req.fee > 0 {
+            return Err((StatusCode::BAD_REQUEST, "Privacy sends can't carry a fee or relay tip".to_string()));
+        }
+        if req.valid_after > 0 {
+            return Err((StatusCode::BAD_REQUEST, "Privacy sends can't be time-locked".to_string()));
+        }
+        let funding = state
+            .send_private(recipient, req.amount)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        let started = chrono::Utc::now().timestamp_millis() as u64;
+        return Ok(Json(PrivateSendResponse {
+            pieces: funding.len(),
+            funding: funding.iter().map(|tx| tx.id.to_string()).collect(),
+            forwarded_by: started + crate::privacy::MAX_HOP_DELAY_MS,
+            status: "scheduled".to_string(),
+        })
+        .into_response());
+    }
+
+    let tx = state
+        .send(recipient, req.amount, memo, req.fee, req.relay_tip, req.valid_after)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(accepted(&state.dag, &tx).into_response())
+}
+
+/// Pay several recipients from the node wallet in one batch transaction
+async fn send_batch(
+    State(state): State<SharedState>,
+    Json(req): Json<SendBatchRequest>,
 ) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
-    let pubkey_bytes: [u8; 32] = hex::decode(&req.recipient_pubkey_hex)
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid hex: {}", e)))?
-        .try_into()
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid public key length".to_string()))?;
+    let memo = req.memo.filter(|m| !m.trim().is_empty());
 
-    let recipient = rhiza_core::crypto::PublicKey::from_bytes(pubkey_bytes);
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let outputs = req
+        .outputs
+        .iter()
+        .map(|output| {
+            Ok(BatchOutput {
+                recipient: resolve_recipient(&state, &output.recipient)?,
+                amount: output.amount,
+            })
+        })
+        .collect::<Result<Vec<_>, (StatusCode, String)>>()?;
+    let tx = state
+        .send_batch(outputs, memo, req.fee, req.relay_tip)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(accepted(&state.dag, &tx))
+}
 
-    let mut state = state.lock().unwrap();
+/// Hand out a payment link paid to a fresh subkey of the node wallet
+async fn create_payment_link(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(req): Json<PaymentLinkRequest>,
+) -> Result<(StatusCode, Json<PaymentLinkResponse>), (StatusCode, String)> {
+    let memo = req.memo.filter(|m| !m.trim().is_empty());
+    let expires_in = req.expires_in_secs.unwrap_or(payment_links::DEFAULT_EXPIRY_SECS);
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let mut state = state.write().await;
+    let link = state
+        .create_payment_link(req.amount, memo, expires_in, now)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok((StatusCode::CREATED, Json(payment_link_response(&link, &state.dag, &headers, now))))
+}
+
+/// Every payment link the node handed out, newest first
+async fn get_payment_links(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> Json<Vec<PaymentLinkResponse>> {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let state = state.read().await;
+    let links = state
+        .payment_links
+        .iter()
+        .rev()
+        .map(|link| payment_link_response(link, &state.dag, &headers, now))
+        .collect();
+    Json(links)
+}
+
+async fn get_payment_link(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PaymentLinkResponse>, (StatusCode, String)> {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let state = state.read().await;
+    let link = find_payment_link(&state, &id)?;
+    Ok(Json(payment_link_response(link, &state.dag, &headers, now)))
+}
+
+/// QR code of a payment link's `rhiza:` URI
+async fn get_payment_link_qr(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let uri = find_payment_link(&*state.read().await, &id)?.uri();
+    let code = qrcode::QrCode::new(uri.as_bytes())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not encode QR code: {}", e)))?;
+    let svg = code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(240, 240)
+        .build();
+    Ok(([(axum::http::header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+fn find_payment_link<'a>(state: &'a NodeState, id: &str) -> Result<&'a PaymentLink, (StatusCode, String)> {
+    state
+        .payment_links
+        .iter()
+        .find(|link| link.id == id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No payment link {}", id)))
+}
+
+fn payment_link_response(
+    link: &PaymentLink,
+    dag: &Dag,
+    headers: &HeaderMap,
+    now_ms: u64,
+) -> PaymentLinkResponse {
+    let progress = link.progress(dag, now_ms);
+    let base = public_base_url(headers);
+    PaymentLinkResponse {
+        id: link.id.clone(),
+        address: link.address(),
+        public_key: link.public_key.to_string(),
+        amount: link.amount,
+        amount_rhz: link.amount as f64 / rhiza_core::UNITS_PER_RHZ as f64,
+        memo: link.memo.clone(),
+        uri: link.uri(),
+        url: format!("{}/pay/{}", base, link.id),
+        qr_url: format!("{}/payment-links/{}/qr.svg", base, link.id),
+        created_ms: link.created_ms,
+        expires_ms: link.expires_ms,
+        status: progress.status,
+        received: progress.received,
+        confirmed: progress.confirmed,
+    }
+}
+
+/// The URL the client reached the API at, as far as the request tells
+fn public_base_url(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    let host = header("x-forwarded-host")
+        .or_else(|| header("host"))
+        .unwrap_or("127.0.0.1");
+    format!("{}://{}", scheme, host)
+}
+
+/// The node's payment channels, once their opens are final
+async fn get_channels(State(state): State<SharedState>) -> Json<Vec<ChannelResponse>> {
+    let state = state.read().await;
+    let me = &state.keypair.public_key;
+    let mut channels: Vec<ChannelResponse> = state
+        .dag
+        .channels()
+        .of(me)
+        .map(|channel| {
+            let entry = state.channel_book.get(&channel.id);
+            let latest = state.channel_book.state(&channel.id);
+            let (status, settles_at, close) = match &channel.status {
+                ChannelStatus::Open => ("open", None, None),
+                ChannelStatus::Closing { close, deadline_ms, .. } => ("closing", Some(*deadline_ms), Some(close)),
+                ChannelStatus::Settled { close, .. } => ("settled", None, Some(close)),
+            };
+            ChannelResponse {
+                id: channel.id.to_string(),
+                role: if channel.funder == *me { "funder" } else { "payee" },
+                counterparty: channel.counterparty(me).map(|key| key.to_string()).unwrap_or_default(),
+                deposit: channel.deposit,
+                paid: latest.paid,
+                sequence: latest.sequence,
+                in_flight: entry.and_then(|entry| entry.in_flight).map(|state| state.paid),
+                status,
+                settles_at,
+                close: close.map(|id| id.to_string()),
+            }
+        })
+        .collect();
+    channels.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(channels)
+}
+
+/// An HTLC by the id of its lock, with the secret as soon as a claim in the
+/// DAG reveals it, so the other side of a swap can claim with it
+async fn get_htlc(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<HtlcResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id).map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid lock id: {}", e)))?;
+
+    let state = state.read().await;
+    let dag = &state.dag;
+    let settled = dag.htlcs().get(&id);
+    let lock = dag.get(&id).map(|vertex| &vertex.transaction);
+    let (locker, claimer, amount, hash_lock, timeout) = match (settled, lock) {
+        (Some(htlc), _) => {
+            (htlc.locker.clone(), htlc.claimer.clone(), htlc.amount, htlc.hash_lock, htlc.timeout)
+        }
+        (None, Some(tx)) => match (&tx.data.tx_type, &tx.data.htlc) {
+            (TransactionType::HtlcLock, Some(HtlcTerms::Lock { hash_lock, timeout })) => {
+                (tx.data.sender.clone(), tx.data.recipient.clone(), tx.data.amount, *hash_lock, *timeout)
+            }
+            _ => return Err((StatusCode::NOT_FOUND, format!("{} isn't an HTLC lock", id))),
+        },
+        (None, None) if dag.pruned().contains(&id) => {
+            return Err((StatusCode::GONE, format!("HTLC {} was settled and pruned", id)))
+        }
+        (None, None) => return Err((StatusCode::NOT_FOUND, format!("No HTLC locked by {}", id))),
+    };
+    let (status, settled_by, preimage) = match settled.map(|htlc| &htlc.status) {
+        None => ("locking", None, None),
+        Some(HtlcStatus::Open) => ("open", None, None),
+        Some(HtlcStatus::Claimed { claim, preimage }) => {
+            ("claimed", Some(claim.to_string()), Some(*preimage))
+        }
+        Some(HtlcStatus::Refunded { refund }) => ("refunded", Some(refund.to_string()), None),
+    };
+    // A pending claim has revealed the secret already
+    let revealed = |vertex: &rhiza_core::dag::DagVertex| match &vertex.transaction.data.htlc {
+        Some(HtlcTerms::Claim { lock, preimage }) if *lock == id && preimage.hash_lock() == hash_lock => {
+            Some(*preimage)
+        }
+        _ => None,
+    };
+    let preimage =
+        preimage.or_else(|| dag.transaction_ids().iter().filter_map(|tx| dag.get(tx)).find_map(revealed));
+
+    let (depth, _) = dag.frontier();
+    Ok(Json(HtlcResponse {
+        id: id.to_string(),
+        locker: locker.to_string(),
+        claimer: claimer.to_string(),
+        amount,
+        hash_lock: hash_lock.to_string(),
+        timeout,
+        status,
+        expired: lock_passed(timeout, depth, chrono::Utc::now().timestamp_millis() as u64),
+        settled_by,
+        preimage: preimage.map(|p| p.to_string()),
+    }))
+}
+
+/// Open a payment channel funded by the node key
+async fn open_channel(
+    State(state): State<SharedState>,
+    Json(req): Json<ChannelOpenRequest>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let payee = resolve_recipient(&state, &req.counterparty)?;
     let tx = state
-        .send(recipient, req.amount)
+        .open_channel(payee, req.deposit)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(accepted(&state.dag, &tx))
+}
 
-    Ok(Json(TransactionResponse {
-        id: tx.id.to_string(),
-        status: "confirmed".to_string(),
+/// Pay off the DAG over a route of open channels
+async fn pay_over_channels(
+    State(state): State<SharedState>,
+    Json(req): Json<ChannelPayRequest>,
+) -> Result<Json<ChannelPayResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let recipient = resolve_recipient(&state, &req.recipient)?;
+    let payment = state
+        .pay_over_channels(&recipient, req.amount)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(ChannelPayResponse {
+        payment_id: payment.payment_id.to_string(),
+        route: payment.route.iter().map(|id| id.to_string()).collect(),
     }))
 }
 
+/// Close one of the node's channels on the latest state it holds
+async fn close_channel(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid channel id: {}", e)))?;
+
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let tx = state
+        .close_channel(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(accepted(&state.dag, &tx))
+}
+
+/// Replace one of the node wallet's unfinalized transfers
+async fn replace_transaction(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Json(req): Json<ReplaceRequest>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let id = Hash::from_hex(&id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid transaction id: {}", e)))?;
+
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let tx = state
+        .replace(&id, req.memo, req.relay_tip)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(accepted(&state.dag, &tx))
+}
+
+/// Accept a transaction signed elsewhere
+async fn submit_transaction(
+    State(state): State<SharedState>,
+    Json(tx): Json<Transaction>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    let tx = state
+        .submit(tx)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(accepted(&state.dag, &tx))
+}
+
+async fn get_mempool(State(state): State<SharedState>) -> Json<MempoolResponse> {
+    let state = state.read().await;
+    let transactions = state
+        .mempool
+        .entries()
+        .into_iter()
+        .filter_map(|(id, entry)| {
+            let vertex = state.dag.get(&id)?;
+            let data = &vertex.transaction.data;
+            Some(PendingTransaction {
+                id: id.to_string(),
+                sender: data.sender.to_string(),
+                recipient: data.recipient.to_string(),
+                amount: data.amount,
+                fee: data.fee,
+                received_ms: entry.received_ms,
+                status: FinalityChecker::finality_status(&state.dag, &id).name(),
+                weight: vertex.cumulative_weight,
+            })
+        })
+        .collect();
+
+    Json(MempoolResponse {
+        count: state.mempool.len(),
+        capacity: MAX_PENDING,
+        per_sender_limit: MAX_PENDING_PER_SENDER,
+        transactions,
+    })
+}
+
+/// What a new transaction should offer in fee and relay tip to get ahead
+async fn get_fee_estimate(State(state): State<SharedState>) -> Json<FeeEstimate> {
+    Json(state.read().await.mempool.fee_estimate())
+}
+
+/// Pay the fee for a transaction signed by someone else (meta-transaction)
+async fn sponsor_transaction(
+    State(state): State<SharedState>,
+    Json(tx): Json<Transaction>,
+) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+    let mut state = state.write().await;
+    wallet_unlocked(&state)?;
+    let tx = state
+        .sponsor(tx)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(accepted(&state.dag, &tx))
+}
+
 async fn claim_relay_reward(
     State(state): State<SharedState>,
 ) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
-    let mut state = state.lock().unwrap();
+    let mut state = state.write().await;
     let tx = state
         .claim_relay_reward()
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    Ok(Json(TransactionResponse {
-        id: tx.id.to_string(),
-        status: "confirmed".to_string(),
-    }))
+    Ok(accepted(&state.dag, &tx))
 }
 
 async fn get_tips(State(state): State<SharedState>) -> Json<Vec<String>> {
-    let state = state.lock().unwrap();
+    let state = state.read().await;
     let tips: Vec<String> = state.dag.tips().iter().map(|t| t.to_string()).collect();
     Json(tips)
 }
+
+/// Final vertices with no final child, which light clients verify
+/// inclusion proofs against
+async fn get_final_tips(State(state): State<SharedState>) -> Json<FinalTipsResponse> {
+    let state = state.read().await;
+    Json(FinalTipsResponse {
+        genesis_id: state.dag.genesis_id.map(|id| id.to_string()),
+        tips: state.dag.final_tips().iter().map(|t| t.to_string()).collect(),
+    })
+}
+
+async fn get_dag_health(State(state): State<SharedState>) -> Json<DagHealth> {
+    let state = state.read().await;
+    Json(DagHealth::analyze(&state.dag))
+}
+
+/// Relayers ranked by the relays their final reward claims proved, and
+/// where this node stands
+async fn get_top_relayers(
+    State(state): State<SharedState>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Json<RelayLeaderboard> {
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_SIZE).min(MAX_LEADERBOARD_SIZE);
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let state = state.read().await;
+    let me = &state.keypair.public_key;
+    Json(RelayLeaderboard::analyze(&state.dag, query.period, now, limit, Some(me)))
+}
+
+/// The founder grant and every spend from the founder key, for auditing
+async fn get_founder(State(state): State<SharedState>) -> Json<FounderReport> {
+    let state = state.read().await;
+    Json(FounderReport::analyze(&state.dag))
+}
+
+/// Whether a newer signed release is out, as of the last update check
+async fn get_update(State(state): State<SharedState>) -> Json<UpdateStatus> {
+    Json(state.read().await.update.clone())
+}
+
+async fn get_peers(State(state): State<SharedState>) -> Json<Vec<PeerResponse>> {
+    let state = state.read().await;
+    let peers = state
+        .network
+        .peers()
+        .into_iter()
+        .map(|(id, peer)| PeerResponse {
+            peer,
+            reputation: state.reputation.score(&id).cloned().unwrap_or_default(),
+        })
+        .collect();
+    Json(peers)
+}
+
+/// Peers that went away and the gossip held for them until they return
+async fn get_held_gossip(State(state): State<SharedState>) -> Json<Vec<HeldSummary>> {
+    let mut held = state.read().await.network.held();
+    held.sort_by_key(|peer| peer.away_since);
+    Json(held)
+}
+
+/// Peers banned right now
+async fn get_peer_bans(State(state): State<SharedState>) -> Json<Vec<BanResponse>> {
+    let state = state.read().await;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let mut bans: Vec<BanResponse> = state
+        .reputation
+        .bans()
+        .iter()
+        .filter(|(_, ban)| ban.until_ms > now)
+        .map(|(peer, ban)| BanResponse {
+            peer_id: peer.public_key.to_string(),
+            address: ban.address.map(|a| a.to_string()),
+            until_ms: ban.until_ms,
+            reason: ban.reason.clone(),
+            count: ban.count,
+        })
+        .collect();
+    bans.sort_by_key(|b| b.until_ms);
+    Json(bans)
+}
+
+/// API response for the peer audit log
+#[derive(Serialize)]
+struct PeerAuditResponse {
+    retention: PeerAuditConfig,
+    /// Newest first; pass the last `seq` as `before` for older ones
+    entries: Vec<PeerAuditEntry>,
+}
+
+async fn get_peer_audit(
+    State(state): State<SharedState>,
+    Query(query): Query<PeerAuditQuery>,
+) -> Result<Json<PeerAuditResponse>, (StatusCode, String)> {
+    let audit = state.read().await.peer_audit.clone();
+    let entries = audit
+        .entries(&query)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(PeerAuditResponse {
+        retention: audit.config().clone(),
+        entries,
+    }))
+}
+
+#[derive(Serialize)]
+struct ApiAuditResponse {
+    config: ApiAuditConfig,
+    /// First entry whose hash doesn't follow from the one before it, if the
+    /// log file was edited
+    broken_at: Option<u64>,
+    entries: Vec<ApiAuditEntry>,
+}
+
+async fn get_api_audit(
+    State(state): State<SharedState>,
+    Query(query): Query<ApiAuditQuery>,
+) -> Result<Json<ApiAuditResponse>, (StatusCode, String)> {
+    let audit = state.read().await.api_audit.clone();
+    let (entries, broken_at) = audit
+        .entries(&query)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(ApiAuditResponse {
+        config: audit.config().clone(),
+        broken_at,
+        entries,
+    }))
+}
+
+/// Format of an API audit log export, next to the filters
+#[derive(Deserialize)]
+struct ApiAuditExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// The API audit log, oldest first, for compliance archives; a broken hash
+/// chain is reported in the `x-rhiza-audit-broken-at` header
+async fn export_api_audit(
+    State(state): State<SharedState>,
+    Query(query): Query<ApiAuditQuery>,
+    Query(export): Query<ApiAuditExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let audit = state.read().await.api_audit.clone();
+    let (body, broken_at) = audit
+        .export(&query, export.format)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let content_type = match export.format {
+        ExportFormat::Jsonl => "application/x-ndjson",
+        ExportFormat::Csv => "text/csv",
+    };
+    let mut response = ([(axum::http::header::CONTENT_TYPE, content_type)], body).into_response();
+    if let Some(seq) = broken_at {
+        response.headers_mut().insert("x-rhiza-audit-broken-at", seq.into());
+    }
+    Ok(response)
+}
+
+#[derive(Serialize)]
+struct PruningResponse {
+    config: PruningConfig,
+    /// Depth at or below which final history has been pruned
+    horizon: Option<u64>,
+    /// Transactions pruned from memory
+    pruned: usize,
+    /// Keys carrying a balance over from pruned history
+    accounts: usize,
+    /// Vertices still in memory
+    in_memory: usize,
+}
+
+async fn get_pruning(State(state): State<SharedState>) -> Json<PruningResponse> {
+    let state = state.read().await;
+    let pruned = state.dag.pruned();
+    Json(PruningResponse {
+        config: state.pruning.clone(),
+        horizon: pruned.horizon(),
+        pruned: pruned.len(),
+        accounts: pruned.accounts(),
+        in_memory: state.dag.len(),
+    })
+}
+
+/// What an operator checks first on a headless node
+#[derive(Serialize)]
+struct AdminStatusResponse {
+    version: &'static str,
+    uptime_secs: u64,
+    network_id: u32,
+    address: String,
+    /// Node identity, which differs from the wallet's key when it has its own keystore
+    public_key: String,
+    backend: String,
+    dag_size: usize,
+    dag_depth: u64,
+    tips_count: usize,
+    pending: usize,
+    /// Proof of work the load asks of a new transaction, in bits
+    work_bits: u32,
+    peers: usize,
+    /// Gossip messages held for absent peers
+    held_messages: usize,
+    /// "ok" or "degraded", as in `/health`
+    health: &'static str,
+    /// Newer release, if the update check found one
+    update_available: Option<String>,
+}
+
+async fn get_admin_status(State(state): State<SharedState>) -> Json<AdminStatusResponse> {
+    let state = state.read().await;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let degraded = state.weight_audit.alarm().is_some()
+        || state.finality_slo.as_ref().is_some_and(|slo| {
+            !slo.breaches(state.finality.latency(), state.mempool.oldest_age_ms(now)).is_empty()
+        });
+    Json(AdminStatusResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs: now.saturating_sub(state.started_at) / 1_000,
+        network_id: state.network_id,
+        address: state.address().to_string(),
+        public_key: state.keypair.public_key.to_string(),
+        backend: format!("{:?}", state.config.backend).to_lowercase(),
+        dag_size: state.dag.len(),
+        dag_depth: state.dag.depth(),
+        tips_count: state.dag.tip_count(),
+        pending: state.mempool.len(),
+        work_bits: state.dag.required_work(&state.dag.select_parents()),
+        peers: state.network.peer_count(),
+        held_messages: state.network.held().iter().map(|peer| peer.messages).sum(),
+        health: if degraded { "degraded" } else { "ok" },
+        update_available: state.update.available.as_ref().map(|update| update.version.clone()),
+    })
+}
+
+/// A ranged DAG sync with one peer
+#[derive(Serialize)]
+struct PeerSyncResponse {
+    peer_id: String,
+    /// Depth the next page starts at
+    next_depth: u64,
+    /// Remote depth being synced towards
+    target_depth: u64,
+    /// "idle", "requesting" or "complete"
+    phase: &'static str,
+}
+
+#[derive(Serialize)]
+struct SyncStatusResponse {
+    dag_depth: u64,
+    /// Whether no sync with a peer has further to go
+    synced: bool,
+    /// Transactions parked until their missing ancestry arrives
+    parked: usize,
+    syncs: Vec<PeerSyncResponse>,
+}
+
+async fn get_sync_status(State(state): State<SharedState>) -> Json<SyncStatusResponse> {
+    let state = state.read().await;
+    let mut syncs: Vec<PeerSyncResponse> = state
+        .syncs()
+        .map(|(peer, sync)| PeerSyncResponse {
+            peer_id: peer.public_key.to_string(),
+            next_depth: sync.next_depth(),
+            target_depth: sync.target_depth(),
+            phase: match sync.phase() {
+                SyncPhase::Idle => "idle",
+                SyncPhase::Requesting { .. } => "requesting",
+                SyncPhase::Complete => "complete",
+            },
+        })
+        .collect();
+    syncs.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+    Json(SyncStatusResponse {
+        dag_depth: state.dag.depth(),
+        synced: syncs.iter().all(|sync| sync.phase == "complete"),
+        parked: state.parked_len(),
+        syncs,
+    })
+}
+
+#[derive(Deserialize)]
+struct BanRequest {
+    /// Hex public key of the peer
+    peer_id: String,
+    /// Defaults to the length of a misbehavior ban, doubling with repeats
+    #[serde(default)]
+    duration_secs: Option<u64>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Ban a peer and drop its connection
+async fn ban_peer(
+    State(state): State<SharedState>,
+    Json(req): Json<BanRequest>,
+) -> Result<Json<BanResponse>, (StatusCode, String)> {
+    let peer = PeerId::new(parse_public_key(&req.peer_id)?);
+    let reason = req.reason.unwrap_or_else(|| "banned by the operator".to_string());
+    let duration_ms = req.duration_secs.map(|secs| secs.saturating_mul(1_000));
+    let ban = state.write().await.ban_peer(&peer, duration_ms, reason);
+    Ok(Json(BanResponse {
+        peer_id: req.peer_id,
+        address: ban.address.map(|a| a.to_string()),
+        until_ms: ban.until_ms,
+        reason: ban.reason,
+        count: ban.count,
+    }))
+}
+
+/// Stop the node as if it got Ctrl+C
+async fn shutdown(State(state): State<SharedState>) -> (StatusCode, &'static str) {
+    let stop = state.read().await.shutdown.clone();
+    tracing::info!("Shutdown requested over the API");
+    // Leave the response time to go out first
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stop.notify_one();
+    });
+    (StatusCode::ACCEPTED, "Shutting down")
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SnapshotRequest {
+    /// Add this node's publisher signature
+    pub sign: bool,
+    /// Serve the snapshot at /snapshot/latest once it is verified
+    pub publish: bool,
+}
+
+impl Default for SnapshotRequest {
+    fn default() -> Self {
+        SnapshotRequest { sign: true, publish: false }
+    }
+}
+
+/// Take a snapshot of the running node in the background
+async fn take_snapshot(
+    State(state): State<SharedState>,
+    req: Option<Json<SnapshotRequest>>,
+) -> Result<(StatusCode, Json<SnapshotJob>), (StatusCode, String)> {
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    let job = snapshot::start(&state, req.sign, req.publish)
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Progress of the snapshot being taken, or the outcome of the last one
+async fn get_snapshot_job(
+    State(state): State<SharedState>,
+) -> Result<Json<SnapshotJob>, (StatusCode, String)> {
+    state
+        .read()
+        .await
+        .snapshots
+        .job
+        .clone()
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "No snapshot has been taken since the node started".to_string()))
+}
+
+/// The snapshot this node last published, for others to bootstrap from
+async fn get_published_snapshot(State(state): State<SharedState>) -> Result<Response, (StatusCode, String)> {
+    let path = state
+        .read()
+        .await
+        .snapshots
+        .published()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "This node has not published a snapshot".to_string()))?;
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(axum::http::header::CONTENT_TYPE, "application/octet-stream")], bytes).into_response())
+}
+
+async fn get_webhook_queue(
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<Delivery>>, (StatusCode, String)> {
+    let store = state.read().await.storage.webhook_store();
+    let queued = store
+        .queued()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(queued))
+}
+
+async fn get_webhook_dead_letters(
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<Delivery>>, (StatusCode, String)> {
+    let store = state.read().await.storage.webhook_store();
+    let dead = store
+        .dead_letters()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(dead))
+}
+
+/// Put a dead-lettered delivery back in the queue for another round of attempts
+async fn replay_webhook_delivery(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<Delivery>, (StatusCode, String)> {
+    let store = state.read().await.storage.webhook_store();
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    store
+        .replay(&id, now)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No dead-lettered delivery {}", id)))
+}
+
+async fn get_webhook_receipt(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<DeliveryReceipt>, (StatusCode, String)> {
+    let store = state.read().await.storage.webhook_store();
+    store
+        .receipt(&id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No receipt for delivery {}", id)))
+}
+
+async fn poll_events(
+    State(state): State<SharedState>,
+    Query(query): Query<EventPollQuery>,
+) -> Json<EventBatch> {
+    // Clone the bus out so the node lock isn't held while waiting
+    let events = state.read().await.events.clone();
+    let wait = query.wait_ms.unwrap_or(MAX_POLL_WAIT_MS).min(MAX_POLL_WAIT_MS);
+    Json(events.poll(query.cursor, Duration::from_millis(wait)).await)
+}
+
+/// Push node events as server-sent events, resuming from `Last-Event-ID`;
+/// the same feed as `/ws` for clients that would rather not speak WebSocket
+async fn stream_events(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<EventStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let filter = match query.address.as_deref() {
+        Some(list) => Some(parse_address_filter(list)?),
+        None => None,
+    };
+    let events = state.read().await.events.clone();
+    let cursor = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or(query.cursor)
+        .unwrap_or_else(|| events.latest_cursor());
+
+    let batches = stream::unfold((events, cursor), |(events, cursor)| async move {
+        let batch = events
+            .poll(cursor, Duration::from_millis(MAX_POLL_WAIT_MS))
+            .await;
+        let next = batch.next_cursor.max(cursor);
+        Some((batch, (events, next)))
+    });
+    let frames = batches.flat_map(move |batch| {
+        let mut frames = Vec::new();
+        if batch.truncated {
+            frames.push(Event::default().event("lagged").data("events were dropped"));
+        }
+        for record in batch.events {
+            if filter.as_ref().is_some_and(|f| !involves(f, &record.event)) {
+                continue;
+            }
+            if let Ok(frame) = Event::default()
+                .id(record.cursor.to_string())
+                .event(record.event.name())
+                .json_data(&record)
+            {
+                frames.push(frame);
+            }
+        }
+        stream::iter(frames.into_iter().map(Ok))
+    });
+
+    Ok(Sse::new(frames).keep_alive(KeepAlive::default()))
+}
+
+/// Push node events as JSON text frames over a WebSocket
+async fn ws_events(
+    State(state): State<SharedState>,
+    Query(query): Query<EventStreamQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    let filter = match query.address.as_deref() {
+        Some(list) => Some(parse_address_filter(list)?),
+        None => None,
+    };
+    let events = state.read().await.events.clone();
+    let cursor = query.cursor.unwrap_or_else(|| events.latest_cursor());
+    Ok(ws.on_upgrade(move |socket| push_events(socket, events, cursor, filter)))
+}
+
+/// Feed a subscriber from the event bus until it hangs up
+async fn push_events(
+    mut socket: WebSocket,
+    events: Arc<EventBus>,
+    mut cursor: u64,
+    filter: Option<HashSet<Address>>,
+) {
+    loop {
+        let batch = tokio::select! {
+            batch = events.poll(cursor, Duration::from_millis(MAX_POLL_WAIT_MS)) => batch,
+            // Subscribers only listen; anything but a close is ignored
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        cursor = batch.next_cursor.max(cursor);
+
+        let mut frames = Vec::new();
+        if batch.truncated {
+            frames.push(serde_json::json!({ "type": "lagged" }).to_string());
+        }
+        for record in batch.events {
+            if filter.as_ref().is_some_and(|f| !involves(f, &record.event)) {
+                continue;
+            }
+            if let Ok(frame) = serde_json::to_string(&record) {
+                frames.push(frame);
+            }
+        }
+        for frame in frames {
+            if socket.send(Message::Text(frame)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Parse a comma-separated list of addresses and hex public keys
+fn parse_address_filter(list: &str) -> Result<HashSet<Address>, (StatusCode, String)> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            if entry.len() == 64 {
+                parse_public_key(entry).map(|pk| Address::from_public_key(&pk))
+            } else {
+                Address::from_str(&entry.to_ascii_lowercase())
+                    .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid address: {}", e)))
+            }
+        })
+        .collect()
+}
+
+/// Whether a transaction event involves one of the filtered addresses
+fn involves(filter: &HashSet<Address>, event: &NodeEvent) -> bool {
+    event.accounts().is_some_and(|accounts| {
+        accounts
+            .iter()
+            .filter_map(|hex| parse_public_key(hex).ok())
+            .any(|pk| filter.contains(&Address::from_public_key(&pk)))
+    })
+}
+
+/// Resolve a payment recipient given as a hex public key, `rhz1...`
+/// address or `name@rhz` alias
+fn resolve_recipient(state: &NodeState, recipient: &str) -> Result<PublicKey, (StatusCode, String)> {
+    let recipient = recipient.trim();
+    if recipient.len() == 64 && recipient.chars().all(|c| c.is_ascii_hexdigit()) {
+        return parse_public_key(recipient);
+    }
+    if let Ok(address) = Address::from_str(&recipient.to_ascii_lowercase()) {
+        // Addresses hash the key, so the key must have shown up on the DAG
+        return state.dag.find_public_key(&address).ok_or((
+            StatusCode::NOT_FOUND,
+            format!(
+                "{} has not appeared on the DAG yet; its owner can publish it with POST /announce",
+                address
+            ),
+        ));
+    }
+    if let Ok(alias) = Alias::from_str(recipient) {
+        return state
+            .dag
+            .aliases()
+            .resolve(&alias)
+            .map(|record| record.receive_key.clone())
+            .ok_or((StatusCode::NOT_FOUND, format!("{} is not registered", alias)));
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        "Recipient must be a hex public key, rhz1 address or name@rhz alias".to_string(),
+    ))
+}
+
+/// Response for a transaction the node just took in, with its finality so far
+fn accepted(dag: &Dag, tx: &Transaction) -> Json<TransactionResponse> {
+    Json(TransactionResponse {
+        id: tx.id.to_string(),
+        status: FinalityChecker::finality_status(dag, &tx.id).name(),
+    })
+}
+
+fn parse_public_key(hex_key: &str) -> Result<PublicKey, (StatusCode, String)> {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid hex: {}", e)))?
+        .try_into()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid public key length".to_string()))?;
+    Ok(PublicKey::from_bytes(bytes))
+}
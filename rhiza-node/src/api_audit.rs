@@ -0,0 +1,541 @@
+use crate::config::NodeConfig;
+use anyhow::Context;
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// File name of the API audit log inside the data directory
+pub const API_AUDIT_FILE: &str = "api-audit.jsonl";
+
+/// Entries `GET /admin/audit/api` returns unless asked for more
+pub const DEFAULT_API_AUDIT_PAGE: usize = 100;
+
+/// Most entries `GET /admin/audit/api` returns at once
+pub const MAX_API_AUDIT_PAGE: usize = 1_000;
+
+/// Largest request or response body read for the details of an entry, the
+/// same as the API's own JSON limit
+const MAX_AUDITED_BODY: usize = 2 * 1024 * 1024;
+
+/// Credentials for the REST API (`api_keys` in config.json)
+///
+/// Requests name their key with `Authorization: Bearer <token>`. Once any
+/// key is configured, every request the audit log covers must carry one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    /// Name the key's requests are attributed to in the audit log
+    pub id: String,
+    /// Hex BLAKE3 hash of the bearer token; the token itself isn't stored
+    pub token_hash: String,
+}
+
+impl ApiKey {
+    /// A new key named `id`, and the token to hand to whoever holds it
+    pub fn generate(id: &str) -> (Self, String) {
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let key = ApiKey {
+            id: id.to_string(),
+            token_hash: blake3::hash(token.as_bytes()).to_hex().to_string(),
+        };
+        (key, token)
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        // blake3::Hash compares in constant time
+        blake3::Hash::from_hex(&self.token_hash).is_ok_and(|hash| hash == blake3::hash(token.as_bytes()))
+    }
+}
+
+/// A part of a request that the audit log can leave out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditField {
+    ClientIp,
+    Recipient,
+    Amount,
+    Memo,
+}
+
+/// What the API audit log records (`api_audit` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiAuditConfig {
+    pub enabled: bool,
+    /// Fields never written to the log; they are dropped before the entry
+    /// reaches the disk, so an export can't reveal them either
+    pub redact: Vec<AuditField>,
+}
+
+impl Default for ApiAuditConfig {
+    fn default() -> Self {
+        ApiAuditConfig {
+            enabled: true,
+            redact: vec![AuditField::Memo],
+        }
+    }
+}
+
+/// One audited API request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiAuditEntry {
+    /// Position in the log, assigned on append; later entries have higher ones
+    pub seq: u64,
+    /// When the request was answered (unix ms)
+    pub at_ms: u64,
+    /// API key the request authenticated with; none if the node has no keys
+    /// or the request was refused for lacking one
+    pub key_id: Option<String>,
+    pub client_ip: Option<String>,
+    pub method: String,
+    /// Route the request matched, e.g. `/tx/:id/replace`
+    pub route: String,
+    pub path: String,
+    /// HTTP status of the response
+    pub status: u16,
+    pub recipient: Option<String>,
+    pub amount: Option<u64>,
+    pub memo: Option<String>,
+    /// Transaction the request created, if it answered with one
+    pub tx_id: Option<String>,
+    /// Fields left out under the redaction settings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redacted: Vec<AuditField>,
+    /// BLAKE3 over the previous entry's hash and this entry; editing or
+    /// dropping a line breaks the hash of every line after it
+    pub hash: String,
+}
+
+impl ApiAuditEntry {
+    fn chain_hash(&self, previous: &str) -> anyhow::Result<String> {
+        let unhashed = ApiAuditEntry {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(previous.as_bytes());
+        hasher.update(&serde_json::to_vec(&unhashed)?);
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    /// Header and row of the CSV export
+    const CSV_HEADER: &'static str =
+        "seq,at_ms,key_id,client_ip,method,route,path,status,recipient,amount,memo,tx_id,hash";
+
+    fn csv_row(&self) -> String {
+        let fields = [
+            self.seq.to_string(),
+            self.at_ms.to_string(),
+            self.key_id.clone().unwrap_or_default(),
+            self.client_ip.clone().unwrap_or_default(),
+            self.method.clone(),
+            self.route.clone(),
+            self.path.clone(),
+            self.status.to_string(),
+            self.recipient.clone().unwrap_or_default(),
+            self.amount.map(|a| a.to_string()).unwrap_or_default(),
+            self.memo.clone().unwrap_or_default(),
+            self.tx_id.clone().unwrap_or_default(),
+            self.hash.clone(),
+        ];
+        fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Which entries to list or export
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiAuditQuery {
+    /// At most this many entries (capped at [`MAX_API_AUDIT_PAGE`] when
+    /// listing; exports are complete unless given)
+    pub limit: Option<usize>,
+    /// Only entries before this sequence number (for paging back)
+    pub before: Option<u64>,
+    /// Only requests made with this API key
+    pub key: Option<String>,
+    /// Only this route, e.g. `/send`
+    pub route: Option<String>,
+    /// Only entries at or after this time (unix ms)
+    pub from_ms: Option<u64>,
+    /// Only entries before this time (unix ms)
+    pub to_ms: Option<u64>,
+}
+
+impl ApiAuditQuery {
+    fn matches(&self, entry: &ApiAuditEntry) -> bool {
+        self.before.is_none_or(|before| entry.seq < before)
+            && self.key.as_ref().is_none_or(|key| entry.key_id.as_ref() == Some(key))
+            && self.route.as_ref().is_none_or(|route| entry.route == *route)
+            && self.from_ms.is_none_or(|from| entry.at_ms >= from)
+            && self.to_ms.is_none_or(|to| entry.at_ms < to)
+    }
+}
+
+/// Format of an audit log export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One JSON entry per line, as in the log file
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+/// Entries read back from the log, oldest first
+pub struct AuditLog {
+    pub entries: Vec<ApiAuditEntry>,
+    /// First entry whose hash doesn't follow from the one before it
+    pub broken_at: Option<u64>,
+}
+
+struct AuditFile {
+    file: File,
+    last_seq: u64,
+    last_hash: String,
+}
+
+/// Append-only record of API requests that change something or read admin
+/// data, attributed to the API key that made them
+///
+/// Entries are JSON lines in [`API_AUDIT_FILE`], chained by hash so an
+/// export shows whether the file was edited. Nothing is rotated out;
+/// retention is left to the operator's archiving.
+#[derive(Clone)]
+pub struct ApiAudit {
+    path: PathBuf,
+    config: ApiAuditConfig,
+    file: Option<Arc<Mutex<AuditFile>>>,
+}
+
+impl ApiAudit {
+    /// A log that records nothing
+    pub fn disabled() -> Self {
+        ApiAudit {
+            path: PathBuf::new(),
+            config: ApiAuditConfig {
+                enabled: false,
+                ..ApiAuditConfig::default()
+            },
+            file: None,
+        }
+    }
+
+    /// Open the log in `data_path`, continuing the chain of what it holds
+    pub fn open(data_path: &Path, config: ApiAuditConfig) -> anyhow::Result<Self> {
+        let path = data_path.join(API_AUDIT_FILE);
+        if !config.enabled {
+            return Ok(ApiAudit { path, config, file: None });
+        }
+        let log = read_log(&path)?;
+        if let Some(seq) = log.broken_at {
+            warn!("API audit log {} was altered at entry {}", path.display(), seq);
+        }
+        let (last_seq, last_hash) = log
+            .entries
+            .last()
+            .map_or((0, String::new()), |last| (last.seq, last.hash.clone()));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+        Ok(ApiAudit {
+            path,
+            config,
+            file: Some(Arc::new(Mutex::new(AuditFile { file, last_seq, last_hash }))),
+        })
+    }
+
+    pub fn config(&self) -> &ApiAuditConfig {
+        &self.config
+    }
+
+    /// Redact, chain and append an entry; failures are logged, never passed
+    /// on to the request being audited
+    pub fn record(&self, mut entry: ApiAuditEntry) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        for field in &self.config.redact {
+            let present = match field {
+                AuditField::ClientIp => entry.client_ip.take().is_some(),
+                AuditField::Recipient => entry.recipient.take().is_some(),
+                AuditField::Amount => entry.amount.take().is_some(),
+                AuditField::Memo => entry.memo.take().is_some(),
+            };
+            if present {
+                entry.redacted.push(*field);
+            }
+        }
+
+        let mut file = file.lock().unwrap();
+        entry.seq = file.last_seq + 1;
+        let result = entry.chain_hash(&file.last_hash).and_then(|hash| {
+            entry.hash = hash;
+            let mut line = serde_json::to_vec(&entry)?;
+            line.push(b'\n');
+            file.file.write_all(&line)?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => {
+                file.last_seq = entry.seq;
+                file.last_hash = entry.hash;
+            }
+            Err(e) => warn!("Failed to write API audit entry: {}", e),
+        }
+    }
+
+    /// Entries matching `query`, newest first
+    pub fn entries(&self, query: &ApiAuditQuery) -> anyhow::Result<(Vec<ApiAuditEntry>, Option<u64>)> {
+        let limit = query.limit.unwrap_or(DEFAULT_API_AUDIT_PAGE).min(MAX_API_AUDIT_PAGE);
+        let log = read_log(&self.path)?;
+        let entries = log
+            .entries
+            .into_iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(limit)
+            .collect();
+        Ok((entries, log.broken_at))
+    }
+
+    /// Entries matching `query` in `format`, oldest first
+    pub fn export(&self, query: &ApiAuditQuery, format: ExportFormat) -> anyhow::Result<(String, Option<u64>)> {
+        let log = read_log(&self.path)?;
+        let matching = log.entries.iter().filter(|entry| query.matches(entry));
+        let matching = matching.take(query.limit.unwrap_or(usize::MAX));
+        let mut out = String::new();
+        match format {
+            ExportFormat::Jsonl => {
+                for entry in matching {
+                    out.push_str(&serde_json::to_string(entry)?);
+                    out.push('\n');
+                }
+            }
+            ExportFormat::Csv => {
+                out.push_str(ApiAuditEntry::CSV_HEADER);
+                out.push('\n');
+                for entry in matching {
+                    out.push_str(&entry.csv_row());
+                    out.push('\n');
+                }
+            }
+        }
+        Ok((out, log.broken_at))
+    }
+}
+
+/// Read the whole log, checking the hash chain on the way
+fn read_log(path: &Path) -> anyhow::Result<AuditLog> {
+    let mut log = AuditLog {
+        entries: Vec::new(),
+        broken_at: None,
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(log),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    let mut previous = String::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: ApiAuditEntry = serde_json::from_str(&line)
+            .with_context(|| format!("Unreadable entry after {} in {}", log.entries.len(), path.display()))?;
+        if log.broken_at.is_none() && entry.chain_hash(&previous)? != entry.hash {
+            log.broken_at = Some(entry.seq);
+        }
+        previous = entry.hash.clone();
+        log.entries.push(entry);
+    }
+    Ok(log)
+}
+
+/// API keys and the audit log, as the request middleware sees them
+#[derive(Clone)]
+pub struct ApiAccess {
+    keys: Arc<Vec<ApiKey>>,
+    audit: ApiAudit,
+}
+
+impl ApiAccess {
+    pub fn new(keys: Vec<ApiKey>, audit: ApiAudit) -> Self {
+        ApiAccess {
+            keys: Arc::new(keys),
+            audit,
+        }
+    }
+
+    /// The key a request's bearer token belongs to
+    fn authenticate(&self, headers: &HeaderMap) -> Option<&ApiKey> {
+        let token = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?
+            .trim();
+        self.keys.iter().find(|key| key.matches(token))
+    }
+}
+
+/// Requests the log covers: anything but a read, and reads of admin data
+fn is_audited(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD) || path.starts_with("/admin/")
+}
+
+/// Requests that control the node rather than read from it
+fn is_admin_action(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD) && path.starts_with("/admin/")
+}
+
+/// Middleware that checks API keys and records audited requests
+pub async fn audit_requests(State(access): State<ApiAccess>, request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if !is_audited(&method, &path) {
+        return next.run(request).await;
+    }
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| path.clone(), |matched| matched.as_str().to_string());
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = client.map(|ip| ip.to_string());
+    let key_id = access.authenticate(request.headers()).map(|key| key.id.clone());
+    let mut entry = ApiAuditEntry {
+        seq: 0,
+        at_ms: 0,
+        key_id,
+        client_ip,
+        method: method.to_string(),
+        route,
+        path,
+        status: 0,
+        recipient: None,
+        amount: None,
+        memo: None,
+        tx_id: None,
+        redacted: Vec::new(),
+        hash: String::new(),
+    };
+
+    let remote = !client.is_some_and(|ip| ip.is_loopback());
+    let response = if !access.keys.is_empty() && entry.key_id.is_none() {
+        (StatusCode::UNAUTHORIZED, "A valid API key is required (Authorization: Bearer <token>)").into_response()
+    } else if access.keys.is_empty() && remote && is_admin_action(&method, &entry.path) {
+        // An open API still can't be stopped or made to ban peers from afar
+        (StatusCode::FORBIDDEN, "Admin actions from other hosts need an API key").into_response()
+    } else {
+        let (parts, body) = request.into_parts();
+        match axum::body::to_bytes(body, MAX_AUDITED_BODY).await {
+            Ok(bytes) => {
+                request_details(&mut entry, &bytes);
+                let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+                response_details(&mut entry, response).await
+            }
+            Err(_) => (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response(),
+        }
+    };
+
+    entry.status = response.status().as_u16();
+    entry.at_ms = chrono::Utc::now().timestamp_millis() as u64;
+    access.audit.record(entry);
+    response
+}
+
+/// Recipient, amount and memo of a JSON request, whether given directly or
+/// inside a signed transaction
+fn request_details(entry: &mut ApiAuditEntry, body: &Bytes) {
+    let Ok(value) = serde_json::from_slice::<Value>(body) else {
+        return;
+    };
+    let fields = value.get("data").unwrap_or(&value);
+    entry.recipient = fields.get("recipient").and_then(Value::as_str).map(str::to_string);
+    entry.amount = fields.get("amount").and_then(Value::as_u64);
+    entry.memo = fields.get("memo").and_then(Value::as_str).map(str::to_string);
+}
+
+/// Note the transaction a JSON response names, passing the response on
+async fn response_details(entry: &mut ApiAuditEntry, response: Response) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_AUDITED_BODY).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Response too large to audit").into_response();
+    };
+    if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+        entry.tx_id = value.get("id").and_then(Value::as_str).map(str::to_string);
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Add an API key to config.json, printing its token once
+pub fn add_key(data_path: &Path, id: &str) -> anyhow::Result<()> {
+    let path = data_path.join("config.json");
+    let mut config = NodeConfig::load_or_default(&path)?;
+    if config.api_keys.iter().any(|key| key.id == id) {
+        anyhow::bail!("An API key named {} already exists; revoke it first to replace it", id);
+    }
+    let (key, token) = ApiKey::generate(id);
+    config.api_keys.push(key);
+    config.save(&path)?;
+
+    println!("🔑 API key {} added", id);
+    println!("Token: {}", token);
+    println!("⚠️  The token is shown only now; restart the node for the key to take effect");
+    Ok(())
+}
+
+/// List the API keys in config.json
+pub fn list_keys(data_path: &Path) -> anyhow::Result<()> {
+    let config = NodeConfig::load_or_default(&data_path.join("config.json"))?;
+    if config.api_keys.is_empty() {
+        println!("No API keys; the API accepts requests without one");
+    }
+    for key in &config.api_keys {
+        println!("{}", key.id);
+    }
+    Ok(())
+}
+
+/// Remove an API key from config.json
+pub fn revoke_key(data_path: &Path, id: &str) -> anyhow::Result<()> {
+    let path = data_path.join("config.json");
+    let mut config = NodeConfig::load_or_default(&path)?;
+    let before = config.api_keys.len();
+    config.api_keys.retain(|key| key.id != id);
+    if config.api_keys.len() == before {
+        anyhow::bail!("No API key named {}", id);
+    }
+    config.save(&path)?;
+    println!("API key {} revoked; restart the node for it to take effect", id);
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use crate::storage::PeerAuditStore;
+use rhiza_core::network::mesh::TransportType;
+use rhiza_core::network::peer::PeerId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use tracing::warn;
+
+/// Transport of a peer connection unless set otherwise: Noise over TCP
+pub const TCP_TRANSPORT: &str = "tcp";
+
+/// Entries `GET /admin/audit/peers` returns unless asked for more
+pub const DEFAULT_AUDIT_PAGE: usize = 100;
+
+/// Most entries `GET /admin/audit/peers` returns at once
+pub const MAX_AUDIT_PAGE: usize = 1_000;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1_000;
+
+/// Retention of the peer audit log (`peer_audit` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerAuditConfig {
+    /// Most entries kept; the oldest are rotated out first
+    pub max_entries: usize,
+    /// Entries older than this are rotated out
+    pub max_age_days: u64,
+}
+
+impl Default for PeerAuditConfig {
+    fn default() -> Self {
+        PeerAuditConfig {
+            max_entries: 100_000,
+            max_age_days: 30,
+        }
+    }
+}
+
+/// What happened to a peer connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerAuditEvent {
+    /// Completed the handshake and was registered
+    Connected,
+    /// A registered connection ended
+    Disconnected,
+    /// Turned away before or during the handshake
+    Refused,
+    /// Banned for misbehaving
+    Banned,
+}
+
+/// Which side opened the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    pub fn of(initiator: bool) -> Self {
+        if initiator {
+            Direction::Outbound
+        } else {
+            Direction::Inbound
+        }
+    }
+}
+
+/// One entry of the peer audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerAuditEntry {
+    /// Position in the log, assigned on append; later entries have higher ones
+    pub seq: u64,
+    /// When it happened (unix ms)
+    pub at_ms: u64,
+    pub event: PeerAuditEvent,
+    /// Hex identity key, once the handshake has established it
+    pub peer_id: Option<String>,
+    /// Remote socket or device address
+    pub address: Option<String>,
+    pub transport: String,
+    pub direction: Option<Direction>,
+    pub reason: Option<String>,
+}
+
+impl PeerAuditEntry {
+    pub fn new(event: PeerAuditEvent) -> Self {
+        PeerAuditEntry {
+            seq: 0,
+            at_ms: chrono::Utc::now().timestamp_millis() as u64,
+            event,
+            peer_id: None,
+            address: None,
+            transport: TCP_TRANSPORT.to_string(),
+            direction: None,
+            reason: None,
+        }
+    }
+
+    pub fn peer(mut self, peer: &PeerId) -> Self {
+        self.peer_id = Some(peer.public_key.to_string());
+        self
+    }
+
+    pub fn address(mut self, address: impl fmt::Display) -> Self {
+        self.address = Some(address.to_string());
+        self
+    }
+
+    pub fn transport(mut self, transport: TransportType) -> Self {
+        self.transport = transport.to_string();
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+/// Which entries to list, newest first
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PeerAuditQuery {
+    /// At most this many entries (capped at [`MAX_AUDIT_PAGE`])
+    pub limit: Option<usize>,
+    /// Only entries before this sequence number (for paging back)
+    pub before: Option<u64>,
+    /// Only this kind of event
+    pub event: Option<PeerAuditEvent>,
+    /// Only this peer (hex identity key)
+    pub peer: Option<String>,
+    /// Only this remote IP address
+    pub ip: Option<String>,
+}
+
+impl PeerAuditQuery {
+    fn matches(&self, entry: &PeerAuditEntry) -> bool {
+        self.before.is_none_or(|before| entry.seq < before)
+            && self.event.is_none_or(|event| entry.event == event)
+            && self.peer.as_ref().is_none_or(|peer| entry.peer_id.as_ref() == Some(peer))
+            && self.ip.as_ref().is_none_or(|ip| {
+                entry
+                    .address
+                    .as_deref()
+                    .and_then(|a| a.parse::<SocketAddr>().ok())
+                    .is_some_and(|a| a.ip().to_string() == *ip)
+            })
+    }
+}
+
+/// Append-only record of peer connects, disconnects, refusals and bans
+///
+/// Entries are never changed once written; the oldest are rotated out once
+/// the log holds `max_entries` or they are older than `max_age_days`.
+#[derive(Clone)]
+pub struct PeerAudit {
+    store: PeerAuditStore,
+    config: PeerAuditConfig,
+}
+
+impl PeerAudit {
+    pub fn new(store: PeerAuditStore, config: PeerAuditConfig) -> Self {
+        PeerAudit { store, config }
+    }
+
+    pub fn config(&self) -> &PeerAuditConfig {
+        &self.config
+    }
+
+    /// Append an entry; failures are logged, never passed on to the
+    /// connection being audited
+    pub fn record(&self, mut entry: PeerAuditEntry) {
+        let cutoff = entry
+            .at_ms
+            .saturating_sub(self.config.max_age_days.saturating_mul(DAY_MS));
+        if let Err(e) = self.store.append(&mut entry, self.config.max_entries, cutoff) {
+            warn!("Failed to write peer audit entry: {}", e);
+        }
+    }
+
+    /// Entries matching `query`, newest first
+    pub fn entries(&self, query: &PeerAuditQuery) -> anyhow::Result<Vec<PeerAuditEntry>> {
+        let limit = query.limit.unwrap_or(DEFAULT_AUDIT_PAGE).min(MAX_AUDIT_PAGE);
+        self.store.newest(limit, |entry| query.matches(entry))
+    }
+}
@@ -0,0 +1,184 @@
+use crate::anti_entropy;
+use crate::config::NodeConfig;
+use crate::keyfile;
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use rhiza_core::crypto::Hash;
+use rhiza_core::dag::bundle::Bundle;
+use rhiza_core::dag::validator::{TimestampRules, TransactionValidator};
+use rhiza_core::dag::vertex::{Dag, DagVertex};
+use rhiza_core::dag::Transaction;
+use std::path::Path;
+use tracing::warn;
+
+/// Message for a database that's locked by a running node
+const DB_LOCKED: &str = "Could not open the node database; stop the node first";
+
+/// Write the transactions from `since_depth` on to a signed, compressed
+/// bundle file
+pub fn export(data_path: &Path, password_file: Option<&Path>, since_depth: u64, out: &Path) -> Result<()> {
+    let key_path = keyfile::identity_path(data_path);
+    if !key_path.exists() {
+        anyhow::bail!("Node not initialized. Run 'rhiza-node init' first.");
+    }
+    let storage = Storage::open(&data_path.join("db")).context(DB_LOCKED)?;
+
+    // Pruned history is only on disk with cold storage
+    if let Some(pruned) = storage.get_balance_snapshot()? {
+        if since_depth <= pruned.horizon {
+            let mut dropped = 0;
+            for id in &pruned.pruned {
+                if storage.get_transaction(id)?.is_none() {
+                    dropped += 1;
+                }
+            }
+            if dropped > 0 {
+                anyhow::bail!(
+                    "{} transactions up to depth {} were pruned from disk; export from a depth above it",
+                    dropped,
+                    pruned.horizon
+                );
+            }
+        }
+    }
+
+    let transactions = storage
+        .vertices_by_depth(since_depth..)
+        .map(|vertex| vertex.map(|v| v.transaction))
+        .collect::<Result<Vec<_>>>()?;
+    if transactions.is_empty() {
+        anyhow::bail!("The node has no transactions at depth {} or deeper", since_depth);
+    }
+
+    let keypair = keyfile::unlock(&key_path, password_file)?;
+    let network_id = transactions[0].data.network_id;
+    let mut bundle = Bundle::new(network_id, since_depth, transactions);
+    bundle.sign(&keypair);
+    let bytes = bundle.to_bytes();
+    std::fs::write(out, &bytes).with_context(|| format!("Could not write {}", out.display()))?;
+
+    println!(
+        "📦 Bundled {} transactions ({} relay proofs) from depth {} into {}",
+        bundle.transactions.len(),
+        bundle.relay_proof_count(),
+        since_depth,
+        out.display()
+    );
+    println!("   {} bytes, signed by {}", bytes.len(), keypair.public_key);
+    Ok(())
+}
+
+/// Validate the transactions of a bundle file and merge the new ones into
+/// the node's DAG
+///
+/// The bundle must be signed and unaltered, but the signer needn't be
+/// trusted: every transaction is validated against the DAG as if it had
+/// arrived from a peer. Ones whose parents are neither in the DAG nor in
+/// the bundle are left out and counted.
+pub fn import(data_path: &Path, file: &Path, config: &NodeConfig) -> Result<()> {
+    let bytes = std::fs::read(file).with_context(|| format!("Could not read {}", file.display()))?;
+    let bundle = Bundle::from_bytes(&bytes).context("Bundle rejected")?;
+    let Some(signer) = bundle.signer() else {
+        anyhow::bail!("Bundle rejected: it is unsigned or was altered after signing");
+    };
+    println!(
+        "📦 Bundle of {} transactions from depth {}, signed by {}",
+        bundle.transactions.len(),
+        bundle.since_depth,
+        signer
+    );
+
+    let storage = Storage::open(&data_path.join("db")).context(DB_LOCKED)?;
+    let mut dag = load(&storage, config.timestamps)?;
+    let network_id = dag.network_id().unwrap_or(config.network_id);
+    if bundle.network_id != network_id {
+        anyhow::bail!("Bundle is for network {}, this node is on network {}", bundle.network_id, network_id);
+    }
+
+    let known = bundle.transactions.iter().filter(|tx| dag.contains(&tx.id)).count();
+    let merged = merge(&mut dag, bundle.transactions);
+    for (tx, e) in &merged.rejected {
+        println!("   ❌ {}  {}", tx.id, e);
+    }
+    for id in merged.added.iter().chain(&dag.take_newly_final()) {
+        if let Some(vertex) = dag.get(id) {
+            storage.put_vertex(vertex)?;
+        }
+    }
+
+    println!(
+        "🌿 Merged {} new transactions; {} already known, {} invalid, {} missing parents",
+        merged.added.len(),
+        known,
+        merged.rejected.len(),
+        merged.orphaned.len()
+    );
+    if !merged.orphaned.is_empty() {
+        println!("   Ask for a bundle from a lower depth to fill in their history");
+    }
+    Ok(())
+}
+
+/// The DAG as the node would restore it from storage
+fn load(storage: &Storage, rules: TimestampRules) -> Result<Dag> {
+    let mut dag = match storage.get_balance_snapshot()? {
+        Some(snapshot) => Dag::from_balance_snapshot(&snapshot),
+        None => Dag::new(),
+    };
+    dag.set_timestamp_rules(rules);
+    for vertex in storage.vertices_by_depth(..) {
+        let vertex = vertex?;
+        let id = vertex.id();
+        if dag.pruned().contains(&id) {
+            continue;
+        }
+        if let Err(e) = dag.insert_restored(vertex) {
+            warn!("Skipping stored transaction {}: {}", id, e);
+        }
+    }
+    Ok(dag)
+}
+
+/// What merging a bundle did
+struct Merged {
+    added: Vec<Hash>,
+    /// Transactions that failed validation, and why
+    rejected: Vec<(Transaction, String)>,
+    /// Transactions whose parents never turned up
+    orphaned: Vec<Transaction>,
+}
+
+/// Validate and insert what's new in `transactions`, parents first
+fn merge(dag: &mut Dag, transactions: Vec<Transaction>) -> Merged {
+    let (mut added, mut rejected) = (Vec::new(), Vec::new());
+    let mut waiting = transactions;
+    loop {
+        let before = added.len();
+        let mut orphaned = Vec::new();
+        for tx in waiting {
+            if dag.contains(&tx.id) {
+                continue;
+            }
+            if !anti_entropy::missing_parents(&tx, dag).is_empty() {
+                orphaned.push(tx);
+                continue;
+            }
+            let valid = TransactionValidator::validate_work(&tx, dag)
+                .and_then(|()| TransactionValidator::validate(&tx, dag));
+            if let Err(e) = valid {
+                rejected.push((tx, e.to_string()));
+                continue;
+            }
+            let depth = if dag.is_empty() { 0 } else { dag.depth() + 1 };
+            match dag.insert(DagVertex::new(tx.clone(), depth)) {
+                Ok(()) => added.push(tx.id),
+                Err(e) => rejected.push((tx, e.to_string())),
+            }
+        }
+        // A bundle lists parents first, but a second pass costs little
+        if added.len() == before || orphaned.is_empty() {
+            return Merged { added, rejected, orphaned };
+        }
+        waiting = orphaned;
+    }
+}
@@ -0,0 +1,73 @@
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::channels::{Channel, ChannelState, SignedChannelState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// This node's side of one of its channels, which only the two parties hold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelEntry {
+    /// Latest state the other party signed, which the node can close on
+    pub signed: SignedChannelState,
+    /// A state the node sent as funder that the payee hasn't answered yet
+    pub in_flight: Option<ChannelState>,
+    /// The close the node submitted, if it has
+    pub close: Option<Hash>,
+}
+
+impl ChannelEntry {
+    pub fn new(channel_id: Hash) -> Self {
+        ChannelEntry {
+            signed: SignedChannelState::opening(channel_id),
+            in_flight: None,
+            close: None,
+        }
+    }
+}
+
+/// Off-DAG state of the node's channels, by channel id
+#[derive(Debug, Clone, Default)]
+pub struct ChannelBook {
+    entries: HashMap<Hash, ChannelEntry>,
+}
+
+impl ChannelBook {
+    pub fn from_entries(entries: HashMap<Hash, ChannelEntry>) -> Self {
+        ChannelBook { entries }
+    }
+
+    pub fn entries(&self) -> &HashMap<Hash, ChannelEntry> {
+        &self.entries
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&ChannelEntry> {
+        self.entries.get(id)
+    }
+
+    /// The entry for `id`, starting at the opening state
+    pub fn entry(&mut self, id: Hash) -> &mut ChannelEntry {
+        self.entries.entry(id).or_insert_with(|| ChannelEntry::new(id))
+    }
+
+    /// Latest state of `id` both parties hold
+    pub fn state(&self, id: &Hash) -> ChannelState {
+        self.get(id)
+            .map_or_else(|| ChannelState::opening(*id), |entry| entry.signed.state)
+    }
+
+    /// What `channel` can still carry from `me` towards its payee
+    ///
+    /// The node knows this only for channels it funds; for anyone else's the
+    /// whole deposit is the estimate, and the hop that funds it refuses the
+    /// payment if that was too hopeful. One payment crosses a channel at a
+    /// time, so a channel with one in flight has nothing to spare.
+    pub fn capacity(&self, channel: &Channel, me: &PublicKey) -> u64 {
+        if channel.funder != *me {
+            return channel.deposit;
+        }
+        match self.get(&channel.id) {
+            Some(entry) if entry.in_flight.is_some() => 0,
+            Some(entry) => channel.deposit.saturating_sub(entry.signed.state.paid),
+            None => channel.deposit,
+        }
+    }
+}
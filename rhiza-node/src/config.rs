@@ -1,11 +1,40 @@
+use crate::api_audit::{ApiAuditConfig, ApiKey};
+use crate::audit::PeerAuditConfig;
+use crate::event_log::EventLogConfig;
+use crate::metrics::FinalitySlo;
+use crate::pruning::PruningConfig;
+use crate::updater::UpdateConfig;
+use crate::webhooks::WebhookConfig;
+use crate::wallet_session::WalletSessionConfig;
+use crate::weight_audit::WeightAuditConfig;
+use rhiza_core::consensus::{TipSelection, VotingConfig};
+use rhiza_core::crypto::PublicKey;
+use rhiza_core::dag::{TimestampRules, WorkPuzzle};
+use rhiza_core::network::{DandelionConfig, DtnConfig, GossipLimits, NetworkBackend, PeerSelection};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+/// Community-run bootstrap peers and DNS seeds, dialed when nothing else is
+/// configured. DNS seeds resolve to a rotating set of healthy nodes.
+#[cfg(feature = "default-peers")]
+pub const DEFAULT_BOOTSTRAP_PEERS: &[&str] = &[
+    "seed1.rhiza-protocol.org:7470",
+    "seed2.rhiza-protocol.org:7470",
+    "dnsseed.rhiza-protocol.org:7470",
+];
+
+/// Built without the `default-peers` feature: no baked-in peers
+#[cfg(not(feature = "default-peers"))]
+pub const DEFAULT_BOOTSTRAP_PEERS: &[&str] = &[];
+
 /// Node configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeConfig {
     /// Node display name
     pub name: String,
+    /// Network to join (see `rhiza_core::MAINNET_ID`)
+    #[serde(default = "default_network_id")]
+    pub network_id: u32,
     /// TCP port for P2P connections
     pub p2p_port: u16,
     /// REST API port
@@ -18,18 +47,136 @@ pub struct NodeConfig {
     pub enable_mdns: bool,
     /// Bootstrap peer addresses
     pub bootstrap_peers: Vec<String>,
+    /// Fall back to the built-in bootstrap peers when none are configured
+    #[serde(default = "default_use_default_peers")]
+    pub use_default_peers: bool,
+    /// How parents are chosen for the node's own transactions
+    #[serde(default)]
+    pub tip_selection: TipSelection,
+    /// Parents the node's own transactions reference, within what the
+    /// network allows (see `rhiza_core::parent_counts`)
+    #[serde(default = "default_parent_count")]
+    pub parent_count: usize,
+    /// Endpoints that node events are POSTed to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Hex public keys of the publishers whose snapshot signatures count
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snapshot_publishers: Vec<String>,
+    /// Publisher signatures a snapshot needs (default: a majority)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_threshold: Option<usize>,
+    /// Finality latency objectives checked by `/health`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality_slo: Option<FinalitySlo>,
+    /// Seconds between refreshes of the analytics replica (off when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica_interval_secs: Option<u64>,
+    /// Size and shape limits for messages from peers
+    #[serde(default)]
+    pub gossip_limits: GossipLimits,
+    /// Stem/fluff propagation of our own transactions
+    #[serde(default)]
+    pub dandelion: DandelionConfig,
+    /// Checks for new signed releases
+    #[serde(default)]
+    pub update: UpdateConfig,
+    /// Retention of the peer audit log
+    #[serde(default)]
+    pub peer_audit: PeerAuditConfig,
+    /// Pruning of old final history
+    #[serde(default)]
+    pub pruning: PruningConfig,
+    /// Inbound limits per address bucket and anchor peers
+    #[serde(default)]
+    pub peer_selection: PeerSelection,
+    /// Stack peers are reached over; `libp2p` needs a build with that feature
+    #[serde(default)]
+    pub network_backend: NetworkBackend,
+    /// Also connect to Rhiza devices in Bluetooth range; needs a build with
+    /// the `bluetooth` feature
+    #[serde(default)]
+    pub bluetooth: bool,
+    /// Keys API requests authenticate with; without any, the API is open to
+    /// whoever can reach it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKey>,
+    /// What the API audit log records
+    #[serde(default)]
+    pub api_audit: ApiAuditConfig,
+    /// Holding gossip for peers that went away, until they return
+    #[serde(default)]
+    pub dtn: DtnConfig,
+    /// How far transaction timestamps may stray from their parents' and
+    /// from this node's clock
+    #[serde(default)]
+    pub timestamps: TimestampRules,
+    /// Voting with peers on which side of a double-spend wins
+    #[serde(default)]
+    pub voting: VotingConfig,
+    /// Background checks of incremental weights against recomputations
+    #[serde(default)]
+    pub weight_audit: WeightAuditConfig,
+    /// Size and number of the event log's files
+    #[serde(default)]
+    pub event_log: EventLogConfig,
+    /// Whether API spends wait for the wallet to be unlocked, and for how long
+    #[serde(default)]
+    pub wallet_session: WalletSessionConfig,
+    /// Puzzle solved when a transaction needs more work than it carries:
+    /// `blake3`, or `argon2` for the memory-hard one
+    #[serde(default)]
+    pub work_puzzle: WorkPuzzle,
+}
+
+fn default_network_id() -> u32 {
+    rhiza_core::MAINNET_ID
+}
+
+fn default_use_default_peers() -> bool {
+    true
+}
+
+fn default_parent_count() -> usize {
+    rhiza_core::PARENT_COUNT
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         NodeConfig {
             name: "rhiza-node".to_string(),
+            network_id: rhiza_core::MAINNET_ID,
             p2p_port: 7470,
             api_port: 7471,
             data_dir: PathBuf::from("~/.rhiza"),
             max_peers: 50,
             enable_mdns: true,
             bootstrap_peers: Vec::new(),
+            use_default_peers: true,
+            tip_selection: TipSelection::default(),
+            parent_count: rhiza_core::PARENT_COUNT,
+            webhooks: Vec::new(),
+            snapshot_publishers: Vec::new(),
+            snapshot_threshold: None,
+            finality_slo: None,
+            replica_interval_secs: None,
+            gossip_limits: GossipLimits::default(),
+            dandelion: DandelionConfig::default(),
+            update: UpdateConfig::default(),
+            peer_audit: PeerAuditConfig::default(),
+            pruning: PruningConfig::default(),
+            peer_selection: PeerSelection::default(),
+            network_backend: NetworkBackend::default(),
+            bluetooth: false,
+            api_keys: Vec::new(),
+            api_audit: ApiAuditConfig::default(),
+            dtn: DtnConfig::default(),
+            timestamps: TimestampRules::default(),
+            voting: VotingConfig::default(),
+            weight_audit: WeightAuditConfig::default(),
+            event_log: EventLogConfig::default(),
+            wallet_session: WalletSessionConfig::default(),
+            work_puzzle: WorkPuzzle::default(),
         }
     }
 }
@@ -41,6 +188,57 @@ impl NodeConfig {
         Ok(config)
     }
 
+    /// Load the config from `path`, or the defaults if it doesn't exist
+    pub fn load_or_default(path: &PathBuf) -> anyhow::Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Peers to dial at startup
+    ///
+    /// Explicit (command-line) peers win, then the configured list, then the
+    /// built-in defaults unless they are disabled.
+    pub fn bootstrap_peers(&self, explicit: &[String]) -> Vec<String> {
+        if !explicit.is_empty() {
+            explicit.to_vec()
+        } else if !self.bootstrap_peers.is_empty() {
+            self.bootstrap_peers.clone()
+        } else if self.use_default_peers {
+            DEFAULT_BOOTSTRAP_PEERS.iter().map(|p| p.to_string()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Trusted snapshot publishers and how many of them must sign
+    pub fn snapshot_trust(&self) -> anyhow::Result<(Vec<PublicKey>, usize)> {
+        if self.snapshot_publishers.is_empty() {
+            anyhow::bail!("No snapshot publishers configured; list their keys under snapshot_publishers in config.json");
+        }
+        let publishers = self
+            .snapshot_publishers
+            .iter()
+            .map(|key| {
+                let bytes: [u8; 32] = hex::decode(key)
+                    .ok()
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| anyhow::anyhow!("Invalid snapshot publisher key {}", key))?;
+                Ok(PublicKey::from_bytes(bytes))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let threshold = self.snapshot_threshold.unwrap_or(publishers.len() / 2 + 1);
+        if threshold == 0 || threshold > publishers.len() {
+            anyhow::bail!(
+                "snapshot_threshold must be between 1 and the number of publishers ({})",
+                publishers.len()
+            );
+        }
+        Ok((publishers, threshold))
+    }
+
     pub fn save(&self, path: &PathBuf) -> anyhow::Result<()> {
         let json = serde_json::to_string_pretty(self)?;
         std::fs::write(path, json)?;
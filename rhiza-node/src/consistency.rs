@@ -0,0 +1,143 @@
+use crate::storage::Storage;
+use rhiza_core::consensus::weight::WeightCalculator;
+use rhiza_core::crypto::Hash;
+use rhiza_core::dag::vertex::Dag;
+use std::collections::HashSet;
+use std::fmt;
+use tracing::warn;
+
+/// What the startup cross-check found and fixed
+#[derive(Debug, Default)]
+pub struct ConsistencyReport {
+    /// Transactions in sled before repair
+    pub persisted: usize,
+    /// Vertices in the in-memory DAG
+    pub in_memory: usize,
+    /// DAG transactions that were missing from sled and have been written
+    pub restored: Vec<Hash>,
+    /// Records that failed to decode or whose key/id didn't match (moved to
+    /// the quarantine tree)
+    pub quarantined: usize,
+    /// Persisted transactions the in-memory DAG doesn't know about
+    pub unknown_persisted: Vec<Hash>,
+    /// Pruned transactions kept in cold storage
+    pub pruned_persisted: usize,
+    /// Tag entries pointing at unknown transactions (removed)
+    pub orphan_tags_removed: usize,
+    /// Vertices whose cumulative weight disagreed with a recomputation
+    /// (rewritten with the recomputed weight)
+    pub weight_mismatches: Vec<WeightMismatch>,
+    /// Whether the depth and timestamp indexes disagreed with the records
+    /// and were rebuilt
+    pub indexes_rebuilt: bool,
+}
+
+/// A vertex whose incremental weight disagrees with a full recomputation
+#[derive(Debug)]
+pub struct WeightMismatch {
+    pub id: Hash,
+    pub stored: u64,
+    pub expected: u64,
+}
+
+impl ConsistencyReport {
+    /// Whether nothing needed attention
+    pub fn is_clean(&self) -> bool {
+        self.restored.is_empty()
+            && self.quarantined == 0
+            && self.unknown_persisted.is_empty()
+            && self.orphan_tags_removed == 0
+            && self.weight_mismatches.is_empty()
+            && !self.indexes_rebuilt
+    }
+}
+
+impl fmt::Display for ConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} persisted ({} pruned) / {} in memory; restored {}, quarantined {} corrupt, \
+             {} unknown on disk, removed {} orphan tags, repaired {} weights{}",
+            self.persisted,
+            self.pruned_persisted,
+            self.in_memory,
+            self.restored.len(),
+            self.quarantined,
+            self.unknown_persisted.len(),
+            self.orphan_tags_removed,
+            self.weight_mismatches.len(),
+            if self.indexes_rebuilt { ", rebuilt indexes" } else { "" }
+        )
+    }
+}
+
+/// Cross-check sled against the in-memory DAG, repairing what can be repaired
+///
+/// The in-memory DAG is authoritative: it only holds validated transactions.
+/// Records that don't decode are quarantined rather than deleted, since a
+/// newer build may still read them.
+pub fn check_and_repair(storage: &Storage, dag: &mut Dag) -> anyhow::Result<ConsistencyReport> {
+    let mut report = ConsistencyReport {
+        in_memory: dag.len(),
+        ..Default::default()
+    };
+
+    // 1. Transaction records: decode, check key/id agreement, compare sets
+    let mut persisted_ids = HashSet::new();
+    for (key, vertex) in storage.scan_vertices()? {
+        report.persisted += 1;
+        match vertex.map(|v| v.transaction) {
+            Some(tx) if key.as_ref() == tx.id.as_bytes() && tx.verify_id() => {
+                if dag.pruned().contains(&tx.id) {
+                    report.pruned_persisted += 1;
+                } else if dag.get(&tx.id).is_none() {
+                    report.unknown_persisted.push(tx.id);
+                }
+                persisted_ids.insert(tx.id);
+            }
+            _ => {
+                warn!("Quarantining undecodable storage record {}", hex::encode(&key));
+                storage.quarantine_record(&key)?;
+                report.quarantined += 1;
+            }
+        }
+    }
+
+    for id in dag.transaction_ids() {
+        if !persisted_ids.contains(&id) {
+            if let Some(vertex) = dag.get(&id) {
+                storage.put_vertex(vertex)?;
+                report.restored.push(id);
+            }
+        }
+    }
+
+    // 2. Tag index: drop entries for transactions we don't have
+    for id in storage.all_tags()?.keys() {
+        if !dag.contains(id) {
+            storage.set_tags(id, &[])?;
+            report.orphan_tags_removed += 1;
+        }
+    }
+
+    // 3. Cumulative weights against a from-scratch recomputation
+    let expected = WeightCalculator::calculate_all_weights(dag);
+    for (id, expected) in expected {
+        let Some(stored) = dag.get(&id).map(|v| v.cumulative_weight).filter(|w| *w != expected) else {
+            continue;
+        };
+        dag.repair_weight(&id, expected);
+        if let Some(vertex) = dag.get(&id) {
+            storage.put_vertex(vertex)?;
+        }
+        report.weight_mismatches.push(WeightMismatch { id, stored, expected });
+    }
+
+    // 4. Depth and timestamp indexes against the records, now they're settled
+    if !storage.indexes_match_records()? {
+        storage.rebuild_indexes()?;
+        report.indexes_rebuilt = true;
+    }
+
+    Ok(report)
+}
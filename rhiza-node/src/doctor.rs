@@ -0,0 +1,524 @@
+use crate::config::NodeConfig;
+use crate::identity;
+use crate::keyfile;
+use crate::storage::Storage;
+use anyhow::Result;
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::wallet::keystore::KeyStore;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Stored transactions may be dated this far ahead of the local clock
+/// before the clock is suspected of running behind
+const MAX_CLOCK_SKEW_MS: u64 = 5 * 60 * 1_000;
+
+/// 2024-01-01; a clock reading earlier than this was never set
+const EARLIEST_SANE_MS: u64 = 1_704_067_200_000;
+
+/// How long each bootstrap peer gets to resolve
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The settings `rhiza-node start` would run with
+pub struct DoctorOptions {
+    pub port: u16,
+    pub api_bind: IpAddr,
+    pub api_port: Option<u16>,
+    pub bootstrap_peers: Vec<String>,
+    pub no_default_peers: bool,
+    pub network: Option<u32>,
+    pub password_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Ok,
+    Warning,
+    Failure,
+}
+
+/// The result of one check, with what to do about it
+struct Finding {
+    severity: Severity,
+    check: &'static str,
+    message: String,
+    fix: Option<String>,
+}
+
+#[derive(Default)]
+struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn ok(&mut self, check: &'static str, message: impl Into<String>) {
+        self.push(Severity::Ok, check, message.into(), None);
+    }
+
+    fn warn(&mut self, check: &'static str, message: impl Into<String>, fix: impl Into<String>) {
+        self.push(Severity::Warning, check, message.into(), Some(fix.into()));
+    }
+
+    fn fail(&mut self, check: &'static str, message: impl Into<String>, fix: impl Into<String>) {
+        self.push(Severity::Failure, check, message.into(), Some(fix.into()));
+    }
+
+    fn push(&mut self, severity: Severity, check: &'static str, message: String, fix: Option<String>) {
+        self.findings.push(Finding {
+            severity,
+            check,
+            message,
+            fix,
+        });
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+
+    fn print(&self) {
+        for finding in &self.findings {
+            let icon = match finding.severity {
+                Severity::Ok => "✅",
+                Severity::Warning => "⚠️ ",
+                Severity::Failure => "❌",
+            };
+            println!("{} {}: {}", icon, finding.check, finding.message);
+            if let Some(fix) = &finding.fix {
+                println!("   → {}", fix);
+            }
+        }
+    }
+}
+
+/// Check that the node could start with these settings and print a fix
+/// for everything that would stop it or hurt it once running
+///
+/// Nothing is changed on disk. Fails when any check fails; warnings alone
+/// don't.
+pub async fn run(data_path: &Path, options: DoctorOptions) -> Result<()> {
+    println!("🩺 Rhiza Node Doctor");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    let mut report = Report::default();
+
+    let mut config = check_config(&mut report, data_path);
+    let network_id = options.network.unwrap_or(config.network_id);
+    check_ports(&mut report, &options);
+    check_data_dir(&mut report, data_path);
+    let password_file = options.password_file.as_deref();
+    let wallet_key = check_keystore(&mut report, &data_path.join("wallet.json"), password_file);
+    // Snapshots are signed by the node identity, which may be kept apart from the wallet
+    let identity_path = keyfile::identity_path(data_path);
+    let public_key = match identity_path.ends_with(keyfile::IDENTITY_FILE) {
+        true => check_keystore(&mut report, &identity_path, password_file),
+        false => wallet_key,
+    };
+    let storage = check_storage(&mut report, data_path, public_key.as_ref(), network_id);
+    check_clock(&mut report, storage.as_ref());
+    if options.no_default_peers {
+        config.use_default_peers = false;
+    }
+    check_bootstrap_peers(&mut report, &config.bootstrap_peers(&options.bootstrap_peers)).await;
+
+    report.print();
+    let (failures, warnings) = (report.count(Severity::Failure), report.count(Severity::Warning));
+    println!();
+    if failures > 0 {
+        anyhow::bail!("{} failed checks, {} warnings", failures, warnings);
+    }
+    if warnings > 0 {
+        println!("🌿 The node can start, with {} warnings", warnings);
+    } else {
+        println!("🌿 Everything looks healthy");
+    }
+    Ok(())
+}
+
+/// Parse config.json and check the settings that only fail once used
+fn check_config(report: &mut Report, data_path: &Path) -> NodeConfig {
+    let path = data_path.join("config.json");
+    if !path.exists() {
+        report.warn(
+            "Config",
+            format!("{} not found, running on the defaults", path.display()),
+            "Run 'rhiza-node init' to write an editable config.json",
+        );
+        return NodeConfig::default();
+    }
+    let config = match NodeConfig::load(&path) {
+        Ok(config) => config,
+        Err(e) => {
+            report.fail(
+                "Config",
+                format!("{} doesn't parse: {}", path.display(), e),
+                "Fix the JSON at the reported line, or move config.json aside to start on the defaults",
+            );
+            return NodeConfig::default();
+        }
+    };
+    report.ok("Config", format!("{} parsed (network {})", path.display(), config.network_id));
+
+    if !config.snapshot_publishers.is_empty() {
+        if let Err(e) = config.snapshot_trust() {
+            report.fail(
+                "Config",
+                e.to_string(),
+                "List 64-character hex keys under snapshot_publishers and a reachable snapshot_threshold",
+            );
+        }
+    }
+    if let Err(e) = config.update.trusted_keys() {
+        report.fail(
+            "Config",
+            format!("update.release_keys: {}", e),
+            "Use 64-character hex keys in update.release_keys",
+        );
+    }
+    if config.peer_selection.max_inbound == 0 || config.peer_selection.max_inbound_per_bucket == 0 {
+        report.warn(
+            "Config",
+            "peer_selection accepts no inbound peers from the internet",
+            "Raise peer_selection.max_inbound and max_inbound_per_bucket above 0 unless this is intended",
+        );
+    }
+    for (i, key) in config.api_keys.iter().enumerate() {
+        if blake3::Hash::from_hex(&key.token_hash).is_err() {
+            report.fail(
+                "Config",
+                format!("api_keys: {} has no valid token_hash", key.id),
+                "Remove it with 'rhiza-node api-key revoke' and create it again with 'api-key add'",
+            );
+        }
+        if config.api_keys[..i].iter().any(|other| other.id == key.id) {
+            report.fail(
+                "Config",
+                format!("api_keys: {} is listed more than once", key.id),
+                "Give every API key its own id, so the audit log can tell them apart",
+            );
+        }
+    }
+    if config.pruning.enabled && config.pruning.keep_depth == 0 {
+        report.warn(
+            "Config",
+            "pruning.keep_depth is 0, so every final transaction is pruned",
+            "Keep some history in memory, e.g. pruning.keep_depth = 10000",
+        );
+    }
+    config
+}
+
+/// Ports must not collide and must be free to bind
+fn check_ports(report: &mut Report, options: &DoctorOptions) {
+    let Some(api_port) = options.api_port.or(options.port.checked_add(1)) else {
+        report.fail(
+            "Ports",
+            format!("P2P port {} leaves no room for the API on the next port", options.port),
+            "Pass --api-port, or pick a lower --port",
+        );
+        return;
+    };
+    if options.port == 0 {
+        report.fail("Ports", "P2P port 0 picks a random port peers can't find", "Pass a fixed --port");
+        return;
+    }
+    if api_port == options.port {
+        report.fail(
+            "Ports",
+            format!("P2P and API both want port {}", api_port),
+            "Give the API its own port with --api-port",
+        );
+        return;
+    }
+
+    let mut free = true;
+    let p2p: SocketAddr = ([0, 0, 0, 0], options.port).into();
+    let api = SocketAddr::new(options.api_bind, api_port);
+    for (what, addr) in [("P2P", p2p), ("API", api)] {
+        if let Err(e) = TcpListener::bind(addr) {
+            free = false;
+            let fix = match e.kind() {
+                std::io::ErrorKind::AddrInUse => format!(
+                    "Another process holds {}; stop it (is a node already running?) or choose another port",
+                    addr
+                ),
+                std::io::ErrorKind::PermissionDenied => {
+                    "Use a port above 1023, or grant the binary CAP_NET_BIND_SERVICE".to_string()
+                }
+                std::io::ErrorKind::AddrNotAvailable => {
+                    format!("{} isn't an address of this machine; fix --api-bind", addr.ip())
+                }
+                _ => "Check the port and bind address".to_string(),
+            };
+            report.fail("Ports", format!("{} can't listen on {}: {}", what, addr, e), fix);
+        }
+    }
+    if free {
+        report.ok("Ports", format!("P2P {} and API {} are free", p2p, api));
+    }
+}
+
+/// The data directory must exist and take writes
+fn check_data_dir(report: &mut Report, data_path: &Path) {
+    if !data_path.is_dir() {
+        report.fail(
+            "Data dir",
+            format!("{} doesn't exist", data_path.display()),
+            "Run 'rhiza-node init', or point --data-dir at an initialized directory",
+        );
+        return;
+    }
+    let probe = data_path.join(".doctor-write-test");
+    match std::fs::write(&probe, b"ok").and_then(|()| std::fs::remove_file(&probe)) {
+        Ok(()) => report.ok("Data dir", format!("{} is writable", data_path.display())),
+        Err(e) => report.fail(
+            "Data dir",
+            format!("{} isn't writable: {}", data_path.display(), e),
+            "Make it writable by the user running the node (chown/chmod), or choose another --data-dir",
+        ),
+    }
+}
+
+/// The keystore at `path` must load, and unlock when a password file is given
+fn check_keystore(report: &mut Report, path: &Path, password_file: Option<&Path>) -> Option<PublicKey> {
+    if !path.exists() {
+        report.fail(
+            "Keystore",
+            format!("{} not found", path.display()),
+            "Run 'rhiza-node init', or restore it from a backup",
+        );
+        return None;
+    }
+    let keystore = match KeyStore::load(path) {
+        Ok(keystore) => keystore,
+        Err(e) => {
+            report.fail(
+                "Keystore",
+                format!("{} can't be read: {}", path.display(), e),
+                "Check the file's owner and permissions, or restore it from a backup",
+            );
+            return None;
+        }
+    };
+    let public_key = match keystore.public_key() {
+        Ok(key) => key,
+        Err(e) => {
+            report.fail(
+                "Keystore",
+                format!("{} holds an invalid public key: {}", path.display(), e),
+                "Restore it from a backup",
+            );
+            return None;
+        }
+    };
+
+    if !keystore.is_encrypted() {
+        report.warn(
+            "Keystore",
+            format!("{} is not encrypted", path.display()),
+            "Run 'rhiza-node start' once and choose a password when it offers to encrypt the wallet",
+        );
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(meta) = std::fs::metadata(path) {
+            if meta.permissions().mode() & 0o077 != 0 {
+                report.warn(
+                    "Keystore",
+                    format!("{} can be read by other users", path.display()),
+                    format!("chmod 600 {}", path.display()),
+                );
+            }
+        }
+    }
+
+    match password_file {
+        Some(password_file) => match keyfile::unlock(path, Some(password_file)) {
+            Ok(_) => report.ok("Keystore", format!("{} unlocks with the password file", path.display())),
+            Err(e) => report.fail(
+                "Keystore",
+                format!("{} doesn't unlock with {}: {}", path.display(), password_file.display(), e),
+                "Put the wallet password, and nothing else, in the password file",
+            ),
+        },
+        None => report.ok("Keystore", format!("{} is readable", path.display())),
+    }
+    Some(public_key)
+}
+
+/// Decode every record, check parents are present and the snapshot of
+/// pruned history still verifies
+///
+/// Returns the opened storage for the clock check.
+fn check_storage(
+    report: &mut Report,
+    data_path: &Path,
+    public_key: Option<&PublicKey>,
+    network_id: u32,
+) -> Option<Storage> {
+    let db_path = data_path.join("db");
+    if !db_path.exists() {
+        report.ok("Storage", "no database yet; it is created on the first start");
+        return None;
+    }
+    let storage = match Storage::open(&db_path) {
+        Ok(storage) => storage,
+        Err(e) => {
+            report.warn(
+                "Storage",
+                format!("the database can't be opened: {:#}", e),
+                "Stop the node and run the doctor again; if it still fails, restore db/ from a backup",
+            );
+            return None;
+        }
+    };
+
+    let snapshot = match storage.get_balance_snapshot() {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            report.fail(
+                "Storage",
+                format!("the balance snapshot of pruned history can't be read: {}", e),
+                "Restore db/ from a backup, or resync into a fresh data directory",
+            );
+            return Some(storage);
+        }
+    };
+    if let (Some(snapshot), Some(public_key)) = (&snapshot, public_key) {
+        let rotations = storage.get_identity_rotations().unwrap_or_default();
+        if !snapshot.verify(&identity::key_history(public_key, &rotations)) {
+            report.fail(
+                "Storage",
+                "the balance snapshot of pruned history isn't signed by this node's key",
+                "The node won't start with it; restore db/ together with its wallet.json, \
+                 or resync into a fresh data directory",
+            );
+        }
+    }
+
+    let records = match storage.scan_vertices() {
+        Ok(records) => records,
+        Err(e) => {
+            report.fail(
+                "Storage",
+                format!("the database can't be scanned: {}", e),
+                "Restore db/ from a backup, or resync into a fresh data directory",
+            );
+            return Some(storage);
+        }
+    };
+    let mut corrupt = 0;
+    let mut ids = HashSet::new();
+    let mut parents = HashSet::new();
+    let mut stored_network = snapshot.as_ref().and_then(|s| s.network_id);
+    for (key, vertex) in &records {
+        match vertex {
+            Some(vertex) if key.as_ref() == vertex.id().as_bytes() && vertex.transaction.verify_id() => {
+                ids.insert(vertex.id());
+                parents.extend(vertex.transaction.data.parents.iter().copied());
+                if vertex.depth == 0 {
+                    stored_network.get_or_insert(vertex.transaction.data.network_id);
+                }
+            }
+            _ => corrupt += 1,
+        }
+    }
+    let pruned: HashSet<Hash> = snapshot.iter().flat_map(|s| s.pruned.iter().copied()).collect();
+    let missing = parents
+        .iter()
+        .filter(|p| **p != Hash::zero() && !ids.contains(*p) && !pruned.contains(*p))
+        .count();
+
+    if let Some(stored) = stored_network.filter(|stored| *stored != network_id) {
+        report.fail(
+            "Storage",
+            format!("the stored DAG belongs to network {}, but the node is set to join {}", stored, network_id),
+            format!("Start with --network {}, or use another data directory for network {}", stored, network_id),
+        );
+    }
+    if corrupt > 0 {
+        report.warn(
+            "Storage",
+            format!("{} of {} records are corrupt", corrupt, records.len()),
+            "Start the node: its consistency check quarantines them and peers send the transactions again",
+        );
+    }
+    if missing > 0 {
+        report.warn(
+            "Storage",
+            format!("{} parents referenced by stored transactions are missing", missing),
+            "Start the node with peers: transactions on missing parents are skipped until sync fills the gap",
+        );
+    }
+    if corrupt == 0 && missing == 0 {
+        report.ok(
+            "Storage",
+            format!("{} records decode and link up; {} transactions pruned", records.len(), pruned.len()),
+        );
+    }
+    Some(storage)
+}
+
+/// The clock must have been set, and not lag behind stored transactions
+fn check_clock(report: &mut Report, storage: Option<&Storage>) {
+    let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    if now < EARLIEST_SANE_MS {
+        report.fail(
+            "Clock",
+            format!("the system clock reads {}", chrono::Utc::now().to_rfc3339()),
+            "Set the clock, e.g. enable NTP with 'timedatectl set-ntp true'",
+        );
+        return;
+    }
+    let ahead = storage.map_or(0, |storage| {
+        storage
+            .vertices_by_time(now.saturating_add(MAX_CLOCK_SKEW_MS)..)
+            .filter(|v| v.is_ok())
+            .count()
+    });
+    if ahead > 0 {
+        report.warn(
+            "Clock",
+            format!("{} stored transactions are dated more than 5 minutes in the future", ahead),
+            "This clock is probably behind; enable NTP, e.g. 'timedatectl set-ntp true'",
+        );
+    } else {
+        report.ok("Clock", format!("the system clock reads {}", chrono::Utc::now().to_rfc3339()));
+    }
+}
+
+/// Every bootstrap peer must resolve to at least one address
+async fn check_bootstrap_peers(report: &mut Report, peers: &[String]) {
+    if peers.is_empty() {
+        report.warn(
+            "Bootstrap",
+            "no bootstrap peers; the node starts a network of its own",
+            "Pass --bootstrap host:port or list peers under bootstrap_peers, unless this is a new network",
+        );
+        return;
+    }
+    let mut unresolved = 0;
+    for peer in peers {
+        let resolved = tokio::time::timeout(RESOLVE_TIMEOUT, tokio::net::lookup_host(peer.as_str())).await;
+        let error = match resolved {
+            Ok(Ok(mut addrs)) => {
+                if addrs.next().is_some() {
+                    continue;
+                }
+                "no addresses".to_string()
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "timed out".to_string(),
+        };
+        unresolved += 1;
+        report.warn(
+            "Bootstrap",
+            format!("{} doesn't resolve: {}", peer, error),
+            "Check the host:port spelling and DNS, or remove it from bootstrap_peers",
+        );
+    }
+    if unresolved < peers.len() {
+        report.ok("Bootstrap", format!("{} of {} peers resolve", peers.len() - unresolved, peers.len()));
+    }
+}
@@ -0,0 +1,310 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// File name of the event log inside the data directory; rotated files get
+/// `.1`, `.2`, ... appended, `.1` the newest
+pub const EVENT_LOG_FILE: &str = "events.jsonl";
+
+/// Size and number of the event log's files (`event_log` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventLogConfig {
+    pub enabled: bool,
+    /// A file is rotated once it would grow past this
+    pub max_file_bytes: u64,
+    /// Files kept, the one being written included; the oldest are deleted
+    pub max_files: usize,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        EventLogConfig {
+            enabled: true,
+            max_file_bytes: 8 * 1024 * 1024,
+            max_files: 4,
+        }
+    }
+}
+
+/// Something significant that happened to the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Incident {
+    /// The node started with this version and network
+    NodeStarted { version: String, network_id: u32 },
+    /// The startup storage check found problems and repaired them
+    StorageRepaired { report: String },
+    /// Two or more transactions spending the same funds were found, and a
+    /// vote on them opened
+    ConflictDetected { conflict: String, members: Vec<String> },
+    /// Voting switched which side of a conflict the node accepts, undoing
+    /// what the other side had credited
+    ConflictSwitched { conflict: String, from: String, to: String },
+    /// A background check found a cumulative weight that doesn't match a
+    /// recomputation
+    WeightDiverged { id: String, incremental: u64, recomputed: u64 },
+    PeerBanned {
+        peer: String,
+        address: Option<String>,
+        reason: String,
+        until_ms: u64,
+        count: u32,
+    },
+    SnapshotCreated { file: String, content_hash: String, transactions: usize },
+    SnapshotFailed { error: String },
+}
+
+impl Incident {
+    /// Name of the kind, as in the `kind` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Incident::NodeStarted { .. } => "node_started",
+            Incident::StorageRepaired { .. } => "storage_repaired",
+            Incident::ConflictDetected { .. } => "conflict_detected",
+            Incident::ConflictSwitched { .. } => "conflict_switched",
+            Incident::WeightDiverged { .. } => "weight_diverged",
+            Incident::PeerBanned { .. } => "peer_banned",
+            Incident::SnapshotCreated { .. } => "snapshot_created",
+            Incident::SnapshotFailed { .. } => "snapshot_failed",
+        }
+    }
+
+    /// One line describing it
+    pub fn summary(&self) -> String {
+        match self {
+            Incident::NodeStarted { version, network_id } => {
+                format!("rhiza-node {} started on network {}", version, network_id)
+            }
+            Incident::StorageRepaired { report } => report.clone(),
+            Incident::ConflictDetected { conflict, members } => {
+                format!("{} spends in conflict {}", members.len(), conflict)
+            }
+            Incident::ConflictSwitched { conflict, from, to } => {
+                format!("conflict {} now accepts {} instead of {}", conflict, to, from)
+            }
+            Incident::WeightDiverged { id, incremental, recomputed } => {
+                format!("weight of {} is {}, recomputed {}", id, incremental, recomputed)
+            }
+            Incident::PeerBanned { peer, address, reason, until_ms, count } => format!(
+                "{}{} banned for {} until {} (ban #{})",
+                peer,
+                address.as_ref().map(|a| format!(" ({})", a)).unwrap_or_default(),
+                reason,
+                format_ms(*until_ms),
+                count
+            ),
+            Incident::SnapshotCreated { file, transactions, .. } => {
+                format!("{} transactions written to {}", transactions, file)
+            }
+            Incident::SnapshotFailed { error } => error.clone(),
+        }
+    }
+}
+
+/// An incident and when it was recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogEntry {
+    /// Unix ms
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub incident: Incident,
+}
+
+struct LogFile {
+    file: File,
+    len: u64,
+}
+
+/// Rotating file of the incidents worth reconstructing after the fact:
+/// conflicts and how voting settled them, failed invariant checks, bans and
+/// snapshots
+///
+/// Kept apart from the tracing output, which is mostly routine and goes
+/// wherever the operator sends it. Entries are JSON lines in
+/// [`EVENT_LOG_FILE`]; `rhiza-node events show` reads them back.
+#[derive(Clone)]
+pub struct EventLog {
+    path: PathBuf,
+    config: EventLogConfig,
+    file: Option<Arc<Mutex<LogFile>>>,
+}
+
+impl EventLog {
+    /// A log that records nothing
+    pub fn disabled() -> Self {
+        EventLog {
+            path: PathBuf::new(),
+            config: EventLogConfig {
+                enabled: false,
+                ..EventLogConfig::default()
+            },
+            file: None,
+        }
+    }
+
+    /// Open the log in `data_path`, appending to what it holds
+    pub fn open(data_path: &Path, config: EventLogConfig) -> anyhow::Result<Self> {
+        let path = data_path.join(EVENT_LOG_FILE);
+        if !config.enabled {
+            return Ok(EventLog { path, config, file: None });
+        }
+        let file = open_append(&path)?;
+        let len = file.metadata().map_or(0, |m| m.len());
+        Ok(EventLog {
+            path,
+            config,
+            file: Some(Arc::new(Mutex::new(LogFile { file, len }))),
+        })
+    }
+
+    /// Append an incident, rotating first if the file would grow too big;
+    /// failures are logged, never passed on
+    pub fn record(&self, incident: Incident) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let entry = EventLogEntry {
+            at_ms: chrono::Utc::now().timestamp_millis() as u64,
+            incident,
+        };
+        let mut file = file.lock().unwrap();
+        let result = serde_json::to_vec(&entry).map_err(anyhow::Error::from).and_then(|mut line| {
+            line.push(b'\n');
+            if file.len > 0 && file.len + line.len() as u64 > self.config.max_file_bytes {
+                self.rotate()?;
+                *file = LogFile { file: open_append(&self.path)?, len: 0 };
+            }
+            file.file.write_all(&line)?;
+            file.len += line.len() as u64;
+            Ok(())
+        });
+        if let Err(e) = result {
+            warn!("Failed to write to the event log: {:#}", e);
+        }
+    }
+
+    /// Shift every file one number up, dropping those past `max_files`
+    fn rotate(&self) -> anyhow::Result<()> {
+        let keep = self.config.max_files.max(1);
+        if keep == 1 {
+            std::fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(rotated(&self.path, keep - 1));
+        for n in (1..keep - 1).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                std::fs::rename(&from, rotated(&self.path, n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated(&self.path, 1))?;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> anyhow::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {}", path.display()))
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Every entry in the log files of `data_path`, oldest first
+///
+/// Reads files as they are, so it works while the node runs; a line cut
+/// short by a crash is skipped.
+pub fn read(data_path: &Path) -> anyhow::Result<Vec<EventLogEntry>> {
+    let path = data_path.join(EVENT_LOG_FILE);
+    let mut files = vec![path.clone()];
+    files.extend((1..).map(|n| rotated(&path, n)).take_while(|p| p.exists()));
+
+    let mut entries = Vec::new();
+    for file in files.iter().rev() {
+        let reader = match File::open(file) {
+            Ok(reader) => BufReader::new(reader),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Could not read {}", file.display())),
+        };
+        for line in reader.lines() {
+            match serde_json::from_str(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipping an unreadable line in {}: {}", file.display(), e),
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// `--since` as unix ms: an age like `30m`, `12h` or `7d`, a date
+/// (2026-01-31) or an RFC 3339 time
+pub fn parse_since(since: &str, now_ms: u64) -> anyhow::Result<u64> {
+    let unit_ms = match since.chars().last() {
+        Some('s') => Some(1_000),
+        Some('m') => Some(60_000),
+        Some('h') => Some(3_600_000),
+        Some('d') => Some(86_400_000),
+        _ => None,
+    };
+    if let Some(unit_ms) = unit_ms {
+        if let Ok(count) = since[..since.len() - 1].parse::<u64>() {
+            return Ok(now_ms.saturating_sub(count.saturating_mul(unit_ms)));
+        }
+    }
+    let time = match chrono::DateTime::parse_from_rfc3339(since) {
+        Ok(time) => time.to_utc(),
+        Err(_) => chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d")
+            .with_context(|| format!("Invalid --since {}; use an age (12h), date or RFC 3339 time", since))?
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc(),
+    };
+    Ok(time.timestamp_millis().max(0) as u64)
+}
+
+/// Print the entries at or after `since_ms`, optionally of one kind only,
+/// at most the newest `limit`
+pub fn show(
+    data_path: &Path,
+    since_ms: u64,
+    kind: Option<&str>,
+    limit: Option<usize>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut entries: Vec<EventLogEntry> = read(data_path)?
+        .into_iter()
+        .filter(|e| e.at_ms >= since_ms && kind.is_none_or(|kind| e.incident.kind() == kind))
+        .collect();
+    if let Some(limit) = limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+    if entries.is_empty() && !json {
+        println!("No events since {}", format_ms(since_ms));
+    }
+    for entry in &entries {
+        if json {
+            println!("{}", serde_json::to_string(entry)?);
+        } else {
+            let incident = &entry.incident;
+            println!("{}  {:<18} {}", format_ms(entry.at_ms), incident.kind(), incident.summary());
+        }
+    }
+    Ok(())
+}
+
+fn format_ms(ms: u64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ms as i64) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => format!("{} ms", ms),
+    }
+}
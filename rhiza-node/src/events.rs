@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Number of events retained for cursor-based delivery
+const EVENT_BUFFER_CAPACITY: usize = 1024;
+
+/// Events published by the node to API subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeEvent {
+    /// A transaction was inserted into the local DAG
+    TxReceived {
+        id: String,
+        sender: String,
+        recipient: String,
+        amount: u64,
+        /// Recipients a batch pays
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<String>,
+    },
+    /// A transaction reached finality
+    TxFinalized {
+        id: String,
+        sender: String,
+        recipient: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<String>,
+    },
+    /// The tip set changed
+    TipChanged { tips: Vec<String> },
+    /// An authenticated peer connection was established
+    PeerConnected { peer_id: String, address: String },
+    /// A signed release newer than this node was published
+    UpdateAvailable { version: String, critical: bool },
+}
+
+impl NodeEvent {
+    /// Event name, as used for the `type` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeEvent::TxReceived { .. } => "tx_received",
+            NodeEvent::TxFinalized { .. } => "tx_finalized",
+            NodeEvent::TipChanged { .. } => "tip_changed",
+            NodeEvent::PeerConnected { .. } => "peer_connected",
+            NodeEvent::UpdateAvailable { .. } => "update_available",
+        }
+    }
+
+    /// Hex public keys of the accounts a transaction event involves
+    pub fn accounts(&self) -> Option<Vec<&str>> {
+        match self {
+            NodeEvent::TxReceived { sender, recipient, outputs, .. }
+            | NodeEvent::TxFinalized { sender, recipient, outputs, .. } => {
+                Some([sender, recipient].into_iter().chain(outputs).map(String::as_str).collect())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An event tagged with its position in the stream
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRecord {
+    /// Monotonic cursor; pass it back to receive only newer events
+    pub cursor: u64,
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: NodeEvent,
+}
+
+/// In-process event bus shared by every event delivery endpoint
+pub struct EventBus {
+    inner: Mutex<EventBuffer>,
+    notify: Notify,
+}
+
+struct EventBuffer {
+    records: VecDeque<EventRecord>,
+    next_cursor: u64,
+}
+
+/// A batch of events returned to a poller
+#[derive(Debug, Serialize)]
+pub struct EventBatch {
+    pub events: Vec<EventRecord>,
+    /// Cursor to pass on the next poll
+    pub next_cursor: u64,
+    /// True if events between the requested cursor and the first returned
+    /// event were dropped from the buffer
+    pub truncated: bool,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus {
+            inner: Mutex::new(EventBuffer {
+                records: VecDeque::with_capacity(EVENT_BUFFER_CAPACITY),
+                next_cursor: 1,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Publish an event to all subscribers
+    pub fn publish(&self, event: NodeEvent) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            let record = EventRecord {
+                cursor: inner.next_cursor,
+                timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                event,
+            };
+            inner.next_cursor += 1;
+            if inner.records.len() == EVENT_BUFFER_CAPACITY {
+                inner.records.pop_front();
+            }
+            inner.records.push_back(record);
+        }
+        self.notify.notify_waiters();
+    }
+
+    /// Cursor of the most recently published event (0 if none yet)
+    pub fn latest_cursor(&self) -> u64 {
+        self.inner.lock().unwrap().next_cursor - 1
+    }
+
+    /// Events with a cursor strictly greater than `cursor`
+    pub fn since(&self, cursor: u64) -> EventBatch {
+        let inner = self.inner.lock().unwrap();
+        let events: Vec<EventRecord> = inner
+            .records
+            .iter()
+            .filter(|r| r.cursor > cursor)
+            .cloned()
+            .collect();
+        let truncated = inner
+            .records
+            .front()
+            .is_some_and(|first| first.cursor > cursor + 1);
+        EventBatch {
+            events,
+            next_cursor: inner.next_cursor - 1,
+            truncated,
+        }
+    }
+
+    /// Wait up to `max_wait` for events newer than `cursor`
+    pub async fn poll(&self, cursor: u64, max_wait: Duration) -> EventBatch {
+        let deadline = tokio::time::Instant::now() + max_wait;
+        loop {
+            // Register interest before checking so a publish in between isn't lost
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let batch = self.since(cursor);
+            if !batch.events.is_empty() {
+                return batch;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return batch;
+            }
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
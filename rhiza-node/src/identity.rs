@@ -0,0 +1,78 @@
+use crate::keyfile;
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use rhiza_core::consensus::relay::{RelayTracker, DEFAULT_HOT_RELAYERS_PER_SHARD};
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::crypto::PublicKey;
+use rhiza_core::network::IdentityRotation;
+use rhiza_core::wallet::address::Address;
+use std::path::Path;
+
+/// Replace the node's identity key with a fresh one
+///
+/// The old key cross-signs the new one; the rotation is stored and handed
+/// to every peer on the next start so they carry the relay count over. The
+/// old keystore is kept next to the new one, since its funds don't move.
+/// Must run while the node is stopped, as it writes to the database.
+pub fn rotate(data_path: &Path, password_file: Option<&Path>, discard_unclaimed: bool) -> Result<()> {
+    let key_path = keyfile::identity_path(data_path);
+    if !key_path.exists() {
+        anyhow::bail!("Node not initialized. Run 'rhiza-node init' first.");
+    }
+    let storage = Storage::open(&data_path.join("db"))
+        .context("Could not open the node database; stop the node before rotating its identity")?;
+
+    // Relay proofs name the key that relayed, so the new key can't claim them
+    let pending = storage.get_pending_relays()?;
+    if !pending.is_empty() && !discard_unclaimed {
+        anyhow::bail!(
+            "{} relay rewards are still unclaimed by the current key; claim them first \
+             (POST /relay-reward) or pass --discard-unclaimed",
+            pending.len()
+        );
+    }
+
+    let old = keyfile::unlock(&key_path, password_file)?;
+    let new = KeyPair::generate();
+    let rotation = IdentityRotation::new(&old, &new);
+
+    // Keep the old wallet: it still holds the old key's funds
+    let stem = key_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("wallet");
+    let backup_path = data_path.join(format!("{}-{}.json", stem, &old.public_key.to_string()[..16]));
+    std::fs::copy(&key_path, &backup_path)
+        .with_context(|| format!("Could not back up the old wallet to {}", backup_path.display()))?;
+
+    let mut tracker = RelayTracker::with_store(
+        Box::new(storage.relay_count_store()),
+        DEFAULT_HOT_RELAYERS_PER_SHARD,
+        storage.get_relay_totals()?,
+    );
+    tracker.transfer_count(&old.public_key, &new.public_key);
+    // Unclaimed proofs were either absent or discarded above
+    storage.put_relay_state(&tracker.totals(), &[])?;
+    let mut rotations = storage.get_identity_rotations()?;
+    rotations.push(rotation);
+    storage.put_identity_rotations(&rotations)?;
+
+    println!("Choose the password for the new identity's wallet.");
+    keyfile::create(&key_path, &new, password_file)?;
+
+    println!("🔁 Node identity rotated");
+    println!("🔑 New address: {}", Address::from_public_key(&new.public_key));
+    println!("📁 Old wallet kept at {}", backup_path.display());
+    println!("⚠️  Funds stay with the old key; move them to the new address, then");
+    println!("   retire the old wallet file. Peers learn of the rotation on the next start.");
+    Ok(())
+}
+
+/// `current` and every key it was rotated from, newest first
+pub fn key_history(current: &PublicKey, rotations: &[IdentityRotation]) -> Vec<PublicKey> {
+    let mut keys = vec![current.clone()];
+    while let Some(rotation) = rotations
+        .iter()
+        .find(|r| keys.last() == Some(&r.new_key) && !keys.contains(&r.old_key))
+    {
+        keys.push(rotation.old_key.clone());
+    }
+    keys
+}
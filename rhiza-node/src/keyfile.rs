@@ -0,0 +1,45 @@
+use anyhow::Result;
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::wallet::keyfile;
+use rhiza_core::wallet::keystore::KeyStore;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+pub use rhiza_core::wallet::keyfile::create;
+
+/// Keystore of the node identity when it is kept apart from the wallet
+pub const IDENTITY_FILE: &str = "identity.json";
+
+/// Keystore of the node identity: its own file if the node has one,
+/// otherwise the wallet, which then doubles as the identity
+pub fn identity_path(data_path: &Path) -> PathBuf {
+    let path = data_path.join(IDENTITY_FILE);
+    if path.exists() {
+        path
+    } else {
+        data_path.join("wallet.json")
+    }
+}
+
+/// Load and unlock the node wallet
+///
+/// A legacy plaintext wallet is encrypted in place when a password can be
+/// had (a password file or a terminal); otherwise it is used as is, with a
+/// warning.
+pub fn unlock(path: &Path, password_file: Option<&Path>) -> Result<KeyPair> {
+    let keystore = KeyStore::load(path)?;
+    if keystore.is_encrypted() {
+        return Ok(keyfile::unlock(&keystore, password_file)?);
+    }
+
+    warn!("Wallet {} stores its secret key unencrypted", path.display());
+    if password_file.is_none() && !std::io::stdin().is_terminal() {
+        warn!("Pass --password-file to encrypt it on the next start");
+        return Ok(keystore.to_keypair()?);
+    }
+
+    let keypair = keyfile::encrypt(path, keystore, password_file)?;
+    info!("Wallet encrypted with the new password");
+    Ok(keypair)
+}
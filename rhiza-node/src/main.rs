@@ -1,19 +1,92 @@
 use anyhow::Result;
+use clap::builder::BoolishValueParser;
 use clap::{Parser, Subcommand};
-use rhiza_core::consensus::relay::RelayTracker;
+use rhiza_core::consensus::relay::{
+    RelayProof, RelayTracker, DEFAULT_HOT_RELAYERS_PER_SHARD, MAX_RELAY_PROOFS_PER_CLAIM,
+};
+use rhiza_core::consensus::{prove_selection, ConflictVoting, TipSelection, TipSelector};
 use rhiza_core::crypto::keys::KeyPair;
-use rhiza_core::dag::transaction::Transaction;
-use rhiza_core::dag::validator::TransactionValidator;
+use rhiza_core::crypto::{Hash, VrfProof};
+use rhiza_core::dag::channels::{ChannelPayment, ChannelStatus, SignedChannelState};
+use rhiza_core::dag::transaction::{BatchOutput, Transaction, TransactionData, TransactionType};
+use rhiza_core::dag::validation_cache::ValidationCache;
+use rhiza_core::dag::validator::ValidationError;
+use rhiza_core::dag::WorkPuzzle;
 use rhiza_core::dag::vertex::{Dag, DagVertex};
-use rhiza_core::network::mesh::MeshConfig;
+use rhiza_core::network::dandelion::{Dandelion, Route};
+use rhiza_core::network::gossip::{GossipLimits, GossipMessage};
+use rhiza_core::network::mesh::{MeshConfig, NetworkBackend, TransportType};
+use rhiza_core::network::peer::PeerId;
+use rhiza_core::network::reputation::{Ban, Behavior, ReputationTracker, FULL_RELAY_CREDIT};
+use rhiza_core::network::{GossipIntake, IdentityRotation, Intake};
+use rhiza_core::network::sync::{RangeSync, DEFAULT_SYNC_PAGE_LIMIT};
 use rhiza_core::wallet::address::Address;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tracing::info;
+use std::sync::Arc;
+use tokio::sync::{Notify, RwLock};
+use tracing::{debug, info, warn};
 
 mod config;
 mod storage;
+mod anti_entropy;
 mod api;
+mod api_audit;
+mod audit;
+mod bundle;
+mod channels;
+mod consistency;
+mod doctor;
+mod event_log;
+mod events;
+mod identity;
+mod keyfile;
+mod mempool;
+mod metrics;
+mod network;
+mod payment_links;
+mod policy;
+mod privacy;
+mod pruning;
+mod replica;
+mod snapshot;
+mod tags;
+mod trace;
+mod updater;
+mod voting;
+mod wallet_session;
+mod webhooks;
+mod weight_audit;
+
+use anti_entropy::AncestryFetch;
+use api_audit::{ApiAccess, ApiAudit};
+use channels::ChannelBook;
+use audit::{PeerAudit, PeerAuditConfig, PeerAuditEntry, PeerAuditEvent};
+use config::NodeConfig;
+use event_log::{EventLog, Incident};
+use events::{EventBus, NodeEvent};
+use mempool::Mempool;
+use metrics::{FinalityMetrics, FinalitySlo};
+use network::PeerNetwork;
+use payment_links::PaymentLink;
+use policy::SpendingPolicy;
+use privacy::PendingHop;
+use pruning::PruningConfig;
+use snapshot::SnapshotMaker;
+use storage::Storage;
+use trace::{Origin, TraceLog, TraceStage};
+use updater::UpdateStatus;
+use wallet_session::WalletSession;
+use weight_audit::WeightAudit;
+
+/// Node state shared by the API, the P2P layer and the background tasks
+///
+/// Most API requests only read, so they hold the lock shared and run side by
+/// side; gossip, submissions and timers hold it exclusively. The lock is
+/// async: a task waiting for it yields instead of blocking an executor
+/// thread, so a slow request can't stall the runtime.
+pub type SharedState = Arc<RwLock<NodeState>>;
 
 /// Rhiza Node — A truly decentralized currency daemon
 #[derive(Parser)]
@@ -23,8 +96,16 @@ struct Cli {
     command: Commands,
 
     /// Data directory
-    #[arg(long, default_value = "~/.rhiza")]
+    #[arg(long, env = "RHIZA_DATA_DIR", default_value = "~/.rhiza", global = true)]
     data_dir: String,
+
+    /// Log to stdout only, without colors or banners (for containers)
+    #[arg(long, env = "RHIZA_PLAIN_LOGS", global = true, value_parser = BoolishValueParser::new())]
+    plain_logs: bool,
+
+    /// Read the wallet password from this file instead of prompting
+    #[arg(long, env = "RHIZA_PASSWORD_FILE", global = true)]
+    password_file: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -35,155 +116,1898 @@ enum Commands {
     /// Start the node daemon
     Start {
         /// TCP port to listen on
-        #[arg(short, long, default_value = "7470")]
+        #[arg(short, long, env = "RHIZA_PORT", default_value = "7470")]
         port: u16,
+
+        /// Address the REST API and wallet UI bind to (use 0.0.0.0 in containers)
+        #[arg(long, env = "RHIZA_API_BIND", default_value = "127.0.0.1")]
+        api_bind: IpAddr,
+
+        /// REST API port (defaults to the P2P port + 1)
+        #[arg(long, env = "RHIZA_API_PORT")]
+        api_port: Option<u16>,
+
+        /// Peer address to connect to (host:port), may be repeated
+        #[arg(long = "bootstrap")]
+        bootstrap_peers: Vec<String>,
+
+        /// Tip selection strategy: deepest, uniform, mcmc or mcmc:<alpha>
+        /// (overrides config.json)
+        #[arg(long, env = "RHIZA_TIP_SELECTION")]
+        tip_selection: Option<TipSelection>,
+
+        /// Parents the node's own transactions reference (overrides
+        /// config.json)
+        #[arg(long, env = "RHIZA_PARENT_COUNT")]
+        parent_count: Option<usize>,
+
+        /// Lock the wallet key's memory so it is never swapped to disk
+        #[arg(long, env = "RHIZA_MLOCK", value_parser = BoolishValueParser::new())]
+        mlock: bool,
+
+        /// Don't fall back to the built-in bootstrap peers
+        #[arg(long, env = "RHIZA_NO_DEFAULT_PEERS", value_parser = BoolishValueParser::new())]
+        no_default_peers: bool,
+
+        /// Refresh a copy of the database for analytics tools every this
+        /// many seconds (overrides config.json)
+        #[arg(long, env = "RHIZA_REPLICA_INTERVAL")]
+        replica_interval: Option<u64>,
+
+        /// Network to join: mainnet, testnet, devnet or a numeric ID
+        /// (overrides config.json)
+        #[arg(long, env = "RHIZA_NETWORK", value_parser = parse_network)]
+        network: Option<u32>,
+
+        /// Check for signed node releases (overrides config.json)
+        #[arg(long, env = "RHIZA_CHECK_UPDATES", value_parser = BoolishValueParser::new())]
+        check_updates: bool,
     },
 
     /// Show node status
     Status,
+
+    /// Check the setup `start` would run with and suggest fixes
+    Doctor {
+        /// TCP port to listen on
+        #[arg(short, long, env = "RHIZA_PORT", default_value = "7470")]
+        port: u16,
+
+        /// Address the REST API and wallet UI bind to
+        #[arg(long, env = "RHIZA_API_BIND", default_value = "127.0.0.1")]
+        api_bind: IpAddr,
+
+        /// REST API port (defaults to the P2P port + 1)
+        #[arg(long, env = "RHIZA_API_PORT")]
+        api_port: Option<u16>,
+
+        /// Peer address to connect to (host:port), may be repeated
+        #[arg(long = "bootstrap")]
+        bootstrap_peers: Vec<String>,
+
+        /// Don't fall back to the built-in bootstrap peers
+        #[arg(long, env = "RHIZA_NO_DEFAULT_PEERS", value_parser = BoolishValueParser::new())]
+        no_default_peers: bool,
+
+        /// Network to join: mainnet, testnet, devnet or a numeric ID
+        #[arg(long, env = "RHIZA_NETWORK", value_parser = parse_network)]
+        network: Option<u32>,
+    },
+
+    /// Manage the node's identity key
+    Identity {
+        #[command(subcommand)]
+        action: IdentityCommands,
+    },
+
+    /// Take, export, sign and import DAG snapshots
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotCommands,
+    },
+
+    /// Carry DAG history between nodes that can't reach each other, as files
+    Bundle {
+        #[command(subcommand)]
+        action: BundleCommands,
+    },
+
+    /// Sign release manifests (for release maintainers)
+    Release {
+        #[command(subcommand)]
+        action: ReleaseCommands,
+    },
+
+    /// Manage the keys API requests authenticate with
+    ApiKey {
+        #[command(subcommand)]
+        action: ApiKeyCommands,
+    },
+
+    /// Read the log of conflicts, failed checks, bans and snapshots
+    Events {
+        #[command(subcommand)]
+        action: EventsCommands,
+    },
+
+    /// Dump the DAG as JSON lines, one vertex per line
+    Export {
+        /// Read the analytics replica instead of the live database, so the
+        /// node can keep running
+        #[arg(long)]
+        read_only: bool,
+        /// File to write (defaults to stdout)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IdentityCommands {
+    /// Switch to a new identity key, keeping the relay count (node must be stopped)
+    Rotate {
+        /// Drop relay rewards the current key hasn't claimed yet
+        #[arg(long)]
+        discard_unclaimed: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiKeyCommands {
+    /// Create a key and print its token (restart the node to apply)
+    Add {
+        /// Name the key's requests are attributed to
+        id: String,
+    },
+    /// List the configured keys
+    List,
+    /// Remove a key (restart the node to apply)
+    Revoke {
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum EventsCommands {
+    /// Print logged events, oldest first
+    Show {
+        /// Only events this recent (30m, 12h, 7d), or since a date or RFC 3339 time
+        #[arg(long, default_value = "24h")]
+        since: String,
+        /// Only events of this kind, e.g. peer_banned or conflict_switched
+        #[arg(long)]
+        kind: Option<String>,
+        /// Only the newest this many
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Print the entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommands {
+    /// Have the running node take, verify and optionally publish a snapshot
+    Create {
+        /// Leave the snapshot unsigned instead of signing it with the node key
+        #[arg(long)]
+        no_sign: bool,
+        /// Serve the snapshot at /snapshot/latest for others to bootstrap from
+        #[arg(long)]
+        publish: bool,
+        /// API of the running node
+        #[arg(long, env = "RHIZA_API_URL", default_value = "http://127.0.0.1:7471")]
+        api_url: String,
+        /// Key for the node's admin API, if it requires one
+        #[arg(long, env = "RHIZA_API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+    },
+    /// Write the node's DAG and relay state to an unsigned snapshot file (node must be stopped)
+    Export {
+        /// Snapshot file to write
+        file: PathBuf,
+    },
+    /// Add this node key's publisher signature to a snapshot file (node must be stopped)
+    Sign {
+        /// Snapshot file to sign in place
+        file: PathBuf,
+    },
+    /// Bootstrap an empty node from a snapshot signed by enough trusted publishers (node must be stopped)
+    Import {
+        /// Snapshot file or http(s) URL
+        source: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleCommands {
+    /// Write transactions from a depth on to a signed, compressed file (node must be stopped)
+    Export {
+        /// Shallowest depth to include; the importing node needs what lies below
+        #[arg(long, default_value = "0")]
+        since_depth: u64,
+        /// Bundle file to write
+        file: PathBuf,
+    },
+    /// Validate a bundle file and merge its new transactions into the DAG (node must be stopped)
+    Import {
+        /// Bundle file to read
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReleaseCommands {
+    /// Add this node key's signature to a release manifest
+    Sign {
+        /// Manifest JSON file to sign in place
+        file: PathBuf,
+    },
 }
 
 /// The node's state
 pub struct NodeState {
     pub dag: Dag,
     pub relay_tracker: RelayTracker,
+    /// Node identity: Noise, relay proofs, channels and attestations
     pub keypair: KeyPair,
+    /// Public key of the wallet the API spends from
+    pub wallet_key: rhiza_core::crypto::PublicKey,
+    /// Network this node's transactions and peers belong to
+    pub network_id: u32,
     pub config: MeshConfig,
+    pub events: Arc<EventBus>,
+    pub network: Arc<PeerNetwork>,
+    pub storage: Storage,
+    /// Ranged DAG syncs in progress, per peer
+    syncs: HashMap<PeerId, RangeSync>,
+    /// Relay work not yet claimed, with the reward each relay earned
+    pending_relays: Vec<(RelayProof, u64)>,
+    /// Spending controls enforced before signing transfers
+    pub policy: Option<SpendingPolicy>,
+    /// Holds the wallet key while the API may spend from it
+    pub wallet_session: WalletSession,
+    /// Chooses parents for the node's own transactions
+    pub tip_selector: Box<dyn TipSelector>,
+    /// How many parents the node's own transactions reference
+    pub parent_count: usize,
+    /// Puzzle the node solves when a transaction needs more work
+    pub work_puzzle: WorkPuzzle,
+    /// Pieces of privacy sends parked on subkeys, waiting to be forwarded
+    private_hops: Vec<PendingHop>,
+    /// Payment links handed out, oldest first
+    pub payment_links: Vec<PaymentLink>,
+    /// The node's side of its payment channels
+    pub channel_book: ChannelBook,
+    /// Recent outcomes for transactions arriving from peers
+    validation_cache: ValidationCache,
+    /// Identity key rotations seen so far (ours and peers'), oldest first
+    identity_rotations: Vec<IdentityRotation>,
+    /// Transactions in the DAG still waiting for finality
+    pub mempool: Mempool,
+    /// Missing history being fetched from peers
+    ancestry: AncestryFetch,
+    /// How long transactions take to become final
+    pub finality: FinalityMetrics,
+    /// Finality latency objectives, if the operator set any
+    pub finality_slo: Option<FinalitySlo>,
+    /// Peer scores and the ban list
+    pub reputation: ReputationTracker,
+    /// Limits messages from peers must respect
+    pub gossip_limits: GossipLimits,
+    /// Stem/fluff routing of new transactions
+    pub dandelion: Dandelion,
+    /// Copies of new transactions peers delivered, to process each once
+    pub intake: GossipIntake,
+    /// What happened to each transaction seen recently, step by step
+    pub trace: TraceLog,
+    /// Votes with peers on which side of each double-spend wins
+    pub voting: ConflictVoting,
+    /// What the background weight checks found
+    pub weight_audit: WeightAudit,
+    /// Result of the last release update check
+    pub update: UpdateStatus,
+    /// Log of peer connects, disconnects, refusals and bans
+    pub peer_audit: PeerAudit,
+    /// Log of API requests that change something or read admin data
+    pub api_audit: ApiAudit,
+    /// Conflicts, failed checks, bans and snapshots, for post-mortems
+    pub event_log: EventLog,
+    /// Pruning of old final history
+    pub pruning: PruningConfig,
+    /// When the node started (unix ms)
+    pub started_at: u64,
+    /// Notified to stop the node as if it got Ctrl+C
+    pub shutdown: Arc<Notify>,
+    /// Snapshots taken on request over the API
+    pub snapshots: SnapshotMaker,
 }
 
 impl NodeState {
-    pub fn new(keypair: KeyPair, config: MeshConfig) -> Self {
+    pub fn new(keypair: KeyPair, config: MeshConfig, storage: Storage) -> Self {
+        let peer_audit = PeerAudit::new(storage.peer_audit_store(), PeerAuditConfig::default());
         NodeState {
             dag: Dag::new(),
             relay_tracker: RelayTracker::new(),
+            wallet_key: keypair.public_key.clone(),
+            wallet_session: WalletSession::new(Default::default(), PathBuf::new(), Some(keypair.clone())),
             keypair,
+            network_id: rhiza_core::MAINNET_ID,
             config,
+            events: Arc::new(EventBus::new()),
+            network: Arc::new(PeerNetwork::new()),
+            storage,
+            syncs: HashMap::new(),
+            pending_relays: Vec::new(),
+            policy: None,
+            tip_selector: TipSelection::default().selector(),
+            parent_count: rhiza_core::PARENT_COUNT,
+            work_puzzle: WorkPuzzle::default(),
+            private_hops: Vec::new(),
+            payment_links: Vec::new(),
+            channel_book: ChannelBook::default(),
+            validation_cache: ValidationCache::default(),
+            identity_rotations: Vec::new(),
+            mempool: Mempool::new(),
+            ancestry: AncestryFetch::new(),
+            finality: FinalityMetrics::new(),
+            finality_slo: None,
+            reputation: ReputationTracker::new(),
+            gossip_limits: GossipLimits::default(),
+            dandelion: Dandelion::default(),
+            intake: GossipIntake::new(),
+            trace: TraceLog::default(),
+            voting: ConflictVoting::default(),
+            weight_audit: WeightAudit::default(),
+            update: UpdateStatus::default(),
+            peer_audit,
+            api_audit: ApiAudit::disabled(),
+            event_log: EventLog::disabled(),
+            pruning: PruningConfig::default(),
+            started_at: chrono::Utc::now().timestamp_millis() as u64,
+            shutdown: Arc::new(Notify::new()),
+            snapshots: SnapshotMaker::default(),
         }
     }
 
     /// Initialize the DAG with a genesis transaction if empty
     pub fn initialize_genesis(&mut self) {
         if self.dag.is_empty() {
-            let genesis = Transaction::genesis(&self.keypair);
+            let genesis = Transaction::genesis(&self.keypair).for_network(self.network_id, &self.keypair);
             let genesis_id = genesis.id;
             info!("Creating genesis transaction: {}", genesis_id);
             self.dag
-                .insert(DagVertex::new(genesis, 0))
+                .insert(DagVertex::new(genesis.clone(), 0))
                 .expect("genesis insertion should not fail");
+            self.record_inserted(&genesis);
 
             // Create founder allocation (5% of max supply)
-            let founder_key_bytes = hex::decode(rhiza_core::FOUNDER_PUBLIC_KEY)
-                .expect("invalid founder public key");
-            let mut key_arr = [0u8; 32];
-            key_arr.copy_from_slice(&founder_key_bytes);
-            let founder_pubkey = rhiza_core::crypto::PublicKey::from_bytes(key_arr);
+            let founder_pubkey = rhiza_core::founder_public_key();
 
             let founder_tx = Transaction::founder_allocation(
                 &self.keypair,
                 founder_pubkey,
                 genesis_id,
-            );
+            )
+            .for_network(self.network_id, &self.keypair);
             info!(
                 "Creating founder allocation: {} RHZ → founder",
                 rhiza_core::FOUNDER_ALLOCATION / rhiza_core::UNITS_PER_RHZ
             );
             self.dag
-                .insert(DagVertex::new(founder_tx, 1))
+                .insert(DagVertex::new(founder_tx.clone(), 1))
                 .expect("founder allocation insertion should not fail");
+            self.record_inserted(&founder_tx);
         }
     }
 
-    /// Process an incoming transaction
-    pub fn process_transaction(&mut self, tx: Transaction) -> Result<(), String> {
-        // Validate
-        TransactionValidator::validate(&tx, &self.dag)
-            .map_err(|e| format!("Validation failed: {}", e))?;
+    /// Rebuild the DAG and relay tracker from storage
+    ///
+    /// Returns the number of vertices restored. Records that can't be
+    /// restored (undecodable, or missing a parent) are skipped and left for
+    /// the consistency check to report. If the node has pruned, the DAG
+    /// starts from the stored balance snapshot and pruned records kept in
+    /// cold storage are skipped.
+    pub fn load_from_storage(&mut self) -> Result<usize> {
+        if let Some(snapshot) = self.storage.get_balance_snapshot()? {
+            // Signed by this node, under its current key or one it rotated from
+            let rotations = self.storage.get_identity_rotations()?;
+            let own_keys = identity::key_history(&self.keypair.public_key, &rotations);
+            if !snapshot.verify(&own_keys) {
+                anyhow::bail!("The stored balance snapshot of pruned history is not signed by this node's key");
+            }
+            self.dag = Dag::from_balance_snapshot(&snapshot);
+            info!(
+                "Pruned history up to depth {} restored from its balance snapshot ({} transactions)",
+                snapshot.horizon,
+                snapshot.pruned.len()
+            );
+        }
+
+        // Depths are assigned parents-first, so walking the depth index
+        // restores parents before children
+        let mut restored = 0;
+        for vertex in self.storage.vertices_by_depth(..) {
+            let vertex = match vertex {
+                Ok(vertex) if vertex.transaction.verify_id() => vertex,
+                Ok(vertex) => {
+                    warn!("Skipping stored transaction {}: id mismatch", vertex.id());
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Skipping unreadable stored transaction: {}", e);
+                    continue;
+                }
+            };
+            let id = vertex.id();
+            if self.dag.pruned().contains(&id) {
+                continue;
+            }
+            match self.dag.insert_restored(vertex) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("Skipping stored transaction {}: {}", id, e),
+            }
+        }
 
-        // Calculate depth
-        let depth = self.dag.depth() + 1;
+        // Vertices that only reached finality with the recomputed weights
+        for id in self.dag.take_newly_final() {
+            self.persist_vertex(&id);
+        }
 
-        // Insert into DAG
-        self.dag
-            .insert(DagVertex::new(tx.clone(), depth))
-            .map_err(|e| format!("DAG insertion failed: {}", e))?;
+        self.relay_tracker = RelayTracker::with_store(
+            Box::new(self.storage.relay_count_store()),
+            DEFAULT_HOT_RELAYERS_PER_SHARD,
+            self.storage.get_relay_totals()?,
+        );
+        self.pending_relays = self.storage.get_pending_relays()?;
+        self.private_hops = self.storage.get_private_hops()?;
+        self.payment_links = self.storage.get_payment_links()?;
+        self.channel_book = ChannelBook::from_entries(self.storage.get_channel_entries()?);
+        self.identity_rotations = self.storage.get_identity_rotations()?;
+        self.reputation = ReputationTracker::with_bans(self.storage.get_peer_bans()?);
+        if !self.private_hops.is_empty() {
+            info!("{} private payment pieces still to forward", self.private_hops.len());
+        }
+        self.mempool = Mempool::from_dag(&self.dag, chrono::Utc::now().timestamp_millis() as u64);
+
+        Ok(restored)
+    }
 
-        // Record relay
+    /// Process an incoming transaction
+    pub fn process_transaction(&mut self, tx: Transaction) -> Result<(), String> {
+        let admitted = self.mempool.admit(&tx, &self.dag, false);
+        self.traced(&tx.id, admitted)?;
+        self.insert(&tx)?;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.finality.seen(tx.id, now);
+
+        // Record relay, keeping a proof of the work for a later reward claim
         let reward = self.relay_tracker.record_relay(&self.keypair.public_key);
         if reward > 0 {
             info!("Relay reward: {} units", reward);
+            let proof = RelayProof::new(&self.keypair, tx.id, 1);
+            self.pending_relays.push((proof, reward));
+        }
+
+        // Store the vertex together with the relay it earned, so a restart
+        // never finds one without the other
+        let finalized = self.dag.take_newly_final();
+        if let Some(vertex) = self.dag.get(&tx.id) {
+            let totals = self.relay_tracker.totals();
+            if let Err(e) = self.storage.put_vertex_with_relays(vertex, &totals, &self.pending_relays) {
+                warn!("Failed to persist transaction {}: {}", tx.id, e);
+            }
+        }
+        for id in &finalized {
+            self.persist_vertex(id);
         }
+        self.finality.finalized(&finalized, now);
+        self.publish_inserted(&tx, &finalized);
+        self.answer_channel_closes();
 
         Ok(())
     }
 
+    /// Count the delayed delivery of gossip held for a peer while it was
+    /// away; carrying someone else's transaction there is relay work like
+    /// flooding it
+    pub fn held_delivered(&mut self, peer: &PeerId, messages: &[GossipMessage]) {
+        let me = self.keypair.public_key.clone();
+        let mut earned = 0;
+        for message in messages {
+            let GossipMessage::NewTransaction(tx) = message else { continue };
+            let relayed = self.pending_relays.iter().any(|(proof, _)| proof.transaction_id == tx.id)
+                || self.dag.is_relay_claimed(&me, &tx.id);
+            if relayed || tx.data.sender == me || self.dag.get(&tx.id).is_none() {
+                continue;
+            }
+            let reward = self.relay_tracker.record_relay(&me);
+            if reward > 0 {
+                self.pending_relays.push((RelayProof::new(&self.keypair, tx.id, 1), reward));
+                earned += reward;
+            }
+        }
+        if earned > 0 {
+            info!("Relay reward: {} units", earned);
+            self.persist_relays();
+        }
+        info!("📬 Delivered {} held messages to {}", messages.len(), peer);
+    }
+
+    /// Admit one of our own (or a client's) transactions to the pending
+    /// pool, insert it into the DAG and persist it
+    ///
+    /// First adds whatever proof of work the current load asks for beyond
+    /// what the transaction carries, on the configured puzzle, so neither
+    /// wallets nor callers have to.
+    fn ingest(&mut self, tx: &mut Transaction) -> Result<(), String> {
+        *tx = tx.clone().with_puzzle(self.work_puzzle, self.dag.required_work(&tx.data.parents));
+        self.trace.first_seen(tx.id, Origin::Local, chrono::Utc::now().timestamp_millis() as u64);
+        // Peers would refuse to relay anything over their limits
+        let within_limits = GossipMessage::NewTransaction(tx.clone())
+            .check_limits(&self.gossip_limits)
+            .map_err(|e| format!("Peers won't relay this transaction: {}", e));
+        self.traced(&tx.id, within_limits)?;
+        let admitted = self.mempool.admit(tx, &self.dag, true);
+        self.traced(&tx.id, admitted)?;
+        self.insert(tx)?;
+        self.finality.seen(tx.id, chrono::Utc::now().timestamp_millis() as u64);
+        self.record_inserted(tx);
+        Ok(())
+    }
+
+    /// Validate a transaction and insert it into the DAG and pending pool
+    fn insert(&mut self, tx: &Transaction) -> Result<(), String> {
+        // Validate (peers often send the same transaction several times)
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let valid = self
+            .validation_cache
+            .validate(tx, &self.dag, now)
+            .map_err(|e| format!("Validation failed: {}", e));
+        self.traced(&tx.id, valid)?;
+        self.trace.record(&tx.id, TraceStage::Validated, now);
+
+        // Calculate depth (a synced genesis lands on an empty DAG)
+        let depth = if self.dag.is_empty() { 0 } else { self.dag.depth() + 1 };
+
+        // Insert into DAG
+        let inserted = self
+            .dag
+            .insert(DagVertex::new(tx.clone(), depth))
+            .map_err(|e| format!("DAG insertion failed: {}", e));
+        self.traced(&tx.id, inserted)?;
+        self.validation_cache.invalidate(&tx.id);
+        self.mempool.add(tx, now);
+        self.mempool.prune(&self.dag, now);
+
+        Ok(())
+    }
+
+    /// Handle a gossip message from a peer, returning replies for that peer
+    pub fn handle_gossip(&mut self, from: &PeerId, msg: GossipMessage) -> Vec<GossipMessage> {
+        match msg {
+            GossipMessage::NewTransaction(tx) => {
+                // It's flooding, so any stem it was on is over
+                let stemmed = self.dandelion.fluffed(&tx.id);
+                let transport = self.network.transport_of(from).unwrap_or(TransportType::Tcp);
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                if self.dag.contains(&tx.id) {
+                    // A later copy, maybe over another transport
+                    if let Intake::Late { credit } = self.intake.receive(tx.id, from, transport, true, now) {
+                        if credit > 0 {
+                            self.reputation.credit_late_relay(from, credit);
+                        }
+                    }
+                    // One of ours back from its stem: help it flood
+                    if stemmed {
+                        self.network
+                            .broadcast_except(&GossipMessage::NewTransaction(tx), from);
+                    }
+                    return Vec::new();
+                }
+                // Another copy is already on its way in
+                if self.intake.receive(tx.id, from, transport, false, now) != Intake::Process {
+                    return Vec::new();
+                }
+                self.trace.first_seen(tx.id, Origin::Gossip { peer_id: from.public_key.to_string(), transport }, now);
+                // We missed part of its history; fetch that first
+                let missing = anti_entropy::missing_parents(&tx, &self.dag);
+                if !missing.is_empty() {
+                    if !is_authentic(&tx) {
+                        self.intake.failed(&tx.id);
+                        self.record_behavior(from, Behavior::InvalidSignature);
+                        return Vec::new();
+                    }
+                    // No room to wait: let the next copy try
+                    let id = tx.id;
+                    if !self.ancestry.park(tx) {
+                        self.intake.failed(&id);
+                    }
+                    return self.ancestry.request(missing, &self.dag).into_iter().collect();
+                }
+                match self.process_transaction(tx.clone()) {
+                    Ok(()) => {
+                        self.credit_deliveries(&tx.id);
+                        let id = tx.id;
+                        let peers = self.network.broadcast_except(&GossipMessage::NewTransaction(tx), from);
+                        self.trace_gossip(&id, peers, false);
+                    }
+                    Err(e) => {
+                        debug!("Rejected transaction {} from {}: {}", tx.id, from, e);
+                        self.intake.failed(&tx.id);
+                        // Honest peers can relay a transaction we reject for
+                        // other reasons (a full pool, a lost conflict), but
+                        // not one that was never properly signed
+                        if !is_authentic(&tx) {
+                            self.record_behavior(from, Behavior::InvalidSignature);
+                        } else if let Some(ValidationError::ForgedRelayClaim { penalty, .. }) =
+                            self.validation_cache.rejection(&tx.id)
+                        {
+                            let penalty = *penalty;
+                            self.record_behavior(from, Behavior::ForgedRelayClaim { penalty });
+                        }
+                    }
+                }
+                Vec::new()
+            }
+            GossipMessage::StemTransaction(tx) => self.handle_stem(from, tx),
+            GossipMessage::SyncRequest { missing } => {
+                let transactions = missing
+                    .iter()
+                    .take(anti_entropy::MAX_SYNC_REQUEST)
+                    .filter_map(|id| self.dag.get(id).map(|v| v.transaction.clone()))
+                    .collect();
+                vec![GossipMessage::SyncResponse { transactions }]
+            }
+            GossipMessage::SyncResponse { transactions } => {
+                let transactions = self.drop_forged(from, transactions);
+                let missing = self.ingest_all(transactions);
+                self.ancestry.request(missing, &self.dag).into_iter().collect()
+            }
+            GossipMessage::SyncRangeRequest { from_depth, to_depth, limit } => {
+                let limit = limit.min(DEFAULT_SYNC_PAGE_LIMIT * 4) as usize;
+                let (vertices, next_depth) = self.dag.range_by_depth(from_depth, to_depth, limit);
+                let transactions = vertices.into_iter().map(|v| v.transaction.clone()).collect();
+                vec![GossipMessage::SyncRangeResponse { transactions, next_depth }]
+            }
+            GossipMessage::SyncRangeResponse { transactions, next_depth } => {
+                let transactions = self.drop_forged(from, transactions);
+                let Some(sync) = self.syncs.get_mut(from) else {
+                    return Vec::new();
+                };
+                let transactions = sync.on_response(transactions, next_depth);
+                let next = sync.next_request();
+                if sync.is_complete() {
+                    info!("✅ DAG sync with {} complete", from);
+                    self.syncs.remove(from);
+                }
+                let missing = self.ingest_all(transactions);
+                next.into_iter()
+                    .chain(self.ancestry.request(missing, &self.dag))
+                    .collect()
+            }
+            GossipMessage::TipAnnounce { tips, depth } => {
+                let behind = self.dag.is_empty() || depth > self.dag.depth();
+                if !behind && !self.syncs.contains_key(from) {
+                    // Level with the peer, but it may have tips we never saw
+                    // (say, from while we were partitioned)
+                    let tips = tips.into_iter().take(anti_entropy::MAX_ANNOUNCED_TIPS);
+                    let request = self.ancestry.request(tips, &self.dag);
+                    if request.is_some() {
+                        debug!("Fetching unknown tips announced by {}", from);
+                    }
+                    return request.into_iter().collect();
+                }
+                let sync = self.syncs.entry(from.clone()).or_insert_with(|| {
+                    info!("🔄 Syncing DAG from {} (remote depth {})", from, depth);
+                    RangeSync::new(depth)
+                });
+                sync.retarget(depth);
+                sync.next_request().into_iter().collect()
+            }
+            GossipMessage::IdentityRotation(rotation) => {
+                if rotation.verify().is_err() {
+                    self.record_behavior(from, Behavior::InvalidSignature);
+                    return Vec::new();
+                }
+                match self.accept_identity_rotation(rotation.clone()) {
+                    Ok(true) => {
+                        self.network
+                            .broadcast_except(&GossipMessage::IdentityRotation(rotation), from);
+                    }
+                    Ok(false) => {}
+                    Err(e) => debug!("Rejected identity rotation from {}: {}", from, e),
+                }
+                Vec::new()
+            }
+            GossipMessage::ChannelPayment(payment) => self.accept_channel_payment(from, payment),
+            GossipMessage::ChannelAck(signed) => {
+                self.channel_acknowledged(from, signed);
+                Vec::new()
+            }
+            GossipMessage::ChannelRefused { channel_id, sequence, reason } => {
+                self.channel_refused(from, &channel_id, sequence, &reason);
+                Vec::new()
+            }
+            GossipMessage::OpinionQuery { conflicts } => {
+                let opinions = self.voting.answer(&conflicts, &self.dag);
+                vec![GossipMessage::OpinionResponse { opinions }]
+            }
+            GossipMessage::OpinionResponse { opinions } => {
+                self.voting.record(from, &opinions);
+                Vec::new()
+            }
+            GossipMessage::Ping { timestamp } => vec![GossipMessage::Pong { timestamp }],
+            GossipMessage::RelayAnnounce(_) | GossipMessage::Pong { .. } => Vec::new(),
+        }
+    }
+
+    /// Pass on a transaction in its stem phase, or end the stem and flood it
+    ///
+    /// Nodes along the stem only check the transaction; it enters their DAG
+    /// once it floods, so a peer asking around can't find where it started.
+    fn handle_stem(&mut self, from: &PeerId, tx: Transaction) -> Vec<GossipMessage> {
+        if !is_authentic(&tx) {
+            self.record_behavior(from, Behavior::InvalidSignature);
+            return Vec::new();
+        }
+        // Already flooding, unless it's one of ours coming back round
+        if self.dag.contains(&tx.id) && !self.dandelion.is_embargoed(&tx.id) {
+            return Vec::new();
+        }
+        // We can't check it without its history; flooding fetches that
+        if !anti_entropy::missing_parents(&tx, &self.dag).is_empty() {
+            return self.handle_gossip(from, GossipMessage::NewTransaction(tx));
+        }
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if !self.dag.contains(&tx.id) {
+            let transport = self.network.transport_of(from).unwrap_or(TransportType::Tcp);
+            self.trace.first_seen(tx.id, Origin::Stem { peer_id: from.public_key.to_string(), transport }, now);
+            if let Err(e) = self.validation_cache.validate(&tx, &self.dag, now) {
+                debug!("Rejected stem transaction {} from {}: {}", tx.id, from, e);
+                if let ValidationError::ForgedRelayClaim { penalty, .. } = e {
+                    self.record_behavior(from, Behavior::ForgedRelayClaim { penalty });
+                }
+                return Vec::new();
+            }
+        }
+
+        let looped = !self.dandelion.embargo(tx.clone(), now);
+        let peers: Vec<PeerId> = self.network.peer_ids().into_iter().filter(|p| p != from).collect();
+        let route = if looped {
+            Route::Fluff
+        } else {
+            self.dandelion.route(false, &peers, now, &mut rand::thread_rng())
+        };
+        match route {
+            Route::Stem(next) if self.network.send_to(&next, GossipMessage::StemTransaction(tx.clone())) => {
+                self.trace_gossip(&tx.id, 1, true);
+            }
+            _ => self.flood(tx),
+        }
+        Vec::new()
+    }
+
+    /// Start one of our own (or a client's) transactions on its way to
+    /// every peer
+    ///
+    /// It goes down a Dandelion stem first, so observers can't easily tell
+    /// it came from this node; with no peers, or Dandelion off, it floods
+    /// straight away.
+    fn propagate(&mut self, tx: &Transaction) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let peers = self.network.peer_ids();
+        match self.dandelion.route(true, &peers, now, &mut rand::thread_rng()) {
+            Route::Stem(next) if self.network.send_to(&next, GossipMessage::StemTransaction(tx.clone())) => {
+                self.dandelion.embargo(tx.clone(), now);
+                self.trace_gossip(&tx.id, 1, true);
+            }
+            _ => {
+                let peers = self.network.broadcast(&GossipMessage::NewTransaction(tx.clone()));
+                self.trace_gossip(&tx.id, peers, false);
+            }
+        }
+    }
+
+    /// Flood a transaction that was on a stem to every peer, adding it to
+    /// the DAG first if it's new here
+    fn flood(&mut self, tx: Transaction) {
+        self.dandelion.fluffed(&tx.id);
+        if !self.dag.contains(&tx.id) {
+            if let Err(e) = self.process_transaction(tx.clone()) {
+                debug!("Could not flood stem transaction {}: {}", tx.id, e);
+                return;
+            }
+        }
+        let id = tx.id;
+        let peers = self.network.broadcast(&GossipMessage::NewTransaction(tx));
+        self.trace_gossip(&id, peers, false);
+    }
+
+    /// Note in a transaction's trace that it was sent on to `peers` peers
+    fn trace_gossip(&mut self, id: &Hash, peers: usize, stem: bool) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.trace.record(id, TraceStage::Gossiped { peers, stem }, now);
+    }
+
+    /// Pass `result` on, noting in the transaction's trace why it was
+    /// rejected if it was
+    fn traced<T>(&mut self, id: &Hash, result: Result<T, String>) -> Result<T, String> {
+        if let Err(reason) = &result {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            self.trace.record(id, TraceStage::Rejected { reason: reason.clone() }, now);
+        }
+        result
+    }
+
+    /// Flood the stem transactions that never came back as a flood, in
+    /// case a node on the stem dropped them
+    pub fn flood_embargoed(&mut self, now_ms: u64) {
+        for tx in self.dandelion.expired(now_ms) {
+            debug!("Embargo on stem transaction {} ran out; flooding it", tx.id);
+            self.flood(tx);
+        }
+    }
+
+    /// Drop transactions a peer sent whose id or signature doesn't check
+    /// out, holding it against the peer
+    fn drop_forged(&mut self, from: &PeerId, transactions: Vec<Transaction>) -> Vec<Transaction> {
+        let count = transactions.len();
+        let authentic: Vec<Transaction> = transactions.into_iter().filter(is_authentic).collect();
+        if authentic.len() < count {
+            debug!("{} sent {} forged transactions", from, count - authentic.len());
+            self.record_behavior(from, Behavior::InvalidSignature);
+        }
+        authentic
+    }
+
+    /// Insert a batch of transactions, skipping ones already known or invalid
+    ///
+    /// Transactions whose parents haven't arrived are parked until they do;
+    /// returns the parents still missing, to be requested from the peer.
+    fn ingest_all(&mut self, transactions: Vec<Transaction>) -> Vec<Hash> {
+        let mut missing = Vec::new();
+        let mut batch = transactions;
+        while !batch.is_empty() {
+            let mut inserted = false;
+            for tx in batch {
+                if self.dag.contains(&tx.id) {
+                    continue;
+                }
+                self.trace.first_seen(tx.id, Origin::Sync, chrono::Utc::now().timestamp_millis() as u64);
+                let absent = anti_entropy::missing_parents(&tx, &self.dag);
+                if !absent.is_empty() {
+                    missing.extend(absent);
+                    if !self.ancestry.park(tx) {
+                        debug!("Too many transactions waiting for parents; dropping one");
+                    }
+                    continue;
+                }
+                // History from a sync skips admission: it's what peers already hold
+                match self.insert(&tx) {
+                    Ok(()) => {
+                        self.record_inserted(&tx);
+                        self.credit_deliveries(&tx.id);
+                        inserted = true;
+                    }
+                    Err(e) => debug!("Skipping synced transaction {}: {}", tx.id, e),
+                }
+            }
+            batch = if inserted { self.ancestry.take_ready(&self.dag) } else { Vec::new() };
+        }
+        self.answer_channel_closes();
+        missing
+    }
+
+    /// Our deepest tips and depth, for announcing to peers
+    pub fn tip_announce(&self) -> GossipMessage {
+        let mut tips = self.dag.tips();
+        tips.truncate(anti_entropy::MAX_ANNOUNCED_TIPS);
+        GossipMessage::TipAnnounce {
+            tips,
+            depth: self.dag.depth(),
+        }
+    }
+
+    /// Record a peer's identity rotation, carrying its relay count over to
+    /// the new key
+    ///
+    /// Returns whether the rotation was new. Each key can only be rotated
+    /// away from once, so a leaked old key can't redirect the identity again.
+    pub fn accept_identity_rotation(&mut self, rotation: IdentityRotation) -> Result<bool, String> {
+        rotation.verify().map_err(|e| e.to_string())?;
+        if let Some(known) = self.identity_rotations.iter().find(|r| r.old_key == rotation.old_key) {
+            if *known == rotation {
+                return Ok(false);
+            }
+            return Err(format!("{} was already rotated to {}", rotation.old_key, known.new_key));
+        }
+
+        info!("🔁 Identity {} rotated to {}", rotation.old_key, rotation.new_key);
+        self.relay_tracker.transfer_count(&rotation.old_key, &rotation.new_key);
+        self.persist_relays();
+        self.identity_rotations.push(rotation);
+        if let Err(e) = self.storage.put_identity_rotations(&self.identity_rotations) {
+            warn!("Failed to persist identity rotations: {}", e);
+        }
+        Ok(true)
+    }
+
+    /// Known identity rotations, to hand to a newly connected peer
+    pub fn identity_rotation_messages(&self) -> Vec<GossipMessage> {
+        self.identity_rotations
+            .iter()
+            .cloned()
+            .map(GossipMessage::IdentityRotation)
+            .collect()
+    }
+
+    /// Forget per-peer state when a peer goes away
+    pub fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.syncs.remove(peer);
+    }
+
+    /// Credit the peers that delivered a transaction that just landed: the
+    /// first in full, those whose copies came after it in part
+    fn credit_deliveries(&mut self, id: &Hash) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        for delivery in self.intake.complete(id, now) {
+            if delivery.credit == FULL_RELAY_CREDIT {
+                self.record_behavior(&delivery.peer, Behavior::UsefulRelay);
+            } else {
+                self.reputation.credit_late_relay(&delivery.peer, delivery.credit);
+            }
+        }
+    }
+
+    /// Adjust a peer's score for something it did, banning it if that sinks
+    /// the score too far
+    pub fn record_behavior(&mut self, peer: &PeerId, behavior: Behavior) {
+        let address = self.network.address_of(peer).map(|a| a.ip());
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let banned = self.reputation.record(peer, address, behavior, now).is_some();
+        if banned {
+            self.peer_banned(peer);
+        }
+    }
+
+    /// Count a message against a peer's rate limit
+    pub fn message_received(&mut self, peer: &PeerId) {
+        let address = self.network.address_of(peer).map(|a| a.ip());
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let banned = self.reputation.message_received(peer, address, now).is_some();
+        if banned {
+            self.peer_banned(peer);
+        }
+    }
+
+    /// Ban a peer on the operator's say-so and drop its connection
+    pub fn ban_peer(&mut self, peer: &PeerId, duration_ms: Option<u64>, reason: String) -> Ban {
+        let address = self.network.address_of(peer).map(|a| a.ip());
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let ban = self.reputation.ban(peer, address, duration_ms, reason, now).clone();
+        self.peer_banned(peer);
+        self.network.disconnect(peer);
+        ban
+    }
+
+    /// Ranged DAG syncs in progress, per peer
+    pub fn syncs(&self) -> impl Iterator<Item = (&PeerId, &RangeSync)> {
+        self.syncs.iter()
+    }
+
+    /// Transactions parked until their missing ancestry arrives
+    pub fn parked_len(&self) -> usize {
+        self.ancestry.parked_len()
+    }
+
+    /// Whether a peer is currently banned
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.reputation.ban_for(peer, now).is_some()
+    }
+
+    fn peer_banned(&self, peer: &PeerId) {
+        if let Some(ban) = self.reputation.bans().get(peer) {
+            warn!("⛔ Banned {} for {} until {} (ban #{})", peer, ban.reason, ban.until_ms, ban.count);
+            let mut entry = PeerAuditEntry::new(PeerAuditEvent::Banned)
+                .peer(peer)
+                .reason(format!("{} (ban #{}, until {})", ban.reason, ban.count, ban.until_ms));
+            if let Some(address) = self.network.address_of(peer) {
+                entry = entry.address(address);
+            }
+            self.peer_audit.record(entry);
+            self.event_log.record(Incident::PeerBanned {
+                peer: peer.public_key.to_string(),
+                address: ban.address.map(|a| a.to_string()),
+                reason: ban.reason.clone(),
+                until_ms: ban.until_ms,
+                count: ban.count,
+            });
+        }
+        if let Err(e) = self.storage.put_peer_bans(self.reputation.bans()) {
+            warn!("Failed to persist peer bans: {}", e);
+        }
+    }
+
     /// Create and process a transfer transaction
     pub fn send(
         &mut self,
         recipient: rhiza_core::crypto::PublicKey,
         amount: u64,
+        memo: Option<String>,
+        fee: u64,
+        relay_tip: u64,
+        valid_after: u64,
     ) -> Result<Transaction, String> {
-        let parents = self.dag.select_parents();
+        // The fee and tip leave the wallet too, so they count against spending limits
+        let spent = amount.saturating_add(fee).saturating_add(relay_tip);
+        self.check_policy(&self.wallet_key, &[&recipient], spent, memo.as_deref())?;
+        let key = self.spending_key()?;
+
         let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&key, nonce);
 
-        let tx = Transaction::transfer(&self.keypair, recipient, amount, parents, nonce);
+        let mut tx = Transaction::transfer_with_tip(&key, recipient, amount, memo, relay_tip, parents, nonce)
+            .for_network(self.network_id, &key)
+            .with_fee(fee, &key)
+            .with_valid_after(valid_after, &key)
+            .with_selection_proof(proof, &key);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
 
-        // Validate first
-        TransactionValidator::validate(&tx, &self.dag)
-            .map_err(|e| format!("Validation failed: {}", e))?;
+        Ok(tx)
+    }
 
-        let depth = self.dag.depth() + 1;
-        self.dag
-            .insert(DagVertex::new(tx.clone(), depth))
-            .map_err(|e| format!("DAG insertion failed: {}", e))?;
+    /// Create and process a batch paying every output at once, under one
+    /// nonce and one parent selection
+    pub fn send_batch(
+        &mut self,
+        outputs: Vec<BatchOutput>,
+        memo: Option<String>,
+        fee: u64,
+        relay_tip: u64,
+    ) -> Result<Transaction, String> {
+        let total = outputs.iter().fold(0u64, |total, output| total.saturating_add(output.amount));
+        let recipients: Vec<_> = outputs.iter().map(|output| &output.recipient).collect();
+        let spent = total.saturating_add(fee).saturating_add(relay_tip);
+        self.check_policy(&self.wallet_key, &recipients, spent, memo.as_deref())?;
+        let key = self.spending_key()?;
+
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&key, nonce);
+
+        let sender = key.public_key.clone();
+        let data = TransactionData::batch(sender, outputs, memo, relay_tip, parents, nonce);
+        let mut tx = Transaction::new(data, &key)
+            .for_network(self.network_id, &key)
+            .with_fee(fee, &key)
+            .with_selection_proof(proof, &key);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Send privately: split the payment into random pieces, move each onto
+    /// a fresh subkey of the node key, and let the subkeys forward them
+    /// to the recipient after random delays
+    ///
+    /// Returns the funding transactions; the forwards follow from
+    /// [`privacy::run`]. This only breaks trivial links in the payment graph
+    /// (one sender, one recipient, one amount), not a determined observer.
+    pub fn send_private(
+        &mut self,
+        recipient: rhiza_core::crypto::PublicKey,
+        amount: u64,
+    ) -> Result<Vec<Transaction>, String> {
+        let key = self.spending_key()?;
+        if amount == 0 {
+            return Err("Validation failed: zero amount transfer".to_string());
+        }
+
+        let mut rng = rand::thread_rng();
+        let me = key.public_key.clone();
+        let first_nonce = self.dag.len() as u64;
+        let mut pieces = Vec::new();
+        for (i, piece) in privacy::split_amount(amount, &mut rng).into_iter().enumerate() {
+            let index = self
+                .storage
+                .next_privacy_subkey()
+                .map_err(|e| format!("Could not reserve a subkey: {}", e))?;
+            let subkey = self.keypair.derive_subkey(privacy::SUBKEY_CONTEXT, index);
+
+            let tip = self.tip_selector.select(&self.dag, 1, &mut rng)[0];
+            let parents =
+                privacy::random_funded_parents(&self.dag, &me, piece, tip, self.parent_count, &mut rng);
+            let nonce = first_nonce + i as u64;
+            let tx = Transaction::transfer(&key, subkey.public_key.clone(), piece, parents, nonce)
+                .for_network(self.network_id, &key);
+            pieces.push((index, tx));
+        }
+
+        // Forwards spend what their funding moved onto the subkeys, so the
+        // funding transactions carry everything that leaves the wallet
+        let spent = pieces.iter().map(|(_, tx)| tx.data.debit_for(&me)).sum::<u128>();
+        let spent = u64::try_from(spent).unwrap_or(u64::MAX);
+        self.check_policy(&self.wallet_key, &[&recipient], spent, None)?;
+        // Fail up front rather than after funding some of the pieces
+        let balance = self.balance();
+        if balance < spent {
+            return Err(format!("Insufficient balance: have {}, need {}", balance, spent));
+        }
+
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let mut funding = Vec::new();
+        for (index, mut tx) in pieces {
+            self.ingest(&mut tx)?;
+            self.propagate(&tx);
+
+            self.private_hops.push(PendingHop {
+                subkey_index: index,
+                recipient: recipient.clone(),
+                amount: tx.data.amount,
+                due_ms: now + privacy::hop_delay(&mut rng),
+                funding: tx.id,
+            });
+            self.persist_private_hops();
+            funding.push(tx);
+        }
+
+        Ok(funding)
+    }
+
+    /// Forward the private payment pieces that are due
+    pub fn forward_private_hops(&mut self, now_ms: u64) {
+        if !self.private_hops.iter().any(|hop| hop.due_ms <= now_ms) {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        let hops = std::mem::take(&mut self.private_hops);
+        for mut hop in hops {
+            if hop.due_ms > now_ms {
+                self.private_hops.push(hop);
+                continue;
+            }
+
+            let subkey = self.keypair.derive_subkey(privacy::SUBKEY_CONTEXT, hop.subkey_index);
+            let parents = privacy::random_funded_parents(
+                &self.dag,
+                &subkey.public_key,
+                hop.amount,
+                hop.funding,
+                self.parent_count,
+                &mut rng,
+            );
+            let nonce = self.dag.len() as u64;
+            let mut tx = Transaction::transfer(&subkey, hop.recipient.clone(), hop.amount, parents, nonce)
+                .for_network(self.network_id, &subkey);
+            match self.ingest(&mut tx) {
+                Ok(()) => {
+                    debug!("Forwarded private payment piece {}", tx.id);
+                    self.propagate(&tx);
+                }
+                Err(e) => {
+                    warn!("Could not forward private payment piece: {}", e);
+                    hop.retry_later(now_ms);
+                    self.private_hops.push(hop);
+                }
+            }
+        }
+        self.persist_private_hops();
+    }
+
+    fn persist_private_hops(&self) {
+        if let Err(e) = self.storage.put_private_hops(&self.private_hops) {
+            warn!("Failed to persist private payment pieces: {}", e);
+        }
+    }
+
+    /// Hand out a payment link for `amount`, paid to a fresh subkey of the
+    /// node wallet
+    ///
+    /// The subkey announces itself, so payers can pay its `rhz1` address.
+    pub fn create_payment_link(
+        &mut self,
+        amount: u64,
+        memo: Option<String>,
+        expires_in_secs: u64,
+        now_ms: u64,
+    ) -> Result<PaymentLink, String> {
+        if amount == 0 {
+            return Err("A payment link needs an amount".to_string());
+        }
+        if expires_in_secs == 0 || expires_in_secs > payment_links::MAX_EXPIRY_SECS {
+            return Err(format!(
+                "Expiry must be between 1 and {} seconds",
+                payment_links::MAX_EXPIRY_SECS
+            ));
+        }
+        let max_memo = self.gossip_limits.max_memo_bytes;
+        if memo.as_ref().is_some_and(|m| m.len() > max_memo) {
+            return Err(format!("Memo is longer than {} bytes", max_memo));
+        }
+
+        let key_index = self
+            .storage
+            .next_payment_link_key()
+            .map_err(|e| format!("Could not reserve a key: {}", e))?;
+        let subkey = self.keypair.derive_subkey(payment_links::SUBKEY_CONTEXT, key_index);
+        let nonce = self.dag.len() as u64;
+        let parents = self.dag.select_deepest_parents(self.parent_count);
+        let mut announcement = Transaction::key_announcement(&subkey, parents, nonce)
+            .for_network(self.network_id, &subkey);
+        self.ingest(&mut announcement)?;
+        self.propagate(&announcement);
+
+        let link = PaymentLink {
+            id: hex::encode(rand::random::<[u8; 12]>()),
+            key_index,
+            public_key: subkey.public_key.clone(),
+            amount,
+            memo,
+            created_ms: now_ms,
+            expires_ms: now_ms.saturating_add(expires_in_secs * 1000),
+            sweeps: Vec::new(),
+        };
+        self.storage
+            .put_payment_link(&link)
+            .map_err(|e| format!("Could not store the payment link: {}", e))?;
+        self.payment_links.push(link.clone());
+        Ok(link)
+    }
+
+    /// Move final funds from paid and expired payment links to the wallet
+    pub fn sweep_payment_links(&mut self, now_ms: u64) {
+        let me = self.wallet_key.clone();
+        for i in 0..self.payment_links.len() {
+            let amount = self.payment_links[i].sweepable(&self.dag, now_ms);
+            if amount == 0 {
+                continue;
+            }
+            let link = &self.payment_links[i];
+            let subkey = self.keypair.derive_subkey(payment_links::SUBKEY_CONTEXT, link.key_index);
+            let parents = self.dag.select_deepest_parents(self.parent_count);
+            let nonce = self.dag.len() as u64;
+            let mut tx = Transaction::transfer(&subkey, me.clone(), amount, parents, nonce)
+                .for_network(self.network_id, &subkey);
+            if let Err(e) = self.ingest(&mut tx) {
+                warn!("Could not sweep payment link {}: {}", self.payment_links[i].id, e);
+                continue;
+            }
+            self.propagate(&tx);
+            let link = &mut self.payment_links[i];
+            debug!("Swept {} from payment link {} in {}", amount, link.id, tx.id);
+            link.sweeps.push((tx.id, amount));
+            if let Err(e) = self.storage.put_payment_link(link) {
+                warn!("Failed to persist payment link {}: {}", link.id, e);
+            }
+        }
+    }
+
+    /// Open a payment channel to `payee`, locking `deposit` from the node
+    /// key until it settles; channels belong to the peer identity
+    pub fn open_channel(&mut self, payee: rhiza_core::crypto::PublicKey, deposit: u64) -> Result<Transaction, String> {
+        if payee == self.keypair.public_key {
+            return Err("A channel needs two different parties".to_string());
+        }
+        self.check_policy(&self.keypair.public_key, &[&payee], deposit, None)?;
+
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&self.keypair, nonce);
+        let mut tx = Transaction::channel_open(&self.keypair, payee, deposit, parents, nonce)
+            .for_network(self.network_id, &self.keypair)
+            .with_selection_proof(proof, &self.keypair);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Pay `recipient` off the DAG, over a route of open channels that
+    /// starts with one this node funds
+    ///
+    /// Returns once the first hop is sent; each hop acknowledges the one
+    /// before it and passes the payment on.
+    pub fn pay_over_channels(
+        &mut self,
+        recipient: &rhiza_core::crypto::PublicKey,
+        amount: u64,
+    ) -> Result<ChannelPayment, String> {
+        if amount == 0 {
+            return Err("Channel payments must be for a non-zero amount".to_string());
+        }
+        let me = self.keypair.public_key.clone();
+        let route = self
+            .dag
+            .channels()
+            .route(&me, recipient, amount, |channel| self.channel_book.capacity(channel, &me))
+            .filter(|route| !route.is_empty())
+            .ok_or_else(|| format!("No route of open channels can carry {} to {}", amount, recipient))?;
+        self.check_policy(&self.keypair.public_key, &[recipient], amount, None)?;
+
+        let payment_id = Hash::digest(&rand::random::<[u8; 32]>());
+        self.send_channel_payment(payment_id, route, 0, amount)
+    }
+
+    /// Pay the next state of `route[hop]`, a channel this node funds, to its
+    /// payee
+    fn send_channel_payment(
+        &mut self,
+        payment_id: Hash,
+        route: Vec<Hash>,
+        hop: usize,
+        amount: u64,
+    ) -> Result<ChannelPayment, String> {
+        let id = route[hop];
+        let channel = self
+            .dag
+            .channels()
+            .get(&id)
+            .filter(|channel| channel.is_open() && channel.funder == self.keypair.public_key)
+            .ok_or_else(|| format!("Channel {} isn't an open channel this node funds", id))?;
+        let payee = PeerId::new(channel.payee.clone());
+        let deposit = channel.deposit;
+        if self.channel_book.get(&id).is_some_and(|entry| entry.in_flight.is_some()) {
+            return Err(format!("A payment over channel {} is still waiting for the payee", id));
+        }
+        let state = self
+            .channel_book
+            .state(&id)
+            .pay(amount)
+            .filter(|state| state.paid <= deposit)
+            .ok_or_else(|| format!("Channel {} can't carry {} more", id, amount))?;
+
+        let payment = ChannelPayment {
+            payment_id,
+            route,
+            hop,
+            amount,
+            update: state.sign(&self.keypair),
+        };
+        if !self.network.send_to(&payee, GossipMessage::ChannelPayment(payment.clone())) {
+            return Err(format!("The payee of channel {} isn't connected", id));
+        }
+        self.channel_book.entry(id).in_flight = Some(state);
+        self.persist_channels();
+        Ok(payment)
+    }
+
+    /// Take a payment over a channel this node is the payee of, then pass
+    /// it on if the route goes further
+    fn accept_channel_payment(&mut self, from: &PeerId, payment: ChannelPayment) -> Vec<GossipMessage> {
+        let state = payment.update.state;
+        let next = match self.check_channel_payment(from, &payment) {
+            Ok(next) => next,
+            Err(reason) => {
+                debug!("Refused payment over channel {} from {}: {}", state.channel_id, from, reason);
+                return vec![GossipMessage::ChannelRefused {
+                    channel_id: state.channel_id,
+                    sequence: state.sequence,
+                    reason,
+                }];
+            }
+        };
+
+        self.channel_book.entry(state.channel_id).signed = payment.update.clone();
+        self.persist_channels();
+        let ack = GossipMessage::ChannelAck(state.sign(&self.keypair));
+        if next {
+            let ChannelPayment { payment_id, route, hop, amount, .. } = payment;
+            if let Err(e) = self.send_channel_payment(payment_id, route, hop + 1, amount) {
+                warn!("Could not pass on channel payment {}: {}", payment_id, e);
+            }
+        } else {
+            info!("💸 Received {} over channel {} (payment {})", payment.amount, state.channel_id, payment.payment_id);
+        }
+        vec![ack]
+    }
+
+    /// Check a channel payment from `from`, returning whether the route
+    /// continues past this node
+    ///
+    /// The next hop is checked before the payment is taken, so the node
+    /// never holds a payment it can't pass on.
+    fn check_channel_payment(&self, from: &PeerId, payment: &ChannelPayment) -> Result<bool, String> {
+        let state = &payment.update.state;
+        if payment.route.get(payment.hop) != Some(&state.channel_id) {
+            return Err("the state isn't for this hop of the route".to_string());
+        }
+        let me = &self.keypair.public_key;
+        let channel = self
+            .dag
+            .channels()
+            .get(&state.channel_id)
+            .filter(|channel| channel.is_open())
+            .ok_or("the channel isn't open")?;
+        if channel.funder != from.public_key || channel.payee != *me {
+            return Err("the channel isn't between this peer and this node".to_string());
+        }
+        if !payment.update.verify(&channel.funder) {
+            return Err("the state isn't signed by the funder".to_string());
+        }
+        let expected = self.channel_book.state(&state.channel_id).pay(payment.amount);
+        if expected != Some(*state) {
+            return Err("the state doesn't follow the last one by the amount paid".to_string());
+        }
+        if state.paid > channel.deposit {
+            return Err("the channel's deposit doesn't cover it".to_string());
+        }
+
+        let Some(next) = payment.route.get(payment.hop + 1) else {
+            return Ok(false);
+        };
+        let next = self
+            .dag
+            .channels()
+            .get(next)
+            .filter(|next| next.is_open() && next.funder == *me)
+            .ok_or("the next channel isn't an open one this node funds")?;
+        if self.channel_book.capacity(next, me) < payment.amount {
+            return Err("the next channel can't carry it now".to_string());
+        }
+        if self.network.address_of(&PeerId::new(next.payee.clone())).is_none() {
+            return Err("the next hop isn't connected".to_string());
+        }
+        Ok(true)
+    }
+
+    /// Keep the payee's signature on the state in flight over one of our
+    /// channels, which the node can now close on
+    fn channel_acknowledged(&mut self, from: &PeerId, signed: SignedChannelState) {
+        let id = signed.state.channel_id;
+        let Some(channel) = self.dag.channels().get(&id) else {
+            return;
+        };
+        if channel.payee != from.public_key || !signed.verify(&channel.payee) {
+            debug!("Ignoring a bad acknowledgement for channel {} from {}", id, from);
+            return;
+        }
+        let entry = self.channel_book.entry(id);
+        if entry.in_flight != Some(signed.state) {
+            return;
+        }
+        entry.signed = signed;
+        entry.in_flight = None;
+        self.persist_channels();
+    }
+
+    /// Drop the state in flight over one of our channels that its payee
+    /// refused
+    fn channel_refused(&mut self, from: &PeerId, id: &Hash, sequence: u64, reason: &str) {
+        let Some(channel) = self.dag.channels().get(id) else {
+            return;
+        };
+        if channel.payee != from.public_key {
+            return;
+        }
+        let entry = self.channel_book.entry(*id);
+        if entry.in_flight.map(|state| state.sequence) != Some(sequence) {
+            return;
+        }
+        warn!("Payment over channel {} refused: {}", id, reason);
+        entry.in_flight = None;
+        self.persist_channels();
+    }
+
+    /// Close one of the node's channels on the latest state the other
+    /// party signed
+    pub fn close_channel(&mut self, id: &Hash) -> Result<Transaction, String> {
+        let channel = self
+            .dag
+            .channels()
+            .get(id)
+            .ok_or_else(|| format!("Channel {} isn't in final history", id))?;
+        let counterparty = channel
+            .counterparty(&self.keypair.public_key)
+            .ok_or_else(|| format!("This node isn't a party to channel {}", id))?
+            .clone();
+        let signed = self
+            .channel_book
+            .get(id)
+            .map_or_else(|| SignedChannelState::opening(*id), |entry| entry.signed.clone());
+
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&self.keypair, nonce);
+        let mut tx = Transaction::channel_close(&self.keypair, counterparty, signed, parents, nonce)
+            .for_network(self.network_id, &self.keypair)
+            .with_selection_proof(proof, &self.keypair);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        self.channel_book.entry(*id).close = Some(tx.id);
+        self.persist_channels();
+        Ok(tx)
+    }
+
+    /// Answer funders closing channels this node is payee of on a state
+    /// older than the one it holds, by closing on that one
+    ///
+    /// This has to happen within the dispute window, or the older state
+    /// settles.
+    fn answer_channel_closes(&mut self) {
+        let me = &self.keypair.public_key;
+        let stale: Vec<Hash> = self
+            .dag
+            .channels()
+            .of(me)
+            .filter(|channel| channel.payee == *me)
+            .filter(|channel| match channel.status {
+                ChannelStatus::Closing { state, .. } => self
+                    .channel_book
+                    .get(&channel.id)
+                    .is_some_and(|entry| entry.close.is_none() && entry.signed.state.sequence > state.sequence),
+                _ => false,
+            })
+            .map(|channel| channel.id)
+            .collect();
+        for id in stale {
+            match self.close_channel(&id) {
+                Ok(close) => info!("⚖️  Answered a stale close of channel {} with {}", id, close.id),
+                Err(e) => warn!("Could not answer the close of channel {}: {}", id, e),
+            }
+        }
+    }
+
+    fn persist_channels(&self) {
+        if let Err(e) = self.storage.put_channel_entries(self.channel_book.entries()) {
+            warn!("Failed to persist channel states: {}", e);
+        }
+    }
+
+    /// Check a payment from `spender` to `recipients` against the spending
+    /// policy, if there is one
+    fn check_policy(
+        &self,
+        spender: &rhiza_core::crypto::PublicKey,
+        recipients: &[&rhiza_core::crypto::PublicKey],
+        amount: u64,
+        memo: Option<&str>,
+    ) -> Result<(), String> {
+        if let Some(policy) = &self.policy {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            policy.check(&self.dag, spender, recipients, amount, memo, now)?;
+        }
+        Ok(())
+    }
+
+    /// Pick parents for `key`'s transaction with `nonce`, using the
+    /// configured strategy on randomness others can check
+    fn select_parents(&self, key: &KeyPair, nonce: u64) -> (Vec<Hash>, VrfProof) {
+        let (proof, mut rng) = prove_selection(key, self.network_id, nonce);
+        (self.tip_selector.select(&self.dag, self.parent_count, &mut rng), proof)
+    }
+
+    /// Accept a transaction signed elsewhere (offline or third-party wallets)
+    pub fn submit(&mut self, mut tx: Transaction) -> Result<Transaction, String> {
+        if self.dag.contains(&tx.id) {
+            return Err("Transaction already known".to_string());
+        }
+
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Replace one of the node wallet's unfinalized transfers with a copy
+    /// carrying a higher relay tip
+    ///
+    /// `memo` corrects the original's memo (empty clears it); `None` keeps it.
+    pub fn replace(&mut self, id: &Hash, memo: Option<String>, relay_tip: u64) -> Result<Transaction, String> {
+        let vertex = self.dag.get(id).ok_or_else(|| format!("Transaction {} not found", id))?;
+        let original = vertex.transaction.clone();
+        if original.data.tx_type != TransactionType::Transfer || original.data.sender != self.wallet_key {
+            return Err("Only the node wallet's own transfers can be replaced".to_string());
+        }
+        if original.data.fee_payer.is_some() {
+            return Err("A sponsored transfer's replacement needs the sponsor's signature".to_string());
+        }
+        if vertex.is_final {
+            return Err(format!("Transaction {} is already final", id));
+        }
+        if let Some(newer) = self.dag.conflicts().replacement_of(id) {
+            return Err(format!("Transaction {} was already replaced by {}", id, newer));
+        }
+        let memo = match memo {
+            Some(memo) => Some(memo).filter(|m| !m.trim().is_empty()),
+            None => original.data.memo.clone(),
+        };
+        // Only the raised tip leaves the wallet on top of the original
+        let extra = relay_tip.saturating_sub(original.data.relay_tip);
+        self.check_policy(&self.wallet_key, &[&original.data.recipient], extra, memo.as_deref())?;
+        let key = self.spending_key()?;
+
+        // Same nonce, same draw: the original's proof carries over
+        let (parents, _) = self.select_parents(&key, original.data.nonce);
+        let mut tx = Transaction::replacement(&original, &key, memo, relay_tip, parents);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Publish the wallet's key so payments to its address can be resolved
+    pub fn announce_key(&mut self) -> Result<Transaction, String> {
+        let key = self.spending_key()?;
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&key, nonce);
+        let mut tx = Transaction::key_announcement(&key, parents, nonce)
+            .for_network(self.network_id, &key)
+            .with_selection_proof(proof, &key);
+
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Announce an alias for the wallet's key, paid to `receive_key`
+    pub fn announce_alias(
+        &mut self,
+        alias: &rhiza_core::wallet::Alias,
+        receive_key: rhiza_core::crypto::PublicKey,
+    ) -> Result<Transaction, String> {
+        let key = self.spending_key()?;
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&key, nonce);
+        let mut tx = Transaction::alias_announcement(&key, alias, receive_key, parents, nonce)
+            .for_network(self.network_id, &key)
+            .with_selection_proof(proof, &key);
+
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
+
+        Ok(tx)
+    }
+
+    /// Co-sign and submit a transaction that names this node as fee payer
+    pub fn sponsor(&mut self, mut tx: Transaction) -> Result<Transaction, String> {
+        if tx.data.fee_payer.as_ref() != Some(&self.wallet_key) {
+            return Err("Transaction does not name this node as fee payer".to_string());
+        }
+        if self.dag.contains(&tx.id) {
+            return Err("Transaction already known".to_string());
+        }
+
+        tx.sign_as_fee_payer(&self.spending_key()?);
+        self.ingest(&mut tx)?;
+        self.propagate(&tx);
 
         Ok(tx)
     }
 
     /// Claim a relay reward
     pub fn claim_relay_reward(&mut self) -> Result<Transaction, String> {
-        let relay_count = self.relay_tracker.get_relay_count(&self.keypair.public_key);
-        let reward = self.relay_tracker.calculate_reward(relay_count);
+        let nonce = self.dag.len() as u64;
+        let (parents, proof) = self.select_parents(&self.keypair, nonce);
 
-        if reward == 0 {
+        // Claim relays the new transaction can see; the rest wait for a later claim
+        let cone = self.dag.past_cone(&parents);
+        let me = self.keypair.public_key.clone();
+        self.pending_relays
+            .retain(|(proof, _)| !self.dag.is_relay_claimed(&me, &proof.transaction_id));
+        let (mut claimable, mut waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_relays)
+            .into_iter()
+            .partition(|(proof, _)| cone.contains(&proof.transaction_id));
+        if claimable.len() > MAX_RELAY_PROOFS_PER_CLAIM {
+            waiting.extend(claimable.split_off(MAX_RELAY_PROOFS_PER_CLAIM));
+        }
+        self.pending_relays = waiting;
+
+        if claimable.is_empty() {
             return Err("No reward available".to_string());
         }
 
-        let parents = self.dag.select_parents();
-        let nonce = self.dag.len() as u64;
+        // Past the maximum supply relays earn nothing more; near it, what's left
+        let remaining = self.dag.supply().remaining();
+        if remaining == 0 {
+            self.pending_relays.extend(claimable);
+            return Err("Maximum supply reached".to_string());
+        }
+        let reward = claimable.iter().map(|(_, reward)| reward).sum::<u64>().min(remaining);
+        let proofs = claimable.iter().map(|(proof, _)| proof.clone()).collect();
+        let mut tx = Transaction::relay_reward_with_proofs(&self.keypair, reward, proofs, parents, nonce)
+            .for_network(self.network_id, &self.keypair)
+            .with_selection_proof(proof, &self.keypair);
 
-        let tx = Transaction::relay_reward(&self.keypair, reward, parents, nonce);
+        if let Err(e) = self.ingest(&mut tx) {
+            // Keep the work so it can be claimed again
+            self.pending_relays.extend(claimable);
+            return Err(e);
+        }
+        self.propagate(&tx);
+        self.persist_relays();
 
-        let depth = self.dag.depth() + 1;
-        self.dag
-            .insert(DagVertex::new(tx.clone(), depth))
-            .map_err(|e| format!("DAG insertion failed: {}", e))?;
+        Ok(tx)
+    }
 
-        self.relay_tracker.record_relay(&self.keypair.public_key);
+    /// Persist and announce a transaction that was just inserted into the DAG
+    fn record_inserted(&mut self, tx: &Transaction) {
+        let finalized = self.dag.take_newly_final();
+        self.persist_vertex(&tx.id);
+        // Finality flags changed on these, so rewrite their records
+        for id in &finalized {
+            self.persist_vertex(id);
+        }
+        self.finality.finalized(&finalized, chrono::Utc::now().timestamp_millis() as u64);
+        self.publish_inserted(tx, &finalized);
+    }
 
-        Ok(tx)
+    /// Collapse final history more than `keep_depth` below the deepest
+    /// vertex into a signed balance snapshot and drop it from memory
+    ///
+    /// The snapshot is stored before any record is deleted, so a crash in
+    /// between only leaves pruned records behind for the next start to skip.
+    /// Returns how many vertices were pruned.
+    pub fn prune(&mut self, config: &PruningConfig) -> Result<usize, String> {
+        let Some(horizon) = self.dag.depth().checked_sub(config.keep_depth) else {
+            return Ok(0);
+        };
+        let removed = self.dag.prune(horizon);
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        let mut snapshot = self.dag.balance_snapshot();
+        snapshot.sign(&self.keypair);
+        self.storage
+            .put_balance_snapshot(&snapshot)
+            .map_err(|e| format!("Could not store the balance snapshot: {}", e))?;
+        if !config.cold_storage {
+            let ids: Vec<Hash> = removed.iter().map(|v| v.id()).collect();
+            self.storage
+                .remove_vertices(&ids)
+                .map_err(|e| format!("Could not delete pruned transactions: {}", e))?;
+        }
+        Ok(removed.len())
     }
 
-    /// Get this node's balance
+    /// Write a vertex's current record to storage
+    ///
+    /// A failed write is logged rather than surfaced: the vertex is already
+    /// in the DAG, and the startup consistency check restores missing records.
+    fn persist_vertex(&self, id: &rhiza_core::crypto::Hash) {
+        if let Some(vertex) = self.dag.get(id) {
+            if let Err(e) = self.storage.put_vertex(vertex) {
+                warn!("Failed to persist transaction {}: {}", id, e);
+            }
+        }
+    }
+
+    fn persist_relays(&self) {
+        if let Err(e) = self.storage.put_relay_state(&self.relay_tracker.totals(), &self.pending_relays) {
+            warn!("Failed to persist relay state: {}", e);
+        }
+    }
+
+    /// Publish events for a transaction that was just inserted into the DAG,
+    /// and trace it and the transactions it approves
+    fn publish_inserted(&mut self, tx: &Transaction, finalized: &[rhiza_core::crypto::Hash]) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if let Some(vertex) = self.dag.get(&tx.id) {
+            self.trace.record(&tx.id, TraceStage::Inserted { depth: vertex.depth }, now);
+        }
+        let parents = &tx.data.parents;
+        for (i, parent) in parents.iter().enumerate() {
+            if parents[..i].contains(parent) {
+                continue;
+            }
+            let approvers = self.dag.approver_count(parent);
+            let stage = TraceStage::Approved { by: tx.id.to_string(), approvers };
+            self.trace.record(parent, stage, now);
+        }
+        for id in finalized {
+            self.trace.record(id, TraceStage::Finalized, now);
+        }
+
+        self.events.publish(NodeEvent::TxReceived {
+            id: tx.id.to_string(),
+            sender: tx.data.sender.to_string(),
+            recipient: tx.data.recipient.to_string(),
+            amount: tx.data.amount,
+            outputs: output_recipients(&tx.data),
+        });
+        self.events.publish(NodeEvent::TipChanged {
+            tips: self.dag.tips().iter().map(|t| t.to_string()).collect(),
+        });
+        for id in finalized {
+            let Some(vertex) = self.dag.get(id) else {
+                continue;
+            };
+            self.events.publish(NodeEvent::TxFinalized {
+                id: id.to_string(),
+                sender: vertex.transaction.data.sender.to_string(),
+                recipient: vertex.transaction.data.recipient.to_string(),
+                outputs: output_recipients(&vertex.transaction.data),
+            });
+        }
+    }
+
+    /// Tag one of this wallet's transactions (local metadata only)
+    pub fn tag_transaction(
+        &mut self,
+        id: &rhiza_core::crypto::Hash,
+        tags: &[String],
+    ) -> Result<Vec<String>, String> {
+        let vertex = self.dag.get(id).ok_or("Transaction not found")?;
+        if !vertex.transaction.data.parties().contains(&&self.wallet_key) {
+            return Err("Only this wallet's own transactions can be tagged".to_string());
+        }
+
+        let tags = tags::normalize_tags(tags)?;
+        self.storage
+            .set_tags(id, &tags)
+            .map_err(|e| format!("Failed to store tags: {}", e))?;
+        Ok(tags)
+    }
+
+    /// Get the wallet's balance
     pub fn balance(&self) -> u64 {
-        self.dag.get_balance(&self.keypair.public_key)
+        self.dag.get_balance(&self.wallet_key)
     }
 
-    /// Get this node's address
+    /// Get the wallet's address
     pub fn address(&self) -> Address {
-        Address::from_public_key(&self.keypair.public_key)
+        Address::from_public_key(&self.wallet_key)
+    }
+
+    /// Key the wallet's spends are signed with, if it is unlocked
+    fn spending_key(&self) -> Result<KeyPair, String> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.wallet_session
+            .key(now)
+            .cloned()
+            .ok_or_else(|| "The wallet is locked; unlock it with POST /wallet/unlock".to_string())
     }
 }
 
+fn parse_network(s: &str) -> Result<u32, String> {
+    rhiza_core::parse_network_id(s).ok_or_else(|| format!("unknown network {:?}", s))
+}
+
+/// Hex keys of the recipients a batch pays, for events
+fn output_recipients(data: &TransactionData) -> Vec<String> {
+    data.outputs.iter().map(|output| output.recipient.to_string()).collect()
+}
+
+/// Whether a transaction's id and signatures match its contents
+fn is_authentic(tx: &Transaction) -> bool {
+    tx.verify_id() && tx.verify_signature()
+}
+
+/// Resolve once the process is asked to stop
+async fn shutdown_signal(stop: Arc<Notify>) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = term.recv() => {}
+            _ = stop.notified() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = stop.notified() => {}
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
         )
+        .with_writer(std::io::stdout)
+        .with_ansi(!cli.plain_logs)
         .init();
-
-    let cli = Cli::parse();
     let data_dir = shellexpand::tilde(&cli.data_dir).to_string();
     let data_path = PathBuf::from(&data_dir);
 
@@ -198,20 +2022,37 @@ async fn main() -> Result<()> {
             let keypair = KeyPair::generate();
             let address = Address::from_public_key(&keypair.public_key);
 
-            // Save keystore
-            let keystore = rhiza_core::wallet::keystore::KeyStore::from_keypair(&keypair);
+            // Save the keystore, encrypted under the wallet password
             let keystore_path = data_path.join("wallet.json");
-            keystore.save(&keystore_path)?;
+            keyfile::create(&keystore_path, &keypair, cli.password_file.as_deref())?;
+
+            // Write the default config so it can be edited
+            let config_path = data_path.join("config.json");
+            if !config_path.exists() {
+                NodeConfig::default().save(&config_path)?;
+            }
 
             println!("🌿 Rhiza Node initialized!");
             println!("📁 Data directory: {}", data_dir);
             println!("🔑 Address: {}", address);
-            println!("⚠️  Keep your wallet.json safe — it contains your private key!");
+            println!("⚠️  Keep your wallet.json and its password safe — together they are your private key!");
 
             Ok(())
         }
 
-        Commands::Start { port } => {
+        Commands::Start {
+            port,
+            api_bind,
+            api_port,
+            bootstrap_peers,
+            tip_selection,
+            parent_count,
+            mlock,
+            no_default_peers,
+            replica_interval,
+            network,
+            check_updates,
+        } => {
             info!("🌿 Starting Rhiza node on port {}...", port);
 
             // Load keypair
@@ -220,29 +2061,215 @@ async fn main() -> Result<()> {
                 anyhow::bail!("Node not initialized. Run 'rhiza-node init' first.");
             }
 
-            let keystore = rhiza_core::wallet::keystore::KeyStore::load(&keystore_path)?;
-            let keypair = keystore.to_keypair()?;
-            let address = Address::from_public_key(&keypair.public_key);
+            let mut node_config = NodeConfig::load_or_default(&data_path.join("config.json"))?;
+            let password_file = cli.password_file.as_deref();
+            let session_required = node_config.wallet_session.required;
+            // Unlocking per session keeps the wallet key out of memory until then,
+            // so the node identity needs a keystore of its own
+            let identity_path = data_path.join(keyfile::IDENTITY_FILE);
+            let (keypair, wallet) = if session_required || identity_path.exists() {
+                let identity = if identity_path.exists() {
+                    keyfile::unlock(&identity_path, password_file)?
+                } else {
+                    println!("Choose the password for the node identity ({}).", keyfile::IDENTITY_FILE);
+                    let identity = KeyPair::generate();
+                    keyfile::create(&identity_path, &identity, password_file)?;
+                    info!("Created a node identity apart from the wallet in {}", keyfile::IDENTITY_FILE);
+                    identity
+                };
+                let wallet = match session_required {
+                    true => None,
+                    false => Some(keyfile::unlock(&keystore_path, password_file)?),
+                };
+                (identity, wallet)
+            } else {
+                let keypair = keyfile::unlock(&keystore_path, password_file)?;
+                (keypair.clone(), Some(keypair))
+            };
+            let wallet_key = match &wallet {
+                Some(wallet) => wallet.public_key.clone(),
+                None => rhiza_core::wallet::keystore::KeyStore::load(&keystore_path)?.public_key()?,
+            };
+            let address = Address::from_public_key(&wallet_key);
 
-            let config = MeshConfig::local_test(port);
-            let mut state = NodeState::new(keypair, config);
-            state.initialize_genesis();
+            if no_default_peers {
+                node_config.use_default_peers = false;
+            }
+            let mut config = MeshConfig::local_test(port);
+            config.bootstrap_peers = node_config.bootstrap_peers(&bootstrap_peers);
+            config.backend = node_config.network_backend;
+            if config.backend == NetworkBackend::Libp2p && !cfg!(feature = "libp2p") {
+                anyhow::bail!("network_backend is libp2p, but this node was built without the libp2p feature");
+            }
+            info!("Network backend: {}", config.backend);
+            if node_config.bluetooth {
+                if !cfg!(feature = "bluetooth") {
+                    anyhow::bail!("bluetooth is on, but this node was built without the bluetooth feature");
+                }
+                config.transports.push(TransportType::Bluetooth);
+            }
+            if !config.bootstrap_peers.is_empty() {
+                info!("Bootstrap peers: {}", config.bootstrap_peers.join(", "));
+            }
+            let storage = Storage::open(&data_path.join("db"))?;
+            let mut state = NodeState::new(keypair, config, storage);
+            state.wallet_key = wallet_key;
+            state.network_id = network.unwrap_or(node_config.network_id);
+            info!("Network ID: {}", state.network_id);
+            let tip_selection = tip_selection.unwrap_or(node_config.tip_selection);
+            info!("Tip selection: {}", tip_selection);
+            state.tip_selector = tip_selection.selector();
+            let parent_count = parent_count.unwrap_or(node_config.parent_count);
+            let allowed = rhiza_core::parent_counts(state.network_id);
+            if !allowed.contains(&parent_count) {
+                anyhow::bail!(
+                    "parent_count is {}, but network {} allows {} to {}",
+                    parent_count,
+                    state.network_id,
+                    allowed.start(),
+                    allowed.end()
+                );
+            }
+            info!("Parents per transaction: {}", parent_count);
+            state.parent_count = parent_count;
+            state.finality_slo = node_config.finality_slo.clone();
+            state.gossip_limits = node_config.gossip_limits;
+            state.dandelion = Dandelion::new(node_config.dandelion);
+            state.peer_audit = PeerAudit::new(state.storage.peer_audit_store(), node_config.peer_audit.clone());
+            state.api_audit = ApiAudit::open(&data_path, node_config.api_audit.clone())?;
+            state.event_log = EventLog::open(&data_path, node_config.event_log.clone())?;
+            state.event_log.record(Incident::NodeStarted {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                network_id: state.network_id,
+            });
+            if !node_config.api_keys.is_empty() {
+                info!("API requires one of {} keys for changes and admin data", node_config.api_keys.len());
+            }
+            state.pruning = node_config.pruning.clone();
+            state.snapshots = SnapshotMaker::new(&data_path);
+            let held = state.storage.get_dtn_queue()?.unwrap_or_default().with_config(node_config.dtn);
+            if !held.is_empty() {
+                info!("📬 {} gossip messages held for absent peers", held.len());
+            }
+            state.network = Arc::new(PeerNetwork::with_selection(node_config.peer_selection).with_dtn(held));
+            let mut update_config = node_config.update.clone();
+            update_config.enabled |= check_updates;
+            state.update.enabled = update_config.enabled;
+            state.work_puzzle = node_config.work_puzzle;
+            state.policy = SpendingPolicy::load(&data_path)?;
+            if state.policy.is_some() {
+                info!("Spending policy loaded from {}", policy::POLICY_FILE);
+            }
+            state.wallet_session =
+                WalletSession::new(node_config.wallet_session, keystore_path.clone(), wallet);
+            if node_config.wallet_session.required {
+                info!("API spends wait for POST /wallet/unlock");
+            }
+            let restored = state.load_from_storage()?;
+            state.dag.set_timestamp_rules(node_config.timestamps);
+            state.voting = ConflictVoting::new(node_config.voting);
+            if let Some(stored) = state.dag.network_id().filter(|id| *id != state.network_id) {
+                anyhow::bail!(
+                    "The stored DAG belongs to network {}, not {}; use another data directory or --network {}",
+                    stored,
+                    state.network_id,
+                    stored
+                );
+            }
+            if restored > 0 || !state.dag.pruned().is_empty() {
+                info!("Restored {} transactions from storage", restored);
+            } else if state.config.bootstrap_peers.is_empty() {
+                state.initialize_genesis();
+            } else {
+                // Joining an existing network: adopt its genesis via sync
+                info!("Waiting to sync the DAG from bootstrap peers");
+            }
 
-            println!("🌿 Rhiza Node running!");
-            println!("🔑 Address: {}", address);
-            println!("📊 DAG size: {} transactions", state.dag.len());
-            println!("🌐 Listening on port {}", port);
-            println!("Press Ctrl+C to stop");
+            let report = consistency::check_and_repair(&state.storage, &mut state.dag)?;
+            if report.is_clean() {
+                info!("Storage consistency check passed: {}", report);
+            } else {
+                warn!("Storage consistency check repaired issues: {}", report);
+                state.event_log.record(Incident::StorageRepaired { report: report.to_string() });
+                for mismatch in &report.weight_mismatches {
+                    warn!(
+                        "Repaired weight of {}: stored {}, recomputed {}",
+                        mismatch.id, mismatch.stored, mismatch.expected
+                    );
+                }
+            }
 
-            // Start the REST API server
-            let shared_state = Arc::new(Mutex::new(state));
-            let api_handle = tokio::spawn(api::run_api_server(shared_state.clone(), port + 1));
+            if cli.plain_logs {
+                info!("Rhiza node running: address {}, {} transactions", address, state.dag.len());
+            } else {
+                println!("🌿 Rhiza Node running!");
+                println!("🔑 Address: {}", address);
+                println!("📊 DAG size: {} transactions", state.dag.len());
+                println!("🌐 Listening on port {}", port);
+                println!("Press Ctrl+C to stop");
+            }
 
-            info!("REST API available at http://127.0.0.1:{}", port + 1);
+            // Start the REST API server
+            let api_addr = SocketAddr::new(api_bind, api_port.unwrap_or(port + 1));
+            let bootstrap_peers = state.config.bootstrap_peers.clone();
+            let backend = state.config.backend;
+            let storage = state.storage.clone();
+            let api_access = ApiAccess::new(node_config.api_keys.clone(), state.api_audit.clone());
+            let shared_state: SharedState = Arc::new(RwLock::new(state));
+            if mlock {
+                // The key has reached its final address inside the Arc
+                match shared_state.read().await.keypair.lock_in_memory() {
+                    Ok(()) => info!("Wallet key locked in memory"),
+                    Err(e) => warn!("Could not lock wallet key in memory: {}", e),
+                }
+            }
+            let _api_handle = tokio::spawn(api::run_api_server(shared_state.clone(), api_addr, api_access));
+            let _p2p_handle = match backend {
+                #[cfg(feature = "libp2p")]
+                NetworkBackend::Libp2p => tokio::spawn(network::run_libp2p(shared_state.clone(), bootstrap_peers)),
+                _ => tokio::spawn(network::run(shared_state.clone(), port, bootstrap_peers)),
+            };
+            #[cfg(feature = "bluetooth")]
+            if shared_state.read().await.config.transports.contains(&TransportType::Bluetooth) {
+                let state = shared_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = network::run_bluetooth(state).await {
+                        warn!("Bluetooth transport stopped: {}", e);
+                    }
+                });
+            }
+            let _privacy_handle = tokio::spawn(privacy::run(shared_state.clone()));
+            let _payment_links_handle = tokio::spawn(payment_links::run(shared_state.clone()));
+            let _anti_entropy_handle = tokio::spawn(anti_entropy::run(shared_state.clone()));
+            let _webhooks_handle = tokio::spawn(webhooks::run(shared_state.clone(), node_config.webhooks));
+            if let Some(secs) = replica_interval.or(node_config.replica_interval_secs).filter(|s| *s > 0) {
+                info!("Analytics replica refreshed every {} s in {}", secs, replica::REPLICA_DIR);
+                tokio::spawn(replica::run(storage, data_path.clone(), std::time::Duration::from_secs(secs)));
+            }
+            if update_config.enabled {
+                tokio::spawn(updater::run(shared_state.clone(), update_config, data_path.clone()));
+            }
+            if node_config.pruning.enabled {
+                tokio::spawn(pruning::run(shared_state.clone(), node_config.pruning.clone()));
+            }
+            if node_config.voting.enabled {
+                tokio::spawn(voting::run(shared_state.clone(), node_config.voting));
+            }
+            if node_config.weight_audit.enabled {
+                tokio::spawn(weight_audit::run(shared_state.clone(), node_config.weight_audit));
+            }
+            let _dtn_handle = tokio::spawn(network::save_held(shared_state.clone()));
 
-            // Wait for shutdown signal
-            tokio::signal::ctrl_c().await?;
+            // Wait for Ctrl+C, SIGTERM under a container runtime, or the admin API
+            let stop = shared_state.read().await.shutdown.clone();
+            shutdown_signal(stop).await?;
             info!("Shutting down...");
+            let state = shared_state.read().await;
+            if let Some(held) = state.network.held_to_save(None) {
+                if let Err(e) = state.storage.put_dtn_queue(&held) {
+                    warn!("Failed to save gossip held for absent peers: {}", e);
+                }
+            }
 
             Ok(())
         }
@@ -255,15 +2282,85 @@ async fn main() -> Result<()> {
             }
 
             let keystore = rhiza_core::wallet::keystore::KeyStore::load(&keystore_path)?;
-            let keypair = keystore.to_keypair()?;
-            let address = Address::from_public_key(&keypair.public_key);
+            let address = Address::from_public_key(&keystore.public_key()?);
 
             println!("🌿 Rhiza Node Status");
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
             println!("🔑 Address: {}", address);
             println!("📁 Data: {}", data_dir);
+            if !keystore.is_encrypted() {
+                println!("⚠️  Wallet is not encrypted; 'rhiza-node start' will offer to encrypt it");
+            }
 
             Ok(())
         }
+
+        Commands::Doctor {
+            port,
+            api_bind,
+            api_port,
+            bootstrap_peers,
+            no_default_peers,
+            network,
+        } => {
+            let options = doctor::DoctorOptions {
+                port,
+                api_bind,
+                api_port,
+                bootstrap_peers,
+                no_default_peers,
+                network,
+                password_file: cli.password_file.clone(),
+            };
+            doctor::run(&data_path, options).await
+        }
+
+        Commands::Identity { action } => match action {
+            IdentityCommands::Rotate { discard_unclaimed } => {
+                identity::rotate(&data_path, cli.password_file.as_deref(), discard_unclaimed)
+            }
+        },
+
+        Commands::Snapshot { action } => match action {
+            SnapshotCommands::Create { no_sign, publish, api_url, api_key } => {
+                snapshot::create(&api_url, api_key.as_deref(), !no_sign, publish).await
+            }
+            SnapshotCommands::Export { file } => snapshot::export(&data_path, &file),
+            SnapshotCommands::Sign { file } => snapshot::sign(&data_path, cli.password_file.as_deref(), &file),
+            SnapshotCommands::Import { source } => {
+                let node_config = NodeConfig::load_or_default(&data_path.join("config.json"))?;
+                snapshot::import(&data_path, &source, &node_config).await
+            }
+        },
+
+        Commands::Bundle { action } => match action {
+            BundleCommands::Export { since_depth, file } => {
+                bundle::export(&data_path, cli.password_file.as_deref(), since_depth, &file)
+            }
+            BundleCommands::Import { file } => {
+                let node_config = NodeConfig::load_or_default(&data_path.join("config.json"))?;
+                bundle::import(&data_path, &file, &node_config)
+            }
+        },
+
+        Commands::Release { action } => match action {
+            ReleaseCommands::Sign { file } => updater::sign(&data_path, cli.password_file.as_deref(), &file),
+        },
+
+        Commands::ApiKey { action } => match action {
+            ApiKeyCommands::Add { id } => api_audit::add_key(&data_path, &id),
+            ApiKeyCommands::List => api_audit::list_keys(&data_path),
+            ApiKeyCommands::Revoke { id } => api_audit::revoke_key(&data_path, &id),
+        },
+
+        Commands::Events { action } => match action {
+            EventsCommands::Show { since, kind, limit, json } => {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                let since = event_log::parse_since(&since, now)?;
+                event_log::show(&data_path, since, kind.as_deref(), limit, json)
+            }
+        },
+
+        Commands::Export { read_only, out } => replica::export(&data_path, read_only, out.as_deref()),
     }
 }
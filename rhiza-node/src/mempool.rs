@@ -0,0 +1,213 @@
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::transaction::Transaction;
+use rhiza_core::dag::vertex::Dag;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Most transactions the pool holds while they wait for finality
+pub const MAX_PENDING: usize = 10_000;
+
+/// Most pending transactions from a single sender
+pub const MAX_PENDING_PER_SENDER: usize = 100;
+
+/// How long a transaction stays in the pool without becoming final
+///
+/// It stays in the DAG after that; it just stops counting against the
+/// limits, so a sender with a stuck transaction isn't locked out.
+pub const PENDING_TTL_MS: u64 = 60 * 60 * 1_000;
+
+/// When a pending transaction arrived, from whom, and what it offers
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub sender: PublicKey,
+    pub received_ms: u64,
+    /// Fee plus relay tip
+    pub priority: u128,
+}
+
+/// What a new transaction should offer (fee plus relay tip), from what the
+/// pool holds now
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FeeEstimate {
+    pub pending: usize,
+    pub capacity: usize,
+    /// Least that gets a transaction into the pool: 0 unless it is full
+    pub minimum: u128,
+    /// Median offer among pending transactions
+    pub median: u128,
+    /// Ninetieth percentile of offers among pending transactions
+    pub fast: u128,
+}
+
+/// Transactions in the DAG that aren't final yet
+///
+/// Every transaction enters the DAG as soon as it validates, since that is
+/// how it collects the weight that finalizes it; the pool tracks the ones
+/// still on their way and decides which new ones to take on. Transactions
+/// leave it when they become final, lose a double-spend conflict or expire.
+///
+/// When the pool is full a transaction has to offer more than the cheapest
+/// one pending, which then makes room: it stays in the DAG, the pool just
+/// stops holding it against the limits.
+#[derive(Debug, Default)]
+pub struct Mempool {
+    entries: HashMap<Hash, PendingEntry>,
+    per_sender: HashMap<PublicKey, usize>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pool of the DAG's unfinalized transactions, as of a restart
+    pub fn from_dag(dag: &Dag, now_ms: u64) -> Self {
+        let mut pool = Mempool::new();
+        for vertex in dag.iter() {
+            if !vertex.is_final && !dag.conflicts().is_rejected(&vertex.id()) {
+                pool.add(&vertex.transaction, now_ms);
+            }
+        }
+        pool
+    }
+
+    /// Check whether a transaction may enter the pool
+    ///
+    /// Transactions built or submitted here (`local`) must also not
+    /// double-spend against what is already pending: the DAG would take them
+    /// and leave consensus to pick a side, but there is no reason to start
+    /// that fight ourselves. Gossiped ones skip that check so this node sees
+    /// the same conflicts as its peers. A replacement may spend what the
+    /// transfer it replaces does.
+    pub fn admit(&self, tx: &Transaction, dag: &Dag, local: bool) -> Result<(), String> {
+        if self.entries.contains_key(&tx.id) {
+            return Ok(());
+        }
+        if self.entries.len() >= MAX_PENDING {
+            let lowest = self.lowest().map_or(0, |(_, entry)| entry.priority);
+            if tx.data.priority() <= lowest {
+                return Err(format!(
+                    "Pending pool is full ({} transactions); offer more than {} in fee and relay tip",
+                    MAX_PENDING, lowest
+                ));
+            }
+        }
+        if self.per_sender.get(&tx.data.sender).copied().unwrap_or(0) >= MAX_PENDING_PER_SENDER {
+            return Err(format!(
+                "Sender already has {} pending transactions; wait for some to become final",
+                MAX_PENDING_PER_SENDER
+            ));
+        }
+
+        if local {
+            let replaced = Some(tx)
+                .filter(|tx| tx.data.tx_type.is_payment())
+                .and_then(|tx| dag.conflicts().current(&tx.data.sender, tx.data.nonce))
+                .filter(|id| !dag.conflicts().is_rejected(id))
+                .and_then(|id| dag.get(&id))
+                .map(|v| &v.transaction);
+            let mut payers = vec![&tx.data.sender];
+            payers.extend(tx.data.fee_payer.as_ref());
+            for payer in payers {
+                let debit = tx.data.debit_for(payer);
+                let refund = replaced.map_or(0, |r| r.data.debit_for(payer));
+                let available = dag.get_balance(payer) as u128 + refund;
+                if debit > available {
+                    return Err(format!(
+                        "Conflicts with pending transactions: {} can spend {}, this needs {}",
+                        payer, available, debit
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Track a transaction that was just inserted into the DAG, making room
+    /// by dropping the cheapest one pending if the pool is full
+    pub fn add(&mut self, tx: &Transaction, now_ms: u64) {
+        if self.entries.contains_key(&tx.id) {
+            return;
+        }
+        if self.entries.len() >= MAX_PENDING {
+            if let Some(id) = self.lowest().map(|(id, _)| id) {
+                if let Some(entry) = self.entries.remove(&id) {
+                    Self::release(&mut self.per_sender, &entry.sender);
+                }
+            }
+        }
+        let entry = PendingEntry {
+            sender: tx.data.sender.clone(),
+            received_ms: now_ms,
+            priority: tx.data.priority(),
+        };
+        self.entries.insert(tx.id, entry);
+        *self.per_sender.entry(tx.data.sender.clone()).or_default() += 1;
+    }
+
+    /// Drop transactions that became final, lost a conflict or expired
+    pub fn prune(&mut self, dag: &Dag, now_ms: u64) {
+        let per_sender = &mut self.per_sender;
+        self.entries.retain(|id, entry| {
+            let keep = dag.get(id).is_some_and(|v| !v.is_final)
+                && !dag.conflicts().is_rejected(id)
+                && now_ms.saturating_sub(entry.received_ms) < PENDING_TTL_MS;
+            if !keep {
+                Self::release(per_sender, &entry.sender);
+            }
+            keep
+        });
+    }
+
+    fn release(per_sender: &mut HashMap<PublicKey, usize>, sender: &PublicKey) {
+        if let Some(count) = per_sender.get_mut(sender) {
+            *count -= 1;
+            if *count == 0 {
+                per_sender.remove(sender);
+            }
+        }
+    }
+
+    /// The pending transaction that offers least, the newest among equals
+    fn lowest(&self) -> Option<(Hash, &PendingEntry)> {
+        self.entries
+            .iter()
+            .min_by_key(|(id, entry)| (entry.priority, Reverse(entry.received_ms), **id))
+            .map(|(id, entry)| (*id, entry))
+    }
+
+    /// What to offer for a new transaction, going by what is pending
+    pub fn fee_estimate(&self) -> FeeEstimate {
+        let mut offers: Vec<u128> = self.entries.values().map(|entry| entry.priority).collect();
+        offers.sort_unstable();
+        let percentile = |p: usize| offers.get(offers.len() * p / 100).copied().unwrap_or(0);
+        let minimum = if offers.len() >= MAX_PENDING {
+            offers.first().map_or(0, |lowest| lowest + 1)
+        } else {
+            0
+        };
+        FeeEstimate {
+            pending: offers.len(),
+            capacity: MAX_PENDING,
+            minimum,
+            median: percentile(50),
+            fast: percentile(90),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// How long the oldest pending transaction has been waiting
+    pub fn oldest_age_ms(&self, now_ms: u64) -> Option<u64> {
+        self.entries.values().map(|e| now_ms.saturating_sub(e.received_ms)).max()
+    }
+
+    /// Pending transactions, oldest first
+    pub fn entries(&self) -> Vec<(Hash, &PendingEntry)> {
+        let mut entries: Vec<_> = self.entries.iter().map(|(id, entry)| (*id, entry)).collect();
+        entries.sort_by_key(|(id, entry)| (entry.received_ms, *id));
+        entries
+    }
+}
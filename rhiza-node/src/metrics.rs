@@ -0,0 +1,100 @@
+use crate::mempool::PENDING_TTL_MS;
+use rhiza_core::consensus::{LatencyHistogram, LatencyPercentiles};
+use rhiza_core::crypto::Hash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most transactions tracked on their way to finality
+const MAX_TRACKED: usize = 10_000;
+
+/// Time from first seeing a transaction to its finality
+///
+/// Only transactions that reach this node as they are made (built here,
+/// submitted or gossiped) are measured; history pulled in by a sync was
+/// final long before we saw it.
+#[derive(Debug, Default)]
+pub struct FinalityMetrics {
+    first_seen: HashMap<Hash, u64>,
+    latency: LatencyHistogram,
+}
+
+impl FinalityMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the clock on a transaction
+    pub fn seen(&mut self, id: Hash, now_ms: u64) {
+        if self.first_seen.len() >= MAX_TRACKED {
+            // Ones pending this long are stuck; the pool gave up on them too
+            self.first_seen.retain(|_, seen| now_ms.saturating_sub(*seen) < PENDING_TTL_MS);
+            if self.first_seen.len() >= MAX_TRACKED {
+                return;
+            }
+        }
+        self.first_seen.entry(id).or_insert(now_ms);
+    }
+
+    /// Stop the clock on transactions that just became final
+    pub fn finalized(&mut self, ids: &[Hash], now_ms: u64) {
+        for id in ids {
+            if let Some(seen) = self.first_seen.remove(id) {
+                self.latency.record(now_ms.saturating_sub(seen));
+            }
+        }
+    }
+
+    pub fn latency(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+}
+
+/// Finality latency objectives; `/health` reports `degraded` while one is missed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FinalitySlo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p50_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p95_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p99_ms: Option<u64>,
+    /// Recent samples needed before the percentiles are judged
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+}
+
+fn default_min_samples() -> usize {
+    20
+}
+
+impl FinalitySlo {
+    /// Objectives currently missed, as human-readable alerts
+    ///
+    /// A transaction still pending past the loosest objective counts too:
+    /// when nothing finalizes, no new samples arrive to move the percentiles.
+    pub fn breaches(&self, latency: &LatencyHistogram, oldest_pending_ms: Option<u64>) -> Vec<String> {
+        let mut alerts = Vec::new();
+        if let Some(p) = latency.percentiles().filter(|p| p.samples >= self.min_samples) {
+            for (name, actual, limit) in self.objectives(&p) {
+                if actual > limit {
+                    alerts.push(format!("finality {} is {} ms, above the {} ms objective", name, actual, limit));
+                }
+            }
+        }
+
+        let loosest = [self.p50_ms, self.p95_ms, self.p99_ms].into_iter().flatten().max();
+        if let (Some(limit), Some(waiting)) = (loosest, oldest_pending_ms) {
+            if waiting > limit {
+                alerts.push(format!("a transaction has been waiting {} ms for finality", waiting));
+            }
+        }
+        alerts
+    }
+
+    fn objectives(&self, p: &LatencyPercentiles) -> Vec<(&'static str, u64, u64)> {
+        [("p50", p.p50_ms, self.p50_ms), ("p95", p.p95_ms, self.p95_ms), ("p99", p.p99_ms, self.p99_ms)]
+            .into_iter()
+            .filter_map(|(name, actual, limit)| limit.map(|limit| (name, actual, limit)))
+            .collect()
+    }
+}
@@ -0,0 +1,998 @@
+use crate::audit::{Direction, PeerAuditEntry, PeerAuditEvent};
+use crate::events::NodeEvent;
+use crate::SharedState;
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::network::buckets::{AddressBucket, AdmissionError, PeerSelection, PeerSlot};
+use rhiza_core::network::dtn::{DtnQueue, HeldMessage};
+use rhiza_core::network::gossip::GossipMessage;
+use rhiza_core::network::mesh::TransportType;
+use rhiza_core::network::peer::PeerId;
+use rhiza_core::network::reputation::Behavior;
+use rhiza_core::network::transport::{Handshake, SecureSession, TransportError};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, info, warn};
+
+/// Time allowed for a peer to complete the Noise handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often disconnected bootstrap peers are redialed
+const REDIAL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often gossip held for absent peers is expired and saved
+const DTN_SAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Where a connected peer was reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerAddress {
+    Ip(SocketAddr),
+    /// Device address of a peer within Bluetooth range
+    #[cfg_attr(not(feature = "bluetooth"), allow(dead_code))]
+    Bluetooth(String),
+}
+
+impl PeerAddress {
+    /// The socket address, for peers reached over IP
+    pub fn ip(&self) -> Option<SocketAddr> {
+        match self {
+            PeerAddress::Ip(address) => Some(*address),
+            PeerAddress::Bluetooth(_) => None,
+        }
+    }
+
+    pub fn transport(&self) -> TransportType {
+        match self {
+            PeerAddress::Ip(_) => TransportType::Tcp,
+            PeerAddress::Bluetooth(_) => TransportType::Bluetooth,
+        }
+    }
+
+    fn bucket(&self) -> AddressBucket {
+        match self {
+            PeerAddress::Ip(address) => AddressBucket::of(TransportType::Tcp, address),
+            PeerAddress::Bluetooth(_) => AddressBucket::radio(TransportType::Bluetooth),
+        }
+    }
+}
+
+impl std::fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerAddress::Ip(address) => address.fmt(f),
+            PeerAddress::Bluetooth(address) => address.fmt(f),
+        }
+    }
+}
+
+/// A live, authenticated peer connection
+struct ConnectedPeer {
+    address: PeerAddress,
+    bucket: AddressBucket,
+    inbound: bool,
+    connected_since: u64,
+    outbox: Outbox,
+    /// Notified when an inbound peer takes over the connection's slot
+    eviction: Arc<Notify>,
+}
+
+/// Outbound queues of one connection, one per logical stream
+///
+/// Gossip and bulk DAG sync share the TCP connection but not a queue: the
+/// writer always drains gossip first, so a backlog of sync pages can't hold
+/// up forwarding a fresh payment by more than the frame being written.
+#[derive(Clone)]
+struct Outbox {
+    gossip: mpsc::UnboundedSender<GossipMessage>,
+    sync: mpsc::UnboundedSender<GossipMessage>,
+}
+
+/// Receiving ends of an [`Outbox`]
+struct Inbox {
+    gossip: mpsc::UnboundedReceiver<GossipMessage>,
+    sync: mpsc::UnboundedReceiver<GossipMessage>,
+    /// Gossip taken off the queue but not handed out yet
+    backlog: Vec<GossipMessage>,
+}
+
+impl Outbox {
+    fn new() -> (Self, Inbox) {
+        let (gossip, gossip_rx) = mpsc::unbounded_channel();
+        let (sync, sync_rx) = mpsc::unbounded_channel();
+        let inbox = Inbox {
+            gossip: gossip_rx,
+            sync: sync_rx,
+            backlog: Vec::new(),
+        };
+        (Outbox { gossip, sync }, inbox)
+    }
+
+    /// Queue a message on the stream it belongs to, false if the connection is gone
+    fn send(&self, msg: GossipMessage) -> bool {
+        let lane = if msg.is_sync() { &self.sync } else { &self.gossip };
+        lane.send(msg).is_ok()
+    }
+}
+
+impl Inbox {
+    /// Next message to send, None once the connection is gone
+    ///
+    /// Gossip always goes ahead of sync traffic, and among gossip that piled
+    /// up while the last frame was written the transaction offering the
+    /// highest fee and relay tip goes first (the oldest among equals).
+    /// Cancel-safe: nothing taken off a queue is lost if the future is dropped.
+    async fn recv(&mut self) -> Option<GossipMessage> {
+        while let Ok(msg) = self.gossip.try_recv() {
+            self.backlog.push(msg);
+        }
+        let best = (0..self.backlog.len()).max_by_key(|i| (self.backlog[*i].priority(), Reverse(*i)));
+        if let Some(best) = best {
+            return Some(self.backlog.remove(best));
+        }
+        tokio::select! {
+            biased;
+            Some(msg) = self.gossip.recv() => Some(msg),
+            Some(msg) = self.sync.recv() => Some(msg),
+            else => None,
+        }
+    }
+}
+
+/// Summary of a connected peer for the API
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PeerSummary {
+    pub peer_id: String,
+    pub address: String,
+    pub direction: Direction,
+    /// Transport and network group the connection is counted in
+    pub bucket: String,
+    /// Remembered across restarts and never evicted
+    pub anchor: bool,
+    pub connected_since: u64,
+}
+
+/// Gossip held for an absent peer, for the API
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HeldSummary {
+    pub peer_id: String,
+    /// When the peer disconnected (unix ms)
+    pub away_since: u64,
+    pub messages: usize,
+    pub bytes: usize,
+}
+
+/// Outcome of registering an authenticated connection
+enum Registration {
+    /// Registered, having evicted this inbound peer to make room
+    Registered(Option<PeerId>),
+    /// Already connected to the peer
+    Duplicate,
+    /// Turned away by the peer selection limits
+    Refused(AdmissionError),
+}
+
+/// Registry of connected peers and their outbound queues
+pub struct PeerNetwork {
+    peers: Mutex<HashMap<PeerId, ConnectedPeer>>,
+    /// Bootstrap addresses with a live outbound connection attempt
+    dialing: Mutex<HashSet<String>>,
+    selection: PeerSelection,
+    /// Gossip held for peers that disconnected, until they return
+    held: Mutex<DtnQueue>,
+}
+
+impl PeerNetwork {
+    pub fn new() -> Self {
+        Self::with_selection(PeerSelection::default())
+    }
+
+    pub fn with_selection(selection: PeerSelection) -> Self {
+        PeerNetwork {
+            peers: Mutex::new(HashMap::new()),
+            dialing: Mutex::new(HashSet::new()),
+            selection,
+            held: Mutex::new(DtnQueue::default()),
+        }
+    }
+
+    /// Hold gossip for absent peers in `queue`
+    pub fn with_dtn(self, queue: DtnQueue) -> Self {
+        PeerNetwork {
+            held: Mutex::new(queue),
+            ..self
+        }
+    }
+
+    pub fn selection(&self) -> &PeerSelection {
+        &self.selection
+    }
+
+    /// Queue a message for every connected peer, and hold it for the
+    /// absent ones; returns how many peers it was queued for
+    pub fn broadcast(&self, msg: &GossipMessage) -> usize {
+        let sent = {
+            let peers = self.peers.lock().unwrap();
+            for peer in peers.values() {
+                peer.outbox.send(msg.clone());
+            }
+            peers.len()
+        };
+        self.held.lock().unwrap().hold(msg, now_ms());
+        sent
+    }
+
+    /// Queue a message for every connected peer except `origin`, and hold
+    /// it for the absent ones; returns how many peers it was queued for
+    pub fn broadcast_except(&self, msg: &GossipMessage, origin: &PeerId) -> usize {
+        let mut sent = 0;
+        for (id, peer) in self.peers.lock().unwrap().iter() {
+            if id != origin {
+                peer.outbox.send(msg.clone());
+                sent += 1;
+            }
+        }
+        self.held.lock().unwrap().hold(msg, now_ms());
+        sent
+    }
+
+    /// Gossip held for a peer while it was away, oldest first; it is no
+    /// longer held
+    fn take_held(&self, peer: &PeerId) -> Vec<HeldMessage> {
+        self.held.lock().unwrap().peer_returned(peer, now_ms())
+    }
+
+    /// Absent peers gossip is held for
+    pub fn held(&self) -> Vec<HeldSummary> {
+        self.held
+            .lock()
+            .unwrap()
+            .holds()
+            .map(|(id, hold)| HeldSummary {
+                peer_id: id.public_key.to_string(),
+                away_since: hold.away_since,
+                messages: hold.messages.len(),
+                bytes: hold.bytes,
+            })
+            .collect()
+    }
+
+    /// Expire held gossip, then copy it for saving unless it is unchanged
+    /// since the copy saved at `saved` changes
+    pub fn held_to_save(&self, saved: Option<u64>) -> Option<DtnQueue> {
+        let mut held = self.held.lock().unwrap();
+        held.expire(now_ms());
+        (saved != Some(held.changes())).then(|| held.clone())
+    }
+
+    /// Queue a message for one peer, false if it isn't connected
+    pub fn send_to(&self, peer: &PeerId, msg: GossipMessage) -> bool {
+        match self.peers.lock().unwrap().get(peer) {
+            Some(connected) => connected.outbox.send(msg),
+            None => false,
+        }
+    }
+
+    /// Number of connected peers
+    pub fn peer_count(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+
+    /// IP address a connected peer is connected from; `None` for peers
+    /// reached another way
+    pub fn address_of(&self, id: &PeerId) -> Option<SocketAddr> {
+        self.peers.lock().unwrap().get(id).and_then(|peer| peer.address.ip())
+    }
+
+    /// Transport a connected peer is connected over
+    pub fn transport_of(&self, id: &PeerId) -> Option<TransportType> {
+        self.peers.lock().unwrap().get(id).map(|peer| peer.address.transport())
+    }
+
+    /// Ids of the connected peers
+    pub fn peer_ids(&self) -> Vec<PeerId> {
+        self.peers.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Connected peers
+    pub fn peers(&self) -> Vec<(PeerId, PeerSummary)> {
+        let peers = self.peers.lock().unwrap();
+        let anchors = self.selection.anchors(&slots(&peers), now_ms());
+        peers
+            .iter()
+            .map(|(id, peer)| {
+                let summary = PeerSummary {
+                    peer_id: id.public_key.to_string(),
+                    address: peer.address.to_string(),
+                    direction: Direction::of(!peer.inbound),
+                    bucket: peer.bucket.to_string(),
+                    anchor: anchors.contains(id),
+                    connected_since: peer.connected_since,
+                };
+                (id.clone(), summary)
+            })
+            .collect()
+    }
+
+    /// Addresses of the outbound peers that currently qualify as anchors,
+    /// leaving out those that can't be redialed by address
+    pub fn anchor_addresses(&self) -> Vec<String> {
+        let peers = self.peers.lock().unwrap();
+        self.selection
+            .anchors(&slots(&peers), now_ms())
+            .iter()
+            .filter_map(|id| peers.get(id)?.address.ip())
+            .map(|address| address.to_string())
+            .collect()
+    }
+
+    fn register(
+        &self,
+        id: PeerId,
+        address: PeerAddress,
+        inbound: bool,
+        outbox: Outbox,
+        eviction: Arc<Notify>,
+    ) -> Registration {
+        let mut peers = self.peers.lock().unwrap();
+        if peers.contains_key(&id) {
+            return Registration::Duplicate;
+        }
+        let bucket = address.bucket();
+        let mut evicted = None;
+        if inbound {
+            match self.selection.admit_inbound(&slots(&peers), &bucket) {
+                Ok(Some(other)) => {
+                    if let Some(peer) = peers.remove(&other) {
+                        peer.eviction.notify_one();
+                    }
+                    evicted = Some(other);
+                }
+                Ok(None) => {}
+                Err(e) => return Registration::Refused(e),
+            }
+        }
+        peers.insert(
+            id,
+            ConnectedPeer {
+                address,
+                bucket,
+                inbound,
+                connected_since: now_ms(),
+                outbox,
+                eviction,
+            },
+        );
+        Registration::Registered(evicted)
+    }
+
+    /// Drop a peer's connection; no gossip is held for it afterwards
+    pub fn disconnect(&self, id: &PeerId) -> bool {
+        match self.peers.lock().unwrap().remove(id) {
+            Some(peer) => {
+                peer.eviction.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a connection, unless it was evicted and the peer has already
+    /// reconnected in its place
+    fn unregister(&self, id: &PeerId, eviction: &Arc<Notify>) {
+        let mut peers = self.peers.lock().unwrap();
+        if peers.get(id).is_some_and(|peer| Arc::ptr_eq(&peer.eviction, eviction)) {
+            peers.remove(id);
+            self.held.lock().unwrap().peer_left(id, now_ms());
+        }
+    }
+}
+
+fn slots(peers: &HashMap<PeerId, ConnectedPeer>) -> Vec<PeerSlot> {
+    peers
+        .iter()
+        .map(|(id, peer)| PeerSlot {
+            id: id.clone(),
+            bucket: peer.bucket,
+            inbound: peer.inbound,
+            connected_since: peer.connected_since,
+        })
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+impl Default for PeerNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Expire gossip held for absent peers and save it whenever it changed, so
+/// a restart doesn't lose what they are owed
+pub async fn save_held(state: SharedState) {
+    let (network, storage) = {
+        let state = state.read().await;
+        (state.network.clone(), state.storage.clone())
+    };
+    let mut saved = None;
+    let mut interval = tokio::time::interval(DTN_SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(held) = network.held_to_save(saved) else { continue };
+        match storage.put_dtn_queue(&held) {
+            Ok(()) => saved = Some(held.changes()),
+            Err(e) => warn!("Failed to save gossip held for absent peers: {}", e),
+        }
+    }
+}
+
+/// Accept inbound peers and keep anchor and bootstrap peers dialed
+///
+/// Anchors saved by the last run are dialed first, so the node is back in
+/// touch with peers it trusted before inbound connections can crowd in.
+/// Whenever the set of anchors changes it is saved again.
+pub async fn run(state: SharedState, port: u16, bootstrap_peers: Vec<String>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("🔗 P2P listening on 0.0.0.0:{}", port);
+
+    let (network, storage) = {
+        let state = state.read().await;
+        (state.network.clone(), state.storage.clone())
+    };
+    let mut anchors = storage.get_anchor_peers().unwrap_or_else(|e| {
+        warn!("Could not load anchor peers: {}", e);
+        Vec::new()
+    });
+    if !anchors.is_empty() {
+        info!("⚓ Redialing anchor peers: {}", anchors.join(", "));
+    }
+    let dial_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            for addr in anchors.iter().chain(bootstrap_peers.iter().filter(|addr| !anchors.contains(addr))) {
+                dial(dial_state.clone(), addr.clone()).await;
+            }
+            tokio::time::sleep(REDIAL_INTERVAL).await;
+
+            // Keep the old anchors until connections have lasted long enough
+            // to replace them
+            let current = network.anchor_addresses();
+            if !current.is_empty() && current != anchors {
+                info!("⚓ Anchor peers: {}", current.join(", "));
+                if let Err(e) = storage.put_anchor_peers(&current) {
+                    warn!("Could not save anchor peers: {}", e);
+                }
+                anchors = current;
+            }
+        }
+    });
+
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(state, stream, addr, false).await {
+                debug!("Inbound connection from {} closed: {}", addr, e);
+            }
+        });
+    }
+}
+
+/// Dial a peer unless a connection to that address is already in progress
+async fn dial(state: SharedState, addr: String) {
+    let network = state.read().await.network.clone();
+    if !network.dialing.lock().unwrap().insert(addr.clone()) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match TcpStream::connect(&addr).await {
+            Ok(stream) => {
+                let remote = stream.peer_addr();
+                if let Ok(remote) = remote {
+                    if let Err(e) = handle_connection(state, stream, remote, true).await {
+                        debug!("Connection to {} closed: {}", addr, e);
+                    }
+                }
+            }
+            Err(e) => debug!("Failed to dial {}: {}", addr, e),
+        }
+        network.dialing.lock().unwrap().remove(&addr);
+    });
+}
+
+async fn handle_connection(
+    state: SharedState,
+    stream: TcpStream,
+    address: SocketAddr,
+    initiator: bool,
+) -> anyhow::Result<()> {
+    {
+        let state = state.read().await;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if state.reputation.is_address_banned(address.ip(), now) {
+            state.peer_audit.record(
+                PeerAuditEntry::new(PeerAuditEvent::Refused)
+                    .address(address)
+                    .direction(Direction::of(initiator))
+                    .reason("address is banned"),
+            );
+            anyhow::bail!("{} is banned", address.ip());
+        }
+    }
+    let (reader, writer) = stream.into_split();
+    run_session(state, reader, writer, PeerAddress::Ip(address), initiator).await
+}
+
+/// Authenticate a peer over an open byte stream, whatever carries it, and
+/// exchange gossip with it until the stream closes
+async fn run_session<R, W>(
+    state: SharedState,
+    mut reader: R,
+    mut writer: W,
+    address: PeerAddress,
+    initiator: bool,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let direction = Direction::of(initiator);
+    let audit_entry = |event| {
+        PeerAuditEntry::new(event)
+            .address(&address)
+            .transport(address.transport())
+            .direction(direction)
+    };
+    let (keypair, network_id, network, audit) = {
+        let state = state.read().await;
+        (state.keypair.clone(), state.network_id, state.network.clone(), state.peer_audit.clone())
+    };
+
+    let session = match tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        handshake(&keypair, network_id, &mut reader, &mut writer, initiator),
+    )
+    .await
+    {
+        Ok(Ok(session)) => session,
+        Ok(Err(e)) => {
+            audit.record(audit_entry(PeerAuditEvent::Refused).reason(format!("handshake failed: {}", e)));
+            return Err(e);
+        }
+        Err(e) => {
+            audit.record(audit_entry(PeerAuditEvent::Refused).reason("handshake timed out"));
+            return Err(e.into());
+        }
+    };
+    let peer = session.remote_peer().clone();
+
+    if peer.public_key == keypair.public_key {
+        anyhow::bail!("refusing connection to self");
+    }
+    if state.read().await.is_banned(&peer) {
+        audit.record(audit_entry(PeerAuditEvent::Refused).peer(&peer).reason("peer is banned"));
+        anyhow::bail!("{} is banned", peer);
+    }
+
+    let (outbox, inbox) = Outbox::new();
+    let eviction = Arc::new(Notify::new());
+    match network.register(peer.clone(), address.clone(), !initiator, outbox.clone(), eviction.clone()) {
+        Registration::Registered(Some(evicted)) => info!("Evicted inbound {} to make room for {}", evicted, peer),
+        Registration::Registered(None) => {}
+        Registration::Duplicate => anyhow::bail!("already connected to {}", peer),
+        Registration::Refused(e) => {
+            audit.record(audit_entry(PeerAuditEvent::Refused).peer(&peer).reason(e.to_string()));
+            anyhow::bail!("refusing {}: {}", peer, e);
+        }
+    }
+    info!("🤝 Peer connected: {} ({})", peer, address);
+    audit.record(audit_entry(PeerAuditEvent::Connected).peer(&peer));
+    state.read().await.events.publish(NodeEvent::PeerConnected {
+        peer_id: peer.public_key.to_string(),
+        address: address.to_string(),
+    });
+
+    let session = Arc::new(Mutex::new(session));
+    let writer_task = tokio::spawn(write_loop(session.clone(), writer, inbox));
+
+    // Let the peer know where we are so it can decide whether to sync, and
+    // which identities have moved
+    {
+        let state = state.read().await;
+        outbox.send(state.tip_announce());
+        for rotation in state.identity_rotation_messages() {
+            outbox.send(rotation);
+        }
+    }
+    // Then what it missed while away
+    let delivered: Vec<GossipMessage> = network
+        .take_held(&peer)
+        .into_iter()
+        .map(|held| held.message)
+        .filter(|message| outbox.send(message.clone()))
+        .collect();
+    if !delivered.is_empty() {
+        state.write().await.held_delivered(&peer, &delivered);
+    }
+
+    let result = tokio::select! {
+        result = read_loop(&state, &session, &mut reader, &peer, &outbox) => result,
+        _ = eviction.notified() => Err(anyhow::anyhow!("dropped by this node (evicted or banned)")),
+    };
+
+    network.unregister(&peer, &eviction);
+    state.write().await.peer_disconnected(&peer);
+    writer_task.abort();
+    info!("👋 Peer disconnected: {}", peer);
+    let reason = match &result {
+        Ok(()) => "closed".to_string(),
+        Err(e) => e.to_string(),
+    };
+    audit.record(audit_entry(PeerAuditEvent::Disconnected).peer(&peer).reason(reason));
+    result
+}
+
+/// Connect to Rhiza devices within Bluetooth range and run the same
+/// sessions with them as with TCP peers
+///
+/// The node is the central: it scans for peripherals advertising the Rhiza
+/// service, such as phone wallets, and dials each one it finds. Devices that
+/// were seen but not reported, or that dropped out of range and came back,
+/// are picked up on the next rescan.
+#[cfg(feature = "bluetooth")]
+pub async fn run_bluetooth(state: SharedState) -> anyhow::Result<()> {
+    use futures_util::StreamExt;
+    use rhiza_core::network::bluetooth::BluetoothTransport;
+
+    let transport = Arc::new(BluetoothTransport::new().await?);
+    let mut discovered = transport.discover().await?;
+    info!("📶 Scanning for Bluetooth peers");
+    let mut rescan = tokio::time::interval(REDIAL_INTERVAL);
+    loop {
+        let found = tokio::select! {
+            Some(id) = discovered.next() => vec![id],
+            _ = rescan.tick() => transport.nearby().await.unwrap_or_else(|e| {
+                warn!("Bluetooth scan failed: {}", e);
+                Vec::new()
+            }),
+        };
+        for id in found {
+            dial_bluetooth(state.clone(), transport.clone(), id).await;
+        }
+    }
+}
+
+/// Connect to a Bluetooth device unless a link to it is already open or
+/// being set up
+#[cfg(feature = "bluetooth")]
+async fn dial_bluetooth(
+    state: SharedState,
+    transport: Arc<rhiza_core::network::bluetooth::BluetoothTransport>,
+    id: rhiza_core::network::bluetooth::PeripheralId,
+) {
+    let network = state.read().await.network.clone();
+    let key = format!("bluetooth:{}", id);
+    if !network.dialing.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match transport.connect(&id).await {
+            Ok(link) => {
+                let (reader, writer) = tokio::io::split(link.stream);
+                let address = PeerAddress::Bluetooth(link.address);
+                if let Err(e) = run_session(state, reader, writer, address.clone(), true).await {
+                    debug!("Bluetooth link to {} closed: {}", address, e);
+                }
+            }
+            Err(e) => debug!("Failed to connect to {}: {}", id, e),
+        }
+        network.dialing.lock().unwrap().remove(&key);
+    });
+}
+
+/// Run the node's peer-to-peer side over libp2p instead of the built-in
+/// transport
+///
+/// Peers are registered with the same [`PeerNetwork`] as native
+/// connections, so the rest of the node can't tell them apart. Whatever is
+/// queued for a peer is handed to the backend, which floods broadcasts over
+/// gossipsub and sends everything else to that peer alone.
+#[cfg(feature = "libp2p")]
+pub async fn run_libp2p(state: SharedState, bootstrap_peers: Vec<String>) -> anyhow::Result<()> {
+    use rhiza_core::network::libp2p_backend::{BackendEvent, Libp2pBackend};
+
+    let (mut backend, network, audit) = {
+        let state = state.read().await;
+        let backend = Libp2pBackend::new(&state.keypair, &state.config, state.network_id, state.gossip_limits)?;
+        (backend, state.network.clone(), state.peer_audit.clone())
+    };
+    dial_bootstrap(&mut backend, &bootstrap_peers).await;
+
+    let (outgoing_tx, mut outgoing) = mpsc::unbounded_channel();
+    let mut evictions: HashMap<PeerId, Arc<Notify>> = HashMap::new();
+    let mut redial = tokio::time::interval(REDIAL_INTERVAL);
+    loop {
+        tokio::select! {
+            event = backend.next_event() => match event {
+                BackendEvent::Connected { peer, address, inbound } => {
+                    let audit_entry = |event| {
+                        let entry = PeerAuditEntry::new(event).peer(&peer).direction(Direction::of(!inbound));
+                        match address {
+                            Some(address) => entry.address(address),
+                            None => entry,
+                        }
+                    };
+                    if peer.public_key == state.read().await.keypair.public_key {
+                        backend.disconnect(&peer);
+                        continue;
+                    }
+                    let Some(address) = address else {
+                        debug!("Dropping {}: no IP address to account it under", peer);
+                        backend.disconnect(&peer);
+                        continue;
+                    };
+                    if state.read().await.is_banned(&peer) {
+                        audit.record(audit_entry(PeerAuditEvent::Refused).reason("peer is banned"));
+                        backend.disconnect(&peer);
+                        continue;
+                    }
+                    let (outbox, inbox) = Outbox::new();
+                    let eviction = Arc::new(Notify::new());
+                    let registered = PeerAddress::Ip(address);
+                    match network.register(peer.clone(), registered, inbound, outbox, eviction.clone()) {
+                        Registration::Registered(evicted) => {
+                            if let Some(evicted) = evicted {
+                                info!("Evicted inbound {} to make room for {}", evicted, peer);
+                            }
+                        }
+                        Registration::Duplicate => continue,
+                        Registration::Refused(e) => {
+                            audit.record(audit_entry(PeerAuditEvent::Refused).reason(e.to_string()));
+                            backend.disconnect(&peer);
+                            continue;
+                        }
+                    }
+                    info!("🤝 Peer connected: {} ({}, libp2p)", peer, address);
+                    audit.record(audit_entry(PeerAuditEvent::Connected));
+                    tokio::spawn(forward(peer.clone(), inbox, eviction.clone(), outgoing_tx.clone()));
+                    evictions.insert(peer.clone(), eviction);
+
+                    {
+                        let state = state.read().await;
+                        state.events.publish(NodeEvent::PeerConnected {
+                            peer_id: peer.public_key.to_string(),
+                            address: address.to_string(),
+                        });
+                        backend.send_direct(&peer, state.tip_announce());
+                        for rotation in state.identity_rotation_messages() {
+                            backend.send_direct(&peer, rotation);
+                        }
+                    }
+                    let delivered: Vec<GossipMessage> = network
+                        .take_held(&peer)
+                        .into_iter()
+                        .map(|held| held.message)
+                        .filter(|message| backend.send_direct(&peer, message.clone()))
+                        .collect();
+                    if !delivered.is_empty() {
+                        state.write().await.held_delivered(&peer, &delivered);
+                    }
+                }
+                BackendEvent::Disconnected(peer) => {
+                    let Some(eviction) = evictions.remove(&peer) else {
+                        continue;
+                    };
+                    network.unregister(&peer, &eviction);
+                    state.write().await.peer_disconnected(&peer);
+                    info!("👋 Peer disconnected: {}", peer);
+                    audit.record(PeerAuditEntry::new(PeerAuditEvent::Disconnected).peer(&peer).reason("closed"));
+                }
+                BackendEvent::Identified(info) => {
+                    debug!("{} runs {} (protocol {})", info.id, info.agent_version, info.protocol_version);
+                }
+                BackendEvent::Message { from, message } => {
+                    debug!("← {} from {}", message.type_name(), from);
+                    let mut state = state.write().await;
+                    state.message_received(&from);
+                    let replies = state.handle_gossip(&from, message);
+                    let banned = state.is_banned(&from);
+                    drop(state);
+                    for reply in replies {
+                        backend.send(&from, reply);
+                    }
+                    if banned {
+                        backend.disconnect(&from);
+                    }
+                }
+                BackendEvent::Malformed { from, reason } => {
+                    debug!("Dropped a message from {}: {}", from, reason);
+                    let mut state = state.write().await;
+                    state.record_behavior(&from, Behavior::MalformedMessage);
+                    if state.is_banned(&from) {
+                        backend.disconnect(&from);
+                    }
+                }
+            },
+            Some(outgoing) = outgoing.recv() => match outgoing {
+                Outgoing::Send(peer, message) => {
+                    backend.send(&peer, message);
+                }
+                Outgoing::Evicted(peer) => backend.disconnect(&peer),
+            },
+            _ = redial.tick() => {
+                if network.peer_count() == 0 {
+                    dial_bootstrap(&mut backend, &bootstrap_peers).await;
+                }
+                backend.discover();
+            }
+        }
+    }
+}
+
+/// Dial each bootstrap peer, resolving host names first since the backend
+/// only takes IP addresses and multiaddrs; DNS seeds are looked up again on
+/// every call
+#[cfg(feature = "libp2p")]
+async fn dial_bootstrap(backend: &mut rhiza_core::network::libp2p_backend::Libp2pBackend, peers: &[String]) {
+    for peer in peers {
+        let addresses = if peer.starts_with('/') || peer.parse::<SocketAddr>().is_ok() {
+            vec![peer.clone()]
+        } else {
+            match tokio::net::lookup_host(peer.as_str()).await {
+                Ok(resolved) => resolved.map(|address| address.to_string()).collect(),
+                Err(e) => {
+                    warn!("Failed to resolve {}: {}", peer, e);
+                    continue;
+                }
+            }
+        };
+        for address in addresses {
+            if let Err(e) = backend.dial(&address) {
+                warn!("Failed to dial {}: {}", address, e);
+            }
+        }
+    }
+}
+
+/// What a libp2p peer's queue hands the backend
+#[cfg(feature = "libp2p")]
+// Moved straight through a channel, like the messages it wraps
+#[allow(clippy::large_enum_variant)]
+enum Outgoing {
+    Send(PeerId, GossipMessage),
+    Evicted(PeerId),
+}
+
+/// Move what is queued for a libp2p peer to the backend, gossip first,
+/// until the peer is unregistered or evicted
+#[cfg(feature = "libp2p")]
+async fn forward(
+    peer: PeerId,
+    mut inbox: Inbox,
+    eviction: Arc<Notify>,
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+) {
+    loop {
+        let msg = tokio::select! {
+            biased;
+            _ = eviction.notified() => {
+                let _ = outgoing.send(Outgoing::Evicted(peer));
+                return;
+            }
+            Some(msg) = inbox.recv() => msg,
+            else => return,
+        };
+        if outgoing.send(Outgoing::Send(peer.clone(), msg)).is_err() {
+            return;
+        }
+    }
+}
+
+async fn handshake(
+    keypair: &KeyPair,
+    network_id: u32,
+    reader: &mut (impl AsyncRead + Unpin),
+    writer: &mut (impl AsyncWrite + Unpin),
+    initiator: bool,
+) -> anyhow::Result<SecureSession> {
+    let mut handshake = if initiator {
+        Handshake::initiator(keypair, network_id)?
+    } else {
+        Handshake::responder(keypair, network_id)?
+    };
+
+    let mut our_turn = initiator;
+    while !handshake.is_finished() {
+        if our_turn {
+            let msg = handshake.write_message()?;
+            writer.write_all(&(msg.len() as u16).to_be_bytes()).await?;
+            writer.write_all(&msg).await?;
+        } else {
+            let len = reader.read_u16().await? as usize;
+            let mut msg = vec![0u8; len];
+            reader.read_exact(&mut msg).await?;
+            handshake.read_message(&msg)?;
+        }
+        our_turn = !our_turn;
+    }
+
+    Ok(handshake.into_session()?)
+}
+
+async fn read_loop(
+    state: &SharedState,
+    session: &Arc<Mutex<SecureSession>>,
+    reader: &mut (impl AsyncRead + Unpin),
+    peer: &PeerId,
+    outbox: &Outbox,
+) -> anyhow::Result<()> {
+    let limits = state.read().await.gossip_limits;
+    loop {
+        let mut prefix = [0u8; 4];
+        reader.read_exact(&mut prefix).await?;
+        let len = SecureSession::frame_len(prefix);
+        if len > limits.max_frame_bytes {
+            state.write().await.record_behavior(peer, Behavior::MalformedMessage);
+            anyhow::bail!("frame of {} bytes exceeds limit", len);
+        }
+        let mut body = vec![0u8; len];
+        reader.read_exact(&mut body).await?;
+
+        let decrypted = session.lock().unwrap().decrypt(&body);
+        let mut state_guard = state.write().await;
+        state_guard.message_received(peer);
+        let replies = match decrypted {
+            Ok(msg) => {
+                debug!("← {} from {}", msg.type_name(), peer);
+                match msg.check_limits(&limits) {
+                    Ok(()) => state_guard.handle_gossip(peer, msg),
+                    Err(e) => {
+                        debug!("Dropped {} from {}: {}", msg.type_name(), peer, e);
+                        state_guard.record_behavior(peer, Behavior::MalformedMessage);
+                        Vec::new()
+                    }
+                }
+            }
+            // The frame authenticated but doesn't hold a valid message
+            Err(TransportError::MalformedFrame) => {
+                state_guard.record_behavior(peer, Behavior::MalformedMessage);
+                Vec::new()
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if state_guard.is_banned(peer) {
+            anyhow::bail!("{} is banned", peer);
+        }
+        drop(state_guard);
+
+        for reply in replies {
+            if !outbox.send(reply) {
+                warn!("Outbound queue for {} closed", peer);
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn write_loop(
+    session: Arc<Mutex<SecureSession>>,
+    mut writer: impl AsyncWrite + Unpin,
+    mut inbox: Inbox,
+) -> anyhow::Result<()> {
+    loop {
+        let Some(msg) = inbox.recv().await else {
+            return Ok(());
+        };
+        let frame = session.lock().unwrap().encrypt(&msg)?;
+        writer.write_all(&frame).await?;
+    }
+}
@@ -0,0 +1,138 @@
+use crate::SharedState;
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::vertex::Dag;
+use rhiza_core::wallet::address::Address;
+use rhiza_core::wallet::PaymentUri;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Derivation context of the keys payment links are paid to
+pub const SUBKEY_CONTEXT: &str = "rhiza payment-link key v1";
+
+/// How long a link stays open when the request doesn't say
+pub const DEFAULT_EXPIRY_SECS: u64 = 60 * 60;
+
+/// Longest a link may stay open
+pub const MAX_EXPIRY_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How long after expiry payments to a link are still swept
+pub const SWEEP_GRACE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// How often links are checked for funds to sweep
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A request for a fixed amount, paid to a key derived for it alone
+///
+/// Payments to the key are what the link tracks, so a payer needs nothing
+/// but the address and amount. Once the link is paid or expired, the node
+/// moves what arrived on to its wallet key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentLink {
+    /// Random, so links can't be enumerated
+    pub id: String,
+    /// Index of the wallet subkey payments go to
+    pub key_index: u64,
+    pub public_key: PublicKey,
+    pub amount: u64,
+    pub memo: Option<String>,
+    pub created_ms: u64,
+    pub expires_ms: u64,
+    /// Transactions that moved funds from the link's key to the wallet, with
+    /// what they moved
+    pub sweeps: Vec<(Hash, u64)>,
+}
+
+/// Where a link stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStatus {
+    /// Nothing has arrived yet
+    Awaiting,
+    /// Some, but not all, of the amount has arrived
+    Partial,
+    /// The whole amount arrived, but isn't final yet
+    Confirming,
+    /// The whole amount arrived and is final
+    Paid,
+    /// Ran out of time before it was paid
+    Expired,
+}
+
+/// What a link has received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LinkProgress {
+    pub status: LinkStatus,
+    /// Pending payments included
+    pub received: u64,
+    pub confirmed: u64,
+}
+
+impl PaymentLink {
+    pub fn address(&self) -> String {
+        Address::from_public_key(&self.public_key).to_string()
+    }
+
+    /// `rhiza:` URI for wallets and QR codes
+    pub fn uri(&self) -> String {
+        let mut uri = PaymentUri::new(Address::from_public_key(&self.public_key)).with_amount(self.amount);
+        uri.memo = self.memo.clone();
+        uri.to_string()
+    }
+
+    /// What has arrived at the link's key, counting funds already swept
+    pub fn progress(&self, dag: &Dag, now_ms: u64) -> LinkProgress {
+        let mut received = dag.get_balance(&self.public_key);
+        let mut confirmed = dag.get_confirmed_balance(&self.public_key);
+        for (sweep, amount) in &self.sweeps {
+            if dag.conflicts().is_rejected(sweep) {
+                continue;
+            }
+            received = received.saturating_add(*amount);
+            // Sweeps missing from the DAG were final and pruned
+            if dag.get(sweep).is_none_or(|vertex| vertex.is_final) {
+                confirmed = confirmed.saturating_add(*amount);
+            }
+        }
+
+        let status = if confirmed >= self.amount {
+            LinkStatus::Paid
+        } else if now_ms >= self.expires_ms {
+            LinkStatus::Expired
+        } else if received >= self.amount {
+            LinkStatus::Confirming
+        } else if received > 0 {
+            LinkStatus::Partial
+        } else {
+            LinkStatus::Awaiting
+        };
+        LinkProgress { status, received, confirmed }
+    }
+
+    /// Final funds on the link's key that can move to the wallet: once the
+    /// link is paid or expired, and nothing to or from the key is pending
+    pub fn sweepable(&self, dag: &Dag, now_ms: u64) -> u64 {
+        if now_ms >= self.expires_ms.saturating_add(SWEEP_GRACE_MS) {
+            return 0;
+        }
+        let status = self.progress(dag, now_ms).status;
+        if !matches!(status, LinkStatus::Paid | LinkStatus::Expired) {
+            return 0;
+        }
+        let confirmed = dag.get_confirmed_balance(&self.public_key);
+        if dag.get_balance(&self.public_key) != confirmed {
+            return 0;
+        }
+        confirmed
+    }
+}
+
+/// Move funds from paid and expired links to the wallet until the node
+/// shuts down
+pub async fn run(state: SharedState) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        state.write().await.sweep_payment_links(now);
+    }
+}
@@ -0,0 +1,95 @@
+use rhiza_core::crypto::PublicKey;
+use rhiza_core::dag::vertex::Dag;
+use rhiza_core::wallet::address::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+/// File name of the spending policy inside the data directory
+pub const POLICY_FILE: &str = "policy.json";
+
+/// Milliseconds in the daily-limit window
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Organization controls enforced before the node signs a transfer
+///
+/// Every rule is optional; an absent policy file means no restrictions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpendingPolicy {
+    /// Only these `rhz1...` addresses may be paid (absent = any address)
+    #[serde(default)]
+    pub allowlist: Option<Vec<String>>,
+    /// Most that may be sent per UTC day, in smallest units (fees included)
+    #[serde(default)]
+    pub daily_limit: Option<u64>,
+    /// Reject transfers without a memo
+    #[serde(default)]
+    pub require_memo: bool,
+    /// Parsed allowlist
+    #[serde(skip)]
+    allowed: HashSet<Address>,
+}
+
+impl SpendingPolicy {
+    /// Load the policy from the data directory, if one is configured
+    pub fn load(data_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = data_dir.join(POLICY_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut policy: SpendingPolicy = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        for entry in policy.allowlist.iter().flatten() {
+            let address = Address::from_str(&entry.trim().to_ascii_lowercase())
+                .map_err(|e| anyhow::anyhow!("Invalid allowlist address {:?}: {}", entry, e))?;
+            policy.allowed.insert(address);
+        }
+        Ok(Some(policy))
+    }
+
+    /// Check a transfer of `amount` in all from `owner` to `recipients`
+    /// against the policy
+    pub fn check(
+        &self,
+        dag: &Dag,
+        owner: &PublicKey,
+        recipients: &[&PublicKey],
+        amount: u64,
+        memo: Option<&str>,
+        now_ms: u64,
+    ) -> Result<(), String> {
+        if self.allowlist.is_some() {
+            for recipient in recipients {
+                let address = Address::from_public_key(recipient);
+                if !self.allowed.contains(&address) {
+                    return Err(format!("Policy: recipient {} is not on the allowlist", address));
+                }
+            }
+        }
+
+        if self.require_memo && memo.is_none_or(|m| m.trim().is_empty()) {
+            return Err("Policy: a memo is required".to_string());
+        }
+
+        if let Some(limit) = self.daily_limit {
+            let spent = spent_since(dag, owner, now_ms - now_ms % DAY_MS);
+            if spent.saturating_add(amount as u128) > limit as u128 {
+                return Err(format!(
+                    "Policy: daily limit of {} would be exceeded ({} already sent today)",
+                    limit, spent
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Total debited from `owner` by transactions timestamped at or after `since_ms`
+fn spent_since(dag: &Dag, owner: &PublicKey, since_ms: u64) -> u128 {
+    dag.iter()
+        .map(|v| &v.transaction)
+        .filter(|tx| tx.data.timestamp >= since_ms && !dag.conflicts().is_rejected(&tx.id))
+        .map(|tx| tx.data.debit_for(owner))
+        .sum()
+}
@@ -0,0 +1,109 @@
+use crate::SharedState;
+use rand::Rng;
+use rhiza_core::consensus::tip_selection::{TipSelector, UniformTips};
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::vertex::Dag;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Derivation context of the throwaway keys privacy sends route through
+pub const SUBKEY_CONTEXT: &str = "rhiza privacy-send subkey v1";
+
+/// Most pieces a private payment is split into
+pub const MAX_SPLIT_PARTS: u64 = 4;
+
+/// Bounds of the random delay before a subkey forwards its piece
+pub const MIN_HOP_DELAY_MS: u64 = 2_000;
+pub const MAX_HOP_DELAY_MS: u64 = 60_000;
+
+/// Delay before retrying a forward that failed
+const HOP_RETRY_DELAY_MS: u64 = 5_000;
+
+/// Random tip pairs tried before falling back to the funding transaction
+const PARENT_ATTEMPTS: usize = 8;
+
+/// How often due forwards are checked
+const HOP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A piece of a private payment parked on a subkey, waiting to be forwarded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingHop {
+    /// Index of the subkey holding the piece
+    pub subkey_index: u64,
+    pub recipient: PublicKey,
+    pub amount: u64,
+    /// When to forward it (unix ms)
+    pub due_ms: u64,
+    /// The transaction that moved the piece onto the subkey
+    pub funding: Hash,
+}
+
+impl PendingHop {
+    /// Push the forward back after a failed attempt
+    pub fn retry_later(&mut self, now_ms: u64) {
+        self.due_ms = now_ms + HOP_RETRY_DELAY_MS;
+    }
+}
+
+/// Split `amount` into 2..=[`MAX_SPLIT_PARTS`] random non-zero pieces
+/// (a single piece when `amount` is 1)
+pub fn split_amount<R: Rng + ?Sized>(amount: u64, rng: &mut R) -> Vec<u64> {
+    let parts = rng.gen_range(2..=MAX_SPLIT_PARTS).min(amount).max(1);
+    let mut cuts = BTreeSet::new();
+    while (cuts.len() as u64) < parts - 1 {
+        cuts.insert(rng.gen_range(1..amount));
+    }
+
+    let mut pieces = Vec::with_capacity(parts as usize);
+    let mut last = 0;
+    for cut in cuts.into_iter().chain(std::iter::once(amount)) {
+        pieces.push(cut - last);
+        last = cut;
+    }
+    pieces
+}
+
+/// Random forwarding delay in milliseconds
+pub fn hop_delay<R: Rng + ?Sized>(rng: &mut R) -> u64 {
+    rng.gen_range(MIN_HOP_DELAY_MS..=MAX_HOP_DELAY_MS)
+}
+
+/// Pick `count` uniformly random tips that can see `amount` of `key`'s
+/// funds
+///
+/// Random parents keep the pieces from hanging off the same point of the
+/// DAG. If no sampled set sees the funds, approve `fallback` (which must)
+/// next to random tips.
+pub fn random_funded_parents<R: Rng>(
+    dag: &Dag,
+    key: &PublicKey,
+    amount: u64,
+    fallback: Hash,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Hash> {
+    for _ in 0..PARENT_ATTEMPTS {
+        let parents = UniformTips.select(dag, count, rng);
+        if dag.balance_seen_by(&parents, key) >= amount {
+            return parents;
+        }
+    }
+    let mut parents = UniformTips.select(dag, count, rng);
+    parents[0] = fallback;
+    parents
+}
+
+/// Forward due pieces, and flood stem transactions whose embargo ran
+/// out, until the node shuts down
+pub async fn run(state: SharedState) {
+    let mut interval = tokio::time::interval(HOP_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let mut state = state.write().await;
+        state.forward_private_hops(now);
+        state.flood_embargoed(now);
+    }
+}
+
@@ -0,0 +1,54 @@
+use crate::SharedState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Pruning of old final history (`pruning` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PruningConfig {
+    /// Prune final history; off unless the operator opts in
+    pub enabled: bool,
+    /// Depths kept in memory below the deepest vertex; final vertices
+    /// further down are pruned
+    pub keep_depth: u64,
+    /// Seconds between pruning passes
+    pub interval_secs: u64,
+    /// Keep pruned transactions on disk, where `GET /tx/:id` and snapshot
+    /// exports still find them
+    pub cold_storage: bool,
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        PruningConfig {
+            enabled: false,
+            keep_depth: 10_000,
+            interval_secs: 10 * 60,
+            cold_storage: true,
+        }
+    }
+}
+
+/// Prune every `interval_secs` until the node shuts down
+pub async fn run(state: SharedState, config: PruningConfig) {
+    info!(
+        "✂️  Pruning final history more than {} deep every {} s",
+        config.keep_depth, config.interval_secs
+    );
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(60)));
+    loop {
+        interval.tick().await;
+        let mut state = state.write().await;
+        match state.prune(&config) {
+            Ok(0) => {}
+            Ok(pruned) => info!(
+                "✂️  Pruned {} final transactions up to depth {}; {} left in memory",
+                pruned,
+                state.dag.pruned().horizon().unwrap_or(0),
+                state.dag.len()
+            ),
+            Err(e) => warn!("Pruning failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Directory of the analytics replica, inside the data directory
+pub const REPLICA_DIR: &str = "replica";
+
+/// Scratch directory a new replica is written to before it replaces the old one
+const REPLICA_TMP_DIR: &str = "replica.tmp";
+
+/// Replace the replica with a fresh copy of the live database
+///
+/// The copy is written next to the replica and moved into place when
+/// complete, so readers never see a half-written one. Each tree is copied
+/// as the node keeps writing; a vertex written during the copy may be in
+/// the replica without its index entries until the next refresh.
+pub fn refresh(storage: &Storage, data_path: &Path) -> Result<()> {
+    let tmp = data_path.join(REPLICA_TMP_DIR);
+    if tmp.exists() {
+        std::fs::remove_dir_all(&tmp)?;
+    }
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    storage.copy_to(&tmp, now)?;
+
+    let replica = data_path.join(REPLICA_DIR);
+    if replica.exists() {
+        std::fs::remove_dir_all(&replica)?;
+    }
+    std::fs::rename(&tmp, &replica)?;
+    Ok(())
+}
+
+/// Refresh the replica every `interval` until the node shuts down
+pub async fn run(storage: Storage, data_path: PathBuf, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let storage = storage.clone();
+        let data_path = data_path.clone();
+        let result = tokio::task::spawn_blocking(move || refresh(&storage, &data_path)).await;
+        match result {
+            Ok(Ok(())) => info!("📑 Analytics replica refreshed"),
+            Ok(Err(e)) => warn!("Failed to refresh the analytics replica: {}", e),
+            Err(e) => warn!("Analytics replica task failed: {}", e),
+        }
+    }
+}
+
+/// Write every DAG vertex as a JSON line, shallowest first
+///
+/// With `read_only` the dump comes from the replica a running node keeps,
+/// and the live database isn't touched; otherwise the node must be stopped.
+pub fn export(data_path: &Path, read_only: bool, out: Option<&Path>) -> Result<()> {
+    let storage = if read_only {
+        let replica = data_path.join(REPLICA_DIR);
+        if !replica.exists() {
+            anyhow::bail!("No analytics replica yet; start the node with --replica-interval");
+        }
+        Storage::open(&replica).context("Could not open the analytics replica")?
+    } else {
+        Storage::open(&data_path.join("db"))
+            .context("Could not open the node database; stop the node or use --read-only")?
+    };
+
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("Could not create {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    let mut count = 0;
+    for vertex in storage.vertices_by_depth(..) {
+        serde_json::to_writer(&mut writer, &vertex?)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+
+    if let Some(path) = out {
+        println!("📤 Exported {} transactions to {}", count, path.display());
+        if let Some(taken) = storage.replicated_at_ms()? {
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            println!("   Replica taken {} s ago", now.saturating_sub(taken) / 1_000);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,421 @@
+use crate::config::NodeConfig;
+use crate::event_log::Incident;
+use crate::keyfile;
+use crate::storage::Storage;
+use crate::SharedState;
+use anyhow::{Context, Result};
+use rhiza_core::consensus::relay::RelayCountStore;
+use rhiza_core::crypto::KeyPair;
+use rhiza_core::dag::snapshot::{RelaySnapshot, SignatureCheck, Snapshot};
+use rhiza_core::dag::validator::{TimestampRules, TransactionValidator};
+use rhiza_core::dag::vertex::{Dag, DagVertex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Message for a database that's locked by a running node
+const DB_LOCKED: &str = "Could not open the node database; stop the node first";
+
+/// Directory under the data directory that on-demand snapshots go to
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// The published snapshot, served at `GET /snapshot/latest`
+const PUBLISHED_FILE: &str = "latest.snapshot";
+
+/// Transactions between progress updates of a snapshot job
+const PROGRESS_STEP: usize = 1_024;
+
+/// How often `snapshot create` asks the node how the job is going
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Stage a snapshot job is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPhase {
+    /// Reading the DAG from storage
+    Collecting,
+    /// Replaying every transaction through the validator
+    Verifying,
+    Done,
+    Failed,
+}
+
+/// A snapshot the running node was asked for, and how far it got
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotJob {
+    pub phase: JobPhase,
+    pub started_ms: u64,
+    pub finished_ms: Option<u64>,
+    /// Transactions read so far
+    pub collected: usize,
+    /// Transactions replayed and found valid so far
+    pub verified: usize,
+    /// Signed with the node key as a publisher
+    pub sign: bool,
+    /// Served at `GET /snapshot/latest` once done
+    pub publish: bool,
+    pub file: Option<PathBuf>,
+    pub content_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+impl SnapshotJob {
+    pub fn is_running(&self) -> bool {
+        matches!(self.phase, JobPhase::Collecting | JobPhase::Verifying)
+    }
+}
+
+/// Snapshots the running node takes on demand
+#[derive(Debug, Default)]
+pub struct SnapshotMaker {
+    /// Where snapshot files go
+    dir: PathBuf,
+    /// The job running, or the last one
+    pub job: Option<SnapshotJob>,
+}
+
+impl SnapshotMaker {
+    pub fn new(data_path: &Path) -> Self {
+        SnapshotMaker {
+            dir: data_path.join(SNAPSHOT_DIR),
+            job: None,
+        }
+    }
+
+    /// The published snapshot file, if there is one
+    pub fn published(&self) -> Option<PathBuf> {
+        Some(self.dir.join(PUBLISHED_FILE)).filter(|path| path.exists())
+    }
+}
+
+/// Start taking a snapshot of the running node in the background
+///
+/// The job reads the DAG and relay state from storage, checks the hash
+/// chain and replays every transaction through the validator, as an import
+/// would, then writes the file under `snapshots/` in the data directory.
+/// With `sign` the node key signs it as a publisher; with `publish` it
+/// becomes the file served at `GET /snapshot/latest`. Progress is in
+/// [`SnapshotMaker::job`]. Fails if a job is already running.
+pub async fn start(state: &SharedState, sign: bool, publish: bool) -> Result<SnapshotJob, String> {
+    let mut guard = state.write().await;
+    if guard.snapshots.job.as_ref().is_some_and(SnapshotJob::is_running) {
+        return Err("A snapshot is already being taken".to_string());
+    }
+    // Relay counts are only written out periodically
+    guard.persist_relays();
+    let job = SnapshotJob {
+        phase: JobPhase::Collecting,
+        started_ms: chrono::Utc::now().timestamp_millis() as u64,
+        finished_ms: None,
+        collected: 0,
+        verified: 0,
+        sign,
+        publish,
+        file: None,
+        content_hash: None,
+        error: None,
+    };
+    guard.snapshots.job = Some(job.clone());
+    let storage = guard.storage.clone();
+    let keypair = sign.then(|| guard.keypair.clone());
+    let dir = guard.snapshots.dir.clone();
+    drop(guard);
+
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        let outcome = take(&state, &storage, keypair.as_ref(), &dir, publish);
+        let finished_ms = chrono::Utc::now().timestamp_millis() as u64;
+        {
+            let guard = state.blocking_read();
+            let incident = match &outcome {
+                Ok((file, content_hash)) => Incident::SnapshotCreated {
+                    file: file.display().to_string(),
+                    content_hash: content_hash.clone(),
+                    transactions: guard.snapshots.job.as_ref().map_or(0, |job| job.collected),
+                },
+                Err(e) => Incident::SnapshotFailed { error: format!("{:#}", e) },
+            };
+            guard.event_log.record(incident);
+        }
+        progress(&state, |job| {
+            job.finished_ms = Some(finished_ms);
+            match outcome {
+                Ok((file, content_hash)) => {
+                    info!("📦 Snapshot of {} transactions written to {}", job.collected, file.display());
+                    job.phase = JobPhase::Done;
+                    job.file = Some(file);
+                    job.content_hash = Some(content_hash);
+                }
+                Err(e) => {
+                    warn!("Snapshot failed: {:#}", e);
+                    job.phase = JobPhase::Failed;
+                    job.error = Some(format!("{:#}", e));
+                }
+            }
+        });
+    });
+    Ok(job)
+}
+
+/// Body of a snapshot job, run on a blocking thread; returns the file and
+/// the snapshot's content hash
+fn take(
+    state: &SharedState,
+    storage: &Storage,
+    keypair: Option<&KeyPair>,
+    dir: &Path,
+    publish: bool,
+) -> Result<(PathBuf, String)> {
+    let mut snapshot = collect(storage, |n| progress(state, |job| job.collected = n))?;
+    let count = snapshot.transaction_count();
+    progress(state, |job| {
+        job.collected = count;
+        job.phase = JobPhase::Verifying;
+    });
+    let rules = state.blocking_read().dag.timestamp_rules();
+    replay(&snapshot, rules, |n| progress(state, |job| job.verified = n))?;
+    progress(state, |job| job.verified = count);
+
+    if let Some(keypair) = keypair {
+        snapshot.sign(keypair);
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    let file = dir.join(format!("rhiza-{}.snapshot", snapshot.created_ms));
+    save(&snapshot, &file)?;
+    if publish {
+        // Renamed into place, so a download never sees half a file
+        let tmp = dir.join(format!("{}.tmp", PUBLISHED_FILE));
+        std::fs::copy(&file, &tmp).with_context(|| format!("Could not write {}", tmp.display()))?;
+        std::fs::rename(&tmp, dir.join(PUBLISHED_FILE)).context("Could not publish the snapshot")?;
+    }
+    Ok((file, snapshot.content_hash().to_string()))
+}
+
+/// Update the running snapshot job from a blocking thread
+fn progress(state: &SharedState, update: impl FnOnce(&mut SnapshotJob)) {
+    if let Some(job) = state.blocking_write().snapshots.job.as_mut() {
+        update(job);
+    }
+}
+
+/// Unsigned snapshot of the DAG and relay state in `storage`, calling
+/// `progress` with the number of transactions read every so often
+fn collect(storage: &Storage, mut progress: impl FnMut(usize)) -> Result<Snapshot> {
+    let mut transactions = Vec::new();
+    for vertex in storage.vertices_by_depth(..) {
+        transactions.push(vertex?.transaction);
+        if transactions.len().is_multiple_of(PROGRESS_STEP) {
+            progress(transactions.len());
+        }
+    }
+    if transactions.is_empty() {
+        anyhow::bail!("The node has no DAG to export yet");
+    }
+    // A snapshot replays from genesis, so it needs every pruned transaction
+    if let Some(pruned) = storage.get_balance_snapshot()? {
+        let exported: HashSet<_> = transactions.iter().map(|tx| tx.id).collect();
+        let missing = pruned.pruned.iter().filter(|id| !exported.contains(*id)).count();
+        if missing > 0 {
+            anyhow::bail!(
+                "{} pruned transactions were dropped from disk (pruning.cold_storage is off); \
+                 export from a node that keeps its full history",
+                missing
+            );
+        }
+    }
+
+    let relay = RelaySnapshot {
+        totals: storage.get_relay_totals()?,
+        counts: storage.relay_counts()?,
+    };
+    Ok(Snapshot::new(transactions, relay))
+}
+
+/// Check a snapshot's hash chain and replay every transaction through the
+/// validator into a fresh DAG under `rules`, calling `progress` with the
+/// number replayed every so often
+fn replay(snapshot: &Snapshot, rules: TimestampRules, mut progress: impl FnMut(usize)) -> Result<Dag> {
+    snapshot.verify_chain().context("Snapshot rejected")?;
+    let mut dag = Dag::new();
+    dag.set_timestamp_rules(rules);
+    for tx in snapshot.segments.iter().flat_map(|s| &s.transactions) {
+        TransactionValidator::validate(tx, &dag)
+            .map_err(|e| anyhow::anyhow!("Snapshot transaction {} is invalid: {}", tx.id, e))?;
+        let depth = if dag.is_empty() { 0 } else { dag.depth() + 1 };
+        dag.insert(DagVertex::new(tx.clone(), depth))
+            .map_err(|e| anyhow::anyhow!("Snapshot transaction {} could not be inserted: {}", tx.id, e))?;
+        if dag.len().is_multiple_of(PROGRESS_STEP) {
+            progress(dag.len());
+        }
+    }
+    Ok(dag)
+}
+
+/// Ask the running node at `api_url` for a snapshot and follow the job
+/// until it's done
+pub async fn create(api_url: &str, api_key: Option<&str>, sign: bool, publish: bool) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/admin/snapshot", api_url.trim_end_matches('/'));
+    let with_key = |request: reqwest::RequestBuilder| match api_key {
+        Some(key) => request.bearer_auth(key),
+        None => request,
+    };
+    let response = with_key(client.post(&url))
+        .json(&serde_json::json!({ "sign": sign, "publish": publish }))
+        .send()
+        .await
+        .with_context(|| format!("Could not reach the node at {}", api_url))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        anyhow::bail!("The node refused the snapshot ({}): {}", status, response.text().await?);
+    }
+    println!("Snapshot started");
+
+    let mut shown = (JobPhase::Collecting, 0, 0);
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let job: SnapshotJob = with_key(client.get(&url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if (job.phase, job.collected, job.verified) != shown {
+            shown = (job.phase, job.collected, job.verified);
+            match job.phase {
+                JobPhase::Collecting => println!("  collecting: {} transactions", job.collected),
+                JobPhase::Verifying => println!("  verifying: {}/{} transactions", job.verified, job.collected),
+                _ => {}
+            }
+        }
+        match job.phase {
+            JobPhase::Collecting | JobPhase::Verifying => continue,
+            JobPhase::Done => {
+                if let Some(file) = &job.file {
+                    println!("Snapshot of {} transactions written to {}", job.collected, file.display());
+                }
+                if let Some(hash) = &job.content_hash {
+                    println!("Content hash: {}", hash);
+                }
+                if job.publish {
+                    println!("Published at {}/snapshot/latest", api_url.trim_end_matches('/'));
+                }
+                return Ok(());
+            }
+            JobPhase::Failed => {
+                anyhow::bail!("Snapshot failed: {}", job.error.unwrap_or_default());
+            }
+        }
+    }
+}
+
+/// Write the node's DAG and relay state to an unsigned snapshot file
+pub fn export(data_path: &Path, out: &Path) -> Result<()> {
+    let storage = Storage::open(&data_path.join("db")).context(DB_LOCKED)?;
+    let snapshot = collect(&storage, |_| {})?;
+    save(&snapshot, out)?;
+    println!(
+        "📦 Exported {} transactions in {} chained segments to {}",
+        snapshot.transaction_count(),
+        snapshot.segments.len(),
+        out.display()
+    );
+    println!("   Content hash: {}", snapshot.content_hash());
+    println!("   Publishers sign it with 'rhiza-node snapshot sign {}'", out.display());
+    Ok(())
+}
+
+/// Add the node key's publisher signature to a snapshot file
+pub fn sign(data_path: &Path, password_file: Option<&Path>, file: &Path) -> Result<()> {
+    let key_path = keyfile::identity_path(data_path);
+    if !key_path.exists() {
+        anyhow::bail!("Node not initialized. Run 'rhiza-node init' first.");
+    }
+    let mut snapshot = load(&std::fs::read(file).with_context(|| format!("Could not read {}", file.display()))?)?;
+    let keypair = keyfile::unlock(&key_path, password_file)?;
+    snapshot.sign(&keypair);
+    save(&snapshot, file)?;
+    println!("✍️  Signed {} as {}", file.display(), keypair.public_key);
+    println!("   {} signatures on this snapshot", snapshot.signatures.len());
+    Ok(())
+}
+
+/// Bootstrap an empty node from a snapshot file or `http(s)://` URL
+///
+/// The snapshot must carry valid signatures from at least the configured
+/// number of trusted publishers, and every transaction is validated again
+/// as it is replayed, so a bad snapshot can't plant invalid history.
+pub async fn import(data_path: &Path, source: &str, config: &NodeConfig) -> Result<()> {
+    let (publishers, threshold) = config.snapshot_trust()?;
+
+    let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+        println!("⬇️  Downloading {}", source);
+        let response = reqwest::get(source).await?.error_for_status()?;
+        response.bytes().await?.to_vec()
+    } else {
+        std::fs::read(source).with_context(|| format!("Could not read {}", source))?
+    };
+    let snapshot = load(&bytes)?;
+    snapshot.verify_chain().context("Snapshot rejected")?;
+
+    println!(
+        "🔏 Snapshot signatures ({} transactions, hash {}):",
+        snapshot.transaction_count(),
+        snapshot.content_hash()
+    );
+    let report = snapshot.verify(&publishers, threshold);
+    if report.checks.is_empty() {
+        println!("   (none)");
+    }
+    for (publisher, check) in &report.checks {
+        match check {
+            SignatureCheck::Valid => println!("   ✅ {}  valid, trusted publisher", publisher),
+            SignatureCheck::Invalid => println!("   ❌ {}  signature does not match the snapshot", publisher),
+            SignatureCheck::Untrusted => println!("   ⚪ {}  not a configured publisher, ignored", publisher),
+        }
+    }
+    if !report.is_accepted() {
+        anyhow::bail!(
+            "Snapshot rejected: {} of the {} required publisher signatures verified",
+            report.valid(),
+            threshold
+        );
+    }
+    println!("   {} of {} required signatures verified", report.valid(), threshold);
+
+    let storage = Storage::open(&data_path.join("db")).context(DB_LOCKED)?;
+    if storage.count() > 0 {
+        anyhow::bail!("The node already has a DAG; import snapshots into a freshly initialized node");
+    }
+
+    let relay = snapshot.relay.clone();
+    let dag = replay(&snapshot, config.timestamps, |_| {})?;
+    for vertex in dag.iter() {
+        storage.put_vertex(vertex)?;
+    }
+    let mut counts = storage.relay_count_store();
+    for (relayer, count) in &relay.counts {
+        counts.save(relayer, *count);
+    }
+    storage.put_relay_state(&relay.totals, &[])?;
+
+    println!("🌿 Imported {} transactions; start the node to sync the rest from peers", dag.len());
+    println!(
+        "   Relay state: {} relays, {} units rewarded, {} relayers",
+        relay.totals.total_relays,
+        relay.totals.total_rewards_distributed,
+        relay.counts.len()
+    );
+    Ok(())
+}
+
+fn load(bytes: &[u8]) -> Result<Snapshot> {
+    bincode::deserialize(bytes).context("Not a Rhiza snapshot file (or one from before hash-chained snapshots)")
+}
+
+fn save(snapshot: &Snapshot, path: &Path) -> Result<()> {
+    std::fs::write(path, bincode::serialize(snapshot)?)
+        .with_context(|| format!("Could not write {}", path.display()))
+}
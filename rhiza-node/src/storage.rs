@@ -1,25 +1,176 @@
+use rhiza_core::consensus::relay::{RelayCountStore, RelayProof, RelayTotals};
+use rhiza_core::crypto::PublicKey;
 use rhiza_core::crypto::Hash;
+use rhiza_core::dag::pruning::BalanceSnapshot;
 use rhiza_core::dag::transaction::Transaction;
-use sled::Db;
+use rhiza_core::dag::vertex::DagVertex;
+use rhiza_core::network::reputation::Ban;
+use rhiza_core::network::{DtnQueue, IdentityRotation, PeerId};
+use sled::transaction::{ConflictableTransactionError, TransactionalTree};
+use sled::{Db, IVec, Transactional, Tree};
+use crate::channels::ChannelEntry;
+use crate::payment_links::PaymentLink;
+use crate::privacy::PendingHop;
+use crate::audit::PeerAuditEntry;
+use crate::webhooks::{Delivery, DeliveryReceipt};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 
+/// Key of the network-wide relay totals in the metadata tree
+const RELAY_TOTALS_KEY: &[u8] = b"relay_totals";
+
+/// Key of the signed snapshot of pruned history in the metadata tree
+const BALANCE_SNAPSHOT_KEY: &[u8] = b"balance_snapshot";
+
+/// Key of the unclaimed relay proofs in the metadata tree
+const PENDING_RELAYS_KEY: &[u8] = b"pending_relays";
+
+/// Key of the private payment pieces not yet forwarded
+const PRIVATE_HOPS_KEY: &[u8] = b"private_hops";
+
+/// Key of the node's off-DAG channel states
+const CHANNELS_KEY: &[u8] = b"channels";
+
+/// Key of the next unused privacy subkey index
+const NEXT_SUBKEY_KEY: &[u8] = b"next_privacy_subkey";
+
+/// Key of the next unused payment link key index
+const NEXT_LINK_KEY: &[u8] = b"next_payment_link_key";
+
+/// Key of the identity rotations this node knows about
+const IDENTITY_ROTATIONS_KEY: &[u8] = b"identity_rotations";
+
+/// Key of the peer ban list
+const PEER_BANS_KEY: &[u8] = b"peer_bans";
+
+/// Key of the anchor peer addresses redialed first on startup
+const ANCHOR_PEERS_KEY: &[u8] = b"anchor_peers";
+
+/// Key of the gossip held for absent peers
+const DTN_QUEUE_KEY: &[u8] = b"dtn_queue";
+
+/// Set once the depth and timestamp indexes cover every record
+const INDEXES_BUILT_KEY: &[u8] = b"indexes_v1";
+
+/// When a copy of the database was taken (unix ms); only set in copies
+const REPLICATED_AT_KEY: &[u8] = b"replicated_at_ms";
+
 /// Persistent storage for DAG data using sled embedded database
+///
+/// The default tree holds one record per DAG vertex (the transaction plus
+/// its locally assigned depth and weight/finality metadata), keyed by
+/// transaction ID. The `by_depth` and `by_time` trees index those records
+/// by `(depth, txid)` and `(timestamp, txid)`, and are written in the same
+/// sled transaction as the record itself. Records that no longer decode are
+/// moved aside to the `quarantine` tree rather than deleted. Relay state (per-relayer counts,
+/// network totals and unclaimed proofs) is written in the same transaction as
+/// the vertex whose relay changed it.
+///
+/// Clones are handles to the same database.
+#[derive(Clone)]
 pub struct Storage {
     db: Db,
+    /// Owner-assigned transaction tags (local metadata, never gossiped)
+    tags: Tree,
+    /// Node bookkeeping that isn't part of the DAG (relay totals, ...)
+    meta: Tree,
+    /// Per-relayer relay counts (cold storage behind the relay tracker)
+    relay_counts: Tree,
+    /// Relay counts changed since relay state was last written
+    unsaved_relay_counts: Arc<Mutex<HashMap<PublicKey, u64>>>,
+    /// Index of vertex records by depth
+    by_depth: Tree,
+    /// Index of vertex records by transaction timestamp
+    by_time: Tree,
+    /// Webhook deliveries still being attempted, by delivery ID
+    webhook_queue: Tree,
+    /// Webhook deliveries that ran out of attempts, by delivery ID
+    webhook_dead: Tree,
+    /// Signed receipts of completed webhook deliveries, by delivery ID
+    webhook_receipts: Tree,
+    /// Peer audit log, by sequence number
+    peer_audit: Tree,
+    /// Payment links the node handed out, by link ID
+    payment_links: Tree,
+    /// Vertex records set aside because they didn't decode, by raw key
+    quarantine: Tree,
 }
 
 impl Storage {
     /// Open or create a storage database
     pub fn open(path: &Path) -> anyhow::Result<Self> {
         let db = sled::open(path)?;
-        Ok(Storage { db })
+        let tags = db.open_tree("tags")?;
+        let meta = db.open_tree("meta")?;
+        let relay_counts = db.open_tree("relay_counts")?;
+        let by_depth = db.open_tree("by_depth")?;
+        let by_time = db.open_tree("by_time")?;
+        let webhook_queue = db.open_tree("webhook_queue")?;
+        let webhook_dead = db.open_tree("webhook_dead")?;
+        let webhook_receipts = db.open_tree("webhook_receipts")?;
+        let peer_audit = db.open_tree("peer_audit")?;
+        let payment_links = db.open_tree("payment_links")?;
+        let quarantine = db.open_tree("quarantine")?;
+        let storage = Storage {
+            db,
+            tags,
+            meta,
+            relay_counts,
+            unsaved_relay_counts: Arc::default(),
+            by_depth,
+            by_time,
+            webhook_queue,
+            webhook_dead,
+            webhook_receipts,
+            peer_audit,
+            payment_links,
+            quarantine,
+        };
+        if !storage.meta.contains_key(INDEXES_BUILT_KEY)? {
+            storage.rebuild_indexes()?;
+        }
+        Ok(storage)
     }
 
-    /// Store a transaction
-    pub fn put_transaction(&self, tx: &Transaction) -> anyhow::Result<()> {
-        let key = tx.id.as_bytes();
-        let value = bincode::serialize(tx)?;
-        self.db.insert(key, value)?;
+    /// Rebuild the depth and timestamp indexes from the vertex records
+    ///
+    /// Databases written before the indexes existed get them this way.
+    pub fn rebuild_indexes(&self) -> anyhow::Result<()> {
+        self.by_depth.clear()?;
+        self.by_time.clear()?;
+        for result in self.db.iter() {
+            let (key, value) = result?;
+            let Ok(id) = <[u8; 32]>::try_from(key.as_ref()) else {
+                continue;
+            };
+            let Ok(vertex) = bincode::deserialize::<DagVertex>(&value) else {
+                continue;
+            };
+            let id = Hash::from_bytes(id);
+            self.by_depth.insert(&index_key(vertex.depth, &id)[..], &[])?;
+            self.by_time.insert(&index_key(vertex.transaction.data.timestamp, &id)[..], &[])?;
+        }
+        self.meta.insert(INDEXES_BUILT_KEY, &[])?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Store a DAG vertex, replacing any earlier record for it
+    pub fn put_vertex(&self, vertex: &DagVertex) -> anyhow::Result<()> {
+        let id = vertex.id();
+        let value = bincode::serialize(vertex)?;
+        (&*self.db, &self.by_depth, &self.by_time)
+            .transaction(|(records, by_depth, by_time)| {
+                if let Some(old) = records.insert(id.as_bytes(), value.as_slice())? {
+                    unindex(by_depth, by_time, &id, &old)?;
+                }
+                by_depth.insert(&index_key(vertex.depth, &id)[..], &[])?;
+                by_time.insert(&index_key(vertex.transaction.data.timestamp, &id)[..], &[])?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("storing {}: {:?}", id, e))?;
         self.db.flush()?;
         Ok(())
     }
@@ -28,26 +179,688 @@ impl Storage {
     pub fn get_transaction(&self, id: &Hash) -> anyhow::Result<Option<Transaction>> {
         match self.db.get(id.as_bytes())? {
             Some(data) => {
-                let tx: Transaction = bincode::deserialize(&data)?;
-                Ok(Some(tx))
+                let vertex: DagVertex = bincode::deserialize(&data)?;
+                Ok(Some(vertex.transaction))
             }
             None => Ok(None),
         }
     }
 
-    /// Get all stored transactions
-    pub fn get_all_transactions(&self) -> anyhow::Result<Vec<Transaction>> {
-        let mut txs = Vec::new();
-        for result in self.db.iter() {
-            let (_, value) = result?;
-            let tx: Transaction = bincode::deserialize(&value)?;
-            txs.push(tx);
+    /// Get a stored vertex by ID
+    pub fn get_vertex(&self, id: &Hash) -> anyhow::Result<Option<DagVertex>> {
+        match self.db.get(id.as_bytes())? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
         }
-        Ok(txs)
     }
 
     /// Number of stored transactions
     pub fn count(&self) -> usize {
         self.db.len()
     }
+
+    /// Scan every vertex record, keeping undecodable ones as `None`
+    pub fn scan_vertices(&self) -> anyhow::Result<Vec<(IVec, Option<DagVertex>)>> {
+        let mut records = Vec::new();
+        for result in self.db.iter() {
+            let (key, value) = result?;
+            records.push((key, bincode::deserialize(&value).ok()));
+        }
+        Ok(records)
+    }
+
+    /// Move a vertex record by its raw key to the quarantine tree, out of
+    /// the DAG's way but kept for inspection or a later decoder
+    pub fn quarantine_record(&self, key: &[u8]) -> anyhow::Result<()> {
+        (&*self.db, &self.by_depth, &self.by_time, &self.quarantine)
+            .transaction(|(records, by_depth, by_time, quarantine)| {
+                let Some(old) = records.remove(key)? else {
+                    return Ok(());
+                };
+                if let Ok(id) = <[u8; 32]>::try_from(key) {
+                    unindex(by_depth, by_time, &Hash::from_bytes(id), &old)?;
+                }
+                quarantine.insert(key, old)?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("quarantining record: {:?}", e))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Number of records in quarantine
+    pub fn quarantined(&self) -> usize {
+        self.quarantine.len()
+    }
+
+    /// Whether the depth and timestamp indexes hold exactly one entry of
+    /// each per decodable vertex record
+    pub fn indexes_match_records(&self) -> anyhow::Result<bool> {
+        let mut by_depth = HashSet::new();
+        let mut by_time = HashSet::new();
+        for result in self.db.iter() {
+            let (key, value) = result?;
+            let Ok(id) = <[u8; 32]>::try_from(key.as_ref()) else {
+                continue;
+            };
+            let Ok(vertex) = bincode::deserialize::<DagVertex>(&value) else {
+                continue;
+            };
+            let id = Hash::from_bytes(id);
+            by_depth.insert(index_key(vertex.depth, &id));
+            by_time.insert(index_key(vertex.transaction.data.timestamp, &id));
+        }
+        Ok(index_matches(&self.by_depth, &by_depth)? && index_matches(&self.by_time, &by_time)?)
+    }
+
+    /// Remove vertex records by transaction ID, all in one transaction
+    pub fn remove_vertices(&self, ids: &[Hash]) -> anyhow::Result<()> {
+        (&*self.db, &self.by_depth, &self.by_time)
+            .transaction(|(records, by_depth, by_time)| {
+                for id in ids {
+                    if let Some(old) = records.remove(id.as_bytes())? {
+                        unindex(by_depth, by_time, id, &old)?;
+                    }
+                }
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("removing {} records: {:?}", ids.len(), e))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Vertices with a depth in `depths`, shallowest first
+    ///
+    /// Records are read lazily, so slices of history can be walked without
+    /// loading the whole DAG. Ties are broken by transaction ID.
+    pub fn vertices_by_depth(
+        &self,
+        depths: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item = anyhow::Result<DagVertex>> + '_ {
+        self.scan_index(&self.by_depth, depths)
+    }
+
+    /// Vertices with a timestamp (unix ms) in `times`, oldest first
+    pub fn vertices_by_time(
+        &self,
+        times: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item = anyhow::Result<DagVertex>> + '_ {
+        self.scan_index(&self.by_time, times)
+    }
+
+    /// Walk an index over `range`, resolving entries to their records
+    ///
+    /// Entries whose record has gone missing are skipped.
+    fn scan_index<'a>(
+        &'a self,
+        index: &Tree,
+        range: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item = anyhow::Result<DagVertex>> + 'a {
+        let low = match range.start_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => n.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let high = match range.end_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => n.checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        let entries = match (low, high) {
+            (Some(low), Some(high)) if low <= high => {
+                let last = index_key(high, &Hash::from_bytes([0xff; 32]));
+                Some(index.range(&low.to_be_bytes()[..]..=&last[..]))
+            }
+            _ => None,
+        };
+
+        entries
+            .into_iter()
+            .flatten()
+            .filter_map(move |entry| self.resolve_index_entry(entry).transpose())
+    }
+
+    /// Load the record an index entry points at
+    fn resolve_index_entry(&self, entry: sled::Result<(IVec, IVec)>) -> anyhow::Result<Option<DagVertex>> {
+        let (key, _) = entry?;
+        match self.db.get(&key[8..])? {
+            Some(value) => Ok(Some(bincode::deserialize(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the tags on a transaction (an empty list removes them)
+    pub fn set_tags(&self, id: &Hash, tags: &[String]) -> anyhow::Result<()> {
+        if tags.is_empty() {
+            self.tags.remove(id.as_bytes())?;
+        } else {
+            self.tags.insert(id.as_bytes(), bincode::serialize(tags)?)?;
+        }
+        self.tags.flush()?;
+        Ok(())
+    }
+
+    /// Get every tagged transaction
+    pub fn all_tags(&self) -> anyhow::Result<HashMap<Hash, Vec<String>>> {
+        let mut all = HashMap::new();
+        for result in self.tags.iter() {
+            let (key, value) = result?;
+            let key: [u8; 32] = key.as_ref().try_into()?;
+            all.insert(Hash::from_bytes(key), bincode::deserialize(&value)?);
+        }
+        Ok(all)
+    }
+
+    /// Store a DAG vertex together with the relay state its relay changed
+    ///
+    /// The record, its index entries, the changed relay counts, the totals
+    /// and the unclaimed proofs are written in one sled transaction.
+    pub fn put_vertex_with_relays(
+        &self,
+        vertex: &DagVertex,
+        totals: &RelayTotals,
+        pending: &[(RelayProof, u64)],
+    ) -> anyhow::Result<()> {
+        let id = vertex.id();
+        let value = bincode::serialize(vertex)?;
+        let totals = bincode::serialize(totals)?;
+        let pending = bincode::serialize(pending)?;
+        let mut unsaved = self.unsaved_relay_counts.lock().unwrap();
+        (&*self.db, &self.by_depth, &self.by_time, &self.relay_counts, &self.meta)
+            .transaction(|(records, by_depth, by_time, relay_counts, meta)| {
+                if let Some(old) = records.insert(id.as_bytes(), value.as_slice())? {
+                    unindex(by_depth, by_time, &id, &old)?;
+                }
+                by_depth.insert(&index_key(vertex.depth, &id)[..], &[])?;
+                by_time.insert(&index_key(vertex.transaction.data.timestamp, &id)[..], &[])?;
+                write_relay_state(relay_counts, meta, &unsaved, &totals, &pending)
+            })
+            .map_err(|e| anyhow::anyhow!("storing {}: {:?}", id, e))?;
+        unsaved.clear();
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Store the relay counts changed since the last write, the network-wide
+    /// totals and the unclaimed relay proofs, in one transaction
+    pub fn put_relay_state(&self, totals: &RelayTotals, pending: &[(RelayProof, u64)]) -> anyhow::Result<()> {
+        let totals = bincode::serialize(totals)?;
+        let pending = bincode::serialize(pending)?;
+        let mut unsaved = self.unsaved_relay_counts.lock().unwrap();
+        (&self.relay_counts, &self.meta)
+            .transaction(|(relay_counts, meta)| write_relay_state(relay_counts, meta, &unsaved, &totals, &pending))
+            .map_err(|e| anyhow::anyhow!("storing relay state: {:?}", e))?;
+        unsaved.clear();
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Load the network-wide relay totals
+    pub fn get_relay_totals(&self) -> anyhow::Result<RelayTotals> {
+        match self.meta.get(RELAY_TOTALS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(RelayTotals::default()),
+        }
+    }
+
+    /// Every stored relay count, by relayer key
+    pub fn relay_counts(&self) -> anyhow::Result<Vec<(PublicKey, u64)>> {
+        let mut counts = Vec::new();
+        for entry in self.relay_counts.iter() {
+            let (key, value) = entry?;
+            let key: [u8; 32] = key.as_ref().try_into()?;
+            let count = u64::from_be_bytes(value.as_ref().try_into()?);
+            counts.push((PublicKey::from_bytes(key), count));
+        }
+        Ok(counts)
+    }
+
+    /// Cold store for the relay tracker's per-relayer counts
+    ///
+    /// Counts saved through it are held back until the next
+    /// [`put_relay_state`](Self::put_relay_state) or
+    /// [`put_vertex_with_relays`](Self::put_vertex_with_relays).
+    pub fn relay_count_store(&self) -> SledRelayCounts {
+        SledRelayCounts {
+            tree: self.relay_counts.clone(),
+            unsaved: self.unsaved_relay_counts.clone(),
+        }
+    }
+
+    /// Webhook delivery queues, shareable with the notifier task
+    pub fn webhook_store(&self) -> WebhookStore {
+        WebhookStore {
+            queue: self.webhook_queue.clone(),
+            dead: self.webhook_dead.clone(),
+            receipts: self.webhook_receipts.clone(),
+        }
+    }
+
+    /// Handle to the peer audit log
+    pub fn peer_audit_store(&self) -> PeerAuditStore {
+        PeerAuditStore {
+            tree: self.peer_audit.clone(),
+            append_lock: Arc::default(),
+        }
+    }
+
+    /// Load the unclaimed relay proofs
+    pub fn get_pending_relays(&self) -> anyhow::Result<Vec<(RelayProof, u64)>> {
+        match self.meta.get(PENDING_RELAYS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Store the private payment pieces waiting to be forwarded
+    pub fn put_private_hops(&self, hops: &[PendingHop]) -> anyhow::Result<()> {
+        self.meta.insert(PRIVATE_HOPS_KEY, bincode::serialize(hops)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the private payment pieces waiting to be forwarded
+    pub fn get_private_hops(&self) -> anyhow::Result<Vec<PendingHop>> {
+        match self.meta.get(PRIVATE_HOPS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Store the node's off-DAG channel states
+    pub fn put_channel_entries(&self, entries: &HashMap<Hash, ChannelEntry>) -> anyhow::Result<()> {
+        self.meta.insert(CHANNELS_KEY, bincode::serialize(entries)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the node's off-DAG channel states
+    pub fn get_channel_entries(&self) -> anyhow::Result<HashMap<Hash, ChannelEntry>> {
+        match self.meta.get(CHANNELS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Store the snapshot of pruned history, replacing the previous one
+    pub fn put_balance_snapshot(&self, snapshot: &BalanceSnapshot) -> anyhow::Result<()> {
+        self.meta.insert(BALANCE_SNAPSHOT_KEY, bincode::serialize(snapshot)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the snapshot of pruned history, if the node has pruned
+    pub fn get_balance_snapshot(&self) -> anyhow::Result<Option<BalanceSnapshot>> {
+        match self.meta.get(BALANCE_SNAPSHOT_KEY)? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the identity rotations this node knows about
+    pub fn put_identity_rotations(&self, rotations: &[IdentityRotation]) -> anyhow::Result<()> {
+        self.meta.insert(IDENTITY_ROTATIONS_KEY, bincode::serialize(rotations)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Replace the persisted peer ban list
+    pub fn put_peer_bans(&self, bans: &HashMap<PeerId, Ban>) -> anyhow::Result<()> {
+        self.meta.insert(PEER_BANS_KEY, bincode::serialize(bans)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the peer ban list
+    pub fn get_peer_bans(&self) -> anyhow::Result<HashMap<PeerId, Ban>> {
+        match self.meta.get(PEER_BANS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Replace the persisted anchor peer addresses
+    pub fn put_anchor_peers(&self, addresses: &[String]) -> anyhow::Result<()> {
+        self.meta.insert(ANCHOR_PEERS_KEY, bincode::serialize(addresses)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the anchor peer addresses
+    pub fn get_anchor_peers(&self) -> anyhow::Result<Vec<String>> {
+        match self.meta.get(ANCHOR_PEERS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Replace the persisted gossip held for absent peers
+    pub fn put_dtn_queue(&self, queue: &DtnQueue) -> anyhow::Result<()> {
+        self.meta.insert(DTN_QUEUE_KEY, bincode::serialize(queue)?)?;
+        self.meta.flush()?;
+        Ok(())
+    }
+
+    /// Load the gossip held for absent peers, if any was saved
+    pub fn get_dtn_queue(&self) -> anyhow::Result<Option<DtnQueue>> {
+        match self.meta.get(DTN_QUEUE_KEY)? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the known identity rotations, oldest first
+    pub fn get_identity_rotations(&self) -> anyhow::Result<Vec<IdentityRotation>> {
+        match self.meta.get(IDENTITY_ROTATIONS_KEY)? {
+            Some(data) => Ok(bincode::deserialize(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Reserve the next privacy subkey index (never handed out twice)
+    pub fn next_privacy_subkey(&self) -> anyhow::Result<u64> {
+        self.reserve_index(NEXT_SUBKEY_KEY)
+    }
+
+    /// Reserve the next payment link key index (never handed out twice)
+    pub fn next_payment_link_key(&self) -> anyhow::Result<u64> {
+        self.reserve_index(NEXT_LINK_KEY)
+    }
+
+    /// Store a payment link, replacing the one with the same ID
+    pub fn put_payment_link(&self, link: &PaymentLink) -> anyhow::Result<()> {
+        self.payment_links.insert(link.id.as_bytes(), bincode::serialize(link)?)?;
+        self.payment_links.flush()?;
+        Ok(())
+    }
+
+    /// Load every payment link, oldest first
+    pub fn get_payment_links(&self) -> anyhow::Result<Vec<PaymentLink>> {
+        let mut links = Vec::new();
+        for result in self.payment_links.iter() {
+            let (_, value) = result?;
+            links.push(bincode::deserialize::<PaymentLink>(&value)?);
+        }
+        links.sort_by_key(|link| link.created_ms);
+        Ok(links)
+    }
+
+    /// Hand out the counter under `key` and advance it
+    fn reserve_index(&self, key: &[u8]) -> anyhow::Result<u64> {
+        let previous = self.meta.fetch_and_update(key, |old| {
+            let next = old.map_or(0, |b| u64::from_be_bytes(b.try_into().unwrap_or_default())) + 1;
+            Some(next.to_be_bytes().to_vec())
+        })?;
+        self.meta.flush()?;
+        Ok(previous.map_or(0, |b| u64::from_be_bytes(b.as_ref().try_into().unwrap_or_default())))
+    }
+
+    /// Copy every tree into a new database at `dest`, stamped with `now_ms`
+    pub fn copy_to(&self, dest: &Path, now_ms: u64) -> anyhow::Result<()> {
+        let copy = sled::open(dest)?;
+        copy.import(self.db.export());
+        copy.open_tree("meta")?.insert(REPLICATED_AT_KEY, &now_ms.to_be_bytes())?;
+        copy.flush()?;
+        Ok(())
+    }
+
+    /// When this database was copied from a live one, if it is a copy
+    pub fn replicated_at_ms(&self) -> anyhow::Result<Option<u64>> {
+        Ok(self
+            .meta
+            .get(REPLICATED_AT_KEY)?
+            .and_then(|b| b.as_ref().try_into().ok())
+            .map(u64::from_be_bytes))
+    }
+}
+
+/// Index key: the big-endian `prefix` (so keys sort numerically), then the txid
+fn index_key(prefix: u64, id: &Hash) -> [u8; 40] {
+    let mut key = [0u8; 40];
+    key[..8].copy_from_slice(&prefix.to_be_bytes());
+    key[8..].copy_from_slice(id.as_bytes());
+    key
+}
+
+/// Whether an index tree holds exactly the `expected` keys
+fn index_matches(index: &Tree, expected: &HashSet<[u8; 40]>) -> anyhow::Result<bool> {
+    if index.len() != expected.len() {
+        return Ok(false);
+    }
+    for result in index.iter().keys() {
+        if !<[u8; 40]>::try_from(result?.as_ref()).is_ok_and(|key| expected.contains(&key)) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Drop the index entries of a replaced or removed record
+fn unindex(
+    by_depth: &TransactionalTree,
+    by_time: &TransactionalTree,
+    id: &Hash,
+    old: &[u8],
+) -> Result<(), ConflictableTransactionError> {
+    if let Ok(old) = bincode::deserialize::<DagVertex>(old) {
+        by_depth.remove(&index_key(old.depth, id)[..])?;
+        by_time.remove(&index_key(old.transaction.data.timestamp, id)[..])?;
+    }
+    Ok(())
+}
+
+/// Write relay counts, totals and unclaimed proofs inside a transaction
+fn write_relay_state(
+    relay_counts: &TransactionalTree,
+    meta: &TransactionalTree,
+    counts: &HashMap<PublicKey, u64>,
+    totals: &[u8],
+    pending: &[u8],
+) -> Result<(), ConflictableTransactionError> {
+    for (relayer, count) in counts {
+        relay_counts.insert(relayer.as_bytes(), &count.to_be_bytes())?;
+    }
+    meta.insert(RELAY_TOTALS_KEY, totals)?;
+    meta.insert(PENDING_RELAYS_KEY, pending)?;
+    Ok(())
+}
+
+/// Relay counts kept in a sled tree, keyed by relayer public key
+///
+/// Saved counts wait in memory until the storage writes them together with
+/// the rest of the relay state, so counts, totals and the vertices that
+/// produced them never disagree after a crash.
+pub struct SledRelayCounts {
+    tree: Tree,
+    unsaved: Arc<Mutex<HashMap<PublicKey, u64>>>,
+}
+
+impl RelayCountStore for SledRelayCounts {
+    fn load(&self, relayer: &PublicKey) -> Option<u64> {
+        if let Some(count) = self.unsaved.lock().unwrap().get(relayer) {
+            return Some(*count);
+        }
+        let value = self.tree.get(relayer.as_bytes()).ok()??;
+        Some(u64::from_be_bytes(value.as_ref().try_into().ok()?))
+    }
+
+    fn save(&mut self, relayer: &PublicKey, count: u64) {
+        self.unsaved.lock().unwrap().insert(relayer.clone(), count);
+    }
+}
+
+/// Peer audit entries, keyed by big-endian sequence number so they iterate
+/// oldest first
+#[derive(Clone)]
+pub struct PeerAuditStore {
+    tree: Tree,
+    /// Appends read the last sequence number, so they take turns
+    append_lock: Arc<Mutex<()>>,
+}
+
+impl PeerAuditStore {
+    /// Append an entry, assigning the next sequence number, then rotate out
+    /// entries more than `max_entries` behind it or written before `cutoff_ms`
+    pub fn append(&self, entry: &mut PeerAuditEntry, max_entries: usize, cutoff_ms: u64) -> anyhow::Result<()> {
+        let _guard = self.append_lock.lock().unwrap();
+        let last = self.tree.last()?.map_or(0, |(key, _)| audit_seq(&key));
+        entry.seq = last + 1;
+        self.tree.insert(entry.seq.to_be_bytes(), bincode::serialize(entry)?)?;
+
+        while let Some((key, value)) = self.tree.first()? {
+            let seq = audit_seq(&key);
+            let expired = bincode::deserialize::<PeerAuditEntry>(&value)
+                .map_or(true, |old| old.at_ms < cutoff_ms);
+            if seq.saturating_add(max_entries as u64) > entry.seq && !expired {
+                break;
+            }
+            self.tree.remove(key)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` entries accepted by `filter`, newest first
+    pub fn newest(&self, limit: usize, filter: impl Fn(&PeerAuditEntry) -> bool) -> anyhow::Result<Vec<PeerAuditEntry>> {
+        let mut entries = Vec::new();
+        for result in self.tree.iter().rev() {
+            if entries.len() >= limit {
+                break;
+            }
+            let (_, value) = result?;
+            let entry: PeerAuditEntry = bincode::deserialize(&value)?;
+            if filter(&entry) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn audit_seq(key: &[u8]) -> u64 {
+    <[u8; 8]>::try_from(key).map_or(0, u64::from_be_bytes)
+}
+
+/// Webhook deliveries in flight, dead letters and delivery receipts
+///
+/// Moves between the trees happen in one sled transaction, so a delivery
+/// is never both queued and dead (or lost between the two).
+#[derive(Clone)]
+pub struct WebhookStore {
+    queue: Tree,
+    dead: Tree,
+    receipts: Tree,
+}
+
+impl WebhookStore {
+    /// Queue a delivery, or update a queued one after an attempt
+    pub fn enqueue(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        self.queue.insert(delivery.id.as_bytes(), bincode::serialize(delivery)?)?;
+        self.queue.flush()?;
+        Ok(())
+    }
+
+    /// Number of deliveries still being attempted
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Queued deliveries, oldest first
+    pub fn queued(&self) -> anyhow::Result<Vec<Delivery>> {
+        let mut deliveries = decode_deliveries(&self.queue)?;
+        deliveries.sort_by_key(|d| d.created_ms);
+        Ok(deliveries)
+    }
+
+    /// Queued deliveries whose next attempt is due at `now_ms`
+    pub fn due(&self, now_ms: u64) -> anyhow::Result<Vec<Delivery>> {
+        let mut due = self.queued()?;
+        due.retain(|d| d.next_attempt_ms <= now_ms);
+        Ok(due)
+    }
+
+    /// Move a delivery that ran out of attempts to the dead-letter queue
+    pub fn bury(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        let value = bincode::serialize(delivery)?;
+        (&self.queue, &self.dead)
+            .transaction(|(queue, dead)| {
+                queue.remove(delivery.id.as_bytes())?;
+                dead.insert(delivery.id.as_bytes(), value.as_slice())?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("dead-lettering {}: {:?}", delivery.id, e))?;
+        self.dead.flush()?;
+        Ok(())
+    }
+
+    /// Dead-lettered deliveries, oldest first
+    pub fn dead_letters(&self) -> anyhow::Result<Vec<Delivery>> {
+        let mut deliveries = decode_deliveries(&self.dead)?;
+        deliveries.sort_by_key(|d| d.created_ms);
+        Ok(deliveries)
+    }
+
+    /// Move a dead letter back to the queue for a fresh round of attempts
+    ///
+    /// Returns the requeued delivery, or `None` if `id` isn't dead-lettered.
+    pub fn replay(&self, id: &str, now_ms: u64) -> anyhow::Result<Option<Delivery>> {
+        let Some(value) = self.dead.get(id.as_bytes())? else {
+            return Ok(None);
+        };
+        let mut delivery: Delivery = bincode::deserialize(&value)?;
+        delivery.reset(now_ms);
+        let requeued = bincode::serialize(&delivery)?;
+        (&self.queue, &self.dead)
+            .transaction(|(queue, dead)| {
+                dead.remove(id.as_bytes())?;
+                queue.insert(id.as_bytes(), requeued.as_slice())?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("replaying {}: {:?}", id, e))?;
+        self.queue.flush()?;
+        Ok(Some(delivery))
+    }
+
+    /// Drop a delivered payload from the queue and keep its receipt
+    pub fn complete(&self, id: &str, receipt: &DeliveryReceipt) -> anyhow::Result<()> {
+        let value = bincode::serialize(receipt)?;
+        (&self.queue, &self.receipts)
+            .transaction(|(queue, receipts)| {
+                queue.remove(id.as_bytes())?;
+                receipts.insert(id.as_bytes(), value.as_slice())?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| anyhow::anyhow!("completing {}: {:?}", id, e))?;
+        self.receipts.flush()?;
+        Ok(())
+    }
+
+    /// Receipt of a completed delivery
+    pub fn receipt(&self, id: &str) -> anyhow::Result<Option<DeliveryReceipt>> {
+        match self.receipts.get(id.as_bytes())? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Drop receipts of deliveries completed before `cutoff_ms`
+    pub fn prune_receipts(&self, cutoff_ms: u64) -> anyhow::Result<()> {
+        for result in self.receipts.iter() {
+            let (key, value) = result?;
+            let expired = bincode::deserialize::<DeliveryReceipt>(&value)
+                .map_or(true, |receipt| receipt.delivered_ms < cutoff_ms);
+            if expired {
+                self.receipts.remove(key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_deliveries(tree: &Tree) -> anyhow::Result<Vec<Delivery>> {
+    let mut deliveries = Vec::new();
+    for result in tree.iter() {
+        let (_, value) = result?;
+        deliveries.push(bincode::deserialize(&value)?);
+    }
+    Ok(deliveries)
 }
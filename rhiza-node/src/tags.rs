@@ -0,0 +1,84 @@
+use rhiza_core::crypto::{Hash, PublicKey};
+use rhiza_core::dag::vertex::Dag;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Maximum number of tags on a single transaction
+pub const MAX_TAGS_PER_TRANSACTION: usize = 8;
+
+/// Maximum length of a tag
+const MAX_TAG_LEN: usize = 32;
+
+/// Normalize a user-supplied tag (`" Groceries "` → `"groceries"`)
+///
+/// Tags are lowercase ASCII letters, digits, `-` and `_`.
+pub fn normalize_tag(raw: &str) -> Result<String, String> {
+    let tag = raw.trim().to_ascii_lowercase();
+    if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+        return Err(format!("Tag must be 1-{} characters: {:?}", MAX_TAG_LEN, raw));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Tag may only contain a-z, 0-9, '-' and '_': {:?}", raw));
+    }
+    Ok(tag)
+}
+
+/// Normalize and de-duplicate a list of tags
+pub fn normalize_tags(raw: &[String]) -> Result<Vec<String>, String> {
+    let mut tags = Vec::new();
+    for tag in raw {
+        let tag = normalize_tag(tag)?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.len() > MAX_TAGS_PER_TRANSACTION {
+        return Err(format!("At most {} tags per transaction", MAX_TAGS_PER_TRANSACTION));
+    }
+    Ok(tags)
+}
+
+/// Totals for one tag, from the owner's point of view
+#[derive(Debug, Default, Serialize)]
+pub struct TagTotals {
+    pub tag: String,
+    pub count: u64,
+    pub incoming: u64,
+    pub outgoing: u64,
+    /// incoming − outgoing, in smallest units
+    pub net: i128,
+}
+
+/// Per-tag totals over the owner's tagged transactions
+pub fn tag_report(
+    dag: &Dag,
+    owner: &PublicKey,
+    tags: &HashMap<Hash, Vec<String>>,
+) -> Vec<TagTotals> {
+    let mut report: BTreeMap<&str, TagTotals> = BTreeMap::new();
+
+    for (id, tx_tags) in tags {
+        let Some(vertex) = dag.get(id) else {
+            continue;
+        };
+        let data = &vertex.transaction.data;
+        for tag in tx_tags {
+            let totals = report.entry(tag).or_insert_with(|| TagTotals {
+                tag: tag.clone(),
+                ..Default::default()
+            });
+            totals.count += 1;
+            // Same accounting as Dag::get_balance
+            let incoming = data.credit_for(owner);
+            let outgoing = data.debit_for(owner);
+            totals.incoming = totals.incoming.saturating_add(incoming.min(u64::MAX as u128) as u64);
+            totals.outgoing = totals.outgoing.saturating_add(outgoing.min(u64::MAX as u128) as u64);
+            totals.net += incoming as i128 - outgoing as i128;
+        }
+    }
+
+    report.into_values().collect()
+}
@@ -0,0 +1,95 @@
+use rhiza_core::crypto::Hash;
+use rhiza_core::network::mesh::TransportType;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+/// Transactions whose lifecycle is kept; the oldest trace goes first
+const MAX_TRACED: usize = 10_000;
+
+/// Steps kept per transaction, so a transaction approved over and over
+/// doesn't grow its trace without bound
+const MAX_STEPS: usize = 48;
+
+/// Where the first copy of a transaction came from
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "via", rename_all = "snake_case")]
+pub enum Origin {
+    /// Made or submitted through this node
+    Local,
+    /// Flooded by a peer
+    Gossip { peer_id: String, transport: TransportType },
+    /// Passed along a Dandelion stem by a peer
+    Stem { peer_id: String, transport: TransportType },
+    /// Fetched while syncing history
+    Sync,
+}
+
+/// One thing that happened to a transaction on this node
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum TraceStage {
+    FirstSeen { origin: Origin },
+    Validated,
+    Rejected { reason: String },
+    Inserted { depth: u64 },
+    /// Sent on to peers: flooded to `peers` of them, or one along a stem
+    Gossiped { peers: usize, stem: bool },
+    /// A new transaction approved this one directly; `approvers` counts the
+    /// distinct keys approving it so far, directly or not
+    Approved { by: String, approvers: u64 },
+    Finalized,
+}
+
+/// A stage and when it was reached
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    /// Unix ms
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub stage: TraceStage,
+}
+
+/// Lifecycle events of the transactions this node saw recently, kept so
+/// `GET /tx/:id/trace` can say where a payment is
+///
+/// A trace starts when a transaction first arrives (or is made here);
+/// later events for transactions without one, such as approvals of old
+/// history, aren't kept. Nothing is persisted, so traces start over after a
+/// restart.
+#[derive(Debug, Default)]
+pub struct TraceLog {
+    traces: HashMap<Hash, Vec<TraceStep>>,
+    /// Traced ids, oldest first
+    order: VecDeque<Hash>,
+}
+
+impl TraceLog {
+    /// Start a trace, unless the transaction already has one
+    pub fn first_seen(&mut self, id: Hash, origin: Origin, now_ms: u64) {
+        if self.traces.contains_key(&id) {
+            return;
+        }
+        if self.order.len() >= MAX_TRACED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.traces.remove(&oldest);
+            }
+        }
+        self.order.push_back(id);
+        let stage = TraceStage::FirstSeen { origin };
+        self.traces.insert(id, vec![TraceStep { at_ms: now_ms, stage }]);
+    }
+
+    /// Add a step to a transaction's trace, if it has one
+    pub fn record(&mut self, id: &Hash, stage: TraceStage, now_ms: u64) {
+        if let Some(steps) = self.traces.get_mut(id) {
+            // Keep room for the step that matters most
+            if steps.len() < MAX_STEPS - 1 || (steps.len() < MAX_STEPS && matches!(stage, TraceStage::Finalized)) {
+                steps.push(TraceStep { at_ms: now_ms, stage });
+            }
+        }
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&[TraceStep]> {
+        self.traces.get(id).map(Vec::as_slice)
+    }
+}
@@ -0,0 +1,248 @@
+use crate::events::NodeEvent;
+use crate::keyfile;
+use crate::SharedState;
+use anyhow::{Context, Result};
+use rhiza_core::crypto::PublicKey;
+use rhiza_core::release::{self, ReleaseManifest, Version};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Directory verified downloads are saved to, inside the data directory
+pub const UPDATES_DIR: &str = "updates";
+
+/// How long the release server gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest manifest that will be parsed
+const MAX_MANIFEST_BYTES: usize = 64 * 1024;
+
+/// Release update checks (`update` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Check for new releases; off unless the operator opts in
+    pub enabled: bool,
+    /// Where the signed release manifest is published
+    pub manifest_url: String,
+    /// Seconds between checks
+    pub interval_secs: u64,
+    /// Also download the build for this platform to `updates/` once the
+    /// manifest verifies; it is never installed automatically
+    pub download: bool,
+    /// Hex keys trusted to sign manifests besides the built-in release keys
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub release_keys: Vec<String>,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        UpdateConfig {
+            enabled: false,
+            manifest_url: "https://releases.rhiza-protocol.org/manifest.json".to_string(),
+            interval_secs: 6 * 60 * 60,
+            download: false,
+            release_keys: Vec::new(),
+        }
+    }
+}
+
+impl UpdateConfig {
+    /// The built-in release keys plus the configured ones
+    pub fn trusted_keys(&self) -> Result<Vec<PublicKey>> {
+        let mut keys = release::release_public_keys();
+        for key in &self.release_keys {
+            let bytes: [u8; 32] = hex::decode(key)
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid release key {}", key))?;
+            keys.push(PublicKey::from_bytes(bytes));
+        }
+        Ok(keys)
+    }
+}
+
+/// What the last update check found, for the API
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateStatus {
+    pub enabled: bool,
+    pub current_version: String,
+    /// When the manifest was last fetched and verified (unix ms)
+    pub checked_ms: Option<u64>,
+    /// A newer release, if the manifest announces one
+    pub available: Option<AvailableUpdate>,
+    /// Why the last check failed
+    pub error: Option<String>,
+}
+
+impl Default for UpdateStatus {
+    fn default() -> Self {
+        UpdateStatus {
+            enabled: false,
+            current_version: env!("CARGO_PKG_VERSION").to_string(),
+            checked_ms: None,
+            available: None,
+            error: None,
+        }
+    }
+}
+
+/// A verified release newer than the running node
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableUpdate {
+    pub version: String,
+    pub critical: bool,
+    pub notes: String,
+    pub released_ms: u64,
+    /// Build for this platform, if the release has one
+    pub download_url: Option<String>,
+    /// Where the verified build was saved, when downloading is on
+    pub downloaded: Option<PathBuf>,
+}
+
+/// Platform name used in release manifests, e.g. `linux-x86_64`
+fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Fetch the manifest and return it if it's signed by a trusted key
+async fn fetch_manifest(
+    client: &reqwest::Client,
+    config: &UpdateConfig,
+    trusted: &[PublicKey],
+) -> Result<(ReleaseManifest, Version)> {
+    let response = client.get(&config.manifest_url).send().await?.error_for_status()?;
+    let bytes = response.bytes().await?;
+    if bytes.len() > MAX_MANIFEST_BYTES {
+        anyhow::bail!("manifest of {} bytes is too large", bytes.len());
+    }
+    let manifest: ReleaseManifest = serde_json::from_slice(&bytes).context("not a release manifest")?;
+    let version = manifest.verify(trusted, 1)?;
+    Ok((manifest, version))
+}
+
+/// Download the release's build for this platform and check its hash
+async fn download(client: &reqwest::Client, manifest: &ReleaseManifest, data_path: &Path) -> Result<Option<PathBuf>> {
+    let Some(build) = manifest.download_for(&platform()) else {
+        return Ok(None);
+    };
+    let path = data_path
+        .join(UPDATES_DIR)
+        .join(format!("rhiza-node-{}-{}", manifest.version, build.target));
+    if path.exists() {
+        return Ok(Some(path));
+    }
+
+    let bytes = client.get(&build.url).send().await?.error_for_status()?.bytes().await?;
+    if !build.matches(&bytes) {
+        anyhow::bail!("download from {} doesn't match the signed hash", build.url);
+    }
+    std::fs::create_dir_all(path.parent().expect("updates dir"))?;
+    std::fs::write(&path, &bytes)?;
+    Ok(Some(path))
+}
+
+/// Check for new releases every `interval_secs` until the node shuts down
+///
+/// A newer release is logged, shown at `GET /update` and published as an
+/// `update_available` event (which webhooks can deliver), once per version.
+/// Manifests that aren't signed by a trusted release key are ignored.
+pub async fn run(state: SharedState, config: UpdateConfig, data_path: PathBuf) {
+    let trusted = match config.trusted_keys() {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!("Update checks disabled: {}", e);
+            return;
+        }
+    };
+    let current: Version = env!("CARGO_PKG_VERSION").parse().expect("crate version is MAJOR.MINOR.PATCH");
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Update checks disabled: could not build HTTP client: {}", e);
+            return;
+        }
+    };
+    info!("🔄 Checking {} for new releases", config.manifest_url);
+
+    let mut notified: Option<Version> = None;
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(60)));
+    loop {
+        interval.tick().await;
+        let (manifest, version) = match fetch_manifest(&client, &config, &trusted).await {
+            Ok(found) => found,
+            Err(e) => {
+                warn!("Update check failed: {:#}", e);
+                state.write().await.update.error = Some(format!("{:#}", e));
+                continue;
+            }
+        };
+
+        let mut available = None;
+        if version > current {
+            let downloaded = if config.download {
+                match download(&client, &manifest, &data_path).await {
+                    Ok(path) => path,
+                    Err(e) => {
+                        warn!("Could not download release {}: {:#}", version, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if notified != Some(version) {
+                if manifest.critical {
+                    warn!("🚨 Critical release {} is available (running {}): {}", version, current, manifest.notes);
+                } else {
+                    info!("🆕 Release {} is available (running {})", version, current);
+                }
+                if let Some(path) = &downloaded {
+                    info!("   Verified build saved to {}", path.display());
+                }
+            }
+            available = Some(AvailableUpdate {
+                version: version.to_string(),
+                critical: manifest.critical,
+                notes: manifest.notes.clone(),
+                released_ms: manifest.released_ms,
+                download_url: manifest.download_for(&platform()).map(|d| d.url.clone()),
+                downloaded,
+            });
+        }
+
+        let mut state = state.write().await;
+        if available.is_some() && notified != Some(version) {
+            state.events.publish(NodeEvent::UpdateAvailable {
+                version: version.to_string(),
+                critical: manifest.critical,
+            });
+            notified = Some(version);
+        }
+        state.update.checked_ms = Some(now_ms());
+        state.update.available = available;
+        state.update.error = None;
+    }
+}
+
+/// Add the node key's signature to a release manifest file
+pub fn sign(data_path: &Path, password_file: Option<&Path>, file: &Path) -> Result<()> {
+    let key_path = keyfile::identity_path(data_path);
+    if !key_path.exists() {
+        anyhow::bail!("Node not initialized. Run 'rhiza-node init' first.");
+    }
+    let data = std::fs::read(file).with_context(|| format!("Could not read {}", file.display()))?;
+    let mut manifest: ReleaseManifest = serde_json::from_slice(&data).context("Not a release manifest")?;
+    manifest.version.parse::<Version>()?;
+    let keypair = keyfile::unlock(&key_path, password_file)?;
+    manifest.sign(&keypair);
+    std::fs::write(file, serde_json::to_string_pretty(&manifest)?)?;
+    println!("✍️  Signed release {} as {}", manifest.version, keypair.public_key);
+    println!("   {} signatures on this manifest", manifest.signatures.len());
+    Ok(())
+}
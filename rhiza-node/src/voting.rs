@@ -0,0 +1,55 @@
+use crate::event_log::Incident;
+use crate::SharedState;
+use rhiza_core::consensus::VotingConfig;
+use rhiza_core::crypto::Hash;
+use rhiza_core::network::gossip::GossipMessage;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Run conflict voting rounds until the node shuts down
+///
+/// Each round closes the one before it, picks up new conflicts, hands the
+/// preferred members to conflict resolution, then queries a fresh sample of peers about every
+/// conflict still open. Nothing is sent while there are no open conflicts.
+pub async fn run(state: SharedState, config: VotingConfig) {
+    let mut interval = tokio::time::interval(Duration::from_millis(config.round_ms.max(100)));
+    loop {
+        interval.tick().await;
+        let mut state = state.write().await;
+        let state = &mut *state;
+        let mut rng = rand::thread_rng();
+        let before: HashMap<Hash, Hash> =
+            state.voting.votes().map(|(key, vote)| (*key, vote.opinion)).collect();
+        if state.voting.finish_round(&mut rng) {
+            info!("🗳️  Conflict vote changed sides");
+        }
+        state.voting.sync(&state.dag);
+        for (key, vote) in state.voting.votes() {
+            let incident = match before.get(key) {
+                None => Incident::ConflictDetected {
+                    conflict: key.to_string(),
+                    members: vote.members.iter().map(Hash::to_string).collect(),
+                },
+                Some(was) if *was != vote.opinion => Incident::ConflictSwitched {
+                    conflict: key.to_string(),
+                    from: was.to_string(),
+                    to: vote.opinion.to_string(),
+                },
+                Some(_) => continue,
+            };
+            state.event_log.record(incident);
+        }
+        let preferred = state.voting.preferred();
+        if &preferred != state.dag.conflicts().preferred() {
+            state.dag.set_conflict_preferences(preferred);
+        }
+        let peers = state.network.peer_ids();
+        if let Some((queried, conflicts)) = state.voting.start_round(&peers, &mut rng) {
+            debug!("Asking {} peers about {} conflicts", queried.len(), conflicts.len());
+            for peer in &queried {
+                state.network.send_to(peer, GossipMessage::OpinionQuery { conflicts: conflicts.clone() });
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use rhiza_core::crypto::keys::KeyPair;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Unlocking the node wallet before the API may spend from it
+/// (`wallet_session` in config.json)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletSessionConfig {
+    /// Whether spends through the API need an unlocked session
+    pub required: bool,
+    /// Seconds a session lasts when the unlock doesn't say
+    pub default_secs: u64,
+    /// Longest a session may last
+    pub max_secs: u64,
+}
+
+impl Default for WalletSessionConfig {
+    fn default() -> Self {
+        WalletSessionConfig {
+            required: false,
+            default_secs: 300,
+            max_secs: 3_600,
+        }
+    }
+}
+
+/// The key API spends are signed with
+///
+/// With `required` on, the key is decrypted by `POST /wallet/unlock` and
+/// held until the session runs out or `POST /wallet/lock` ends it; dropping
+/// it wipes it from memory. Otherwise the wallet is unlocked at startup and
+/// stays so. The node's own duties (Noise, relay proofs, channels) sign with
+/// its identity key, which is never held here.
+pub struct WalletSession {
+    config: WalletSessionConfig,
+    /// Keystore the password is checked against
+    keystore_path: PathBuf,
+    unlocked: Option<Unlocked>,
+}
+
+struct Unlocked {
+    /// Decrypted from the keystore; wiped when dropped
+    keypair: KeyPair,
+    until_ms: u64,
+}
+
+impl WalletSession {
+    /// A session over the wallet at `keystore_path`, unlocked for good with
+    /// `keypair` if one is given
+    pub fn new(config: WalletSessionConfig, keystore_path: PathBuf, keypair: Option<KeyPair>) -> Self {
+        let unlocked = keypair.map(|keypair| Unlocked { keypair, until_ms: u64::MAX });
+        WalletSession { config, keystore_path, unlocked }
+    }
+
+    pub fn config(&self) -> &WalletSessionConfig {
+        &self.config
+    }
+
+    pub fn keystore_path(&self) -> &PathBuf {
+        &self.keystore_path
+    }
+
+    /// How long a session asked to last `requested` seconds may last, in ms
+    pub fn duration_ms(&self, requested: Option<u64>) -> u64 {
+        let secs = requested.unwrap_or(self.config.default_secs);
+        secs.clamp(1, self.config.max_secs.max(1)).saturating_mul(1_000)
+    }
+
+    /// Hold the decrypted key until `until_ms`, replacing any earlier session
+    pub fn unlock(&mut self, keypair: KeyPair, until_ms: u64) {
+        self.unlocked = Some(Unlocked { keypair, until_ms });
+    }
+
+    /// End the session, wiping the key; returns whether one was open
+    pub fn lock(&mut self) -> bool {
+        self.unlocked.take().is_some()
+    }
+
+    /// Lock the wallet if its session ran out by `now_ms`
+    pub fn expire(&mut self, now_ms: u64) {
+        if self.unlocked_until(now_ms).is_none() {
+            self.unlocked = None;
+        }
+    }
+
+    /// When the open session ends (unix ms), if one is open at `now_ms`
+    pub fn unlocked_until(&self, now_ms: u64) -> Option<u64> {
+        self.unlocked.as_ref().map(|u| u.until_ms).filter(|until| *until > now_ms)
+    }
+
+    /// The wallet key, if the wallet is unlocked at `now_ms`
+    pub fn key(&self, now_ms: u64) -> Option<&KeyPair> {
+        self.unlocked.as_ref().filter(|u| u.until_ms > now_ms).map(|u| &u.keypair)
+    }
+
+    /// Whether the API may spend from the wallet at `now_ms`
+    pub fn may_spend(&self, now_ms: u64) -> bool {
+        self.key(now_ms).is_some()
+    }
+}
@@ -0,0 +1,311 @@
+use crate::events::EventRecord;
+use crate::storage::WebhookStore;
+use crate::SharedState;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rhiza_core::crypto::keys::KeyPair;
+use rhiza_core::crypto::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Attempts before a delivery is moved to the dead-letter queue
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// Delay before the first retry; doubles with every failed attempt
+const BASE_RETRY_DELAY_MS: u64 = 5_000;
+
+/// Longest delay between two attempts
+const MAX_RETRY_DELAY_MS: u64 = 60 * 60 * 1_000;
+
+/// How long an endpoint gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait for new events before retrying due deliveries
+const EVENT_WAIT: Duration = Duration::from_secs(1);
+
+/// How long delivery receipts are kept
+pub const RECEIPT_RETENTION_MS: u64 = 30 * 24 * 60 * 60 * 1_000;
+
+/// How often old receipts are pruned
+const RECEIPT_PRUNE_INTERVAL_MS: u64 = 60 * 60 * 1_000;
+
+/// Event types delivered when a webhook doesn't list any
+const DEFAULT_EVENTS: &[&str] = &["tx_received", "tx_finalized"];
+
+/// Domain separator of the statement a receipt signs
+const RECEIPT_DOMAIN: &[u8] = b"rhiza webhook receipt v1";
+
+/// An endpoint that node events are POSTed to (`webhooks` in config.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Shared secret for the `X-Rhiza-Signature` HMAC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Event types to deliver (default: `tx_received` and `tx_finalized`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// Only deliver events involving these hex public keys (default: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<String>,
+}
+
+impl WebhookConfig {
+    /// Whether `record` should be delivered to this endpoint
+    fn wants(&self, record: &EventRecord) -> bool {
+        let name = record.event.name();
+        let type_matches = if self.events.is_empty() {
+            DEFAULT_EVENTS.contains(&name)
+        } else {
+            self.events.iter().any(|e| e == name)
+        };
+        let account_matches = self.accounts.is_empty()
+            || record
+                .event
+                .accounts()
+                .is_some_and(|involved| involved.iter().any(|a| self.accounts.iter().any(|w| w == a)));
+        type_matches && account_matches
+    }
+}
+
+/// One event on its way to one endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    /// Random ID, sent as `X-Rhiza-Delivery` so endpoints can deduplicate
+    pub id: String,
+    pub url: String,
+    /// Event type, sent as `X-Rhiza-Event`
+    pub event: String,
+    /// JSON body
+    pub payload: String,
+    /// Attempts made so far
+    pub attempts: u32,
+    /// When to try next (unix ms)
+    pub next_attempt_ms: u64,
+    pub last_error: Option<String>,
+    pub created_ms: u64,
+}
+
+impl Delivery {
+    fn new(url: &str, record: &EventRecord, now_ms: u64) -> Self {
+        let mut id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut id);
+        Delivery {
+            id: hex::encode(id),
+            url: url.to_string(),
+            event: record.event.name().to_string(),
+            payload: serde_json::to_string(record).expect("events serialize to JSON"),
+            attempts: 0,
+            next_attempt_ms: now_ms,
+            last_error: None,
+            created_ms: now_ms,
+        }
+    }
+
+    /// Record a failed attempt; returns whether the delivery is out of attempts
+    fn fail(&mut self, error: String, now_ms: u64) -> bool {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        self.next_attempt_ms = now_ms + retry_delay(self.attempts);
+        self.attempts >= MAX_ATTEMPTS
+    }
+
+    /// Put a dead letter back in line for a fresh round of attempts
+    pub fn reset(&mut self, now_ms: u64) {
+        self.attempts = 0;
+        self.next_attempt_ms = now_ms;
+    }
+}
+
+/// Exponential backoff after `attempts` failures
+pub fn retry_delay(attempts: u32) -> u64 {
+    let doublings = attempts.saturating_sub(1).min(20);
+    (BASE_RETRY_DELAY_MS << doublings).min(MAX_RETRY_DELAY_MS)
+}
+
+/// Proof, signed by the node key, that a payload was accepted by an endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryReceipt {
+    pub delivery_id: String,
+    pub url: String,
+    /// BLAKE3 hash of the delivered body (hex)
+    pub payload_hash: String,
+    /// HTTP status the endpoint answered with
+    pub status: u16,
+    pub attempts: u32,
+    pub delivered_ms: u64,
+    pub node_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl DeliveryReceipt {
+    fn new(keypair: &KeyPair, delivery: &Delivery, status: u16, now_ms: u64) -> Self {
+        let payload_hash = blake3::hash(delivery.payload.as_bytes()).to_hex().to_string();
+        let statement = Self::statement(&delivery.id, &delivery.url, &payload_hash, status, now_ms);
+        DeliveryReceipt {
+            delivery_id: delivery.id.clone(),
+            url: delivery.url.clone(),
+            payload_hash,
+            status,
+            attempts: delivery.attempts + 1,
+            delivered_ms: now_ms,
+            node_key: keypair.public_key.clone(),
+            signature: keypair.sign(&statement),
+        }
+    }
+
+    /// Check the node's signature
+    pub fn verify(&self) -> bool {
+        let statement = Self::statement(&self.delivery_id, &self.url, &self.payload_hash, self.status, self.delivered_ms);
+        self.node_key.verify(&statement, &self.signature)
+    }
+
+    fn statement(id: &str, url: &str, payload_hash: &str, status: u16, delivered_ms: u64) -> Vec<u8> {
+        let mut data = RECEIPT_DOMAIN.to_vec();
+        for field in [id, url, payload_hash] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&status.to_le_bytes());
+        data.extend_from_slice(&delivered_ms.to_le_bytes());
+        data
+    }
+}
+
+/// `X-Rhiza-Signature` value: HMAC-SHA256 of `"{timestamp}.{body}"`
+fn payload_signature(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Queue matching events for every configured webhook and deliver them
+/// until the node shuts down
+///
+/// Deliveries are persisted before the first attempt, so a restart resumes
+/// them. Failed ones are retried with exponential backoff and, after
+/// [`MAX_ATTEMPTS`], parked in the dead-letter queue for replay through
+/// the admin API.
+pub async fn run(state: SharedState, webhooks: Vec<WebhookConfig>) {
+    let (events, store, keypair) = {
+        let state = state.read().await;
+        (state.events.clone(), state.storage.webhook_store(), state.keypair.clone())
+    };
+    if webhooks.is_empty() && store.queue_len() == 0 {
+        return;
+    }
+    info!("🪝 Delivering events to {} webhooks", webhooks.len());
+
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Webhooks disabled: could not build HTTP client: {}", e);
+            return;
+        }
+    };
+
+    let mut cursor = events.latest_cursor();
+    let mut next_prune = 0;
+    loop {
+        let batch = events.poll(cursor, EVENT_WAIT).await;
+        if batch.truncated {
+            warn!("Webhook notifier fell behind; some events were dropped before they could be queued");
+        }
+        cursor = batch.next_cursor;
+
+        let now = now_ms();
+        for record in &batch.events {
+            for webhook in webhooks.iter().filter(|w| w.wants(record)) {
+                if let Err(e) = store.enqueue(&Delivery::new(&webhook.url, record, now)) {
+                    warn!("Failed to queue webhook delivery: {}", e);
+                }
+            }
+        }
+
+        match store.due(now) {
+            Ok(due) => {
+                for delivery in due {
+                    let webhook = webhooks.iter().find(|w| w.url == delivery.url);
+                    attempt(&client, &store, &keypair, webhook, delivery).await;
+                }
+            }
+            Err(e) => warn!("Failed to read webhook queue: {}", e),
+        }
+
+        if now >= next_prune {
+            if let Err(e) = store.prune_receipts(now.saturating_sub(RECEIPT_RETENTION_MS)) {
+                warn!("Failed to prune webhook receipts: {}", e);
+            }
+            next_prune = now + RECEIPT_PRUNE_INTERVAL_MS;
+        }
+    }
+}
+
+/// Make one delivery attempt and record the outcome
+async fn attempt(
+    client: &reqwest::Client,
+    store: &WebhookStore,
+    keypair: &KeyPair,
+    webhook: Option<&WebhookConfig>,
+    mut delivery: Delivery,
+) {
+    let Some(webhook) = webhook else {
+        delivery.last_error = Some("webhook is no longer configured".to_string());
+        if let Err(e) = store.bury(&delivery) {
+            warn!("Failed to dead-letter webhook delivery {}: {}", delivery.id, e);
+        }
+        return;
+    };
+
+    let timestamp = now_ms();
+    let mut request = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Rhiza-Event", &delivery.event)
+        .header("X-Rhiza-Delivery", &delivery.id)
+        .header("X-Rhiza-Timestamp", timestamp.to_string())
+        .body(delivery.payload.clone());
+    if let Some(secret) = &webhook.secret {
+        request = request.header("X-Rhiza-Signature", payload_signature(secret, timestamp, &delivery.payload));
+    }
+
+    let outcome = match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(response.status().as_u16()),
+        Ok(response) => Err(format!("endpoint answered {}", response.status())),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let now = now_ms();
+    let result = match outcome {
+        Ok(status) => {
+            debug!("Delivered {} to {}", delivery.id, delivery.url);
+            store.complete(&delivery.id, &DeliveryReceipt::new(keypair, &delivery, status, now))
+        }
+        Err(error) => {
+            let exhausted = delivery.fail(error, now);
+            if exhausted {
+                warn!(
+                    "Webhook delivery {} to {} failed {} times, moved to the dead-letter queue: {}",
+                    delivery.id,
+                    delivery.url,
+                    delivery.attempts,
+                    delivery.last_error.as_deref().unwrap_or_default()
+                );
+                store.bury(&delivery)
+            } else {
+                store.enqueue(&delivery)
+            }
+        }
+    };
+    if let Err(e) = result {
+        warn!("Failed to record webhook delivery {}: {}", delivery.id, e);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::event_log::Incident;
+use crate::SharedState;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rhiza_core::consensus::weight::{WeightCalculator, WeightDivergence};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// Background re-derivation of cumulative weights (`weight_audit` in
+/// config.json)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeightAuditConfig {
+    pub enabled: bool,
+    /// Seconds between checks
+    pub interval_secs: u64,
+    /// How far below the deepest vertex a checked sub-DAG may start
+    pub lookback_depth: u64,
+    /// Most vertices recomputed in one check; bigger sub-DAGs are skipped
+    pub max_vertices: usize,
+}
+
+impl Default for WeightAuditConfig {
+    fn default() -> Self {
+        WeightAuditConfig {
+            enabled: true,
+            interval_secs: 30,
+            lookback_depth: 1_000,
+            max_vertices: 2_000,
+        }
+    }
+}
+
+/// What the background weight checks found since startup
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WeightAudit {
+    /// Sub-DAGs checked
+    pub checks: u64,
+    /// Sub-DAGs too big to check
+    pub skipped: u64,
+    /// Vertices recomputed, over every check
+    pub vertices_checked: u64,
+    /// Vertices whose weight diverged, over every check
+    pub divergences: u64,
+    /// When the last check ran (unix ms)
+    pub last_check_ms: Option<u64>,
+    /// The latest divergence found
+    pub last_divergence: Option<WeightDivergence>,
+}
+
+impl WeightAudit {
+    /// Alert for `/health` once any weight diverged
+    ///
+    /// It stays raised until the node restarts: nothing corrects a wrong
+    /// incremental weight, but loading the DAG recomputes every weight.
+    pub fn alarm(&self) -> Option<String> {
+        let divergence = self.last_divergence.as_ref()?;
+        Some(format!(
+            "{} cumulative weights diverged from a recomputation, latest {} ({} held, {} recomputed)",
+            self.divergences, divergence.id, divergence.incremental, divergence.recomputed
+        ))
+    }
+}
+
+/// Check the weights of a random recent sub-DAG every `interval_secs` until
+/// the node shuts down
+///
+/// The sub-DAG is recomputed under the read lock, so the check holds up
+/// nothing but writers, and only for as long as its bounded size takes.
+pub async fn run(state: SharedState, config: WeightAuditConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+    // Nothing to check yet at startup, and loading just recomputed everything
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let check = {
+            let state = state.read().await;
+            let dag = &state.dag;
+            let mut rng = rand::thread_rng();
+            let from = rng.gen_range(dag.depth().saturating_sub(config.lookback_depth)..=dag.depth());
+            let (vertices, _) = dag.range_by_depth(from, dag.depth(), 1);
+            let Some(root) = vertices.choose(&mut rng).map(|v| v.id()) else {
+                continue;
+            };
+            (root, WeightCalculator::verify_subdag(dag, &root, config.max_vertices))
+        };
+
+        let mut state = state.write().await;
+        let state = &mut *state;
+        let audit = &mut state.weight_audit;
+        audit.last_check_ms = Some(chrono::Utc::now().timestamp_millis() as u64);
+        let (root, Some(check)) = check else {
+            audit.skipped += 1;
+            debug!("Sub-DAG under {} too big for a weight check", check.0);
+            continue;
+        };
+        audit.checks += 1;
+        audit.vertices_checked += check.checked as u64;
+        audit.divergences += check.divergences.len() as u64;
+        for divergence in &check.divergences {
+            error!(
+                "⚠️  Cumulative weight of {} is {}, recomputed {}",
+                divergence.id, divergence.incremental, divergence.recomputed
+            );
+            state.event_log.record(Incident::WeightDiverged {
+                id: divergence.id.to_string(),
+                incremental: divergence.incremental,
+                recomputed: divergence.recomputed,
+            });
+        }
+        if let Some(divergence) = check.divergences.last() {
+            audit.last_divergence = Some(divergence.clone());
+        } else {
+            debug!("Weights of {} vertices under {} check out", check.checked, root);
+        }
+    }
+}